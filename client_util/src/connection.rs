@@ -1,3 +1,4 @@
+use crate::tls::TlsConfig;
 use crate::tower::{SetRequestHeadersLayer, SetRequestHeadersService};
 use http::header::HeaderName;
 use http::{uri::InvalidUri, HeaderValue, Uri};
@@ -32,6 +33,10 @@ pub enum Error {
     /// Client received an unexpected error from the server
     #[error("Invalid URI: {}", .0)]
     InvalidUri(#[from] InvalidUri),
+
+    /// The TLS certificates or key are invalid
+    #[error("Invalid TLS configuration: {}", .0)]
+    InvalidTlsConfig(String),
 }
 
 // Custom impl to include underlying source (not included in tonic
@@ -68,12 +73,17 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 ///     .expect("connection must succeed");
 /// # }
 /// ```
+///
+/// Connections to `https` URLs are encrypted with TLS, verifying the server
+/// certificate against the well-known root certificates unless a CA
+/// certificate is set with [`tls_ca_certificate`](Self::tls_ca_certificate).
 #[derive(Debug, Clone)]
 pub struct Builder {
     user_agent: String,
     headers: Vec<(HeaderName, HeaderValue)>,
     connect_timeout: Duration,
     timeout: Duration,
+    tls: TlsConfig,
 }
 
 impl std::default::Default for Builder {
@@ -83,6 +93,7 @@ impl std::default::Default for Builder {
             connect_timeout: DEFAULT_CONNECT_TIMEOUT,
             timeout: DEFAULT_TIMEOUT,
             headers: Default::default(),
+            tls: Default::default(),
         }
     }
 }
@@ -93,8 +104,18 @@ impl Builder {
     where
         D: TryInto<Uri, Error = InvalidUri> + Send,
     {
-        let endpoint = self.create_endpoint(dst)?;
-        let channel = endpoint.connect().await?;
+        let uri = dst.try_into()?;
+        let channel = if uri.scheme_str() == Some("https") {
+            let connector = self
+                .tls
+                .connector(self.connect_timeout)
+                .map_err(Error::InvalidTlsConfig)?;
+            self.create_endpoint(uri)?
+                .connect_with_connector(connector)
+                .await?
+        } else {
+            self.create_endpoint(uri)?.connect().await?
+        };
         Ok(self.compose_middleware(channel))
     }
 
@@ -107,16 +128,13 @@ impl Builder {
         C::Future: Send + 'static,
         Box<dyn std::error::Error + Send + Sync>: From<C::Error> + Send + 'static,
     {
-        let endpoint = self.create_endpoint(dst)?;
+        let endpoint = self.create_endpoint(dst.try_into()?)?;
         let channel = endpoint.connect_with_connector(connector).await?;
         Ok(self.compose_middleware(channel))
     }
 
-    fn create_endpoint<D>(&self, dst: D) -> Result<Endpoint>
-    where
-        D: TryInto<Uri, Error = InvalidUri> + Send,
-    {
-        let endpoint = Endpoint::from(dst.try_into()?)
+    fn create_endpoint(&self, dst: Uri) -> Result<Endpoint> {
+        let endpoint = Endpoint::from(dst)
             .user_agent(&self.user_agent)?
             .connect_timeout(self.connect_timeout)
             .timeout(self.timeout);
@@ -146,7 +164,8 @@ impl Builder {
     }
 
     /// Sets the maximum duration of time the client will wait for the IOx
-    /// server to accept the TCP connection before aborting the request.
+    /// server to accept the TCP connection, and to complete the TLS handshake
+    /// for `https` URLs, before aborting the request.
     ///
     /// Note this does not bound the request duration - see
     /// [`timeout`][Self::timeout].
@@ -170,6 +189,26 @@ impl Builder {
     pub fn timeout(self, timeout: Duration) -> Self {
         Self { timeout, ..self }
     }
+
+    /// Sets the PEM encoded CA certificates used to verify the certificate
+    /// of `https` servers, instead of the well-known root certificates.
+    pub fn tls_ca_certificate(self, pem: impl Into<Vec<u8>>) -> Self {
+        let tls = TlsConfig {
+            ca_certificate: Some(pem.into()),
+            ..self.tls
+        };
+        Self { tls, ..self }
+    }
+
+    /// Sets the PEM encoded certificate chain and private key presented to
+    /// `https` servers that require client certificates.
+    pub fn tls_client_identity(self, cert: impl Into<Vec<u8>>, key: impl Into<Vec<u8>>) -> Self {
+        let tls = TlsConfig {
+            identity: Some((cert.into(), key.into())),
+            ..self.tls
+        };
+        Self { tls, ..self }
+    }
 }
 
 #[cfg(test)]
@@ -18,4 +18,5 @@
 /// Builder for constructing connections for use with the various gRPC clients
 pub mod connection;
 
+mod tls;
 mod tower;
@@ -0,0 +1,238 @@
+use http::Uri;
+use std::{
+    convert::TryFrom,
+    fmt,
+    future::Future,
+    io::BufReader,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{Certificate, ClientConfig, OwnedTrustAnchor, PrivateKey, RootCertStore, ServerName},
+};
+
+/// The default port of `https` URLs
+const DEFAULT_HTTPS_PORT: u16 = 443;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The PEM encoded certificates and key used for `https` connections
+#[derive(Clone, Default)]
+pub(crate) struct TlsConfig {
+    /// The CA certificates trusted to sign the server certificate, if not
+    /// the well-known root certificates
+    pub(crate) ca_certificate: Option<Vec<u8>>,
+
+    /// The certificate chain and private key presented to servers that
+    /// require client certificates
+    pub(crate) identity: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't print the private key
+        f.debug_struct("TlsConfig")
+            .field("ca_certificate", &self.ca_certificate.is_some())
+            .field("identity", &self.identity.is_some())
+            .finish()
+    }
+}
+
+impl TlsConfig {
+    /// Creates a connector establishing TLS connections with these settings,
+    /// failing connections that are not established within `connect_timeout`
+    pub(crate) fn connector(&self, connect_timeout: Duration) -> Result<TlsConnector, String> {
+        let mut roots = RootCertStore::empty();
+        match &self.ca_certificate {
+            Some(pem) => {
+                let (added, _) = roots.add_parsable_certificates(&read_certs(pem)?);
+                if added == 0 {
+                    return Err("no valid CA certificate found".to_string());
+                }
+            }
+            None => {
+                roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+                    OwnedTrustAnchor::from_subject_spki_name_constraints(
+                        ta.subject,
+                        ta.spki,
+                        ta.name_constraints,
+                    )
+                }))
+            }
+        }
+
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+
+        let mut config = match &self.identity {
+            Some((cert, key)) => {
+                let certs = read_certs(cert)?.into_iter().map(Certificate).collect();
+                builder
+                    .with_single_cert(certs, read_key(key)?)
+                    .map_err(|e| format!("invalid client certificate: {}", e))?
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        // gRPC requires HTTP/2
+        config.alpn_protocols = vec![b"h2".to_vec()];
+
+        Ok(TlsConnector {
+            config: Arc::new(config),
+            connect_timeout,
+        })
+    }
+}
+
+/// Reads the DER encoded certificates from PEM encoded `pem`
+fn read_certs(pem: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(pem))
+        .map_err(|e| format!("invalid certificate: {}", e))?;
+    if certs.is_empty() {
+        return Err("no certificate found".to_string());
+    }
+    Ok(certs)
+}
+
+/// Reads the first private key from PEM encoded `pem`
+fn read_key(pem: &[u8]) -> Result<PrivateKey, String> {
+    let items = rustls_pemfile::read_all(&mut BufReader::new(pem))
+        .map_err(|e| format!("invalid private key: {}", e))?;
+
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) => {
+                Some(PrivateKey(key))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| "no private key found".to_string())
+}
+
+/// Connects to the host and port of a URI over TLS
+#[derive(Clone)]
+pub(crate) struct TlsConnector {
+    config: Arc<ClientConfig>,
+    connect_timeout: Duration,
+}
+
+impl fmt::Debug for TlsConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConnector")
+            .field("connect_timeout", &self.connect_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl tower::Service<Uri> for TlsConnector {
+    type Response = TlsStream<TcpStream>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connector = tokio_rustls::TlsConnector::from(Arc::clone(&self.config));
+        let connect_timeout = self.connect_timeout;
+
+        Box::pin(async move {
+            let host = uri.host().ok_or("URI has no host")?;
+            let port = uri.port_u16().unwrap_or(DEFAULT_HTTPS_PORT);
+
+            // IPv6 addresses are bracketed in URIs
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            let server_name = ServerName::try_from(host)?;
+
+            // The timeout covers the TLS handshake as well as the TCP
+            // connection, as a server that accepts connections but never
+            // responds would otherwise hang the client
+            let connect = async {
+                let stream = TcpStream::connect((host, port)).await?;
+                stream.set_nodelay(true)?;
+
+                Ok::<_, BoxError>(connector.connect(server_name, stream).await?)
+            };
+
+            tokio::time::timeout(connect_timeout, connect)
+                .await
+                .map_err(|_| "timed out connecting to server")?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CA: &[u8] = include_bytes!("../../test_fixtures/tls/ca.pem");
+    const CLIENT_CERT: &[u8] = include_bytes!("../../test_fixtures/tls/client.pem");
+    const CLIENT_KEY: &[u8] = include_bytes!("../../test_fixtures/tls/client.key");
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_connector() {
+        TlsConfig::default().connector(TIMEOUT).unwrap();
+
+        TlsConfig {
+            ca_certificate: Some(CA.to_vec()),
+            identity: Some((CLIENT_CERT.to_vec(), CLIENT_KEY.to_vec())),
+        }
+        .connector(TIMEOUT)
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout() {
+        // The listener never accepts, so the TLS handshake never completes
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri: Uri = format!("https://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let mut connector = TlsConfig::default()
+            .connector(Duration::from_millis(100))
+            .unwrap();
+        let err = tower::Service::call(&mut connector, uri).await.unwrap_err();
+        assert_eq!(err.to_string(), "timed out connecting to server");
+    }
+
+    #[test]
+    fn test_invalid() {
+        let err = TlsConfig {
+            ca_certificate: Some(b"foo".to_vec()),
+            identity: None,
+        }
+        .connector(TIMEOUT)
+        .unwrap_err();
+        assert_eq!(err, "no certificate found");
+
+        let err = TlsConfig {
+            ca_certificate: Some(CA.to_vec()),
+            identity: Some((CLIENT_CERT.to_vec(), CLIENT_CERT.to_vec())),
+        }
+        .connector(TIMEOUT)
+        .unwrap_err();
+        assert_eq!(err, "no private key found");
+    }
+
+    #[test]
+    fn test_debug_hides_key() {
+        let config = TlsConfig {
+            ca_certificate: None,
+            identity: Some((CLIENT_CERT.to_vec(), CLIENT_KEY.to_vec())),
+        };
+        assert_eq!(
+            format!("{:?}", config),
+            "TlsConfig { ca_certificate: false, identity: true }"
+        );
+    }
+}
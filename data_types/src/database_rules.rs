@@ -1,6 +1,10 @@
-use crate::{write_buffer::WriteBufferConnection, DatabaseName};
+use crate::{
+    ingest::IngestRule, subscription::Subscription, write_buffer::WriteBufferConnection,
+    DatabaseName,
+};
 use snafu::Snafu;
 use std::{
+    collections::{BTreeMap, BTreeSet},
     num::{NonZeroU32, NonZeroU64, NonZeroUsize},
     time::Duration,
 };
@@ -43,6 +47,20 @@ pub struct DatabaseRules {
 
     /// An optional connection string to a write buffer for either writing or reading.
     pub write_buffer_connection: Option<WriteBufferConnection>,
+
+    /// An optional schema restricting the tables and columns writes may
+    /// create. If not set, writes may create any table or column.
+    pub schema: Option<DatabaseSchema>,
+
+    /// Rules rewriting the tables of each write before it is buffered
+    pub ingest_rules: Vec<IngestRule>,
+
+    /// Queries run periodically over closed windows of time, whose results
+    /// are written back to this database
+    pub continuous_queries: Vec<ContinuousQuery>,
+
+    /// Subscriptions the writes stored by this database are delivered to
+    pub subscriptions: Vec<Subscription>,
 }
 
 impl DatabaseRules {
@@ -53,6 +71,10 @@ impl DatabaseRules {
             lifecycle_rules: Default::default(),
             worker_cleanup_avg_sleep: Duration::from_secs(500),
             write_buffer_connection: None,
+            schema: None,
+            ingest_rules: vec![],
+            continuous_queries: vec![],
+            subscriptions: vec![],
         }
     }
 
@@ -119,6 +141,22 @@ pub struct LifecycleRules {
     /// Use up to this amount of space in bytes for caching Parquet files. None
     /// will disable Parquet file caching.
     pub parquet_cache_limit: Option<NonZeroU64>,
+
+    /// Reject writes that would create more than this many tables
+    pub max_tables: Option<NonZeroUsize>,
+
+    /// Reject writes that would create more than this many columns in a table
+    pub max_columns_per_table: Option<NonZeroUsize>,
+
+    /// Reject writes that would give a tag column more than this many
+    /// distinct values in a partition. Only values written while the limit
+    /// is set are counted
+    pub max_tag_cardinality: Option<NonZeroUsize>,
+
+    /// Drop data once its timestamps are older than this period. Chunks
+    /// that only contain expired rows are dropped, expired rows within
+    /// other chunks are hidden by delete predicates. None keeps data forever.
+    pub retention_period: Option<Duration>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -195,6 +233,10 @@ impl Default for LifecycleRules {
                 .unwrap(),
             mub_row_threshold: NonZeroUsize::new(DEFAULT_MUB_ROW_THRESHOLD).unwrap(),
             parquet_cache_limit: None,
+            max_tables: None,
+            max_columns_per_table: None,
+            max_tag_cardinality: None,
+            retention_period: None,
         }
     }
 }
@@ -254,6 +296,103 @@ pub struct StrftimeColumn {
     pub format: String,
 }
 
+/// The tables and columns declared for a database, and how writes that
+/// don't match them are treated.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct DatabaseSchema {
+    pub mode: SchemaMode,
+
+    /// The declared tables, keyed by table name
+    pub tables: BTreeMap<String, TableSchema>,
+}
+
+impl DatabaseSchema {
+    /// Returns true if writes may create tables and columns that are not
+    /// declared in this schema
+    pub fn accepts_new_columns(&self) -> bool {
+        match self.mode {
+            SchemaMode::Strict => false,
+            SchemaMode::Learn => true,
+        }
+    }
+}
+
+/// How writes to tables or columns missing from a [`DatabaseSchema`] are
+/// treated.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum SchemaMode {
+    /// Writes to tables or columns that are not declared are rejected.
+    Strict,
+
+    /// Writes may create new tables and columns, until the schema is locked,
+    /// which declares every table and column seen so far and switches the
+    /// database to [`SchemaMode::Strict`].
+    Learn,
+}
+
+impl Default for SchemaMode {
+    fn default() -> Self {
+        Self::Strict
+    }
+}
+
+/// The columns declared for a table. Every table implicitly has a `time`
+/// column.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct TableSchema {
+    pub tags: BTreeSet<String>,
+
+    /// The declared fields and their types, keyed by field name
+    pub fields: BTreeMap<String, FieldType>,
+}
+
+/// The type of a declared field
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum FieldType {
+    Float,
+    Integer,
+    UInteger,
+    String,
+    Boolean,
+}
+
+/// A query run over consecutive windows of time once they close, for
+/// example to downsample raw data into 1 minute averages.
+///
+/// Windows are aligned to multiples of `interval` since the epoch. A window
+/// closes `delay` after its end, at which point the query is run over it and
+/// its results are written to `target_table`. Only windows closing after the
+/// query is created are run.
+///
+/// Results can only be written to a table of the same database, and databases
+/// with a write buffer connection cannot have continuous queries.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ContinuousQuery {
+    /// The name of the query, unique within the database. The progress of a
+    /// query is tracked by name, so renaming a query restarts it.
+    pub name: String,
+
+    /// The SQL query run over each window, in which `$start` and `$end` are
+    /// replaced by the inclusive start and exclusive end of the window.
+    ///
+    /// String and dictionary columns of the result are written as tags,
+    /// other columns as fields. If the result has no `time` column, the start
+    /// of the window is used as the time of every row.
+    pub sql: String,
+
+    /// The table of this database the results are written to. Writing them
+    /// to another database is not supported.
+    pub target_table: String,
+
+    /// The width of the windows
+    pub interval: Duration,
+
+    /// How long to wait for late writes after the end of a window before
+    /// running the query over it. If not set, the late arrival window of the
+    /// lifecycle rules is used.
+    pub delay: Option<Duration>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,105 @@
+//! Rules rewriting writes as they are ingested, before they are buffered by a
+//! database or sharded by a router
+use regex::Regex;
+
+/// A rule of an ingest pipeline. The rules of a pipeline are applied in
+/// order, each to the output of the previous one.
+#[derive(Debug, Clone)]
+pub struct IngestRule {
+    /// If provided, the rule only applies to tables whose name matches this
+    /// regex, otherwise it applies to every table
+    pub table_name_regex: Option<Regex>,
+
+    pub action: IngestAction,
+}
+
+impl IngestRule {
+    /// Returns true if this rule applies to the table `table_name`
+    pub fn matches_table(&self, table_name: &str) -> bool {
+        self.table_name_regex
+            .as_ref()
+            .map(|regex| regex.is_match(table_name))
+            .unwrap_or(true)
+    }
+}
+
+impl PartialEq for IngestRule {
+    fn eq(&self, other: &Self) -> bool {
+        regex_str(&self.table_name_regex) == regex_str(&other.table_name_regex)
+            && self.action == other.action
+    }
+}
+impl Eq for IngestRule {}
+
+/// How an [`IngestRule`] rewrites the tables it applies to. Rules naming a
+/// column that a table does not have leave the table unchanged.
+#[derive(Debug, Clone)]
+pub enum IngestAction {
+    /// Drops a column
+    DropColumn(String),
+
+    /// Renames a column, failing the write if a column named `to` exists
+    RenameColumn { from: String, to: String },
+
+    /// Converts a field to a tag, formatting non-string values
+    FieldToTag(String),
+
+    /// Converts a tag to a string field
+    TagToField(String),
+
+    /// Sets a tag to a constant value on every row, replacing existing values
+    AddTag { tag: String, value: String },
+
+    /// Drops the rows whose value in a tag or string field column matches a
+    /// regex. Tables left without rows are dropped from the write.
+    DropRows { column: String, regex: Regex },
+
+    /// Renames tables whose name matches `regex`, replacing the match with
+    /// `replacement` as in [`Regex::replace`], so that `$1` refers to the
+    /// first capture group. Tables renamed to the same name are merged.
+    RenameTable { regex: Regex, replacement: String },
+}
+
+impl PartialEq for IngestAction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::DropColumn(a), Self::DropColumn(b)) => a == b,
+            (
+                Self::RenameColumn { from, to },
+                Self::RenameColumn {
+                    from: other_from,
+                    to: other_to,
+                },
+            ) => from == other_from && to == other_to,
+            (Self::FieldToTag(a), Self::FieldToTag(b)) => a == b,
+            (Self::TagToField(a), Self::TagToField(b)) => a == b,
+            (
+                Self::AddTag { tag, value },
+                Self::AddTag {
+                    tag: other_tag,
+                    value: other_value,
+                },
+            ) => tag == other_tag && value == other_value,
+            (
+                Self::DropRows { column, regex },
+                Self::DropRows {
+                    column: other_column,
+                    regex: other_regex,
+                },
+            ) => column == other_column && regex.as_str() == other_regex.as_str(),
+            (
+                Self::RenameTable { regex, replacement },
+                Self::RenameTable {
+                    regex: other_regex,
+                    replacement: other_replacement,
+                },
+            ) => regex.as_str() == other_regex.as_str() && replacement == other_replacement,
+            _ => false,
+        }
+    }
+}
+impl Eq for IngestAction {}
+
+fn regex_str(regex: &Option<Regex>) -> Option<&str> {
+    regex.as_ref().map(Regex::as_str)
+}
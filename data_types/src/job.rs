@@ -50,6 +50,12 @@ pub enum Job {
 
     /// Rebuild preserved catalog
     RebuildPreservedCatalog { db_name: Arc<str> },
+
+    /// Run a continuous query over the windows that closed since it last ran
+    RunContinuousQuery {
+        db_name: Arc<str>,
+        query_name: Arc<str>,
+    },
 }
 
 impl Job {
@@ -66,6 +72,7 @@ impl Job {
             Self::WipePreservedCatalog { db_name, .. } => Some(db_name),
             Self::LoadReadBufferChunk { chunk } => Some(&chunk.db_name),
             Self::RebuildPreservedCatalog { db_name } => Some(db_name),
+            Self::RunContinuousQuery { db_name, .. } => Some(db_name),
         }
     }
 
@@ -82,6 +89,7 @@ impl Job {
             Self::WipePreservedCatalog { .. } => None,
             Self::LoadReadBufferChunk { chunk } => Some(&chunk.partition_key),
             Self::RebuildPreservedCatalog { .. } => None,
+            Self::RunContinuousQuery { .. } => None,
         }
     }
 
@@ -98,6 +106,7 @@ impl Job {
             Self::WipePreservedCatalog { .. } => None,
             Self::LoadReadBufferChunk { chunk } => Some(&chunk.table_name),
             Self::RebuildPreservedCatalog { .. } => None,
+            Self::RunContinuousQuery { .. } => None,
         }
     }
 
@@ -114,6 +123,7 @@ impl Job {
             Self::WipePreservedCatalog { .. } => None,
             Self::LoadReadBufferChunk { chunk } => Some(vec![chunk.chunk_id]),
             Self::RebuildPreservedCatalog { .. } => None,
+            Self::RunContinuousQuery { .. } => None,
         }
     }
 
@@ -134,6 +144,7 @@ impl Job {
             Self::WipePreservedCatalog { .. } => "Wipe preserved catalog",
             Self::LoadReadBufferChunk { .. } => "Loading chunk to read buffer",
             Self::RebuildPreservedCatalog { .. } => "Rebuild preserved catalog",
+            Self::RunContinuousQuery { .. } => "Running continuous query",
         }
     }
 }
@@ -157,6 +168,10 @@ impl std::fmt::Display for Job {
             Job::RebuildPreservedCatalog { db_name } => {
                 write!(f, "Job::RebuildPreservedCatalog({})", db_name)
             }
+            Job::RunContinuousQuery {
+                db_name,
+                query_name,
+            } => write!(f, "Job::RunContinuousQuery({}, {})", db_name, query_name),
         }
     }
 }
@@ -17,6 +17,7 @@ pub mod database_rules;
 pub mod delete_predicate;
 pub mod detailed_database;
 pub mod error;
+pub mod ingest;
 pub mod job;
 pub mod names;
 pub mod non_empty;
@@ -24,6 +25,7 @@ pub mod partition_metadata;
 pub mod router;
 pub mod sequence;
 pub mod server_id;
+pub mod subscription;
 pub mod timestamp;
 pub mod write_buffer;
 pub mod write_summary;
@@ -3,7 +3,8 @@ use std::collections::BTreeMap;
 use regex::Regex;
 
 use crate::{
-    consistent_hasher::ConsistentHasher, server_id::ServerId, write_buffer::WriteBufferConnection,
+    consistent_hasher::ConsistentHasher, ingest::IngestRule, server_id::ServerId,
+    write_buffer::WriteBufferConnection,
 };
 
 #[derive(Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Clone, Copy)]
@@ -138,4 +139,7 @@ pub struct Router {
 
     /// Sinks for query requests.
     pub query_sinks: QuerySinks,
+
+    /// Rules rewriting the tables of each write before it is sharded.
+    pub ingest_rules: Vec<IngestRule>,
 }
@@ -0,0 +1,53 @@
+//! Subscriptions delivering the writes of a database to external systems
+use std::num::NonZeroUsize;
+
+use crate::router::Matcher;
+
+/// Default number of writes that may wait for delivery to a subscription
+pub const DEFAULT_SUBSCRIPTION_QUEUE_SIZE: usize = 1_000;
+
+/// A subscription asynchronously delivers the writes stored by a database,
+/// filtered to the tables matching `matcher`, to `target`
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Subscription {
+    /// The name of the subscription, unique within the database
+    pub name: String,
+
+    /// Only tables matching this matcher are delivered. A matcher without a
+    /// table name regex matches every table
+    pub matcher: Matcher,
+
+    /// Where matching writes are delivered to
+    pub target: SubscriptionTarget,
+
+    /// The maximum number of writes waiting for delivery. Writes arriving
+    /// while the queue is full are dropped for this subscription
+    pub queue_size: NonZeroUsize,
+}
+
+impl Subscription {
+    /// Returns true if the table `table_name` is delivered to this
+    /// subscription
+    pub fn matches_table(&self, table_name: &str) -> bool {
+        self.matcher
+            .table_name_regex
+            .as_ref()
+            .map(|regex| regex.is_match(table_name))
+            .unwrap_or(true)
+    }
+}
+
+/// The target of a [`Subscription`]
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum SubscriptionTarget {
+    /// POST writes as line protocol to an HTTP endpoint, such as the
+    /// `/api/v2/write` endpoint of InfluxDB
+    Http { url: String },
+
+    /// Write to a database of another IOx server via gRPC
+    Grpc { connection: String, db_name: String },
+
+    /// Append writes as line protocol to a local file. The path is relative
+    /// to the subscription file directory of the server, and may not leave it
+    File { path: String },
+}
@@ -17,10 +17,11 @@ use data_types::router::{ShardConfig, ShardId};
 use hashbrown::HashMap;
 
 use data_types::delete_predicate::DeletePredicate;
+use data_types::ingest::IngestRule;
 use data_types::non_empty::NonEmptyString;
 use data_types::partition_metadata::{StatValues, Statistics};
 use data_types::sequence::Sequence;
-use mutable_batch::MutableBatch;
+use mutable_batch::{transform, MutableBatch};
 use time::Time;
 use trace::ctx::SpanContext;
 
@@ -215,6 +216,20 @@ impl DmlWrite {
         self.max_timestamp
     }
 
+    /// Rewrites the tables of this [`DmlWrite`] by applying the ingest `rules`
+    /// in order, returning `None` if no rows are left
+    pub fn transform(self, rules: &[IngestRule]) -> Result<Option<Self>, transform::Error> {
+        if rules.is_empty() {
+            return Ok(Some(self));
+        }
+
+        let tables = transform::transform(rules, self.tables)?;
+        if tables.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(tables, self.meta)))
+    }
+
     /// Shards this [`DmlWrite`]
     pub fn shard(self, config: &ShardConfig) -> BTreeMap<ShardId, Self> {
         let mut batches: HashMap<ShardId, HashMap<String, MutableBatch>> = HashMap::new();
@@ -383,6 +398,7 @@ mod tests {
     use data_types::{
         consistent_hasher::ConsistentHasher,
         delete_predicate::DeletePredicate,
+        ingest::IngestAction,
         non_empty::NonEmptyString,
         router::{HashRing, Matcher, MatcherToShard},
         timestamp::TimestampRange,
@@ -486,6 +502,47 @@ mod tests {
         assert!(actual.is_empty());
     }
 
+    #[test]
+    fn test_write_transform() {
+        let meta = DmlMeta::unsequenced(None);
+        let write = db_write(
+            &[
+                "telegraf_cpu,host=a usage=1 10",
+                "telegraf_cpu,host=test usage=2 20",
+                "mem,host=test free=3i 30",
+            ],
+            &meta,
+        );
+
+        let rules = vec![
+            IngestRule {
+                table_name_regex: None,
+                action: IngestAction::DropRows {
+                    column: "host".to_string(),
+                    regex: Regex::new("^test$").unwrap(),
+                },
+            },
+            IngestRule {
+                table_name_regex: Some(Regex::new("^telegraf_").unwrap()),
+                action: IngestAction::RenameTable {
+                    regex: Regex::new("^telegraf_").unwrap(),
+                    replacement: "".to_string(),
+                },
+            },
+        ];
+
+        let actual = write.clone().transform(&rules).unwrap().unwrap();
+        assert_writes_eq(&actual, &db_write(&["cpu,host=a usage=1 10"], &meta));
+        assert_eq!(actual.max_timestamp(), 10);
+
+        assert!(write.clone().transform(&rules[..1]).unwrap().is_some());
+        let unchanged = write.clone().transform(&[]).unwrap().unwrap();
+        assert_writes_eq(&unchanged, &write);
+
+        let write = db_write(&["mem,host=test free=3i 30"], &meta);
+        assert!(write.transform(&rules).unwrap().is_none());
+    }
+
     #[test]
     fn test_delete_sharding() {
         let config = ShardConfig {
@@ -18,29 +18,41 @@ fn main() -> Result<()> {
 ///
 /// Creates:
 ///
+/// - `arrow.flight.protocol.sql.rs`
 /// - `influxdata.iox.delete.v1.rs`
 /// - `influxdata.iox.deployment.v1.rs`
+/// - `influxdata.iox.ingest.v1.rs`
 /// - `influxdata.iox.management.v1.rs`
 /// - `influxdata.iox.preserved_catalog.v1.rs`
 /// - `influxdata.iox.remote.v1.rs`
 /// - `influxdata.iox.router.v1.rs`
+/// - `influxdata.iox.subscription.v1.rs`
 /// - `influxdata.iox.write.v1.rs`
 /// - `influxdata.iox.write_buffer.v1.rs`
 /// - `influxdata.platform.storage.rs`
+/// - `opentelemetry.proto.*.v1.rs`
+/// - `prometheus.rs`
 fn generate_grpc_types(root: &Path) -> Result<()> {
     let delete_path = root.join("influxdata/iox/delete/v1");
+    let flight_sql_path = root.join("arrow/flight/protocol/sql");
     let deployment_path = root.join("influxdata/iox/deployment/v1");
+    let ingest_path = root.join("influxdata/iox/ingest/v1");
     let management_path = root.join("influxdata/iox/management/v1");
     let predicate_path = root.join("influxdata/iox/predicate/v1");
     let preserved_catalog_path = root.join("influxdata/iox/preserved_catalog/v1");
+    let prometheus_path = root.join("prometheus");
+    let otlp_path = root.join("opentelemetry/proto");
     let remote_path = root.join("influxdata/iox/remote/v1");
     let router_path = root.join("influxdata/iox/router/v1");
     let storage_path = root.join("influxdata/platform/storage");
+    let subscription_path = root.join("influxdata/iox/subscription/v1");
     let write_buffer_path = root.join("influxdata/iox/write_buffer/v1");
 
     let proto_files = vec![
         delete_path.join("service.proto"),
+        flight_sql_path.join("FlightSql.proto"),
         deployment_path.join("service.proto"),
+        ingest_path.join("ingest.proto"),
         management_path.join("chunk.proto"),
         management_path.join("database_rules.proto"),
         management_path.join("jobs.proto"),
@@ -51,6 +63,12 @@ fn generate_grpc_types(root: &Path) -> Result<()> {
         predicate_path.join("predicate.proto"),
         preserved_catalog_path.join("catalog.proto"),
         preserved_catalog_path.join("parquet_metadata.proto"),
+        otlp_path.join("collector/metrics/v1/metrics_service.proto"),
+        otlp_path.join("common/v1/common.proto"),
+        otlp_path.join("metrics/v1/metrics.proto"),
+        otlp_path.join("resource/v1/resource.proto"),
+        prometheus_path.join("remote.proto"),
+        prometheus_path.join("types.proto"),
         root.join("google/longrunning/operations.proto"),
         root.join("google/rpc/error_details.proto"),
         root.join("google/rpc/status.proto"),
@@ -66,6 +84,7 @@ fn generate_grpc_types(root: &Path) -> Result<()> {
         storage_path.join("source.proto"),
         storage_path.join("storage_common.proto"),
         storage_path.join("test.proto"),
+        subscription_path.join("subscription.proto"),
         write_buffer_path.join("write_buffer.proto"),
     ];
 
@@ -1,16 +1,23 @@
 use crate::{
-    google::{FieldViolation, FieldViolationExt, FromOptionalField},
+    google::{FieldViolation, FieldViolationExt, FromOptionalField, FromRepeatedField},
     influxdata::iox::management::v1 as management,
     DecodeError, EncodeError,
 };
-use data_types::{database_rules::DatabaseRules, DatabaseName};
+use data_types::{
+    database_rules::{ContinuousQuery, DatabaseRules},
+    subscription::Subscription,
+    DatabaseName,
+};
 use std::{
+    collections::HashSet,
     convert::{TryFrom, TryInto},
     time::Duration,
 };
 
+mod continuous_query;
 mod lifecycle;
 mod partition;
+mod schema;
 
 impl From<DatabaseRules> for management::DatabaseRules {
     fn from(rules: DatabaseRules) -> Self {
@@ -20,6 +27,14 @@ impl From<DatabaseRules> for management::DatabaseRules {
             lifecycle_rules: Some(rules.lifecycle_rules.into()),
             worker_cleanup_avg_sleep: Some(rules.worker_cleanup_avg_sleep.into()),
             write_buffer_connection: rules.write_buffer_connection.map(Into::into),
+            schema: rules.schema.map(Into::into),
+            ingest_rules: rules.ingest_rules.into_iter().map(Into::into).collect(),
+            continuous_queries: rules
+                .continuous_queries
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            subscriptions: rules.subscriptions.into_iter().map(Into::into).collect(),
         }
     }
 }
@@ -50,12 +65,56 @@ impl TryFrom<management::DatabaseRules> for DatabaseRules {
             None => None,
         };
 
+        let schema = proto.schema.optional("schema")?;
+
+        let ingest_rules = proto.ingest_rules.repeated("ingest_rules")?;
+
+        let continuous_queries: Vec<ContinuousQuery> =
+            proto.continuous_queries.repeated("continuous_queries")?;
+        // The results of continuous queries are stored directly, which a
+        // database with a write buffer connection does not support
+        if write_buffer_connection.is_some() && !continuous_queries.is_empty() {
+            return Err(FieldViolation {
+                field: "continuous_queries".to_string(),
+                description: "continuous queries are not supported by databases with a write \
+                              buffer connection"
+                    .to_string(),
+            });
+        }
+        let mut names = HashSet::with_capacity(continuous_queries.len());
+        for query in &continuous_queries {
+            if !names.insert(query.name.as_str()) {
+                return Err(FieldViolation {
+                    field: "continuous_queries".to_string(),
+                    description: format!("continuous query {} defined more than once", query.name),
+                });
+            }
+        }
+
+        let subscriptions: Vec<Subscription> = proto.subscriptions.repeated("subscriptions")?;
+        let mut names = HashSet::with_capacity(subscriptions.len());
+        for subscription in &subscriptions {
+            if !names.insert(subscription.name.as_str()) {
+                return Err(FieldViolation {
+                    field: "subscriptions".to_string(),
+                    description: format!(
+                        "subscription {} defined more than once",
+                        subscription.name
+                    ),
+                });
+            }
+        }
+
         Ok(Self {
             name,
             partition_template,
             lifecycle_rules,
             worker_cleanup_avg_sleep,
             write_buffer_connection,
+            schema,
+            ingest_rules,
+            continuous_queries,
+            subscriptions,
         })
     }
 }
@@ -75,6 +134,23 @@ pub fn encode_persisted_database_rules(
     prost::Message::encode(rules, bytes)
 }
 
+/// Decode the progress of continuous queries that was encoded using
+/// `encode_continuous_query_progress`
+pub fn decode_continuous_query_progress(
+    bytes: prost::bytes::Bytes,
+) -> Result<management::ContinuousQueryProgress, DecodeError> {
+    prost::Message::decode(bytes)
+}
+
+/// Encode the progress of continuous queries into a serialized format suitable for storage in
+/// object store
+pub fn encode_continuous_query_progress(
+    progress: &management::ContinuousQueryProgress,
+    bytes: &mut prost::bytes::BytesMut,
+) -> Result<(), EncodeError> {
+    prost::Message::encode(progress, bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,5 +175,88 @@ mod tests {
         // DatabaseRules
         assert_eq!(back.partition_template, Some(Default::default()));
         assert_eq!(back.lifecycle_rules, Some(LifecycleRules::default().into()));
+
+        // No schema is declared by default
+        assert_eq!(rules.schema, None);
+        assert_eq!(back.schema, None);
+    }
+
+    #[test]
+    fn test_database_rules_duplicate_continuous_queries() {
+        let query = management::ContinuousQuery {
+            name: "cpu_1m".to_string(),
+            sql: "SELECT 1".to_string(),
+            target_table: "cpu_1m".to_string(),
+            interval: Some(Duration::from_secs(60).into()),
+            delay: None,
+        };
+        let protobuf = management::DatabaseRules {
+            name: "database".to_string(),
+            continuous_queries: vec![query.clone(), query],
+            ..Default::default()
+        };
+
+        let err = DatabaseRules::try_from(protobuf).unwrap_err();
+        assert_eq!(err.field, "continuous_queries");
+        assert_eq!(
+            err.description,
+            "continuous query cpu_1m defined more than once"
+        );
+    }
+
+    #[test]
+    fn test_database_rules_continuous_queries_with_write_buffer() {
+        use crate::influxdata::iox::write_buffer::v1 as write_buffer;
+
+        let protobuf = management::DatabaseRules {
+            name: "database".to_string(),
+            write_buffer_connection: Some(write_buffer::WriteBufferConnection {
+                r#type: "mock".to_string(),
+                connection: "my_mock".to_string(),
+                ..Default::default()
+            }),
+            continuous_queries: vec![management::ContinuousQuery {
+                name: "cpu_1m".to_string(),
+                sql: "SELECT 1".to_string(),
+                target_table: "cpu_1m".to_string(),
+                interval: Some(Duration::from_secs(60).into()),
+                delay: None,
+            }],
+            ..Default::default()
+        };
+
+        let err = DatabaseRules::try_from(protobuf).unwrap_err();
+        assert_eq!(err.field, "continuous_queries");
+        assert_eq!(
+            err.description,
+            "continuous queries are not supported by databases with a write buffer connection"
+        );
+    }
+
+    #[test]
+    fn test_database_rules_duplicate_subscriptions() {
+        use crate::influxdata::iox::subscription::v1 as subscription;
+
+        let sub = subscription::Subscription {
+            name: "alerts".to_string(),
+            target: Some(subscription::subscription::Target::File(
+                subscription::FileTarget {
+                    path: "/tmp/writes.lp".to_string(),
+                },
+            )),
+            ..Default::default()
+        };
+        let protobuf = management::DatabaseRules {
+            name: "database".to_string(),
+            subscriptions: vec![sub.clone(), sub],
+            ..Default::default()
+        };
+
+        let err = DatabaseRules::try_from(protobuf).unwrap_err();
+        assert_eq!(err.field, "subscriptions");
+        assert_eq!(
+            err.description,
+            "subscription alerts defined more than once"
+        );
     }
 }
@@ -0,0 +1,110 @@
+use std::convert::{TryFrom, TryInto};
+
+use data_types::database_rules::ContinuousQuery;
+
+use crate::google::{FieldViolation, FieldViolationExt, NonEmptyString, OptionalField};
+use crate::influxdata::iox::management::v1 as management;
+
+impl From<ContinuousQuery> for management::ContinuousQuery {
+    fn from(query: ContinuousQuery) -> Self {
+        Self {
+            name: query.name,
+            sql: query.sql,
+            target_table: query.target_table,
+            interval: Some(query.interval.into()),
+            delay: query.delay.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<management::ContinuousQuery> for ContinuousQuery {
+    type Error = FieldViolation;
+
+    fn try_from(proto: management::ContinuousQuery) -> Result<Self, Self::Error> {
+        let interval: std::time::Duration = proto
+            .interval
+            .unwrap_field("interval")?
+            .try_into()
+            .scope("interval")?;
+        if interval.is_zero() {
+            return Err(FieldViolation {
+                field: "interval".to_string(),
+                description: "interval must be greater than zero".to_string(),
+            });
+        }
+
+        let delay = match proto.delay {
+            Some(d) => Some(d.try_into().scope("delay")?),
+            None => None,
+        };
+
+        Ok(Self {
+            name: proto.name.non_empty("name")?,
+            sql: proto.sql.non_empty("sql")?,
+            target_table: proto.target_table.non_empty("target_table")?,
+            interval,
+            delay,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn query() -> management::ContinuousQuery {
+        management::ContinuousQuery {
+            name: "cpu_1m".to_string(),
+            sql: "SELECT host, avg(usage) AS usage FROM cpu \
+                  WHERE time >= $start AND time < $end GROUP BY host"
+                .to_string(),
+            target_table: "cpu_1m".to_string(),
+            interval: Some(Duration::from_secs(60).into()),
+            delay: None,
+        }
+    }
+
+    #[test]
+    fn test_continuous_query_round_trip() {
+        let protobuf = query();
+        let query: ContinuousQuery = protobuf.clone().try_into().unwrap();
+        assert_eq!(query.interval, Duration::from_secs(60));
+        assert_eq!(query.delay, None);
+
+        let back: management::ContinuousQuery = query.into();
+        assert_eq!(back, protobuf);
+
+        let protobuf = management::ContinuousQuery {
+            delay: Some(Duration::from_secs(30).into()),
+            ..query()
+        };
+        let query: ContinuousQuery = protobuf.try_into().unwrap();
+        assert_eq!(query.delay, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_continuous_query_invalid() {
+        let err = ContinuousQuery::try_from(management::ContinuousQuery {
+            interval: None,
+            ..query()
+        })
+        .unwrap_err();
+        assert_eq!(err.field, "interval");
+
+        let err = ContinuousQuery::try_from(management::ContinuousQuery {
+            interval: Some(Duration::from_secs(0).into()),
+            ..query()
+        })
+        .unwrap_err();
+        assert_eq!(err.field, "interval");
+        assert_eq!(err.description, "interval must be greater than zero");
+
+        let err = ContinuousQuery::try_from(management::ContinuousQuery {
+            target_table: Default::default(),
+            ..query()
+        })
+        .unwrap_err();
+        assert_eq!(err.field, "target_table");
+    }
+}
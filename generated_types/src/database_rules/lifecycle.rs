@@ -39,6 +39,19 @@ impl From<LifecycleRules> for management::LifecycleRules {
                 .parquet_cache_limit
                 .map(|v| v.get())
                 .unwrap_or_default(),
+            max_tables: config
+                .max_tables
+                .map(|x| x.get() as u64)
+                .unwrap_or_default(),
+            max_columns_per_table: config
+                .max_columns_per_table
+                .map(|x| x.get() as u64)
+                .unwrap_or_default(),
+            max_tag_cardinality: config
+                .max_tag_cardinality
+                .map(|x| x.get() as u64)
+                .unwrap_or_default(),
+            retention_period: config.retention_period.map(Into::into),
         }
     }
 }
@@ -73,6 +86,17 @@ impl TryFrom<management::LifecycleRules> for LifecycleRules {
             });
         }
 
+        let retention_period: Option<std::time::Duration> = match proto.retention_period {
+            Some(d) => Some(d.try_into().scope("retention_period")?),
+            None => None,
+        };
+        if matches!(retention_period, Some(d) if d.is_zero()) {
+            return Err(FieldViolation {
+                field: "retention_period".to_string(),
+                description: "retention_period must be greater than zero".to_string(),
+            });
+        }
+
         Ok(Self {
             buffer_size_soft: (proto.buffer_size_soft as usize).try_into().ok(),
             buffer_size_hard: (proto.buffer_size_hard as usize).try_into().ok(),
@@ -103,6 +127,10 @@ impl TryFrom<management::LifecycleRules> for LifecycleRules {
             mub_row_threshold: NonZeroUsize::new(proto.mub_row_threshold as usize)
                 .unwrap_or_else(|| NonZeroUsize::new(DEFAULT_MUB_ROW_THRESHOLD).unwrap()),
             parquet_cache_limit: NonZeroU64::new(proto.parquet_cache_limit),
+            max_tables: NonZeroUsize::new(proto.max_tables as usize),
+            max_columns_per_table: NonZeroUsize::new(proto.max_columns_per_table as usize),
+            max_tag_cardinality: NonZeroUsize::new(proto.max_tag_cardinality as usize),
+            retention_period,
         })
     }
 }
@@ -148,6 +176,13 @@ mod tests {
             persist_age_threshold_seconds: 60,
             mub_row_threshold: 3454,
             parquet_cache_limit: 10,
+            max_tables: 100,
+            max_columns_per_table: 200,
+            max_tag_cardinality: 300,
+            retention_period: Some(pbjson_types::Duration {
+                seconds: 86400,
+                nanos: 0,
+            }),
         };
 
         let config: LifecycleRules = protobuf.clone().try_into().unwrap();
@@ -194,6 +229,28 @@ mod tests {
             protobuf.parquet_cache_limit
         );
         assert_eq!(back.parquet_cache_limit, protobuf.parquet_cache_limit);
+        assert_eq!(config.max_tables.unwrap().get(), 100);
+        assert_eq!(config.max_columns_per_table.unwrap().get(), 200);
+        assert_eq!(config.max_tag_cardinality.unwrap().get(), 300);
+        assert_eq!(back.max_tables, protobuf.max_tables);
+        assert_eq!(back.max_columns_per_table, protobuf.max_columns_per_table);
+        assert_eq!(back.max_tag_cardinality, protobuf.max_tag_cardinality);
+        assert_eq!(
+            config.retention_period,
+            Some(std::time::Duration::from_secs(86400))
+        );
+        assert_eq!(back.retention_period, protobuf.retention_period);
+
+        let e = LifecycleRules::try_from(management::LifecycleRules {
+            retention_period: Some(pbjson_types::Duration::default()),
+            ..protobuf.clone()
+        })
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            e,
+            "Violation for field \"retention_period\": retention_period must be greater than zero"
+        );
 
         protobuf.late_arrive_window_seconds = 20;
         protobuf.persist_age_threshold_seconds = 4;
@@ -0,0 +1,249 @@
+use std::convert::TryFrom;
+
+use data_types::database_rules::{DatabaseSchema, FieldType, SchemaMode, TableSchema};
+
+use crate::google::{FieldViolation, FromRepeatedField, NonEmptyString};
+use crate::influxdata::iox::management::v1 as management;
+
+impl From<DatabaseSchema> for management::DatabaseSchema {
+    fn from(schema: DatabaseSchema) -> Self {
+        let mode: management::database_schema::Mode = schema.mode.into();
+
+        Self {
+            mode: mode as _,
+            tables: schema
+                .tables
+                .into_iter()
+                .map(|(name, table)| {
+                    let mut table: management::TableSchema = table.into();
+                    table.name = name;
+                    table
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<management::DatabaseSchema> for DatabaseSchema {
+    type Error = FieldViolation;
+
+    fn try_from(proto: management::DatabaseSchema) -> Result<Self, Self::Error> {
+        let mode = management::database_schema::Mode::from_i32(proto.mode)
+            .ok_or_else(|| FieldViolation {
+                field: "mode".to_string(),
+                description: format!("invalid schema mode: {}", proto.mode),
+            })?
+            .into();
+
+        let tables: Vec<(String, TableSchema)> = proto.tables.repeated("tables")?;
+
+        let mut schema = Self {
+            mode,
+            tables: Default::default(),
+        };
+        for (name, table) in tables {
+            if schema.tables.insert(name.clone(), table).is_some() {
+                return Err(FieldViolation {
+                    field: "tables".to_string(),
+                    description: format!("table {} declared more than once", name),
+                });
+            }
+        }
+
+        Ok(schema)
+    }
+}
+
+impl From<SchemaMode> for management::database_schema::Mode {
+    fn from(mode: SchemaMode) -> Self {
+        match mode {
+            SchemaMode::Strict => Self::Strict,
+            SchemaMode::Learn => Self::Learn,
+        }
+    }
+}
+
+impl From<management::database_schema::Mode> for SchemaMode {
+    fn from(proto: management::database_schema::Mode) -> Self {
+        match proto {
+            management::database_schema::Mode::Unspecified => Self::default(),
+            management::database_schema::Mode::Strict => Self::Strict,
+            management::database_schema::Mode::Learn => Self::Learn,
+        }
+    }
+}
+
+/// The table name is set by the caller, as it is the key of the table
+impl From<TableSchema> for management::TableSchema {
+    fn from(table: TableSchema) -> Self {
+        Self {
+            name: Default::default(),
+            tags: table.tags.into_iter().collect(),
+            fields: table
+                .fields
+                .into_iter()
+                .map(|(name, field_type)| {
+                    let field_type: management::field_schema::FieldType = field_type.into();
+                    management::FieldSchema {
+                        name,
+                        r#type: field_type as _,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<management::TableSchema> for (String, TableSchema) {
+    type Error = FieldViolation;
+
+    fn try_from(proto: management::TableSchema) -> Result<Self, Self::Error> {
+        let name = proto.name.non_empty("name")?;
+
+        let mut table = TableSchema::default();
+        for (i, tag) in proto.tags.into_iter().enumerate() {
+            let tag = tag.non_empty(i.to_string()).map_err(|e| e.scope("tags"))?;
+            if tag == TIME_COLUMN_NAME || !table.tags.insert(tag.clone()) {
+                return Err(invalid_column("tags", &tag));
+            }
+        }
+
+        let fields: Vec<(String, FieldType)> = proto.fields.repeated("fields")?;
+        for (field, field_type) in fields {
+            if field == TIME_COLUMN_NAME
+                || table.tags.contains(&field)
+                || table.fields.insert(field.clone(), field_type).is_some()
+            {
+                return Err(invalid_column("fields", &field));
+            }
+        }
+
+        Ok((name, table))
+    }
+}
+
+/// Every table has an implicit `time` column
+const TIME_COLUMN_NAME: &str = "time";
+
+fn invalid_column(field: &str, column: &str) -> FieldViolation {
+    FieldViolation {
+        field: field.to_string(),
+        description: format!(
+            "invalid column {}: column names must be unique and not {}",
+            column, TIME_COLUMN_NAME
+        ),
+    }
+}
+
+impl From<FieldType> for management::field_schema::FieldType {
+    fn from(field_type: FieldType) -> Self {
+        match field_type {
+            FieldType::Float => Self::Float,
+            FieldType::Integer => Self::Integer,
+            FieldType::UInteger => Self::Uinteger,
+            FieldType::String => Self::String,
+            FieldType::Boolean => Self::Boolean,
+        }
+    }
+}
+
+impl TryFrom<management::FieldSchema> for (String, FieldType) {
+    type Error = FieldViolation;
+
+    fn try_from(proto: management::FieldSchema) -> Result<Self, Self::Error> {
+        use management::field_schema::FieldType as ProtoFieldType;
+
+        let name = proto.name.non_empty("name")?;
+        let field_type = match ProtoFieldType::from_i32(proto.r#type) {
+            Some(ProtoFieldType::Float) => FieldType::Float,
+            Some(ProtoFieldType::Integer) => FieldType::Integer,
+            Some(ProtoFieldType::Uinteger) => FieldType::UInteger,
+            Some(ProtoFieldType::String) => FieldType::String,
+            Some(ProtoFieldType::Boolean) => FieldType::Boolean,
+            Some(ProtoFieldType::Unspecified) | None => {
+                return Err(FieldViolation::required("type"))
+            }
+        };
+
+        Ok((name, field_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn table(name: &str) -> management::TableSchema {
+        management::TableSchema {
+            name: name.to_string(),
+            tags: vec!["host".to_string()],
+            fields: vec![management::FieldSchema {
+                name: "usage".to_string(),
+                r#type: management::field_schema::FieldType::Float as _,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_database_schema_round_trip() {
+        let protobuf = management::DatabaseSchema {
+            mode: management::database_schema::Mode::Learn as _,
+            tables: vec![table("cpu")],
+        };
+
+        let schema: DatabaseSchema = protobuf.clone().try_into().unwrap();
+        assert_eq!(schema.mode, SchemaMode::Learn);
+
+        let cpu = &schema.tables["cpu"];
+        assert!(cpu.tags.contains("host"));
+        assert_eq!(cpu.fields["usage"], FieldType::Float);
+
+        let back: management::DatabaseSchema = schema.into();
+        assert_eq!(back, protobuf);
+    }
+
+    #[test]
+    fn test_database_schema_default_mode() {
+        let schema: DatabaseSchema = management::DatabaseSchema::default().try_into().unwrap();
+        assert_eq!(schema.mode, SchemaMode::Strict);
+        assert!(schema.tables.is_empty());
+    }
+
+    #[test]
+    fn test_database_schema_invalid() {
+        let err = DatabaseSchema::try_from(management::DatabaseSchema {
+            mode: 0,
+            tables: vec![table("cpu"), table("cpu")],
+        })
+        .unwrap_err();
+        assert_eq!(err.field, "tables");
+
+        let mut missing_type = table("cpu");
+        missing_type.fields[0].r#type = 0;
+        let err = DatabaseSchema::try_from(management::DatabaseSchema {
+            mode: 0,
+            tables: vec![missing_type],
+        })
+        .unwrap_err();
+        assert_eq!(err.field, "tables.0.fields.0.type");
+
+        let mut duplicate_column = table("cpu");
+        duplicate_column.fields[0].name = "host".to_string();
+        let err = DatabaseSchema::try_from(management::DatabaseSchema {
+            mode: 0,
+            tables: vec![duplicate_column],
+        })
+        .unwrap_err();
+        assert_eq!(err.field, "tables.0.fields");
+
+        let mut time_column = table("cpu");
+        time_column.tags.push("time".to_string());
+        let err = DatabaseSchema::try_from(management::DatabaseSchema {
+            mode: 0,
+            tables: vec![time_column],
+        })
+        .unwrap_err();
+        assert_eq!(err.field, "tables.0.tags");
+    }
+}
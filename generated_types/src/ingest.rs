@@ -0,0 +1,204 @@
+use std::convert::TryFrom;
+
+use data_types::ingest::{IngestAction, IngestRule};
+use regex::Regex;
+
+use crate::google::{FieldViolation, FieldViolationExt, NonEmptyString, OptionalField};
+use crate::influxdata::iox::ingest::v1 as ingest;
+
+impl From<IngestRule> for ingest::IngestRule {
+    fn from(rule: IngestRule) -> Self {
+        use ingest::ingest_rule::Action;
+
+        let action = match rule.action {
+            IngestAction::DropColumn(column) => Action::DropColumn(ingest::DropColumn { column }),
+            IngestAction::RenameColumn { from, to } => {
+                Action::RenameColumn(ingest::RenameColumn { from, to })
+            }
+            IngestAction::FieldToTag(column) => {
+                Action::FieldToTag(ingest::ConvertColumn { column })
+            }
+            IngestAction::TagToField(column) => {
+                Action::TagToField(ingest::ConvertColumn { column })
+            }
+            IngestAction::AddTag { tag, value } => Action::AddTag(ingest::AddTag { tag, value }),
+            IngestAction::DropRows { column, regex } => Action::DropRows(ingest::DropRows {
+                column,
+                regex: regex.to_string(),
+            }),
+            IngestAction::RenameTable { regex, replacement } => {
+                Action::RenameTable(ingest::RenameTable {
+                    regex: regex.to_string(),
+                    replacement,
+                })
+            }
+        };
+
+        Self {
+            table_name_regex: rule
+                .table_name_regex
+                .map(|r| r.to_string())
+                .unwrap_or_default(),
+            action: Some(action),
+        }
+    }
+}
+
+impl TryFrom<ingest::IngestRule> for IngestRule {
+    type Error = FieldViolation;
+
+    fn try_from(proto: ingest::IngestRule) -> Result<Self, Self::Error> {
+        use ingest::ingest_rule::Action;
+
+        let table_name_regex = match proto.table_name_regex.as_str() {
+            "" => None,
+            re => Some(Regex::new(re).scope("table_name_regex")?),
+        };
+
+        let action = match proto.action.unwrap_field("action")? {
+            Action::DropColumn(v) => {
+                IngestAction::DropColumn(column(v.column, "drop_column.column")?)
+            }
+            Action::RenameColumn(v) => IngestAction::RenameColumn {
+                from: column(v.from, "rename_column.from")?,
+                to: column(v.to, "rename_column.to")?,
+            },
+            Action::FieldToTag(v) => {
+                IngestAction::FieldToTag(column(v.column, "field_to_tag.column")?)
+            }
+            Action::TagToField(v) => {
+                IngestAction::TagToField(column(v.column, "tag_to_field.column")?)
+            }
+            Action::AddTag(v) => IngestAction::AddTag {
+                tag: column(v.tag, "add_tag.tag")?,
+                value: v.value,
+            },
+            Action::DropRows(v) => IngestAction::DropRows {
+                column: column(v.column, "drop_rows.column")?,
+                regex: Regex::new(&v.regex).scope("drop_rows.regex")?,
+            },
+            Action::RenameTable(v) => IngestAction::RenameTable {
+                regex: Regex::new(&v.regex).scope("rename_table.regex")?,
+                replacement: v.replacement,
+            },
+        };
+
+        Ok(Self {
+            table_name_regex,
+            action,
+        })
+    }
+}
+
+/// Every table has a `time` column, which ingest rules may not modify
+const TIME_COLUMN_NAME: &str = "time";
+
+fn column(name: String, field: &'static str) -> Result<String, FieldViolation> {
+    let name = name.non_empty(field)?;
+    if name == TIME_COLUMN_NAME {
+        return Err(FieldViolation {
+            field: field.to_string(),
+            description: format!("ingest rules cannot modify the {} column", TIME_COLUMN_NAME),
+        });
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    use ingest::ingest_rule::Action;
+
+    fn rule(action: Action) -> ingest::IngestRule {
+        ingest::IngestRule {
+            table_name_regex: "^cpu".to_string(),
+            action: Some(action),
+        }
+    }
+
+    #[test]
+    fn test_ingest_rule_round_trip() {
+        let actions = vec![
+            Action::DropColumn(ingest::DropColumn {
+                column: "host".to_string(),
+            }),
+            Action::RenameColumn(ingest::RenameColumn {
+                from: "host".to_string(),
+                to: "hostname".to_string(),
+            }),
+            Action::FieldToTag(ingest::ConvertColumn {
+                column: "region".to_string(),
+            }),
+            Action::TagToField(ingest::ConvertColumn {
+                column: "region".to_string(),
+            }),
+            Action::AddTag(ingest::AddTag {
+                tag: "dc".to_string(),
+                value: "us-east".to_string(),
+            }),
+            Action::DropRows(ingest::DropRows {
+                column: "host".to_string(),
+                regex: "^test-".to_string(),
+            }),
+            Action::RenameTable(ingest::RenameTable {
+                regex: "^telegraf_(.*)$".to_string(),
+                replacement: "$1".to_string(),
+            }),
+        ];
+
+        for action in actions {
+            let protobuf = rule(action);
+            let rule: IngestRule = protobuf.clone().try_into().unwrap();
+            assert_eq!(rule.table_name_regex.as_ref().unwrap().as_str(), "^cpu");
+
+            let back: ingest::IngestRule = rule.into();
+            assert_eq!(back, protobuf);
+        }
+
+        let rule: IngestRule = ingest::IngestRule {
+            table_name_regex: Default::default(),
+            action: Some(Action::DropColumn(ingest::DropColumn {
+                column: "host".to_string(),
+            })),
+        }
+        .try_into()
+        .unwrap();
+        assert!(rule.table_name_regex.is_none());
+        assert_eq!(rule.action, IngestAction::DropColumn("host".to_string()));
+    }
+
+    #[test]
+    fn test_ingest_rule_invalid() {
+        let err = IngestRule::try_from(ingest::IngestRule::default()).unwrap_err();
+        assert_eq!(err.field, "action");
+
+        let mut protobuf = rule(Action::DropColumn(ingest::DropColumn {
+            column: "host".to_string(),
+        }));
+        protobuf.table_name_regex = "*".to_string();
+        let err = IngestRule::try_from(protobuf).unwrap_err();
+        assert_eq!(err.field, "table_name_regex");
+
+        let err = IngestRule::try_from(rule(Action::RenameColumn(ingest::RenameColumn {
+            from: "host".to_string(),
+            to: "time".to_string(),
+        })))
+        .unwrap_err();
+        assert_eq!(err.field, "rename_column.to");
+
+        let err = IngestRule::try_from(rule(Action::FieldToTag(ingest::ConvertColumn {
+            column: Default::default(),
+        })))
+        .unwrap_err();
+        assert_eq!(err.field, "field_to_tag.column");
+
+        let err = IngestRule::try_from(rule(Action::DropRows(ingest::DropRows {
+            column: "host".to_string(),
+            regex: "(".to_string(),
+        })))
+        .unwrap_err();
+        assert_eq!(err.field, "drop_rows.regex");
+    }
+}
@@ -24,6 +24,7 @@ impl management::operation_metadata::Job {
                 db_name,
                 ..
             }) => db_name,
+            Self::RunContinuousQuery(management::RunContinuousQuery { db_name, .. }) => db_name,
         }
     }
 }
@@ -94,6 +95,13 @@ impl From<Job> for management::operation_metadata::Job {
                     db_name: db_name.to_string(),
                 })
             }
+            Job::RunContinuousQuery {
+                db_name,
+                query_name,
+            } => Self::RunContinuousQuery(management::RunContinuousQuery {
+                db_name: db_name.to_string(),
+                query_name: query_name.to_string(),
+            }),
         }
     }
 }
@@ -57,6 +57,16 @@ pub mod influxdata {
             }
         }
 
+        pub mod ingest {
+            pub mod v1 {
+                include!(concat!(env!("OUT_DIR"), "/influxdata.iox.ingest.v1.rs"));
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/influxdata.iox.ingest.v1.serde.rs"
+                ));
+            }
+        }
+
         pub mod management {
             pub mod v1 {
                 /// Operation metadata type
@@ -114,6 +124,19 @@ pub mod influxdata {
             }
         }
 
+        pub mod subscription {
+            pub mod v1 {
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/influxdata.iox.subscription.v1.rs"
+                ));
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/influxdata.iox.subscription.v1.serde.rs"
+                ));
+            }
+        }
+
         pub mod write_buffer {
             pub mod v1 {
                 include!(concat!(
@@ -136,6 +159,67 @@ pub mod influxdata {
     }
 }
 
+/// Arrow Flight SQL protocol messages, used as commands and tickets on top
+/// of the Arrow Flight service
+pub mod arrow {
+    pub mod flight {
+        pub mod protocol {
+            pub mod sql {
+                include!(concat!(env!("OUT_DIR"), "/arrow.flight.protocol.sql.rs"));
+            }
+        }
+    }
+}
+
+/// Prometheus remote storage protocol messages, used by the remote write and
+/// remote read HTTP endpoints
+pub mod prometheus {
+    include!(concat!(env!("OUT_DIR"), "/prometheus.rs"));
+}
+
+/// OpenTelemetry protocol (OTLP) messages and the metrics collector service
+pub mod opentelemetry {
+    pub mod proto {
+        pub mod collector {
+            pub mod metrics {
+                pub mod v1 {
+                    include!(concat!(
+                        env!("OUT_DIR"),
+                        "/opentelemetry.proto.collector.metrics.v1.rs"
+                    ));
+                }
+            }
+        }
+
+        pub mod common {
+            pub mod v1 {
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/opentelemetry.proto.common.v1.rs"
+                ));
+            }
+        }
+
+        pub mod metrics {
+            pub mod v1 {
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/opentelemetry.proto.metrics.v1.rs"
+                ));
+            }
+        }
+
+        pub mod resource {
+            pub mod v1 {
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/opentelemetry.proto.resource.v1.rs"
+                ));
+            }
+        }
+    }
+}
+
 // Needed because of https://github.com/hyperium/tonic/issues/471
 pub mod grpc {
     pub mod health {
@@ -206,12 +290,16 @@ pub mod delete_predicate;
 #[cfg(any(feature = "data_types_conversions", test))]
 pub mod detailed_database;
 #[cfg(any(feature = "data_types_conversions", test))]
+pub mod ingest;
+#[cfg(any(feature = "data_types_conversions", test))]
 pub mod job;
 #[cfg(any(feature = "data_types_conversions", test))]
 pub mod router;
 #[cfg(any(feature = "data_types_conversions", test))]
 pub mod server_config;
 #[cfg(any(feature = "data_types_conversions", test))]
+pub mod subscription;
+#[cfg(any(feature = "data_types_conversions", test))]
 pub mod write_buffer;
 
 pub use prost::{DecodeError, EncodeError};
@@ -4,7 +4,9 @@ use data_types::router::{
 };
 use regex::Regex;
 
-use crate::google::{FieldViolation, FieldViolationExt, FromField, FromOptionalField};
+use crate::google::{
+    FieldViolation, FieldViolationExt, FromField, FromOptionalField, FromRepeatedField,
+};
 use crate::influxdata::iox::router::v1 as router;
 
 impl From<ShardConfig> for router::ShardConfig {
@@ -227,6 +229,7 @@ impl From<Router> for router::Router {
                 .map(|(id, sink_set)| (id.get(), sink_set.into()))
                 .collect(),
             query_sinks: none_if_default(router.query_sinks.into()),
+            ingest_rules: router.ingest_rules.into_iter().map(Into::into).collect(),
         }
     }
 }
@@ -250,6 +253,7 @@ impl TryFrom<router::Router> for Router {
                 .query_sinks
                 .optional("query_sinks")?
                 .unwrap_or_default(),
+            ingest_rules: proto.ingest_rules.repeated("ingest_rules")?,
         })
     }
 }
@@ -423,6 +427,7 @@ mod tests {
             query_sinks: Some(router::QuerySinks {
                 grpc_remotes: vec![1, 3],
             }),
+            ingest_rules: vec![],
         };
 
         let router: Router = protobuf.try_into().unwrap();
@@ -447,6 +452,7 @@ mod tests {
                         ServerId::try_from(3).unwrap()
                     ]
                 }
+                ingest_rules: vec![],
             },
         );
     }
@@ -0,0 +1,172 @@
+use std::convert::TryFrom;
+use std::num::NonZeroUsize;
+use std::path::{Component, Path};
+
+use data_types::subscription::{Subscription, SubscriptionTarget, DEFAULT_SUBSCRIPTION_QUEUE_SIZE};
+
+use crate::google::{FieldViolation, FromOptionalField, NonEmptyString, OptionalField};
+use crate::influxdata::iox::subscription::v1 as subscription;
+
+impl From<Subscription> for subscription::Subscription {
+    fn from(sub: Subscription) -> Self {
+        use subscription::subscription::Target;
+
+        let target = match sub.target {
+            SubscriptionTarget::Http { url } => Target::Http(subscription::HttpTarget { url }),
+            SubscriptionTarget::Grpc {
+                connection,
+                db_name,
+            } => Target::Grpc(subscription::GrpcTarget {
+                connection,
+                db_name,
+            }),
+            SubscriptionTarget::File { path } => Target::File(subscription::FileTarget { path }),
+        };
+
+        Self {
+            name: sub.name,
+            matcher: Some(sub.matcher.into()),
+            target: Some(target),
+            queue_size: sub.queue_size.get() as u64,
+        }
+    }
+}
+
+impl TryFrom<subscription::Subscription> for Subscription {
+    type Error = FieldViolation;
+
+    fn try_from(proto: subscription::Subscription) -> Result<Self, Self::Error> {
+        use subscription::subscription::Target;
+
+        let target = match proto.target.unwrap_field("target")? {
+            Target::Http(v) => SubscriptionTarget::Http {
+                url: v.url.non_empty("http.url")?,
+            },
+            Target::Grpc(v) => SubscriptionTarget::Grpc {
+                connection: v.connection.non_empty("grpc.connection")?,
+                db_name: v.db_name.non_empty("grpc.db_name")?,
+            },
+            Target::File(v) => {
+                let path = v.path.non_empty("file.path")?;
+                let relative = Path::new(&path)
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+                if !relative {
+                    return Err(FieldViolation {
+                        field: "file.path".to_string(),
+                        description: "Path must be relative and must not contain '..'".to_string(),
+                    });
+                }
+                SubscriptionTarget::File { path }
+            }
+        };
+
+        Ok(Self {
+            name: proto.name.non_empty("name")?,
+            matcher: proto.matcher.optional("matcher")?.unwrap_or_default(),
+            target,
+            queue_size: NonZeroUsize::new(proto.queue_size as usize)
+                .unwrap_or_else(|| NonZeroUsize::new(DEFAULT_SUBSCRIPTION_QUEUE_SIZE).unwrap()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::influxdata::iox::router::v1 as router;
+    use std::convert::TryInto;
+    use subscription::subscription::Target;
+
+    fn proto_subscription(target: Target) -> subscription::Subscription {
+        subscription::Subscription {
+            name: "alerts".to_string(),
+            matcher: Some(router::Matcher {
+                table_name_regex: "^cpu".to_string(),
+            }),
+            target: Some(target),
+            queue_size: 10,
+        }
+    }
+
+    #[test]
+    fn test_subscription_round_trip() {
+        let targets = vec![
+            Target::Http(subscription::HttpTarget {
+                url: "http://localhost:8086/api/v2/write?org=o&bucket=b".to_string(),
+            }),
+            Target::Grpc(subscription::GrpcTarget {
+                connection: "http://localhost:8082".to_string(),
+                db_name: "db".to_string(),
+            }),
+            Target::File(subscription::FileTarget {
+                path: "writes.lp".to_string(),
+            }),
+        ];
+
+        for target in targets {
+            let protobuf = proto_subscription(target);
+            let sub: Subscription = protobuf.clone().try_into().unwrap();
+            assert!(sub.matches_table("cpu_load"));
+            assert!(!sub.matches_table("mem"));
+
+            let back: subscription::Subscription = sub.into();
+            assert_eq!(back, protobuf);
+        }
+    }
+
+    #[test]
+    fn test_subscription_defaults() {
+        let sub: Subscription = subscription::Subscription {
+            matcher: None,
+            queue_size: 0,
+            ..proto_subscription(Target::File(subscription::FileTarget {
+                path: "writes.lp".to_string(),
+            }))
+        }
+        .try_into()
+        .unwrap();
+
+        assert!(sub.matches_table("mem"));
+        assert_eq!(sub.queue_size.get(), DEFAULT_SUBSCRIPTION_QUEUE_SIZE);
+    }
+
+    #[test]
+    fn test_subscription_invalid() {
+        let err = Subscription::try_from(subscription::Subscription {
+            target: None,
+            ..proto_subscription(Target::Http(Default::default()))
+        })
+        .unwrap_err();
+        assert_eq!(err.field, "target");
+
+        let err =
+            Subscription::try_from(proto_subscription(Target::Grpc(subscription::GrpcTarget {
+                connection: "http://localhost:8082".to_string(),
+                db_name: Default::default(),
+            })))
+            .unwrap_err();
+        assert_eq!(err.field, "grpc.db_name");
+
+        for path in ["/tmp/writes.lp", "../writes.lp"] {
+            let err = Subscription::try_from(proto_subscription(Target::File(
+                subscription::FileTarget {
+                    path: path.to_string(),
+                },
+            )))
+            .unwrap_err();
+            assert_eq!(err.field, "file.path");
+        }
+
+        let err = Subscription::try_from(subscription::Subscription {
+            matcher: Some(router::Matcher {
+                table_name_regex: "(".to_string(),
+            }),
+            ..proto_subscription(Target::Http(subscription::HttpTarget {
+                url: "http://localhost:8086/write".to_string(),
+            }))
+        })
+        .unwrap_err();
+        assert_eq!(err.field, "matcher.table_name_regex");
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::TABLE_STYLE_SINGLE_LINE_BORDERS;
 use comfy_table::{Cell, Table};
+use dml::{DmlMeta, DmlWrite};
 use influxdb_iox_client::{
     connection::Connection,
     flight,
@@ -9,7 +10,16 @@ use influxdb_iox_client::{
     management::{self, generated_types::*},
     write,
 };
-use std::{fs::File, io::Read, num::NonZeroU64, path::PathBuf, str::FromStr, time::Duration};
+use influxdb_line_protocol::LineSplitter;
+use mutable_batch_lp::LinesConverter;
+use std::{
+    fs::File,
+    io::{Read, Write as _},
+    num::NonZeroU64,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
 use structopt::StructOpt;
 use thiserror::Error;
 use time::TimeProvider;
@@ -28,6 +38,25 @@ pub enum Error {
         source: std::io::Error,
     },
 
+    #[error("Error reading file {:?} as UTF-8: {}", file_name, source)]
+    ReadingFileAsUtf8 {
+        file_name: PathBuf,
+        source: std::str::Utf8Error,
+    },
+
+    #[error(
+        "Line in file {:?} exceeds limit of {} bytes, see --max-line-size",
+        file_name,
+        max_line_size
+    )]
+    LineSizeExceeded {
+        file_name: PathBuf,
+        max_line_size: usize,
+    },
+
+    #[error("Error parsing line protocol: {0}")]
+    ParsingLineProtocol(#[from] mutable_batch_lp::Error),
+
     #[error("Error formatting: {0}")]
     FormattingError(#[from] influxdb_iox_client::format::Error),
 
@@ -48,10 +77,16 @@ pub enum Error {
 
     #[error("Client error: {0}")]
     ClientError(#[from] influxdb_iox_client::error::Error),
+
+    #[error("Error writing query results: {0}")]
+    WritingOutput(#[source] std::io::Error),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Size of the blocks that files are read in by `database write`
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
 /// Manage IOx databases
 #[derive(Debug, StructOpt)]
 pub struct Config {
@@ -115,6 +150,26 @@ struct Create {
     /// value of zero disables Parquet file caching.
     #[structopt(long, default_value = "0")]
     parquet_cache_limit: u64,
+
+    /// Reject writes that would create more than this many tables. A value
+    /// of zero disables the limit.
+    #[structopt(long, default_value = "0")]
+    max_tables: u64,
+
+    /// Reject writes that would create more than this many columns in a
+    /// table. A value of zero disables the limit.
+    #[structopt(long, default_value = "0")]
+    max_columns_per_table: u64,
+
+    /// Reject writes that would give a tag more than this many distinct
+    /// values in a partition. A value of zero disables the limit.
+    #[structopt(long, default_value = "0")]
+    max_tag_cardinality: u64,
+
+    /// Drop data once its timestamps are older than this period, e.g. "30d".
+    /// By default data is kept forever.
+    #[structopt(long, parse(try_from_str = humantime::parse_duration))]
+    retention_period: Option<Duration>,
 }
 
 /// Get list of databases
@@ -146,6 +201,16 @@ struct Write {
 
     /// File with data to load. Currently supported formats are .lp
     file_name: PathBuf,
+
+    /// Approximate size in bytes of the line protocol sent per write
+    /// request. Larger files are read and written in multiple batches.
+    #[structopt(long, default_value = "1048576")] // 1 MiB
+    batch_size: usize,
+
+    /// Maximum size in bytes of a single line of line protocol. Lines
+    /// larger than the server's maximum request size cannot be written.
+    #[structopt(long, default_value = "10485760")] // 10 MiB
+    max_line_size: usize,
 }
 
 /// Query the data with SQL
@@ -157,7 +222,8 @@ struct Query {
     /// The query to run, in SQL format
     query: String,
 
-    /// Optional format ('pretty', 'json', or 'csv')
+    /// Optional format ('pretty', 'json', 'jsonl', 'csv', 'arrow', 'parquet'
+    /// or 'line_protocol')
     #[structopt(short, long, default_value = "pretty")]
     format: String,
 }
@@ -231,6 +297,10 @@ pub async fn command(connection: Connection, config: Config) -> Result<()> {
                     persist_age_threshold_seconds: command.persist_age_threshold_seconds,
                     mub_row_threshold: command.mub_row_threshold,
                     parquet_cache_limit: command.parquet_cache_limit,
+                    max_tables: command.max_tables,
+                    max_columns_per_table: command.max_columns_per_table,
+                    max_tag_cardinality: command.max_tag_cardinality,
+                    retention_period: command.retention_period.map(Into::into),
                 }),
 
                 // Default to hourly partitions
@@ -295,15 +365,62 @@ pub async fn command(connection: Connection, config: Config) -> Result<()> {
                 source: e,
             })?;
 
-            let mut lp_data = String::new();
-            file.read_to_string(&mut lp_data)
-                .map_err(|e| Error::ReadingFile {
+            let default_time = time::SystemProvider::new().now().timestamp_nanos();
+            let mut splitter = LineSplitter::default();
+            let mut converter = LinesConverter::new(default_time);
+
+            let mut buf = vec![0; READ_BUFFER_SIZE];
+            let mut batch_size = 0;
+            let mut lines_written = 0;
+            loop {
+                let read = file.read(&mut buf).map_err(|e| Error::ReadingFile {
                     file_name: write.file_name.clone(),
                     source: e,
                 })?;
+                let done = read == 0;
+
+                let lines = if done {
+                    std::mem::take(&mut splitter).finish()
+                } else {
+                    splitter.push(&buf[..read])
+                };
+                if let Some(lines) = lines {
+                    let lines =
+                        std::str::from_utf8(&lines).map_err(|e| Error::ReadingFileAsUtf8 {
+                            file_name: write.file_name.clone(),
+                            source: e,
+                        })?;
+                    converter.write_lp(lines)?;
+                    batch_size += lines.len();
+                }
 
-            let default_time = time::SystemProvider::new().now().timestamp_nanos();
-            let lines_written = client.write_lp(write.name, lp_data, default_time).await?;
+                // Don't buffer a line without end that could never be written
+                if splitter.pending_len() > write.max_line_size {
+                    return Err(Error::LineSizeExceeded {
+                        file_name: write.file_name.clone(),
+                        max_line_size: write.max_line_size,
+                    });
+                }
+
+                if (done || batch_size >= write.batch_size) && !converter.is_empty() {
+                    let (tables, stats) = converter.finish()?;
+                    let dml_write = DmlWrite::new(tables, DmlMeta::unsequenced(None));
+                    let database_batch =
+                        mutable_batch_pb::encode::encode_write(&write.name, &dml_write);
+                    client
+                        .write_pb(write::generated_types::WriteRequest {
+                            database_batch: Some(database_batch),
+                        })
+                        .await?;
+
+                    lines_written += stats.num_lines;
+                    batch_size = 0;
+                }
+
+                if done {
+                    break;
+                }
+            }
 
             println!("{} Lines OK", lines_written);
         }
@@ -326,9 +443,17 @@ pub async fn command(connection: Connection, config: Config) -> Result<()> {
                 batches.push(data);
             }
 
-            let formatted_result = format.format(&batches)?;
+            if format.is_binary() {
+                // Binary formats are written as is, to be redirected to a file
+                let bytes = format.format_bytes(&batches)?;
+                std::io::stdout()
+                    .write_all(&bytes)
+                    .map_err(Error::WritingOutput)?;
+            } else {
+                let formatted_result = format.format(&batches)?;
 
-            println!("{}", formatted_result);
+                println!("{}", formatted_result);
+            }
         }
         Command::Chunk(config) => {
             chunk::command(connection, config).await?;
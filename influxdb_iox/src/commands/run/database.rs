@@ -1,6 +1,6 @@
 //! Implementation of command line option for running server
 
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use crate::{
     influxdb_ioxd::{
@@ -62,6 +62,128 @@ pub struct Config {
     #[structopt(long = "--num-worker-threads", env = "INFLUXDB_IOX_NUM_WORKER_THREADS")]
     pub num_worker_threads: Option<usize>,
 
+    /// The maximum time a query may run for before it is cancelled, such as
+    /// "30s" or "5m".
+    ///
+    /// Queries can set a shorter timeout of their own: gRPC queries through
+    /// their deadline, and HTTP queries through the `timeout` parameter.
+    ///
+    /// If not specified, queries run until they complete
+    #[structopt(
+        long = "--query-timeout",
+        env = "INFLUXDB_IOX_QUERY_TIMEOUT",
+        parse(try_from_str = humantime::parse_duration)
+    )]
+    pub query_timeout: Option<Duration>,
+
+    /// The maximum memory, in bytes, all running queries may use together.
+    ///
+    /// If not specified, only `--query-memory-limit` limits the memory of
+    /// queries
+    #[structopt(
+        long = "--query-memory-pool-size",
+        env = "INFLUXDB_IOX_QUERY_MEMORY_POOL_SIZE"
+    )]
+    pub query_memory_pool_size: Option<usize>,
+
+    /// The maximum memory, in bytes, a single query may use.
+    ///
+    /// Sorts exceeding the memory available to them spill to
+    /// `--query-spill-dir`, other queries fail.
+    ///
+    /// If not specified, only `--query-memory-pool-size` limits the memory
+    /// of queries
+    #[structopt(long = "--query-memory-limit", env = "INFLUXDB_IOX_QUERY_MEMORY_LIMIT")]
+    pub query_memory_limit: Option<usize>,
+
+    /// The directory queries spill sorted data to once they exceed their
+    /// memory.
+    ///
+    /// If not specified, queries fail instead of spilling
+    #[structopt(long = "--query-spill-dir", env = "INFLUXDB_IOX_QUERY_SPILL_DIR")]
+    pub query_spill_dir: Option<PathBuf>,
+
+    /// The maximum number of queries running at once across all databases.
+    ///
+    /// Further queries wait for a running query to complete. If not
+    /// specified, the number of running queries is not limited
+    #[structopt(
+        long = "--max-concurrent-queries",
+        env = "INFLUXDB_IOX_MAX_CONCURRENT_QUERIES"
+    )]
+    pub max_concurrent_queries: Option<usize>,
+
+    /// The maximum number of queries of a single database running at once.
+    ///
+    /// If not specified, only `--max-concurrent-queries` limits the queries
+    /// of a database
+    #[structopt(
+        long = "--max-concurrent-queries-per-database",
+        env = "INFLUXDB_IOX_MAX_CONCURRENT_QUERIES_PER_DATABASE"
+    )]
+    pub max_concurrent_queries_per_database: Option<usize>,
+
+    /// The maximum number of queries waiting to run, across all databases and
+    /// for each database.
+    ///
+    /// Queries exceeding it are rejected. If not specified, any number of
+    /// queries may wait
+    #[structopt(long = "--max-queued-queries", env = "INFLUXDB_IOX_MAX_QUEUED_QUERIES")]
+    pub max_queued_queries: Option<usize>,
+
+    /// The directory subscriptions with a file target write to.
+    ///
+    /// The paths of file targets are relative to this directory. If not
+    /// specified, writes are not delivered to file targets
+    #[structopt(
+        long = "--subscription-file-dir",
+        env = "INFLUXDB_IOX_SUBSCRIPTION_FILE_DIR",
+        parse(from_os_str)
+    )]
+    pub subscription_file_dir: Option<PathBuf>,
+
+    /// The maximum time a request delivering a write to a subscription with
+    /// an HTTP target may take, such as "30s" or "1m".
+    #[structopt(
+        long = "--subscription-http-timeout",
+        env = "INFLUXDB_IOX_SUBSCRIPTION_HTTP_TIMEOUT",
+        default_value = "30s",
+        parse(try_from_str = humantime::parse_duration)
+    )]
+    pub subscription_http_timeout: Duration,
+
+    /// Path of the PEM encoded CA certificate used to verify the certificate
+    /// of subscription gRPC targets with `https` addresses, instead of the
+    /// well-known root certificates.
+    #[structopt(
+        long = "--subscription-tls-ca-cert",
+        env = "INFLUXDB_IOX_SUBSCRIPTION_TLS_CA_CERT",
+        parse(from_os_str)
+    )]
+    pub subscription_tls_ca_cert: Option<PathBuf>,
+
+    /// Path of the PEM encoded certificate presented to subscription gRPC
+    /// targets with `https` addresses that require client certificates.
+    #[structopt(
+        long = "--subscription-tls-cert",
+        env = "INFLUXDB_IOX_SUBSCRIPTION_TLS_CERT",
+        parse(from_os_str)
+    )]
+    pub subscription_tls_cert: Option<PathBuf>,
+
+    /// Path of the PEM encoded private key of `--subscription-tls-cert`.
+    #[structopt(
+        long = "--subscription-tls-key",
+        env = "INFLUXDB_IOX_SUBSCRIPTION_TLS_KEY",
+        parse(from_os_str)
+    )]
+    pub subscription_tls_key: Option<PathBuf>,
+
+    /// The token presented to subscription targets in the `authorization`
+    /// header, for targets that require authentication.
+    #[structopt(long = "--subscription-token", env = "INFLUXDB_IOX_SUBSCRIPTION_TOKEN")]
+    pub subscription_token: Option<String>,
+
     // TODO(marco): Remove once the database-run-mode (aka the `server` crate) cannot handle routing anymore and we're
     //              fully migrated to the new router code.
     /// When IOx nodes need to talk to remote peers they consult an internal remote address
@@ -92,7 +214,7 @@ pub struct Config {
 }
 
 pub async fn command(config: Config) -> Result<()> {
-    let common_state = CommonServerState::from_config(config.run_config.clone())?;
+    let common_state = CommonServerState::from_config(config.run_config.clone()).await?;
 
     let application = make_application(&config, common_state.trace_collector()).await?;
     let app_server = make_server(Arc::clone(&application), &config);
@@ -1,6 +1,6 @@
 //! Implementation of command line option for running server
 
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use crate::{
     influxdb_ioxd::{
@@ -10,8 +10,12 @@ use crate::{
             router::RouterServerType,
         },
     },
-    structopt_blocks::run_config::RunConfig,
+    structopt_blocks::{
+        run_config::RunConfig,
+        tls::{configure_client, ClientTlsError},
+    },
 };
+use influxdb_iox_client::connection::Builder;
 use observability_deps::tracing::warn;
 use router::{resolver::RemoteTemplate, server::RouterServer};
 use structopt::StructOpt;
@@ -28,6 +32,9 @@ pub enum Error {
 
     #[error("Invalid config: {0}")]
     InvalidConfig(#[from] CommonServerStateError),
+
+    #[error("Invalid remote TLS config: {0}")]
+    InvalidRemoteTlsConfig(#[from] ClientTlsError),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -59,10 +66,42 @@ pub struct Config {
     /// Example: http://node-{id}.ioxmydomain.com:8082
     #[structopt(long = "--remote-template", env = "INFLUXDB_IOX_REMOTE_TEMPLATE")]
     pub remote_template: Option<String>,
+
+    /// Path of the PEM encoded CA certificate used to verify the certificate of remotes with
+    /// `https` addresses, instead of the well-known root certificates.
+    #[structopt(
+        long = "--remote-tls-ca-cert",
+        env = "INFLUXDB_IOX_REMOTE_TLS_CA_CERT",
+        parse(from_os_str)
+    )]
+    pub remote_tls_ca_cert: Option<PathBuf>,
+
+    /// Path of the PEM encoded certificate presented to remotes with `https` addresses that
+    /// require client certificates.
+    #[structopt(
+        long = "--remote-tls-cert",
+        env = "INFLUXDB_IOX_REMOTE_TLS_CERT",
+        parse(from_os_str)
+    )]
+    pub remote_tls_cert: Option<PathBuf>,
+
+    /// Path of the PEM encoded private key of `--remote-tls-cert`.
+    #[structopt(
+        long = "--remote-tls-key",
+        env = "INFLUXDB_IOX_REMOTE_TLS_KEY",
+        parse(from_os_str)
+    )]
+    pub remote_tls_key: Option<PathBuf>,
 }
 
 pub async fn command(config: Config) -> Result<()> {
-    let common_state = CommonServerState::from_config(config.run_config.clone())?;
+    let common_state = CommonServerState::from_config(config.run_config.clone()).await?;
+    let grpc_builder = configure_client(
+        Builder::default(),
+        config.remote_tls_ca_cert.as_deref(),
+        config.remote_tls_cert.as_deref(),
+        config.remote_tls_key.as_deref(),
+    )?;
 
     let remote_template = config.remote_template.map(RemoteTemplate::new);
     let time_provider = Arc::new(SystemProvider::new());
@@ -71,6 +110,7 @@ pub async fn command(config: Config) -> Result<()> {
             remote_template,
             common_state.trace_collector(),
             time_provider,
+            grpc_builder,
         )
         .await,
     );
@@ -22,7 +22,8 @@ pub struct Config {
     /// Format to use for output. Can be overridden using
     /// `SET FORMAT` command
     ///
-    /// Optional format ('pretty', 'json', or 'csv')
+    /// Optional format ('pretty', 'json', 'jsonl', 'csv', 'arrow', 'parquet'
+    /// or 'line_protocol')
     #[structopt(short, long, default_value = "pretty")]
     format: String,
 }
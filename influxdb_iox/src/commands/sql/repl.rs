@@ -1,4 +1,4 @@
-use std::{convert::TryInto, path::PathBuf, sync::Arc, time::Instant};
+use std::{convert::TryInto, io::Write, path::PathBuf, sync::Arc, time::Instant};
 
 use arrow::{
     array::{ArrayRef, StringArray},
@@ -27,6 +27,9 @@ pub enum Error {
         source: influxdb_iox_client::format::Error,
     },
 
+    #[snafu(display("Error writing results: {}", source))]
+    WritingResults { source: std::io::Error },
+
     #[snafu(display("Error setting format to '{}': {}", requested_format, source))]
     SettingFormat {
         requested_format: String,
@@ -310,6 +313,17 @@ impl Repl {
 
     /// Prints to the specified output format
     fn print_results(&self, batches: &[RecordBatch]) -> Result<()> {
+        if self.output_format.is_binary() {
+            // Binary formats are written as is, e.g. when piping the REPL
+            let bytes = self
+                .output_format
+                .format_bytes(batches)
+                .context(FormattingResults)?;
+            let mut stdout = std::io::stdout();
+            stdout.write_all(&bytes).context(WritingResults)?;
+            return stdout.flush().context(WritingResults);
+        }
+
         let formatted_results = self
             .output_format
             .format(batches)
@@ -96,7 +96,7 @@ SHOW DATABASES: List databases available on the server
 
 USE [DATABASE] <name>: Set the current remote database to name
 
-SET FORMAT <format>: Set the output format to Pretty, csv or json
+SET FORMAT <format>: Set the output format to Pretty, csv, json, jsonl, arrow, parquet or line_protocol
 
 OBSERVER: Locally query unified queryable views of remote system tables
 
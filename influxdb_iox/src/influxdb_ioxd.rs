@@ -7,12 +7,14 @@ use snafu::{ResultExt, Snafu};
 use std::{net::SocketAddr, sync::Arc};
 use trace_http::ctx::TraceHeaderParser;
 
+pub(crate) mod auth;
 mod http;
 mod jemalloc;
 mod planner;
 pub(crate) mod rpc;
 pub(crate) mod server_type;
 pub(crate) mod serving_readiness;
+pub(crate) mod tls;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -200,10 +202,20 @@ where
                 .traces_jaeger_debug_name,
         );
 
+    let tls_acceptor = common_state.tls_acceptor().cloned();
+    if let Some(tls_acceptor) = &tls_acceptor {
+        tokio::spawn(
+            tls_acceptor
+                .clone()
+                .reload_on_change(frontend_shutdown.clone()),
+        );
+    }
+
     // Construct and start up gRPC server
 
     let grpc_server = rpc::serve(
         grpc_listener,
+        tls_acceptor.clone(),
         Arc::clone(&server_type),
         trace_header_parser.clone(),
         frontend_shutdown.clone(),
@@ -215,6 +227,7 @@ where
 
     let http_server = http::serve(
         http_listener,
+        tls_acceptor,
         Arc::clone(&server_type),
         frontend_shutdown.clone(),
         trace_header_parser,
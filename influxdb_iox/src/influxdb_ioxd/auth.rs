@@ -0,0 +1,604 @@
+//! Token-based authentication and per-database authorization of API requests.
+//!
+//! The tokens accepted by the server are defined in a JSON file, read either from the local
+//! filesystem (`--auth-tokens-file`) or from next to the server config in the object store
+//! (`--auth-tokens-in-object-store`):
+//!
+//! ```json
+//! {
+//!   "tokens": [
+//!     { "name": "telegraf", "token": "s3cr3t", "permissions": { "mydb": ["write"] } },
+//!     { "name": "ops", "token": "0ps", "permissions": { "*": ["admin"] } }
+//!   ]
+//! }
+//! ```
+//!
+//! Permissions granted on the database `*` apply to all databases as well as to server-wide
+//! operations such as listing databases or setting the server ID. `admin` implies `read` and
+//! `write`.
+//!
+//! Requests present their token in the `authorization` HTTP header or gRPC metadata, as
+//! `Bearer <token>` or `Token <token>`. If no tokens are configured, requests are not
+//! authenticated at all.
+//!
+//! Every authorization decision is logged with the [`AUDIT_TARGET`] target.
+
+use std::{collections::HashMap, fmt, path::PathBuf, sync::Arc};
+
+use hyper::{header::AUTHORIZATION, HeaderMap, Request};
+use iox_object_store::IoxObjectStore;
+use object_store::ObjectStore;
+use observability_deps::tracing::{info, warn};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use subtle::ConstantTimeEq;
+use tonic::{metadata::MetadataMap, Status};
+
+use crate::{
+    influxdb_ioxd::http::error::{HttpApiError, HttpApiErrorExt, HttpApiErrorSource},
+    structopt_blocks::run_config::RunConfig,
+};
+
+/// Tracing target of the audit log of authorization decisions
+pub const AUDIT_TARGET: &str = "iox::audit";
+
+/// Database name under which permissions for all databases and the server are granted
+pub const ALL_DATABASES: &str = "*";
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Cannot read auth tokens file {:?}: {}", path, source))]
+    ReadingFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Cannot parse object store config: {}", source))]
+    ObjectStoreParsing {
+        source: crate::structopt_blocks::object_store::ParseError,
+    },
+
+    #[snafu(display("Cannot read auth tokens from object store: {}", source))]
+    ReadingObjectStore { source: object_store::Error },
+
+    #[snafu(display("The server ID must be configured to read auth tokens from object store"))]
+    ServerIdNotSet,
+
+    #[snafu(display("Auth tokens cannot be read from both a file and the object store"))]
+    ConflictingSources,
+
+    #[snafu(display("Cannot parse auth tokens: {}", source))]
+    Parsing { source: serde_json::Error },
+
+    #[snafu(display("Auth token '{}' is defined more than once", name))]
+    DuplicateToken { name: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Reason for denying a request
+#[derive(Debug, Snafu)]
+pub enum AuthError {
+    #[snafu(display("Missing authorization token"))]
+    MissingToken,
+
+    #[snafu(display("Invalid authorization token"))]
+    InvalidToken,
+
+    #[snafu(display(
+        "Token '{}' does not grant {} permission on {}",
+        name,
+        permission,
+        target
+    ))]
+    PermissionDenied {
+        name: String,
+        permission: Permission,
+        target: String,
+    },
+}
+
+impl HttpApiErrorSource for AuthError {
+    fn to_http_api_error(&self) -> HttpApiError {
+        match self {
+            e @ Self::MissingToken => e.unauthorized(),
+            e @ Self::InvalidToken => e.unauthorized(),
+            e @ Self::PermissionDenied { .. } => e.forbidden(),
+        }
+    }
+}
+
+impl From<AuthError> for Status {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::MissingToken | AuthError::InvalidToken => {
+                Self::unauthenticated(e.to_string())
+            }
+            AuthError::PermissionDenied { .. } => Self::permission_denied(e.to_string()),
+        }
+    }
+}
+
+/// Permission a token can be granted on a database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// Query data
+    Read,
+    /// Write and delete data
+    Write,
+    /// Manage the database or, if granted on [`ALL_DATABASES`], the server
+    Admin,
+}
+
+impl Permission {
+    /// Returns true if being granted `self` also grants `other`
+    fn implies(self, other: Self) -> bool {
+        self == other || self == Self::Admin
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+            Self::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Contents of an auth tokens file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokensFile {
+    tokens: Vec<TokenDefinition>,
+}
+
+/// A token and the permissions it grants
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokenDefinition {
+    /// Name of the token, used to identify it in logs and errors
+    name: String,
+    /// The secret presented by requests
+    token: String,
+    /// Permissions granted, by database name
+    #[serde(default)]
+    permissions: HashMap<String, Vec<Permission>>,
+}
+
+impl TokenDefinition {
+    /// Returns true if the token grants `permission` on `database`, or on the server if
+    /// `database` is `None`
+    fn grants(&self, database: Option<&str>, permission: Permission) -> bool {
+        let granted = |database: &str| {
+            self.permissions
+                .get(database)
+                .map(|granted| granted.iter().any(|p| p.implies(permission)))
+                .unwrap_or(false)
+        };
+
+        granted(ALL_DATABASES) || database.map(granted).unwrap_or(false)
+    }
+}
+
+/// SHA-256 digest of a token secret
+type SecretDigest = [u8; 32];
+
+fn secret_digest(secret: &str) -> SecretDigest {
+    Sha256::digest(secret.as_bytes()).into()
+}
+
+/// Checks the permissions of requests against the configured tokens
+#[derive(Default)]
+pub struct Authorizer {
+    /// Token definitions with the digests of their secrets, or `None` if authorization is
+    /// disabled
+    tokens: Option<Vec<(SecretDigest, TokenDefinition)>>,
+}
+
+impl fmt::Debug for Authorizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't print the secrets
+        let names = self.tokens.as_ref().map(|tokens| {
+            tokens
+                .iter()
+                .map(|(_, t)| t.name.as_str())
+                .collect::<Vec<_>>()
+        });
+        f.debug_struct("Authorizer")
+            .field("tokens", &names)
+            .finish()
+    }
+}
+
+impl Authorizer {
+    /// Creates an authorizer that allows all requests
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Creates an authorizer from the contents of an auth tokens file
+    pub fn try_from_json(data: &[u8]) -> Result<Self> {
+        let file: TokensFile = serde_json::from_slice(data).context(Parsing)?;
+
+        let mut tokens: Vec<(SecretDigest, TokenDefinition)> =
+            Vec::with_capacity(file.tokens.len());
+        for definition in file.tokens {
+            let digest = secret_digest(&definition.token);
+            ensure!(
+                !tokens.iter().any(|(d, _)| *d == digest),
+                DuplicateToken {
+                    name: definition.name
+                }
+            );
+            tokens.push((digest, definition));
+        }
+
+        Ok(Self {
+            tokens: Some(tokens),
+        })
+    }
+
+    /// Creates an authorizer from the auth tokens file configured in `run_config`, if any
+    pub async fn from_config(run_config: &RunConfig) -> Result<Self> {
+        let in_object_store: bool = run_config.auth_tokens_in_object_store.into();
+
+        match (&run_config.auth_tokens_file, in_object_store) {
+            (Some(_), true) => ConflictingSources.fail(),
+            (Some(path), false) => {
+                let data = std::fs::read(path).context(ReadingFile { path })?;
+                Self::try_from_json(&data)
+            }
+            (None, true) => {
+                let server_id = run_config
+                    .server_id_config
+                    .server_id
+                    .context(ServerIdNotSet)?;
+                let object_store = ObjectStore::try_from(&run_config.object_store_config)
+                    .context(ObjectStoreParsing)?;
+                let data = IoxObjectStore::get_auth_tokens_file(&object_store, server_id)
+                    .await
+                    .context(ReadingObjectStore)?;
+                Self::try_from_json(&data)
+            }
+            (None, false) => Ok(Self::disabled()),
+        }
+    }
+
+    /// Returns true if requests are checked against a set of tokens
+    pub fn is_enabled(&self) -> bool {
+        self.tokens.is_some()
+    }
+
+    /// Checks that `token` grants `permission` on `database`, or on the server if `database` is
+    /// `None`, in order to perform `action`, and records the decision in the audit log
+    pub fn authorize(
+        &self,
+        token: &AuthToken,
+        database: Option<&str>,
+        permission: Permission,
+        action: &str,
+    ) -> Result<(), AuthError> {
+        let tokens = match &self.tokens {
+            Some(tokens) => tokens,
+            None => return Ok(()),
+        };
+
+        let target = database.unwrap_or(ALL_DATABASES);
+        let result = match token.0.as_deref().map(|secret| find_token(tokens, secret)) {
+            None => Err(AuthError::MissingToken),
+            Some(None) => Err(AuthError::InvalidToken),
+            Some(Some(definition)) if definition.grants(database, permission) => Ok(definition),
+            Some(Some(definition)) => Err(AuthError::PermissionDenied {
+                name: definition.name.clone(),
+                permission,
+                target: match database {
+                    Some(database) => format!("database '{}'", database),
+                    None => "the server".to_string(),
+                },
+            }),
+        };
+
+        match result {
+            Ok(definition) => {
+                info!(target: AUDIT_TARGET, token=%definition.name, database=%target, %permission, %action, "access granted");
+                Ok(())
+            }
+            Err(e) => {
+                warn!(target: AUDIT_TARGET, database=%target, %permission, %action, reason=%e, "access denied");
+                Err(e)
+            }
+        }
+    }
+
+    /// Implements the gRPC interceptor that checks requests are granted `permission` on the
+    /// server, for services that are not specific to a database
+    pub fn into_interceptor(
+        self: Arc<Self>,
+        permission: Permission,
+        action: &'static str,
+    ) -> impl FnMut(tonic::Request<()>) -> Result<tonic::Request<()>, Status> + Clone {
+        move |req| {
+            let token = AuthToken::from_metadata(req.metadata());
+            self.authorize(&token, None, permission, action)?;
+            Ok(req)
+        }
+    }
+}
+
+/// Returns the definition of the token with the given secret, if any.
+///
+/// The digest of `secret` is compared in constant time against the digests of all tokens, so
+/// that the time taken reveals nothing about how much of a secret was guessed correctly.
+fn find_token<'a>(
+    tokens: &'a [(SecretDigest, TokenDefinition)],
+    secret: &str,
+) -> Option<&'a TokenDefinition> {
+    let digest = secret_digest(secret);
+    tokens.iter().fold(None, |found, (d, definition)| {
+        if bool::from(d[..].ct_eq(&digest[..])) {
+            Some(definition)
+        } else {
+            found
+        }
+    })
+}
+
+/// The token presented by a request, if any
+#[derive(Clone, Default)]
+pub struct AuthToken(Option<String>);
+
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't print the secret
+        match self.0 {
+            Some(_) => write!(f, "AuthToken(..)"),
+            None => write!(f, "AuthToken(None)"),
+        }
+    }
+}
+
+impl AuthToken {
+    /// Extracts the token from the `authorization` header value
+    fn parse(value: Option<&str>) -> Self {
+        let token = value.and_then(|value| {
+            let (scheme, token) = value.trim().split_once(' ')?;
+            (scheme.eq_ignore_ascii_case("bearer") || scheme.eq_ignore_ascii_case("token"))
+                .then(|| token.trim().to_string())
+        });
+        Self(token)
+    }
+
+    /// Extracts the token from HTTP request headers
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Self::parse(headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok()))
+    }
+
+    /// Extracts the token from gRPC request metadata
+    pub fn from_metadata(metadata: &MetadataMap) -> Self {
+        Self::parse(
+            metadata
+                .get(AUTHORIZATION.as_str())
+                .and_then(|v| v.to_str().ok()),
+        )
+    }
+
+    /// Returns the token of an HTTP request, which is moved from its headers to its extensions
+    /// before it is routed
+    pub fn from_request<B>(req: &Request<B>) -> Self {
+        req.extensions().get().cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use data_types::server_id::ServerId;
+    use hyper::header::HeaderValue;
+    use std::convert::TryFrom;
+
+    const TOKENS: &str = r#"{
+        "tokens": [
+            { "name": "reader", "token": "r", "permissions": { "db1": ["read"] } },
+            { "name": "writer", "token": "w", "permissions": { "db1": ["write"], "db2": ["read", "write"] } },
+            { "name": "db_admin", "token": "a", "permissions": { "db2": ["admin"] } },
+            { "name": "server_admin", "token": "s", "permissions": { "*": ["admin"] } },
+            { "name": "nothing", "token": "n" }
+        ]
+    }"#;
+
+    fn token(secret: &str) -> AuthToken {
+        AuthToken(Some(secret.to_string()))
+    }
+
+    #[test]
+    fn test_disabled() {
+        let authorizer = Authorizer::disabled();
+        assert!(!authorizer.is_enabled());
+        authorizer
+            .authorize(&AuthToken::default(), None, Permission::Admin, "test")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_authorize() {
+        let authorizer = Authorizer::try_from_json(TOKENS.as_bytes()).unwrap();
+        assert!(authorizer.is_enabled());
+
+        let allowed = |secret: &str, database: Option<&str>, permission: Permission| {
+            authorizer
+                .authorize(&token(secret), database, permission, "test")
+                .is_ok()
+        };
+
+        assert!(allowed("r", Some("db1"), Permission::Read));
+        assert!(!allowed("r", Some("db1"), Permission::Write));
+        assert!(!allowed("r", Some("db2"), Permission::Read));
+        assert!(!allowed("r", None, Permission::Read));
+
+        assert!(allowed("w", Some("db1"), Permission::Write));
+        assert!(!allowed("w", Some("db1"), Permission::Read));
+        assert!(allowed("w", Some("db2"), Permission::Read));
+        assert!(!allowed("w", Some("db2"), Permission::Admin));
+
+        assert!(allowed("a", Some("db2"), Permission::Read));
+        assert!(allowed("a", Some("db2"), Permission::Write));
+        assert!(allowed("a", Some("db2"), Permission::Admin));
+        assert!(!allowed("a", Some("db1"), Permission::Read));
+        assert!(!allowed("a", None, Permission::Admin));
+
+        assert!(allowed("s", Some("db1"), Permission::Write));
+        assert!(allowed("s", Some("other"), Permission::Admin));
+        assert!(allowed("s", None, Permission::Admin));
+
+        assert!(!allowed("n", Some("db1"), Permission::Read));
+    }
+
+    #[test]
+    fn test_errors() {
+        let authorizer = Authorizer::try_from_json(TOKENS.as_bytes()).unwrap();
+
+        let err = authorizer
+            .authorize(&AuthToken::default(), Some("db1"), Permission::Read, "test")
+            .unwrap_err();
+        assert!(matches!(err, AuthError::MissingToken));
+        assert_eq!(Status::from(err).code(), tonic::Code::Unauthenticated);
+
+        for secret in ["x", "", "rr", "R"] {
+            let err = authorizer
+                .authorize(&token(secret), Some("db1"), Permission::Read, "test")
+                .unwrap_err();
+            assert!(matches!(err, AuthError::InvalidToken));
+        }
+
+        let err = authorizer
+            .authorize(&token("r"), Some("db1"), Permission::Write, "test")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Token 'reader' does not grant write permission on database 'db1'"
+        );
+        assert_eq!(Status::from(err).code(), tonic::Code::PermissionDenied);
+
+        let err = authorizer
+            .authorize(&token("r"), None, Permission::Admin, "test")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Token 'reader' does not grant admin permission on the server"
+        );
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let err = Authorizer::try_from_json(br#"{"tokens": [{"name": "a"}]}"#).unwrap_err();
+        assert!(matches!(err, Error::Parsing { .. }));
+
+        let err = Authorizer::try_from_json(
+            br#"{"tokens": [{"name": "a", "token": "x", "permissions": {"db": ["delete"]}}]}"#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::Parsing { .. }));
+
+        let err = Authorizer::try_from_json(
+            br#"{"tokens": [{"name": "a", "token": "x"}, {"name": "b", "token": "x"}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Auth token 'b' is defined more than once");
+    }
+
+    #[test]
+    fn test_debug_hides_secrets() {
+        let authorizer = Authorizer::try_from_json(TOKENS.as_bytes()).unwrap();
+        assert!(!format!("{:?}", authorizer).contains("\"s\""));
+        assert_eq!(format!("{:?}", token("s3cr3t")), "AuthToken(..)");
+    }
+
+    #[test]
+    fn test_parse_token() {
+        let parse = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+            AuthToken::from_headers(&headers).0
+        };
+
+        assert_eq!(parse("Bearer abc").as_deref(), Some("abc"));
+        assert_eq!(parse("Token abc").as_deref(), Some("abc"));
+        assert_eq!(parse("bearer  abc ").as_deref(), Some("abc"));
+        assert_eq!(parse("Basic abc"), None);
+        assert_eq!(parse("abc"), None);
+        assert_eq!(AuthToken::from_headers(&HeaderMap::new()).0, None);
+
+        let mut metadata = MetadataMap::new();
+        metadata.insert("authorization", "Bearer abc".parse().unwrap());
+        assert_eq!(
+            AuthToken::from_metadata(&metadata).0.as_deref(),
+            Some("abc")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_from_config() {
+        use structopt::StructOpt;
+
+        let config = |args: &[&str]| {
+            RunConfig::from_iter_safe(std::iter::once("not_used").chain(args.iter().copied()))
+                .unwrap()
+        };
+
+        let authorizer = Authorizer::from_config(&config(&[])).await.unwrap();
+        assert!(!authorizer.is_enabled());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.json");
+        std::fs::write(&path, TOKENS).unwrap();
+        let path = path.to_str().unwrap();
+
+        let authorizer = Authorizer::from_config(&config(&["--auth-tokens-file", path]))
+            .await
+            .unwrap();
+        assert!(authorizer.is_enabled());
+
+        let err = Authorizer::from_config(&config(&[
+            "--auth-tokens-file",
+            path,
+            "--auth-tokens-in-object-store",
+            "yes",
+        ]))
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::ConflictingSources));
+
+        let err = Authorizer::from_config(&config(&["--auth-tokens-in-object-store", "yes"]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ServerIdNotSet));
+
+        // the object store is persisted in `dir`, so that the config can refer to it
+        let object_store = ObjectStore::new_file(dir.path());
+        let server_id = ServerId::try_from(1).unwrap();
+        IoxObjectStore::put_auth_tokens_file(&object_store, server_id, Bytes::from(TOKENS))
+            .await
+            .unwrap();
+
+        let authorizer = Authorizer::from_config(&config(&[
+            "--auth-tokens-in-object-store",
+            "yes",
+            "--server-id",
+            "1",
+            "--object-store",
+            "file",
+            "--data-dir",
+            dir.path().to_str().unwrap(),
+        ]))
+        .await
+        .unwrap();
+        authorizer
+            .authorize(&token("s"), None, Permission::Admin, "test")
+            .unwrap();
+    }
+}
@@ -8,13 +8,22 @@ use data_types::{
     DatabaseName,
 };
 use dml::{DmlDelete, DmlMeta, DmlOperation, DmlWrite};
+use generated_types::prometheus::WriteRequest;
 use hyper::{Body, Method, Request, Response, StatusCode};
+use influxdb_line_protocol::LineSplitter;
+use mutable_batch_lp::LinesConverter;
 use observability_deps::tracing::debug;
 use predicate::delete_predicate::{parse_delete_predicate, parse_http_delete_request};
+use prost::Message;
 use serde::Deserialize;
-use snafu::{OptionExt, ResultExt, Snafu};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use trace::ctx::SpanContext;
 
-use crate::influxdb_ioxd::{http::utils::parse_body, server_type::ServerType};
+use crate::influxdb_ioxd::{
+    auth::{AuthError, AuthToken, Authorizer, Permission},
+    http::utils::{parse_body, stream_body},
+    server_type::ServerType,
+};
 
 use super::{
     error::{HttpApiError, HttpApiErrorExt, HttpApiErrorSource},
@@ -80,6 +89,15 @@ pub enum HttpDmlError {
     #[snafu(display("Error parsing line protocol: {}", source))]
     ParsingLineProtocol { source: mutable_batch_lp::Error },
 
+    #[snafu(display("Line protocol line exceeds limit of {} bytes", max_line_size))]
+    LineSizeExceeded { max_line_size: usize },
+
+    #[snafu(display("Error decoding Prometheus remote write request: {}", source))]
+    DecodingPrometheusWrite { source: prost::DecodeError },
+
+    #[snafu(display("Error converting Prometheus remote write request: {}", source))]
+    ConvertingPrometheusWrite { source: mutable_batch_prom::Error },
+
     #[snafu(display("Database {} not found", db_name))]
     NotFoundDatabase { db_name: String },
 
@@ -99,6 +117,9 @@ pub enum HttpDmlError {
         source: predicate::delete_predicate::Error,
         input: String,
     },
+
+    #[snafu(display("Access denied: {}", source))]
+    Unauthorized { source: AuthError },
 }
 
 impl HttpApiErrorSource for HttpDmlError {
@@ -113,10 +134,14 @@ impl HttpApiErrorSource for HttpDmlError {
             e @ Self::InvalidQueryString { .. } => e.invalid(),
             e @ Self::ReadingBodyAsUtf8 { .. } => e.invalid(),
             e @ Self::ParsingLineProtocol { .. } => e.invalid(),
+            e @ Self::LineSizeExceeded { .. } => e.invalid(),
+            e @ Self::DecodingPrometheusWrite { .. } => e.invalid(),
+            e @ Self::ConvertingPrometheusWrite { .. } => e.invalid(),
             e @ Self::NotFoundDatabase { .. } => e.not_found(),
             Self::ParseBody { source } => source.to_http_api_error(),
             e @ Self::ParsingDelete { .. } => e.invalid(),
             e @ Self::BuildingDeletePredicate { .. } => e.invalid(),
+            Self::Unauthorized { source } => source.to_http_api_error(),
         }
     }
 }
@@ -174,6 +199,12 @@ pub enum RequestOrResponse {
 pub trait HttpDrivenDml: ServerType {
     /// Routes HTTP write requests.
     ///
+    /// The line protocol body is converted as it is received and written in
+    /// batches of roughly [`write_batch_size`](Self::write_batch_size) bytes,
+    /// so only a single batch is held in memory at a time. If an error occurs
+    /// part way through the body, batches written before it are not rolled
+    /// back.
+    ///
     /// Returns `RequestOrResponse::Response` if the request was routed,
     /// Returns `RequestOrResponse::Response` if the request did not match (and needs to be handled some other way)
     async fn route_write_http_request(
@@ -187,7 +218,7 @@ pub trait HttpDrivenDml: ServerType {
         let span_ctx = req.extensions().get().cloned();
 
         let max_request_size = self.max_request_size();
-        let lp_metrics = self.lp_metrics();
+        let write_batch_size = self.write_batch_size();
 
         let query = req.uri().query().context(ExpectedQueryString)?;
 
@@ -199,17 +230,135 @@ pub trait HttpDrivenDml: ServerType {
         let db_name = org_and_bucket_to_database(&write_info.org, &write_info.bucket)
             .context(BucketMappingError)?;
 
-        let body = parse_body(req, max_request_size).await.context(ParseBody)?;
+        self.authorizer()
+            .authorize(
+                &AuthToken::from_request(&req),
+                Some(db_name.as_str()),
+                Permission::Write,
+                "http write",
+            )
+            .context(Unauthorized)?;
 
-        let body = std::str::from_utf8(&body).context(ReadingBodyAsUtf8)?;
+        debug!(
+            %db_name,
+            org=%write_info.org,
+            bucket=%write_info.bucket,
+            "writing lines into database",
+        );
+
+        let mut body = stream_body(req, max_request_size).context(ParseBody)?;
+        let mut splitter = LineSplitter::default();
 
         // The time, in nanoseconds since the epoch, to assign to any points that don't
         // contain a timestamp
         let default_time = Utc::now().timestamp_nanos();
+        let mut converter = LinesConverter::new(default_time);
+
+        // The size of the line protocol converted since the last write
+        let mut batch_size = 0;
+        let mut num_writes = 0;
+
+        loop {
+            let chunk = body.next_chunk().await.context(ParseBody)?;
+            let done = chunk.is_none();
+
+            // Split the chunk into pieces no larger than a batch, so that
+            // large chunks are also written in batches of the expected size
+            let pieces: Vec<_> = match chunk {
+                Some(chunk) => chunk
+                    .chunks(write_batch_size.max(1))
+                    .filter_map(|piece| splitter.push(piece))
+                    .collect(),
+                None => std::mem::take(&mut splitter).finish().into_iter().collect(),
+            };
+            for lines in pieces {
+                let lines = std::str::from_utf8(&lines).context(ReadingBodyAsUtf8)?;
+                converter.write_lp(lines).context(ParsingLineProtocol)?;
+                batch_size += lines.len();
+
+                if batch_size >= write_batch_size && !converter.is_empty() {
+                    write_lines(self, &db_name, &mut converter, batch_size, span_ctx.clone())
+                        .await?;
+                    batch_size = 0;
+                    num_writes += 1;
+                }
+            }
+
+            ensure!(
+                splitter.pending_len() <= max_request_size,
+                LineSizeExceeded {
+                    max_line_size: max_request_size
+                }
+            );
+
+            if done {
+                if !converter.is_empty() {
+                    write_lines(self, &db_name, &mut converter, batch_size, span_ctx).await?;
+                    num_writes += 1;
+                }
+                break;
+            }
+        }
+
+        if num_writes == 0 {
+            debug!("nothing to write");
+        }
+
+        Ok(RequestOrResponse::Response(
+            Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap(),
+        ))
+    }
+
+    /// Routes Prometheus remote write requests.
+    ///
+    /// The body is a snappy-compressed `prometheus.WriteRequest` protobuf. Each
+    /// metric is written to the table of the same name, with its labels as tags
+    /// and its samples in the `value` field.
+    ///
+    /// Returns `RequestOrResponse::Response` if the request was routed,
+    /// Returns `RequestOrResponse::Response` if the request did not match (and needs to be handled some other way)
+    async fn route_prometheus_write_http_request(
+        &self,
+        req: Request<Body>,
+    ) -> Result<RequestOrResponse, HttpDmlError> {
+        if (req.method() != Method::POST) || (req.uri().path() != "/api/v1/prom/write") {
+            return Ok(RequestOrResponse::Request(req));
+        }
+
+        let span_ctx = req.extensions().get().cloned();
+
+        let max_request_size = self.max_request_size();
+        let lp_metrics = self.lp_metrics();
+
+        let query = req.uri().query().context(ExpectedQueryString)?;
+
+        let write_info: WriteInfo =
+            serde_urlencoded::from_str(query).context(InvalidQueryString {
+                query_string: String::from(query),
+            })?;
+
+        let db_name = org_and_bucket_to_database(&write_info.org, &write_info.bucket)
+            .context(BucketMappingError)?;
 
-        let (tables, stats) = match mutable_batch_lp::lines_to_batches_stats(body, default_time) {
+        self.authorizer()
+            .authorize(
+                &AuthToken::from_request(&req),
+                Some(db_name.as_str()),
+                Permission::Write,
+                "prometheus write",
+            )
+            .context(Unauthorized)?;
+
+        let body = parse_body(req, max_request_size).await.context(ParseBody)?;
+
+        let request = WriteRequest::decode(body.clone()).context(DecodingPrometheusWrite)?;
+
+        let (tables, stats) = match mutable_batch_prom::write_request_to_batches_stats(&request) {
             Ok(x) => x,
-            Err(mutable_batch_lp::Error::EmptyPayload) => {
+            Err(mutable_batch_prom::Error::EmptyPayload) => {
                 debug!("nothing to write");
                 return Ok(RequestOrResponse::Response(
                     Response::builder()
@@ -218,27 +367,28 @@ pub trait HttpDrivenDml: ServerType {
                         .unwrap(),
                 ));
             }
-            Err(source) => return Err(HttpDmlError::ParsingLineProtocol { source }),
+            Err(source) => return Err(HttpDmlError::ConvertingPrometheusWrite { source }),
         };
 
         debug!(
-            num_lines=stats.num_lines,
-            num_fields=stats.num_fields,
+            num_series=stats.num_series,
+            num_samples=stats.num_samples,
             body_size=body.len(),
             %db_name,
             org=%write_info.org,
             bucket=%write_info.bucket,
-            "inserting lines into database",
+            "inserting prometheus samples into database",
         );
 
         let write = DmlWrite::new(tables, DmlMeta::unsequenced(span_ctx));
 
+        // Every sample is recorded as a line with a single field
         match self.write(&db_name, DmlOperation::Write(write)).await {
             Ok(_) => {
                 lp_metrics.record_write(
                     &db_name,
-                    stats.num_lines,
-                    stats.num_fields,
+                    stats.num_samples,
+                    stats.num_samples,
                     body.len(),
                     true,
                 );
@@ -256,8 +406,8 @@ pub trait HttpDrivenDml: ServerType {
             Err(e @ (InnerDmlError::UserError { .. } | InnerDmlError::InternalError { .. })) => {
                 lp_metrics.record_write(
                     &db_name,
-                    stats.num_lines,
-                    stats.num_fields,
+                    stats.num_samples,
+                    stats.num_samples,
                     body.len(),
                     false,
                 );
@@ -292,6 +442,15 @@ pub trait HttpDrivenDml: ServerType {
         let db_name = org_and_bucket_to_database(&delete_info.org, &delete_info.bucket)
             .context(BucketMappingError)?;
 
+        self.authorizer()
+            .authorize(
+                &AuthToken::from_request(&req),
+                Some(db_name.as_str()),
+                Permission::Write,
+                "http delete",
+            )
+            .context(Unauthorized)?;
+
         // Parse body
         let body = parse_body(req, max_request_size).await.context(ParseBody)?;
         let body = std::str::from_utf8(&body).context(ReadingBodyAsUtf8)?;
@@ -331,6 +490,7 @@ pub trait HttpDrivenDml: ServerType {
     ///
     /// Combines:
     /// - [`route_delete_http_request`](Self::route_delete_http_request)
+    /// - [`route_prometheus_write_http_request`](Self::route_prometheus_write_http_request)
     /// - [`route_write_http_request`](Self::route_write_http_request)
     ///
     /// Returns `RequestOrResponse::Response` if the request was routed,
@@ -339,18 +499,31 @@ pub trait HttpDrivenDml: ServerType {
         &self,
         req: Request<Body>,
     ) -> Result<RequestOrResponse, HttpDmlError> {
-        match self.route_delete_http_request(req).await? {
+        let req = match self.route_delete_http_request(req).await? {
+            RequestOrResponse::Response(resp) => return Ok(RequestOrResponse::Response(resp)),
+            RequestOrResponse::Request(req) => req,
+        };
+
+        match self.route_prometheus_write_http_request(req).await? {
             RequestOrResponse::Response(resp) => Ok(RequestOrResponse::Response(resp)),
             RequestOrResponse::Request(req) => self.route_write_http_request(req).await,
         }
     }
 
     /// Max request size.
+    ///
+    /// For line protocol writes this limits the size of a single line.
     fn max_request_size(&self) -> usize;
 
+    /// Size of the line protocol batches that HTTP writes are split into.
+    fn write_batch_size(&self) -> usize;
+
     /// Line protocol metrics.
     fn lp_metrics(&self) -> Arc<LineProtocolMetrics>;
 
+    /// Authorizer checking that requests may write to the database.
+    fn authorizer(&self) -> &Authorizer;
+
     /// Perform DML operation.
     async fn write(
         &self,
@@ -359,6 +532,47 @@ pub trait HttpDrivenDml: ServerType {
     ) -> Result<(), InnerDmlError>;
 }
 
+/// Writes the lines converted by `converter` to the database `db_name`,
+/// recording them in the line protocol metrics.
+async fn write_lines<T>(
+    server: &T,
+    db_name: &DatabaseName<'_>,
+    converter: &mut LinesConverter,
+    body_size: usize,
+    span_ctx: Option<SpanContext>,
+) -> Result<(), HttpDmlError>
+where
+    T: HttpDrivenDml + ?Sized,
+{
+    let lp_metrics = server.lp_metrics();
+    let (tables, stats) = converter.finish().context(ParsingLineProtocol)?;
+
+    debug!(
+        num_lines=stats.num_lines,
+        num_fields=stats.num_fields,
+        body_size,
+        %db_name,
+        "inserting lines into database",
+    );
+
+    let write = DmlWrite::new(tables, DmlMeta::unsequenced(span_ctx));
+
+    match server.write(db_name, DmlOperation::Write(write)).await {
+        Ok(_) => {
+            lp_metrics.record_write(db_name, stats.num_lines, stats.num_fields, body_size, true);
+            Ok(())
+        }
+        Err(e @ InnerDmlError::DatabaseNotFound { .. }) => {
+            // Purposefully do not record ingest metrics
+            Err(e.into())
+        }
+        Err(e @ (InnerDmlError::UserError { .. } | InnerDmlError::InternalError { .. })) => {
+            lp_metrics.record_write(db_name, stats.num_lines, stats.num_fields, body_size, false);
+            Err(e.into())
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 /// Body of the request to the dml endpoints
 pub struct WriteInfo {
@@ -369,9 +583,12 @@ pub struct WriteInfo {
 #[cfg(test)]
 pub mod test_utils {
     use dml::DmlWrite;
+    use generated_types::prometheus::{Label, Sample, TimeSeries, WriteRequest};
     use http::{header::CONTENT_ENCODING, StatusCode};
     use metric::{Attributes, DurationHistogram, Metric, U64Counter, U64Histogram};
     use mutable_batch_lp::lines_to_batches;
+    use mutable_batch_prom::write_request_to_batches;
+    use prost::Message;
     use reqwest::Client;
 
     use crate::influxdb_ioxd::{
@@ -443,6 +660,75 @@ pub mod test_utils {
         DmlWrite::new(lines_to_batches(lp_data, 0).unwrap(), Default::default())
     }
 
+    /// Assert that Prometheus remote writes work.
+    ///
+    /// The database `bucket_name="MyBucket", org_name="MyOrg"` must exist for this test to work.
+    ///
+    /// Returns write that was generated. The caller MUST check that the write is actually present.
+    pub async fn assert_prometheus_write<T>(test_server: &TestServer<T>) -> DmlWrite
+    where
+        T: ServerType,
+    {
+        let client = Client::new();
+
+        let label = |name: &str, value: &str| Label {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        let request = WriteRequest {
+            timeseries: vec![TimeSeries {
+                labels: vec![
+                    label("__name__", "h2o_temperature"),
+                    label("location", "santa_monica"),
+                    label("state", "CA"),
+                ],
+                samples: vec![
+                    Sample {
+                        value: 65.2,
+                        timestamp: 1617286224000,
+                    },
+                    Sample {
+                        value: 66.1,
+                        timestamp: 1617286225000,
+                    },
+                ],
+                exemplars: vec![],
+            }],
+            metadata: vec![],
+        };
+        let body = snap::raw::Encoder::new()
+            .compress_vec(&request.encode_to_vec())
+            .unwrap();
+
+        // send write data encoded with snappy
+        let bucket_name = "MyBucket";
+        let org_name = "MyOrg";
+        let response = client
+            .post(&format!(
+                "{}/api/v1/prom/write?bucket={}&org={}",
+                test_server.url(),
+                bucket_name,
+                org_name
+            ))
+            .header(CONTENT_ENCODING, "snappy")
+            .body(body)
+            .send()
+            .await;
+
+        check_response(
+            "prometheus_write",
+            response,
+            StatusCode::NO_CONTENT,
+            Some(""),
+        )
+        .await;
+
+        DmlWrite::new(
+            write_request_to_batches(&request).unwrap(),
+            Default::default(),
+        )
+    }
+
     /// Assert that write to an invalid database behave as expected.
     pub async fn assert_write_to_invalid_database<T>(test_server: TestServer<T>)
     where
@@ -651,6 +937,162 @@ pub mod test_utils {
         }
     }
 
+    /// Assert that line protocol writes are split into batches, and that the
+    /// size of a single line is limited.
+    ///
+    /// The database `bucket_name="MyBucket", org_name="MyOrg"` must exist for this test to work,
+    /// and the server must use a write batch size of 1 byte and a max request size of 20 bytes.
+    pub async fn assert_batched_write<T>(test_server: TestServer<T>)
+    where
+        T: ServerType,
+    {
+        let metric_registry = test_server.server_type().metric_registry();
+
+        let client = Client::new();
+
+        let lp_data = "cpu val=1i 1\ncpu val=2i 2\n\ncpu val=3i 3";
+        let post_url = format!(
+            "{}/api/v2/write?bucket=MyBucket&org=MyOrg",
+            test_server.url(),
+        );
+        let response = client.post(&post_url).body(lp_data).send().await;
+        check_response("batched_write", response, StatusCode::NO_CONTENT, Some("")).await;
+
+        // Each line is written separately
+        let ingest_lines_ok = metric_registry
+            .get_instrument::<Metric<U64Counter>>("ingest_lines")
+            .unwrap()
+            .get_observer(&Attributes::from(&[
+                ("db_name", "MyOrg_MyBucket"),
+                ("status", "ok"),
+            ]))
+            .unwrap()
+            .clone();
+        assert_eq!(ingest_lines_ok.fetch(), 3);
+
+        let observation = metric_registry
+            .get_instrument::<Metric<U64Histogram>>("ingest_batch_size_bytes")
+            .unwrap()
+            .get_observer(&Attributes::from(&[
+                ("db_name", "MyOrg_MyBucket"),
+                ("status", "ok"),
+            ]))
+            .unwrap()
+            .fetch();
+        assert_eq!(observation.sample_count(), 3);
+        assert_eq!(observation.total, lp_data.len() as u64);
+
+        // A line exceeding the limit is rejected, but the lines before it
+        // have already been written
+        let lp_data = "cpu val=4i 4\ncpu val=5i,this_line_is=\"too long\" 5";
+        let response = client.post(&post_url).body(lp_data).send().await;
+        check_response(
+            "batched_write_line_too_long",
+            response,
+            StatusCode::BAD_REQUEST,
+            Some("Line protocol line exceeds limit of 20 bytes"),
+        )
+        .await;
+        assert_eq!(ingest_lines_ok.fetch(), 4);
+    }
+
+    /// Auth tokens the server must be configured with for [`assert_dml_authorization`].
+    pub const TEST_AUTH_TOKENS: &str = r#"{
+        "tokens": [
+            { "name": "reader", "token": "r", "permissions": { "MyOrg_MyBucket": ["read"] } },
+            { "name": "writer", "token": "w", "permissions": { "MyOrg_MyBucket": ["write"] } }
+        ]
+    }"#;
+
+    /// Assert that writes and deletes require a token with write permission.
+    ///
+    /// The database `bucket_name="MyBucket", org_name="MyOrg"` must exist for this test to work,
+    /// and the server must be configured with [`TEST_AUTH_TOKENS`].
+    pub async fn assert_dml_authorization<T>(test_server: TestServer<T>)
+    where
+        T: ServerType,
+    {
+        let client = Client::new();
+
+        let write_url = format!(
+            "{}/api/v2/write?bucket=MyBucket&org=MyOrg",
+            test_server.url(),
+        );
+        let lp_data =
+            "h2o_temperature,location=santa_monica surface_degrees=65.2 1617286224000000000";
+
+        let response = client.post(&write_url).body(lp_data).send().await;
+        check_response(
+            "write_without_token",
+            response,
+            StatusCode::UNAUTHORIZED,
+            Some("Missing authorization token"),
+        )
+        .await;
+
+        let response = client
+            .post(&write_url)
+            .bearer_auth("unknown")
+            .body(lp_data)
+            .send()
+            .await;
+        check_response(
+            "write_with_invalid_token",
+            response,
+            StatusCode::UNAUTHORIZED,
+            Some("Invalid authorization token"),
+        )
+        .await;
+
+        let response = client
+            .post(&write_url)
+            .bearer_auth("r")
+            .body(lp_data)
+            .send()
+            .await;
+        check_response(
+            "write_with_read_token",
+            response,
+            StatusCode::FORBIDDEN,
+            Some("Token 'reader' does not grant write permission on database 'MyOrg_MyBucket'"),
+        )
+        .await;
+
+        let response = client
+            .post(&write_url)
+            .header("Authorization", "Token w")
+            .body(lp_data)
+            .send()
+            .await;
+        check_response(
+            "write_with_write_token",
+            response,
+            StatusCode::NO_CONTENT,
+            Some(""),
+        )
+        .await;
+
+        let delete_url = format!(
+            "{}/api/v2/delete?bucket=MyBucket&org=MyOrg",
+            test_server.url(),
+        );
+        let delete_line = r#"{"start":"1","stop":"2", "predicate":"foo=1"}"#;
+
+        let response = client
+            .post(&delete_url)
+            .bearer_auth("r")
+            .body(delete_line)
+            .send()
+            .await;
+        check_response(
+            "delete_with_read_token",
+            response,
+            StatusCode::FORBIDDEN,
+            Some("Token 'reader' does not grant write permission on database 'MyOrg_MyBucket'"),
+        )
+        .await;
+    }
+
     /// Assert that deleting from an unknown database/router returns the expected message and error code.
     pub async fn assert_delete_unknown_database<T>(test_server: TestServer<T>)
     where
@@ -19,6 +19,7 @@ pub enum HttpApiErrorCode {
     MethodNotAllowed,
     RequestTooLarge,
     UnsupportedMediaType,
+    Timeout,
 }
 
 impl HttpApiErrorCode {
@@ -38,6 +39,7 @@ impl HttpApiErrorCode {
             Self::MethodNotAllowed => "method not allowed",
             Self::RequestTooLarge => "request too large",
             Self::UnsupportedMediaType => "unsupported media type",
+            Self::Timeout => "timeout",
         }
     }
 
@@ -57,6 +59,7 @@ impl HttpApiErrorCode {
             Self::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
             Self::RequestTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             Self::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Timeout => StatusCode::GATEWAY_TIMEOUT,
         }
     }
 
@@ -133,6 +136,18 @@ pub trait HttpApiErrorExt {
 
     /// Resource was not found.
     fn not_found(&self) -> HttpApiError;
+
+    /// Request was not authenticated.
+    fn unauthorized(&self) -> HttpApiError;
+
+    /// Request is not permitted.
+    fn forbidden(&self) -> HttpApiError;
+
+    /// Request did not complete within its timeout.
+    fn timeout(&self) -> HttpApiError;
+
+    /// Too many requests are already being handled.
+    fn too_many_requests(&self) -> HttpApiError;
 }
 
 impl<E> HttpApiErrorExt for E
@@ -154,6 +169,22 @@ where
     fn not_found(&self) -> HttpApiError {
         HttpApiError::new(HttpApiErrorCode::NotFound, self.to_string())
     }
+
+    fn unauthorized(&self) -> HttpApiError {
+        HttpApiError::new(HttpApiErrorCode::Unauthorized, self.to_string())
+    }
+
+    fn forbidden(&self) -> HttpApiError {
+        HttpApiError::new(HttpApiErrorCode::Forbidden, self.to_string())
+    }
+
+    fn timeout(&self) -> HttpApiError {
+        HttpApiError::new(HttpApiErrorCode::Timeout, self.to_string())
+    }
+
+    fn too_many_requests(&self) -> HttpApiError {
+        HttpApiError::new(HttpApiErrorCode::TooManyRequests, self.to_string())
+    }
 }
 
 /// An error that can be transformed into a [`HttpApiError`].
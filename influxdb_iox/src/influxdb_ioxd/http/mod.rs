@@ -1,20 +1,23 @@
-use std::{convert::Infallible, num::NonZeroI32, sync::Arc};
+use std::{convert::Infallible, num::NonZeroI32, pin::Pin, sync::Arc};
 
 use hyper::{
     http::HeaderValue,
-    server::conn::{AddrIncoming, AddrStream},
+    server::{accept::Accept, conn::AddrIncoming},
     Body, Method, Request, Response,
 };
 use observability_deps::tracing::{debug, error};
 use serde::Deserialize;
 use snafu::Snafu;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::sync::CancellationToken;
 use tower::Layer;
 use trace_http::{ctx::TraceHeaderParser, tower::TraceLayer};
 
 use crate::influxdb_ioxd::{
+    auth::AuthToken,
     http::error::{HttpApiError, HttpApiErrorExt, HttpApiErrorSource},
     server_type::ServerType,
+    tls::TlsAcceptor,
 };
 
 #[cfg(feature = "heappy")]
@@ -88,21 +91,45 @@ impl HttpApiErrorSource for ApplicationError {
 }
 
 pub async fn serve<M>(
-    addr: AddrIncoming,
+    mut addr: AddrIncoming,
+    tls_acceptor: Option<TlsAcceptor>,
     server_type: Arc<M>,
     shutdown: CancellationToken,
     trace_header_parser: TraceHeaderParser,
 ) -> Result<(), hyper::Error>
 where
     M: ServerType,
+{
+    match tls_acceptor {
+        Some(tls_acceptor) => {
+            let incoming = futures::stream::poll_fn(move |cx| Pin::new(&mut addr).poll_accept(cx));
+            let incoming = hyper::server::accept::from_stream(tls_acceptor.accept(incoming));
+            serve_incoming(incoming, server_type, shutdown, trace_header_parser).await
+        }
+        None => serve_incoming(addr, server_type, shutdown, trace_header_parser).await,
+    }
+}
+
+/// Serves the HTTP API on the connections accepted from `incoming`
+async fn serve_incoming<M, I>(
+    incoming: I,
+    server_type: Arc<M>,
+    shutdown: CancellationToken,
+    trace_header_parser: TraceHeaderParser,
+) -> Result<(), hyper::Error>
+where
+    M: ServerType,
+    I: Accept,
+    I::Conn: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    I::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
 {
     let metric_registry = server_type.metric_registry();
     let trace_collector = server_type.trace_collector();
 
     let trace_layer = TraceLayer::new(trace_header_parser, metric_registry, trace_collector, false);
 
-    hyper::Server::builder(addr)
-        .serve(hyper::service::make_service_fn(|_conn: &AddrStream| {
+    hyper::Server::builder(incoming)
+        .serve(hyper::service::make_service_fn(|_conn: &I::Conn| {
             let server_type = Arc::clone(&server_type);
             let service = hyper::service::service_fn(move |request: Request<_>| {
                 route_request(Arc::clone(&server_type), request)
@@ -122,7 +149,10 @@ async fn route_request<M>(
 where
     M: ServerType,
 {
-    // we don't need the authorization header and we don't want to accidentally log it.
+    // the token is kept in the extensions for authorization, but we don't want to accidentally
+    // log the authorization header.
+    let token = AuthToken::from_headers(req.headers());
+    req.extensions_mut().insert(token);
     req.headers_mut().remove("authorization");
     debug!(request = ?req,"Processing request");
 
@@ -111,6 +111,7 @@ where
         let join_handle = tokio::task::spawn(async {
             serve(
                 addr,
+                None,
                 server_type_captured,
                 CancellationToken::new(),
                 trace_header_parser,
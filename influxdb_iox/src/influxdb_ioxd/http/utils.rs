@@ -6,6 +6,15 @@ use snafu::{ResultExt, Snafu};
 
 use super::error::{HttpApiError, HttpApiErrorExt, HttpApiErrorSource};
 
+/// Content encodings supported by [`parse_body`] and [`stream_body`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ContentEncoding {
+    Identity,
+    Gzip,
+    /// Raw (unframed) snappy, as used by the Prometheus remote write protocol
+    Snappy,
+}
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Snafu)]
 pub enum ParseBodyError {
@@ -24,6 +33,9 @@ pub enum ParseBodyError {
     #[snafu(display("Error decompressing body as gzip: {}", source))]
     ReadingBodyAsGzip { source: std::io::Error },
 
+    #[snafu(display("Error decompressing body as snappy: {}", source))]
+    ReadingBodyAsSnappy { source: snap::Error },
+
     #[snafu(display("Client hung up while sending body: {}", source))]
     ClientHangup { source: hyper::Error },
 }
@@ -35,35 +47,64 @@ impl HttpApiErrorSource for ParseBodyError {
             e @ Self::InvalidContentEncoding { .. } => e.invalid(),
             e @ Self::ReadingHeaderAsUtf8 { .. } => e.invalid(),
             e @ Self::ReadingBodyAsGzip { .. } => e.invalid(),
+            e @ Self::ReadingBodyAsSnappy { .. } => e.invalid(),
             e @ Self::ClientHangup { .. } => e.invalid(),
         }
     }
 }
 
-/// Parse the request's body into raw bytes, applying size limits and
-/// content encoding as needed.
-pub async fn parse_body(
-    req: hyper::Request<Body>,
-    max_size: usize,
-) -> Result<Bytes, ParseBodyError> {
+/// Determine the content encoding of the request's body.
+fn content_encoding(req: &hyper::Request<Body>) -> Result<ContentEncoding, ParseBodyError> {
     // clippy says the const needs to be assigned to a local variable:
     // error: a `const` item with interior mutability should not be borrowed
     let header_name = CONTENT_ENCODING;
-    let ungzip = match req.headers().get(&header_name) {
-        None => false,
+    Ok(match req.headers().get(&header_name) {
+        None => ContentEncoding::Identity,
         Some(content_encoding) => {
             let content_encoding = content_encoding.to_str().context(ReadingHeaderAsUtf8 {
                 header_name: header_name.as_str(),
             })?;
             match content_encoding {
-                "gzip" => true,
+                "gzip" => ContentEncoding::Gzip,
+                "snappy" => ContentEncoding::Snappy,
                 _ => InvalidContentEncoding { content_encoding }.fail()?,
             }
         }
-    };
+    })
+}
 
-    let mut payload = req.into_body();
+/// Parse the request's body into raw bytes, applying size limits and
+/// content encoding as needed.
+pub async fn parse_body(
+    req: hyper::Request<Body>,
+    max_size: usize,
+) -> Result<Bytes, ParseBodyError> {
+    let encoding = content_encoding(&req)?;
+
+    let body = read_body(req.into_body(), max_size).await?;
+
+    // apply any content encoding needed
+    match encoding {
+        ContentEncoding::Identity => Ok(body),
+        ContentEncoding::Gzip => {
+            use std::io::Read;
+            let decoder = flate2::read::GzDecoder::new(&body[..]);
+
+            // Read at most max_size bytes to prevent a decompression bomb based
+            // DoS.
+            let mut decoder = decoder.take(max_size as u64);
+            let mut decoded_data = Vec::new();
+            decoder
+                .read_to_end(&mut decoded_data)
+                .context(ReadingBodyAsGzip)?;
+            Ok(decoded_data.into())
+        }
+        ContentEncoding::Snappy => decode_snappy(&body, max_size),
+    }
+}
 
+/// Read the whole of `payload` into memory, failing if it exceeds `max_size`.
+async fn read_body(mut payload: Body, max_size: usize) -> Result<Bytes, ParseBodyError> {
     let mut body = BytesMut::new();
     while let Some(chunk) = payload.next().await {
         let chunk = chunk.context(ClientHangup)?;
@@ -75,23 +116,165 @@ pub async fn parse_body(
         }
         body.extend_from_slice(&chunk);
     }
-    let body = body.freeze();
+    Ok(body.freeze())
+}
+
+fn decode_snappy(body: &[u8], max_size: usize) -> Result<Bytes, ParseBodyError> {
+    // The raw snappy format stores the decompressed length up front,
+    // so check it before allocating to prevent a decompression bomb
+    // based DoS.
+    let decoded_len = snap::raw::decompress_len(body).context(ReadingBodyAsSnappy)?;
+    if decoded_len > max_size {
+        return Err(ParseBodyError::RequestSizeExceeded {
+            max_body_size: max_size,
+        });
+    }
 
-    // apply any content encoding needed
-    if ungzip {
-        use std::io::Read;
-        let decoder = flate2::read::GzDecoder::new(&body[..]);
-
-        // Read at most max_size bytes to prevent a decompression bomb based
-        // DoS.
-        let mut decoder = decoder.take(max_size as u64);
-        let mut decoded_data = Vec::new();
-        decoder
-            .read_to_end(&mut decoded_data)
-            .context(ReadingBodyAsGzip)?;
-        Ok(decoded_data.into())
-    } else {
-        Ok(body)
+    let decoded_data = snap::raw::Decoder::new()
+        .decompress_vec(body)
+        .context(ReadingBodyAsSnappy)?;
+    Ok(decoded_data.into())
+}
+
+/// A request's body decoded chunk by chunk as it is received, see
+/// [`stream_body`].
+#[derive(Debug)]
+pub struct BodyStream {
+    payload: Option<Body>,
+    decoder: StreamDecoder,
+    max_size: usize,
+}
+
+#[derive(Debug)]
+enum StreamDecoder {
+    Identity,
+    Gzip(flate2::write::GzDecoder<LimitedWriter>),
+    /// Raw snappy cannot be decoded incrementally, so the whole body is
+    /// buffered and decoded at once
+    Snappy,
+}
+
+/// Returns a [`BodyStream`] decoding the request's body as it is received,
+/// applying content encoding as needed.
+///
+/// Unlike [`parse_body`], this does not hold the whole body in memory, and
+/// it is up to the caller to bound the size of any data it retains. The
+/// exception is a snappy encoded body, which is buffered in its entirety and
+/// so still limited to `max_size` bytes. A gzip encoded body is limited to
+/// `max_size` bytes of decompressed data per received chunk.
+pub fn stream_body(
+    req: hyper::Request<Body>,
+    max_size: usize,
+) -> Result<BodyStream, ParseBodyError> {
+    let decoder = match content_encoding(&req)? {
+        ContentEncoding::Identity => StreamDecoder::Identity,
+        ContentEncoding::Gzip => {
+            StreamDecoder::Gzip(flate2::write::GzDecoder::new(LimitedWriter::new(max_size)))
+        }
+        ContentEncoding::Snappy => StreamDecoder::Snappy,
+    };
+
+    Ok(BodyStream {
+        payload: Some(req.into_body()),
+        decoder,
+        max_size,
+    })
+}
+
+impl BodyStream {
+    /// Returns the next chunk of decoded data, or `None` once the whole
+    /// body has been received.
+    pub async fn next_chunk(&mut self) -> Result<Option<Bytes>, ParseBodyError> {
+        use std::io::Write;
+
+        let max_size = self.max_size;
+        loop {
+            let payload = match &mut self.payload {
+                Some(payload) => payload,
+                None => return Ok(None),
+            };
+
+            match &mut self.decoder {
+                StreamDecoder::Identity => {
+                    let chunk = payload.next().await.transpose().context(ClientHangup)?;
+                    if chunk.is_none() {
+                        self.payload = None;
+                    }
+                    return Ok(chunk);
+                }
+                StreamDecoder::Gzip(decoder) => {
+                    let result = match payload.next().await.transpose().context(ClientHangup)? {
+                        Some(chunk) => decoder.write_all(&chunk),
+                        None => {
+                            self.payload = None;
+                            decoder.try_finish()
+                        }
+                    };
+
+                    // The data decompressed from each chunk is limited as it
+                    // is inflated to prevent a decompression bomb based DoS.
+                    if decoder.get_ref().exceeded {
+                        return Err(ParseBodyError::RequestSizeExceeded {
+                            max_body_size: max_size,
+                        });
+                    }
+                    result.context(ReadingBodyAsGzip)?;
+
+                    let decoded = decoder.get_mut().take();
+                    if !decoded.is_empty() {
+                        return Ok(Some(decoded.into()));
+                    }
+                }
+                StreamDecoder::Snappy => {
+                    let payload = self.payload.take().expect("checked above");
+                    let body = read_body(payload, max_size).await?;
+                    return decode_snappy(&body, max_size).map(Some);
+                }
+            }
+        }
+    }
+}
+
+/// Collects decompressed data, failing once more than `limit` bytes are
+/// written to it between calls to [`LimitedWriter::take`]
+#[derive(Debug)]
+struct LimitedWriter {
+    buf: Vec<u8>,
+    limit: usize,
+    exceeded: bool,
+}
+
+impl LimitedWriter {
+    fn new(limit: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            limit,
+            exceeded: false,
+        }
+    }
+
+    /// Returns the data written so far, allowing up to `limit` more bytes to
+    /// be written
+    fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl std::io::Write for LimitedWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        if self.buf.len() + data.len() > self.limit {
+            self.exceeded = true;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "decompressed chunk exceeds limit",
+            ));
+        }
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
     }
 }
 
@@ -131,4 +314,108 @@ mod tests {
             "Client hung up while sending body: error reading a body from connection: Blarg Error"
         );
     }
+
+    #[tokio::test]
+    async fn parse_snappy_body() {
+        let data = "cpu,host=a val=1i 1".repeat(10);
+        let compressed = snap::raw::Encoder::new()
+            .compress_vec(data.as_bytes())
+            .unwrap();
+
+        let request = Request::builder()
+            .uri("https://ye-olde-non-existent-server/")
+            .header(CONTENT_ENCODING, "snappy")
+            .body(Body::from(compressed.clone()))
+            .unwrap();
+        let body = parse_body(request, TEST_MAX_REQUEST_SIZE).await.unwrap();
+        assert_eq!(body, data.as_bytes());
+
+        // decompressed length exceeds the limit
+        let request = Request::builder()
+            .uri("https://ye-olde-non-existent-server/")
+            .header(CONTENT_ENCODING, "snappy")
+            .body(Body::from(compressed))
+            .unwrap();
+        let err = parse_body(request, data.len() - 1).await.unwrap_err();
+        assert!(matches!(err, ParseBodyError::RequestSizeExceeded { .. }));
+
+        let request = Request::builder()
+            .uri("https://ye-olde-non-existent-server/")
+            .header(CONTENT_ENCODING, "snappy")
+            .body(Body::from("not snappy"))
+            .unwrap();
+        let err = parse_body(request, TEST_MAX_REQUEST_SIZE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ParseBodyError::ReadingBodyAsSnappy { .. }));
+    }
+
+    /// Returns a request with a body sent in the given chunks
+    fn chunked_request(chunks: Vec<Vec<u8>>, content_encoding: Option<&str>) -> Request<Body> {
+        let chunks: Vec<Result<_, std::io::Error>> = chunks.into_iter().map(Ok).collect();
+        let body = Body::wrap_stream(futures::stream::iter(chunks));
+
+        let mut request = Request::builder().uri("https://ye-olde-non-existent-server/");
+        if let Some(content_encoding) = content_encoding {
+            request = request.header(CONTENT_ENCODING, content_encoding);
+        }
+        request.body(body).unwrap()
+    }
+
+    async fn collect_stream(mut stream: BodyStream) -> Result<Vec<Bytes>, ParseBodyError> {
+        let mut chunks = vec![];
+        while let Some(chunk) = stream.next_chunk().await? {
+            chunks.push(chunk);
+        }
+        Ok(chunks)
+    }
+
+    #[tokio::test]
+    async fn stream_identity_body() {
+        let request = chunked_request(vec![b"foo".to_vec(), b"bar".to_vec()], None);
+        let stream = stream_body(request, 1).unwrap();
+
+        // the limit does not apply to uncompressed data
+        let chunks = collect_stream(stream).await.unwrap();
+        assert_eq!(chunks, vec![Bytes::from("foo"), Bytes::from("bar")]);
+    }
+
+    #[tokio::test]
+    async fn stream_gzip_body() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let data = "cpu,host=a val=1i 1\n".repeat(100);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let chunks = compressed
+            .chunks(10)
+            .map(|c| c.to_vec())
+            .collect::<Vec<_>>();
+        let request = chunked_request(chunks.clone(), Some("gzip"));
+        let stream = stream_body(request, TEST_MAX_REQUEST_SIZE).unwrap();
+        let decoded = collect_stream(stream).await.unwrap().concat();
+        assert_eq!(decoded, data.as_bytes());
+
+        // decompressed chunk exceeds the limit
+        let request = chunked_request(vec![compressed.clone()], Some("gzip"));
+        let stream = stream_body(request, data.len() - 1).unwrap();
+        let err = collect_stream(stream).await.unwrap_err();
+        assert!(matches!(err, ParseBodyError::RequestSizeExceeded { .. }));
+
+        // every chunk decompresses to less than the limit, so the body as a
+        // whole may exceed it
+        let request = chunked_request(chunks.clone(), Some("gzip"));
+        let stream = stream_body(request, data.len() - 1).unwrap();
+        let decoded = collect_stream(stream).await.unwrap().concat();
+        assert_eq!(decoded, data.as_bytes());
+
+        // truncated body
+        let request = chunked_request(chunks[..chunks.len() - 1].to_vec(), Some("gzip"));
+        let stream = stream_body(request, TEST_MAX_REQUEST_SIZE).unwrap();
+        let err = collect_stream(stream).await.unwrap_err();
+        assert!(matches!(err, ParseBodyError::ReadingBodyAsGzip { .. }));
+    }
 }
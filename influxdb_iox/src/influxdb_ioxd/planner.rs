@@ -7,7 +7,10 @@ use query::{
     exec::IOxExecutionContext,
     frontend::{influxrpc::InfluxRpcPlanner, sql::SqlQueryPlanner},
     group_by::{Aggregate, WindowDuration},
-    plan::{fieldlist::FieldListPlan, seriesset::SeriesSetPlans, stringset::StringSetPlan},
+    plan::{
+        fieldlist::FieldListPlan, seriescardinality::SeriesCardinalityPlan,
+        seriesset::SeriesSetPlans, stringset::StringSetPlan,
+    },
     QueryDatabase,
 };
 
@@ -126,6 +129,27 @@ impl Planner {
             .await
     }
 
+    /// Creates a plan as described on
+    /// [`InfluxRpcPlanner::series_cardinality`], on a separate threadpool
+    pub async fn series_cardinality<D>(
+        &self,
+        database: Arc<D>,
+        predicate: Predicate,
+    ) -> Result<SeriesCardinalityPlan>
+    where
+        D: QueryDatabase + 'static,
+    {
+        let planner = InfluxRpcPlanner::new();
+
+        self.ctx
+            .run(async move {
+                planner
+                    .series_cardinality(database.as_ref(), predicate)
+                    .map_err(|e| Error::Plan(format!("series_cardinality error: {}", e)))
+            })
+            .await
+    }
+
     /// Creates a plan as described on
     /// [`InfluxRpcPlanner::read_filter`], on a separate threadpool
     pub async fn read_filter<D>(
@@ -179,6 +203,7 @@ impl Planner {
         agg: Aggregate,
         every: WindowDuration,
         offset: WindowDuration,
+        create_empty: bool,
     ) -> Result<SeriesSetPlans>
     where
         D: QueryDatabase + 'static,
@@ -188,7 +213,14 @@ impl Planner {
         self.ctx
             .run(async move {
                 planner
-                    .read_window_aggregate(database.as_ref(), predicate, agg, every, offset)
+                    .read_window_aggregate(
+                        database.as_ref(),
+                        predicate,
+                        agg,
+                        every,
+                        offset,
+                        create_empty,
+                    )
                     .map_err(|e| Error::Plan(format!("read_window_aggregate error: {}", e)))
             })
             .await
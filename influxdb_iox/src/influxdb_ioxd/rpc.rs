@@ -9,8 +9,11 @@ use trace_http::ctx::TraceHeaderParser;
 use crate::influxdb_ioxd::{
     server_type::{RpcError, ServerType},
     serving_readiness::ServingReadiness,
+    tls::TlsAcceptor,
 };
 
+pub(crate) mod deadline;
+pub(crate) mod priority;
 pub(crate) mod testing;
 
 /// Returns the name of the gRPC service S.
@@ -21,6 +24,7 @@ pub fn service_name<S: NamedService>(_: &S) -> &'static str {
 #[derive(Debug)]
 pub struct RpcBuilderInput {
     pub socket: TcpListener,
+    pub tls_acceptor: Option<TlsAcceptor>,
     pub trace_header_parser: TraceHeaderParser,
     pub shutdown: CancellationToken,
     pub serving_readiness: ServingReadiness,
@@ -32,6 +36,7 @@ pub struct RpcBuilder<T> {
     pub health_reporter: HealthReporter,
     pub shutdown: CancellationToken,
     pub socket: TcpListener,
+    pub tls_acceptor: Option<TlsAcceptor>,
     pub serving_readiness: ServingReadiness,
 }
 
@@ -52,6 +57,7 @@ macro_rules! add_service {
                     mut health_reporter,
                     shutdown,
                     socket,
+                    tls_acceptor,
                     serving_readiness,
                 } = $builder;
                 let service = $svc;
@@ -68,6 +74,7 @@ macro_rules! add_service {
                     health_reporter,
                     shutdown,
                     socket,
+                    tls_acceptor,
                     serving_readiness,
                 }
             }
@@ -109,6 +116,7 @@ macro_rules! setup_builder {
 
         let RpcBuilderInput {
             socket,
+            tls_acceptor,
             trace_header_parser,
             shutdown,
             serving_readiness,
@@ -133,6 +141,7 @@ macro_rules! setup_builder {
             health_reporter,
             shutdown,
             socket,
+            tls_acceptor,
             serving_readiness,
         };
 
@@ -157,13 +166,23 @@ macro_rules! serve_builder {
             inner,
             shutdown,
             socket,
+            tls_acceptor,
             ..
         } = $builder;
 
         let stream = TcpListenerStream::new(socket);
-        inner
-            .serve_with_incoming_shutdown(stream, shutdown.cancelled())
-            .await?;
+        match tls_acceptor {
+            Some(tls_acceptor) => {
+                inner
+                    .serve_with_incoming_shutdown(tls_acceptor.accept(stream), shutdown.cancelled())
+                    .await?
+            }
+            None => {
+                inner
+                    .serve_with_incoming_shutdown(stream, shutdown.cancelled())
+                    .await?
+            }
+        }
     }};
 }
 
@@ -175,6 +194,7 @@ pub(crate) use serve_builder;
 /// shutdown.
 pub async fn serve<T>(
     socket: TcpListener,
+    tls_acceptor: Option<TlsAcceptor>,
     server_type: Arc<T>,
     trace_header_parser: TraceHeaderParser,
     shutdown: CancellationToken,
@@ -185,6 +205,7 @@ where
 {
     let builder_input = RpcBuilderInput {
         socket,
+        tls_acceptor,
         trace_header_parser,
         shutdown,
         serving_readiness,
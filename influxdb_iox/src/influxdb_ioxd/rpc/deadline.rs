@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use tonic::metadata::MetadataMap;
+
+/// The header clients send the time remaining until their deadline in
+const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Returns the timeout of a gRPC request, derived from the deadline of the
+/// client, if it set one
+///
+/// See <https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md>
+pub fn grpc_timeout(metadata: &MetadataMap) -> Option<Duration> {
+    let value = metadata.get(GRPC_TIMEOUT_HEADER)?.to_str().ok()?;
+
+    // At most 8 digits followed by a unit
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    let amount: u64 = digits.parse().ok()?;
+
+    match unit {
+        "H" => Some(Duration::from_secs(amount * 60 * 60)),
+        "M" => Some(Duration::from_secs(amount * 60)),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeout(value: &str) -> Option<Duration> {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(GRPC_TIMEOUT_HEADER, value.parse().unwrap());
+        grpc_timeout(&metadata)
+    }
+
+    #[test]
+    fn test_grpc_timeout() {
+        assert_eq!(grpc_timeout(&MetadataMap::new()), None);
+
+        assert_eq!(timeout("2H"), Some(Duration::from_secs(7200)));
+        assert_eq!(timeout("5M"), Some(Duration::from_secs(300)));
+        assert_eq!(timeout("30S"), Some(Duration::from_secs(30)));
+        assert_eq!(timeout("1500m"), Some(Duration::from_millis(1500)));
+        assert_eq!(timeout("100u"), Some(Duration::from_micros(100)));
+        assert_eq!(timeout("99999999n"), Some(Duration::from_nanos(99999999)));
+
+        assert_eq!(timeout("S"), None);
+        assert_eq!(timeout("10"), None);
+        assert_eq!(timeout("10s"), None);
+        assert_eq!(timeout("123456789S"), None);
+        assert_eq!(timeout("-1S"), None);
+    }
+}
@@ -0,0 +1,62 @@
+use query::exec::admission::QueryPriority;
+use tonic::{metadata::MetadataMap, Status};
+
+/// The header clients send the priority of their queries in
+const QUERY_PRIORITY_HEADER: &str = "iox-query-priority";
+
+/// Returns the priority a gRPC request asks its queries to be admitted with,
+/// defaulting to [`QueryPriority::Interactive`]
+///
+/// Clients may ask for interactive or batch queries. The priority of system
+/// queries is reserved for queries of the system tables.
+pub fn query_priority(metadata: &MetadataMap) -> Result<QueryPriority, Status> {
+    let value = match metadata.get(QUERY_PRIORITY_HEADER) {
+        Some(value) => value.to_str().map_err(|_| {
+            Status::invalid_argument(format!(
+                "'{}' header is not valid ASCII",
+                QUERY_PRIORITY_HEADER
+            ))
+        })?,
+        None => return Ok(QueryPriority::default()),
+    };
+
+    match value.parse() {
+        Ok(QueryPriority::System) => Err(Status::invalid_argument(format!(
+            "Query priority '{}' can not be requested",
+            value
+        ))),
+        Ok(priority) => Ok(priority),
+        Err(e) => Err(Status::invalid_argument(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn priority(value: &str) -> Result<QueryPriority, Status> {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(QUERY_PRIORITY_HEADER, value.parse().unwrap());
+        query_priority(&metadata)
+    }
+
+    #[test]
+    fn test_query_priority() {
+        assert_eq!(
+            query_priority(&MetadataMap::new()).unwrap(),
+            QueryPriority::Interactive
+        );
+
+        assert_eq!(priority("interactive").unwrap(), QueryPriority::Interactive);
+        assert_eq!(priority("batch").unwrap(), QueryPriority::Batch);
+
+        assert_eq!(
+            priority("system").unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+        assert_eq!(
+            priority("urgent").unwrap_err().message(),
+            "Unknown query priority 'urgent', expected interactive, batch or system"
+        );
+    }
+}
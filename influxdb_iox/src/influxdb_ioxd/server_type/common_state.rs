@@ -4,13 +4,24 @@ use snafu::{ResultExt, Snafu};
 use trace::TraceCollector;
 
 use crate::{
-    influxdb_ioxd::serving_readiness::ServingReadiness, structopt_blocks::run_config::RunConfig,
+    influxdb_ioxd::{auth::Authorizer, serving_readiness::ServingReadiness, tls::TlsAcceptor},
+    structopt_blocks::run_config::RunConfig,
 };
 
 #[derive(Debug, Snafu)]
 pub enum CommonServerStateError {
     #[snafu(display("Cannot create tracing pipeline: {}", source))]
     Tracing { source: trace_exporters::Error },
+
+    #[snafu(display("Cannot load auth tokens: {}", source))]
+    Auth {
+        source: crate::influxdb_ioxd::auth::Error,
+    },
+
+    #[snafu(display("Cannot load TLS certificates: {}", source))]
+    Tls {
+        source: crate::influxdb_ioxd::tls::Error,
+    },
 }
 
 /// Common state used by all server types (e.g. `Database` and `Router`)
@@ -19,17 +30,27 @@ pub struct CommonServerState {
     run_config: RunConfig,
     serving_readiness: ServingReadiness,
     trace_exporter: Option<Arc<trace_exporters::export::AsyncExporter>>,
+    authorizer: Arc<Authorizer>,
+    tls_acceptor: Option<TlsAcceptor>,
 }
 
 impl CommonServerState {
-    pub fn from_config(run_config: RunConfig) -> Result<Self, CommonServerStateError> {
+    pub async fn from_config(run_config: RunConfig) -> Result<Self, CommonServerStateError> {
+        let authorizer = Authorizer::from_config(&run_config).await.context(Auth)?;
+        Self::new(run_config, authorizer)
+    }
+
+    fn new(run_config: RunConfig, authorizer: Authorizer) -> Result<Self, CommonServerStateError> {
         let serving_readiness = run_config.initial_serving_state.clone().into();
         let trace_exporter = run_config.tracing_config.build().context(Tracing)?;
+        let tls_acceptor = TlsAcceptor::from_config(&run_config.tls_config).context(Tls)?;
 
         Ok(Self {
             run_config,
             serving_readiness,
             trace_exporter,
+            authorizer: Arc::new(authorizer),
+            tls_acceptor,
         })
     }
 
@@ -37,9 +58,10 @@ impl CommonServerState {
     pub fn for_testing() -> Self {
         use structopt::StructOpt;
 
-        Self::from_config(
+        Self::new(
             RunConfig::from_iter_safe(["not_used".to_string()].into_iter())
                 .expect("default parsing should work"),
+            Authorizer::disabled(),
         )
         .expect("default configs should work")
     }
@@ -52,6 +74,14 @@ impl CommonServerState {
         &self.serving_readiness
     }
 
+    pub fn authorizer(&self) -> Arc<Authorizer> {
+        Arc::clone(&self.authorizer)
+    }
+
+    pub fn tls_acceptor(&self) -> Option<&TlsAcceptor> {
+        self.tls_acceptor.as_ref()
+    }
+
     pub fn trace_exporter(&self) -> Option<Arc<trace_exporters::export::AsyncExporter>> {
         self.trace_exporter.clone()
     }
@@ -11,9 +11,19 @@
 //! database names and may remove this quasi /v2 API.
 
 // Influx crates
-use data_types::{names::OrgBucketMappingError, DatabaseName};
+use data_types::{
+    names::{org_and_bucket_to_database, OrgBucketMappingError},
+    DatabaseName,
+};
 use influxdb_iox_client::format::QueryOutputFormat;
-use query::{exec::ExecutionContextProvider, QueryDatabase};
+use query::{
+    exec::{
+        admission::{is_admission_rejected, QueryPriority, UnknownPriority},
+        deadline::is_query_timeout,
+        ExecutionContextProvider,
+    },
+    QueryDatabase,
+};
 use server::Error;
 
 // External crates
@@ -25,10 +35,12 @@ use serde::Deserialize;
 use snafu::{OptionExt, ResultExt, Snafu};
 
 use crate::influxdb_ioxd::{
+    auth::{AuthError, AuthToken, Authorizer, Permission},
     http::{
-        dml::{HttpDrivenDml, InnerDmlError, RequestOrResponse},
+        dml::{HttpDrivenDml, InnerDmlError, RequestOrResponse, WriteInfo},
         error::{HttpApiError, HttpApiErrorExt, HttpApiErrorSource},
         metrics::LineProtocolMetrics,
+        utils::parse_body,
     },
     planner::Planner,
 };
@@ -40,6 +52,10 @@ use std::{
 };
 
 use super::DatabaseServerType;
+use stream::QueryResponseStream;
+
+mod prometheus;
+mod stream;
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Snafu)]
@@ -64,6 +80,18 @@ pub enum ApplicationError {
         source: serde_urlencoded::de::Error,
     },
 
+    #[snafu(display("Invalid query timeout '{}': {}", timeout, source))]
+    InvalidTimeout {
+        timeout: String,
+        source: humantime::DurationError,
+    },
+
+    #[snafu(display("Invalid query priority: {}", source))]
+    InvalidPriority { source: UnknownPriority },
+
+    #[snafu(display("Query priority '{}' can not be requested", priority))]
+    ReservedPriority { priority: QueryPriority },
+
     #[snafu(display("No handler for {:?} {}", method, path))]
     RouteNotFound { method: Method, path: String },
 
@@ -84,18 +112,6 @@ pub enum ApplicationError {
         source: influxdb_iox_client::format::Error,
     },
 
-    #[snafu(display(
-        "Error formatting results of SQL query '{}' using '{:?}': {}",
-        q,
-        format,
-        source
-    ))]
-    FormattingResult {
-        q: String,
-        format: QueryOutputFormat,
-        source: influxdb_iox_client::format::Error,
-    },
-
     #[snafu(display("Error while planning query: {}", source))]
     Planning {
         source: crate::influxdb_ioxd::planner::Error,
@@ -117,6 +133,17 @@ pub enum ApplicationError {
     DmlError {
         source: crate::influxdb_ioxd::http::dml::HttpDmlError,
     },
+
+    #[snafu(display("Cannot parse body: {}", source))]
+    ParseBody {
+        source: crate::influxdb_ioxd::http::utils::ParseBodyError,
+    },
+
+    #[snafu(display("Cannot perform Prometheus remote read: {}", source))]
+    PrometheusRead { source: prometheus::Error },
+
+    #[snafu(display("Access denied: {}", source))]
+    Unauthorized { source: AuthError },
 }
 
 type Result<T, E = ApplicationError> = std::result::Result<T, E>;
@@ -125,21 +152,29 @@ impl HttpApiErrorSource for ApplicationError {
     fn to_http_api_error(&self) -> HttpApiError {
         match self {
             e @ Self::BucketMappingError { .. } => e.internal_error(),
+            e @ Self::Query { .. } if is_query_timeout(e) => e.timeout(),
+            e @ Self::Query { .. } if is_admission_rejected(e) => e.too_many_requests(),
             e @ Self::Query { .. } => e.internal_error(),
             e @ Self::ExpectedQueryString { .. } => e.invalid(),
             e @ Self::InvalidQueryString { .. } => e.invalid(),
+            e @ Self::InvalidTimeout { .. } => e.invalid(),
+            e @ Self::InvalidPriority { .. } => e.invalid(),
+            e @ Self::ReservedPriority { .. } => e.invalid(),
             e @ Self::RouteNotFound { .. } => e.not_found(),
             e @ Self::DatabaseNameError { .. } => e.invalid(),
             e @ Self::DatabaseNotFound { .. } => e.not_found(),
             e @ Self::CreatingResponse { .. } => e.internal_error(),
-            e @ Self::FormattingResult { .. } => e.internal_error(),
             e @ Self::ParsingFormat { .. } => e.invalid(),
+            e @ Self::Planning { .. } if is_query_timeout(e) => e.timeout(),
             e @ Self::Planning { .. } => e.invalid(),
             e @ Self::ServerIdNotSet => e.invalid(),
             e @ Self::ServerNotInitialized => e.invalid(),
             e @ Self::DatabaseNotInitialized { .. } => e.invalid(),
             e @ Self::InternalServerError => e.internal_error(),
             Self::DmlError { source } => source.to_http_api_error(),
+            Self::ParseBody { source } => source.to_http_api_error(),
+            Self::PrometheusRead { source } => source.to_http_api_error(),
+            Self::Unauthorized { source } => source.to_http_api_error(),
         }
     }
 }
@@ -167,10 +202,18 @@ impl HttpDrivenDml for DatabaseServerType {
         self.max_request_size
     }
 
+    fn write_batch_size(&self) -> usize {
+        self.write_batch_size
+    }
+
     fn lp_metrics(&self) -> Arc<LineProtocolMetrics> {
         Arc::clone(&self.lp_metrics)
     }
 
+    fn authorizer(&self) -> &Authorizer {
+        &self.authorizer
+    }
+
     async fn write(
         &self,
         db_name: &DatabaseName<'_>,
@@ -207,6 +250,7 @@ pub async fn route_request(
 
             match (method.clone(), uri.path()) {
                 (Method::GET, "/api/v3/query") => query(req, server_type).await,
+                (Method::POST, "/api/v1/prom/read") => prometheus_read(req, server_type).await,
 
                 (method, path) => Err(ApplicationError::RouteNotFound {
                     method,
@@ -226,6 +270,11 @@ struct QueryParams {
     q: String,
     #[serde(default = "default_format")]
     format: String,
+    /// Shortens the server's default query timeout, e.g. `30s`
+    timeout: Option<String>,
+    /// The priority the query is admitted with, `interactive` (the default)
+    /// or `batch`
+    priority: Option<String>,
 }
 
 fn default_format() -> String {
@@ -240,36 +289,58 @@ async fn query(
 
     let uri_query = req.uri().query().context(ExpectedQueryString {})?;
 
-    let QueryParams { d, q, format } =
-        serde_urlencoded::from_str(uri_query).context(InvalidQueryString {
-            query_string: uri_query,
-        })?;
+    let QueryParams {
+        d,
+        q,
+        format,
+        timeout,
+        priority,
+    } = serde_urlencoded::from_str(uri_query).context(InvalidQueryString {
+        query_string: uri_query,
+    })?;
 
     let format = QueryOutputFormat::from_str(&format).context(ParsingFormat { format })?;
+    let timeout = timeout
+        .map(|timeout| humantime::parse_duration(&timeout).context(InvalidTimeout { timeout }))
+        .transpose()?;
+    let priority = match priority {
+        Some(priority) => priority.parse().context(InvalidPriority)?,
+        None => QueryPriority::default(),
+    };
+    if priority == QueryPriority::System {
+        return ReservedPriority { priority }.fail();
+    }
 
     let db_name = DatabaseName::new(&d).context(DatabaseNameError)?;
-    debug!(uri = ?req.uri(), %q, ?format, %db_name, "running SQL query");
+    server_type
+        .authorizer
+        .authorize(
+            &AuthToken::from_request(&req),
+            Some(db_name.as_str()),
+            Permission::Read,
+            "http query",
+        )
+        .context(Unauthorized)?;
+    debug!(uri = ?req.uri(), %q, ?format, ?timeout, %priority, %db_name, "running SQL query");
 
     let db = server.db(&db_name)?;
 
-    db.record_query("sql", &q);
+    let ctx = db
+        .new_query_context(req.extensions().get().cloned())
+        .with_request_timeout(timeout)
+        .with_priority(priority);
+    db.record_query(&ctx, "sql", &q);
 
-    let ctx = db.new_query_context(req.extensions().get().cloned());
     let physical_plan = Planner::new(&ctx).sql(&q).await.context(Planning)?;
 
-    // TODO: stream read results out rather than rendering the
-    // whole thing in mem
-    let batches = ctx
-        .collect(physical_plan)
+    let stream = ctx
+        .execute_stream(physical_plan)
         .await
         .map_err(|e| Box::new(e) as _)
         .context(Query { db_name })?;
 
-    let results = format
-        .format(&batches)
-        .context(FormattingResult { q, format })?;
-
-    let body = Body::from(results.into_bytes());
+    // Results are sent with chunked transfer encoding as they are produced
+    let body = Body::wrap_stream(QueryResponseStream::new(stream, format));
 
     let response = Response::builder()
         .header(CONTENT_TYPE, format.content_type())
@@ -279,14 +350,55 @@ async fn query(
     Ok(response)
 }
 
+/// Serves a Prometheus remote read request for the database mapped from the
+/// `org` and `bucket` query parameters
+async fn prometheus_read(
+    req: Request<Body>,
+    server_type: &DatabaseServerType,
+) -> Result<Response<Body>, ApplicationError> {
+    let server = &server_type.server;
+
+    let uri_query = req.uri().query().context(ExpectedQueryString {})?;
+
+    let read_info: WriteInfo =
+        serde_urlencoded::from_str(uri_query).context(InvalidQueryString {
+            query_string: uri_query,
+        })?;
+
+    let db_name = org_and_bucket_to_database(&read_info.org, &read_info.bucket)
+        .context(BucketMappingError)?;
+
+    server_type
+        .authorizer
+        .authorize(
+            &AuthToken::from_request(&req),
+            Some(db_name.as_str()),
+            Permission::Read,
+            "prometheus read",
+        )
+        .context(Unauthorized)?;
+
+    let db = server.db(&db_name)?;
+
+    let span_ctx = req.extensions().get().cloned();
+    let body = parse_body(req, server_type.max_request_size)
+        .await
+        .context(ParseBody)?;
+
+    prometheus::read(db, db_name.as_str(), body, span_ctx)
+        .await
+        .context(PrometheusRead)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::influxdb_ioxd::{
         http::{
             dml::test_utils::{
-                assert_delete_bad_request, assert_delete_unknown_database,
-                assert_delete_unknown_table, assert_gzip_write, assert_write, assert_write_metrics,
-                assert_write_to_invalid_database,
+                assert_batched_write, assert_delete_bad_request, assert_delete_unknown_database,
+                assert_delete_unknown_table, assert_dml_authorization, assert_gzip_write,
+                assert_prometheus_write, assert_write, assert_write_metrics,
+                assert_write_to_invalid_database, TEST_AUTH_TOKENS,
             },
             test_utils::{
                 assert_health, assert_metrics, assert_tracing, check_response, get_content_type,
@@ -315,6 +427,10 @@ mod tests {
         Arc::new(ApplicationState::new(
             Arc::new(ObjectStore::new_in_memory()),
             None,
+            None,
+            Default::default(),
+            Default::default(),
+            Default::default(),
             Some(Arc::new(RingBufferTraceCollector::new(5))),
         ))
     }
@@ -370,6 +486,81 @@ mod tests {
         assert_write_metrics(setup_server().await, true).await;
     }
 
+    #[tokio::test]
+    async fn test_batched_write() {
+        let application = make_application();
+
+        let app_server = make_server(Arc::clone(&application));
+        app_server.set_id(ServerId::try_from(1).unwrap()).unwrap();
+        app_server.wait_for_init().await.unwrap();
+        app_server
+            .create_database(make_rules("MyOrg_MyBucket"))
+            .await
+            .unwrap();
+
+        let mut server_type =
+            DatabaseServerType::new(application, app_server, &CommonServerState::for_testing());
+        server_type.write_batch_size = 1;
+        server_type.max_request_size = 20;
+
+        assert_batched_write(TestServer::new(Arc::new(server_type))).await;
+    }
+
+    #[tokio::test]
+    async fn test_dml_authorization() {
+        assert_dml_authorization(setup_server_with_auth().await).await;
+    }
+
+    #[tokio::test]
+    async fn test_query_authorization() {
+        let test_server = setup_server_with_auth().await;
+        let client = Client::new();
+
+        let url = format!(
+            "{}/api/v3/query?d=MyOrg_MyBucket&q={}",
+            test_server.url(),
+            "select%20*%20from%20h2o_temperature"
+        );
+
+        let response = client.get(&url).send().await;
+        check_response(
+            "query_without_token",
+            response,
+            StatusCode::UNAUTHORIZED,
+            Some("Missing authorization token"),
+        )
+        .await;
+
+        let response = client.get(&url).bearer_auth("w").send().await;
+        check_response(
+            "query_with_write_token",
+            response,
+            StatusCode::FORBIDDEN,
+            Some("Token 'writer' does not grant read permission on database 'MyOrg_MyBucket'"),
+        )
+        .await;
+
+        let response = client
+            .post(&format!(
+                "{}/api/v2/write?bucket=MyBucket&org=MyOrg",
+                test_server.url()
+            ))
+            .bearer_auth("w")
+            .body("h2o_temperature,location=santa_monica surface_degrees=65.2 1617286224000000000")
+            .send()
+            .await;
+        check_response("write", response, StatusCode::NO_CONTENT, Some("")).await;
+
+        let response = client.get(&url).bearer_auth("r").send().await;
+        check_response(
+            "query_with_read_token",
+            response,
+            StatusCode::OK,
+            Some("santa_monica"),
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn test_gzip_write() {
         let test_server = setup_server().await;
@@ -378,6 +569,14 @@ mod tests {
         assert_dbwrite(test_server, write).await;
     }
 
+    #[tokio::test]
+    async fn test_prometheus_write() {
+        let test_server = setup_server().await;
+        let write = assert_prometheus_write(&test_server).await;
+
+        assert_dbwrite(test_server, write).await;
+    }
+
     #[tokio::test]
     async fn write_to_invalid_database() {
         assert_write_to_invalid_database(setup_server().await).await;
@@ -571,6 +770,38 @@ mod tests {
         check_response("query", response, StatusCode::OK, Some(res)).await;
     }
 
+    #[tokio::test]
+    async fn test_query_timeout() {
+        let (client, test_server) = setup_test_data().await;
+
+        // a query completing within its timeout is unaffected
+        let response = client
+            .get(&format!(
+                "{}/api/v3/query?d=MyOrg_MyBucket&q={}&format=csv&timeout=1m",
+                test_server.url(),
+                "select%20state%20from%20h2o_temperature"
+            ))
+            .send()
+            .await;
+        check_response("query", response, StatusCode::OK, Some("state\nCA\n")).await;
+
+        let response = client
+            .get(&format!(
+                "{}/api/v3/query?d=MyOrg_MyBucket&q={}&timeout=soon",
+                test_server.url(),
+                "select%20state%20from%20h2o_temperature"
+            ))
+            .send()
+            .await;
+        check_response(
+            "query",
+            response,
+            StatusCode::BAD_REQUEST,
+            Some(r#"{"code":"invalid","message":"Invalid query timeout 'soon': expected number at 0"}"#),
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn test_query_json() {
         let (client, test_server) = setup_test_data().await;
@@ -612,6 +843,84 @@ mod tests {
         check_response("query", response, StatusCode::OK, Some(res)).await;
     }
 
+    #[tokio::test]
+    async fn test_query_json_lines() {
+        let (client, test_server) = setup_test_data().await;
+
+        let lp_data =
+            "h2o_temperature,location=Boston,state=MA surface_degrees=50.2 1617286224000000000";
+        let response = client
+            .post(&format!(
+                "{}/api/v2/write?bucket=MyBucket&org=MyOrg",
+                test_server.url(),
+            ))
+            .body(lp_data)
+            .send()
+            .await;
+        check_response("write", response, StatusCode::NO_CONTENT, Some("")).await;
+
+        let response = client
+            .get(&format!(
+                "{}/api/v3/query?d=MyOrg_MyBucket&q={}&format=jsonl",
+                test_server.url(),
+                "select%20*%20from%20h2o_temperature%20order%20by%20surface_degrees"
+            ))
+            .send()
+            .await;
+
+        assert_eq!(get_content_type(&response), "application/x-ndjson");
+        // results are streamed rather than sent with a known length
+        assert_eq!(
+            response
+                .as_ref()
+                .unwrap()
+                .headers()
+                .get("transfer-encoding")
+                .unwrap(),
+            "chunked"
+        );
+
+        let res = concat!(
+            r#"{"location":"Boston","state":"MA","surface_degrees":50.2,"time":"2021-04-01 14:10:24"}"#,
+            "\n",
+            r#"{"bottom_degrees":50.4,"location":"santa_monica","state":"CA","surface_degrees":65.2,"time":"2021-04-01 14:10:24"}"#,
+            "\n",
+        );
+        check_response("query", response, StatusCode::OK, Some(res)).await;
+    }
+
+    #[tokio::test]
+    async fn test_query_arrow() {
+        let (client, test_server) = setup_test_data().await;
+
+        let response = client
+            .get(&format!(
+                "{}/api/v3/query?d=MyOrg_MyBucket&q={}&format=arrow",
+                test_server.url(),
+                "select%20*%20from%20h2o_temperature"
+            ))
+            .send()
+            .await;
+
+        assert_eq!(
+            get_content_type(&response),
+            "application/vnd.apache.arrow.stream"
+        );
+
+        let body = response.unwrap().bytes().await.unwrap();
+        let reader = arrow::ipc::reader::StreamReader::try_new(std::io::Cursor::new(body)).unwrap();
+        let batches: Vec<_> = reader.map(|batch| batch.unwrap()).collect();
+
+        let expected = vec![
+            "+----------------+--------------+-------+-----------------+----------------------+",
+            "| bottom_degrees | location     | state | surface_degrees | time                 |",
+            "+----------------+--------------+-------+-----------------+----------------------+",
+            "| 50.4           | santa_monica | CA    | 65.2            | 2021-04-01T14:10:24Z |",
+            "+----------------+--------------+-------+-----------------+----------------------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+    }
+
     #[tokio::test]
     async fn test_query_invalid_name() {
         let (client, test_server) = setup_test_data().await;
@@ -635,6 +944,110 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_prometheus_read() {
+        use generated_types::prometheus::{
+            label_matcher::Type, read_request::ResponseType, ChunkedReadResponse, LabelMatcher,
+            Query, ReadRequest, ReadResponse,
+        };
+        use prost::Message;
+
+        let test_server = setup_server().await;
+        assert_prometheus_write(&test_server).await;
+
+        let client = Client::new();
+        let matcher = |r#type: Type, name: &str, value: &str| LabelMatcher {
+            r#type: r#type as i32,
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        let mut request = ReadRequest {
+            queries: vec![
+                Query {
+                    start_timestamp_ms: 1617286224000,
+                    end_timestamp_ms: 1617286225000,
+                    matchers: vec![
+                        matcher(Type::Eq, "__name__", "h2o_temperature"),
+                        matcher(Type::Re, "location", "santa.*"),
+                    ],
+                    hints: None,
+                },
+                Query {
+                    start_timestamp_ms: 1617286224000,
+                    end_timestamp_ms: 1617286225000,
+                    matchers: vec![matcher(Type::Neq, "state", "CA")],
+                    hints: None,
+                },
+            ],
+            accepted_response_types: vec![],
+        };
+
+        let send = |request: &ReadRequest| {
+            let body = snap::raw::Encoder::new()
+                .compress_vec(&request.encode_to_vec())
+                .unwrap();
+            client
+                .post(&format!(
+                    "{}/api/v1/prom/read?bucket=MyBucket&org=MyOrg",
+                    test_server.url(),
+                ))
+                .header(http::header::CONTENT_ENCODING, "snappy")
+                .body(body)
+                .send()
+        };
+
+        // Raw samples
+        let response = send(&request).await;
+        assert_eq!(get_content_type(&response), "application/x-protobuf");
+        let response = response.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.bytes().await.unwrap();
+        let body = snap::raw::Decoder::new().decompress_vec(&body).unwrap();
+        let response = ReadResponse::decode(body.as_slice()).unwrap();
+
+        assert_eq!(response.results.len(), 2);
+        assert!(response.results[1].timeseries.is_empty());
+
+        let timeseries = &response.results[0].timeseries;
+        assert_eq!(timeseries.len(), 1);
+        let labels: Vec<_> = timeseries[0]
+            .labels
+            .iter()
+            .map(|l| (l.name.as_str(), l.value.as_str()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("__name__", "h2o_temperature"),
+                ("location", "santa_monica"),
+                ("state", "CA")
+            ]
+        );
+        let samples: Vec<_> = timeseries[0]
+            .samples
+            .iter()
+            .map(|s| (s.timestamp, s.value))
+            .collect();
+        assert_eq!(samples, vec![(1617286224000, 65.2), (1617286225000, 66.1)]);
+
+        // Streamed chunks
+        request.accepted_response_types = vec![ResponseType::StreamedXorChunks as i32];
+        let response = send(&request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.bytes().await.unwrap();
+
+        // A single frame: varint length, 4 byte checksum, message
+        let len = body[0] as usize;
+        assert_eq!(body.len(), 1 + 4 + len);
+        let response = ChunkedReadResponse::decode(&body[5..]).unwrap();
+        assert_eq!(response.query_index, 0);
+        assert_eq!(response.chunked_series.len(), 1);
+        let chunks = &response.chunked_series[0].chunks;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].min_time_ms, 1617286224000);
+        assert_eq!(chunks[0].max_time_ms, 1617286225000);
+    }
+
     /// Run the specified SQL query and return formatted results as a string
     async fn run_query(db: Arc<Db>, query: &str) -> Vec<RecordBatch> {
         let ctx = db.new_query_context(None);
@@ -661,6 +1074,21 @@ mod tests {
         TestServer::new(Arc::new(server_type))
     }
 
+    /// Returns a server accepting the [`TEST_AUTH_TOKENS`]
+    async fn setup_server_with_auth() -> TestServer<DatabaseServerType> {
+        let test_server = setup_server().await;
+
+        let mut server_type = DatabaseServerType::new(
+            Arc::clone(&test_server.server_type().application),
+            Arc::clone(&test_server.server_type().server),
+            &CommonServerState::for_testing(),
+        );
+        server_type.authorizer =
+            Arc::new(Authorizer::try_from_json(TEST_AUTH_TOKENS.as_bytes()).unwrap());
+
+        TestServer::new(Arc::new(server_type))
+    }
+
     fn make_rules(db_name: impl Into<String>) -> ProvidedDatabaseRules {
         let db_name = DatabaseName::new(db_name.into()).unwrap();
         ProvidedDatabaseRules::new_rules(DatabaseRules::new(db_name).into())
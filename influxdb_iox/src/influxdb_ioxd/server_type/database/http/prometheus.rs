@@ -0,0 +1,600 @@
+//! Implementation of the Prometheus remote read protocol
+//!
+//! Each query of a `prometheus.ReadRequest` is translated into a
+//! [`Predicate`] and planned with
+//! [`InfluxRpcPlanner::read_filter`](query::frontend::influxrpc::InfluxRpcPlanner::read_filter).
+//! The resulting series are returned either as raw samples or as
+//! streamed XOR encoded chunks, depending on the response types the client
+//! accepts.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use datafusion::{logical_plan::Expr, prelude::*};
+use generated_types::prometheus::{
+    label_matcher, read_request::ResponseType, Chunk, ChunkedReadResponse, ChunkedSeries, Label,
+    LabelMatcher, Query, QueryResult, ReadRequest, ReadResponse, Sample, TimeSeries,
+};
+use http::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use hyper::{Body, Response};
+use mutable_batch_prom::{METRIC_NAME_LABEL, VALUE_COLUMN};
+use observability_deps::tracing::debug;
+use predicate::{
+    predicate::{Predicate, PredicateBuilder},
+    regex::regex_match_expr,
+};
+use prost::Message;
+use query::{
+    exec::{
+        admission::is_admission_rejected,
+        deadline::is_query_timeout,
+        seriesset::series::{Data, Either, Series},
+        ExecutionContextProvider,
+    },
+    frontend::influxrpc::{FIELD_COLUMN_NAME, MEASUREMENT_COLUMN_NAME},
+    QueryDatabase,
+};
+use snafu::{OptionExt, ResultExt, Snafu};
+use trace::ctx::SpanContext;
+
+use crate::influxdb_ioxd::{
+    http::error::{HttpApiError, HttpApiErrorExt, HttpApiErrorSource},
+    planner::Planner,
+};
+
+mod chunk;
+
+use chunk::{write_frame, XorChunkEncoder, MAX_SAMPLES_PER_CHUNK};
+
+/// Content type of a `SAMPLES` response
+const SAMPLES_CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// Content type of a `STREAMED_XOR_CHUNKS` response
+const STREAMED_CONTENT_TYPE: &str =
+    "application/x-streamed-protobuf; proto=prometheus.ChunkedReadResponse";
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Error decoding Prometheus remote read request: {}", source))]
+    DecodingRequest { source: prost::DecodeError },
+
+    #[snafu(display(
+        "None of the accepted response types are supported: {:?}",
+        response_types
+    ))]
+    UnsupportedResponseType { response_types: Vec<i32> },
+
+    #[snafu(display("Unknown label matcher type {} for label '{}'", matcher_type, name))]
+    UnknownMatcherType { matcher_type: i32, name: String },
+
+    #[snafu(display("Error while planning query: {}", source))]
+    Planning {
+        source: crate::influxdb_ioxd::planner::Error,
+    },
+
+    #[snafu(display("Error reading series from database {}: {}", db_name, source))]
+    ReadingSeries {
+        db_name: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[snafu(display("Error compressing response: {}", source))]
+    CompressingResponse { source: snap::Error },
+
+    #[snafu(display("Internal error creating HTTP response:  {}", source))]
+    CreatingResponse { source: http::Error },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl HttpApiErrorSource for Error {
+    fn to_http_api_error(&self) -> HttpApiError {
+        match self {
+            e @ Self::DecodingRequest { .. } => e.invalid(),
+            e @ Self::UnsupportedResponseType { .. } => e.invalid(),
+            e @ Self::UnknownMatcherType { .. } => e.invalid(),
+            e @ Self::Planning { .. } if is_query_timeout(e) => e.timeout(),
+            e @ Self::Planning { .. } => e.invalid(),
+            e @ Self::ReadingSeries { .. } if is_query_timeout(e) => e.timeout(),
+            e @ Self::ReadingSeries { .. } if is_admission_rejected(e) => e.too_many_requests(),
+            e @ Self::ReadingSeries { .. } => e.internal_error(),
+            e @ Self::CompressingResponse { .. } => e.internal_error(),
+            e @ Self::CreatingResponse { .. } => e.internal_error(),
+        }
+    }
+}
+
+/// Runs the snappy-compressed `prometheus.ReadRequest` in `body` against
+/// `db` and returns the encoded response.
+pub async fn read<D>(
+    db: Arc<D>,
+    db_name: &str,
+    body: Bytes,
+    span_ctx: Option<SpanContext>,
+) -> Result<Response<Body>>
+where
+    D: QueryDatabase + ExecutionContextProvider + 'static,
+{
+    let request = ReadRequest::decode(body).context(DecodingRequest)?;
+    let response_type = response_type(&request)?;
+
+    debug!(
+        num_queries = request.queries.len(),
+        ?response_type,
+        %db_name,
+        "running prometheus remote read"
+    );
+    let ctx = db.new_query_context(span_ctx);
+    db.record_query(&ctx, "prometheus_read", format!("{:?}", request.queries));
+
+    let mut results = Vec::with_capacity(request.queries.len());
+    for query in &request.queries {
+        let predicate = query_to_predicate(query)?;
+
+        let series_plan = Planner::new(&ctx)
+            .read_filter(Arc::clone(&db), predicate)
+            .await
+            .context(Planning)?;
+
+        let series = ctx
+            .to_series_and_groups(series_plan)
+            .await
+            .map_err(|e| Box::new(e) as _)
+            .context(ReadingSeries { db_name })?;
+
+        results.push(
+            series
+                .into_iter()
+                .filter_map(|either| match either {
+                    Either::Series(series) => series_to_time_series(series),
+                    Either::Group(_) => None,
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let response = match response_type {
+        ResponseType::Samples => {
+            let response = ReadResponse {
+                results: results
+                    .into_iter()
+                    .map(|timeseries| QueryResult { timeseries })
+                    .collect(),
+            };
+            let body = snap::raw::Encoder::new()
+                .compress_vec(&response.encode_to_vec())
+                .context(CompressingResponse)?;
+
+            Response::builder()
+                .header(CONTENT_TYPE, SAMPLES_CONTENT_TYPE)
+                .header(CONTENT_ENCODING, "snappy")
+                .body(Body::from(body))
+        }
+        ResponseType::StreamedXorChunks => {
+            // Each series is encoded as it is sent, one frame per series, so
+            // the encoded response is never buffered as a whole
+            let frames = results
+                .into_iter()
+                .enumerate()
+                .flat_map(|(query_index, timeseries)| {
+                    timeseries.into_iter().map(move |series| {
+                        let response = ChunkedReadResponse {
+                            chunked_series: vec![time_series_to_chunked(series)],
+                            query_index: query_index as i64,
+                        };
+                        let mut frame = vec![];
+                        write_frame(&mut frame, &response.encode_to_vec());
+                        Ok::<_, std::convert::Infallible>(frame)
+                    })
+                });
+
+            Response::builder()
+                .header(CONTENT_TYPE, STREAMED_CONTENT_TYPE)
+                .body(Body::wrap_stream(futures::stream::iter(frames)))
+        }
+    };
+
+    response.context(CreatingResponse)
+}
+
+/// Returns the first of the response types accepted by the client that is
+/// supported, defaulting to `SAMPLES` if the client did not specify any.
+fn response_type(request: &ReadRequest) -> Result<ResponseType> {
+    if request.accepted_response_types.is_empty() {
+        return Ok(ResponseType::Samples);
+    }
+
+    request
+        .accepted_response_types
+        .iter()
+        .find_map(|t| ResponseType::from_i32(*t))
+        .context(UnsupportedResponseType {
+            response_types: request.accepted_response_types.clone(),
+        })
+}
+
+/// Translates a Prometheus query into a [`Predicate`] selecting the `value`
+/// field of the matching series within the query's (inclusive) time range.
+///
+/// The metric name is mapped to the table, and all other labels to tags.
+fn query_to_predicate(query: &Query) -> Result<Predicate> {
+    // Prometheus uses inclusive millisecond bounds
+    let start = query.start_timestamp_ms.saturating_mul(1_000_000);
+    let end = query
+        .end_timestamp_ms
+        .saturating_mul(1_000_000)
+        .saturating_add(1);
+
+    let mut builder = PredicateBuilder::default()
+        .timestamp_range(start, end)
+        .field_columns(vec![VALUE_COLUMN]);
+
+    for matcher in &query.matchers {
+        let is_metric_name = matcher.name == METRIC_NAME_LABEL;
+        let matcher_type =
+            label_matcher::Type::from_i32(matcher.r#type).context(UnknownMatcherType {
+                matcher_type: matcher.r#type,
+                name: &matcher.name,
+            })?;
+
+        builder = match (is_metric_name, matcher_type) {
+            (true, label_matcher::Type::Eq) => builder.table(&matcher.value),
+            (true, _) => builder.add_expr(matcher_expr(
+                col(MEASUREMENT_COLUMN_NAME),
+                matcher_type,
+                matcher,
+            )),
+            (false, _) => {
+                let column = col(matcher.name.as_str());
+                let expr = matcher_expr(column.clone(), matcher_type, matcher);
+
+                // Prometheus treats a missing label as having an empty value
+                match matches_empty(matcher_type, matcher) {
+                    true => builder.add_expr(expr.or(column.is_null())),
+                    false => builder.add_expr(expr),
+                }
+            }
+        };
+    }
+
+    Ok(builder.build())
+}
+
+/// Returns the expression for `matcher` applied to `column`
+///
+/// Note that a tag that is not present is null and matches none of the
+/// matcher types, see [`matches_empty`].
+fn matcher_expr(column: Expr, matcher_type: label_matcher::Type, matcher: &LabelMatcher) -> Expr {
+    match matcher_type {
+        label_matcher::Type::Eq => column.eq(lit(matcher.value.as_str())),
+        label_matcher::Type::Neq => column.not_eq(lit(matcher.value.as_str())),
+        label_matcher::Type::Re => regex_match_expr(column, anchored(matcher), true),
+        label_matcher::Type::Nre => regex_match_expr(column, anchored(matcher), false),
+    }
+}
+
+/// Returns true if `matcher` matches an empty label value, and therefore
+/// series without the label. An invalid regular expression matches nothing,
+/// and fails the query once evaluated.
+fn matches_empty(matcher_type: label_matcher::Type, matcher: &LabelMatcher) -> bool {
+    let regex_matches_empty = || {
+        regex::Regex::new(&anchored(matcher))
+            .map(|regex| regex.is_match(""))
+            .ok()
+    };
+
+    match matcher_type {
+        label_matcher::Type::Eq => matcher.value.is_empty(),
+        label_matcher::Type::Neq => !matcher.value.is_empty(),
+        label_matcher::Type::Re => regex_matches_empty() == Some(true),
+        label_matcher::Type::Nre => regex_matches_empty() == Some(false),
+    }
+}
+
+/// Returns the regular expression of `matcher`, which Prometheus anchors at
+/// both ends
+fn anchored(matcher: &LabelMatcher) -> String {
+    format!("^(?:{})$", matcher.value)
+}
+
+/// Converts a [`Series`] to a Prometheus [`TimeSeries`] with sorted labels,
+/// returning `None` for series whose values are not numeric.
+fn series_to_time_series(series: Series) -> Option<TimeSeries> {
+    let samples = match series.data {
+        Data::FloatPoints { timestamps, values } => to_samples(timestamps, values),
+        Data::IntegerPoints { timestamps, values } => {
+            to_samples(timestamps, values.into_iter().map(|v| v as f64))
+        }
+        Data::UnsignedPoints { timestamps, values } => {
+            to_samples(timestamps, values.into_iter().map(|v| v as f64))
+        }
+        Data::BooleanPoints { .. } | Data::StringPoints { .. } => return None,
+    };
+
+    let mut labels: Vec<_> = series
+        .tags
+        .into_iter()
+        .filter(|tag| tag.key.as_ref() != FIELD_COLUMN_NAME)
+        .map(|tag| {
+            let name = match tag.key.as_ref() {
+                MEASUREMENT_COLUMN_NAME => METRIC_NAME_LABEL.to_string(),
+                key => key.to_string(),
+            };
+            Label {
+                name,
+                value: tag.value.to_string(),
+            }
+        })
+        .collect();
+    labels.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+    Some(TimeSeries {
+        labels,
+        samples,
+        exemplars: vec![],
+    })
+}
+
+/// Zips nanosecond timestamps and values into samples with millisecond
+/// timestamps
+fn to_samples(timestamps: Vec<i64>, values: impl IntoIterator<Item = f64>) -> Vec<Sample> {
+    timestamps
+        .into_iter()
+        .zip(values)
+        .map(|(timestamp, value)| Sample {
+            value,
+            timestamp: timestamp / 1_000_000,
+        })
+        .collect()
+}
+
+/// Encodes the samples of a [`TimeSeries`] into XOR chunks
+fn time_series_to_chunked(series: TimeSeries) -> ChunkedSeries {
+    let chunks = series
+        .samples
+        .chunks(MAX_SAMPLES_PER_CHUNK)
+        .map(|samples| {
+            let mut encoder = XorChunkEncoder::default();
+            for sample in samples {
+                encoder.append(sample.timestamp, sample.value);
+            }
+
+            Chunk {
+                min_time_ms: samples.first().map(|s| s.timestamp).unwrap_or_default(),
+                max_time_ms: samples.last().map(|s| s.timestamp).unwrap_or_default(),
+                r#type: generated_types::prometheus::chunk::Encoding::Xor as i32,
+                data: encoder.finish(),
+            }
+        })
+        .collect();
+
+    ChunkedSeries {
+        labels: series.labels,
+        chunks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_types::timestamp::TimestampRange;
+    use query::exec::seriesset::series::Tag;
+
+    fn matcher(matcher_type: label_matcher::Type, name: &str, value: &str) -> LabelMatcher {
+        LabelMatcher {
+            r#type: matcher_type as i32,
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_query_to_predicate() {
+        let query = Query {
+            start_timestamp_ms: 1000,
+            end_timestamp_ms: 2000,
+            matchers: vec![
+                matcher(label_matcher::Type::Eq, "__name__", "cpu"),
+                matcher(label_matcher::Type::Eq, "host", "a"),
+                matcher(label_matcher::Type::Re, "az", "us-.*"),
+            ],
+            hints: None,
+        };
+
+        let predicate = query_to_predicate(&query).unwrap();
+        assert_eq!(
+            predicate.table_names,
+            Some(std::iter::once("cpu".to_string()).collect())
+        );
+        assert_eq!(
+            predicate.field_columns,
+            Some(std::iter::once("value".to_string()).collect())
+        );
+        assert_eq!(
+            predicate.range,
+            Some(TimestampRange::new(1_000_000_000, 2_000_000_001))
+        );
+        assert_eq!(
+            predicate.exprs,
+            vec![
+                col("host").eq(lit("a")),
+                regex_match_expr(col("az"), "^(?:us-.*)$".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_to_predicate_missing_labels() {
+        let query = Query {
+            start_timestamp_ms: 0,
+            end_timestamp_ms: 0,
+            matchers: vec![
+                matcher(label_matcher::Type::Neq, "region", "west"),
+                matcher(label_matcher::Type::Nre, "env", "dev|test"),
+                matcher(label_matcher::Type::Eq, "host", ""),
+                matcher(label_matcher::Type::Re, "az", "us-.*|"),
+                matcher(label_matcher::Type::Neq, "rack", ""),
+                matcher(label_matcher::Type::Nre, "zone", ".*"),
+            ],
+            hints: None,
+        };
+
+        // Matchers that match an empty value also match series without the
+        // label
+        let predicate = query_to_predicate(&query).unwrap();
+        assert_eq!(
+            predicate.exprs,
+            vec![
+                col("region")
+                    .not_eq(lit("west"))
+                    .or(col("region").is_null()),
+                regex_match_expr(col("env"), "^(?:dev|test)$".to_string(), false)
+                    .or(col("env").is_null()),
+                col("host").eq(lit("")).or(col("host").is_null()),
+                regex_match_expr(col("az"), "^(?:us-.*|)$".to_string(), true)
+                    .or(col("az").is_null()),
+                col("rack").not_eq(lit("")),
+                regex_match_expr(col("zone"), "^(?:.*)$".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_to_predicate_metric_name() {
+        let query = Query {
+            start_timestamp_ms: 0,
+            end_timestamp_ms: 0,
+            matchers: vec![matcher(label_matcher::Type::Re, "__name__", "cpu|mem")],
+            hints: None,
+        };
+
+        let predicate = query_to_predicate(&query).unwrap();
+        assert_eq!(predicate.table_names, None);
+        assert_eq!(
+            predicate.exprs,
+            vec![regex_match_expr(
+                col("_measurement"),
+                "^(?:cpu|mem)$".to_string(),
+                true
+            )]
+        );
+
+        let query = Query {
+            matchers: vec![LabelMatcher {
+                r#type: 42,
+                name: "host".to_string(),
+                value: "a".to_string(),
+            }],
+            ..query
+        };
+        let err = query_to_predicate(&query).unwrap_err();
+        assert!(matches!(err, Error::UnknownMatcherType { .. }));
+    }
+
+    #[test]
+    fn test_response_type() {
+        let mut request = ReadRequest::default();
+        assert_eq!(response_type(&request).unwrap(), ResponseType::Samples);
+
+        request.accepted_response_types = vec![42, ResponseType::StreamedXorChunks as i32];
+        assert_eq!(
+            response_type(&request).unwrap(),
+            ResponseType::StreamedXorChunks
+        );
+
+        request.accepted_response_types = vec![42];
+        assert!(matches!(
+            response_type(&request),
+            Err(Error::UnsupportedResponseType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_series_to_time_series() {
+        let tag = |key: &str, value: &str| Tag {
+            key: key.into(),
+            value: value.into(),
+        };
+
+        let series = Series {
+            tags: vec![
+                tag("_measurement", "cpu"),
+                tag("region", "west"),
+                tag("host", "a"),
+                tag("_field", "value"),
+            ],
+            data: Data::IntegerPoints {
+                timestamps: vec![1_000_000, 2_500_000],
+                values: vec![1, 2],
+            },
+        };
+
+        let time_series = series_to_time_series(series).unwrap();
+        let labels: Vec<_> = time_series
+            .labels
+            .iter()
+            .map(|l| (l.name.as_str(), l.value.as_str()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![("__name__", "cpu"), ("host", "a"), ("region", "west")]
+        );
+        assert_eq!(
+            time_series.samples,
+            vec![
+                Sample {
+                    value: 1.,
+                    timestamp: 1
+                },
+                Sample {
+                    value: 2.,
+                    timestamp: 2
+                }
+            ]
+        );
+
+        let series = Series {
+            tags: vec![tag("_measurement", "cpu"), tag("_field", "value")],
+            data: Data::StringPoints {
+                timestamps: vec![1],
+                values: vec!["foo".to_string()],
+            },
+        };
+        assert!(series_to_time_series(series).is_none());
+    }
+
+    #[test]
+    fn test_time_series_to_chunked() {
+        let samples: Vec<_> = (0..250)
+            .map(|i| Sample {
+                value: i as f64,
+                timestamp: i * 1000,
+            })
+            .collect();
+        let series = TimeSeries {
+            labels: vec![Label {
+                name: "__name__".to_string(),
+                value: "cpu".to_string(),
+            }],
+            samples,
+            exemplars: vec![],
+        };
+
+        let chunked = time_series_to_chunked(series);
+        assert_eq!(chunked.labels.len(), 1);
+
+        let ranges: Vec<_> = chunked
+            .chunks
+            .iter()
+            .map(|c| (c.min_time_ms, c.max_time_ms, c.data[1]))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (0, 119_000, 120),
+                (120_000, 239_000, 120),
+                (240_000, 249_000, 10)
+            ]
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! Encoding of samples into the Prometheus XOR chunk format and of
+//! messages into the framing used by streamed remote read responses.
+//!
+//! See `tsdb/chunkenc/xor.go` and `storage/remote/chunked.go` in the
+//! Prometheus repository for the reference implementations.
+
+/// The maximum number of samples Prometheus stores in a single chunk
+pub const MAX_SAMPLES_PER_CHUNK: usize = 120;
+
+/// Encodes a sequence of `(timestamp_ms, value)` samples, which must be
+/// ordered by time, into a Prometheus XOR chunk.
+#[derive(Debug)]
+pub struct XorChunkEncoder {
+    stream: BitWriter,
+    num_samples: u16,
+    t: i64,
+    t_delta: u64,
+    v: f64,
+    leading: u8,
+    trailing: u8,
+}
+
+impl Default for XorChunkEncoder {
+    fn default() -> Self {
+        let mut stream = BitWriter::default();
+        // Space for the big endian sample count
+        stream.write_byte(0);
+        stream.write_byte(0);
+
+        Self {
+            stream,
+            num_samples: 0,
+            t: 0,
+            t_delta: 0,
+            v: 0.,
+            leading: u8::MAX,
+            trailing: 0,
+        }
+    }
+}
+
+impl XorChunkEncoder {
+    /// Returns the number of samples appended so far
+    pub fn num_samples(&self) -> usize {
+        self.num_samples as usize
+    }
+
+    /// Appends a sample to the chunk
+    pub fn append(&mut self, t: i64, v: f64) {
+        match self.num_samples {
+            0 => {
+                for b in varint(t) {
+                    self.stream.write_byte(b);
+                }
+                self.stream.write_bits(v.to_bits(), 64);
+            }
+            1 => {
+                let t_delta = t.wrapping_sub(self.t) as u64;
+                for b in uvarint(t_delta) {
+                    self.stream.write_byte(b);
+                }
+                self.write_value_delta(v);
+                self.t_delta = t_delta;
+            }
+            _ => {
+                let t_delta = t.wrapping_sub(self.t) as u64;
+                let dod = t_delta.wrapping_sub(self.t_delta) as i64;
+
+                if dod == 0 {
+                    self.stream.write_bit(false);
+                } else if bit_range(dod, 14) {
+                    self.stream.write_bits(0b10, 2);
+                    self.stream.write_bits(dod as u64, 14);
+                } else if bit_range(dod, 17) {
+                    self.stream.write_bits(0b110, 3);
+                    self.stream.write_bits(dod as u64, 17);
+                } else if bit_range(dod, 20) {
+                    self.stream.write_bits(0b1110, 4);
+                    self.stream.write_bits(dod as u64, 20);
+                } else {
+                    self.stream.write_bits(0b1111, 4);
+                    self.stream.write_bits(dod as u64, 64);
+                }
+
+                self.write_value_delta(v);
+                self.t_delta = t_delta;
+            }
+        }
+
+        self.t = t;
+        self.v = v;
+        self.num_samples += 1;
+    }
+
+    fn write_value_delta(&mut self, v: f64) {
+        let delta = v.to_bits() ^ self.v.to_bits();
+
+        if delta == 0 {
+            self.stream.write_bit(false);
+            return;
+        }
+        self.stream.write_bit(true);
+
+        // The leading zero count is stored in 5 bits
+        let leading = (delta.leading_zeros() as u8).min(31);
+        let trailing = delta.trailing_zeros() as u8;
+
+        if self.leading != u8::MAX && leading >= self.leading && trailing >= self.trailing {
+            // The meaningful bits fit within those of the previous value
+            self.stream.write_bit(false);
+            self.stream.write_bits(
+                delta >> self.trailing,
+                64 - self.leading as u32 - self.trailing as u32,
+            );
+        } else {
+            self.leading = leading;
+            self.trailing = trailing;
+
+            self.stream.write_bit(true);
+            self.stream.write_bits(leading as u64, 5);
+
+            // 64 significant bits overflow the 6 bit field and are stored
+            // as 0, which readers interpret as 64
+            let significant = 64 - leading as u32 - trailing as u32;
+            self.stream.write_bits(significant as u64, 6);
+            self.stream.write_bits(delta >> trailing, significant);
+        }
+    }
+
+    /// Returns the encoded chunk
+    pub fn finish(self) -> Vec<u8> {
+        let mut bytes = self.stream.bytes;
+        bytes[..2].copy_from_slice(&self.num_samples.to_be_bytes());
+        bytes
+    }
+}
+
+/// Returns true if `x` can be stored in `nbits` bits
+fn bit_range(x: i64, nbits: u32) -> bool {
+    -((1 << (nbits - 1)) - 1) <= x && x <= 1 << (nbits - 1)
+}
+
+/// A stream of bits, written most significant bit first
+#[derive(Debug, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// The number of bits still available in the last byte
+    available: u32,
+}
+
+impl BitWriter {
+    fn write_bit(&mut self, bit: bool) {
+        if self.available == 0 {
+            self.bytes.push(0);
+            self.available = 8;
+        }
+        self.available -= 1;
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 1 << self.available;
+        }
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        self.write_bits(byte as u64, 8)
+    }
+
+    /// Writes the lowest `nbits` bits of `value`
+    fn write_bits(&mut self, value: u64, nbits: u32) {
+        for i in (0..nbits).rev() {
+            self.write_bit((value >> i) & 1 == 1)
+        }
+    }
+}
+
+/// Encodes `value` as an unsigned LEB128 varint
+fn uvarint(mut value: u64) -> Vec<u8> {
+    let mut out = Vec::with_capacity(10);
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+    out
+}
+
+/// Encodes `value` as a zig-zag encoded signed varint
+fn varint(value: i64) -> Vec<u8> {
+    uvarint(((value << 1) ^ (value >> 63)) as u64)
+}
+
+/// Appends `message` to `out` in the framing used by streamed remote read
+/// responses: the message length as a varint, followed by the big endian
+/// CRC32 (Castagnoli) of the message, followed by the message itself.
+pub fn write_frame(out: &mut Vec<u8>, message: &[u8]) {
+    out.extend(uvarint(message.len() as u64));
+    out.extend_from_slice(&crc32c(message).to_be_bytes());
+    out.extend_from_slice(message);
+}
+
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+const fn crc32c_table() -> [u32; 256] {
+    // Reversed Castagnoli polynomial
+    const POLY: u32 = 0x82F6_3B78;
+
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Computes the CRC32 checksum of `data` using the Castagnoli polynomial
+fn crc32c(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, b| {
+        CRC32C_TABLE[((crc ^ *b as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A port of the Prometheus XOR chunk iterator
+    struct XorChunkDecoder<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> XorChunkDecoder<'a> {
+        fn read_bit(&mut self) -> bool {
+            let bit = (self.bytes[self.pos / 8] >> (7 - self.pos % 8)) & 1 == 1;
+            self.pos += 1;
+            bit
+        }
+
+        fn read_bits(&mut self, nbits: u32) -> u64 {
+            (0..nbits).fold(0, |acc, _| (acc << 1) | self.read_bit() as u64)
+        }
+
+        fn read_uvarint(&mut self) -> u64 {
+            let mut value = 0;
+            let mut shift = 0;
+            loop {
+                let byte = self.read_bits(8);
+                value |= (byte & 0x7F) << shift;
+                if byte & 0x80 == 0 {
+                    return value;
+                }
+                shift += 7;
+            }
+        }
+
+        fn read_varint(&mut self) -> i64 {
+            let ux = self.read_uvarint();
+            ((ux >> 1) as i64) ^ -((ux & 1) as i64)
+        }
+
+        fn decode(bytes: &'a [u8]) -> Vec<(i64, f64)> {
+            let num_samples = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+            let mut decoder = Self { bytes, pos: 16 };
+
+            let mut samples = Vec::with_capacity(num_samples);
+            let (mut t, mut t_delta, mut v) = (0_i64, 0_u64, 0_u64);
+            let (mut leading, mut trailing) = (0_u32, 0_u32);
+
+            for i in 0..num_samples {
+                match i {
+                    0 => {
+                        t = decoder.read_varint();
+                        v = decoder.read_bits(64);
+                        samples.push((t, f64::from_bits(v)));
+                        continue;
+                    }
+                    1 => t_delta = decoder.read_uvarint(),
+                    _ => {
+                        let mut prefix = 0;
+                        while prefix < 4 && decoder.read_bit() {
+                            prefix += 1;
+                        }
+                        let dod = match prefix {
+                            0 => 0,
+                            4 => decoder.read_bits(64) as i64,
+                            n => {
+                                let nbits = [14, 17, 20][n - 1];
+                                let bits = decoder.read_bits(nbits) as i64;
+                                // the encoded range is [-(2^(n-1) - 1), 2^(n-1)]
+                                if bits > 1 << (nbits - 1) {
+                                    bits - (1 << nbits)
+                                } else {
+                                    bits
+                                }
+                            }
+                        };
+                        t_delta = t_delta.wrapping_add(dod as u64);
+                    }
+                }
+                t = t.wrapping_add(t_delta as i64);
+
+                if decoder.read_bit() {
+                    if decoder.read_bit() {
+                        leading = decoder.read_bits(5) as u32;
+                        let mut significant = decoder.read_bits(6) as u32;
+                        if significant == 0 {
+                            significant = 64;
+                        }
+                        trailing = 64 - leading - significant;
+                    }
+                    let significant = 64 - leading - trailing;
+                    v ^= decoder.read_bits(significant) << trailing;
+                }
+                samples.push((t, f64::from_bits(v)));
+            }
+            samples
+        }
+    }
+
+    fn encode(samples: &[(i64, f64)]) -> Vec<u8> {
+        let mut encoder = XorChunkEncoder::default();
+        for (t, v) in samples {
+            encoder.append(*t, *v);
+        }
+        assert_eq!(encoder.num_samples(), samples.len());
+        encoder.finish()
+    }
+
+    #[test]
+    fn test_xor_chunk_bytes() {
+        assert_eq!(encode(&[]), vec![0, 0]);
+
+        assert_eq!(
+            encode(&[(1000, 1.)]),
+            vec![0, 1, 0xD0, 0x0F, 0x3F, 0xF0, 0, 0, 0, 0, 0, 0]
+        );
+
+        assert_eq!(
+            encode(&[(1000, 1.), (2000, 1.), (3000, 1.)]),
+            vec![0, 3, 0xD0, 0x0F, 0x3F, 0xF0, 0, 0, 0, 0, 0, 0, 0xE8, 0x07, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_xor_chunk_roundtrip() {
+        let samples: Vec<_> = (0..MAX_SAMPLES_PER_CHUNK as i64)
+            .map(|i| {
+                // irregular intervals exercising every delta-of-delta width
+                let t = 1_600_000_000_000 + i * 15_000 + (i % 7) * (1 << (i % 23));
+                let v = match i % 5 {
+                    0 => i as f64,
+                    1 => -0.5 * i as f64,
+                    2 => f64::MAX / (i + 1) as f64,
+                    3 => f64::MIN_POSITIVE,
+                    _ => 42.,
+                };
+                (t, v)
+            })
+            .chain(std::iter::once((i64::MAX, f64::INFINITY)))
+            .collect();
+
+        let chunk = encode(&samples);
+        assert_eq!(XorChunkDecoder::decode(&chunk), samples);
+
+        let samples = vec![(-5, f64::NAN), (0, 0.), (3, -0.)];
+        let decoded = XorChunkDecoder::decode(&encode(&samples));
+        assert!(decoded[0].1.is_nan());
+        assert_eq!(decoded[1], samples[1]);
+        assert_eq!(decoded[2].1.to_bits(), (-0_f64).to_bits());
+    }
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(crc32c(b""), 0);
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let mut out = vec![];
+        write_frame(&mut out, b"123456789");
+        assert_eq!(out, b"\x09\xE3\x06\x92\x83123456789".to_vec());
+    }
+}
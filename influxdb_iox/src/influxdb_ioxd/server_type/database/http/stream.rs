@@ -0,0 +1,168 @@
+//! Streaming of SQL query results as an HTTP response body
+use std::{pin::Pin, task::Poll};
+
+use bytes::Bytes;
+use datafusion::{error::DataFusionError, physical_plan::SendableRecordBatchStream};
+use futures::{SinkExt, Stream, StreamExt};
+use influxdb_iox_client::format::{BatchFormatter, QueryOutputFormat};
+use pin_project::{pin_project, pinned_drop};
+use snafu::{ResultExt, Snafu};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Error executing query: {}", source))]
+    Query { source: DataFusionError },
+
+    #[snafu(display("Error formatting query results as {}: {}", format, source))]
+    Formatting {
+        format: QueryOutputFormat,
+        source: influxdb_iox_client::format::Error,
+    },
+}
+
+/// A stream of formatted query results, suitable for a chunked HTTP response
+/// body.
+///
+/// The query is executed and its results formatted on a background task,
+/// which is aborted when this stream is dropped. As hyper drops the body of
+/// a response when the client disconnects, this cancels the query.
+///
+/// An error after the first chunk has been sent cannot be reported with an
+/// HTTP status, and instead terminates the response prematurely.
+#[pin_project(PinnedDrop)]
+#[derive(Debug)]
+pub struct QueryResponseStream {
+    #[pin]
+    rx: futures::channel::mpsc::Receiver<Result<Bytes, Error>>,
+    join_handle: JoinHandle<()>,
+}
+
+impl QueryResponseStream {
+    pub fn new(mut stream: SendableRecordBatchStream, format: QueryOutputFormat) -> Self {
+        let (mut tx, rx) = futures::channel::mpsc::channel(1);
+
+        let join_handle = tokio::spawn(async move {
+            let mut formatter = BatchFormatter::new(format);
+
+            while let Some(batch) = stream.next().await {
+                let bytes = batch.context(Query).and_then(|batch| {
+                    formatter
+                        .write(&batch)
+                        .context(Formatting { format })
+                        .map(Bytes::from)
+                });
+
+                let is_err = bytes.is_err();
+                if tx.send(bytes).await.is_err() || is_err {
+                    // receiver is gone or the stream is cut due to the error
+                    return;
+                }
+            }
+
+            let bytes = formatter.finish().context(Formatting { format });
+            // failure sending here is OK because the stream is done anyways
+            tx.send(bytes.map(Bytes::from)).await.ok();
+        });
+
+        Self { rx, join_handle }
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for QueryResponseStream {
+    fn drop(self: Pin<&mut Self>) {
+        self.join_handle.abort();
+    }
+}
+
+impl Stream for QueryResponseStream {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.as_mut().project().rx.poll_next(cx) {
+                // Don't emit empty chunks, e.g. while formats buffer batches
+                Poll::Ready(Some(Ok(bytes))) if bytes.is_empty() => continue,
+                poll => return poll,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{ArrayRef, Int64Array},
+        datatypes::{DataType, Field, Schema},
+        error::ArrowError,
+        record_batch::RecordBatch,
+    };
+    use datafusion_util::AdapterStream;
+    use std::sync::Arc;
+
+    fn batch(values: Vec<i64>) -> RecordBatch {
+        RecordBatch::try_from_iter(vec![(
+            "val",
+            Arc::new(Int64Array::from(values)) as ArrayRef,
+        )])
+        .unwrap()
+    }
+
+    fn record_batch_stream(
+        batches: Vec<Result<RecordBatch, ArrowError>>,
+    ) -> SendableRecordBatchStream {
+        let schema = Arc::new(Schema::new(vec![Field::new("val", DataType::Int64, true)]));
+        let (tx, rx) = tokio::sync::mpsc::channel(batches.len());
+        for batch in batches {
+            tx.try_send(batch).unwrap();
+        }
+        AdapterStream::adapt(schema, rx)
+    }
+
+    #[tokio::test]
+    async fn test_stream_csv() {
+        let stream = record_batch_stream(vec![Ok(batch(vec![1, 2])), Ok(batch(vec![3]))]);
+        let chunks: Vec<_> = QueryResponseStream::new(stream, QueryOutputFormat::Csv)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(chunks, vec!["val\n1\n2\n", "3\n"]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_pretty() {
+        let stream = record_batch_stream(vec![Ok(batch(vec![1])), Ok(batch(vec![2]))]);
+        let chunks: Vec<_> = QueryResponseStream::new(stream, QueryOutputFormat::Pretty)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        // pretty output is buffered into a single chunk
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(
+            chunks[0],
+            "+-----+\n| val |\n+-----+\n| 1   |\n| 2   |\n+-----+"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_error() {
+        let stream = record_batch_stream(vec![
+            Ok(batch(vec![1])),
+            Err(ArrowError::ComputeError("boom".to_string())),
+            Ok(batch(vec![2])),
+        ]);
+        let mut response = QueryResponseStream::new(stream, QueryOutputFormat::JsonLines);
+
+        assert_eq!(response.next().await.unwrap().unwrap(), "{\"val\":1}\n");
+        let err = response.next().await.unwrap().unwrap_err();
+        assert!(matches!(err, Error::Query { .. }));
+        assert!(response.next().await.is_none());
+    }
+}
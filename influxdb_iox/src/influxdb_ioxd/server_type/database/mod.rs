@@ -10,6 +10,7 @@ use tokio_util::sync::CancellationToken;
 use trace::TraceCollector;
 
 use crate::influxdb_ioxd::{
+    auth::Authorizer,
     http::metrics::LineProtocolMetrics,
     rpc::RpcBuilderInput,
     server_type::{RpcError, ServerType},
@@ -30,7 +31,9 @@ pub struct DatabaseServerType {
     pub server: Arc<Server>,
     pub lp_metrics: Arc<LineProtocolMetrics>,
     pub max_request_size: usize,
+    pub write_batch_size: usize,
     pub serving_readiness: ServingReadiness,
+    pub authorizer: Arc<Authorizer>,
     shutdown: CancellationToken,
 }
 
@@ -49,7 +52,9 @@ impl DatabaseServerType {
             server,
             lp_metrics,
             max_request_size: common_state.run_config().max_http_request_size,
+            write_batch_size: common_state.run_config().http_write_batch_size,
             serving_readiness: common_state.serving_readiness().clone(),
+            authorizer: common_state.authorizer(),
             shutdown: CancellationToken::new(),
         }
     }
@@ -157,7 +162,7 @@ mod tests {
             .await
             .unwrap();
 
-        let common_state = CommonServerState::from_config(config).unwrap();
+        let common_state = CommonServerState::from_config(config).await.unwrap();
         let server_type = Arc::new(DatabaseServerType::new(application, server, &common_state));
 
         serve(common_state, grpc_listener, http_listener, server_type)
@@ -358,7 +363,9 @@ mod tests {
 
         let addr = grpc_listener.local_addr().unwrap();
 
-        let common_state = CommonServerState::from_config(config.run_config.clone()).unwrap();
+        let common_state = CommonServerState::from_config(config.run_config.clone())
+            .await
+            .unwrap();
         let server_type = Arc::new(DatabaseServerType::new(
             application,
             Arc::clone(&server),
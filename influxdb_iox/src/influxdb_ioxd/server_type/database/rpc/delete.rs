@@ -10,9 +10,11 @@ use tonic::Response;
 
 struct DeleteService {
     server: Arc<Server>,
+    authorizer: Arc<Authorizer>,
 }
 
 use super::error::{default_dml_error_handler, default_server_error_handler};
+use crate::influxdb_ioxd::auth::{AuthToken, Authorizer, Permission};
 
 #[tonic::async_trait]
 impl delete_service_server::DeleteService for DeleteService {
@@ -21,6 +23,7 @@ impl delete_service_server::DeleteService for DeleteService {
         request: tonic::Request<DeleteRequest>,
     ) -> Result<tonic::Response<DeleteResponse>, tonic::Status> {
         let span_ctx = request.extensions().get().cloned();
+        let token = AuthToken::from_metadata(request.metadata());
         let DeleteRequest { payload } = request.into_inner();
         let DeletePayload {
             db_name,
@@ -35,6 +38,12 @@ impl delete_service_server::DeleteService for DeleteService {
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Write,
+            "grpc delete",
+        )?;
         let db = self
             .server
             .db(&db_name)
@@ -49,6 +58,7 @@ impl delete_service_server::DeleteService for DeleteService {
 
 pub fn make_server(
     server: Arc<Server>,
+    authorizer: Arc<Authorizer>,
 ) -> delete_service_server::DeleteServiceServer<impl delete_service_server::DeleteService> {
-    delete_service_server::DeleteServiceServer::new(DeleteService { server })
+    delete_service_server::DeleteServiceServer::new(DeleteService { server, authorizer })
 }
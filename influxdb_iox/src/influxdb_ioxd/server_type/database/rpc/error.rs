@@ -37,6 +37,7 @@ pub fn default_server_error_handler(error: server::Error) -> tonic::Status {
         }
         .into(),
         Error::WipePreservedCatalog { source } => default_database_error_handler(source),
+        Error::CanNotUpdateRules { source, .. } => default_database_error_handler(source),
         Error::DatabaseInit { source } => {
             tonic::Status::invalid_argument(format!("Cannot initialize database: {}", source))
         }
@@ -109,7 +110,11 @@ pub fn default_database_error_handler(error: server::database::Error) -> tonic::
             error!(%source, "Unexpected error skipping replay");
             InternalError {}.into()
         }
-        Error::CannotReleaseUnowned { .. } => tonic::Status::failed_precondition(error.to_string()),
+        Error::CannotReleaseUnowned { .. }
+        | Error::CannotLockSchema { .. }
+        | Error::InvalidLockedSchema { .. } => {
+            tonic::Status::failed_precondition(error.to_string())
+        }
         Error::CannotRelease { source, .. } => {
             error!(%source, "Unexpected error releasing database");
             InternalError {}.into()
@@ -1,6 +1,7 @@
 //! Implements the native gRPC IOx query API using Arrow Flight
 use std::fmt::Debug;
 use std::task::Poll;
+use std::time::Duration;
 use std::{pin::Pin, sync::Arc};
 
 use arrow::{
@@ -11,25 +12,44 @@ use arrow::{
 };
 use arrow_flight::{
     flight_service_server::{FlightService as Flight, FlightServiceServer as FlightServer},
-    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightInfo,
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
     HandshakeRequest, HandshakeResponse, PutResult, SchemaAsIpc, SchemaResult, Ticket,
 };
 use datafusion::physical_plan::ExecutionPlan;
 use futures::{SinkExt, Stream, StreamExt};
 use pin_project::{pin_project, pinned_drop};
 use query::QueryDatabase;
-use serde::Deserialize;
-use snafu::{ResultExt, Snafu};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt, Snafu};
 use tokio::task::JoinHandle;
-use tonic::{Request, Response, Streaming};
+use tonic::{metadata::MetadataMap, Request, Response, Streaming};
 
 use data_types::{DatabaseName, DatabaseNameError};
+use generated_types::arrow::flight::protocol::sql::{
+    ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest,
+    ActionCreatePreparedStatementResult, TicketStatementQuery,
+};
 use observability_deps::tracing::{info, warn};
-use query::exec::{ExecutionContextProvider, IOxExecutionContext};
-use server::Server;
+use query::exec::{
+    admission::{is_admission_rejected, QueryPriority},
+    deadline::is_query_timeout,
+    memory::is_memory_exhausted,
+    ExecutionContextProvider, IOxExecutionContext,
+};
+use server::{db::Db, Server};
+use trace::ctx::SpanContext;
 
 use super::error::default_server_error_handler;
-use crate::influxdb_ioxd::planner::Planner;
+use crate::influxdb_ioxd::{
+    auth::{AuthToken, Authorizer, Permission},
+    planner::Planner,
+    rpc::{deadline::grpc_timeout, priority::query_priority},
+};
+use sql::{
+    FlightSqlCommand, FlightSqlMessage, CLOSE_PREPARED_STATEMENT, CREATE_PREPARED_STATEMENT,
+};
+
+mod sql;
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug, Snafu)]
@@ -71,6 +91,27 @@ pub enum Error {
     Planning {
         source: crate::influxdb_ioxd::planner::Error,
     },
+
+    #[snafu(display("Invalid Flight SQL command: {}", source))]
+    InvalidFlightSqlCommand { source: prost::DecodeError },
+
+    #[snafu(display("Unsupported Flight SQL command: {}", type_url))]
+    UnsupportedFlightSqlCommand { type_url: String },
+
+    #[snafu(display("Error listing databases: {}", source))]
+    ListDatabases { source: server::Error },
+
+    #[snafu(display("Flight descriptor does not contain a Flight SQL command"))]
+    NotFlightSqlCommand,
+
+    #[snafu(display("Invalid statement handle: {}", source))]
+    InvalidHandle { source: serde_json::Error },
+
+    #[snafu(display("Missing '{}' header naming the database to query", header))]
+    MissingDatabaseHeader { header: &'static str },
+
+    #[snafu(display("Unsupported action: {}", action_type))]
+    UnsupportedAction { action_type: String },
 }
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -86,11 +127,18 @@ impl From<Error> for tonic::Status {
             | Error::InvalidTicket { .. }
             | Error::InvalidQuery { .. }
             // TODO(edd): this should be `debug`. Keeping at info whilst IOx still in early development
-            | Error::InvalidDatabaseName { .. } => info!(?err, msg),
+            | Error::InvalidDatabaseName { .. }
+            | Error::InvalidFlightSqlCommand { .. }
+            | Error::UnsupportedFlightSqlCommand { .. }
+            | Error::NotFlightSqlCommand
+            | Error::InvalidHandle { .. }
+            | Error::MissingDatabaseHeader { .. }
+            | Error::UnsupportedAction { .. } => info!(?err, msg),
             Error::Query { .. } => info!(?err, msg),
             Error::DictionaryError { .. }
             | Error::InvalidRecordBatch { .. }
-            | Error::Planning { .. } => warn!(?err, msg),
+            | Error::Planning { .. }
+            | Error::ListDatabases { .. } => warn!(?err, msg),
         }
         err.to_status()
     }
@@ -105,20 +153,44 @@ impl Error {
             Self::InvalidTicket { .. } => Status::invalid_argument(self.to_string()),
             Self::InvalidQuery { .. } => Status::invalid_argument(self.to_string()),
             Self::DatabaseNotFound { .. } => Status::not_found(self.to_string()),
+            Self::Query { source, .. } if is_query_timeout(source.as_ref()) => {
+                Status::deadline_exceeded(self.to_string())
+            }
+            Self::Query { source, .. }
+                if is_memory_exhausted(source.as_ref())
+                    || is_admission_rejected(source.as_ref()) =>
+            {
+                Status::resource_exhausted(self.to_string())
+            }
             Self::Query { .. } => Status::internal(self.to_string()),
             Self::InvalidDatabaseName { .. } => Status::invalid_argument(self.to_string()),
             Self::InvalidRecordBatch { .. } => Status::internal(self.to_string()),
+            Self::Planning { source } if is_query_timeout(source) => {
+                Status::deadline_exceeded(self.to_string())
+            }
+            Self::Planning { source } if is_memory_exhausted(source) => {
+                Status::resource_exhausted(self.to_string())
+            }
             Self::Planning { .. } => Status::invalid_argument(self.to_string()),
             Self::DictionaryError { .. } => Status::internal(self.to_string()),
+            Self::InvalidFlightSqlCommand { .. } => Status::invalid_argument(self.to_string()),
+            Self::UnsupportedFlightSqlCommand { .. } => Status::unimplemented(self.to_string()),
+            Self::ListDatabases { .. } => Status::failed_precondition(self.to_string()),
+            Self::NotFlightSqlCommand => Status::invalid_argument(self.to_string()),
+            Self::InvalidHandle { .. } => Status::invalid_argument(self.to_string()),
+            Self::MissingDatabaseHeader { .. } => Status::invalid_argument(self.to_string()),
+            Self::UnsupportedAction { .. } => Status::unimplemented(self.to_string()),
         }
     }
 }
 
 type TonicStream<T> = Pin<Box<dyn Stream<Item = Result<T, tonic::Status>> + Send + Sync + 'static>>;
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 /// Body of the `Ticket` serialized and sent to the do_get endpoint; this should
 /// be shared with the read API probably...
+///
+/// Also used as the opaque statement handle of Flight SQL statements.
 struct ReadInfo {
     database_name: String,
     sql_query: String,
@@ -128,10 +200,136 @@ struct ReadInfo {
 #[derive(Debug)]
 struct FlightService {
     server: Arc<Server>,
+    authorizer: Arc<Authorizer>,
 }
 
-pub fn make_server(server: Arc<Server>) -> FlightServer<impl Flight> {
-    FlightServer::new(FlightService { server })
+pub fn make_server(server: Arc<Server>, authorizer: Arc<Authorizer>) -> FlightServer<impl Flight> {
+    FlightServer::new(FlightService { server, authorizer })
+}
+
+impl FlightService {
+    /// Returns the database queried by `read_info` and a context to query it
+    fn query_context(
+        &self,
+        read_info: &ReadInfo,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<(Arc<Db>, IOxExecutionContext), tonic::Status> {
+        let database = DatabaseName::new(&read_info.database_name).context(InvalidDatabaseName)?;
+
+        let db = self
+            .server
+            .db(&database)
+            .map_err(default_server_error_handler)?;
+
+        let ctx = db.new_query_context(span_ctx);
+        Ok((db, ctx))
+    }
+
+    /// Plans the SQL query described by `read_info`
+    async fn plan_sql(
+        &self,
+        read_info: &ReadInfo,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<(IOxExecutionContext, Arc<dyn ExecutionPlan>), tonic::Status> {
+        let (_, ctx) = self.query_context(read_info, span_ctx)?;
+
+        let physical_plan = Planner::new(&ctx)
+            .sql(&read_info.sql_query)
+            .await
+            .context(Planning)?;
+
+        Ok((ctx, physical_plan))
+    }
+
+    /// Runs the SQL query described by `read_info`, streaming the results
+    ///
+    /// The query is admitted with `priority`, and cancelled after `timeout`,
+    /// if set, rather than the server-wide default query timeout
+    async fn run_sql(
+        &self,
+        read_info: ReadInfo,
+        token: &AuthToken,
+        span_ctx: Option<SpanContext>,
+        timeout: Option<Duration>,
+        priority: QueryPriority,
+    ) -> Result<Response<TonicStream<FlightData>>, tonic::Status> {
+        self.authorizer.authorize(
+            token,
+            Some(read_info.database_name.as_str()),
+            Permission::Read,
+            "flight query",
+        )?;
+
+        let (db, ctx) = self.query_context(&read_info, span_ctx)?;
+        let ctx = ctx.with_request_timeout(timeout).with_priority(priority);
+        db.record_query(&ctx, "sql", &read_info.sql_query);
+
+        let physical_plan = Planner::new(&ctx)
+            .sql(&read_info.sql_query)
+            .await
+            .context(Planning)?;
+
+        let output = GetStream::new(ctx, physical_plan, read_info.database_name).await?;
+
+        Ok(Response::new(Box::pin(output) as TonicStream<FlightData>))
+    }
+
+    /// Returns the description of the statement to run for a Flight SQL
+    /// command, or `None` if the command is a catalog metadata command
+    fn statement_read_info(
+        &self,
+        cmd: &FlightSqlCommand,
+        metadata: &MetadataMap,
+    ) -> Result<Option<ReadInfo>, Error> {
+        let read_info = match cmd {
+            FlightSqlCommand::StatementQuery(cmd) => ReadInfo {
+                database_name: sql::database_name(metadata)?.to_string(),
+                sql_query: cmd.query.clone(),
+            },
+            FlightSqlCommand::PreparedStatementQuery(cmd) => {
+                ReadInfo::from_handle(&cmd.prepared_statement_handle)?
+            }
+            FlightSqlCommand::TicketStatementQuery(cmd) => {
+                ReadInfo::from_handle(&cmd.statement_handle)?
+            }
+            FlightSqlCommand::GetSqlInfo(_)
+            | FlightSqlCommand::GetCatalogs(_)
+            | FlightSqlCommand::GetDbSchemas(_)
+            | FlightSqlCommand::GetTables(_)
+            | FlightSqlCommand::GetTableTypes(_) => return Ok(None),
+        };
+        Ok(Some(read_info))
+    }
+
+    /// Returns the schema of the results of a Flight SQL command
+    async fn flight_sql_schema(
+        &self,
+        cmd: &FlightSqlCommand,
+        metadata: &MetadataMap,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<Schema, tonic::Status> {
+        let token = AuthToken::from_metadata(metadata);
+        match self.statement_read_info(cmd, metadata)? {
+            Some(read_info) => {
+                self.authorizer.authorize(
+                    &token,
+                    Some(read_info.database_name.as_str()),
+                    Permission::Read,
+                    "flight sql schema",
+                )?;
+                let (_, physical_plan) = self.plan_sql(&read_info, span_ctx).await?;
+                Ok(optimize_schema(&physical_plan.schema()))
+            }
+            None => {
+                self.authorizer
+                    .authorize(&token, None, Permission::Read, "flight sql metadata")?;
+                let batch = cmd
+                    .metadata_batch(&self.server)?
+                    .expect("metadata command produces a batch");
+                Ok(batch.schema().as_ref().clone())
+            }
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -146,9 +344,18 @@ impl Flight for FlightService {
 
     async fn get_schema(
         &self,
-        _request: Request<FlightDescriptor>,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<SchemaResult>, tonic::Status> {
-        Err(tonic::Status::unimplemented("Not yet implemented"))
+        let span_ctx = request.extensions().get().cloned();
+        let cmd =
+            FlightSqlCommand::try_decode(&request.get_ref().cmd)?.context(NotFlightSqlCommand)?;
+
+        let schema = self
+            .flight_sql_schema(&cmd, request.metadata(), span_ctx)
+            .await?;
+
+        let options = arrow::ipc::writer::IpcWriteOptions::default();
+        Ok(Response::new(SchemaAsIpc::new(&schema, &options).into()))
     }
 
     async fn do_get(
@@ -156,6 +363,38 @@ impl Flight for FlightService {
         request: Request<Ticket>,
     ) -> Result<Response<Self::DoGetStream>, tonic::Status> {
         let span_ctx = request.extensions().get().cloned();
+        let token = AuthToken::from_metadata(request.metadata());
+        let timeout = grpc_timeout(request.metadata());
+        let priority = query_priority(request.metadata())?;
+
+        if let Some(cmd) = FlightSqlCommand::try_decode(&request.get_ref().ticket)? {
+            if let Some(read_info) = self.statement_read_info(&cmd, request.metadata())? {
+                return self
+                    .run_sql(read_info, &token, span_ctx, timeout, priority)
+                    .await;
+            }
+
+            self.authorizer
+                .authorize(&token, None, Permission::Read, "flight sql metadata")?;
+            let batch = cmd
+                .metadata_batch(&self.server)?
+                .expect("metadata command produces a batch");
+
+            let options = arrow::ipc::writer::IpcWriteOptions::default();
+            let schema_flight_data: FlightData = SchemaAsIpc::new(&batch.schema(), &options).into();
+            let (flight_dictionaries, flight_batch) =
+                arrow_flight::utils::flight_data_from_arrow_batch(&batch, &options);
+
+            let flight_data: Vec<_> = std::iter::once(schema_flight_data)
+                .chain(flight_dictionaries)
+                .chain(std::iter::once(flight_batch))
+                .map(Ok)
+                .collect();
+
+            let output = futures::stream::iter(flight_data);
+            return Ok(Response::new(Box::pin(output) as Self::DoGetStream));
+        }
+
         let ticket = request.into_inner();
         let json_str = String::from_utf8(ticket.ticket.to_vec()).context(InvalidTicket {
             ticket: ticket.ticket,
@@ -164,25 +403,8 @@ impl Flight for FlightService {
         let read_info: ReadInfo =
             serde_json::from_str(&json_str).context(InvalidQuery { query: &json_str })?;
 
-        let database = DatabaseName::new(&read_info.database_name).context(InvalidDatabaseName)?;
-
-        let db = self
-            .server
-            .db(&database)
-            .map_err(default_server_error_handler)?;
-
-        db.record_query("sql", &read_info.sql_query);
-
-        let ctx = db.new_query_context(span_ctx);
-
-        let physical_plan = Planner::new(&ctx)
-            .sql(&read_info.sql_query)
+        self.run_sql(read_info, &token, span_ctx, timeout, priority)
             .await
-            .context(Planning)?;
-
-        let output = GetStream::new(ctx, physical_plan, read_info.database_name).await?;
-
-        Ok(Response::new(Box::pin(output) as Self::DoGetStream))
     }
 
     async fn handshake(
@@ -198,18 +420,50 @@ impl Flight for FlightService {
         Ok(Response::new(Box::pin(output) as Self::HandshakeStream))
     }
 
+    /// Every flight of IOx is described by the Flight SQL command or query
+    /// of a `get_flight_info` request, there are no standing flights to list
     async fn list_flights(
         &self,
         _request: Request<Criteria>,
     ) -> Result<Response<Self::ListFlightsStream>, tonic::Status> {
-        Err(tonic::Status::unimplemented("Not yet implemented"))
+        let output = futures::stream::empty();
+        Ok(Response::new(Box::pin(output) as Self::ListFlightsStream))
     }
 
     async fn get_flight_info(
         &self,
-        _request: Request<FlightDescriptor>,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, tonic::Status> {
-        Err(tonic::Status::unimplemented("Not yet implemented"))
+        let span_ctx = request.extensions().get().cloned();
+        let cmd =
+            FlightSqlCommand::try_decode(&request.get_ref().cmd)?.context(NotFlightSqlCommand)?;
+
+        let schema = self
+            .flight_sql_schema(&cmd, request.metadata(), span_ctx)
+            .await?;
+
+        // Statements are fetched with a ticket referencing the query to
+        // run, metadata commands are fetched with the command itself
+        let ticket = match self.statement_read_info(&cmd, request.metadata())? {
+            Some(read_info) => FlightSqlCommand::TicketStatementQuery(TicketStatementQuery {
+                statement_handle: read_info.to_handle(),
+            })
+            .to_bytes(),
+            None => cmd.to_bytes(),
+        };
+
+        let info = FlightInfo {
+            schema: sql::ipc_schema(&schema),
+            flight_descriptor: Some(request.into_inner()),
+            endpoint: vec![FlightEndpoint {
+                ticket: Some(Ticket { ticket }),
+                location: vec![],
+            }],
+            total_records: -1,
+            total_bytes: -1,
+        };
+
+        Ok(Response::new(info))
     }
 
     async fn do_put(
@@ -221,16 +475,80 @@ impl Flight for FlightService {
 
     async fn do_action(
         &self,
-        _request: Request<Action>,
+        request: Request<Action>,
     ) -> Result<Response<Self::DoActionStream>, tonic::Status> {
-        Err(tonic::Status::unimplemented("Not yet implemented"))
+        let span_ctx = request.extensions().get().cloned();
+        let action = request.get_ref();
+
+        let results = match action.r#type.as_str() {
+            CREATE_PREPARED_STATEMENT => {
+                let cmd = ActionCreatePreparedStatementRequest::from_any_bytes(&action.body)?;
+
+                // Prepared statements are stateless: the handle contains the
+                // query itself, which is planned to validate it and
+                // determine its schema
+                let read_info = ReadInfo {
+                    database_name: sql::database_name(request.metadata())?.to_string(),
+                    sql_query: cmd.query,
+                };
+                self.authorizer.authorize(
+                    &AuthToken::from_metadata(request.metadata()),
+                    Some(read_info.database_name.as_str()),
+                    Permission::Read,
+                    "flight prepare statement",
+                )?;
+                let (_, physical_plan) = self.plan_sql(&read_info, span_ctx).await?;
+                let schema = optimize_schema(&physical_plan.schema());
+
+                let result = ActionCreatePreparedStatementResult {
+                    prepared_statement_handle: read_info.to_handle(),
+                    dataset_schema: sql::ipc_schema(&schema),
+                    parameter_schema: vec![],
+                };
+
+                vec![arrow_flight::Result {
+                    body: prost::Message::encode_to_vec(&result.to_any()),
+                }]
+            }
+            CLOSE_PREPARED_STATEMENT => {
+                let cmd = ActionClosePreparedStatementRequest::from_any_bytes(&action.body)?;
+
+                // nothing to release, but reject handles that were not issued by IOx
+                ReadInfo::from_handle(&cmd.prepared_statement_handle)?;
+                vec![]
+            }
+            action_type => {
+                return Err(UnsupportedAction { action_type }.build().into());
+            }
+        };
+
+        let output = futures::stream::iter(results.into_iter().map(Ok));
+        Ok(Response::new(Box::pin(output) as Self::DoActionStream))
     }
 
     async fn list_actions(
         &self,
         _request: Request<Empty>,
     ) -> Result<Response<Self::ListActionsStream>, tonic::Status> {
-        Err(tonic::Status::unimplemented("Not yet implemented"))
+        let actions = vec![
+            ActionType {
+                r#type: CREATE_PREPARED_STATEMENT.to_string(),
+                description: "Creates a reusable prepared statement resource on the server. \
+                    Request Message: ActionCreatePreparedStatementRequest \
+                    Response Message: ActionCreatePreparedStatementResult"
+                    .to_string(),
+            },
+            ActionType {
+                r#type: CLOSE_PREPARED_STATEMENT.to_string(),
+                description: "Closes a reusable prepared statement resource on the server. \
+                    Request Message: ActionClosePreparedStatementRequest \
+                    Response Message: N/A"
+                    .to_string(),
+            },
+        ];
+
+        let output = futures::stream::iter(actions.into_iter().map(Ok));
+        Ok(Response::new(Box::pin(output) as Self::ListActionsStream))
     }
 
     async fn do_exchange(
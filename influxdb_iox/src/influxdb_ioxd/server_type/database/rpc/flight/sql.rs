@@ -0,0 +1,682 @@
+//! Implements the Arrow Flight SQL protocol on top of the IOx Flight service
+//!
+//! Flight SQL commands are sent as `google.protobuf.Any` encoded messages in
+//! the `cmd` of a `FlightDescriptor`, in the `ticket` of a `Ticket`, or in the
+//! `body` of an `Action`.
+//!
+//! IOx maps the Flight SQL concepts as follows:
+//!
+//! * A Flight SQL catalog is an IOx database
+//! * A Flight SQL database schema is one of the schemas of a database (`iox`
+//!   for user tables and `system` for the system tables)
+//!
+//! SQL statements (prepared or not) are run against the database named in the
+//! [`DATABASE_HEADER`] request header.
+//!
+//! `CommandGetSqlInfo` reports the server name, version and that the server
+//! is read-only, which JDBC and other clients request when connecting.
+use std::sync::Arc;
+
+use arrow::{
+    array::{
+        ArrayRef, BinaryArray, BooleanArray, Int32Array, Int64Array, StringArray, UInt32Array,
+        UnionArray,
+    },
+    buffer::Buffer,
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    ipc::writer::IpcWriteOptions,
+    record_batch::RecordBatch,
+};
+use arrow_flight::{SchemaAsIpc, SchemaResult};
+use bytes::Bytes;
+use data_types::DatabaseName;
+use datafusion::catalog::catalog::CatalogProvider;
+use generated_types::{
+    arrow::flight::protocol::sql::{
+        ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest,
+        ActionCreatePreparedStatementResult, CommandGetCatalogs, CommandGetDbSchemas,
+        CommandGetSqlInfo, CommandGetTableTypes, CommandGetTables, CommandPreparedStatementQuery,
+        CommandStatementQuery, TicketStatementQuery,
+    },
+    google::protobuf::Any,
+    protobuf_type_url, protobuf_type_url_eq,
+};
+use prost::Message;
+use server::{db::SYSTEM_SCHEMA, Db, Server};
+use snafu::{OptionExt, ResultExt};
+use tonic::metadata::MetadataMap;
+
+use super::{
+    InvalidDatabaseName, InvalidFlightSqlCommand, InvalidHandle, InvalidRecordBatch, ListDatabases,
+    MissingDatabaseHeader, ReadInfo, Result, UnsupportedFlightSqlCommand,
+};
+
+/// Request header naming the database SQL statements are run against
+pub const DATABASE_HEADER: &str = "iox-database";
+
+/// Protobuf package of the Flight SQL messages
+const FLIGHT_SQL_PACKAGE: &str = "arrow.flight.protocol.sql";
+
+/// Action type to create a prepared statement
+pub const CREATE_PREPARED_STATEMENT: &str = "CreatePreparedStatement";
+
+/// Action type to close a prepared statement
+pub const CLOSE_PREPARED_STATEMENT: &str = "ClosePreparedStatement";
+
+/// Table type reported for user tables
+const TABLE_TYPE_BASE: &str = "BASE TABLE";
+
+/// Table type reported for system tables
+const TABLE_TYPE_SYSTEM: &str = "SYSTEM TABLE";
+
+/// `SqlInfo` id of the server name
+const SQL_INFO_SERVER_NAME: u32 = 0;
+
+/// `SqlInfo` id of the server version
+const SQL_INFO_SERVER_VERSION: u32 = 1;
+
+/// `SqlInfo` id of whether the server is read-only
+const SQL_INFO_SERVER_READ_ONLY: u32 = 3;
+
+/// A Flight SQL protocol message, which is sent wrapped in a
+/// `google.protobuf.Any`
+pub trait FlightSqlMessage: Message + Default {
+    /// The fully qualified protobuf message name
+    const TYPE_NAME: &'static str;
+
+    /// Wraps this message into a `google.protobuf.Any`
+    fn to_any(&self) -> Any {
+        Any {
+            type_url: protobuf_type_url(Self::TYPE_NAME),
+            value: self.encode_to_vec().into(),
+        }
+    }
+
+    /// Unwraps this message from a `google.protobuf.Any`, returning
+    /// `None` if `any` contains a different message type
+    fn from_any(any: &Any) -> Result<Option<Self>> {
+        if !protobuf_type_url_eq(&any.type_url, Self::TYPE_NAME) {
+            return Ok(None);
+        }
+
+        Self::decode(Bytes::clone(&any.value))
+            .map(Some)
+            .context(InvalidFlightSqlCommand)
+    }
+
+    /// Decodes this message from the bytes of an encoded
+    /// `google.protobuf.Any`, such as the body of an `Action`
+    fn from_any_bytes(bytes: &[u8]) -> Result<Self> {
+        let any = Any::decode(bytes).context(InvalidFlightSqlCommand)?;
+        Self::from_any(&any)?.context(UnsupportedFlightSqlCommand {
+            type_url: any.type_url,
+        })
+    }
+}
+
+macro_rules! impl_flight_sql_message {
+    ($($message:ident),* $(,)?) => {
+        $(
+            impl FlightSqlMessage for $message {
+                const TYPE_NAME: &'static str =
+                    concat!("arrow.flight.protocol.sql.", stringify!($message));
+            }
+        )*
+    };
+}
+
+impl_flight_sql_message!(
+    ActionClosePreparedStatementRequest,
+    ActionCreatePreparedStatementRequest,
+    ActionCreatePreparedStatementResult,
+    CommandGetCatalogs,
+    CommandGetDbSchemas,
+    CommandGetSqlInfo,
+    CommandGetTableTypes,
+    CommandGetTables,
+    CommandPreparedStatementQuery,
+    CommandStatementQuery,
+    TicketStatementQuery,
+);
+
+/// A decoded Flight SQL command
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlightSqlCommand {
+    /// Run an ad-hoc SQL statement
+    StatementQuery(CommandStatementQuery),
+    /// Run a previously prepared SQL statement
+    PreparedStatementQuery(CommandPreparedStatementQuery),
+    /// Fetch the results of a statement planned by `get_flight_info`
+    TicketStatementQuery(TicketStatementQuery),
+    /// Describe the SQL server
+    GetSqlInfo(CommandGetSqlInfo),
+    /// List the catalogs (databases)
+    GetCatalogs(CommandGetCatalogs),
+    /// List the database schemas
+    GetDbSchemas(CommandGetDbSchemas),
+    /// List the tables
+    GetTables(CommandGetTables),
+    /// List the table types
+    GetTableTypes(CommandGetTableTypes),
+}
+
+impl FlightSqlCommand {
+    /// Decodes a command from the bytes of an encoded `google.protobuf.Any`.
+    ///
+    /// Returns `None` if `msg` is not a Flight SQL message at all, so that
+    /// callers can fall back to other encodings.
+    pub fn try_decode(msg: &[u8]) -> Result<Option<Self>> {
+        let any = match Any::decode(msg) {
+            Ok(any) => any,
+            Err(_) => return Ok(None),
+        };
+
+        let cmd = if let Some(cmd) = CommandStatementQuery::from_any(&any)? {
+            Self::StatementQuery(cmd)
+        } else if let Some(cmd) = CommandPreparedStatementQuery::from_any(&any)? {
+            Self::PreparedStatementQuery(cmd)
+        } else if let Some(cmd) = TicketStatementQuery::from_any(&any)? {
+            Self::TicketStatementQuery(cmd)
+        } else if let Some(cmd) = CommandGetCatalogs::from_any(&any)? {
+            Self::GetCatalogs(cmd)
+        } else if let Some(cmd) = CommandGetDbSchemas::from_any(&any)? {
+            Self::GetDbSchemas(cmd)
+        } else if let Some(cmd) = CommandGetTables::from_any(&any)? {
+            Self::GetTables(cmd)
+        } else if let Some(cmd) = CommandGetTableTypes::from_any(&any)? {
+            Self::GetTableTypes(cmd)
+        } else if let Some(cmd) = CommandGetSqlInfo::from_any(&any)? {
+            Self::GetSqlInfo(cmd)
+        } else if any.type_url.contains(FLIGHT_SQL_PACKAGE) {
+            return UnsupportedFlightSqlCommand {
+                type_url: any.type_url,
+            }
+            .fail();
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(cmd))
+    }
+
+    /// Encodes this command as the bytes of a `google.protobuf.Any`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let any = match self {
+            Self::StatementQuery(cmd) => cmd.to_any(),
+            Self::PreparedStatementQuery(cmd) => cmd.to_any(),
+            Self::TicketStatementQuery(cmd) => cmd.to_any(),
+            Self::GetSqlInfo(cmd) => cmd.to_any(),
+            Self::GetCatalogs(cmd) => cmd.to_any(),
+            Self::GetDbSchemas(cmd) => cmd.to_any(),
+            Self::GetTables(cmd) => cmd.to_any(),
+            Self::GetTableTypes(cmd) => cmd.to_any(),
+        };
+        any.encode_to_vec()
+    }
+
+    /// Evaluates a catalog metadata command against `server`.
+    ///
+    /// Returns `None` for commands that run SQL statements
+    pub fn metadata_batch(&self, server: &Server) -> Result<Option<RecordBatch>> {
+        let batch = match self {
+            Self::StatementQuery(_)
+            | Self::PreparedStatementQuery(_)
+            | Self::TicketStatementQuery(_) => return Ok(None),
+            Self::GetSqlInfo(cmd) => get_sql_info(cmd)?,
+            Self::GetCatalogs(_) => get_catalogs(server)?,
+            Self::GetDbSchemas(cmd) => get_db_schemas(server, cmd)?,
+            Self::GetTables(cmd) => get_tables(server, cmd)?,
+            Self::GetTableTypes(_) => get_table_types()?,
+        };
+        Ok(Some(batch))
+    }
+}
+
+impl ReadInfo {
+    /// Encodes this query as an opaque statement handle
+    pub(super) fn to_handle(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("ReadInfo can be serialized")
+    }
+
+    /// Decodes a query from a handle created by [`Self::to_handle`]
+    pub(super) fn from_handle(handle: &[u8]) -> Result<Self> {
+        serde_json::from_slice(handle).context(InvalidHandle)
+    }
+}
+
+/// Returns the database named in the [`DATABASE_HEADER`] of a request
+pub fn database_name(metadata: &MetadataMap) -> Result<DatabaseName<'static>> {
+    let name = metadata
+        .get(DATABASE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .context(MissingDatabaseHeader {
+            header: DATABASE_HEADER,
+        })?;
+
+    DatabaseName::new(name.to_string()).context(InvalidDatabaseName)
+}
+
+/// Serializes `schema` as an IPC message, as used in `FlightInfo` and
+/// `ActionCreatePreparedStatementResult`
+pub fn ipc_schema(schema: &Schema) -> Vec<u8> {
+    let options = IpcWriteOptions::default();
+    SchemaResult::from(SchemaAsIpc::new(schema, &options)).schema
+}
+
+/// Returns `(name, db)` of all initialized databases, sorted by name
+fn catalogs(server: &Server) -> Result<Vec<(String, Arc<Db>)>> {
+    Ok(server
+        .databases()
+        .context(ListDatabases)?
+        .into_iter()
+        .filter(|database| database.is_active())
+        .filter_map(|database| {
+            let db = database.initialized_db()?;
+            Some((database.config().name.to_string(), db))
+        })
+        .collect())
+}
+
+/// Returns the requested `SqlInfo` items, or all of them if none are
+/// requested. Their values are a dense union of the value types defined by
+/// Flight SQL, of which IOx only uses strings and booleans.
+fn get_sql_info(cmd: &CommandGetSqlInfo) -> Result<RecordBatch> {
+    enum Value {
+        String(&'static str),
+        Bool(bool),
+    }
+
+    let info = [
+        (SQL_INFO_SERVER_NAME, Value::String("InfluxDB IOx")),
+        (
+            SQL_INFO_SERVER_VERSION,
+            Value::String(env!("CARGO_PKG_VERSION")),
+        ),
+        // Flight SQL statements can only query
+        (SQL_INFO_SERVER_READ_ONLY, Value::Bool(true)),
+    ];
+
+    let mut info_names = vec![];
+    let mut type_ids: Vec<i8> = vec![];
+    let mut offsets: Vec<i32> = vec![];
+    let mut strings = vec![];
+    let mut bools = vec![];
+    for (info_name, value) in info {
+        if !cmd.info.is_empty() && !cmd.info.contains(&info_name) {
+            continue;
+        }
+
+        info_names.push(info_name);
+        match value {
+            Value::String(value) => {
+                type_ids.push(0);
+                offsets.push(strings.len() as i32);
+                strings.push(value);
+            }
+            Value::Bool(value) => {
+                type_ids.push(1);
+                offsets.push(bools.len() as i32);
+                bools.push(value);
+            }
+        }
+    }
+
+    let values = UnionArray::try_new(
+        Buffer::from_slice_ref(&type_ids),
+        Some(Buffer::from_slice_ref(&offsets)),
+        vec![
+            (
+                Field::new("string_value", DataType::Utf8, true),
+                Arc::new(StringArray::from(strings)) as ArrayRef,
+            ),
+            (
+                Field::new("bool_value", DataType::Boolean, true),
+                Arc::new(BooleanArray::from(bools)),
+            ),
+            (
+                Field::new("bigint_value", DataType::Int64, true),
+                Arc::new(Int64Array::from(Vec::<i64>::new())),
+            ),
+            (
+                Field::new("int32_bitmask", DataType::Int32, true),
+                Arc::new(Int32Array::from(Vec::<i32>::new())),
+            ),
+        ],
+        None,
+    )
+    .context(InvalidRecordBatch)?;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("info_name", DataType::UInt32, false),
+        Field::new("value", values.data_type().clone(), false),
+    ]));
+
+    make_batch(
+        schema,
+        vec![Arc::new(UInt32Array::from(info_names)), Arc::new(values)],
+    )
+}
+
+fn get_catalogs(server: &Server) -> Result<RecordBatch> {
+    let names: Vec<_> = catalogs(server)?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+
+    let schema = Arc::new(Schema::new(vec![Field::new(
+        "catalog_name",
+        DataType::Utf8,
+        false,
+    )]));
+
+    make_batch(schema, vec![Arc::new(StringArray::from(str_refs(&names)))])
+}
+
+fn get_db_schemas(server: &Server, cmd: &CommandGetDbSchemas) -> Result<RecordBatch> {
+    let mut catalog_names = vec![];
+    let mut schema_names = vec![];
+
+    for (catalog_name, db) in catalogs(server)? {
+        if !cmd.catalog.is_empty() && cmd.catalog != catalog_name {
+            continue;
+        }
+
+        for schema_name in sorted_schema_names(db.as_ref()) {
+            if like(&schema_name, &cmd.db_schema_filter_pattern) {
+                catalog_names.push(catalog_name.clone());
+                schema_names.push(schema_name);
+            }
+        }
+    }
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("catalog_name", DataType::Utf8, true),
+        Field::new("db_schema_name", DataType::Utf8, false),
+    ]));
+
+    make_batch(
+        schema,
+        vec![
+            Arc::new(StringArray::from(str_refs(&catalog_names))),
+            Arc::new(StringArray::from(str_refs(&schema_names))),
+        ],
+    )
+}
+
+fn get_tables(server: &Server, cmd: &CommandGetTables) -> Result<RecordBatch> {
+    let mut catalog_names = vec![];
+    let mut schema_names = vec![];
+    let mut table_names = vec![];
+    let mut table_types = vec![];
+    let mut table_schemas = vec![];
+
+    for (catalog_name, db) in catalogs(server)? {
+        if !cmd.catalog.is_empty() && cmd.catalog != catalog_name {
+            continue;
+        }
+
+        for schema_name in sorted_schema_names(db.as_ref()) {
+            if !like(&schema_name, &cmd.db_schema_filter_pattern) {
+                continue;
+            }
+
+            let table_type = table_type(&schema_name);
+            if !cmd.table_types.is_empty() && !cmd.table_types.iter().any(|t| t == table_type) {
+                continue;
+            }
+
+            let schema_provider = match db.schema(&schema_name) {
+                Some(schema_provider) => schema_provider,
+                None => continue,
+            };
+
+            let mut names = schema_provider.table_names();
+            names.sort();
+
+            for table_name in names {
+                if !like(&table_name, &cmd.table_name_filter_pattern) {
+                    continue;
+                }
+
+                if cmd.include_schema {
+                    // table may have been dropped concurrently
+                    let table = match schema_provider.table(&table_name) {
+                        Some(table) => table,
+                        None => continue,
+                    };
+                    table_schemas.push(ipc_schema(&table.schema()));
+                }
+
+                catalog_names.push(catalog_name.clone());
+                schema_names.push(schema_name.clone());
+                table_names.push(table_name);
+                table_types.push(table_type);
+            }
+        }
+    }
+
+    let mut fields = vec![
+        Field::new("catalog_name", DataType::Utf8, true),
+        Field::new("db_schema_name", DataType::Utf8, true),
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("table_type", DataType::Utf8, false),
+    ];
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(str_refs(&catalog_names))),
+        Arc::new(StringArray::from(str_refs(&schema_names))),
+        Arc::new(StringArray::from(str_refs(&table_names))),
+        Arc::new(StringArray::from(table_types)),
+    ];
+
+    if cmd.include_schema {
+        fields.push(Field::new("table_schema", DataType::Binary, false));
+        let table_schemas: Vec<&[u8]> = table_schemas.iter().map(|s| s.as_slice()).collect();
+        columns.push(Arc::new(BinaryArray::from(table_schemas)));
+    }
+
+    make_batch(Arc::new(Schema::new(fields)), columns)
+}
+
+fn get_table_types() -> Result<RecordBatch> {
+    let schema = Arc::new(Schema::new(vec![Field::new(
+        "table_type",
+        DataType::Utf8,
+        false,
+    )]));
+
+    make_batch(
+        schema,
+        vec![Arc::new(StringArray::from(vec![
+            TABLE_TYPE_BASE,
+            TABLE_TYPE_SYSTEM,
+        ]))],
+    )
+}
+
+fn sorted_schema_names(catalog: &dyn CatalogProvider) -> Vec<String> {
+    let mut names = catalog.schema_names();
+    names.sort();
+    names
+}
+
+/// Returns the Flight SQL table type of the tables in `schema_name`
+fn table_type(schema_name: &str) -> &'static str {
+    if schema_name == SYSTEM_SCHEMA {
+        TABLE_TYPE_SYSTEM
+    } else {
+        TABLE_TYPE_BASE
+    }
+}
+
+fn str_refs(strings: &[String]) -> Vec<&str> {
+    strings.iter().map(|s| s.as_str()).collect()
+}
+
+fn make_batch(schema: SchemaRef, columns: Vec<ArrayRef>) -> Result<RecordBatch> {
+    RecordBatch::try_new(schema, columns).context(InvalidRecordBatch)
+}
+
+/// Returns true if `s` matches the SQL `LIKE` style `pattern`, where `%`
+/// matches any (possibly empty) substring and `_` matches any single
+/// character. An empty pattern matches everything.
+fn like(s: &str, pattern: &str) -> bool {
+    if pattern.is_empty() {
+        return true;
+    }
+
+    let s: Vec<char> = s.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    // classic wildcard matching with backtracking to the last `%`
+    let (mut s_idx, mut p_idx) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s_idx < s.len() {
+        match pattern.get(p_idx) {
+            Some('%') => {
+                backtrack = Some((p_idx, s_idx));
+                p_idx += 1;
+            }
+            Some(c) if *c == '_' || *c == s[s_idx] => {
+                s_idx += 1;
+                p_idx += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_s)) => {
+                    p_idx = star_p + 1;
+                    s_idx = star_s + 1;
+                    backtrack = Some((star_p, star_s + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p_idx..].iter().all(|c| *c == '%')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Error;
+    use super::*;
+
+    #[test]
+    fn test_like() {
+        assert!(like("cpu", ""));
+        assert!(like("cpu", "cpu"));
+        assert!(like("cpu", "%"));
+        assert!(like("cpu", "c%"));
+        assert!(like("cpu", "%u"));
+        assert!(like("cpu", "c_u"));
+        assert!(like("cpu", "%p%"));
+        assert!(like("cpu_load", "cpu%load"));
+        assert!(like("cpu", "cpu%"));
+
+        assert!(!like("cpu", "mem"));
+        assert!(!like("cpu", "c_"));
+        assert!(!like("cpu", "_cpu"));
+        assert!(!like("cpu", "%x%"));
+        assert!(!like("", "_"));
+    }
+
+    #[test]
+    fn test_command_roundtrip() {
+        let cmd = FlightSqlCommand::GetTables(CommandGetTables {
+            catalog: "my_db".to_string(),
+            db_schema_filter_pattern: "io%".to_string(),
+            table_name_filter_pattern: "".to_string(),
+            table_types: vec![TABLE_TYPE_BASE.to_string()],
+            include_schema: true,
+        });
+
+        let decoded = FlightSqlCommand::try_decode(&cmd.to_bytes()).unwrap();
+        assert_eq!(decoded, Some(cmd));
+    }
+
+    #[test]
+    fn test_decode_non_flight_sql() {
+        // JSON tickets used by the native IOx Flight API are not Flight SQL commands
+        let ticket = br#"{"database_name":"my_db","sql_query":"select 1"}"#;
+        assert_eq!(FlightSqlCommand::try_decode(ticket).unwrap(), None);
+
+        // Neither are Any messages of other types
+        let any = Any {
+            type_url: protobuf_type_url("google.protobuf.Empty"),
+            value: Bytes::new(),
+        };
+        assert_eq!(
+            FlightSqlCommand::try_decode(&any.encode_to_vec()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_unsupported_flight_sql() {
+        let any = Any {
+            type_url: protobuf_type_url("arrow.flight.protocol.sql.CommandGetPrimaryKeys"),
+            value: Bytes::new(),
+        };
+        let err = FlightSqlCommand::try_decode(&any.encode_to_vec()).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedFlightSqlCommand { .. }));
+    }
+
+    #[test]
+    fn test_handle_roundtrip() {
+        let read_info = ReadInfo {
+            database_name: "my_db".to_string(),
+            sql_query: "select * from cpu".to_string(),
+        };
+
+        let decoded = ReadInfo::from_handle(&read_info.to_handle()).unwrap();
+        assert_eq!(decoded.database_name, read_info.database_name);
+        assert_eq!(decoded.sql_query, read_info.sql_query);
+
+        assert!(matches!(
+            ReadInfo::from_handle(b"not json"),
+            Err(Error::InvalidHandle { .. })
+        ));
+    }
+
+    #[test]
+    fn test_sql_info() {
+        let batch = get_sql_info(&CommandGetSqlInfo { info: vec![] }).unwrap();
+        assert_eq!(batch.num_rows(), 3);
+
+        let info_names = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<UInt32Array>()
+            .unwrap();
+        assert_eq!(
+            info_names.values(),
+            &[
+                SQL_INFO_SERVER_NAME,
+                SQL_INFO_SERVER_VERSION,
+                SQL_INFO_SERVER_READ_ONLY
+            ]
+        );
+
+        let values = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<UnionArray>()
+            .unwrap();
+        let read_only = values.value(2);
+        let read_only = read_only.as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert!(read_only.value(0));
+
+        // only the requested items are returned
+        let batch = get_sql_info(&CommandGetSqlInfo {
+            info: vec![SQL_INFO_SERVER_READ_ONLY, 1000],
+        })
+        .unwrap();
+        assert_eq!(batch.num_rows(), 1);
+    }
+
+    #[test]
+    fn test_table_types() {
+        let batch = get_table_types().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.schema().field(0).name(), "table_type");
+    }
+}
@@ -12,11 +12,13 @@ use uuid::Uuid;
 struct ManagementService {
     application: Arc<ApplicationState>,
     server: Arc<Server>,
+    authorizer: Arc<Authorizer>,
 }
 
 use super::error::{
     default_database_error_handler, default_db_error_handler, default_server_error_handler,
 };
+use crate::influxdb_ioxd::auth::{AuthToken, Authorizer, Permission};
 
 #[tonic::async_trait]
 impl management_service_server::ManagementService for ManagementService {
@@ -24,6 +26,10 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<ListDatabasesRequest>,
     ) -> Result<Response<ListDatabasesResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
+        self.authorizer
+            .authorize(&token, None, Permission::Admin, "list_databases")?;
+
         let ListDatabasesRequest { omit_defaults } = request.into_inner();
 
         let rules = self
@@ -42,12 +48,19 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<GetDatabaseRequest>,
     ) -> Result<Response<GetDatabaseResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let GetDatabaseRequest {
             name,
             omit_defaults,
         } = request.into_inner();
 
         let name = DatabaseName::new(name).scope("name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(name.as_str()),
+            Permission::Admin,
+            "get_database",
+        )?;
         let database = self
             .server
             .active_database(&name)
@@ -70,6 +83,10 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<CreateDatabaseRequest>,
     ) -> Result<Response<CreateDatabaseResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
+        self.authorizer
+            .authorize(&token, None, Permission::Admin, "create_database")?;
+
         let rules: DatabaseRules = request
             .into_inner()
             .rules
@@ -97,6 +114,7 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<UpdateDatabaseRequest>,
     ) -> Result<Response<UpdateDatabaseResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let rules: DatabaseRules = request
             .into_inner()
             .rules
@@ -104,6 +122,12 @@ impl management_service_server::ManagementService for ManagementService {
 
         let provided_rules =
             ProvidedDatabaseRules::new_rules(rules).map_err(|e| e.scope("rules"))?;
+        self.authorizer.authorize(
+            &token,
+            Some(provided_rules.db_name().as_str()),
+            Permission::Admin,
+            "update_database",
+        )?;
 
         let updated_rules = self
             .server
@@ -116,13 +140,44 @@ impl management_service_server::ManagementService for ManagementService {
         }))
     }
 
+    async fn lock_database_schema(
+        &self,
+        request: Request<LockDatabaseSchemaRequest>,
+    ) -> Result<Response<LockDatabaseSchemaResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
+        let db_name = DatabaseName::new(request.into_inner().db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "lock_database_schema",
+        )?;
+
+        let updated_rules = self
+            .server
+            .lock_db_schema(&db_name)
+            .await
+            .map_err(default_server_error_handler)?;
+
+        Ok(Response::new(LockDatabaseSchemaResponse {
+            rules: Some(updated_rules.rules().as_ref().clone().into()),
+        }))
+    }
+
     async fn release_database(
         &self,
         request: Request<ReleaseDatabaseRequest>,
     ) -> Result<Response<ReleaseDatabaseResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let ReleaseDatabaseRequest { db_name, uuid } = request.into_inner();
 
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "release_database",
+        )?;
         let uuid = if uuid.is_empty() {
             None
         } else {
@@ -144,6 +199,10 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<ClaimDatabaseRequest>,
     ) -> Result<Response<ClaimDatabaseResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
+        self.authorizer
+            .authorize(&token, None, Permission::Admin, "claim_database")?;
+
         let ClaimDatabaseRequest { uuid, force } = request.into_inner();
 
         let uuid = Uuid::from_slice(&uuid).scope("uuid")?;
@@ -161,8 +220,12 @@ impl management_service_server::ManagementService for ManagementService {
 
     async fn list_detailed_databases(
         &self,
-        _: Request<ListDetailedDatabasesRequest>,
+        request: Request<ListDetailedDatabasesRequest>,
     ) -> Result<Response<ListDetailedDatabasesResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
+        self.authorizer
+            .authorize(&token, None, Permission::Admin, "list_detailed_databases")?;
+
         let databases = self
             .server
             .list_detailed_databases()
@@ -179,7 +242,14 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<ListChunksRequest>,
     ) -> Result<Response<ListChunksResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let db_name = DatabaseName::new(request.into_inner().db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "list_chunks",
+        )?;
         let db = self
             .server
             .db(&db_name)
@@ -202,6 +272,10 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<CreateDummyJobRequest>,
     ) -> Result<Response<CreateDummyJobResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
+        self.authorizer
+            .authorize(&token, None, Permission::Admin, "create_dummy_job")?;
+
         let request = request.into_inner();
         let tracker = self
             .application
@@ -215,8 +289,15 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<ListPartitionsRequest>,
     ) -> Result<Response<ListPartitionsResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let ListPartitionsRequest { db_name } = request.into_inner();
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "list_partitions",
+        )?;
 
         let db = self
             .server
@@ -236,11 +317,18 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<GetPartitionRequest>,
     ) -> Result<Response<GetPartitionResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let GetPartitionRequest {
             db_name,
             partition_key,
         } = request.into_inner();
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "get_partition",
+        )?;
         let db = self
             .server
             .db(&db_name)
@@ -262,11 +350,18 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<ListPartitionChunksRequest>,
     ) -> Result<Response<ListPartitionChunksResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let ListPartitionChunksRequest {
             db_name,
             partition_key,
         } = request.into_inner();
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "list_partition_chunks",
+        )?;
         let db = self
             .server
             .db(&db_name)
@@ -285,12 +380,19 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<NewPartitionChunkRequest>,
     ) -> Result<Response<NewPartitionChunkResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let NewPartitionChunkRequest {
             db_name,
             partition_key,
             table_name,
         } = request.into_inner();
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "new_partition_chunk",
+        )?;
         let db = self
             .server
             .db(&db_name)
@@ -307,6 +409,7 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<ClosePartitionChunkRequest>,
     ) -> Result<Response<ClosePartitionChunkResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let ClosePartitionChunkRequest {
             db_name,
             partition_key,
@@ -316,6 +419,12 @@ impl management_service_server::ManagementService for ManagementService {
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "close_partition_chunk",
+        )?;
 
         let chunk_id = ChunkId::try_from(chunk_id).scope("chunk_id")?;
 
@@ -333,6 +442,7 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: tonic::Request<UnloadPartitionChunkRequest>,
     ) -> Result<tonic::Response<UnloadPartitionChunkResponse>, tonic::Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let UnloadPartitionChunkRequest {
             db_name,
             partition_key,
@@ -342,6 +452,12 @@ impl management_service_server::ManagementService for ManagementService {
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "unload_partition_chunk",
+        )?;
         let db = self
             .server
             .db(&db_name)
@@ -359,6 +475,7 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: tonic::Request<LoadPartitionChunkRequest>,
     ) -> Result<tonic::Response<LoadPartitionChunkResponse>, tonic::Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let LoadPartitionChunkRequest {
             db_name,
             partition_key,
@@ -368,6 +485,12 @@ impl management_service_server::ManagementService for ManagementService {
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "load_partition_chunk",
+        )?;
         let chunk_id = ChunkId::try_from(chunk_id).scope("chunk_id")?;
 
         let db = self
@@ -386,8 +509,12 @@ impl management_service_server::ManagementService for ManagementService {
 
     async fn get_server_status(
         &self,
-        _request: Request<GetServerStatusRequest>,
+        request: Request<GetServerStatusRequest>,
     ) -> Result<Response<GetServerStatusResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
+        self.authorizer
+            .authorize(&token, None, Permission::Admin, "get_server_status")?;
+
         let initialized = self.server.initialized();
 
         // Purposefully suppress error from server::Databases as don't want
@@ -427,10 +554,17 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<WipePreservedCatalogRequest>,
     ) -> Result<Response<WipePreservedCatalogResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let WipePreservedCatalogRequest { db_name } = request.into_inner();
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "wipe_preserved_catalog",
+        )?;
 
         let tracker = self
             .server
@@ -446,10 +580,17 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<RebuildPreservedCatalogRequest>,
     ) -> Result<Response<RebuildPreservedCatalogResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let RebuildPreservedCatalogRequest { db_name, force } = request.into_inner();
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "rebuild_preserved_catalog",
+        )?;
         let database = self
             .server
             .database(&db_name)
@@ -468,10 +609,17 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<SkipReplayRequest>,
     ) -> Result<Response<SkipReplayResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let SkipReplayRequest { db_name } = request.into_inner();
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "skip_replay",
+        )?;
 
         let database = self
             .server
@@ -490,6 +638,7 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: tonic::Request<PersistPartitionRequest>,
     ) -> Result<tonic::Response<PersistPartitionResponse>, tonic::Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let PersistPartitionRequest {
             db_name,
             partition_key,
@@ -499,6 +648,12 @@ impl management_service_server::ManagementService for ManagementService {
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "persist_partition",
+        )?;
         let db = self
             .server
             .db(&db_name)
@@ -515,6 +670,7 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: tonic::Request<DropPartitionRequest>,
     ) -> Result<tonic::Response<DropPartitionResponse>, tonic::Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let DropPartitionRequest {
             db_name,
             partition_key,
@@ -523,6 +679,12 @@ impl management_service_server::ManagementService for ManagementService {
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "drop_partition",
+        )?;
         let db = self
             .server
             .db(&db_name)
@@ -540,6 +702,7 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<CompactObjectStoreChunksRequest>,
     ) -> Result<Response<CompactObjectStoreChunksResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let CompactObjectStoreChunksRequest {
             db_name,
             partition_key,
@@ -549,6 +712,12 @@ impl management_service_server::ManagementService for ManagementService {
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "compact_object_store_chunks",
+        )?;
 
         let db = self
             .server
@@ -577,6 +746,7 @@ impl management_service_server::ManagementService for ManagementService {
         &self,
         request: Request<CompactObjectStorePartitionRequest>,
     ) -> Result<Response<CompactObjectStorePartitionResponse>, Status> {
+        let token = AuthToken::from_metadata(request.metadata());
         let CompactObjectStorePartitionRequest {
             db_name,
             partition_key,
@@ -585,6 +755,12 @@ impl management_service_server::ManagementService for ManagementService {
 
         // Validate that the database name is legit
         let db_name = DatabaseName::new(db_name).scope("db_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Admin,
+            "compact_object_store_partition",
+        )?;
 
         let db = self
             .server
@@ -618,11 +794,13 @@ fn format_rules(provided_rules: Arc<ProvidedDatabaseRules>, omit_defaults: bool)
 pub fn make_server(
     application: Arc<ApplicationState>,
     server: Arc<Server>,
+    authorizer: Arc<Authorizer>,
 ) -> management_service_server::ManagementServiceServer<
     impl management_service_server::ManagementService,
 > {
     management_service_server::ManagementServiceServer::new(ManagementService {
         application,
         server,
+        authorizer,
     })
 }
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::influxdb_ioxd::{
+    auth::Permission,
     rpc::{add_gated_service, add_service, serve_builder, setup_builder, RpcBuilderInput},
     server_type::{database::DatabaseServerType, RpcError},
 };
@@ -11,6 +12,7 @@ mod error;
 mod flight;
 mod management;
 mod operations;
+mod otlp;
 mod storage;
 mod write_pb;
 
@@ -22,19 +24,38 @@ pub async fn server_grpc(
 
     add_gated_service!(
         builder,
-        storage::make_server(Arc::clone(&server_type.server),)
+        storage::make_server(
+            Arc::clone(&server_type.server),
+            Arc::clone(&server_type.authorizer),
+        )
     );
     add_gated_service!(
         builder,
-        flight::make_server(Arc::clone(&server_type.server))
+        flight::make_server(
+            Arc::clone(&server_type.server),
+            Arc::clone(&server_type.authorizer),
+        )
     );
     add_gated_service!(
         builder,
-        delete::make_server(Arc::clone(&server_type.server))
+        delete::make_server(
+            Arc::clone(&server_type.server),
+            Arc::clone(&server_type.authorizer),
+        )
     );
     add_gated_service!(
         builder,
-        write_pb::make_server(Arc::clone(&server_type.server))
+        write_pb::make_server(
+            Arc::clone(&server_type.server),
+            Arc::clone(&server_type.authorizer),
+        )
+    );
+    add_gated_service!(
+        builder,
+        otlp::make_server(
+            Arc::clone(&server_type.server),
+            Arc::clone(&server_type.authorizer),
+        )
     );
     // Also important this is not behind a readiness check (as it is
     // used to change the check!)
@@ -43,18 +64,26 @@ pub async fn server_grpc(
         management::make_server(
             Arc::clone(&server_type.application),
             Arc::clone(&server_type.server),
+            Arc::clone(&server_type.authorizer),
         )
     );
+    // The deployment and operations services are not specific to a database
     add_service!(
         builder,
-        deployment::make_server(
-            Arc::clone(&server_type.server),
-            server_type.serving_readiness.clone(),
+        tonic::codegen::InterceptedService::new(
+            deployment::make_server(
+                Arc::clone(&server_type.server),
+                server_type.serving_readiness.clone(),
+            ),
+            Arc::clone(&server_type.authorizer).into_interceptor(Permission::Admin, "deployment"),
         )
     );
     add_service!(
         builder,
-        operations::make_server(Arc::clone(server_type.application.job_registry()))
+        tonic::codegen::InterceptedService::new(
+            operations::make_server(Arc::clone(server_type.application.job_registry())),
+            Arc::clone(&server_type.authorizer).into_interceptor(Permission::Admin, "operations"),
+        )
     );
 
     serve_builder!(builder);
@@ -0,0 +1,87 @@
+//! Implements the OpenTelemetry (OTLP) metrics collector service, writing
+//! exported metrics to the database named in the `iox-database` request
+//! header.
+use data_types::DatabaseName;
+use dml::{DmlMeta, DmlOperation, DmlWrite};
+use generated_types::google::FieldViolation;
+use generated_types::opentelemetry::proto::collector::metrics::v1::*;
+use observability_deps::tracing::debug;
+use server::Server;
+use std::sync::Arc;
+
+use super::error::{default_dml_error_handler, default_server_error_handler};
+use crate::influxdb_ioxd::auth::{AuthToken, Authorizer, Permission};
+
+/// Request header naming the database to write exported metrics to
+const DATABASE_HEADER: &str = "iox-database";
+
+struct OtlpMetricsService {
+    server: Arc<Server>,
+    authorizer: Arc<Authorizer>,
+}
+
+#[tonic::async_trait]
+impl metrics_service_server::MetricsService for OtlpMetricsService {
+    async fn export(
+        &self,
+        request: tonic::Request<ExportMetricsServiceRequest>,
+    ) -> Result<tonic::Response<ExportMetricsServiceResponse>, tonic::Status> {
+        let span_ctx = request.extensions().get().cloned();
+        let db_name = request
+            .metadata()
+            .get(DATABASE_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| {
+                tonic::Status::invalid_argument(format!(
+                    "missing or invalid {} header",
+                    DATABASE_HEADER
+                ))
+            })?;
+        let db_name = DatabaseName::new(db_name.to_string()).map_err(|e| FieldViolation {
+            field: DATABASE_HEADER.into(),
+            description: e.to_string(),
+        })?;
+        self.authorizer.authorize(
+            &AuthToken::from_metadata(request.metadata()),
+            Some(db_name.as_str()),
+            Permission::Write,
+            "otlp export",
+        )?;
+
+        let tables = match mutable_batch_otlp::export_request_to_batches(request.get_ref()) {
+            Ok(tables) => tables,
+            // An export without any data points is valid, acknowledge it so
+            // that collectors neither retry nor drop it
+            Err(mutable_batch_otlp::Error::EmptyPayload) => {
+                debug!("nothing to write");
+                return Ok(tonic::Response::new(ExportMetricsServiceResponse {}));
+            }
+            Err(e) => {
+                return Err(FieldViolation {
+                    field: "resource_metrics".into(),
+                    description: format!("Invalid metrics: {}", e),
+                }
+                .into())
+            }
+        };
+
+        let write = DmlWrite::new(tables, DmlMeta::unsequenced(span_ctx));
+
+        let db = self
+            .server
+            .db(&db_name)
+            .map_err(default_server_error_handler)?;
+
+        db.store_operation(&DmlOperation::Write(write))
+            .map_err(default_dml_error_handler)?;
+
+        Ok(tonic::Response::new(ExportMetricsServiceResponse {}))
+    }
+}
+
+pub fn make_server(
+    server: Arc<Server>,
+    authorizer: Arc<Authorizer>,
+) -> metrics_service_server::MetricsServiceServer<impl metrics_service_server::MetricsService> {
+    metrics_service_server::MetricsServiceServer::new(OtlpMetricsService { server, authorizer })
+}
@@ -18,12 +18,21 @@ use generated_types::storage_server::{Storage, StorageServer};
 use server::DatabaseStore;
 use std::sync::Arc;
 
+use crate::influxdb_ioxd::auth::Authorizer;
+
 /// Concrete implementation of the gRPC InfluxDB Storage Service API
 #[derive(Debug)]
 struct StorageService<T: DatabaseStore> {
     pub db_store: Arc<T>,
+    pub authorizer: Arc<Authorizer>,
 }
 
-pub fn make_server<T: DatabaseStore + 'static>(db_store: Arc<T>) -> StorageServer<impl Storage> {
-    StorageServer::new(StorageService { db_store })
+pub fn make_server<T: DatabaseStore + 'static>(
+    db_store: Arc<T>,
+    authorizer: Arc<Authorizer>,
+) -> StorageServer<impl Storage> {
+    StorageServer::new(StorageService {
+        db_store,
+        authorizer,
+    })
 }
@@ -9,6 +9,7 @@ use std::{convert::TryFrom, fmt};
 
 use datafusion::{
     logical_plan::{binary_expr, Expr, Operator},
+    physical_plan::functions::BuiltinScalarFunction,
     prelude::*,
     scalar::ScalarValue,
 };
@@ -76,8 +77,11 @@ pub enum Error {
     #[snafu(display("Internal error: incorrect number of nodes: {:?}", num_children))]
     InternalInvalidRegexExprChildren { num_children: usize },
 
-    #[snafu(display("Error creating predicate: StartsWith comparisons not supported"))]
-    StartsWithNotSupported {},
+    #[snafu(display("Error creating predicate: StartsWith prefix must be a string"))]
+    StartsWithPrefixInvalid {},
+
+    #[snafu(display("Internal error: incorrect number of nodes: {:?}", num_children))]
+    InternalInvalidStartsWithExprChildren { num_children: usize },
 
     #[snafu(display(
         "Error creating predicate: Unexpected children for predicate: {:?}",
@@ -146,6 +150,9 @@ pub enum GroupByAndAggregate {
         agg: QueryAggregate,
         every: WindowDuration,
         offset: WindowDuration,
+        /// also produce rows for windows within the time range that
+        /// contain no data
+        create_empty: bool,
     },
 }
 
@@ -475,7 +482,7 @@ fn build_comparison_node(comparison: i32, inputs: Vec<Expr>) -> Result<Expr> {
     match comparison_enum {
         Some(RPCComparison::Equal) => build_binary_expr(Operator::Eq, inputs),
         Some(RPCComparison::NotEqual) => build_binary_expr(Operator::NotEq, inputs),
-        Some(RPCComparison::StartsWith) => StartsWithNotSupported {}.fail(),
+        Some(RPCComparison::StartsWith) => build_starts_with_expr(inputs),
         Some(RPCComparison::Regex) => build_regex_match_expr(true, inputs),
         Some(RPCComparison::NotRegex) => build_regex_match_expr(false, inputs),
         Some(RPCComparison::Lt) => build_binary_expr(Operator::Lt, inputs),
@@ -520,6 +527,25 @@ fn build_regex_match_expr(matches: bool, mut inputs: Vec<Expr>) -> Result<Expr>
     }
 }
 
+// Creates a DataFusion starts_with expression, which compares the prefix of
+// the values in the column.
+fn build_starts_with_expr(inputs: Vec<Expr>) -> Result<Expr> {
+    let num_children = inputs.len();
+    ensure!(
+        num_children == 2,
+        InternalInvalidStartsWithExprChildren { num_children }
+    );
+    ensure!(
+        matches!(&inputs[1], Expr::Literal(ScalarValue::Utf8(Some(_)))),
+        StartsWithPrefixInvalid
+    );
+
+    Ok(Expr::ScalarFunction {
+        fun: BuiltinScalarFunction::StartsWith,
+        args: inputs,
+    })
+}
+
 pub fn make_read_group_aggregate(
     aggregate: Option<RPCAggregate>,
     group: RPCGroup,
@@ -548,6 +574,7 @@ pub fn make_read_window_aggregate(
     window_every: i64,
     offset: i64,
     window: Option<RPCWindow>,
+    create_empty: bool,
 ) -> Result<GroupByAndAggregate> {
     // only support single aggregate for now
     if aggregates.len() != 1 {
@@ -592,7 +619,12 @@ pub fn make_read_window_aggregate(
         }
     };
 
-    Ok(GroupByAndAggregate::Window { agg, every, offset })
+    Ok(GroupByAndAggregate::Window {
+        agg,
+        every,
+        offset,
+        create_empty,
+    })
 }
 
 enum DurationValidation {
@@ -877,6 +909,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_convert_predicate_starts_with() {
+        // host starts with "serv"
+        let comparison = make_starts_with_comparison(RPCValue::StringValue("serv".into()));
+
+        let rpc_predicate = RPCPredicate {
+            root: Some(comparison),
+        };
+
+        let predicate = PredicateBuilder::default()
+            .rpc_predicate(Some(rpc_predicate))
+            .expect("successfully converting predicate")
+            .build();
+
+        let expected_exprs = vec![Expr::ScalarFunction {
+            fun: BuiltinScalarFunction::StartsWith,
+            args: vec![col("host"), lit("serv")],
+        }];
+
+        assert_eq!(
+            &expected_exprs, &predicate.exprs,
+            "expected '{:#?}' doesn't match actual '{:#?}'",
+            expected_exprs, predicate.exprs,
+        );
+    }
+
+    #[test]
+    fn test_convert_predicate_starts_with_bad_prefix() {
+        // host starts with 5
+        let comparison = make_starts_with_comparison(RPCValue::IntValue(5));
+
+        let rpc_predicate = RPCPredicate {
+            root: Some(comparison),
+        };
+
+        let res = PredicateBuilder::default().rpc_predicate(Some(rpc_predicate));
+
+        let expected_error = "Error creating predicate: StartsWith prefix must be a string";
+        let actual_error = res.unwrap_err().to_string();
+        assert!(
+            actual_error.contains(expected_error),
+            "expected '{}' not found in '{}'",
+            expected_error,
+            actual_error
+        );
+    }
+
     #[test]
     fn test_convert_predicate_no_children() {
         let comparison = RPCNode {
@@ -1143,6 +1222,24 @@ mod tests {
         (comparison, vec![expected_expr])
     }
 
+    fn make_starts_with_comparison(prefix: RPCValue) -> RPCNode {
+        let tag_ref = RPCNode {
+            node_type: RPCNodeType::TagRef as i32,
+            children: vec![],
+            value: Some(RPCValue::TagRefValue(b"host".to_vec())),
+        };
+        let prefix = RPCNode {
+            node_type: RPCNodeType::Literal as i32,
+            children: vec![],
+            value: Some(prefix),
+        };
+        RPCNode {
+            node_type: RPCNodeType::ComparisonExpression as i32,
+            children: vec![tag_ref, prefix],
+            value: Some(RPCValue::Comparison(RPCComparison::StartsWith as i32)),
+        }
+    }
+
     fn make_tag_ref_node(tag_name: &[u8], field_name: impl Into<String>) -> RPCNode {
         let field_tag_ref_node = RPCNode {
             node_type: RPCNodeType::TagRef as i32,
@@ -1236,23 +1333,28 @@ mod tests {
         let pos_3_months = WindowDuration::from_months(3, false);
         let neg_1_months = WindowDuration::from_months(1, true);
 
-        let agg = make_read_window_aggregate(vec![], 5, 10, None);
+        let agg = make_read_window_aggregate(vec![], 5, 10, None, false);
         let expected =
             "Error creating aggregate: Exactly one aggregate is supported, but 0 were supplied: []";
         assert_eq!(agg.unwrap_err().to_string(), expected);
 
-        let agg =
-            make_read_window_aggregate(vec![make_aggregate(1), make_aggregate(2)], 5, 10, None);
+        let agg = make_read_window_aggregate(
+            vec![make_aggregate(1), make_aggregate(2)],
+            5,
+            10,
+            None,
+            false,
+        );
         let expected = "Error creating aggregate: Exactly one aggregate is supported, but 2 were supplied: [Aggregate { r#type: Sum }, Aggregate { r#type: Count }]";
         assert_eq!(agg.unwrap_err().to_string(), expected);
 
         // now window specified
-        let agg = make_read_window_aggregate(vec![make_aggregate(1)], 0, 0, None);
+        let agg = make_read_window_aggregate(vec![make_aggregate(1)], 0, 0, None, false);
         let expected = "Error parsing window bounds: No window specified";
         assert_eq!(agg.unwrap_err().to_string(), expected);
 
         // correct window + window_every
-        let agg = make_read_window_aggregate(vec![make_aggregate(1)], 5, 10, None).unwrap();
+        let agg = make_read_window_aggregate(vec![make_aggregate(1)], 5, 10, None, false).unwrap();
         let expected = make_storage_window(QueryAggregate::Sum, &pos_5_ns, &pos_10_ns);
         assert_eq!(agg, expected);
 
@@ -1262,6 +1364,7 @@ mod tests {
             0,
             0,
             Some(make_rpc_window(5, 0, false, 10, 0, false)),
+            false,
         )
         .unwrap();
         let expected = make_storage_window(QueryAggregate::Sum, &pos_5_ns, &pos_10_ns);
@@ -1273,6 +1376,7 @@ mod tests {
             0,
             0,
             Some(make_rpc_window(5, 0, false, 0, 0, false)),
+            false,
         )
         .unwrap();
         let expected =
@@ -1285,6 +1389,7 @@ mod tests {
             0,
             0,
             Some(make_rpc_window(0, 3, false, 0, 1, true)),
+            false,
         )
         .unwrap();
         let expected = make_storage_window(QueryAggregate::Sum, &pos_3_months, &neg_1_months);
@@ -1296,6 +1401,7 @@ mod tests {
             0,
             0,
             Some(make_rpc_window(0, 1, true, 0, 3, false)),
+            false,
         )
         .unwrap();
         let expected = make_storage_window(QueryAggregate::Sum, &neg_1_months, &pos_3_months);
@@ -1308,6 +1414,7 @@ mod tests {
             5,
             10,
             Some(make_rpc_window(100, 0, false, 200, 0, false)),
+            false,
         )
         .unwrap();
         let expected = make_storage_window(QueryAggregate::Sum, &pos_5_ns, &pos_10_ns);
@@ -1319,6 +1426,7 @@ mod tests {
             0,
             0,
             Some(make_rpc_window(5, 1, false, 10, 0, false)),
+            false,
         );
         let expected = "Error parsing window bounds duration \'window.every\': duration used as an interval cannot mix month and nanosecond units";
         assert_eq!(agg.unwrap_err().to_string(), expected);
@@ -1329,6 +1437,7 @@ mod tests {
             0,
             0,
             Some(make_rpc_window(5, 0, false, 10, 1, false)),
+            false,
         );
         let expected = "Error parsing window bounds duration \'window.offset\': duration used as an interval cannot mix month and nanosecond units";
         assert_eq!(agg.unwrap_err().to_string(), expected);
@@ -1339,9 +1448,20 @@ mod tests {
             0,
             0,
             Some(make_rpc_window(0, 0, false, 5, 0, false)),
+            false,
         );
         let expected = "Error parsing window bounds duration \'window.every\': duration used as an interval cannot be zero";
         assert_eq!(agg.unwrap_err().to_string(), expected);
+
+        // create empty windows
+        let agg = make_read_window_aggregate(vec![make_aggregate(2)], 5, 10, None, true).unwrap();
+        let expected = GroupByAndAggregate::Window {
+            agg: QueryAggregate::Count,
+            every: pos_5_ns,
+            offset: pos_10_ns,
+            create_empty: true,
+        };
+        assert_eq!(agg, expected);
     }
 
     #[test]
@@ -1432,6 +1552,7 @@ mod tests {
             agg,
             every: every.clone(),
             offset: offset.clone(),
+            create_empty: false,
         }
     }
 
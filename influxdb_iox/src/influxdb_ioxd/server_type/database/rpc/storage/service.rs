@@ -22,15 +22,18 @@ use observability_deps::tracing::{error, info, trace};
 use predicate::predicate::PredicateBuilder;
 use query::{
     exec::{
-        fieldlist::FieldList, seriesset::converter::Error as SeriesSetError,
-        ExecutionContextProvider,
+        admission::is_admission_rejected, deadline::is_query_timeout, fieldlist::FieldList,
+        memory::is_memory_exhausted, seriesset::converter::Error as SeriesSetError,
+        ExecutionContextProvider, IOxExecutionContext,
     },
     QueryDatabase,
 };
 use server::DatabaseStore;
 
 use crate::influxdb_ioxd::{
+    auth::{AuthToken, Permission},
     planner::Planner,
+    rpc::{deadline::grpc_timeout, priority::query_priority},
     server_type::database::rpc::storage::{
         data::{
             fieldlist_to_measurement_fields_response, series_or_groups_to_read_response,
@@ -41,7 +44,6 @@ use crate::influxdb_ioxd::{
         StorageService,
     },
 };
-use trace::ctx::SpanContext;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -66,6 +68,12 @@ pub enum Error {
         source: Box<dyn std::error::Error + Send + Sync>,
     },
 
+    #[snafu(display("Error counting series in database '{}': {}", db_name, source))]
+    CountingSeries {
+        db_name: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
     #[snafu(display("Error creating series plans for database '{}': {}", db_name, source))]
     PlanningFilteringSeries {
         db_name: String,
@@ -173,6 +181,13 @@ impl Error {
     /// Converts a result from the business logic into the appropriate tonic
     /// status
     fn to_status(&self) -> tonic::Status {
+        if is_query_timeout(self) {
+            return Status::deadline_exceeded(self.to_string());
+        }
+        if is_memory_exhausted(self) || is_admission_rejected(self) {
+            return Status::resource_exhausted(self.to_string());
+        }
+
         match &self {
             Self::DatabaseNotFound { .. } => Status::not_found(self.to_string()),
             Self::ListingTables { .. } => Status::internal(self.to_string()),
@@ -184,6 +199,7 @@ impl Error {
                 // TODO: distinguish between input errors and internal errors
                 Status::invalid_argument(self.to_string())
             }
+            Self::CountingSeries { .. } => Status::invalid_argument(self.to_string()),
             Self::PlanningFilteringSeries { .. } => Status::invalid_argument(self.to_string()),
             Self::PlanningGroupSeries { .. } => Status::invalid_argument(self.to_string()),
             Self::FilteringSeries { .. } => Status::invalid_argument(self.to_string()),
@@ -216,18 +232,31 @@ where
         req: tonic::Request<ReadFilterRequest>,
     ) -> Result<tonic::Response<Self::ReadFilterStream>, Status> {
         let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
 
+        let token = AuthToken::from_metadata(req.metadata());
         let req = req.into_inner();
         let db_name = get_database_name(&req)?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Read,
+            "read_filter",
+        )?;
         info!(%db_name, ?req.range, predicate=%req.predicate.loggable(), "read filter");
 
         let db = self
             .db_store
             .db(&db_name)
             .context(DatabaseNotFound { db_name: &db_name })?;
-        db.record_query("read_filter", defer_json(&req));
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "read_filter", defer_json(&req));
 
-        let results = read_filter_impl(db, db_name, req, span_ctx)
+        let results = read_filter_impl(db, db_name, req, ctx)
             .await?
             .into_iter()
             .map(Ok)
@@ -243,14 +272,27 @@ where
         req: tonic::Request<ReadGroupRequest>,
     ) -> Result<tonic::Response<Self::ReadGroupStream>, Status> {
         let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
+        let token = AuthToken::from_metadata(req.metadata());
         let req = req.into_inner();
 
         let db_name = get_database_name(&req)?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Read,
+            "read_group",
+        )?;
         let db = self
             .db_store
             .db(&db_name)
             .context(DatabaseNotFound { db_name: &db_name })?;
-        db.record_query("read_group", defer_json(&req));
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "read_group", defer_json(&req));
 
         let ReadGroupRequest {
             read_source: _read_source,
@@ -275,7 +317,7 @@ where
         let gby_agg = expr::make_read_group_aggregate(aggregate, group, group_keys)
             .context(ConvertingReadGroupAggregate { aggregate_string })?;
 
-        let results = query_group_impl(db, db_name, range, predicate, gby_agg, span_ctx)
+        let results = query_group_impl(db, db_name, range, predicate, gby_agg, ctx)
             .await
             .map_err(|e| e.to_status())?
             .into_iter()
@@ -293,14 +335,27 @@ where
         req: tonic::Request<ReadWindowAggregateRequest>,
     ) -> Result<tonic::Response<Self::ReadGroupStream>, Status> {
         let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
+        let token = AuthToken::from_metadata(req.metadata());
         let req = req.into_inner();
 
         let db_name = get_database_name(&req)?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Read,
+            "read_window_aggregate",
+        )?;
         let db = self
             .db_store
             .db(&db_name)
             .context(DatabaseNotFound { db_name: &db_name })?;
-        db.record_query("read_window_aggregate", defer_json(&req));
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "read_window_aggregate", defer_json(&req));
 
         let ReadWindowAggregateRequest {
             read_source: _read_source,
@@ -310,19 +365,21 @@ where
             offset,
             aggregate,
             window,
+            create_empty,
         } = req;
 
-        info!(%db_name, ?range, ?window_every, ?offset, ?aggregate, ?window, predicate=%predicate.loggable(),"read_window_aggregate");
+        info!(%db_name, ?range, ?window_every, ?offset, ?aggregate, ?window, create_empty, predicate=%predicate.loggable(),"read_window_aggregate");
 
         let aggregate_string = format!(
             "aggregate: {:?}, window_every: {:?}, offset: {:?}, window: {:?}",
             aggregate, window_every, offset, window
         );
 
-        let gby_agg = expr::make_read_window_aggregate(aggregate, window_every, offset, window)
-            .context(ConvertingWindowAggregate { aggregate_string })?;
+        let gby_agg =
+            expr::make_read_window_aggregate(aggregate, window_every, offset, window, create_empty)
+                .context(ConvertingWindowAggregate { aggregate_string })?;
 
-        let results = query_group_impl(db, db_name, range, predicate, gby_agg, span_ctx)
+        let results = query_group_impl(db, db_name, range, predicate, gby_agg, ctx)
             .await
             .map_err(|e| e.to_status())?
             .into_iter()
@@ -339,16 +396,25 @@ where
         req: tonic::Request<TagKeysRequest>,
     ) -> Result<tonic::Response<Self::TagKeysStream>, Status> {
         let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
         let (tx, rx) = mpsc::channel(4);
 
+        let token = AuthToken::from_metadata(req.metadata());
         let req = req.into_inner();
 
         let db_name = get_database_name(&req)?;
+        self.authorizer
+            .authorize(&token, Some(db_name.as_str()), Permission::Read, "tag_keys")?;
         let db = self
             .db_store
             .db(&db_name)
             .context(DatabaseNotFound { db_name: &db_name })?;
-        db.record_query("tag_keys", defer_json(&req));
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "tag_keys", defer_json(&req));
 
         let TagKeysRequest {
             tags_source: _tag_source,
@@ -360,7 +426,7 @@ where
 
         let measurement = None;
 
-        let response = tag_keys_impl(db, db_name, measurement, range, predicate, span_ctx)
+        let response = tag_keys_impl(db, db_name, measurement, range, predicate, ctx)
             .await
             .map_err(|e| e.to_status());
 
@@ -378,16 +444,29 @@ where
         req: tonic::Request<TagValuesRequest>,
     ) -> Result<tonic::Response<Self::TagValuesStream>, Status> {
         let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
         let (tx, rx) = mpsc::channel(4);
 
+        let token = AuthToken::from_metadata(req.metadata());
         let req = req.into_inner();
 
         let db_name = get_database_name(&req)?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Read,
+            "tag_values",
+        )?;
         let db = self
             .db_store
             .db(&db_name)
             .context(DatabaseNotFound { db_name: &db_name })?;
-        db.record_query("tag_values", defer_json(&req));
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "tag_values", defer_json(&req));
 
         let TagValuesRequest {
             tags_source: _tag_source,
@@ -410,11 +489,11 @@ where
                 .to_status());
             }
 
-            measurement_name_impl(db, db_name, range, predicate, span_ctx).await
+            measurement_name_impl(db, db_name, range, predicate, ctx).await
         } else if tag_key.is_field() {
             info!(%db_name, ?range, predicate=%predicate.loggable(), "tag_values with tag_key=[xff] (field name)");
 
-            let fieldlist = field_names_impl(db, db_name, None, range, predicate, span_ctx).await?;
+            let fieldlist = field_names_impl(db, db_name, None, range, predicate, ctx).await?;
 
             // Pick out the field names into a Vec<Vec<u8>>for return
             let values = fieldlist
@@ -429,16 +508,7 @@ where
 
             info!(%db_name, ?range, %tag_key, predicate=%predicate.loggable(), "tag_values",);
 
-            tag_values_impl(
-                db,
-                db_name,
-                tag_key,
-                measurement,
-                range,
-                predicate,
-                span_ctx,
-            )
-            .await
+            tag_values_impl(db, db_name, tag_key, measurement, range, predicate, ctx).await
         };
 
         let response = response.map_err(|e| e.to_status());
@@ -454,9 +524,50 @@ where
 
     async fn read_series_cardinality(
         &self,
-        _req: tonic::Request<ReadSeriesCardinalityRequest>,
+        req: tonic::Request<ReadSeriesCardinalityRequest>,
     ) -> Result<tonic::Response<Self::ReadSeriesCardinalityStream>, Status> {
-        unimplemented!("read_series_cardinality not yet implemented. https://github.com/influxdata/influxdb_iox/issues/447");
+        let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
+        let (tx, rx) = mpsc::channel(4);
+
+        let token = AuthToken::from_metadata(req.metadata());
+        let req = req.into_inner();
+
+        let db_name = get_database_name(&req)?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Read,
+            "read_series_cardinality",
+        )?;
+        let db = self
+            .db_store
+            .db(&db_name)
+            .context(DatabaseNotFound { db_name: &db_name })?;
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "read_series_cardinality", defer_json(&req));
+
+        let ReadSeriesCardinalityRequest {
+            read_series_cardinality_source: _read_source,
+            range,
+            predicate,
+        } = req;
+
+        info!(%db_name, ?range, predicate=%predicate.loggable(), "read_series_cardinality");
+
+        let response = series_cardinality_impl(db, db_name, range, predicate, ctx)
+            .await
+            .map_err(|e| e.to_status());
+
+        tx.send(response)
+            .await
+            .expect("sending read_series_cardinality response to server");
+
+        Ok(tonic::Response::new(ReceiverStream::new(rx)))
     }
 
     async fn capabilities(
@@ -480,6 +591,9 @@ where
                 ],
             ),
             ("Group", vec!["First", "Last", "Min", "Max"]),
+            // Series are counted exactly where metadata allows, and
+            // estimated otherwise
+            ("ReadSeriesCardinality", vec!["Estimate"]),
         ];
 
         // Turn it into the HashMap -> Capabiltity
@@ -502,16 +616,29 @@ where
         req: tonic::Request<MeasurementNamesRequest>,
     ) -> Result<tonic::Response<Self::MeasurementNamesStream>, Status> {
         let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
         let (tx, rx) = mpsc::channel(4);
 
+        let token = AuthToken::from_metadata(req.metadata());
         let req = req.into_inner();
 
         let db_name = get_database_name(&req)?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Read,
+            "measurement_names",
+        )?;
         let db = self
             .db_store
             .db(&db_name)
             .context(DatabaseNotFound { db_name: &db_name })?;
-        db.record_query("measurement_names", defer_json(&req));
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "measurement_names", defer_json(&req));
 
         let MeasurementNamesRequest {
             source: _source,
@@ -521,7 +648,7 @@ where
 
         info!(%db_name, ?range, predicate=%predicate.loggable(), "measurement_names");
 
-        let response = measurement_name_impl(db, db_name, range, predicate, span_ctx)
+        let response = measurement_name_impl(db, db_name, range, predicate, ctx)
             .await
             .map_err(|e| e.to_status());
 
@@ -539,16 +666,29 @@ where
         req: tonic::Request<MeasurementTagKeysRequest>,
     ) -> Result<tonic::Response<Self::MeasurementTagKeysStream>, Status> {
         let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
         let (tx, rx) = mpsc::channel(4);
 
+        let token = AuthToken::from_metadata(req.metadata());
         let req = req.into_inner();
 
         let db_name = get_database_name(&req)?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Read,
+            "measurement_tag_keys",
+        )?;
         let db = self
             .db_store
             .db(&db_name)
             .context(DatabaseNotFound { db_name: &db_name })?;
-        db.record_query("measurement_tag_keys", defer_json(&req));
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "measurement_tag_keys", defer_json(&req));
 
         let MeasurementTagKeysRequest {
             source: _source,
@@ -561,7 +701,7 @@ where
 
         let measurement = Some(measurement);
 
-        let response = tag_keys_impl(db, db_name, measurement, range, predicate, span_ctx)
+        let response = tag_keys_impl(db, db_name, measurement, range, predicate, ctx)
             .await
             .map_err(|e| e.to_status());
 
@@ -579,16 +719,29 @@ where
         req: tonic::Request<MeasurementTagValuesRequest>,
     ) -> Result<tonic::Response<Self::MeasurementTagValuesStream>, Status> {
         let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
         let (tx, rx) = mpsc::channel(4);
 
+        let token = AuthToken::from_metadata(req.metadata());
         let req = req.into_inner();
 
         let db_name = get_database_name(&req)?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Read,
+            "measurement_tag_values",
+        )?;
         let db = self
             .db_store
             .db(&db_name)
             .context(DatabaseNotFound { db_name: &db_name })?;
-        db.record_query("measurement_tag_values", defer_json(&req));
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "measurement_tag_values", defer_json(&req));
 
         let MeasurementTagValuesRequest {
             source: _source,
@@ -602,17 +755,9 @@ where
 
         let measurement = Some(measurement);
 
-        let response = tag_values_impl(
-            db,
-            db_name,
-            tag_key,
-            measurement,
-            range,
-            predicate,
-            span_ctx,
-        )
-        .await
-        .map_err(|e| e.to_status());
+        let response = tag_values_impl(db, db_name, tag_key, measurement, range, predicate, ctx)
+            .await
+            .map_err(|e| e.to_status());
 
         tx.send(response)
             .await
@@ -628,16 +773,29 @@ where
         req: tonic::Request<MeasurementFieldsRequest>,
     ) -> Result<tonic::Response<Self::MeasurementFieldsStream>, Status> {
         let span_ctx = req.extensions().get().cloned();
+        let timeout = grpc_timeout(req.metadata());
+        let priority = query_priority(req.metadata())?;
         let (tx, rx) = mpsc::channel(4);
 
+        let token = AuthToken::from_metadata(req.metadata());
         let req = req.into_inner();
 
         let db_name = get_database_name(&req)?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Read,
+            "measurement_fields",
+        )?;
         let db = self
             .db_store
             .db(&db_name)
             .context(DatabaseNotFound { db_name: &db_name })?;
-        db.record_query("measurement_fields", defer_json(&req));
+        let ctx = db
+            .new_query_context(span_ctx)
+            .with_request_timeout(timeout)
+            .with_priority(priority);
+        db.record_query(&ctx, "measurement_fields", defer_json(&req));
 
         let MeasurementFieldsRequest {
             source: _source,
@@ -650,7 +808,7 @@ where
 
         let measurement = Some(measurement);
 
-        let response = field_names_impl(db, db_name, measurement, range, predicate, span_ctx)
+        let response = field_names_impl(db, db_name, measurement, range, predicate, ctx)
             .await
             .map(|fieldlist| {
                 fieldlist_to_measurement_fields_response(fieldlist)
@@ -710,10 +868,10 @@ async fn measurement_name_impl<D>(
     db_name: DatabaseName<'static>,
     range: Option<TimestampRange>,
     rpc_predicate: Option<Predicate>,
-    span_ctx: Option<SpanContext>,
+    ctx: IOxExecutionContext,
 ) -> Result<StringValuesResponse>
 where
-    D: QueryDatabase + ExecutionContextProvider + 'static,
+    D: QueryDatabase + 'static,
 {
     let rpc_predicate_string = format!("{:?}", rpc_predicate);
     let db_name = db_name.as_str();
@@ -726,8 +884,6 @@ where
         })?
         .build();
 
-    let ctx = db.new_query_context(span_ctx);
-
     let plan = Planner::new(&ctx)
         .table_names(db, predicate)
         .await
@@ -758,10 +914,10 @@ async fn tag_keys_impl<D>(
     measurement: Option<String>,
     range: Option<TimestampRange>,
     rpc_predicate: Option<Predicate>,
-    span_ctx: Option<SpanContext>,
+    ctx: IOxExecutionContext,
 ) -> Result<StringValuesResponse>
 where
-    D: QueryDatabase + ExecutionContextProvider + 'static,
+    D: QueryDatabase + 'static,
 {
     let rpc_predicate_string = format!("{:?}", rpc_predicate);
     let db_name = db_name.as_str();
@@ -775,8 +931,6 @@ where
         })?
         .build();
 
-    let ctx = db.new_query_context(span_ctx);
-
     let tag_key_plan = Planner::new(&ctx)
         .tag_keys(db, predicate)
         .await
@@ -796,6 +950,47 @@ where
     Ok(StringValuesResponse { values })
 }
 
+/// Return the number of series that match the timestamp and arbitrary
+/// predicates
+async fn series_cardinality_impl<D>(
+    db: Arc<D>,
+    db_name: DatabaseName<'static>,
+    range: Option<TimestampRange>,
+    rpc_predicate: Option<Predicate>,
+    ctx: IOxExecutionContext,
+) -> Result<Int64ValuesResponse>
+where
+    D: QueryDatabase + 'static,
+{
+    let rpc_predicate_string = format!("{:?}", rpc_predicate);
+    let db_name = db_name.as_str();
+
+    let predicate = PredicateBuilder::default()
+        .set_range(range)
+        .rpc_predicate(rpc_predicate)
+        .context(ConvertingPredicate {
+            rpc_predicate_string,
+        })?
+        .build();
+
+    let plan = Planner::new(&ctx)
+        .series_cardinality(db, predicate)
+        .await
+        .map_err(|e| Box::new(e) as _)
+        .context(CountingSeries { db_name })?;
+
+    let count = ctx
+        .to_series_cardinality(plan)
+        .await
+        .map_err(|e| Box::new(e) as _)
+        .context(CountingSeries { db_name })?;
+
+    trace!(count, "Series cardinality response");
+    Ok(Int64ValuesResponse {
+        values: vec![count as i64],
+    })
+}
+
 /// Return tag values for tag_name, with optional measurement, timestamp and
 /// arbitratry predicates
 async fn tag_values_impl<D>(
@@ -805,10 +1000,10 @@ async fn tag_values_impl<D>(
     measurement: Option<String>,
     range: Option<TimestampRange>,
     rpc_predicate: Option<Predicate>,
-    span_ctx: Option<SpanContext>,
+    ctx: IOxExecutionContext,
 ) -> Result<StringValuesResponse>
 where
-    D: QueryDatabase + ExecutionContextProvider + 'static,
+    D: QueryDatabase + 'static,
 {
     let rpc_predicate_string = format!("{:?}", rpc_predicate);
 
@@ -824,8 +1019,6 @@ where
     let db_name = db_name.as_str();
     let tag_name = &tag_name;
 
-    let ctx = db.new_query_context(span_ctx);
-
     let tag_value_plan = Planner::new(&ctx)
         .tag_values(db, tag_name, predicate)
         .await
@@ -853,15 +1046,13 @@ async fn read_filter_impl<D>(
     db: Arc<D>,
     db_name: DatabaseName<'static>,
     req: ReadFilterRequest,
-    span_ctx: Option<SpanContext>,
+    ctx: IOxExecutionContext,
 ) -> Result<Vec<ReadResponse>, Error>
 where
-    D: QueryDatabase + ExecutionContextProvider + 'static,
+    D: QueryDatabase + 'static,
 {
     let db_name = db_name.as_str();
 
-    let ctx = db.new_query_context(span_ctx);
-
     let rpc_predicate_string = format!("{:?}", req.predicate);
 
     let predicate = PredicateBuilder::default()
@@ -904,13 +1095,12 @@ async fn query_group_impl<D>(
     range: Option<TimestampRange>,
     rpc_predicate: Option<Predicate>,
     gby_agg: GroupByAndAggregate,
-    span_ctx: Option<SpanContext>,
+    ctx: IOxExecutionContext,
 ) -> Result<Vec<ReadResponse>, Error>
 where
-    D: QueryDatabase + ExecutionContextProvider + 'static,
+    D: QueryDatabase + 'static,
 {
     let db_name = db_name.as_str();
-    let ctx = db.new_query_context(span_ctx);
 
     let rpc_predicate_string = format!("{:?}", rpc_predicate);
 
@@ -927,9 +1117,14 @@ where
         GroupByAndAggregate::Columns { agg, group_columns } => {
             planner.read_group(db, predicate, agg, group_columns).await
         }
-        GroupByAndAggregate::Window { agg, every, offset } => {
+        GroupByAndAggregate::Window {
+            agg,
+            every,
+            offset,
+            create_empty,
+        } => {
             planner
-                .read_window_aggregate(db, predicate, agg, every, offset)
+                .read_window_aggregate(db, predicate, agg, every, offset, create_empty)
                 .await
         }
     };
@@ -964,10 +1159,10 @@ async fn field_names_impl<D>(
     measurement: Option<String>,
     range: Option<TimestampRange>,
     rpc_predicate: Option<Predicate>,
-    span_ctx: Option<SpanContext>,
+    ctx: IOxExecutionContext,
 ) -> Result<FieldList>
 where
-    D: QueryDatabase + ExecutionContextProvider + 'static,
+    D: QueryDatabase + 'static,
 {
     let rpc_predicate_string = format!("{:?}", rpc_predicate);
 
@@ -981,7 +1176,6 @@ where
         .build();
 
     let db_name = db_name.as_str();
-    let ctx = db.new_query_context(span_ctx);
 
     let field_list_plan = Planner::new(&ctx)
         .field_columns(db, predicate)
@@ -1057,6 +1251,7 @@ mod tests {
     use test_helpers::{assert_contains, tracing::TracingCapture};
 
     use super::*;
+    use crate::influxdb_ioxd::auth::Authorizer;
     use metric::{Attributes, Metric, U64Counter};
 
     fn to_str_vec(s: &[&str]) -> Vec<String> {
@@ -1106,12 +1301,61 @@ mod tests {
 
         expected_capabilities.insert("Group".into(), to_str_vec(&["First", "Last", "Min", "Max"]));
 
+        expected_capabilities.insert("ReadSeriesCardinality".into(), to_str_vec(&["Estimate"]));
+
         assert_eq!(
             expected_capabilities,
             fixture.storage_client.capabilities().await.unwrap()
         );
     }
 
+    #[tokio::test]
+    async fn test_storage_rpc_authorization() {
+        test_helpers::maybe_start_logging();
+
+        let db_info = org_and_bucket();
+        let tokens = format!(
+            r#"{{"tokens": [
+                {{"name": "reader", "token": "r", "permissions": {{"{db}": ["read"]}}}},
+                {{"name": "writer", "token": "w", "permissions": {{"{db}": ["write"]}}}}
+            ]}}"#,
+            db = db_info.db_name()
+        );
+
+        let request = MeasurementNamesRequest {
+            source: Some(StorageClient::read_source(&db_info, 1)),
+            range: None,
+            predicate: None,
+        };
+
+        for (token, expected) in [
+            (None, Some(tonic::Code::Unauthenticated)),
+            (Some("w"), Some(tonic::Code::PermissionDenied)),
+            (Some("r"), None),
+        ] {
+            let authorizer = Authorizer::try_from_json(tokens.as_bytes()).unwrap();
+            let mut fixture = Fixture::new_with_auth(authorizer, token)
+                .await
+                .expect("Connecting to test server");
+            fixture
+                .test_storage
+                .db_or_create(db_info.db_name())
+                .await
+                .unwrap();
+
+            let result = fixture
+                .storage_client
+                .measurement_names(request.clone())
+                .await;
+            assert_eq!(
+                result.err().map(|e| e.code()),
+                expected,
+                "token {:?}",
+                token
+            );
+        }
+    }
+
     fn org_and_bucket() -> OrgAndBucket {
         OrgAndBucket::new(NonZeroU64::new(123).unwrap(), NonZeroU64::new(456).unwrap())
     }
@@ -1438,6 +1682,99 @@ mod tests {
         grpc_request_metric_has_count(&fixture, "MeasurementTagKeys", "client_error", 1);
     }
 
+    /// test the plumbing of the RPC layer for read_series_cardinality
+    #[tokio::test]
+    async fn test_storage_rpc_read_series_cardinality() {
+        test_helpers::maybe_start_logging();
+        // Start a test gRPC server on a randomally allocated port
+        let mut fixture = Fixture::new().await.expect("Connecting to test server");
+
+        let db_info = org_and_bucket();
+
+        let chunk = TestChunk::new("h2o")
+            .with_id(0)
+            .with_tag_column("tag1")
+            .with_tag_column("tag2")
+            .with_i64_field_column("field_int")
+            .with_time_column()
+            .with_five_rows_of_data();
+
+        fixture
+            .test_storage
+            .db_or_create(db_info.db_name())
+            .await
+            .unwrap()
+            .add_chunk("my_partition_key", Arc::new(chunk));
+
+        let source = Some(StorageClient::read_source(&db_info, 1));
+
+        // 4 distinct tag sets with one field
+        let request = ReadSeriesCardinalityRequest {
+            read_series_cardinality_source: source.clone(),
+            range: Some(make_timestamp_range(0, 10000)),
+            predicate: None,
+        };
+
+        let actual_counts = fixture
+            .storage_client
+            .read_series_cardinality(request)
+            .await
+            .unwrap();
+        assert_eq!(actual_counts, vec![4]);
+
+        // only the rows at 50 and 100 are in the range
+        let request = ReadSeriesCardinalityRequest {
+            read_series_cardinality_source: source.clone(),
+            range: Some(make_timestamp_range(0, 1000)),
+            predicate: None,
+        };
+
+        let actual_counts = fixture
+            .storage_client
+            .read_series_cardinality(request)
+            .await
+            .unwrap();
+        assert_eq!(actual_counts, vec![2]);
+
+        grpc_request_metric_has_count(&fixture, "ReadSeriesCardinality", "ok", 2);
+    }
+
+    #[tokio::test]
+    async fn test_storage_rpc_read_series_cardinality_error() {
+        test_helpers::maybe_start_logging();
+        // Start a test gRPC server on a randomally allocated port
+        let mut fixture = Fixture::new().await.expect("Connecting to test server");
+
+        let db_info = org_and_bucket();
+
+        let chunk = TestChunk::new("my_table").with_error("Sugar we are going down");
+
+        fixture
+            .test_storage
+            .db_or_create(db_info.db_name())
+            .await
+            .unwrap()
+            .add_chunk("my_partition_key", Arc::new(chunk));
+
+        let source = Some(StorageClient::read_source(&db_info, 1));
+
+        let request = ReadSeriesCardinalityRequest {
+            read_series_cardinality_source: source.clone(),
+            range: None,
+            predicate: None,
+        };
+
+        let response_string = fixture
+            .storage_client
+            .read_series_cardinality(request)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert_contains!(response_string, "Sugar we are going down");
+
+        grpc_request_metric_has_count(&fixture, "ReadSeriesCardinality", "client_error", 1);
+    }
+
     /// test the plumbing of the RPC layer for tag_keys -- specifically that
     /// the right parameters are passed into the Database interface
     /// and that the returned values are sent back via gRPC.
@@ -1983,6 +2320,7 @@ mod tests {
             }],
             // old skool window definition
             window: None,
+            create_empty: false,
         };
 
         let frames = fixture
@@ -2048,6 +2386,7 @@ mod tests {
                     negative: false,
                 }),
             }),
+            create_empty: false,
         };
 
         let frames = fixture
@@ -2097,6 +2436,7 @@ mod tests {
             }],
             // old skool window definition
             window: None,
+            create_empty: false,
         };
 
         let response_string = fixture
@@ -2256,6 +2596,15 @@ mod tests {
         /// Start up a test storage server listening on `port`, returning
         /// a fixture with the test server and clients
         async fn new() -> Result<Self, FixtureError> {
+            Self::new_with_auth(Authorizer::disabled(), None).await
+        }
+
+        /// Start up a test storage server checking requests with `authorizer`,
+        /// returning a fixture with clients sending `token`
+        async fn new_with_auth(
+            authorizer: Authorizer,
+            token: Option<&str>,
+        ) -> Result<Self, FixtureError> {
             let test_storage = Arc::new(TestDatabaseStore::new());
 
             // Get a random port from the kernel by asking for port 0.
@@ -2285,6 +2634,7 @@ mod tests {
                 .add_service(
                     crate::influxdb_ioxd::server_type::database::rpc::storage::make_server(
                         Arc::clone(&test_storage),
+                        Arc::new(authorizer),
                     ),
                 );
 
@@ -2299,7 +2649,15 @@ mod tests {
 
             tokio::task::spawn(server);
 
-            let conn = ConnectionBuilder::default()
+            let builder = ConnectionBuilder::default();
+            let builder = match token {
+                Some(token) => builder.header(
+                    http::header::AUTHORIZATION,
+                    http::HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+                ),
+                None => builder,
+            };
+            let conn = builder
                 .connect_timeout(std::time::Duration::from_secs(30))
                 .build(format!("http://{}", bind_addr))
                 .await
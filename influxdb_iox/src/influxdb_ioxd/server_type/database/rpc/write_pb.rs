@@ -6,9 +6,11 @@ use server::Server;
 use std::sync::Arc;
 
 use super::error::{default_dml_error_handler, default_server_error_handler};
+use crate::influxdb_ioxd::auth::{AuthToken, Authorizer, Permission};
 
 struct PBWriteService {
     server: Arc<Server>,
+    authorizer: Arc<Authorizer>,
 }
 
 #[tonic::async_trait]
@@ -18,6 +20,7 @@ impl write_service_server::WriteService for PBWriteService {
         request: tonic::Request<WriteRequest>,
     ) -> Result<tonic::Response<WriteResponse>, tonic::Status> {
         let span_ctx = request.extensions().get().cloned();
+        let token = AuthToken::from_metadata(request.metadata());
         let database_batch = request
             .into_inner()
             .database_batch
@@ -35,6 +38,12 @@ impl write_service_server::WriteService for PBWriteService {
 
         let db_name = DatabaseName::new(&database_batch.database_name)
             .scope("database_batch.database_name")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Write,
+            "grpc write",
+        )?;
 
         let db = self
             .server
@@ -50,6 +59,7 @@ impl write_service_server::WriteService for PBWriteService {
 
 pub fn make_server(
     server: Arc<Server>,
+    authorizer: Arc<Authorizer>,
 ) -> write_service_server::WriteServiceServer<impl write_service_server::WriteService> {
-    write_service_server::WriteServiceServer::new(PBWriteService { server })
+    write_service_server::WriteServiceServer::new(PBWriteService { server, authorizer })
 }
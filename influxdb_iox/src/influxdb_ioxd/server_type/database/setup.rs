@@ -1,14 +1,19 @@
 use std::sync::Arc;
 
+use influxdb_iox_client::connection::Builder;
 use object_store::ObjectStore;
 use observability_deps::tracing::warn;
-use server::{ApplicationState, Server, ServerConfig};
+use query::exec::{admission::AdmissionConfig, memory::MemoryConfig};
+use server::{db::subscription::SubscriptionConfig, ApplicationState, Server, ServerConfig};
 use snafu::{ResultExt, Snafu};
 use trace::TraceCollector;
 
 use crate::{
     commands::run::database::Config,
-    structopt_blocks::object_store::{check_object_store, warn_about_inmem_store},
+    structopt_blocks::{
+        object_store::{check_object_store, warn_about_inmem_store},
+        tls::{configure_client, ClientTlsError},
+    },
 };
 
 #[derive(Debug, Snafu)]
@@ -22,6 +27,9 @@ pub enum Error {
     ObjectStoreCheck {
         source: crate::structopt_blocks::object_store::CheckError,
     },
+
+    #[snafu(display("Invalid subscription TLS config: {}", source))]
+    SubscriptionTls { source: ClientTlsError },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -38,9 +46,34 @@ pub async fn make_application(
         .context(ObjectStoreCheck)?;
     let object_storage = Arc::new(object_store);
 
+    let subscription_config = SubscriptionConfig {
+        file_dir: config.subscription_file_dir.clone(),
+        http_timeout: config.subscription_http_timeout,
+        grpc_builder: configure_client(
+            Builder::default(),
+            config.subscription_tls_ca_cert.as_deref(),
+            config.subscription_tls_cert.as_deref(),
+            config.subscription_tls_key.as_deref(),
+        )
+        .context(SubscriptionTls)?,
+        auth_token: config.subscription_token.clone(),
+    };
+
     Ok(Arc::new(ApplicationState::new(
         object_storage,
         config.num_worker_threads,
+        config.query_timeout,
+        MemoryConfig {
+            pool_size: config.query_memory_pool_size,
+            query_limit: config.query_memory_limit,
+            spill_dir: config.query_spill_dir.clone(),
+        },
+        AdmissionConfig {
+            max_concurrent_queries: config.max_concurrent_queries,
+            max_concurrent_queries_per_database: config.max_concurrent_queries_per_database,
+            max_queued_queries: config.max_queued_queries,
+        },
+        subscription_config,
         trace_collector,
     )))
 }
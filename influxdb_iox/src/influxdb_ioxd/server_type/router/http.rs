@@ -6,10 +6,13 @@ use dml::DmlOperation;
 use hyper::{Body, Method, Request, Response};
 use snafu::{ResultExt, Snafu};
 
-use crate::influxdb_ioxd::http::{
-    dml::{HttpDrivenDml, InnerDmlError, RequestOrResponse},
-    error::{HttpApiError, HttpApiErrorExt, HttpApiErrorSource},
-    metrics::LineProtocolMetrics,
+use crate::influxdb_ioxd::{
+    auth::Authorizer,
+    http::{
+        dml::{HttpDrivenDml, InnerDmlError, RequestOrResponse},
+        error::{HttpApiError, HttpApiErrorExt, HttpApiErrorSource},
+        metrics::LineProtocolMetrics,
+    },
 };
 
 use super::RouterServerType;
@@ -40,23 +43,34 @@ impl HttpDrivenDml for RouterServerType {
         self.max_request_size
     }
 
+    fn write_batch_size(&self) -> usize {
+        self.write_batch_size
+    }
+
     fn lp_metrics(&self) -> Arc<LineProtocolMetrics> {
         Arc::clone(&self.lp_metrics)
     }
 
+    fn authorizer(&self) -> &Authorizer {
+        &self.authorizer
+    }
+
     async fn write(
         &self,
         db_name: &DatabaseName<'_>,
         op: DmlOperation,
     ) -> Result<(), InnerDmlError> {
         match self.server.router(db_name) {
-            Some(router) => router
-                .write(op)
-                .await
-                .map_err(|e| InnerDmlError::InternalError {
+            Some(router) => router.write(op).await.map_err(|e| match e {
+                e @ router::router::WriteError::IngestRules { .. } => InnerDmlError::UserError {
                     db_name: db_name.to_string(),
                     source: Box::new(e),
-                }),
+                },
+                e => InnerDmlError::InternalError {
+                    db_name: db_name.to_string(),
+                    source: Box::new(e),
+                },
+            }),
             None => Err(InnerDmlError::DatabaseNotFound {
                 db_name: db_name.to_string(),
             }),
@@ -101,8 +115,9 @@ mod tests {
     use crate::influxdb_ioxd::{
         http::{
             dml::test_utils::{
-                assert_delete_bad_request, assert_delete_unknown_database, assert_gzip_write,
-                assert_write, assert_write_metrics, assert_write_to_invalid_database,
+                assert_delete_bad_request, assert_delete_unknown_database,
+                assert_dml_authorization, assert_gzip_write, assert_prometheus_write, assert_write,
+                assert_write_metrics, assert_write_to_invalid_database, TEST_AUTH_TOKENS,
             },
             test_utils::{
                 assert_health, assert_metrics, assert_tracing, check_response, TestServer,
@@ -142,6 +157,13 @@ mod tests {
         assert_dbwrite(test_server, DmlOperation::Write(write)).await;
     }
 
+    #[tokio::test]
+    async fn test_prometheus_write() {
+        let test_server = test_server().await;
+        let write = assert_prometheus_write(&test_server).await;
+        assert_dbwrite(test_server, DmlOperation::Write(write)).await;
+    }
+
     #[tokio::test]
     async fn test_write_metrics() {
         assert_write_metrics(test_server().await, false).await;
@@ -198,7 +220,17 @@ mod tests {
         assert_delete_bad_request(test_server().await).await;
     }
 
+    #[tokio::test]
+    async fn test_dml_authorization() {
+        let authorizer = Authorizer::try_from_json(TEST_AUTH_TOKENS.as_bytes()).unwrap();
+        assert_dml_authorization(test_server_with_authorizer(authorizer).await).await;
+    }
+
     async fn test_server() -> TestServer<RouterServerType> {
+        test_server_with_authorizer(Authorizer::disabled()).await
+    }
+
+    async fn test_server_with_authorizer(authorizer: Authorizer) -> TestServer<RouterServerType> {
         use data_types::router::{
             Matcher, MatcherToShard, Router, ShardConfig, ShardId, WriteSink, WriteSinkSet,
             WriteSinkVariant,
@@ -240,10 +272,12 @@ mod tests {
                 },
             )]),
             query_sinks: Default::default(),
+            ingest_rules: vec![],
         });
 
-        let server_type = Arc::new(RouterServerType::new(server, &common_state));
-        TestServer::new(server_type)
+        let mut server_type = RouterServerType::new(server, &common_state);
+        server_type.authorizer = Arc::new(authorizer);
+        TestServer::new(Arc::new(server_type))
     }
 
     async fn assert_dbwrite(test_server: TestServer<RouterServerType>, write: DmlOperation) {
@@ -8,6 +8,7 @@ use tokio_util::sync::CancellationToken;
 use trace::TraceCollector;
 
 use crate::influxdb_ioxd::{
+    auth::Authorizer,
     http::metrics::LineProtocolMetrics,
     rpc::RpcBuilderInput,
     server_type::{common_state::CommonServerState, RpcError, ServerType},
@@ -25,7 +26,9 @@ pub struct RouterServerType {
     serving_readiness: ServingReadiness,
     shutdown: CancellationToken,
     max_request_size: usize,
+    write_batch_size: usize,
     lp_metrics: Arc<LineProtocolMetrics>,
+    authorizer: Arc<Authorizer>,
 }
 
 impl RouterServerType {
@@ -37,7 +40,9 @@ impl RouterServerType {
             serving_readiness: common_state.serving_readiness().clone(),
             shutdown: CancellationToken::new(),
             max_request_size: common_state.run_config().max_http_request_size,
+            write_batch_size: common_state.run_config().http_write_batch_size,
             lp_metrics,
+            authorizer: common_state.authorizer(),
         }
     }
 }
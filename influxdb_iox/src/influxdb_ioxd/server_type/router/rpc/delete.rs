@@ -7,8 +7,11 @@ use generated_types::influxdata::iox::delete::v1::*;
 use router::server::RouterServer;
 use tonic::Response;
 
+use crate::influxdb_ioxd::auth::{AuthToken, Authorizer, Permission};
+
 struct DeleteService {
     server: Arc<RouterServer>,
+    authorizer: Arc<Authorizer>,
 }
 
 #[tonic::async_trait]
@@ -18,12 +21,19 @@ impl delete_service_server::DeleteService for DeleteService {
         request: tonic::Request<DeleteRequest>,
     ) -> Result<tonic::Response<DeleteResponse>, tonic::Status> {
         let span_ctx = request.extensions().get().cloned();
+        let token = AuthToken::from_metadata(request.metadata());
         let DeleteRequest { payload } = request.into_inner();
         let DeletePayload {
             db_name,
             table_name,
             predicate,
         } = payload.unwrap_field("payload")?;
+        self.authorizer.authorize(
+            &token,
+            Some(db_name.as_str()),
+            Permission::Write,
+            "grpc delete",
+        )?;
         let predicate = predicate.required("predicate")?;
 
         let table_name = NonEmptyString::new(table_name);
@@ -46,6 +56,7 @@ impl delete_service_server::DeleteService for DeleteService {
 
 pub fn make_server(
     server: Arc<RouterServer>,
+    authorizer: Arc<Authorizer>,
 ) -> delete_service_server::DeleteServiceServer<impl delete_service_server::DeleteService> {
-    delete_service_server::DeleteServiceServer::new(DeleteService { server })
+    delete_service_server::DeleteServiceServer::new(DeleteService { server, authorizer })
 }
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::influxdb_ioxd::{
+    auth::Permission,
     rpc::{add_gated_service, add_service, serve_builder, setup_builder, RpcBuilderInput},
     server_type::RpcError,
 };
@@ -9,6 +10,7 @@ use super::RouterServerType;
 
 mod delete;
 mod deployment;
+mod otlp;
 mod remote;
 mod router;
 mod write_pb;
@@ -19,28 +21,51 @@ pub async fn server_grpc(
 ) -> Result<(), RpcError> {
     let builder = setup_builder!(builder_input, server_type);
 
+    // The configuration services are not specific to a router
     add_service!(
         builder,
-        deployment::make_server(
-            Arc::clone(&server_type.server),
-            server_type.serving_readiness.clone(),
+        tonic::codegen::InterceptedService::new(
+            deployment::make_server(
+                Arc::clone(&server_type.server),
+                server_type.serving_readiness.clone(),
+            ),
+            Arc::clone(&server_type.authorizer).into_interceptor(Permission::Admin, "deployment"),
         )
     );
     add_service!(
         builder,
-        remote::make_server(Arc::clone(&server_type.server))
+        tonic::codegen::InterceptedService::new(
+            remote::make_server(Arc::clone(&server_type.server)),
+            Arc::clone(&server_type.authorizer).into_interceptor(Permission::Admin, "remote"),
+        )
     );
     add_service!(
         builder,
-        router::make_server(Arc::clone(&server_type.server))
+        tonic::codegen::InterceptedService::new(
+            router::make_server(Arc::clone(&server_type.server)),
+            Arc::clone(&server_type.authorizer).into_interceptor(Permission::Admin, "router"),
+        )
+    );
+    add_gated_service!(
+        builder,
+        delete::make_server(
+            Arc::clone(&server_type.server),
+            Arc::clone(&server_type.authorizer),
+        )
     );
     add_gated_service!(
         builder,
-        delete::make_server(Arc::clone(&server_type.server))
+        write_pb::make_server(
+            Arc::clone(&server_type.server),
+            Arc::clone(&server_type.authorizer),
+        )
     );
     add_gated_service!(
         builder,
-        write_pb::make_server(Arc::clone(&server_type.server))
+        otlp::make_server(
+            Arc::clone(&server_type.server),
+            Arc::clone(&server_type.authorizer),
+        )
     );
 
     serve_builder!(builder);
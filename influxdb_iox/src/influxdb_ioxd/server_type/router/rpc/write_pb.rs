@@ -4,8 +4,11 @@ use generated_types::influxdata::pbdata::v1::*;
 use router::server::RouterServer;
 use std::sync::Arc;
 
+use crate::influxdb_ioxd::auth::{AuthToken, Authorizer, Permission};
+
 struct PBWriteService {
     server: Arc<RouterServer>,
+    authorizer: Arc<Authorizer>,
 }
 
 #[tonic::async_trait]
@@ -15,10 +18,17 @@ impl write_service_server::WriteService for PBWriteService {
         request: tonic::Request<WriteRequest>,
     ) -> Result<tonic::Response<WriteResponse>, tonic::Status> {
         let span_ctx = request.extensions().get().cloned();
+        let token = AuthToken::from_metadata(request.metadata());
         let database_batch = request
             .into_inner()
             .database_batch
             .ok_or_else(|| FieldViolation::required("database_batch"))?;
+        self.authorizer.authorize(
+            &token,
+            Some(database_batch.database_name.as_str()),
+            Permission::Write,
+            "grpc write",
+        )?;
 
         let tables =
             mutable_batch_pb::decode::decode_database_batch(&database_batch).map_err(|e| {
@@ -46,6 +56,7 @@ impl write_service_server::WriteService for PBWriteService {
 
 pub fn make_server(
     server: Arc<RouterServer>,
+    authorizer: Arc<Authorizer>,
 ) -> write_service_server::WriteServiceServer<impl write_service_server::WriteService> {
-    write_service_server::WriteServiceServer::new(PBWriteService { server })
+    write_service_server::WriteServiceServer::new(PBWriteService { server, authorizer })
 }
@@ -0,0 +1,465 @@
+//! TLS for the HTTP and gRPC listeners
+//!
+//! The certificate files are checked for changes periodically and reloaded, so that
+//! certificates can be rotated without restarting the server. Connections established before a
+//! reload keep using the previous certificate.
+use std::{
+    fmt,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+
+use futures::{Stream, StreamExt};
+use observability_deps::tracing::{error, info, warn};
+use parking_lot::RwLock;
+use snafu::{ensure, ResultExt, Snafu};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_rustls::rustls::{
+    server::{AllowAnyAuthenticatedClient, NoClientAuth},
+    Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::structopt_blocks::tls::TlsConfig;
+
+/// How often the certificate files are checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long clients have to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum number of TLS handshakes performed concurrently per listener
+const MAX_CONCURRENT_HANDSHAKES: usize = 128;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("--tls-cert and --tls-key must be set together"))]
+    IncompleteConfig,
+
+    #[snafu(display("--tls-client-ca requires --tls-cert and --tls-key"))]
+    ClientCaWithoutCert,
+
+    #[snafu(display("Cannot read {}: {}", path.display(), source))]
+    ReadingFile { path: PathBuf, source: io::Error },
+
+    #[snafu(display("No certificate found in {}", path.display()))]
+    NoCertificate { path: PathBuf },
+
+    #[snafu(display("No private key found in {}", path.display()))]
+    NoPrivateKey { path: PathBuf },
+
+    #[snafu(display("Invalid certificate or key: {}", source))]
+    InvalidCertificate { source: tokio_rustls::rustls::Error },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The certificate files
+#[derive(Debug, Clone)]
+struct TlsFiles {
+    cert: PathBuf,
+    key: PathBuf,
+    client_ca: Option<PathBuf>,
+}
+
+impl TlsFiles {
+    fn paths(&self) -> impl Iterator<Item = &Path> {
+        [Some(&self.cert), Some(&self.key), self.client_ca.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(PathBuf::as_path)
+    }
+
+    /// Returns the modification times of the files, which are `None` if they can't be read
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        self.paths()
+            .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+
+    fn load(&self) -> Result<ServerConfig> {
+        let certs = read_certs(&self.cert)?
+            .into_iter()
+            .map(Certificate)
+            .collect();
+        let key = read_key(&self.key)?;
+
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let builder = match &self.client_ca {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                let (added, _) = roots.add_parsable_certificates(&read_certs(path)?);
+                ensure!(added > 0, NoCertificate { path });
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots))
+            }
+            None => builder.with_client_cert_verifier(NoClientAuth::new()),
+        };
+
+        let mut config = builder
+            .with_single_cert(certs, key)
+            .context(InvalidCertificate)?;
+
+        // gRPC requires HTTP/2, the HTTP API supports both
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        Ok(config)
+    }
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).context(ReadingFile { path })
+}
+
+/// Reads the DER encoded certificates from the PEM file at `path`
+fn read_certs(path: &Path) -> Result<Vec<Vec<u8>>> {
+    let pem = read_file(path)?;
+    let certs =
+        rustls_pemfile::certs(&mut BufReader::new(pem.as_slice())).context(ReadingFile { path })?;
+    ensure!(!certs.is_empty(), NoCertificate { path });
+    Ok(certs)
+}
+
+/// Reads the first private key from the PEM file at `path`
+fn read_key(path: &Path) -> Result<PrivateKey> {
+    let pem = read_file(path)?;
+    let items = rustls_pemfile::read_all(&mut BufReader::new(pem.as_slice()))
+        .context(ReadingFile { path })?;
+
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) => {
+                Some(PrivateKey(key))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| Error::NoPrivateKey { path: path.into() })
+}
+
+/// Performs the server side of TLS handshakes, with the current certificates
+#[derive(Clone)]
+pub struct TlsAcceptor {
+    files: Arc<TlsFiles>,
+    config: Arc<RwLock<Arc<ServerConfig>>>,
+}
+
+impl fmt::Debug for TlsAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsAcceptor")
+            .field("files", &self.files)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TlsAcceptor {
+    /// Loads the certificates configured in `config`, returning `None` if TLS is not enabled
+    pub fn from_config(config: &TlsConfig) -> Result<Option<Self>> {
+        let files = match (&config.tls_cert, &config.tls_key) {
+            (Some(cert), Some(key)) => TlsFiles {
+                cert: cert.clone(),
+                key: key.clone(),
+                client_ca: config.tls_client_ca.clone(),
+            },
+            (None, None) => {
+                ensure!(config.tls_client_ca.is_none(), ClientCaWithoutCert);
+                return Ok(None);
+            }
+            _ => return IncompleteConfig.fail(),
+        };
+
+        let server_config = files.load()?;
+        info!(
+            cert=%files.cert.display(),
+            client_auth=files.client_ca.is_some(),
+            "TLS enabled"
+        );
+
+        Ok(Some(Self {
+            files: Arc::new(files),
+            config: Arc::new(RwLock::new(Arc::new(server_config))),
+        }))
+    }
+
+    /// Reloads the certificates whenever the files change, until `shutdown` is triggered
+    ///
+    /// If the new certificates are invalid, an error is logged and the previous ones are kept.
+    pub async fn reload_on_change(self, shutdown: CancellationToken) {
+        let mut modified = self.files.modified();
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tokio::time::sleep(RELOAD_INTERVAL) => {},
+            }
+
+            let current = self.files.modified();
+            if current == modified {
+                continue;
+            }
+            modified = current;
+
+            match self.files.load() {
+                Ok(config) => {
+                    *self.config.write() = Arc::new(config);
+                    info!(cert=%self.files.cert.display(), "reloaded TLS certificates");
+                }
+                Err(e) => error!(%e, "cannot reload TLS certificates, keeping the previous ones"),
+            }
+        }
+    }
+
+    /// Performs the TLS handshake on the connections accepted from `incoming`
+    ///
+    /// Connections that fail the handshake, or don't complete it in time, are logged and
+    /// dropped, so that they don't stop the server.
+    pub fn accept<S, IO>(self, incoming: S) -> impl Stream<Item = io::Result<TlsStream<IO>>>
+    where
+        S: Stream<Item = io::Result<IO>>,
+        IO: AsyncRead + AsyncWrite + Unpin,
+    {
+        incoming
+            .map(move |conn| {
+                let acceptor = tokio_rustls::TlsAcceptor::from(Arc::clone(&*self.config.read()));
+                async move {
+                    let conn = match conn {
+                        Ok(conn) => conn,
+                        Err(e) => return Some(Err(e)),
+                    };
+
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(conn)).await {
+                        Ok(Ok(stream)) => Some(Ok(TlsStream(stream))),
+                        Ok(Err(e)) => {
+                            warn!(%e, "TLS handshake failed");
+                            None
+                        }
+                        Err(_) => {
+                            warn!("TLS handshake timed out");
+                            None
+                        }
+                    }
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_HANDSHAKES)
+            .filter_map(futures::future::ready)
+    }
+}
+
+/// A connection over which the server side of the TLS handshake completed
+#[derive(Debug)]
+pub struct TlsStream<IO>(tokio_rustls::server::TlsStream<IO>);
+
+impl<IO> TlsStream<IO> {
+    /// Returns the underlying connection
+    pub fn get_ref(&self) -> &IO {
+        self.0.get_ref().0
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin> AsyncRead for TlsStream<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin> AsyncWrite for TlsStream<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_shutdown(cx)
+    }
+}
+
+impl tonic::transport::server::Connected for TlsStream<tokio::net::TcpStream> {
+    type ConnectInfo = <tokio::net::TcpStream as tonic::transport::server::Connected>::ConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.get_ref().connect_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use influxdb_iox_client::{connection::Builder, health};
+    use structopt::StructOpt;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    fn fixture(name: &str) -> PathBuf {
+        [
+            env!("CARGO_MANIFEST_DIR"),
+            "..",
+            "test_fixtures",
+            "tls",
+            name,
+        ]
+        .iter()
+        .collect()
+    }
+
+    fn tls_config(args: &[&str]) -> TlsConfig {
+        TlsConfig::from_iter_safe(std::iter::once("not_used").chain(args.iter().copied())).unwrap()
+    }
+
+    fn server_config(client_ca: bool) -> TlsConfig {
+        let cert = fixture("server.pem");
+        let key = fixture("server.key");
+        let ca = fixture("ca.pem");
+
+        let mut args = vec![
+            "--tls-cert",
+            cert.to_str().unwrap(),
+            "--tls-key",
+            key.to_str().unwrap(),
+        ];
+        if client_ca {
+            args.extend(["--tls-client-ca", ca.to_str().unwrap()]);
+        }
+        tls_config(&args)
+    }
+
+    /// Serves the gRPC health service over TLS, returning the port
+    async fn serve_health(acceptor: TlsAcceptor) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let (_, health_service) = tonic_health::server::health_reporter();
+        let incoming = acceptor.accept(TcpListenerStream::new(listener));
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(health_service)
+                .serve_with_incoming(incoming),
+        );
+
+        port
+    }
+
+    #[test]
+    fn test_config() {
+        assert!(TlsAcceptor::from_config(&tls_config(&[]))
+            .unwrap()
+            .is_none());
+        assert!(TlsAcceptor::from_config(&server_config(true))
+            .unwrap()
+            .is_some());
+
+        let err = TlsAcceptor::from_config(&tls_config(&["--tls-cert", "foo"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--tls-cert and --tls-key must be set together"
+        );
+
+        let err = TlsAcceptor::from_config(&tls_config(&["--tls-client-ca", "foo"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--tls-client-ca requires --tls-cert and --tls-key"
+        );
+
+        let cert = fixture("server.pem");
+        let err = TlsAcceptor::from_config(&tls_config(&[
+            "--tls-cert",
+            cert.to_str().unwrap(),
+            "--tls-key",
+            cert.to_str().unwrap(),
+        ]))
+        .unwrap_err();
+        assert!(matches!(err, Error::NoPrivateKey { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_tls() {
+        let acceptor = TlsAcceptor::from_config(&server_config(false))
+            .unwrap()
+            .unwrap();
+        let port = serve_health(acceptor).await;
+        let url = format!("https://localhost:{}", port);
+
+        let ca = std::fs::read(fixture("ca.pem")).unwrap();
+        let connection = Builder::default()
+            .tls_ca_certificate(ca)
+            .build(&url)
+            .await
+            .unwrap();
+        assert!(health::Client::new(connection).check("").await.unwrap());
+
+        // the server certificate is not signed by a well-known CA
+        let connection = Builder::default().build(&url).await;
+        assert!(connection.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        let acceptor = TlsAcceptor::from_config(&server_config(true))
+            .unwrap()
+            .unwrap();
+        let port = serve_health(acceptor).await;
+        let url = format!("https://localhost:{}", port);
+
+        let ca = std::fs::read(fixture("ca.pem")).unwrap();
+        let cert = std::fs::read(fixture("client.pem")).unwrap();
+        let key = std::fs::read(fixture("client.key")).unwrap();
+
+        let connection = Builder::default()
+            .tls_ca_certificate(ca.clone())
+            .tls_client_identity(cert, key)
+            .build(&url)
+            .await
+            .unwrap();
+        assert!(health::Client::new(connection).check("").await.unwrap());
+
+        // clients must present a certificate
+        let result = match Builder::default().tls_ca_certificate(ca).build(&url).await {
+            Ok(connection) => health::Client::new(connection)
+                .check("")
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert = dir.path().join("cert.pem");
+        let key = dir.path().join("key.pem");
+        std::fs::copy(fixture("server.pem"), &cert).unwrap();
+        std::fs::copy(fixture("server.key"), &key).unwrap();
+
+        let files = TlsFiles {
+            cert: cert.clone(),
+            key,
+            client_ca: None,
+        };
+        let modified = files.modified();
+        assert!(modified.iter().all(Option::is_some));
+        files.load().unwrap();
+
+        // replacing the certificate changes the modification times, an invalid certificate
+        // fails to load
+        std::fs::remove_file(&cert).unwrap();
+        assert_ne!(files.modified(), modified);
+        std::fs::write(&cert, "invalid").unwrap();
+        assert!(matches!(
+            files.load().unwrap_err(),
+            Error::NoCertificate { .. }
+        ));
+    }
+}
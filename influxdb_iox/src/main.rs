@@ -14,7 +14,7 @@ use dotenv::dotenv;
 use influxdb_iox_client::connection::Builder;
 use observability_deps::tracing::warn;
 use once_cell::sync::Lazy;
-use std::str::FromStr;
+use std::{path::PathBuf, str::FromStr};
 use structopt::StructOpt;
 use tokio::runtime::Runtime;
 
@@ -134,6 +134,20 @@ struct Config {
     #[structopt(long, global = true)]
     header: Vec<KeyValue<http::header::HeaderName, http::HeaderValue>>,
 
+    /// Path of the PEM encoded CA certificate used to verify the certificate
+    /// of `https` hosts, instead of the well-known root certificates
+    #[structopt(long, global = true, env = "IOX_TLS_CA_CERT", parse(from_os_str))]
+    tls_ca_cert: Option<PathBuf>,
+
+    /// Path of the PEM encoded certificate presented to `https` hosts that
+    /// require client certificates
+    #[structopt(long, global = true, env = "IOX_TLS_CLIENT_CERT", parse(from_os_str))]
+    tls_client_cert: Option<PathBuf>,
+
+    /// Path of the PEM encoded private key of `--tls-client-cert`
+    #[structopt(long, global = true, env = "IOX_TLS_CLIENT_KEY", parse(from_os_str))]
+    tls_client_key: Option<PathBuf>,
+
     #[structopt(long)]
     /// Set the maximum number of threads to use. Defaults to the number of
     /// cores on the system
@@ -167,6 +181,9 @@ fn main() -> Result<(), std::io::Error> {
     tokio_runtime.block_on(async move {
         let host = config.host;
         let headers = config.header;
+        let tls_ca_cert = config.tls_ca_cert;
+        let tls_client_cert = config.tls_client_cert;
+        let tls_client_key = config.tls_client_key;
         let log_verbose_count = config.log_verbose_count;
 
         let connection = || async move {
@@ -174,6 +191,19 @@ fn main() -> Result<(), std::io::Error> {
                 builder.header(kv.key, kv.value)
             });
 
+            let builder = match structopt_blocks::tls::configure_client(
+                builder,
+                tls_ca_cert.as_deref(),
+                tls_client_cert.as_deref(),
+                tls_client_key.as_deref(),
+            ) {
+                Ok(builder) => builder,
+                Err(e) => {
+                    eprintln!("Invalid TLS configuration: {}", e);
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            };
+
             match builder.build(&host).await {
                 Ok(connection) => connection,
                 Err(e) => {
@@ -6,3 +6,4 @@ pub mod object_store;
 pub mod run_config;
 pub mod server_id;
 pub mod socket_addr;
+pub mod tls;
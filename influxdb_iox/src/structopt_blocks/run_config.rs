@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use structopt::StructOpt;
 use trace_exporters::TracingConfig;
 use trogging::cli::LoggingConfig;
@@ -5,7 +7,8 @@ use trogging::cli::LoggingConfig;
 use crate::{
     influxdb_ioxd::serving_readiness::ServingReadinessState,
     structopt_blocks::{
-        object_store::ObjectStoreConfig, server_id::ServerIdConfig, socket_addr::SocketAddr,
+        boolean_flag::BooleanFlag, object_store::ObjectStoreConfig, server_id::ServerIdConfig,
+        socket_addr::SocketAddr, tls::TlsConfig,
     },
 };
 
@@ -56,6 +59,9 @@ pub struct RunConfig {
     pub initial_serving_state: ServingReadinessState,
 
     /// Maximum size of HTTP requests.
+    ///
+    /// Line protocol writes are processed as they are received, so for them
+    /// this limits the size of a single line rather than the whole request.
     #[structopt(
         long = "--max-http-request-size",
         env = "INFLUXDB_IOX_MAX_HTTP_REQUEST_SIZE",
@@ -63,6 +69,45 @@ pub struct RunConfig {
     )]
     pub max_http_request_size: usize,
 
+    /// Size of the line protocol batches that HTTP writes are split into.
+    ///
+    /// Line protocol received over HTTP is converted and written in batches
+    /// of roughly this many bytes, so that large writes don't need to be
+    /// held in memory in their entirety.
+    #[structopt(
+        long = "--http-write-batch-size",
+        env = "INFLUXDB_IOX_HTTP_WRITE_BATCH_SIZE",
+        default_value = "10485760" // 10 MiB
+    )]
+    pub http_write_batch_size: usize,
+
+    /// Path of a JSON file defining the API tokens accepted by the server
+    /// and the permissions they grant.
+    ///
+    /// If no tokens are configured, either with this option or with
+    /// `--auth-tokens-in-object-store`, requests are not authenticated.
+    #[structopt(
+        long = "--auth-tokens-file",
+        env = "INFLUXDB_IOX_AUTH_TOKENS_FILE",
+        parse(from_os_str)
+    )]
+    pub auth_tokens_file: Option<PathBuf>,
+
+    /// Read the API tokens accepted by the server from the `tokens.json`
+    /// file stored next to the server config in the object store.
+    ///
+    /// Requires the server ID to be set with `--server-id`.
+    #[structopt(
+        long = "--auth-tokens-in-object-store",
+        env = "INFLUXDB_IOX_AUTH_TOKENS_IN_OBJECT_STORE",
+        default_value = "no"
+    )]
+    pub auth_tokens_in_object_store: BooleanFlag,
+
+    /// TLS options
+    #[structopt(flatten)]
+    pub(crate) tls_config: TlsConfig,
+
     /// object store config
     #[structopt(flatten)]
     pub(crate) object_store_config: ObjectStoreConfig,
@@ -0,0 +1,68 @@
+use std::path::{Path, PathBuf};
+
+use influxdb_iox_client::connection::Builder;
+use snafu::{ResultExt, Snafu};
+use structopt::StructOpt;
+
+#[derive(Debug, Snafu)]
+pub enum ClientTlsError {
+    #[snafu(display("Cannot read {}: {}", path.display(), source))]
+    ReadingFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("A TLS client certificate requires a private key, and vice versa"))]
+    IncompleteIdentity,
+}
+
+/// CLI config for serving the HTTP and gRPC APIs over TLS.
+#[derive(Debug, StructOpt, Clone)]
+pub struct TlsConfig {
+    /// Path of the PEM encoded certificate chain presented by the HTTP and
+    /// gRPC listeners.
+    ///
+    /// If set, both listeners only accept TLS connections. The certificate
+    /// and key files are reloaded when they change.
+    #[structopt(long = "--tls-cert", env = "INFLUXDB_IOX_TLS_CERT", parse(from_os_str))]
+    pub tls_cert: Option<PathBuf>,
+
+    /// Path of the PEM encoded private key of the `--tls-cert` certificate.
+    #[structopt(long = "--tls-key", env = "INFLUXDB_IOX_TLS_KEY", parse(from_os_str))]
+    pub tls_key: Option<PathBuf>,
+
+    /// Path of the PEM encoded CA certificates that client certificates must
+    /// be signed by.
+    ///
+    /// If set, clients must present a certificate (mutual TLS).
+    #[structopt(
+        long = "--tls-client-ca",
+        env = "INFLUXDB_IOX_TLS_CLIENT_CA",
+        parse(from_os_str)
+    )]
+    pub tls_client_ca: Option<PathBuf>,
+}
+
+/// Configures `builder` to connect to `https` servers with the PEM encoded
+/// CA certificate at `ca_cert`, instead of the well-known root certificates,
+/// and to present the client certificate at `cert` with the private key at
+/// `key`, if set.
+pub fn configure_client(
+    builder: Builder,
+    ca_cert: Option<&Path>,
+    cert: Option<&Path>,
+    key: Option<&Path>,
+) -> Result<Builder, ClientTlsError> {
+    let read = |path: &Path| std::fs::read(path).context(ReadingFile { path });
+
+    let builder = match ca_cert {
+        Some(ca_cert) => builder.tls_ca_certificate(read(ca_cert)?),
+        None => builder,
+    };
+
+    match (cert, key) {
+        (Some(cert), Some(key)) => Ok(builder.tls_client_identity(read(cert)?, read(key)?)),
+        (None, None) => Ok(builder),
+        _ => IncompleteIdentity.fail(),
+    }
+}
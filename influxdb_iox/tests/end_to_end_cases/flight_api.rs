@@ -1,6 +1,24 @@
+use std::convert::TryFrom;
+
 use super::scenario::{create_readable_database, rand_name, Scenario};
 use crate::common::server_fixture::{ServerFixture, ServerType};
+use arrow::{array::StringArray, datatypes::Schema, record_batch::RecordBatch};
+use arrow_flight::{
+    flight_descriptor::DescriptorType, flight_service_client::FlightServiceClient,
+    utils::flight_data_to_arrow_batch, Action, Criteria, FlightDescriptor,
+};
 use arrow_util::assert_batches_sorted_eq;
+use futures::StreamExt;
+use generated_types::{
+    arrow::flight::protocol::sql::{
+        ActionCreatePreparedStatementRequest, ActionCreatePreparedStatementResult,
+        CommandGetSqlInfo, CommandGetTables, CommandPreparedStatementQuery, CommandStatementQuery,
+    },
+    google::protobuf::Any,
+    protobuf_type_url,
+};
+use influxdb_iox_client::connection::Connection;
+use prost::Message;
 
 #[tokio::test]
 pub async fn test() {
@@ -50,3 +68,167 @@ pub async fn test_no_rows() {
     let batch = query_results.next().await.unwrap();
     assert!(batch.is_none());
 }
+
+/// Wraps a Flight SQL message into an encoded `google.protobuf.Any`
+fn flight_sql_any(type_name: &str, msg: &impl Message) -> Vec<u8> {
+    Any {
+        type_url: protobuf_type_url(&format!("arrow.flight.protocol.sql.{}", type_name)),
+        value: msg.encode_to_vec().into(),
+    }
+    .encode_to_vec()
+}
+
+/// Runs a Flight SQL command via `get_flight_info` + `do_get`
+async fn flight_sql_get(
+    client: &mut FlightServiceClient<Connection>,
+    db_name: &str,
+    cmd: Vec<u8>,
+) -> Vec<RecordBatch> {
+    let mut request = tonic::Request::new(FlightDescriptor {
+        r#type: DescriptorType::Cmd as i32,
+        cmd,
+        path: vec![],
+    });
+    request
+        .metadata_mut()
+        .insert("iox-database", db_name.parse().unwrap());
+
+    let info = client.get_flight_info(request).await.unwrap().into_inner();
+    let ticket = info.endpoint[0].ticket.clone().unwrap();
+
+    let mut stream = client.do_get(ticket).await.unwrap().into_inner();
+
+    let schema_data = stream.next().await.unwrap().unwrap();
+    let schema = std::sync::Arc::new(Schema::try_from(&schema_data).unwrap());
+    let dictionaries = vec![None; schema.fields().len()];
+
+    let mut batches = vec![];
+    while let Some(data) = stream.next().await {
+        let batch = flight_data_to_arrow_batch(
+            &data.unwrap(),
+            std::sync::Arc::clone(&schema),
+            &dictionaries,
+        )
+        .unwrap();
+        batches.push(batch);
+    }
+    batches
+}
+
+#[tokio::test]
+pub async fn test_flight_sql() {
+    let server_fixture = ServerFixture::create_shared(ServerType::Database).await;
+
+    let mut write_client = server_fixture.write_client();
+    let mut management_client = server_fixture.management_client();
+
+    let scenario = Scenario::new();
+    scenario.create_database(&mut management_client).await;
+
+    let expected_read_data = scenario.load_data(&mut write_client).await;
+    let expected_read_data: Vec<_> = expected_read_data.iter().map(|s| s.as_str()).collect();
+    let db_name = scenario.database_name().to_string();
+
+    let mut client = FlightServiceClient::new(server_fixture.grpc_channel());
+
+    // ad-hoc statement
+    let cmd = CommandStatementQuery {
+        query: "select * from cpu_load_short".to_string(),
+    };
+    let batches = flight_sql_get(
+        &mut client,
+        &db_name,
+        flight_sql_any("CommandStatementQuery", &cmd),
+    )
+    .await;
+    assert_batches_sorted_eq!(&expected_read_data, &batches);
+
+    // prepared statement
+    let cmd = ActionCreatePreparedStatementRequest {
+        query: "select * from cpu_load_short".to_string(),
+    };
+    let mut request = tonic::Request::new(Action {
+        r#type: "CreatePreparedStatement".to_string(),
+        body: flight_sql_any("ActionCreatePreparedStatementRequest", &cmd),
+    });
+    request
+        .metadata_mut()
+        .insert("iox-database", db_name.parse().unwrap());
+    let result = client
+        .do_action(request)
+        .await
+        .unwrap()
+        .into_inner()
+        .next()
+        .await
+        .unwrap()
+        .unwrap();
+    let any = Any::decode(result.body.as_slice()).unwrap();
+    let result = ActionCreatePreparedStatementResult::decode(any.value).unwrap();
+    assert!(!result.dataset_schema.is_empty());
+
+    let cmd = CommandPreparedStatementQuery {
+        prepared_statement_handle: result.prepared_statement_handle,
+    };
+    let batches = flight_sql_get(
+        &mut client,
+        &db_name,
+        flight_sql_any("CommandPreparedStatementQuery", &cmd),
+    )
+    .await;
+    assert_batches_sorted_eq!(&expected_read_data, &batches);
+
+    // table listing
+    let cmd = CommandGetTables {
+        catalog: db_name.clone(),
+        db_schema_filter_pattern: "iox".to_string(),
+        table_name_filter_pattern: "cpu%".to_string(),
+        table_types: vec![],
+        include_schema: false,
+    };
+    let batches = flight_sql_get(
+        &mut client,
+        &db_name,
+        flight_sql_any("CommandGetTables", &cmd),
+    )
+    .await;
+
+    let batches: Vec<_> = batches
+        .into_iter()
+        .filter(|batch| batch.num_rows() > 0)
+        .collect();
+    assert_eq!(batches.len(), 1);
+    let table_names = batches[0]
+        .column(2)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    let table_types = batches[0]
+        .column(3)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!(table_names.len(), 1);
+    assert_eq!(table_names.value(0), "cpu_load_short");
+    assert_eq!(table_types.value(0), "BASE TABLE");
+
+    // there are no standing flights
+    let flights: Vec<_> = client
+        .list_flights(Criteria::default())
+        .await
+        .unwrap()
+        .into_inner()
+        .collect()
+        .await;
+    assert!(flights.is_empty());
+
+    // SQL server metadata, requested by JDBC clients when connecting
+    let batches = flight_sql_get(
+        &mut client,
+        &db_name,
+        flight_sql_any("CommandGetSqlInfo", &CommandGetSqlInfo { info: vec![] }),
+    )
+    .await;
+    let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+    assert_eq!(rows, 3);
+}
@@ -229,6 +229,10 @@ async fn test_create_get_update_release_claim_database() {
             nanos: 0,
         }),
         write_buffer_connection: None,
+        schema: None,
+        ingest_rules: vec![],
+        continuous_queries: vec![],
+        subscriptions: vec![],
     };
 
     let created_uuid = client
@@ -528,6 +532,72 @@ async fn test_create_get_update_database_omit_defaults() {
     assert_rule_defaults(&mut client, &db_name, &rules).await;
 }
 
+#[tokio::test]
+async fn test_lock_database_schema() {
+    let fixture = ServerFixture::create_shared(ServerType::Database).await;
+    let mut management_client = fixture.management_client();
+    let mut write_client = fixture.write_client();
+
+    let db_name = rand_name();
+    create_readable_database(&db_name, fixture.grpc_channel()).await;
+
+    let mut rules = management_client
+        .get_database(&db_name, false)
+        .await
+        .expect("get database failed");
+    rules.schema = Some(DatabaseSchema {
+        mode: database_schema::Mode::Learn.into(),
+        tables: vec![],
+    });
+    management_client
+        .update_database(rules)
+        .await
+        .expect("update database failed");
+
+    write_client
+        .write_lp(&db_name, "cpu,region=west user=23.2 100", 0)
+        .await
+        .expect("write succeded");
+
+    let rules = management_client
+        .lock_database_schema(&db_name)
+        .await
+        .expect("lock schema failed");
+    let schema = rules.schema.unwrap();
+    assert_eq!(schema.mode, database_schema::Mode::Strict as i32);
+    assert_eq!(
+        schema.tables,
+        vec![TableSchema {
+            name: "cpu".to_string(),
+            tags: vec!["region".to_string()],
+            fields: vec![FieldSchema {
+                name: "user".to_string(),
+                r#type: field_schema::FieldType::Float.into(),
+            }],
+        }]
+    );
+
+    write_client
+        .write_lp(&db_name, "cpu,region=east user=21.0 150", 0)
+        .await
+        .expect("write succeded");
+
+    let err = write_client
+        .write_lp(&db_name, "cpu,region=east usr=21.0 150", 0)
+        .await
+        .expect_err("write to undeclared column succeeded");
+    assert_contains!(
+        err.to_string(),
+        "Column(s) usr of table 'cpu' are not declared in the database schema"
+    );
+
+    let err = management_client
+        .lock_database_schema(rand_name())
+        .await
+        .expect_err("locked unknown database");
+    assert!(matches!(err, Error::NotFound(_)));
+}
+
 #[tokio::test]
 async fn test_chunk_get() {
     use generated_types::influxdata::iox::management::v1::{
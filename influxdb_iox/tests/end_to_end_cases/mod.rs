@@ -17,6 +17,7 @@ mod management_cli;
 mod metrics;
 mod operations_api;
 mod operations_cli;
+mod otlp;
 mod persistence;
 mod read_api;
 mod read_cli;
@@ -0,0 +1,136 @@
+use super::scenario::{create_readable_database, create_router_to_write_buffer, rand_name};
+use crate::common::server_fixture::{ServerFixture, ServerType};
+use arrow_util::assert_batches_sorted_eq;
+use dml::{test_util::assert_write_op_eq, DmlWrite};
+use futures::StreamExt;
+use generated_types::opentelemetry::proto::{
+    collector::metrics::v1::{metrics_service_client::MetricsServiceClient, *},
+    common::v1::{any_value, AnyValue, KeyValue},
+    metrics::v1::{
+        metric, number_data_point, Gauge, InstrumentationLibraryMetrics, Metric, NumberDataPoint,
+        ResourceMetrics,
+    },
+};
+use mutable_batch_lp::lines_to_batches;
+
+#[tokio::test]
+pub async fn test_otlp_export_database() {
+    let fixture = ServerFixture::create_shared(ServerType::Database).await;
+
+    let db_name = rand_name();
+    create_readable_database(&db_name, fixture.grpc_channel()).await;
+
+    MetricsServiceClient::new(fixture.grpc_channel())
+        .export(export_request(&db_name))
+        .await
+        .expect("cannot export");
+
+    let mut query_results = fixture
+        .flight_client()
+        .perform_query(&db_name, "select * from cpu")
+        .await
+        .unwrap();
+
+    let mut batches = Vec::new();
+    while let Some(data) = query_results.next().await.unwrap() {
+        batches.push(data);
+    }
+
+    let expected = vec![
+        "+------+--------------------------------+-------+",
+        "| host | time                           | value |",
+        "+------+--------------------------------+-------+",
+        "| a    | 1970-01-01T00:00:00.000000003Z | 0.5   |",
+        "+------+--------------------------------+-------+",
+    ];
+    assert_batches_sorted_eq!(&expected, &batches);
+}
+
+#[tokio::test]
+pub async fn test_otlp_export_router() {
+    let fixture = ServerFixture::create_shared(ServerType::Router).await;
+
+    let db_name = rand_name();
+    let (_tmpdir, mut write_buffer) = create_router_to_write_buffer(&fixture, &db_name).await;
+
+    MetricsServiceClient::new(fixture.grpc_channel())
+        .export(export_request(&db_name))
+        .await
+        .expect("cannot export");
+
+    let mut stream = write_buffer.streams().into_values().next().unwrap();
+    let write_actual = stream.stream.next().await.unwrap().unwrap();
+    let write_expected = DmlWrite::new(
+        lines_to_batches("cpu,host=a value=0.5 3", 0).unwrap(),
+        // We don't care about the metadata here, timestamps and sequence numbers are hard to guess
+        write_actual.meta().clone(),
+    );
+    assert_write_op_eq(&write_actual, &write_expected);
+}
+
+#[tokio::test]
+pub async fn test_otlp_export_empty() {
+    let fixture = ServerFixture::create_shared(ServerType::Database).await;
+
+    let db_name = rand_name();
+    create_readable_database(&db_name, fixture.grpc_channel()).await;
+
+    let mut request = tonic::Request::new(ExportMetricsServiceRequest::default());
+    request
+        .metadata_mut()
+        .insert("iox-database", db_name.parse().unwrap());
+
+    MetricsServiceClient::new(fixture.grpc_channel())
+        .export(request)
+        .await
+        .expect("empty export should be accepted");
+}
+
+#[tokio::test]
+pub async fn test_otlp_export_missing_database() {
+    let fixture = ServerFixture::create_shared(ServerType::Database).await;
+
+    let status = MetricsServiceClient::new(fixture.grpc_channel())
+        .export(ExportMetricsServiceRequest::default())
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
+fn export_request(db_name: &str) -> tonic::Request<ExportMetricsServiceRequest> {
+    let point = NumberDataPoint {
+        attributes: vec![KeyValue {
+            key: "host".to_string(),
+            value: Some(AnyValue {
+                value: Some(any_value::Value::StringValue("a".to_string())),
+            }),
+        }],
+        start_time_unix_nano: 0,
+        time_unix_nano: 3,
+        value: Some(number_data_point::Value::AsDouble(0.5)),
+        flags: 0,
+    };
+
+    let mut request = tonic::Request::new(ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            resource: None,
+            instrumentation_library_metrics: vec![InstrumentationLibraryMetrics {
+                instrumentation_library: None,
+                metrics: vec![Metric {
+                    name: "cpu".to_string(),
+                    description: String::new(),
+                    unit: String::new(),
+                    data: Some(metric::Data::Gauge(Gauge {
+                        data_points: vec![point],
+                    })),
+                }],
+                schema_url: String::new(),
+            }],
+            schema_url: String::new(),
+        }],
+    });
+    request
+        .metadata_mut()
+        .insert("iox-database", db_name.parse().unwrap());
+    request
+}
@@ -19,6 +19,7 @@ pub async fn test() {
     test_read_format_pretty(&db_name, addr).await;
     test_read_format_csv(&db_name, addr).await;
     test_read_format_json(&db_name, addr).await;
+    test_read_format_line_protocol(&db_name, addr).await;
     test_read_error(&db_name, addr).await;
 }
 
@@ -167,6 +168,24 @@ async fn test_read_format_json(db_name: &str, addr: &str) {
         .stdout(predicate::str::contains(expected));
 }
 
+async fn test_read_format_line_protocol(db_name: &str, addr: &str) {
+    let expected = "cpu user=23.2 100\ncpu user=21 150\n";
+
+    Command::cargo_bin("influxdb_iox")
+        .unwrap()
+        .arg("database")
+        .arg("query")
+        .arg(db_name)
+        .arg("select 'cpu' as _measurement, user, time from cpu order by time")
+        .arg("--host")
+        .arg(addr)
+        .arg("--format")
+        .arg("line_protocol")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(expected));
+}
+
 async fn test_read_error(db_name: &str, addr: &str) {
     Command::cargo_bin("influxdb_iox")
         .unwrap()
@@ -195,6 +214,7 @@ async fn test_read_error(db_name: &str, addr: &str) {
         .assert()
         .failure()
         .stderr(predicate::str::contains(
-            "Unknown format type: not_a_valid_format. Expected one of 'pretty', 'csv' or 'json'",
+            "Unknown format type: not_a_valid_format. Expected one of 'pretty', 'csv', 'json', \
+             'jsonl', 'arrow', 'parquet' or 'line_protocol'",
         ));
 }
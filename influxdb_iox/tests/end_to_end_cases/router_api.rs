@@ -27,6 +27,7 @@ async fn test_router_crud() {
         write_sharder: Default::default(),
         write_sinks: Default::default(),
         query_sinks: Default::default(),
+        ingest_rules: vec![],
     };
     let cfg_foo_2 = Router {
         query_sinks: Some(QuerySinks {
@@ -41,6 +42,7 @@ async fn test_router_crud() {
         write_sharder: Default::default(),
         write_sinks: Default::default(),
         query_sinks: Default::default(),
+        ingest_rules: vec![],
     };
 
     // no routers
@@ -91,6 +93,7 @@ async fn test_router_update_invalid_argument() {
         write_sharder: Default::default(),
         write_sinks: Default::default(),
         query_sinks: Default::default(),
+        ingest_rules: vec![],
     };
     let cfg_invalid = Router {
         write_sharder: Some(ShardConfig {
@@ -412,6 +412,10 @@ impl DatabaseBuilder {
                 lifecycle_rules: Some(self.lifecycle_rules),
                 worker_cleanup_avg_sleep: None,
                 write_buffer_connection: self.write_buffer,
+                schema: None,
+                ingest_rules: vec![],
+                continuous_queries: vec![],
+                subscriptions: vec![],
             })
             .await
     }
@@ -835,6 +839,7 @@ pub fn wildcard_router_config(
             },
         )]),
         query_sinks: Default::default(),
+        ingest_rules: vec![],
     }
 }
 
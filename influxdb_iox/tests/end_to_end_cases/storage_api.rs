@@ -13,8 +13,8 @@ use generated_types::{
     storage_client::StorageClient,
     Aggregate, MeasurementFieldsRequest, MeasurementNamesRequest, MeasurementTagKeysRequest,
     MeasurementTagValuesRequest, Node, OffsetsResponse, Predicate, ReadFilterRequest,
-    ReadGroupRequest, ReadWindowAggregateRequest, Tag, TagKeysRequest, TagValuesRequest,
-    TimestampRange,
+    ReadGroupRequest, ReadSeriesCardinalityRequest, ReadWindowAggregateRequest, Tag,
+    TagKeysRequest, TagValuesRequest, TimestampRange,
 };
 use influxdb_iox_client::connection::Connection;
 use influxdb_storage_client::tag_key_bytes_to_strings;
@@ -39,6 +39,7 @@ pub async fn test() {
     measurement_tag_keys_endpoint(&mut storage_client, &scenario).await;
     measurement_tag_values_endpoint(&mut storage_client, &scenario).await;
     measurement_fields_endpoint(&mut storage_client, &scenario).await;
+    read_series_cardinality_endpoint(&mut storage_client, &scenario).await;
 }
 
 /// Validate that capabilities storage endpoint is hooked up
@@ -54,7 +55,7 @@ async fn capabilities_endpoint() {
         .into_inner();
     assert_eq!(
         capabilities_response.caps.len(),
-        2,
+        3,
         "Response: {:?}",
         capabilities_response
     );
@@ -316,6 +317,36 @@ async fn measurement_fields_endpoint(
     assert_eq!(field.timestamp, scenario.ns_since_epoch() + 4);
 }
 
+async fn read_series_cardinality_endpoint(
+    storage_client: &mut StorageClient<Connection>,
+    scenario: &Scenario,
+) {
+    let read_source = scenario.read_source();
+    let range = scenario.timestamp_range();
+    let predicate = make_tag_predicate("host", "server01");
+    let predicate = Some(predicate);
+
+    let read_series_cardinality_request = tonic::Request::new(ReadSeriesCardinalityRequest {
+        read_series_cardinality_source: read_source,
+        range,
+        predicate,
+    });
+
+    let read_series_cardinality_response = storage_client
+        .read_series_cardinality(read_series_cardinality_request)
+        .await
+        .unwrap();
+    let responses: Vec<_> = read_series_cardinality_response
+        .into_inner()
+        .try_collect()
+        .await
+        .unwrap();
+
+    // the series returned by read_filter_endpoint
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0].values, vec![5]);
+}
+
 #[tokio::test]
 pub async fn regex_operator_test() {
     let fixture = ServerFixture::create_shared(ServerType::Database).await;
@@ -697,6 +728,7 @@ pub async fn read_window_aggregate_test() {
             r#type: AggregateType::Sum as i32,
         }],
         window: None,
+        create_empty: false,
     };
 
     let response = storage_client.read_window_aggregate(request).await.unwrap();
@@ -725,6 +757,50 @@ pub async fn read_window_aggregate_test() {
         expected_frames.join("\n"),
         actual_frames.join("\n")
     );
+
+    // now ask for the empty windows as well
+    let request = ReadWindowAggregateRequest {
+        read_source: read_source.clone(),
+        range: Some(TimestampRange {
+            start: 200,
+            end: 1200,
+        }),
+        predicate: Some(make_tag_predicate("state", "MA")),
+        window_every: 200,
+        offset: 0,
+        aggregate: vec![Aggregate {
+            r#type: AggregateType::Count as i32,
+        }],
+        window: None,
+        create_empty: true,
+    };
+
+    let response = storage_client.read_window_aggregate(request).await.unwrap();
+
+    let responses: Vec<_> = response.into_inner().try_collect().await.unwrap();
+
+    let frames: Vec<_> = responses
+        .into_iter()
+        .flat_map(|r| r.frames)
+        .flat_map(|f| f.data)
+        .collect();
+
+    let expected_frames = vec![
+        "SeriesFrame, tags: _measurement=h2o,city=Boston,state=MA,_field=temp, type: 1",
+        "IntegerPointsFrame, timestamps: [400, 600, 800, 1000, 1200], values: \"2,2,0,0,0\"",
+        "SeriesFrame, tags: _measurement=h2o,city=Cambridge,state=MA,_field=temp, type: 1",
+        "IntegerPointsFrame, timestamps: [400, 600, 800, 1000, 1200], values: \"2,2,0,0,0\"",
+    ];
+
+    let actual_frames = dump_data_frames(&frames);
+
+    assert_eq!(
+        expected_frames,
+        actual_frames,
+        "Expected:\n{}\nActual:\n{}",
+        expected_frames.join("\n"),
+        actual_frames.join("\n")
+    );
 }
 
 /// Create a predicate representing tag_name=tag_value in the horrible gRPC
@@ -115,7 +115,7 @@ async fn test_queries() {
         .unwrap();
 
     // Note: don't select issue_time as that changes from run to run
-    let query = "select query_type, query_text from system.queries";
+    let query = "select query_type, query_text, priority from system.queries";
 
     // Query system.queries and should have an entry for the storage rpc
     let batches = fixture
@@ -129,21 +129,21 @@ async fn test_queries() {
     let batches = normalize_batches(batches, scenario.normalizer());
 
     let expected_read_data = vec![
-        "+-------------+---------------------------------------------------+",
-        "| query_type  | query_text                                        |",
-        "+-------------+---------------------------------------------------+",
-        "| read_filter | {                                                 |",
-        "|             |   \"ReadSource\": {                                 |",
-        "|             |     \"typeUrl\": \"/TODO\",                           |",
-        "|             |     \"value\": \"ZZZZZZZZZZZZZZZZ\"                   |",
-        "|             |   },                                              |",
-        "|             |   \"range\": {                                      |",
-        "|             |     \"start\": \"111111\",                            |",
-        "|             |     \"end\": \"222222\"                               |",
-        "|             |   }                                               |",
-        "|             | }                                                 |",
-        "| sql         | select query_type, query_text from system.queries |",
-        "+-------------+---------------------------------------------------+",
+        "+-------------+-------------------------------------------------------------+-------------+",
+        "| query_type  | query_text                                                  | priority    |",
+        "+-------------+-------------------------------------------------------------+-------------+",
+        "| read_filter | {                                                           | interactive |",
+        "|             |   \"ReadSource\": {                                           |             |",
+        "|             |     \"typeUrl\": \"/TODO\",                                     |             |",
+        "|             |     \"value\": \"ZZZZZZZZZZZZZZZZ\"                             |             |",
+        "|             |   },                                                        |             |",
+        "|             |   \"range\": {                                                |             |",
+        "|             |     \"start\": \"111111\",                                      |             |",
+        "|             |     \"end\": \"222222\"                                         |             |",
+        "|             |   }                                                         |             |",
+        "|             | }                                                           |             |",
+        "| sql         | select query_type, query_text, priority from system.queries | system      |",
+        "+-------------+-------------------------------------------------------------+-------------+",
     ];
     assert_batches_eq!(expected_read_data, &batches);
 }
@@ -215,6 +215,7 @@ async fn test_write_routed() {
             ),
         ]),
         query_sinks: None,
+        ingest_rules: vec![],
     };
     router_router
         .update_router(router_config)
@@ -360,6 +361,7 @@ async fn test_write_routed_errors() {
             },
         )]),
         query_sinks: None,
+        ingest_rules: vec![],
     };
     router_router
         .update_router(router_config)
@@ -417,6 +419,7 @@ async fn test_write_dev_null() {
         }),
         write_sinks: HashMap::from([(TEST_SHARD_ID, WriteSinkSet { sinks: vec![] })]),
         query_sinks: None,
+        ingest_rules: vec![],
     };
     router_router
         .update_router(router_config)
@@ -535,6 +538,7 @@ async fn test_write_routed_no_shard() {
                 },
             )]),
             query_sinks: None,
+            ingest_rules: vec![],
         };
         router_router
             .update_router(router_config)
@@ -49,6 +49,36 @@ async fn test_write(db_name: &str, addr: &str) {
         .success()
         .stdout(predicate::str::contains("2 Lines OK"));
 
+    // write each line in a separate request
+    Command::cargo_bin("influxdb_iox")
+        .unwrap()
+        .arg("database")
+        .arg("write")
+        .arg(db_name)
+        .arg(lp_data_file.as_ref())
+        .arg("--batch-size")
+        .arg("1")
+        .arg("--host")
+        .arg(addr)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 Lines OK"));
+
+    // lines longer than the maximum line size are rejected
+    Command::cargo_bin("influxdb_iox")
+        .unwrap()
+        .arg("database")
+        .arg("write")
+        .arg(db_name)
+        .arg(lp_data_file.as_ref())
+        .arg("--max-line-size")
+        .arg("10")
+        .arg("--host")
+        .arg(addr)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("exceeds limit of 10 bytes"));
+
     // try reading a non existent file
     Command::cargo_bin("influxdb_iox")
         .unwrap()
@@ -100,6 +100,23 @@ impl Client {
         Ok(response.into_inner().rules.unwrap_field("rules")?)
     }
 
+    /// Declares every table and column of a database in its schema and
+    /// switches the schema to strict mode, so that writes can no longer
+    /// create new tables or columns.
+    pub async fn lock_database_schema(
+        &mut self,
+        db_name: impl Into<String> + Send,
+    ) -> Result<DatabaseRules, Error> {
+        let response = self
+            .inner
+            .lock_database_schema(LockDatabaseSchemaRequest {
+                db_name: db_name.into(),
+            })
+            .await?;
+
+        Ok(response.into_inner().rules.unwrap_field("rules")?)
+    }
+
     /// List databases.
     ///
     /// See [`Self::get_database`] for the semanitcs of `omit_defaults`
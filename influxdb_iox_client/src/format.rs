@@ -1,20 +1,41 @@
 //! Output formatting utilities for Arrow record batches
 
-use std::{fmt::Display, str::FromStr};
+use std::{
+    fmt::Display,
+    io::{Cursor, Seek, SeekFrom, Write},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use thiserror::Error;
 
 use arrow::{
-    self, csv::WriterBuilder, error::ArrowError, json::ArrayWriter, record_batch::RecordBatch,
+    self,
+    csv::WriterBuilder,
+    error::ArrowError,
+    ipc::writer::StreamWriter,
+    json::{ArrayWriter, LineDelimitedWriter},
+    record_batch::RecordBatch,
 };
+use parquet::{arrow::ArrowWriter, errors::ParquetError, file::writer::TryClone};
+
+pub mod line_protocol;
 
 /// Error type for results formatting
 #[derive(Debug, Error)]
 pub enum Error {
     /// Unknown formatting type
-    #[error("Unknown format type: {}. Expected one of 'pretty', 'csv' or 'json'", .0)]
+    #[error(
+        "Unknown format type: {}. Expected one of 'pretty', 'csv', 'json', 'jsonl', \
+         'arrow', 'parquet' or 'line_protocol'",
+        .0
+    )]
     Invalid(String),
 
+    /// Binary format requested as text
+    #[error("{} output is binary and cannot be formatted as text", .0)]
+    NotText(QueryOutputFormat),
+
     /// Error pretty printing
     #[error("Arrow pretty printing error: {}", .0)]
     PrettyArrow(ArrowError),
@@ -34,6 +55,18 @@ pub enum Error {
     /// Error converting JSON output to utf-8
     #[error("Error converting JSON output to UTF-8: {}", .0)]
     JsonUtf8(std::string::FromUtf8Error),
+
+    /// Error during Arrow IPC conversion
+    #[error("Arrow IPC writing error: {}", .0)]
+    ArrowIpc(ArrowError),
+
+    /// Error during Parquet conversion
+    #[error("Parquet writing error: {}", .0)]
+    Parquet(ParquetError),
+
+    /// Error during line protocol conversion
+    #[error("Line protocol conversion error: {}", .0)]
+    LineProtocol(#[from] line_protocol::Error),
 }
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -46,6 +79,14 @@ pub enum QueryOutputFormat {
     Csv,
     /// Arrow JSON format
     Json,
+    /// Newline delimited JSON, one object per row
+    JsonLines,
+    /// Arrow IPC streaming format
+    ArrowIpc,
+    /// Parquet file
+    Parquet,
+    /// InfluxDB line protocol, reconstructed from the IOx schema metadata
+    LineProtocol,
 }
 
 impl Display for QueryOutputFormat {
@@ -54,6 +95,10 @@ impl Display for QueryOutputFormat {
             QueryOutputFormat::Pretty => write!(f, "pretty"),
             QueryOutputFormat::Csv => write!(f, "csv"),
             QueryOutputFormat::Json => write!(f, "json"),
+            QueryOutputFormat::JsonLines => write!(f, "jsonl"),
+            QueryOutputFormat::ArrowIpc => write!(f, "arrow"),
+            QueryOutputFormat::Parquet => write!(f, "parquet"),
+            QueryOutputFormat::LineProtocol => write!(f, "line_protocol"),
         }
     }
 }
@@ -72,6 +117,10 @@ impl FromStr for QueryOutputFormat {
             "pretty" => Ok(Self::Pretty),
             "csv" => Ok(Self::Csv),
             "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::JsonLines),
+            "arrow" => Ok(Self::ArrowIpc),
+            "parquet" => Ok(Self::Parquet),
+            "line_protocol" | "lp" => Ok(Self::LineProtocol),
             _ => Err(Error::Invalid(s.to_string())),
         }
     }
@@ -84,8 +133,17 @@ impl QueryOutputFormat {
             Self::Pretty => "text/plain",
             Self::Csv => "text/csv",
             Self::Json => "application/json",
+            Self::JsonLines => "application/x-ndjson",
+            Self::ArrowIpc => "application/vnd.apache.arrow.stream",
+            Self::Parquet => "application/vnd.apache.parquet",
+            Self::LineProtocol => "text/plain; charset=utf-8",
         }
     }
+
+    /// Returns true if the output of this format is not text
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Self::ArrowIpc | Self::Parquet)
+    }
 }
 
 impl QueryOutputFormat {
@@ -116,51 +174,238 @@ impl QueryOutputFormat {
     ///  {"location":"Boston","state":"MA","surface_degrees":50.2,"time":1568756160}
     /// ]
     /// ```
+    ///
+    /// JSON lines:
+    /// ```text
+    /// {"bottom_degrees":50.4,"location":"santa_monica","state":"CA","surface_degrees":65.2,"time":1568756160}
+    /// {"location":"Boston","state":"MA","surface_degrees":50.2,"time":1568756160}
+    /// ```
+    ///
+    /// Line protocol:
+    /// ```text
+    /// h2o,location=santa_monica,state=CA bottom_degrees=50.4,surface_degrees=65.2 1568756160
+    /// ```
+    ///
+    /// Binary formats are rejected, use [`QueryOutputFormat::format_bytes`]
+    /// instead.
     pub fn format(&self, batches: &[RecordBatch]) -> Result<String> {
-        match self {
-            Self::Pretty => batches_to_pretty(batches),
-            Self::Csv => batches_to_csv(batches),
-            Self::Json => batches_to_json(batches),
+        if self.is_binary() {
+            return Err(Error::NotText(*self));
+        }
+
+        let bytes = self.format_bytes(batches)?;
+        String::from_utf8(bytes).map_err(|e| match self {
+            Self::Csv => Error::CsvUtf8(e),
+            _ => Error::JsonUtf8(e),
+        })
+    }
+
+    /// Format the [`RecordBatch`]es into bytes in any format, including the
+    /// binary Arrow IPC and Parquet formats
+    pub fn format_bytes(&self, batches: &[RecordBatch]) -> Result<Vec<u8>> {
+        let mut formatter = BatchFormatter::new(*self);
+        let mut bytes = vec![];
+        for batch in batches {
+            bytes.extend(formatter.write(batch)?);
         }
+        bytes.extend(formatter.finish()?);
+        Ok(bytes)
     }
 }
 
-fn batches_to_pretty(batches: &[RecordBatch]) -> Result<String> {
-    arrow_util::display::pretty_format_batches(batches).map_err(Error::PrettyArrow)
+/// Incrementally formats [`RecordBatch`]es in a [`QueryOutputFormat`], so
+/// that results can be written out as they are produced.
+///
+/// The concatenation of the output of [`BatchFormatter::write`] for each
+/// batch followed by [`BatchFormatter::finish`] is the same as
+/// [`QueryOutputFormat::format_bytes`] of all batches.
+pub struct BatchFormatter {
+    format: QueryOutputFormat,
+    /// Whether any output has been produced yet
+    started: bool,
+    /// Batches held back by formats that need the entire result
+    buffered: Vec<RecordBatch>,
+    /// Writer for binary formats, created with the schema of the first batch
+    writer: Option<BinaryWriter>,
+    /// Buffer the binary writer writes to
+    buffer: SharedBuffer,
 }
 
-fn batches_to_csv(batches: &[RecordBatch]) -> Result<String> {
-    let mut bytes = vec![];
+impl std::fmt::Debug for BatchFormatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchFormatter")
+            .field("format", &self.format)
+            .field("started", &self.started)
+            .field("buffered", &self.buffered.len())
+            .finish_non_exhaustive()
+    }
+}
 
-    {
-        let mut writer = WriterBuilder::new().has_headers(true).build(&mut bytes);
+enum BinaryWriter {
+    ArrowIpc(StreamWriter<SharedBuffer>),
+    Parquet(ArrowWriter<SharedBuffer>),
+}
 
-        for batch in batches {
-            writer.write(batch).map_err(Error::CsvArrow)?;
+impl BatchFormatter {
+    /// Create a new formatter for `format`
+    pub fn new(format: QueryOutputFormat) -> Self {
+        Self {
+            format,
+            started: false,
+            buffered: vec![],
+            writer: None,
+            buffer: SharedBuffer::default(),
+        }
+    }
+
+    /// Format `batch`, returning the bytes to append to the output.
+    ///
+    /// The pretty format needs all batches to size its columns, and Parquet
+    /// writes its metadata after all data, so these produce no output until
+    /// [`BatchFormatter::finish`]
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        match self.format {
+            QueryOutputFormat::Pretty => self.buffered.push(batch.clone()),
+            QueryOutputFormat::Csv => {
+                // Only the first batch is preceded by a header
+                let mut writer = WriterBuilder::new()
+                    .has_headers(!self.started)
+                    .build(&mut bytes);
+                writer.write(batch).map_err(Error::CsvArrow)?;
+                self.started = true;
+            }
+            // The JSON writer writes nothing, not even the enclosing
+            // brackets, for a batch without rows
+            QueryOutputFormat::Json if batch.num_rows() == 0 => {}
+            QueryOutputFormat::Json => {
+                let mut array = vec![];
+                {
+                    let mut writer = ArrayWriter::new(&mut array);
+                    writer
+                        .write_batches(&[batch.clone()])
+                        .map_err(Error::JsonArrow)?;
+                    writer.finish().map_err(Error::JsonArrow)?;
+                }
+
+                // Splice the rows of this batch into a single array spanning
+                // all batches, opened by the first row and closed by `finish`
+                let rows = &array[1..array.len() - 1];
+                if !rows.is_empty() {
+                    bytes.push(if self.started { b',' } else { b'[' });
+                    bytes.extend_from_slice(rows);
+                    self.started = true;
+                }
+            }
+            QueryOutputFormat::JsonLines => {
+                let mut writer = LineDelimitedWriter::new(&mut bytes);
+                writer
+                    .write_batches(&[batch.clone()])
+                    .map_err(Error::JsonArrow)?;
+                writer.finish().map_err(Error::JsonArrow)?;
+            }
+            QueryOutputFormat::ArrowIpc => {
+                if self.writer.is_none() {
+                    let writer = StreamWriter::try_new(self.buffer.clone(), &batch.schema())
+                        .map_err(Error::ArrowIpc)?;
+                    self.writer = Some(BinaryWriter::ArrowIpc(writer));
+                }
+                if let Some(BinaryWriter::ArrowIpc(writer)) = &mut self.writer {
+                    writer.write(batch).map_err(Error::ArrowIpc)?;
+                }
+                bytes = self.buffer.take();
+            }
+            QueryOutputFormat::Parquet => {
+                if self.writer.is_none() {
+                    let writer = ArrowWriter::try_new(self.buffer.clone(), batch.schema(), None)
+                        .map_err(Error::Parquet)?;
+                    self.writer = Some(BinaryWriter::Parquet(writer));
+                }
+                if let Some(BinaryWriter::Parquet(writer)) = &mut self.writer {
+                    writer.write(batch).map_err(Error::Parquet)?;
+                }
+            }
+            QueryOutputFormat::LineProtocol => line_protocol::write_batch(batch, &mut bytes)?,
+        }
+        Ok(bytes)
+    }
+
+    /// Complete the output, returning any remaining bytes
+    pub fn finish(self) -> Result<Vec<u8>> {
+        match self.format {
+            QueryOutputFormat::Pretty => arrow_util::display::pretty_format_batches(&self.buffered)
+                .map(String::into_bytes)
+                .map_err(Error::PrettyArrow),
+            QueryOutputFormat::Csv
+            | QueryOutputFormat::JsonLines
+            | QueryOutputFormat::LineProtocol => Ok(vec![]),
+            QueryOutputFormat::Json if self.started => Ok(b"]".to_vec()),
+            QueryOutputFormat::Json => Ok(b"[]".to_vec()),
+            // Without any batches there is no schema to write binary formats
+            // with, so the output is empty
+            QueryOutputFormat::ArrowIpc | QueryOutputFormat::Parquet => {
+                match self.writer {
+                    Some(BinaryWriter::ArrowIpc(mut writer)) => {
+                        writer.finish().map_err(Error::ArrowIpc)?
+                    }
+                    Some(BinaryWriter::Parquet(mut writer)) => {
+                        writer.close().map_err(Error::Parquet)?;
+                    }
+                    None => {}
+                }
+                Ok(self.buffer.take())
+            }
         }
     }
-    let csv = String::from_utf8(bytes).map_err(Error::CsvUtf8)?;
-    Ok(csv)
 }
 
-fn batches_to_json(batches: &[RecordBatch]) -> Result<String> {
-    let mut bytes = vec![];
+/// An in-memory buffer shared between a writer that owns its output and the
+/// [`BatchFormatter`], which takes the written bytes from it
+#[derive(Debug, Default, Clone)]
+struct SharedBuffer {
+    mem: Arc<Mutex<Cursor<Vec<u8>>>>,
+}
+
+impl SharedBuffer {
+    /// Takes the bytes written so far, leaving the buffer empty
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.mem.lock().expect("mutex poisoned")).into_inner()
+    }
+}
 
-    {
-        let mut writer = ArrayWriter::new(&mut bytes);
-        writer.write_batches(batches).map_err(Error::CsvArrow)?;
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.mem.lock().expect("mutex poisoned").write(buf)
+    }
 
-        writer.finish().map_err(Error::CsvArrow)?;
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
     }
+}
 
-    let json = String::from_utf8(bytes).map_err(Error::JsonUtf8)?;
+impl Seek for SharedBuffer {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.mem.lock().expect("mutex poisoned").seek(pos)
+    }
+}
 
-    Ok(json)
+impl TryClone for SharedBuffer {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(self.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use arrow::{
+        array::{ArrayRef, Float64Array, Int64Array, StringArray, TimestampNanosecondArray},
+        ipc::reader::StreamReader,
+    };
+    use parquet::{
+        arrow::{ArrowReader, ParquetFileArrowReader},
+        file::{reader::SerializedFileReader, serialized_reader::SliceableCursor},
+    };
 
     #[test]
     fn test_from_str() {
@@ -191,9 +436,36 @@ mod tests {
             QueryOutputFormat::Json
         );
 
+        assert_eq!(
+            QueryOutputFormat::from_str("jsonl").unwrap(),
+            QueryOutputFormat::JsonLines
+        );
+        assert_eq!(
+            QueryOutputFormat::from_str("JSONL").unwrap(),
+            QueryOutputFormat::JsonLines
+        );
+
         assert_eq!(
             QueryOutputFormat::from_str("un").unwrap_err().to_string(),
-            "Unknown format type: un. Expected one of 'pretty', 'csv' or 'json'"
+            "Unknown format type: un. Expected one of 'pretty', 'csv', 'json', 'jsonl', \
+             'arrow', 'parquet' or 'line_protocol'"
+        );
+
+        assert_eq!(
+            QueryOutputFormat::from_str("arrow").unwrap(),
+            QueryOutputFormat::ArrowIpc
+        );
+        assert_eq!(
+            QueryOutputFormat::from_str("Parquet").unwrap(),
+            QueryOutputFormat::Parquet
+        );
+        assert_eq!(
+            QueryOutputFormat::from_str("line_protocol").unwrap(),
+            QueryOutputFormat::LineProtocol
+        );
+        assert_eq!(
+            QueryOutputFormat::from_str("lp").unwrap(),
+            QueryOutputFormat::LineProtocol
         );
     }
 
@@ -213,5 +485,165 @@ mod tests {
             QueryOutputFormat::from_str(&QueryOutputFormat::Json.to_string()).unwrap(),
             QueryOutputFormat::Json
         );
+
+        assert_eq!(
+            QueryOutputFormat::from_str(&QueryOutputFormat::JsonLines.to_string()).unwrap(),
+            QueryOutputFormat::JsonLines
+        );
+    }
+
+    #[test]
+    fn test_format_incrementally() {
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "tag",
+                Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef,
+            ),
+            ("val", Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef),
+        ])
+        .unwrap();
+        let empty = batch.slice(0, 0);
+        let batches = [batch.clone(), empty, batch.slice(1, 1)];
+
+        let check = |format: QueryOutputFormat| {
+            let mut formatter = BatchFormatter::new(format);
+            let mut output = vec![];
+            for batch in &batches {
+                output.push(String::from_utf8(formatter.write(batch).unwrap()).unwrap());
+            }
+            output.push(String::from_utf8(formatter.finish().unwrap()).unwrap());
+
+            // Incremental output is the same as formatting all batches at once
+            assert_eq!(output.concat(), format.format(&batches).unwrap());
+            output
+        };
+
+        assert_eq!(
+            check(QueryOutputFormat::Csv),
+            vec!["tag,val\na,1\nb,2\n", "", "b,2\n", ""]
+        );
+        assert_eq!(
+            check(QueryOutputFormat::Json),
+            vec![
+                r#"[{"tag":"a","val":1},{"tag":"b","val":2}"#,
+                "",
+                r#",{"tag":"b","val":2}"#,
+                "]"
+            ]
+        );
+        assert_eq!(
+            check(QueryOutputFormat::JsonLines),
+            vec![
+                "{\"tag\":\"a\",\"val\":1}\n{\"tag\":\"b\",\"val\":2}\n",
+                "",
+                "{\"tag\":\"b\",\"val\":2}\n",
+                ""
+            ]
+        );
+
+        let pretty = check(QueryOutputFormat::Pretty);
+        assert_eq!(pretty[..3], ["", "", ""]);
+        assert!(pretty[3].contains("| tag | val |"));
+
+        assert_eq!(
+            QueryOutputFormat::Json.format(&[]).unwrap(),
+            "[]".to_string()
+        );
+
+        // only empty batches
+        let mut formatter = BatchFormatter::new(QueryOutputFormat::Json);
+        assert!(formatter.write(&batch.slice(0, 0)).unwrap().is_empty());
+        assert_eq!(formatter.finish().unwrap(), b"[]");
+    }
+
+    fn test_batches() -> Vec<RecordBatch> {
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "tag",
+                Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef,
+            ),
+            ("val", Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef),
+        ])
+        .unwrap();
+        vec![batch.slice(0, 1), batch.slice(1, 1)]
+    }
+
+    #[test]
+    fn test_format_arrow_ipc() {
+        let batches = test_batches();
+        let mut formatter = BatchFormatter::new(QueryOutputFormat::ArrowIpc);
+
+        // the schema and each batch are written out as they arrive
+        let mut bytes = formatter.write(&batches[0]).unwrap();
+        assert!(!bytes.is_empty());
+        let next = formatter.write(&batches[1]).unwrap();
+        assert!(!next.is_empty());
+        bytes.extend(next);
+        bytes.extend(formatter.finish().unwrap());
+
+        let reader = StreamReader::try_new(Cursor::new(bytes)).unwrap();
+        let actual: Vec<_> = reader.map(|batch| batch.unwrap()).collect();
+        assert_eq!(actual.len(), 2);
+        assert_eq!(
+            arrow_util::display::pretty_format_batches(&actual).unwrap(),
+            arrow_util::display::pretty_format_batches(&batches).unwrap()
+        );
+
+        assert_eq!(
+            QueryOutputFormat::ArrowIpc
+                .format(&batches)
+                .unwrap_err()
+                .to_string(),
+            "arrow output is binary and cannot be formatted as text"
+        );
+    }
+
+    #[test]
+    fn test_format_parquet() {
+        let batches = test_batches();
+        let bytes = QueryOutputFormat::Parquet.format_bytes(&batches).unwrap();
+
+        let file_reader = SerializedFileReader::new(SliceableCursor::new(bytes)).unwrap();
+        let mut arrow_reader = ParquetFileArrowReader::new(Arc::new(file_reader));
+        let actual: Vec<_> = arrow_reader
+            .get_record_reader(1024)
+            .unwrap()
+            .map(|batch| batch.unwrap())
+            .collect();
+
+        assert_eq!(
+            arrow_util::display::pretty_format_batches(&actual).unwrap(),
+            arrow_util::display::pretty_format_batches(&batches).unwrap()
+        );
+
+        assert!(QueryOutputFormat::Parquet
+            .format_bytes(&[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_format_line_protocol() {
+        let schema = schema::builder::SchemaBuilder::new()
+            .measurement("cpu")
+            .tag("host")
+            .influx_field("usage", schema::InfluxFieldType::Float)
+            .timestamp()
+            .build()
+            .unwrap();
+        let batch = RecordBatch::try_new(
+            schema.as_arrow(),
+            vec![
+                Arc::new(StringArray::from(vec!["a", "b"])),
+                Arc::new(Float64Array::from(vec![0.5, 1.])),
+                Arc::new(TimestampNanosecondArray::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            QueryOutputFormat::LineProtocol.format(&[batch]).unwrap(),
+            "cpu,host=a usage=0.5 1\ncpu,host=b usage=1 2\n"
+        );
     }
 }
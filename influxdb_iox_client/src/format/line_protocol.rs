@@ -0,0 +1,213 @@
+//! Conversion of Arrow record batches to InfluxDB line protocol
+
+use arrow::{
+    array::{ArrayRef, StringArray},
+    compute::cast,
+    datatypes::{DataType, TimeUnit},
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
+use mutable_batch_lp::encode::{encode_columns, Measurement};
+use schema::{InfluxColumnType, InfluxFieldType, Schema};
+use thiserror::Error;
+
+/// Name of a column overriding the measurement name of each row, as the
+/// measurement name in the schema metadata does not survive most queries
+pub const MEASUREMENT_COLUMN: &str = "_measurement";
+
+/// Error type for line protocol conversion
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Schema with invalid IOx metadata
+    #[error("Invalid IOx schema: {}", .0)]
+    Schema(#[from] schema::Error),
+
+    /// No measurement name
+    #[error(
+        "Cannot determine measurement name: results have no measurement metadata \
+         or '{}' column",
+        MEASUREMENT_COLUMN
+    )]
+    NoMeasurement,
+
+    /// Error converting the measurement column
+    #[error("Error converting column '{}': {}", MEASUREMENT_COLUMN, .0)]
+    Arrow(ArrowError),
+
+    /// Error encoding the rows
+    #[error("Error encoding line protocol: {}", .0)]
+    Encode(#[from] mutable_batch_lp::encode::Error),
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Appends a line for each row of `batch` to `out`.
+///
+/// Columns are classified using the IOx column type metadata if present, and
+/// otherwise by their type: dictionary encoded strings are tags, a nanosecond
+/// timestamp column named `time` is the timestamp and all other columns are
+/// fields. Rows without any non-null field are skipped, as are non-finite
+/// float values which line protocol cannot represent.
+pub fn write_batch(batch: &RecordBatch, out: &mut Vec<u8>) -> Result<()> {
+    let schema = Schema::try_from(batch.schema())?;
+
+    let mut measurement_column = None;
+    let column_types: Vec<_> = batch
+        .columns()
+        .iter()
+        .zip(schema.iter())
+        .map(|(column, (influx_type, field))| {
+            let name = field.name().as_str();
+            match (influx_type, field.data_type()) {
+                _ if name == MEASUREMENT_COLUMN => {
+                    measurement_column = Some(column);
+                    None
+                }
+                (Some(influx_type), _) => Some(influx_type),
+                (None, DataType::Dictionary(_, _)) => Some(InfluxColumnType::Tag),
+                (None, DataType::Timestamp(TimeUnit::Nanosecond, _)) if name == "time" => {
+                    Some(InfluxColumnType::Timestamp)
+                }
+                (None, data_type) => Some(InfluxColumnType::Field(
+                    InfluxFieldType::try_from(data_type.clone())
+                        // Unsupported types are rejected by the encoder
+                        .unwrap_or(InfluxFieldType::Float),
+                )),
+            }
+        })
+        .collect();
+
+    let measurement_column = measurement_column
+        .map(|column| cast(column, &DataType::Utf8).map_err(Error::Arrow))
+        .transpose()?;
+    let measurement = match (&measurement_column, schema.measurement()) {
+        (Some(column), _) => Measurement::Column(as_strings(column)),
+        (None, Some(measurement)) => Measurement::Name(measurement),
+        (None, None) => return Err(Error::NoMeasurement),
+    };
+
+    Ok(encode_columns(measurement, batch, &column_types, out)?)
+}
+
+fn as_strings(column: &ArrayRef) -> &StringArray {
+    column
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .expect("cast to string")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{
+            BooleanArray, DictionaryArray, Float64Array, Int64Array, TimestampNanosecondArray,
+            UInt64Array,
+        },
+        datatypes::{Field, Int32Type, Schema as ArrowSchema},
+    };
+    use schema::builder::SchemaBuilder;
+    use std::sync::Arc;
+
+    fn to_lp(batch: &RecordBatch) -> String {
+        let mut out = vec![];
+        write_batch(batch, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_with_metadata() {
+        let schema = SchemaBuilder::new()
+            .measurement("my measurement")
+            .tag("host,name")
+            .influx_field("val", InfluxFieldType::Float)
+            .influx_field("count", InfluxFieldType::Integer)
+            .influx_field("total", InfluxFieldType::UInteger)
+            .influx_field("msg", InfluxFieldType::String)
+            .influx_field("ok", InfluxFieldType::Boolean)
+            .timestamp()
+            .build()
+            .unwrap();
+
+        let batch = RecordBatch::try_new(
+            schema.as_arrow(),
+            vec![
+                Arc::new(StringArray::from(vec![Some("a b"), None, Some("")])),
+                Arc::new(Float64Array::from(vec![Some(1.5), Some(f64::NAN), None])),
+                Arc::new(Int64Array::from(vec![Some(-1), None, None])),
+                Arc::new(UInt64Array::from(vec![Some(2), None, None])),
+                Arc::new(StringArray::from(vec![Some(r#"say "hi" \o/"#), None, None])),
+                Arc::new(BooleanArray::from(vec![Some(true), None, Some(false)])),
+                Arc::new(TimestampNanosecondArray::from(vec![
+                    Some(10),
+                    Some(20),
+                    None,
+                ])),
+            ],
+        )
+        .unwrap();
+
+        // the second row only has a non-finite value and is skipped
+        assert_eq!(
+            to_lp(&batch),
+            concat!(
+                r#"my\ measurement,host\,name=a\ b val=1.5,count=-1i,total=2u,msg="say \"hi\" \\o/",ok=true 10"#,
+                "\n",
+                "my\\ measurement ok=false\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_without_metadata() {
+        let tags: DictionaryArray<Int32Type> = vec![Some("a"), Some("b")].into_iter().collect();
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                MEASUREMENT_COLUMN,
+                Arc::new(StringArray::from(vec![Some("cpu"), None])) as ArrayRef,
+            ),
+            ("host", Arc::new(tags) as ArrayRef),
+            (
+                "region",
+                Arc::new(StringArray::from(vec!["west", "east"])) as ArrayRef,
+            ),
+            (
+                "time",
+                Arc::new(TimestampNanosecondArray::from(vec![1, 2])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+
+        // dictionaries are tags, strings are fields
+        assert_eq!(to_lp(&batch), "cpu,host=a region=\"west\" 1\n");
+
+        let batch = RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![Field::new(
+                "val",
+                DataType::Int64,
+                true,
+            )])),
+            vec![Arc::new(Int64Array::from(vec![1]))],
+        )
+        .unwrap();
+        let err = write_batch(&batch, &mut vec![]).unwrap_err();
+        assert!(matches!(err, Error::NoMeasurement));
+
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                MEASUREMENT_COLUMN,
+                Arc::new(StringArray::from(vec!["cpu"])) as ArrayRef,
+            ),
+            (
+                "val",
+                Arc::new(arrow::array::Int32Array::from(vec![1])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let err = write_batch(&batch, &mut vec![]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error encoding line protocol: column 'val' of type Int32 cannot be encoded as line protocol"
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! A builder for serializing data as line protocol
+
+use crate::{FieldValue, ParsedLine};
+use std::{io::Write, marker::PhantomData};
+
+/// Characters to escape when writing measurement names
+const MEASUREMENT_DELIMITERS: &[char] = &[',', ' ', '\\'];
+
+/// Characters to escape when writing tag keys, tag values and field keys
+const KEY_DELIMITERS: &[char] = &[',', '=', ' ', '\\'];
+
+/// Characters to escape when writing string field values
+const FIELD_VALUE_STRING_DELIMITERS: &[char] = &['"', '\\'];
+
+/// Builder state before the measurement name of a line
+#[derive(Debug, Clone, Copy)]
+pub struct BeforeMeasurement;
+
+/// Builder state after the measurement name or a tag of a line
+#[derive(Debug, Clone, Copy)]
+pub struct AfterMeasurement;
+
+/// Builder state after a field of a line
+#[derive(Debug, Clone, Copy)]
+pub struct AfterField;
+
+/// Builder state after the timestamp of a line
+#[derive(Debug, Clone, Copy)]
+pub struct AfterTimestamp;
+
+/// Builds line protocol a line at a time, escaping measurement names, tag
+/// keys and values, field keys and string field values as needed.
+///
+/// The order of the calls is enforced by the type system: each line consists
+/// of a measurement, any number of tags, at least one field and an optional
+/// timestamp.
+///
+/// ```
+/// use influxdb_line_protocol::builder::LineProtocolBuilder;
+///
+/// let lp = LineProtocolBuilder::new()
+///     .measurement("cpu load")
+///     .tag("host", "a,b")
+///     .field("usage", 0.5)
+///     .field("message", r#"say "hi""#)
+///     .timestamp(1)
+///     .close_line()
+///     .measurement("cpu")
+///     .field("count", 2i64)
+///     .close_line()
+///     .build();
+///
+/// assert_eq!(
+///     std::str::from_utf8(&lp).unwrap(),
+///     "cpu\\ load,host=a\\,b usage=0.5,message=\"say \\\"hi\\\"\" 1\ncpu count=2i\n"
+/// );
+/// ```
+///
+/// Any data can be written, but not all of it can be represented as line
+/// protocol and parsed back: names and tag values must not be empty, end
+/// with a backslash or contain tabs or newlines, a measurement name must not
+/// start with `#`, and float field values must be finite.
+#[derive(Debug)]
+pub struct LineProtocolBuilder<S = BeforeMeasurement> {
+    buf: Vec<u8>,
+    _state: PhantomData<S>,
+}
+
+impl Default for LineProtocolBuilder<BeforeMeasurement> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineProtocolBuilder<BeforeMeasurement> {
+    /// Creates a new, empty builder
+    pub fn new() -> Self {
+        Self::with_buffer(Vec::new())
+    }
+
+    /// Creates a new builder appending lines to `buf`
+    pub fn with_buffer(buf: Vec<u8>) -> Self {
+        Self {
+            buf,
+            _state: PhantomData,
+        }
+    }
+
+    /// Starts a new line with the measurement name `measurement`
+    pub fn measurement(mut self, measurement: &str) -> LineProtocolBuilder<AfterMeasurement> {
+        escape(measurement, MEASUREMENT_DELIMITERS, &mut self.buf);
+        self.into_state()
+    }
+
+    /// Appends `line` as a complete line
+    pub fn parsed_line(self, line: &ParsedLine<'_>) -> Self {
+        let mut builder = self.measurement(line.series.measurement.as_str());
+        for (key, value) in line.series.tag_set.iter().flatten() {
+            builder = builder.tag(key.as_str(), value.as_str());
+        }
+
+        let mut fields = line.field_set.iter();
+        let builder = match fields.next() {
+            Some((key, value)) => fields.fold(
+                builder.field(key.as_str(), value.clone()),
+                |builder, (key, value)| builder.field(key.as_str(), value.clone()),
+            ),
+            // write the invalid line as is, the same as its `Display`
+            None => builder.into_state(),
+        };
+
+        match line.timestamp {
+            Some(timestamp) => builder.timestamp(timestamp).close_line(),
+            None => builder.close_line(),
+        }
+    }
+
+    /// Returns the line protocol built
+    pub fn build(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl LineProtocolBuilder<AfterMeasurement> {
+    /// Adds a tag to the current line
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.buf.push(b',');
+        escape(key, KEY_DELIMITERS, &mut self.buf);
+        self.buf.push(b'=');
+        escape(value, KEY_DELIMITERS, &mut self.buf);
+        self
+    }
+
+    /// Adds the first field to the current line
+    pub fn field<'a>(
+        mut self,
+        key: &str,
+        value: impl Into<FieldValue<'a>>,
+    ) -> LineProtocolBuilder<AfterField> {
+        self.buf.push(b' ');
+        write_field(key, &value.into(), &mut self.buf);
+        self.into_state()
+    }
+}
+
+impl LineProtocolBuilder<AfterField> {
+    /// Adds another field to the current line
+    pub fn field<'a>(mut self, key: &str, value: impl Into<FieldValue<'a>>) -> Self {
+        self.buf.push(b',');
+        write_field(key, &value.into(), &mut self.buf);
+        self
+    }
+
+    /// Sets the timestamp of the current line, in nanoseconds since the epoch
+    pub fn timestamp(mut self, timestamp: i64) -> LineProtocolBuilder<AfterTimestamp> {
+        write!(self.buf, " {}", timestamp).expect("writing to vec");
+        self.into_state()
+    }
+
+    /// Completes the current line without a timestamp
+    pub fn close_line(self) -> LineProtocolBuilder<BeforeMeasurement> {
+        self.into_state::<AfterTimestamp>().close_line()
+    }
+}
+
+impl LineProtocolBuilder<AfterTimestamp> {
+    /// Completes the current line
+    pub fn close_line(mut self) -> LineProtocolBuilder<BeforeMeasurement> {
+        self.buf.push(b'\n');
+        self.into_state()
+    }
+}
+
+impl<S> LineProtocolBuilder<S> {
+    fn into_state<T>(self) -> LineProtocolBuilder<T> {
+        LineProtocolBuilder {
+            buf: self.buf,
+            _state: PhantomData,
+        }
+    }
+}
+
+/// Appends the line protocol representation of `value` to `out`, quoting
+/// and escaping string values
+///
+/// ```
+/// use influxdb_line_protocol::{builder::write_field_value, FieldValue};
+///
+/// let mut out = vec![];
+/// write_field_value(&FieldValue::from(r#"C:\"#), &mut out);
+/// write_field_value(&FieldValue::from(42u64), &mut out);
+/// assert_eq!(out, br#""C:\\"42u"#);
+/// ```
+pub fn write_field_value(value: &FieldValue<'_>, out: &mut Vec<u8>) {
+    let result = match value {
+        FieldValue::I64(v) => write!(out, "{}i", v),
+        FieldValue::U64(v) => write!(out, "{}u", v),
+        FieldValue::F64(v) => write!(out, "{}", v),
+        FieldValue::Boolean(v) => write!(out, "{}", v),
+        FieldValue::String(v) => {
+            out.push(b'"');
+            escape(v.as_str(), FIELD_VALUE_STRING_DELIMITERS, out);
+            out.push(b'"');
+            Ok(())
+        }
+    };
+    result.expect("writing to vec")
+}
+
+fn write_field(key: &str, value: &FieldValue<'_>, out: &mut Vec<u8>) {
+    escape(key, KEY_DELIMITERS, out);
+    out.push(b'=');
+    write_field_value(value, out);
+}
+
+/// Appends `value` to `out`, escaping `delimiters` with a backslash
+fn escape(value: &str, delimiters: &[char], out: &mut Vec<u8>) {
+    let mut last = 0;
+    for (idx, delim) in value.match_indices(delimiters) {
+        out.extend_from_slice(&value.as_bytes()[last..idx]);
+        out.push(b'\\');
+        out.extend_from_slice(delim.as_bytes());
+        last = idx + delim.len();
+    }
+    out.extend_from_slice(&value.as_bytes()[last..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_lines;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_escaping() {
+        let lp = LineProtocolBuilder::new()
+            .measurement(r#"a\ ,=""#)
+            .tag(r#"b\ ,=""#, r#"c\ ,=""#)
+            .field(r#"d\ ,=""#, "e\\ ,=\"\n")
+            .close_line()
+            .build();
+
+        assert_eq!(
+            std::str::from_utf8(&lp).unwrap(),
+            concat!(
+                r#"a\\\ \,=",b\\\ \,\="=c\\\ \,\=" d\\\ \,\="="e\\ ,=\""#,
+                "\n\"\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_parsed_line() {
+        let input = concat!(
+            "cpu,host=a\\ b usage=0.5,count=2i,total=3u,ok=true,msg=\"\\\"hi\\\"\" 1\n",
+            "mem free=1\n",
+        );
+
+        let builder = parse_lines(input).fold(LineProtocolBuilder::new(), |builder, line| {
+            builder.parsed_line(&line.unwrap())
+        });
+
+        assert_eq!(std::str::from_utf8(&builder.build()).unwrap(), input);
+    }
+
+    /// Names and tag values that can be represented in line protocol
+    fn name() -> impl Strategy<Value = String> {
+        "[^#\t\n\\\\]|[^#\t\n][^\t\n]{0,9}[^\t\n\\\\]"
+    }
+
+    fn field_value() -> impl Strategy<Value = FieldValue<'static>> {
+        prop_oneof![
+            any::<i64>().prop_map(FieldValue::I64),
+            any::<u64>().prop_map(FieldValue::U64),
+            any::<f64>()
+                .prop_filter("must be finite", |v| v.is_finite())
+                .prop_map(FieldValue::F64),
+            any::<bool>().prop_map(FieldValue::Boolean),
+            any::<String>().prop_map(FieldValue::from),
+        ]
+    }
+
+    /// A line without duplicate tag or field keys
+    #[derive(Debug)]
+    struct Line {
+        measurement: String,
+        tags: Vec<(String, String)>,
+        fields: Vec<(String, FieldValue<'static>)>,
+        timestamp: Option<i64>,
+    }
+
+    fn line() -> impl Strategy<Value = Line> {
+        (
+            name(),
+            prop::collection::btree_map(name(), name(), 0..4),
+            prop::collection::btree_map(name(), field_value(), 1..4),
+            any::<Option<i64>>(),
+        )
+            .prop_map(|(measurement, tags, fields, timestamp)| Line {
+                measurement,
+                tags: tags.into_iter().collect(),
+                fields: fields.into_iter().collect(),
+                timestamp,
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(lines in prop::collection::vec(line(), 1..10)) {
+            let mut builder = LineProtocolBuilder::new();
+            for line in &lines {
+                let mut line_builder = builder.measurement(&line.measurement);
+                for (key, value) in &line.tags {
+                    line_builder = line_builder.tag(key, value);
+                }
+
+                let (key, value) = &line.fields[0];
+                let mut line_builder = line_builder.field(key, value.clone());
+                for (key, value) in &line.fields[1..] {
+                    line_builder = line_builder.field(key, value.clone());
+                }
+
+                builder = match line.timestamp {
+                    Some(timestamp) => line_builder.timestamp(timestamp).close_line(),
+                    None => line_builder.close_line(),
+                };
+            }
+
+            let lp = String::from_utf8(builder.build()).unwrap();
+            let parsed = parse_lines(&lp).collect::<Result<Vec<_>, _>>().unwrap();
+            prop_assert_eq!(parsed.len(), lines.len());
+
+            for (parsed, line) in parsed.iter().zip(&lines) {
+                prop_assert_eq!(parsed.series.measurement.as_str(), line.measurement.as_str());
+
+                let tags: Vec<_> = parsed
+                    .series
+                    .tag_set
+                    .iter()
+                    .flatten()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+                prop_assert_eq!(&tags, &line.tags);
+
+                let fields: Vec<_> = parsed
+                    .field_set
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.clone()))
+                    .collect();
+                prop_assert_eq!(fields.len(), line.fields.len());
+                for ((parsed_key, parsed_value), (key, value)) in fields.iter().zip(&line.fields) {
+                    prop_assert_eq!(parsed_key, key);
+                    prop_assert_eq!(parsed_value, value);
+                }
+
+                prop_assert_eq!(parsed.timestamp, line.timestamp);
+            }
+        }
+    }
+}
@@ -37,6 +37,8 @@ use std::{
     ops::Deref,
 };
 
+pub mod builder;
+
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display(r#"Must not contain duplicate tags, but "{}" was repeated"#, tag_key))]
@@ -357,6 +359,42 @@ impl<'a> Display for FieldValue<'a> {
     }
 }
 
+impl From<i64> for FieldValue<'_> {
+    fn from(v: i64) -> Self {
+        Self::I64(v)
+    }
+}
+
+impl From<u64> for FieldValue<'_> {
+    fn from(v: u64) -> Self {
+        Self::U64(v)
+    }
+}
+
+impl From<f64> for FieldValue<'_> {
+    fn from(v: f64) -> Self {
+        Self::F64(v)
+    }
+}
+
+impl From<bool> for FieldValue<'_> {
+    fn from(v: bool) -> Self {
+        Self::Boolean(v)
+    }
+}
+
+impl<'a> From<&'a str> for FieldValue<'a> {
+    fn from(v: &'a str) -> Self {
+        Self::String(v.into())
+    }
+}
+
+impl From<String> for FieldValue<'_> {
+    fn from(v: String) -> Self {
+        Self::String(EscapedStr::CopiedValue(v))
+    }
+}
+
 /// Represents single logical string in the input.
 ///
 /// We do not use `&str` directly here because the actual input may be
@@ -506,6 +544,13 @@ pub fn parse_lines(input: &str) -> impl Iterator<Item = Result<ParsedLine<'_>>>
 
 /// Split `input` into individual lines to be parsed, based on the
 /// rules of the Line Protocol format.
+fn split_lines(input: &str) -> impl Iterator<Item = &str> {
+    let mut state = SplitState::default();
+    input.split(move |c| state.is_line_end(c))
+}
+
+/// State of the line splitter, tracking whether a newline terminates the
+/// current line or is part of a quoted field value.
 ///
 /// This code is more or less a direct port of the [Go implementation of
 /// `scanLine`](https://github.com/influxdata/influxdb/blob/217eddc87e14a79b01d0c22994fc139f530094a2/models/points.go#L1078)
@@ -514,61 +559,138 @@ pub fn parse_lines(input: &str) -> impl Iterator<Item = Result<ParsedLine<'_>>>
 /// logic duplication for scanning fields, duplicating it also means
 /// we can be more sure of the compatibility of the rust parser and
 /// the canonical Go parser.
-fn split_lines(input: &str) -> impl Iterator<Item = &str> {
-    // NB: This is ported as closely as possibly from the original Go code:
-    let mut quoted = false;
-    let mut fields = false;
+#[derive(Debug, Default, Clone, Copy)]
+struct SplitState {
+    quoted: bool,
+    fields: bool,
 
     // tracks how many '=' and commas we've seen
     // this duplicates some of the functionality in scanFields
-    let mut equals = 0;
-    let mut commas = 0;
+    equals: usize,
+    commas: usize,
+
+    in_escape: bool,
+}
+
+impl SplitState {
+    /// Feeds the next character of the input, returning true if it is the
+    /// newline terminating the current line
+    fn is_line_end(&mut self, c: char) -> bool {
+        // NB: This is ported as closely as possibly from the original Go code:
 
-    let mut in_escape = false;
-    input.split(move |c| {
         // skip past escaped characters
-        if in_escape {
-            in_escape = false;
+        if self.in_escape {
+            self.in_escape = false;
             return false;
         }
 
         if c == '\\' {
-            in_escape = true;
+            self.in_escape = true;
             return false;
         }
 
         if c == ' ' {
-            fields = true;
+            self.fields = true;
             return false;
         }
 
         // If we see a double quote, makes sure it is not escaped
-        if fields {
-            if !quoted && c == '=' {
-                equals += 1;
+        if self.fields {
+            if !self.quoted && c == '=' {
+                self.equals += 1;
                 return false;
-            } else if !quoted && c == ',' {
-                commas += 1;
+            } else if !self.quoted && c == ',' {
+                self.commas += 1;
                 return false;
-            } else if c == '"' && equals > commas {
-                quoted = !quoted;
+            } else if c == '"' && self.equals > self.commas {
+                self.quoted = !self.quoted;
                 return false;
             }
         }
 
-        if c == '\n' && !quoted {
+        if c == '\n' && !self.quoted {
             // reset all the state -- we found a line
-            quoted = false;
-            fields = false;
-            equals = 0;
-            commas = 0;
-            assert!(!in_escape);
-            in_escape = false;
+            assert!(!self.in_escape);
+            *self = Self::default();
             return true;
         }
 
         false
-    })
+    }
+}
+
+/// Splits a stream of line protocol data, received as arbitrary byte
+/// chunks, into runs of complete lines that can be passed to
+/// [`parse_lines`].
+///
+/// Only the bytes of the trailing partial line are retained between
+/// chunks, so the memory used is bounded by the longest line rather than
+/// the size of the whole input. Newlines within quoted string field values
+/// are handled the same way as by [`parse_lines`].
+///
+/// ```
+/// use influxdb_line_protocol::LineSplitter;
+///
+/// let mut splitter = LineSplitter::default();
+///
+/// let lines = splitter.push(b"cpu val=1 1\ncpu val=").unwrap();
+/// assert_eq!(lines, b"cpu val=1 1\n");
+/// assert_eq!(splitter.pending_len(), 8);
+///
+/// assert!(splitter.push(b"2").is_none());
+/// assert_eq!(splitter.finish().unwrap(), b"cpu val=2");
+/// ```
+#[derive(Debug, Default)]
+pub struct LineSplitter {
+    /// Bytes received after the last complete line
+    partial: Vec<u8>,
+
+    /// Split state at the end of `partial`
+    state: SplitState,
+}
+
+impl LineSplitter {
+    /// Appends `chunk` to the buffered input, returning all lines completed
+    /// by it (including their trailing newline), or `None` if no line was
+    /// completed.
+    ///
+    /// Lines are split on bytes, so a chunk boundary may fall anywhere,
+    /// including within a multi-byte UTF-8 character.
+    pub fn push(&mut self, chunk: &[u8]) -> Option<Vec<u8>> {
+        let scanned = self.partial.len();
+        self.partial.extend_from_slice(chunk);
+
+        // All characters significant to the splitter are ASCII and UTF-8
+        // continuation bytes never are, so scanning bytes is equivalent to
+        // scanning characters
+        let mut line_end = None;
+        for (idx, b) in self.partial[scanned..].iter().enumerate() {
+            if self.state.is_line_end(*b as char) {
+                line_end = Some(scanned + idx + 1);
+            }
+        }
+
+        line_end.map(|line_end| {
+            let rest = self.partial.split_off(line_end);
+            std::mem::replace(&mut self.partial, rest)
+        })
+    }
+
+    /// Returns the number of bytes received that are not yet part of a
+    /// complete line
+    pub fn pending_len(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// Returns the remaining input after the last complete line, or `None`
+    /// if there is none
+    pub fn finish(self) -> Option<Vec<u8>> {
+        if self.partial.is_empty() {
+            None
+        } else {
+            Some(self.partial)
+        }
+    }
 }
 
 fn parse_line(i: &str) -> IResult<&str, ParsedLine<'_>> {
@@ -1191,6 +1313,48 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_line_splitter() {
+        let input =
+            "meas tag=val field=1,field=\"\nval\"\nnext val=\"\\\"\"\nnon ascii=\"\u{1F600}\"";
+
+        // every possible chunk boundary yields the same lines as split_lines
+        for split in 0..=input.len() {
+            let (a, b) = input.as_bytes().split_at(split);
+            let mut splitter = LineSplitter::default();
+
+            let mut output = vec![];
+            for chunk in [a, b] {
+                if let Some(lines) = splitter.push(chunk) {
+                    assert_eq!(lines.last(), Some(&b'\n'));
+                    output.push(lines);
+                }
+            }
+            let pending = splitter.pending_len();
+            let rest = splitter.finish();
+            assert_eq!(rest.as_ref().map(Vec::len).unwrap_or_default(), pending);
+            output.extend(rest);
+
+            assert_eq!(String::from_utf8(output.concat()).unwrap(), input);
+            let lines: Vec<_> = output
+                .iter()
+                .flat_map(|lines| {
+                    let lines = std::str::from_utf8(lines).unwrap();
+                    split_lines(lines.strip_suffix('\n').unwrap_or(lines))
+                })
+                .collect();
+            assert_eq!(lines, split_lines(input).collect::<Vec<_>>());
+        }
+
+        // a newline within a quoted field value does not complete a line
+        let mut splitter = LineSplitter::default();
+        assert!(splitter.push(b"meas field=\"\n").is_none());
+        assert_eq!(splitter.pending_len(), 13);
+        assert_eq!(splitter.push(b"\"\n").unwrap(), b"meas field=\"\n\"\n");
+        assert_eq!(splitter.pending_len(), 0);
+        assert!(splitter.finish().is_none());
+    }
+
     #[test]
     fn escaped_str_multi_to_string() {
         let (_, es) = measurement("Foo\\aBar").unwrap();
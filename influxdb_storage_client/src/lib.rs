@@ -150,6 +150,23 @@ impl Client {
         Ok(Self::collect_data(responses))
     }
 
+    /// Make a request to query::read_series_cardinality and do the
+    /// required async dance to flatten the resulting stream to counts
+    pub async fn read_series_cardinality(
+        &mut self,
+        request: ReadSeriesCardinalityRequest,
+    ) -> Result<Vec<i64>, tonic::Status> {
+        let responses: Vec<_> = self
+            .inner
+            .read_series_cardinality(request)
+            .await?
+            .into_inner()
+            .try_collect()
+            .await?;
+
+        Ok(responses.into_iter().flat_map(|r| r.values).collect())
+    }
+
     /// Make a request to query::tag_keys and do the
     /// required async dance to flatten the resulting stream to Strings
     pub async fn tag_keys(
@@ -104,6 +104,7 @@ Examples:
             },
         )]),
         query_sinks: None,
+        ingest_rules: vec![],
     };
     let database_rules = DatabaseRules {
         name: db_name.clone(),
@@ -131,6 +132,10 @@ Examples:
             connection: kafka.to_string(),
             ..Default::default()
         }),
+        schema: None,
+        ingest_rules: vec![],
+        continuous_queries: vec![],
+        subscriptions: vec![],
     };
 
     // Create the writer db
@@ -97,6 +97,23 @@ impl IoxObjectStore {
         paths::server_config_path(inner, server_id)
     }
 
+    /// Get the data of the file defining the API tokens accepted by the server, stored next to
+    /// the server config.
+    pub async fn get_auth_tokens_file(inner: &ObjectStore, server_id: ServerId) -> Result<Bytes> {
+        let path = paths::auth_tokens_path(inner, server_id);
+        Ok(inner.get(&path).await?.bytes().await?.into())
+    }
+
+    /// Store the data of the file defining the API tokens accepted by the server.
+    pub async fn put_auth_tokens_file(
+        inner: &ObjectStore,
+        server_id: ServerId,
+        bytes: Bytes,
+    ) -> Result<()> {
+        let path = paths::auth_tokens_path(inner, server_id);
+        inner.put(&path, bytes).await
+    }
+
     /// Returns what the root path would be for a given database. Does not check existence or
     /// validity of the path in object storage.
     pub fn root_path_for(inner: &ObjectStore, uuid: Uuid) -> RootPath {
@@ -193,6 +210,21 @@ impl IoxObjectStore {
         Ok(self.inner.get(&owner_path).await?.bytes().await?.into())
     }
 
+    /// Store the progress of the database's continuous queries, which is kept next to the rules
+    /// so that restarted queries resume where they stopped.
+    pub async fn put_continuous_query_progress_file(&self, bytes: Bytes) -> Result<()> {
+        let path = self.root_path.continuous_query_progress_path();
+
+        self.inner.put(&path, bytes).await
+    }
+
+    /// Return the progress of the database's continuous queries.
+    pub async fn get_continuous_query_progress_file(&self) -> Result<Bytes> {
+        let path = self.root_path.continuous_query_progress_path();
+
+        Ok(self.inner.get(&path).await?.bytes().await?.into())
+    }
+
     /// The location in object storage for all files for this database, suitable for logging or
     /// debugging purposes only. Do not parse this, as its format is subject to change!
     pub fn debug_database_path(&self) -> String {
@@ -681,6 +713,40 @@ mod tests {
         assert_eq!(expected_content, actual_content);
     }
 
+    #[tokio::test]
+    async fn continuous_query_progress_should_be_a_file() {
+        let object_store = make_object_store();
+        let uuid = Uuid::new_v4();
+        let mut progress_path = object_store.new_path();
+        progress_path.push_all_dirs(&[ALL_DATABASES_DIRECTORY, uuid.to_string().as_str()]);
+        progress_path.set_file_name("continuous_queries.pb");
+
+        let iox_object_store = IoxObjectStore::create(Arc::clone(&object_store), uuid)
+            .await
+            .unwrap();
+
+        let content = Bytes::from("progress");
+        iox_object_store
+            .put_continuous_query_progress_file(content.clone())
+            .await
+            .unwrap();
+
+        let actual_content = object_store
+            .get(&progress_path)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(content, actual_content);
+
+        let actual_content = iox_object_store
+            .get_continuous_query_progress_file()
+            .await
+            .unwrap();
+        assert_eq!(content, actual_content);
+    }
+
     #[tokio::test]
     async fn create_new_with_same_uuid_errors() {
         let object_store = make_object_store();
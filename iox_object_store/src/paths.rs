@@ -17,7 +17,9 @@ use transaction_file::TransactionFilePath;
 pub(crate) const ALL_DATABASES_DIRECTORY: &str = "dbs";
 const ALL_SERVERS_DIRECTORY: &str = "nodes";
 pub(crate) const SERVER_CONFIG_FILE_NAME: &str = "config.pb";
+const AUTH_TOKENS_FILE_NAME: &str = "tokens.json";
 const DATABASE_OWNER_FILE_NAME: &str = "owner.pb";
+const CONTINUOUS_QUERY_PROGRESS_FILE_NAME: &str = "continuous_queries.pb";
 
 /// The path to the server file containing the list of databases this server owns.
 // TODO: this is in the process of replacing all_databases_path for the floating databases design
@@ -29,6 +31,15 @@ pub(crate) fn server_config_path(object_store: &ObjectStore, server_id: ServerId
     path
 }
 
+/// The path to the file containing the API tokens accepted by this server, next to its config.
+pub(crate) fn auth_tokens_path(object_store: &ObjectStore, server_id: ServerId) -> Path {
+    let mut path = object_store.new_path();
+    path.push_dir(ALL_SERVERS_DIRECTORY);
+    path.push_dir(server_id.to_string());
+    path.set_file_name(AUTH_TOKENS_FILE_NAME);
+    path
+}
+
 /// A database-specific object store path that all `IoxObjectStore` `Path`s should be within.
 /// This can be serialized to facilitate initial loading of a database from object storage, but
 /// the path should not be parsed into its component parts as the format might change.
@@ -64,6 +75,12 @@ impl RootPath {
         result
     }
 
+    pub(crate) fn continuous_query_progress_path(&self) -> Path {
+        let mut result = self.inner.clone();
+        result.set_file_name(CONTINUOUS_QUERY_PROGRESS_FILE_NAME);
+        result
+    }
+
     pub(crate) fn rules_path(&self) -> RulesPath {
         RulesPath::new(self)
     }
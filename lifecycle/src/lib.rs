@@ -43,6 +43,11 @@ pub trait LifecycleDb {
 
     /// Return the time provider for this database
     fn time_provider(&self) -> &Arc<dyn TimeProvider>;
+
+    /// Hide all rows of `table_name` with timestamps before `before`
+    /// from queries, used to enforce the retention period on chunks
+    /// that cannot be dropped
+    fn delete_before(&self, table_name: &str, before: Time);
 }
 
 /// A `LockablePartition` is a wrapper around a `LifecyclePartition` that allows
@@ -119,6 +124,11 @@ pub trait LockablePartition: Sized {
         partition: LifecycleWriteGuard<'_, Self::Partition, Self>,
         chunk: LifecycleWriteGuard<'_, <Self::Chunk as LockableChunk>::Chunk, Self::Chunk>,
     ) -> Result<TaskTracker<<Self::Chunk as LockableChunk>::Job>, Self::Error>;
+
+    /// Drops all chunks from the partition
+    fn drop_partition(
+        partition: LifecycleWriteGuard<'_, Self::Partition, Self>,
+    ) -> Result<TaskTracker<<Self::Chunk as LockableChunk>::Job>, Self::Error>;
 }
 
 /// A `LockableChunk` is a wrapper around a `LifecycleChunk` that allows for
@@ -188,6 +198,9 @@ pub trait LifecycleChunk {
     /// Returns the min timestamp contained within this chunk
     fn min_timestamp(&self) -> Time;
 
+    /// Returns the max timestamp contained within this chunk
+    fn max_timestamp(&self) -> Time;
+
     /// Returns the access metrics for this chunk
     fn access_metrics(&self) -> AccessMetrics;
 
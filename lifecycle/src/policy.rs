@@ -10,13 +10,22 @@ use data_types::{
 use futures::future::BoxFuture;
 use internal_types::access::AccessMetrics;
 use observability_deps::tracing::{debug, info, trace, warn};
-use std::{fmt::Debug, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    sync::Arc,
+    time::Duration,
+};
 use time::Time;
 use tracker::TaskTracker;
 
 /// Number of seconds to wait before retrying a failed lifecycle action
 pub const LIFECYCLE_ACTION_BACKOFF: Duration = Duration::from_secs(10);
 
+/// Minimum advance of the retention cutoff before another delete predicate
+/// is created for a table's partially expired chunks
+pub const RETENTION_DELETE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 /// A `LifecyclePolicy` is created with a `LifecycleDb`
 ///
 /// `LifecyclePolicy::check_for_work` can then be used to drive progress
@@ -35,6 +44,9 @@ pub struct LifecyclePolicy<M> {
     ///
     /// This can be helpful during some phases of the database startup process.
     suppress_persistence: bool,
+
+    /// The retention cutoff of the last delete predicate created for each table
+    retention_deletes: BTreeMap<Arc<str>, Time>,
 }
 
 impl<M> LifecyclePolicy<M>
@@ -50,6 +62,7 @@ where
             trackers: vec![],
             active_compactions: 0,
             suppress_persistence: false,
+            retention_deletes: Default::default(),
         }
     }
 
@@ -60,6 +73,7 @@ where
             trackers: vec![],
             active_compactions: 0,
             suppress_persistence: true,
+            retention_deletes: Default::default(),
         }
     }
 
@@ -432,6 +446,125 @@ where
         }
     }
 
+    /// Enforce the retention period on a partition
+    ///
+    /// Drops the whole partition if all of its chunks only contain rows older than
+    /// `cutoff`, otherwise drops the individual expired chunks. Chunks that still
+    /// contain expired rows but cannot be dropped, either because they also contain
+    /// newer rows or because they are not yet persisted in a persisted database,
+    /// have their table added to `expired_tables` so the rows can be deleted.
+    ///
+    /// Returns true if the partition is being dropped
+    fn maybe_enforce_retention<P: LockablePartition>(
+        &mut self,
+        db_name: &DatabaseName<'static>,
+        partition: &P,
+        persist: bool,
+        cutoff: Time,
+        expired_tables: &mut BTreeSet<Arc<str>>,
+    ) -> bool {
+        let guard = partition.read();
+        let chunks = LockablePartition::chunks(&guard);
+        if chunks.is_empty() {
+            return false;
+        }
+
+        let mut to_drop = Vec::new();
+        for chunk in &chunks {
+            let chunk = chunk.read();
+            if chunk.min_timestamp() >= cutoff {
+                continue;
+            }
+
+            let action = chunk.lifecycle_action().map(|x| *x.metadata());
+            if action == Some(ChunkLifecycleAction::Dropping) {
+                continue;
+            }
+
+            let max_timestamp = chunk.max_timestamp();
+            let droppable = max_timestamp < cutoff
+                && action.is_none()
+                && match chunk.storage() {
+                    ChunkStorage::OpenMutableBuffer => false,
+                    ChunkStorage::ClosedMutableBuffer | ChunkStorage::ReadBuffer => !persist,
+                    ChunkStorage::ReadBufferAndObjectStore | ChunkStorage::ObjectStoreOnly => true,
+                };
+
+            if droppable {
+                to_drop.push(chunk.addr().chunk_id);
+                continue;
+            }
+
+            // Rows before the cutoff of a previous delete are already hidden
+            let table_name = &chunk.addr().table_name;
+            let covered = matches!(
+                self.retention_deletes.get(table_name),
+                Some(deleted_before) if max_timestamp < *deleted_before
+            );
+            if !covered {
+                expired_tables.insert(Arc::clone(table_name));
+            }
+        }
+
+        if to_drop.len() == chunks.len() {
+            info!(%db_name, partition=%guard, "dropping expired partition");
+            let tracker = LockablePartition::drop_partition(guard.upgrade())
+                .expect("failed to drop partition")
+                .with_metadata(ChunkLifecycleAction::Dropping);
+            self.trackers.push(tracker);
+            return true;
+        }
+        std::mem::drop(guard);
+
+        // Drop chunks one at a time, re-checking the pre-conditions as
+        // the partition lock is released in between
+        for chunk_id in to_drop {
+            let guard = partition.read();
+            let chunk = match LockablePartition::chunk(&guard, chunk_id) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            let chunk = chunk.read();
+            if chunk.lifecycle_action().is_some() || chunk.max_timestamp() >= cutoff {
+                continue;
+            }
+
+            info!(%db_name, chunk=%chunk.addr(), "dropping expired chunk");
+            let tracker = LockablePartition::drop_chunk(guard.upgrade(), chunk.upgrade())
+                .expect("failed to drop chunk")
+                .with_metadata(ChunkLifecycleAction::Dropping);
+            self.trackers.push(tracker);
+        }
+
+        false
+    }
+
+    /// Delete the rows older than `cutoff` from `expired_tables`
+    ///
+    /// To avoid accumulating delete predicates, a table is only deleted
+    /// from again once `cutoff` has advanced by `RETENTION_DELETE_INTERVAL`
+    fn maybe_delete_expired(
+        &mut self,
+        db_name: &DatabaseName<'static>,
+        expired_tables: BTreeSet<Arc<str>>,
+        cutoff: Time,
+    ) {
+        for table_name in expired_tables {
+            if let Some(deleted_before) = self.retention_deletes.get(&table_name) {
+                let advanced = cutoff
+                    .checked_duration_since(*deleted_before)
+                    .unwrap_or_default();
+                if advanced < RETENTION_DELETE_INTERVAL {
+                    continue;
+                }
+            }
+
+            info!(%db_name, %table_name, before=%cutoff.to_rfc3339(), "deleting expired rows");
+            self.db.delete_before(&table_name, cutoff);
+            self.retention_deletes.insert(table_name, cutoff);
+        }
+    }
+
     /// The core policy logic
     ///
     /// Returns a future that resolves when this method should be called next
@@ -446,9 +579,27 @@ where
         let rules = self.db.rules();
         let partitions = self.db.partitions();
 
+        let retention_cutoff = rules
+            .retention_period
+            .and_then(|period| now.checked_sub(period));
+        let mut expired_tables = BTreeSet::new();
+
         for partition in &partitions {
             self.maybe_cleanup_failed(&db_name, partition, now);
 
+            if let Some(cutoff) = retention_cutoff {
+                let dropping = self.maybe_enforce_retention(
+                    &db_name,
+                    partition,
+                    rules.persist,
+                    cutoff,
+                    &mut expired_tables,
+                );
+                if dropping {
+                    continue;
+                }
+            }
+
             // Persistence cannot split chunks if they are currently being compacted
             //
             // To avoid compaction "starving" persistence we employ a
@@ -490,6 +641,10 @@ where
             self.maybe_compact_chunks(partition, &rules, now);
         }
 
+        if let Some(cutoff) = retention_cutoff {
+            self.maybe_delete_expired(&db_name, expired_tables, cutoff);
+        }
+
         if let Some(soft_limit) = rules.buffer_size_soft {
             self.maybe_free_memory(&db_name, &partitions, soft_limit.get(), rules.persist)
         }
@@ -707,6 +862,8 @@ mod tests {
         Unload(ChunkId),
         Compact(Vec<ChunkId>),
         Persist(Vec<ChunkId>),
+        DropPartition(Vec<ChunkId>),
+        Delete(Time),
     }
 
     #[derive(Debug)]
@@ -746,6 +903,7 @@ mod tests {
         addr: ChunkAddr,
         row_count: usize,
         min_timestamp: Option<Time>,
+        max_timestamp: Option<Time>,
         access_metrics: AccessMetrics,
         time_of_last_write: Time,
         lifecycle_action: Option<TaskTracker<ChunkLifecycleAction>>,
@@ -766,6 +924,7 @@ mod tests {
                 addr,
                 row_count: 10,
                 min_timestamp: None,
+                max_timestamp: None,
                 access_metrics: AccessMetrics {
                     count: 0,
                     last_access: Time::from_timestamp(0, 0),
@@ -792,6 +951,12 @@ mod tests {
             self
         }
 
+        fn with_timestamps(mut self, min_timestamp: Time, max_timestamp: Time) -> Self {
+            self.min_timestamp = Some(min_timestamp);
+            self.max_timestamp = Some(max_timestamp);
+            self
+        }
+
         fn with_access_metrics(mut self, metrics: AccessMetrics) -> Self {
             self.access_metrics = metrics;
             self
@@ -893,6 +1058,12 @@ mod tests {
                     (None, Some(ts)) => Some(ts),
                     (None, None) => None,
                 };
+                new_chunk.max_timestamp = match (new_chunk.max_timestamp, chunk.max_timestamp) {
+                    (Some(ts1), Some(ts2)) => Some(ts1.max(ts2)),
+                    (Some(ts), None) => Some(ts),
+                    (None, Some(ts)) => Some(ts),
+                    (None, None) => None,
+                };
                 order = order.min(chunk.order);
             }
 
@@ -962,6 +1133,17 @@ mod tests {
             db.events.write().push(MoverEvents::Drop(chunk_id));
             Ok(db.registry.lock().complete(()))
         }
+
+        fn drop_partition(
+            mut partition: LifecycleWriteGuard<'_, Self::Partition, Self>,
+        ) -> Result<TaskTracker<()>, Self::Error> {
+            let chunk_ids = std::mem::take(&mut partition.chunks).into_keys().collect();
+            let db = partition.data().db;
+            db.events
+                .write()
+                .push(MoverEvents::DropPartition(chunk_ids));
+            Ok(db.registry.lock().complete(()))
+        }
     }
 
     impl<'a> LockableChunk for TestLockableChunk<'a> {
@@ -1032,6 +1214,10 @@ mod tests {
             self.min_timestamp.unwrap()
         }
 
+        fn max_timestamp(&self) -> Time {
+            self.max_timestamp.unwrap()
+        }
+
         fn access_metrics(&self) -> AccessMetrics {
             self.access_metrics.clone()
         }
@@ -1142,6 +1328,10 @@ mod tests {
         fn time_provider(&self) -> &Arc<dyn TimeProvider> {
             &self.time_provider
         }
+
+        fn delete_before(&self, _table_name: &str, before: Time) {
+            self.events.write().push(MoverEvents::Delete(before));
+        }
     }
 
     fn from_secs(secs: i64) -> Time {
@@ -2031,4 +2221,100 @@ mod tests {
         lifecycle.check_for_work();
         assert!(chunk.read().lifecycle_action().is_none());
     }
+
+    #[test]
+    fn test_retention() {
+        let rules = LifecycleRules {
+            retention_period: Some(Duration::from_secs(100)),
+            ..Default::default()
+        };
+
+        let partitions = vec![
+            // entirely expired => dropped as a whole
+            TestPartition::new(vec![
+                TestChunk::new(ChunkId::new_test(0), 0, ChunkStorage::ObjectStoreOnly)
+                    .with_timestamps(from_secs(10), from_secs(20)),
+                TestChunk::new(ChunkId::new_test(1), 0, ChunkStorage::ReadBuffer)
+                    .with_timestamps(from_secs(30), from_secs(40)),
+            ]),
+            // expired chunk is dropped, partially expired one needs a delete
+            TestPartition::new(vec![
+                TestChunk::new(ChunkId::new_test(2), 0, ChunkStorage::ObjectStoreOnly)
+                    .with_timestamps(from_secs(10), from_secs(20)),
+                TestChunk::new(ChunkId::new_test(3), 0, ChunkStorage::ObjectStoreOnly)
+                    .with_timestamps(from_secs(850), from_secs(10_000)),
+            ]),
+            // nothing expired
+            TestPartition::new(vec![TestChunk::new(
+                ChunkId::new_test(4),
+                0,
+                ChunkStorage::ObjectStoreOnly,
+            )
+            .with_timestamps(from_secs(950), from_secs(960))]),
+        ];
+
+        let (db, time_provider) = test_db_partitions(rules, partitions, from_secs(1_000));
+        let mut lifecycle = LifecyclePolicy::new(&db);
+
+        lifecycle.check_for_work();
+        assert_eq!(
+            *db.events.read(),
+            vec![
+                MoverEvents::DropPartition(vec![ChunkId::new_test(0), ChunkId::new_test(1)]),
+                MoverEvents::Drop(ChunkId::new_test(2)),
+                MoverEvents::Delete(from_secs(900)),
+            ]
+        );
+
+        // The delete is not repeated until the cutoff advanced sufficiently
+        db.events.write().clear();
+        time_provider.inc(Duration::from_secs(60));
+        lifecycle.check_for_work();
+        assert_eq!(*db.events.read(), vec![]);
+
+        time_provider.inc(RETENTION_DELETE_INTERVAL);
+        lifecycle.check_for_work();
+        assert_eq!(
+            *db.events.read(),
+            vec![
+                MoverEvents::DropPartition(vec![ChunkId::new_test(4)]),
+                MoverEvents::Delete(from_secs(900 + 60) + RETENTION_DELETE_INTERVAL),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retention_persisted() {
+        // Unpersisted chunks cannot be dropped from a persisted database
+        let rules = LifecycleRules {
+            persist: true,
+            retention_period: Some(Duration::from_secs(100)),
+            ..Default::default()
+        };
+
+        let chunks = vec![
+            TestChunk::new(
+                ChunkId::new_test(0),
+                0,
+                ChunkStorage::ReadBufferAndObjectStore,
+            )
+            .with_timestamps(from_secs(10), from_secs(20)),
+            TestChunk::new(ChunkId::new_test(1), 0, ChunkStorage::ReadBuffer)
+                .with_timestamps(from_secs(30), from_secs(40)),
+            TestChunk::new(ChunkId::new_test(2), 1_000, ChunkStorage::OpenMutableBuffer)
+                .with_timestamps(from_secs(950), from_secs(960)),
+        ];
+
+        let (db, _) = test_db_chunks(rules, chunks, from_secs(1_000));
+        let mut lifecycle = LifecyclePolicy::new(&db);
+
+        lifecycle.check_for_work();
+        assert_eq!(
+            *db.events.read(),
+            vec![
+                MoverEvents::Drop(ChunkId::new_test(0)),
+                MoverEvents::Delete(from_secs(900)),
+            ]
+        );
+    }
 }
@@ -30,6 +30,7 @@ use crate::column::{Column, ColumnData};
 
 pub mod column;
 pub mod payload;
+pub mod transform;
 pub mod writer;
 
 pub use payload::*;
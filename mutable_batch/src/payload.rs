@@ -1,9 +1,9 @@
 //! Write payload abstractions derived from [`MutableBatch`]
 
-use crate::column::ColumnData;
+use crate::column::{ColumnData, INVALID_DID};
 use crate::{MutableBatch, Result};
 use data_types::database_rules::PartitionTemplate;
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use schema::TIME_COLUMN_NAME;
 use std::num::NonZeroUsize;
 use std::ops::Range;
@@ -68,6 +68,32 @@ impl<'a> PartitionWrite<'a> {
         self.row_count
     }
 
+    /// Returns the distinct non-null values of each tag column in the write
+    pub fn tag_values(&self) -> Vec<(&'a str, Vec<&'a str>)> {
+        let batch: &'a MutableBatch = self.batch;
+
+        batch
+            .columns()
+            .filter_map(|(name, column)| {
+                let (keys, dictionary) = match &column.data {
+                    ColumnData::Tag(keys, dictionary, _) => (keys, dictionary),
+                    _ => return None,
+                };
+
+                let mut seen = HashSet::new();
+                let values = self
+                    .ranges
+                    .iter()
+                    .flat_map(|range| &keys[range.clone()])
+                    .filter(|key| **key != INVALID_DID && seen.insert(**key))
+                    .map(|key| dictionary.lookup_id(*key).expect("valid dictionary id"))
+                    .collect();
+
+                Some((name.as_str(), values))
+            })
+            .collect()
+    }
+
     /// Returns a [`PartitionWrite`] containing just the rows of `Self` that pass
     /// the provided time predicate, or None if no rows
     pub fn filter(&self, predicate: impl Fn(i64) -> bool) -> Option<PartitionWrite<'a>> {
@@ -0,0 +1,324 @@
+//! Rewriting the [`MutableBatch`]es of a write according to [`IngestRule`]s
+
+use std::ops::Range;
+
+use hashbrown::hash_map::Entry;
+use hashbrown::HashMap;
+use regex::Regex;
+use snafu::{ensure, ResultExt, Snafu};
+
+use data_types::ingest::{IngestAction, IngestRule};
+use schema::{InfluxColumnType, TIME_COLUMN_NAME};
+
+use crate::column::{Column, ColumnData, INVALID_DID};
+use crate::writer::{self, Writer};
+use crate::MutableBatch;
+
+#[allow(missing_docs)]
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Ingest rules cannot modify the {} column", TIME_COLUMN_NAME))]
+    TimeColumn,
+
+    #[snafu(display(
+        "Cannot rename column '{}' of table '{}' to '{}': the column already exists",
+        from,
+        table_name,
+        to
+    ))]
+    ColumnExists {
+        table_name: String,
+        from: String,
+        to: String,
+    },
+
+    #[snafu(display(
+        "Cannot set tag '{}' of table '{}': the column has type {}",
+        tag,
+        table_name,
+        influx_type
+    ))]
+    NotATag {
+        table_name: String,
+        tag: String,
+        influx_type: InfluxColumnType,
+    },
+
+    #[snafu(display("Cannot rename table '{}' to an empty name", table_name))]
+    EmptyTableName { table_name: String },
+
+    #[snafu(display("Cannot merge table '{}' into table '{}': {}", from, to, source))]
+    MergeTables {
+        from: String,
+        to: String,
+        source: crate::Error,
+    },
+}
+
+/// A specialized `Error` for [`transform`] errors
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Applies `rules` in order to `tables`, the batches of a write keyed by
+/// table name
+///
+/// Tables left without rows are removed, so the returned map may be empty
+pub fn transform(
+    rules: &[IngestRule],
+    mut tables: HashMap<String, MutableBatch>,
+) -> Result<HashMap<String, MutableBatch>> {
+    for rule in rules {
+        if let IngestAction::RenameTable { regex, replacement } = &rule.action {
+            tables = rename_tables(rule, regex, replacement, tables)?;
+            continue;
+        }
+
+        for (table_name, batch) in tables.iter_mut() {
+            if rule.matches_table(table_name) {
+                apply(&rule.action, table_name, batch)?;
+            }
+        }
+        tables.retain(|_, batch| batch.rows() != 0);
+    }
+
+    Ok(tables)
+}
+
+fn rename_tables(
+    rule: &IngestRule,
+    regex: &Regex,
+    replacement: &str,
+    tables: HashMap<String, MutableBatch>,
+) -> Result<HashMap<String, MutableBatch>> {
+    let mut renamed = HashMap::with_capacity(tables.len());
+    for (table_name, batch) in tables {
+        let new_name = match rule.matches_table(&table_name) {
+            true => regex.replace(&table_name, replacement).into_owned(),
+            false => table_name.clone(),
+        };
+        ensure!(!new_name.is_empty(), EmptyTableName { table_name });
+
+        match renamed.entry(new_name) {
+            Entry::Vacant(entry) => {
+                entry.insert(batch);
+            }
+            Entry::Occupied(mut entry) => {
+                let to = entry.key().clone();
+                entry.get_mut().extend_from(&batch).context(MergeTables {
+                    from: table_name,
+                    to,
+                })?;
+            }
+        }
+    }
+    Ok(renamed)
+}
+
+fn apply(action: &IngestAction, table_name: &str, batch: &mut MutableBatch) -> Result<()> {
+    match action {
+        IngestAction::DropColumn(column) => {
+            check_not_time(column)?;
+            batch.remove_column(column);
+        }
+        IngestAction::RenameColumn { from, to } => {
+            check_not_time(from)?;
+            check_not_time(to)?;
+            if let Some(idx) = batch.column_names.get(from.as_str()).copied() {
+                ensure!(
+                    !batch.column_names.contains_key(to.as_str()),
+                    ColumnExists {
+                        table_name,
+                        from,
+                        to
+                    }
+                );
+                batch.column_names.remove(from.as_str());
+                batch.column_names.insert(to.clone(), idx);
+            }
+        }
+        IngestAction::FieldToTag(name) => {
+            check_not_time(name)?;
+            let tag = batch.column(name).ok().and_then(|c| field_to_tag(name, c));
+            if let Some(tag) = tag {
+                batch.set_column(name, tag);
+            }
+        }
+        IngestAction::TagToField(name) => {
+            check_not_time(name)?;
+            let field = batch.column(name).ok().and_then(|c| tag_to_field(name, c));
+            if let Some(field) = field {
+                batch.set_column(name, field);
+            }
+        }
+        IngestAction::AddTag { tag, value } => {
+            check_not_time(tag)?;
+            if let Ok(column) = batch.column(tag) {
+                let influx_type = column.influx_type();
+                ensure!(
+                    influx_type == InfluxColumnType::Tag,
+                    NotATag {
+                        table_name,
+                        tag,
+                        influx_type
+                    }
+                );
+            }
+
+            let rows = batch.rows();
+            let column = build_column(rows, |writer| {
+                writer.write_tag(tag, None, std::iter::repeat(value.as_str()).take(rows))
+            });
+            batch.set_column(tag, column);
+        }
+        IngestAction::DropRows { column, regex } => {
+            check_not_time(column)?;
+            let ranges = batch
+                .column(column)
+                .ok()
+                .and_then(|c| retained_ranges(c, regex));
+            if let Some(ranges) = ranges {
+                let mut retained = MutableBatch::new();
+                if !ranges.is_empty() {
+                    retained
+                        .extend_from_ranges(batch, &ranges)
+                        .expect("writing to an empty batch");
+                }
+                *batch = retained;
+            }
+        }
+        IngestAction::RenameTable { .. } => unreachable!("tables are renamed by transform"),
+    }
+    Ok(())
+}
+
+fn check_not_time(column: &str) -> Result<()> {
+    ensure!(column != TIME_COLUMN_NAME, TimeColumn);
+    Ok(())
+}
+
+/// Converts a field column to a tag column, returning `None` if `column`
+/// already is a tag
+fn field_to_tag(name: &str, column: &Column) -> Option<Column> {
+    let rows = valid_rows(column);
+    let values: Vec<String> = match &column.data {
+        ColumnData::F64(data, _) => rows.map(|idx| data[idx].to_string()).collect(),
+        ColumnData::I64(data, _) => rows.map(|idx| data[idx].to_string()).collect(),
+        ColumnData::U64(data, _) => rows.map(|idx| data[idx].to_string()).collect(),
+        ColumnData::Bool(data, _) => rows.map(|idx| data.get(idx).to_string()).collect(),
+        ColumnData::String(data, _) => rows
+            .map(|idx| data.get(idx).expect("valid row").to_string())
+            .collect(),
+        ColumnData::Tag(_, _, _) => return None,
+    };
+
+    Some(build_column(column.len(), |writer| {
+        writer.write_tag(
+            name,
+            Some(column.valid.bytes()),
+            values.iter().map(String::as_str),
+        )
+    }))
+}
+
+/// Converts a tag column to a string field column, returning `None` if
+/// `column` is not a tag
+fn tag_to_field(name: &str, column: &Column) -> Option<Column> {
+    let values = match &column.data {
+        ColumnData::Tag(keys, dictionary, _) => valid_rows(column).map(|idx| {
+            dictionary
+                .lookup_id(keys[idx])
+                .expect("valid dictionary id")
+        }),
+        _ => return None,
+    };
+
+    Some(build_column(column.len(), |writer| {
+        writer.write_string(name, Some(column.valid.bytes()), values)
+    }))
+}
+
+/// Returns the ranges of rows whose value in `column` does not match `regex`,
+/// or `None` if no rows match. Only tag and string columns are matched.
+fn retained_ranges(column: &Column, regex: &Regex) -> Option<Vec<Range<usize>>> {
+    let matches: Vec<bool> = match &column.data {
+        ColumnData::Tag(keys, dictionary, _) => {
+            // Match each distinct value once
+            let matching: Vec<bool> = dictionary
+                .values()
+                .iter()
+                .map(|value| regex.is_match(value))
+                .collect();
+            keys.iter()
+                .map(|key| *key != INVALID_DID && matching[*key as usize])
+                .collect()
+        }
+        ColumnData::String(data, _) => (0..column.len())
+            .map(|idx| column.valid.get(idx) && regex.is_match(data.get(idx).unwrap()))
+            .collect(),
+        _ => return None,
+    };
+
+    if !matches.contains(&true) {
+        return None;
+    }
+
+    let mut ranges = vec![];
+    let mut start = None;
+    for (idx, matched) in matches.iter().enumerate() {
+        match (matched, start) {
+            (false, None) => start = Some(idx),
+            (true, Some(s)) => {
+                ranges.push(s..idx);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        ranges.push(s..matches.len());
+    }
+    Some(ranges)
+}
+
+/// Returns the indexes of the non-null rows of `column`
+fn valid_rows(column: &Column) -> impl Iterator<Item = usize> + '_ {
+    (0..column.len()).filter(move |idx| column.valid.get(*idx))
+}
+
+/// Builds a column of `rows` rows by calling `write` with a [`Writer`]
+fn build_column<F>(rows: usize, write: F) -> Column
+where
+    F: FnOnce(&mut Writer<'_>) -> writer::Result<()>,
+{
+    let mut batch = MutableBatch::new();
+    let mut writer = Writer::new(&mut batch, rows);
+    write(&mut writer).expect("a value for each valid row");
+    writer.commit();
+    batch.columns.pop().expect("column written")
+}
+
+impl MutableBatch {
+    /// Removes the column `name`, if any
+    fn remove_column(&mut self, name: &str) -> Option<Column> {
+        let idx = self.column_names.remove(name)?;
+        for other in self.column_names.values_mut() {
+            if *other > idx {
+                *other -= 1;
+            }
+        }
+        Some(self.columns.remove(idx))
+    }
+
+    /// Sets the column `name` to `column`, which must have one row for each
+    /// row of this batch
+    fn set_column(&mut self, name: &str, column: Column) {
+        assert_eq!(column.len(), self.row_count);
+        match self.column_names.get(name) {
+            Some(idx) => self.columns[*idx] = column,
+            None => {
+                self.column_names
+                    .insert(name.to_string(), self.columns.len());
+                self.columns.push(column);
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use data_types::database_rules::{PartitionTemplate, TemplatePart};
+use mutable_batch::writer::Writer;
+use mutable_batch::{MutableBatch, PartitionWrite};
+
+fn batch(tag1: Vec<&str>, tag2_mask: u8, tag2: Vec<&str>, times: Vec<i64>) -> MutableBatch {
+    let mut batch = MutableBatch::new();
+    let mut writer = Writer::new(&mut batch, times.len());
+
+    writer.write_tag("tag1", None, tag1.into_iter()).unwrap();
+    writer
+        .write_tag("tag2", Some(&[tag2_mask]), tag2.into_iter())
+        .unwrap();
+    writer
+        .write_f64("f64", None, std::iter::repeat(1.).take(times.len()))
+        .unwrap();
+    writer.write_time("time", times.into_iter()).unwrap();
+    writer.commit();
+
+    batch
+}
+
+fn sorted<'a>(mut tag_values: Vec<(&'a str, Vec<&'a str>)>) -> Vec<(&'a str, Vec<&'a str>)> {
+    for (_, values) in &mut tag_values {
+        values.sort_unstable();
+    }
+    tag_values.sort_unstable();
+    tag_values
+}
+
+#[test]
+fn test_tag_values() {
+    let b = batch(
+        vec!["a", "c", "c", "d"],
+        0b00001110,
+        vec!["x", "x", "z"],
+        vec![1, 2, 3, 4],
+    );
+    let write = PartitionWrite::new(&b);
+
+    // Nulls are skipped and values only returned once
+    assert_eq!(
+        sorted(write.tag_values()),
+        vec![("tag1", vec!["a", "c", "d"]), ("tag2", vec!["x", "z"])]
+    );
+}
+
+#[test]
+fn test_tag_values_partitioned() {
+    let b = batch(
+        vec!["a", "b", "c", "d"],
+        0b00001111,
+        vec!["x", "y", "x", "y"],
+        vec![1, 2, 3, 4],
+    );
+
+    let template = PartitionTemplate {
+        parts: vec![TemplatePart::Column("tag2".to_string())],
+    };
+    let partitioned = PartitionWrite::partition("table", &b, &template);
+
+    // Only the rows of each partition are returned
+    let mut tag_values: Vec<_> = partitioned
+        .into_iter()
+        .map(|(key, write)| (key, sorted(write.tag_values())))
+        .collect();
+    tag_values.sort_unstable();
+    assert_eq!(
+        tag_values,
+        vec![
+            (
+                "tag2_x".to_string(),
+                vec![("tag1", vec!["a", "c"]), ("tag2", vec!["x"])]
+            ),
+            (
+                "tag2_y".to_string(),
+                vec![("tag1", vec!["b", "d"]), ("tag2", vec!["y"])]
+            ),
+        ]
+    );
+}
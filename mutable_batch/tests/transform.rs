@@ -0,0 +1,211 @@
+use arrow_util::assert_batches_eq;
+use data_types::ingest::{IngestAction, IngestRule};
+use hashbrown::HashMap;
+use mutable_batch::transform::{transform, Error};
+use mutable_batch::writer::Writer;
+use mutable_batch::MutableBatch;
+use regex::Regex;
+use schema::selection::Selection;
+use schema::InfluxColumnType;
+
+/// Returns a batch of three rows, the last of which is from a test host
+/// and has no region
+fn batch() -> MutableBatch {
+    let mut batch = MutableBatch::new();
+    let mut writer = Writer::new(&mut batch, 3);
+
+    writer
+        .write_tag("host", None, vec!["a", "b", "test-1"].into_iter())
+        .unwrap();
+    writer
+        .write_string("region", Some(&[0b00000011]), vec!["us", "eu"].into_iter())
+        .unwrap();
+    writer
+        .write_f64("usage", None, vec![1., 2., 3.].into_iter())
+        .unwrap();
+    writer
+        .write_time("time", vec![1, 2, 3].into_iter())
+        .unwrap();
+    writer.commit();
+
+    batch
+}
+
+fn rule(action: IngestAction) -> IngestRule {
+    IngestRule {
+        table_name_regex: None,
+        action,
+    }
+}
+
+fn tables(names: &[&str]) -> HashMap<String, MutableBatch> {
+    names
+        .iter()
+        .map(|name| (name.to_string(), batch()))
+        .collect()
+}
+
+#[test]
+fn test_transform_columns() {
+    let rules = vec![
+        rule(IngestAction::DropRows {
+            column: "host".to_string(),
+            regex: Regex::new("^test-").unwrap(),
+        }),
+        rule(IngestAction::RenameColumn {
+            from: "usage".to_string(),
+            to: "usage_percent".to_string(),
+        }),
+        rule(IngestAction::FieldToTag("region".to_string())),
+        rule(IngestAction::AddTag {
+            tag: "dc".to_string(),
+            value: "east".to_string(),
+        }),
+        rule(IngestAction::DropColumn("host".to_string())),
+        // Columns the table doesn't have are ignored
+        rule(IngestAction::DropColumn("missing".to_string())),
+    ];
+
+    let transformed = transform(&rules, tables(&["cpu"])).unwrap();
+    let cpu = &transformed["cpu"];
+
+    assert_eq!(cpu.rows(), 2);
+    assert_eq!(
+        cpu.column("region").unwrap().influx_type(),
+        InfluxColumnType::Tag
+    );
+    assert_eq!(
+        cpu.column("dc").unwrap().influx_type(),
+        InfluxColumnType::Tag
+    );
+
+    assert_batches_eq!(
+        &[
+            "+------+--------+--------------------------------+---------------+",
+            "| dc   | region | time                           | usage_percent |",
+            "+------+--------+--------------------------------+---------------+",
+            "| east | us     | 1970-01-01T00:00:00.000000001Z | 1             |",
+            "| east | eu     | 1970-01-01T00:00:00.000000002Z | 2             |",
+            "+------+--------+--------------------------------+---------------+",
+        ],
+        &[cpu.to_arrow(Selection::All).unwrap()]
+    );
+}
+
+#[test]
+fn test_transform_convert() {
+    let rules = vec![
+        rule(IngestAction::TagToField("host".to_string())),
+        rule(IngestAction::FieldToTag("usage".to_string())),
+        // Converting a column to its own type does nothing
+        rule(IngestAction::TagToField("region".to_string())),
+    ];
+
+    let transformed = transform(&rules, tables(&["cpu"])).unwrap();
+    let cpu = &transformed["cpu"];
+
+    assert_eq!(
+        cpu.column("host").unwrap().influx_type(),
+        InfluxColumnType::Field(schema::InfluxFieldType::String)
+    );
+    assert_eq!(
+        cpu.column("usage").unwrap().influx_type(),
+        InfluxColumnType::Tag
+    );
+
+    assert_batches_eq!(
+        &[
+            "+--------+--------+--------------------------------+-------+",
+            "| host   | region | time                           | usage |",
+            "+--------+--------+--------------------------------+-------+",
+            "| a      | us     | 1970-01-01T00:00:00.000000001Z | 1     |",
+            "| b      | eu     | 1970-01-01T00:00:00.000000002Z | 2     |",
+            "| test-1 |        | 1970-01-01T00:00:00.000000003Z | 3     |",
+            "+--------+--------+--------------------------------+-------+",
+        ],
+        &[cpu.to_arrow(Selection::All).unwrap()]
+    );
+}
+
+#[test]
+fn test_transform_tables() {
+    let rules = vec![
+        IngestRule {
+            table_name_regex: Some(Regex::new("^mem$").unwrap()),
+            action: IngestAction::DropRows {
+                column: "region".to_string(),
+                regex: Regex::new(".*").unwrap(),
+            },
+        },
+        rule(IngestAction::RenameTable {
+            regex: Regex::new("^telegraf_(.*)$").unwrap(),
+            replacement: "$1".to_string(),
+        }),
+    ];
+
+    let transformed = transform(&rules, tables(&["mem", "telegraf_cpu", "cpu", "disk"])).unwrap();
+
+    // Only the row without a region is left in mem
+    assert_eq!(transformed["mem"].rows(), 1);
+
+    // telegraf_cpu is merged into cpu
+    assert_eq!(transformed["cpu"].rows(), 6);
+    assert_eq!(transformed["disk"].rows(), 3);
+    assert_eq!(transformed.len(), 3);
+
+    // Tables left without rows are removed
+    let rules = vec![rule(IngestAction::DropRows {
+        column: "host".to_string(),
+        regex: Regex::new("").unwrap(),
+    })];
+    let transformed = transform(&rules, tables(&["cpu"])).unwrap();
+    assert!(transformed.is_empty());
+}
+
+#[test]
+fn test_transform_errors() {
+    let rules = vec![rule(IngestAction::RenameColumn {
+        from: "host".to_string(),
+        to: "region".to_string(),
+    })];
+    let err = transform(&rules, tables(&["cpu"])).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Cannot rename column 'host' of table 'cpu' to 'region': the column already exists"
+    );
+
+    let rules = vec![rule(IngestAction::AddTag {
+        tag: "usage".to_string(),
+        value: "high".to_string(),
+    })];
+    let err = transform(&rules, tables(&["cpu"])).unwrap_err();
+    assert!(matches!(err, Error::NotATag { .. }));
+
+    let rules = vec![rule(IngestAction::DropColumn("time".to_string()))];
+    let err = transform(&rules, tables(&["cpu"])).unwrap_err();
+    assert!(matches!(err, Error::TimeColumn));
+
+    let rules = vec![rule(IngestAction::RenameTable {
+        regex: Regex::new(".*").unwrap(),
+        replacement: "".to_string(),
+    })];
+    let err = transform(&rules, tables(&["cpu"])).unwrap_err();
+    assert!(matches!(err, Error::EmptyTableName { .. }));
+
+    // Merging tables with conflicting column types fails
+    let rules = vec![rule(IngestAction::TagToField("host".to_string()))];
+    let mem = transform(&rules, tables(&["mem"]))
+        .unwrap()
+        .remove("mem")
+        .unwrap();
+
+    let rules = vec![rule(IngestAction::RenameTable {
+        regex: Regex::new("^.*$").unwrap(),
+        replacement: "all".to_string(),
+    })];
+    let tables = vec![("cpu".to_string(), batch()), ("mem".to_string(), mem)]
+        .into_iter()
+        .collect();
+    let err = transform(&rules, tables).unwrap_err();
+    assert!(matches!(err, Error::MergeTables { .. }));
+}
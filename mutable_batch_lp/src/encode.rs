@@ -0,0 +1,336 @@
+//! Code to encode [`MutableBatch`] and arrow [`RecordBatch`] as line protocol
+
+use arrow::{
+    array::{
+        Array, ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray,
+        TimestampNanosecondArray, UInt64Array,
+    },
+    compute::cast,
+    datatypes::DataType,
+    record_batch::RecordBatch,
+};
+use influxdb_line_protocol::{builder::LineProtocolBuilder, FieldValue};
+use mutable_batch::MutableBatch;
+use schema::{selection::Selection, InfluxColumnType, Schema};
+use snafu::{OptionExt, ResultExt, Snafu};
+
+/// Error type for line protocol encoding
+#[derive(Debug, Snafu)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[snafu(display("invalid IOx schema: {}", source))]
+    Schema { source: schema::Error },
+
+    #[snafu(display("column '{}' has no IOx column type", column))]
+    MissingColumnType { column: String },
+
+    #[snafu(display(
+        "column '{}' of type {:?} cannot be encoded as line protocol",
+        column,
+        data_type
+    ))]
+    UnsupportedColumn { column: String, data_type: DataType },
+
+    #[snafu(display("error converting column '{}': {}", column, source))]
+    Arrow {
+        column: String,
+        source: arrow::error::ArrowError,
+    },
+
+    #[snafu(display("error converting batch to arrow: {}", source))]
+    ToArrow { source: mutable_batch::Error },
+}
+
+/// Result type for line protocol encoding
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Values of a field column
+enum FieldValues<'a> {
+    F64(&'a Float64Array),
+    I64(&'a Int64Array),
+    U64(&'a UInt64Array),
+    String(&'a StringArray),
+    Bool(&'a BooleanArray),
+}
+
+impl<'a> FieldValues<'a> {
+    fn new(column: &'a ArrayRef) -> Option<Self> {
+        let any = column.as_any();
+        match column.data_type() {
+            DataType::Float64 => any.downcast_ref().map(Self::F64),
+            DataType::Int64 => any.downcast_ref().map(Self::I64),
+            DataType::UInt64 => any.downcast_ref().map(Self::U64),
+            DataType::Utf8 => any.downcast_ref().map(Self::String),
+            DataType::Boolean => any.downcast_ref().map(Self::Bool),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of `row`, or `None` if it is null or cannot be
+    /// represented in line protocol
+    fn value(&self, row: usize) -> Option<FieldValue<'a>> {
+        match self {
+            Self::F64(values) => (values.is_valid(row) && values.value(row).is_finite())
+                .then(|| FieldValue::F64(values.value(row))),
+            Self::I64(values) => values.is_valid(row).then(|| values.value(row).into()),
+            Self::U64(values) => values.is_valid(row).then(|| values.value(row).into()),
+            Self::String(values) => values.is_valid(row).then(|| values.value(row).into()),
+            Self::Bool(values) => values.is_valid(row).then(|| values.value(row).into()),
+        }
+    }
+}
+
+/// Appends the rows of the [`MutableBatch`] for the table `table_name` to
+/// `out` as line protocol
+///
+/// See [`encode_record_batch`] for the rows that cannot be represented.
+pub fn encode_batch(table_name: &str, batch: &MutableBatch, out: &mut Vec<u8>) -> Result<()> {
+    let batch = batch.to_arrow(Selection::All).context(ToArrow)?;
+    encode_record_batch(table_name, &batch, out)
+}
+
+/// The measurement name of the rows of a [`RecordBatch`]
+#[derive(Debug, Clone, Copy)]
+pub enum Measurement<'a> {
+    /// Every row has this measurement name
+    Name(&'a str),
+
+    /// Each row has the measurement name in this column, rows where it is
+    /// null are skipped
+    Column(&'a StringArray),
+}
+
+/// Appends the rows of `batch`, which must have IOx schema metadata, to `out`
+/// as line protocol with the measurement name `measurement`
+///
+/// Null and empty tag values and null field values are omitted from each
+/// line, as are non-finite float values which line protocol cannot represent.
+/// Rows without any field values left are skipped entirely.
+pub fn encode_record_batch(
+    measurement: &str,
+    batch: &RecordBatch,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let schema = Schema::try_from(batch.schema()).context(Schema)?;
+    let column_types = schema
+        .iter()
+        .map(|(influx_type, field)| {
+            influx_type
+                .context(MissingColumnType {
+                    column: field.name(),
+                })
+                .map(Some)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    encode_columns(Measurement::Name(measurement), batch, &column_types, out)
+}
+
+/// Appends the rows of `batch` to `out` as line protocol, encoding each
+/// column as the corresponding type of `column_types` and skipping columns
+/// without a type
+///
+/// See [`encode_record_batch`] for the values that are omitted.
+pub fn encode_columns(
+    measurement: Measurement<'_>,
+    batch: &RecordBatch,
+    column_types: &[Option<InfluxColumnType>],
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let schema = batch.schema();
+
+    let mut tags = vec![];
+    let mut fields = vec![];
+    let mut time = None;
+
+    for ((column, field), column_type) in batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .zip(column_types)
+    {
+        let name = field.name().as_str();
+        let unsupported = || UnsupportedColumn {
+            column: name,
+            data_type: field.data_type().clone(),
+        };
+
+        match column_type {
+            Some(InfluxColumnType::Tag) => {
+                let values = cast(column, &DataType::Utf8).context(Arrow { column: name })?;
+                tags.push((name, values));
+            }
+            Some(InfluxColumnType::Field(_)) => {
+                fields.push((name, FieldValues::new(column).context(unsupported())?))
+            }
+            Some(InfluxColumnType::Timestamp) => {
+                let values = column
+                    .as_any()
+                    .downcast_ref::<TimestampNanosecondArray>()
+                    .context(unsupported())?;
+                time = Some(values);
+            }
+            Some(InfluxColumnType::IOx(_)) => return unsupported().fail(),
+            None => {}
+        }
+    }
+
+    let tags: Vec<_> = tags
+        .iter()
+        .map(|(name, values)| {
+            let values = values
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .expect("cast to string");
+            (*name, values)
+        })
+        .collect();
+
+    let mut builder = LineProtocolBuilder::with_buffer(std::mem::take(out));
+    let mut field_values = Vec::with_capacity(fields.len());
+    for row in 0..batch.num_rows() {
+        let measurement = match measurement {
+            Measurement::Name(name) => name,
+            Measurement::Column(column) if column.is_valid(row) => column.value(row),
+            Measurement::Column(_) => continue,
+        };
+
+        field_values.clear();
+        field_values.extend(
+            fields
+                .iter()
+                .filter_map(|(name, values)| Some((*name, values.value(row)?))),
+        );
+
+        // A line must have at least one field
+        let mut field_values = field_values.drain(..);
+        let (first_name, first_value) = match field_values.next() {
+            Some(field) => field,
+            None => continue,
+        };
+
+        let mut line = builder.measurement(measurement);
+        for (name, values) in &tags {
+            if values.is_valid(row) && !values.value(row).is_empty() {
+                line = line.tag(name, values.value(row));
+            }
+        }
+
+        let line = field_values.fold(
+            line.field(first_name, first_value),
+            |line, (name, value)| line.field(name, value),
+        );
+
+        builder = match time.filter(|time| time.is_valid(row)) {
+            Some(time) => line.timestamp(time.value(row)).close_line(),
+            None => line.close_line(),
+        };
+    }
+
+    *out = builder.build();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lines_to_batches;
+    use arrow_util::display::pretty_format_batches;
+
+    #[test]
+    fn test_round_trip() {
+        let lp = concat!(
+            "cpu,host=a\\ b,region=west usage=0.5,count=2i,total=3u,ok=true,msg=\"say \\\"hi\\\"\" 1\n",
+            "cpu,host=c usage=1 2\n",
+            "cpu,region=east ok=false 3\n",
+            "mem free=10u 4\n",
+        );
+
+        let batches = lines_to_batches(lp, 0).unwrap();
+        let mut out = vec![];
+        for table_name in ["cpu", "mem"] {
+            encode_batch(table_name, &batches[table_name], &mut out).unwrap();
+        }
+
+        // columns are sorted by name
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap(),
+            concat!(
+                "cpu,host=a\\ b,region=west count=2i,msg=\"say \\\"hi\\\"\",ok=true,total=3u,usage=0.5 1\n",
+                "cpu,host=c usage=1 2\n",
+                "cpu,region=east ok=false 3\n",
+                "mem free=10u 4\n",
+            )
+        );
+
+        let round_trip = lines_to_batches(std::str::from_utf8(&out).unwrap(), 0).unwrap();
+        for table_name in ["cpu", "mem"] {
+            let expected = batches[table_name].to_arrow(Selection::All).unwrap();
+            let actual = round_trip[table_name].to_arrow(Selection::All).unwrap();
+            assert_eq!(
+                pretty_format_batches(&[actual]).unwrap(),
+                pretty_format_batches(&[expected]).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_columns() {
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "measurement",
+                std::sync::Arc::new(StringArray::from(vec![Some("cpu"), None, Some("mem")]))
+                    as ArrayRef,
+            ),
+            (
+                "host",
+                std::sync::Arc::new(StringArray::from(vec!["a", "b", "c"])) as ArrayRef,
+            ),
+            (
+                "val",
+                std::sync::Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let measurements = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let column_types = [
+            None,
+            Some(InfluxColumnType::Tag),
+            Some(InfluxColumnType::Field(schema::InfluxFieldType::Integer)),
+        ];
+
+        // rows without a measurement name are skipped
+        let mut out = vec![];
+        encode_columns(
+            Measurement::Column(measurements),
+            &batch,
+            &column_types,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap(),
+            "cpu,host=a val=1i\nmem,host=c val=3i\n"
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let schema = schema::builder::SchemaBuilder::new()
+            .field("val", DataType::Int64)
+            .build()
+            .unwrap();
+        let batch = RecordBatch::try_new(
+            schema.as_arrow(),
+            vec![std::sync::Arc::new(Int64Array::from(vec![1]))],
+        )
+        .unwrap();
+
+        let err = encode_record_batch("cpu", &batch, &mut vec![]).unwrap_err();
+        assert_eq!(err.to_string(), "column 'val' has no IOx column type");
+    }
+}
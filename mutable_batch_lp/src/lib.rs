@@ -17,6 +17,8 @@ use mutable_batch::writer::Writer;
 use mutable_batch::MutableBatch;
 use snafu::{ensure, ResultExt, Snafu};
 
+pub mod encode;
+
 /// Error type for line protocol conversion
 #[derive(Debug, Snafu)]
 #[allow(missing_docs)]
@@ -61,29 +63,93 @@ pub fn lines_to_batches_stats(
     lines: &str,
     default_time: i64,
 ) -> Result<(HashMap<String, MutableBatch>, PayloadStatistics)> {
-    let mut stats = PayloadStatistics::default();
-    let mut batches = HashMap::new();
-    for (line_idx, maybe_line) in parse_lines(lines).enumerate() {
-        let line = maybe_line.context(LineProtocol { line: line_idx + 1 })?;
+    let mut converter = LinesConverter::new(default_time);
+    converter.write_lp(lines)?;
+    converter.finish()
+}
+
+/// Incrementally converts line protocol to a set of [`MutableBatch`] keyed by
+/// measurement name, allowing a large payload to be converted in pieces,
+/// each consisting of complete lines (e.g. as returned by
+/// [`influxdb_line_protocol::LineSplitter`])
+#[derive(Debug)]
+pub struct LinesConverter {
+    /// The timestamp to use for lines without one
+    default_time: i64,
+
+    /// The batches converted since the last call to [`Self::finish`]
+    batches: HashMap<String, MutableBatch>,
+
+    /// Statistics about the lines in `batches`
+    stats: PayloadStatistics,
+
+    /// The number of lines converted in total, used for error messages
+    line_number: usize,
+}
+
+impl LinesConverter {
+    /// Creates a new [`LinesConverter`] using `default_time` for lines
+    /// without a timestamp
+    pub fn new(default_time: i64) -> Self {
+        Self {
+            default_time,
+            batches: Default::default(),
+            stats: Default::default(),
+            line_number: 0,
+        }
+    }
+
+    /// Converts the provided lines of line protocol, adding them to the
+    /// batches returned by the next call to [`Self::finish`]
+    ///
+    /// Line numbers in errors are relative to the first line passed to this
+    /// converter, and if an error is returned some of the lines may have
+    /// already been converted
+    pub fn write_lp(&mut self, lines: &str) -> Result<()> {
+        for maybe_line in parse_lines(lines) {
+            self.line_number += 1;
+            let line = maybe_line.context(LineProtocol {
+                line: self.line_number,
+            })?;
+
+            self.stats.num_lines += 1;
+            self.stats.num_fields += line.field_set.len();
 
-        stats.num_lines += 1;
-        stats.num_fields += line.field_set.len();
+            let measurement = line.series.measurement.as_str();
 
-        let measurement = line.series.measurement.as_str();
+            let (_, batch) = self
+                .batches
+                .raw_entry_mut()
+                .from_key(measurement)
+                .or_insert_with(|| (measurement.to_string(), MutableBatch::new()));
 
-        let (_, batch) = batches
-            .raw_entry_mut()
-            .from_key(measurement)
-            .or_insert_with(|| (measurement.to_string(), MutableBatch::new()));
+            // TODO: Reuse writer
+            let mut writer = Writer::new(batch, 1);
+            write_line(&mut writer, &line, self.default_time).context(Write {
+                line: self.line_number,
+            })?;
+            writer.commit();
+        }
+        Ok(())
+    }
 
-        // TODO: Reuse writer
-        let mut writer = Writer::new(batch, 1);
-        write_line(&mut writer, &line, default_time).context(Write { line: line_idx + 1 })?;
-        writer.commit();
+    /// Returns true if no lines have been converted since the last call to
+    /// [`Self::finish`]
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
     }
-    ensure!(!batches.is_empty(), EmptyPayload);
 
-    Ok((batches, stats))
+    /// Returns the batches and statistics of the lines converted since the
+    /// last call to this method, resetting them, or [`Error::EmptyPayload`]
+    /// if there are none
+    pub fn finish(&mut self) -> Result<(HashMap<String, MutableBatch>, PayloadStatistics)> {
+        ensure!(!self.batches.is_empty(), EmptyPayload);
+
+        Ok((
+            std::mem::take(&mut self.batches),
+            std::mem::take(&mut self.stats),
+        ))
+    }
 }
 
 /// Writes the [`ParsedLine`] to the [`MutableBatch`]
@@ -180,4 +246,34 @@ mod tests {
             &[batch["mem"].to_arrow(Selection::All).unwrap()]
         );
     }
+
+    #[test]
+    fn test_converter() {
+        let mut converter = LinesConverter::new(5);
+        assert!(converter.is_empty());
+        assert!(matches!(converter.finish(), Err(Error::EmptyPayload)));
+
+        converter.write_lp("cpu val=1i 1\nmem val=2i\n").unwrap();
+        converter.write_lp("cpu val=3i 2").unwrap();
+        assert!(!converter.is_empty());
+
+        let (batches, stats) = converter.finish().unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches["cpu"].rows(), 2);
+        assert_eq!(stats.num_lines, 3);
+        assert_eq!(stats.num_fields, 3);
+
+        // finish resets the batches, but not the line numbering
+        assert!(converter.is_empty());
+        converter.write_lp("mem val=4i 3\n").unwrap();
+        let err = converter.write_lp("mem val=5.0 4").unwrap_err();
+        assert!(matches!(err, Error::Write { line: 5, .. }));
+
+        let (batches, _) = converter.finish().unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches["mem"].rows(), 1);
+
+        let err = converter.write_lp("mem val=").unwrap_err();
+        assert!(matches!(err, Error::LineProtocol { line: 6, .. }));
+    }
 }
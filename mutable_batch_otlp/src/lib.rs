@@ -0,0 +1,594 @@
+//! Code to convert OpenTelemetry (OTLP) metrics to [`MutableBatch`]
+
+#![deny(rustdoc::broken_intra_doc_links, rustdoc::bare_urls, rust_2018_idioms)]
+#![warn(
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    clippy::explicit_iter_loop,
+    clippy::future_not_send,
+    clippy::use_self,
+    clippy::clone_on_ref_ptr
+)]
+
+use std::collections::BTreeMap;
+
+use generated_types::opentelemetry::proto::{
+    collector::metrics::v1::ExportMetricsServiceRequest,
+    common::v1::{any_value, KeyValue},
+    metrics::v1::{metric, number_data_point, HistogramDataPoint, NumberDataPoint},
+};
+use hashbrown::{HashMap, HashSet};
+use mutable_batch::writer::Writer;
+use mutable_batch::MutableBatch;
+use snafu::{ensure, ResultExt, Snafu};
+
+/// The name of the field column gauge and sum values are written to
+pub const VALUE_COLUMN: &str = "value";
+
+/// The name of the field column histogram counts are written to
+pub const COUNT_COLUMN: &str = "count";
+
+/// The name of the field column histogram sums are written to
+pub const SUM_COLUMN: &str = "sum";
+
+/// The prefix of the field columns histogram bucket counts are written to,
+/// followed by the upper bound of the bucket
+pub const BUCKET_COLUMN_PREFIX: &str = "bucket_";
+
+/// The name of the timestamp column
+pub const TIME_COLUMN: &str = "time";
+
+/// Error type for OTLP metrics conversion
+#[derive(Debug, Snafu)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[snafu(display("metric with empty name"))]
+    EmptyMetricName,
+
+    #[snafu(display("data point of metric {} has no value", metric))]
+    MissingValue { metric: String },
+
+    #[snafu(display(
+        "histogram data point of metric {} has {} bucket counts for {} bounds",
+        metric,
+        num_counts,
+        num_bounds
+    ))]
+    InvalidBuckets {
+        metric: String,
+        num_counts: usize,
+        num_bounds: usize,
+    },
+
+    #[snafu(display("data point of metric {} has duplicate column \"{}\"", metric, column))]
+    DuplicateColumn { metric: String, column: String },
+
+    #[snafu(display("error writing metric {}: {}", metric, source))]
+    Write {
+        source: mutable_batch::writer::Error,
+        metric: String,
+    },
+
+    #[snafu(display("empty write payload"))]
+    EmptyPayload,
+}
+
+/// Result type for OTLP metrics conversion
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Statistics about an OTLP metrics payload
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PayloadStatistics {
+    /// The number of metrics converted
+    pub num_metrics: usize,
+    /// The number of data points converted
+    pub num_data_points: usize,
+    /// The number of metrics skipped because their data type is not supported
+    pub num_skipped_metrics: usize,
+}
+
+/// Converts the provided [`ExportMetricsServiceRequest`] to a set of
+/// [`MutableBatch`] keyed by metric name
+pub fn export_request_to_batches(
+    request: &ExportMetricsServiceRequest,
+) -> Result<HashMap<String, MutableBatch>> {
+    Ok(export_request_to_batches_stats(request)?.0)
+}
+
+/// Converts the provided [`ExportMetricsServiceRequest`] to a set of
+/// [`MutableBatch`] keyed by metric name, and a set of statistics about the
+/// converted request
+///
+/// Each data point is written as a row to the table named after its metric,
+/// with the resource and data point attributes as tags. Data point attributes
+/// take precedence over resource attributes with the same key, and attributes
+/// that are not scalar values are ignored.
+///
+/// Gauges and sums are written to the `value` field, as a float or integer
+/// depending on the data point. Histograms are written to the `count` and
+/// `sum` fields, and a `bucket_<upper bound>` field per bucket containing the
+/// number of values in that bucket, with the unbounded last bucket written to
+/// `bucket_inf`.
+///
+/// Metrics of other data types are skipped.
+pub fn export_request_to_batches_stats(
+    request: &ExportMetricsServiceRequest,
+) -> Result<(HashMap<String, MutableBatch>, PayloadStatistics)> {
+    let mut stats = PayloadStatistics::default();
+    let mut batches = HashMap::new();
+
+    for resource_metrics in &request.resource_metrics {
+        let resource_attributes = resource_metrics
+            .resource
+            .as_ref()
+            .map(|r| r.attributes.as_slice())
+            .unwrap_or_default();
+
+        let metrics = resource_metrics
+            .instrumentation_library_metrics
+            .iter()
+            .flat_map(|m| &m.metrics);
+
+        for metric in metrics {
+            let data = match &metric.data {
+                Some(data) => data,
+                None => {
+                    stats.num_skipped_metrics += 1;
+                    continue;
+                }
+            };
+            ensure!(!metric.name.is_empty(), EmptyMetricName);
+
+            let name = metric.name.as_str();
+            let (_, batch) = batches
+                .raw_entry_mut()
+                .from_key(name)
+                .or_insert_with(|| (name.to_string(), MutableBatch::new()));
+
+            let num_data_points = match data {
+                metric::Data::Gauge(gauge) => {
+                    write_number_points(batch, name, resource_attributes, &gauge.data_points)?
+                }
+                metric::Data::Sum(sum) => {
+                    write_number_points(batch, name, resource_attributes, &sum.data_points)?
+                }
+                metric::Data::Histogram(histogram) => write_histogram_points(
+                    batch,
+                    name,
+                    resource_attributes,
+                    &histogram.data_points,
+                )?,
+            };
+
+            stats.num_metrics += 1;
+            stats.num_data_points += num_data_points;
+        }
+    }
+
+    // Metrics without data points may leave empty batches behind
+    batches.retain(|_, batch| batch.rows() > 0);
+    ensure!(!batches.is_empty(), EmptyPayload);
+
+    Ok((batches, stats))
+}
+
+fn write_number_points(
+    batch: &mut MutableBatch,
+    metric: &str,
+    resource_attributes: &[KeyValue],
+    points: &[NumberDataPoint],
+) -> Result<usize> {
+    for point in points {
+        let value = point.value.as_ref().ok_or_else(|| Error::MissingValue {
+            metric: metric.to_string(),
+        })?;
+
+        let mut writer = Writer::new(batch, 1);
+        let mut row = RowWriter::new(&mut writer, metric);
+        row.write_tags(resource_attributes, &point.attributes)?;
+        match value {
+            number_data_point::Value::AsDouble(v) => row.write_f64(VALUE_COLUMN, *v)?,
+            number_data_point::Value::AsInt(v) => row.write_i64(VALUE_COLUMN, *v)?,
+        }
+        row.write_time(point.time_unix_nano)?;
+        writer.commit();
+    }
+    Ok(points.len())
+}
+
+fn write_histogram_points(
+    batch: &mut MutableBatch,
+    metric: &str,
+    resource_attributes: &[KeyValue],
+    points: &[HistogramDataPoint],
+) -> Result<usize> {
+    for point in points {
+        ensure!(
+            point.bucket_counts.is_empty()
+                || point.bucket_counts.len() == point.explicit_bounds.len() + 1,
+            InvalidBuckets {
+                metric,
+                num_counts: point.bucket_counts.len(),
+                num_bounds: point.explicit_bounds.len(),
+            }
+        );
+
+        let mut writer = Writer::new(batch, 1);
+        let mut row = RowWriter::new(&mut writer, metric);
+        row.write_tags(resource_attributes, &point.attributes)?;
+        row.write_u64(COUNT_COLUMN, point.count)?;
+        row.write_f64(SUM_COLUMN, point.sum)?;
+
+        let bounds = point
+            .explicit_bounds
+            .iter()
+            .map(|bound| bound.to_string())
+            .chain(std::iter::once("inf".to_string()));
+        for (bound, count) in bounds.zip(&point.bucket_counts) {
+            row.write_u64(&format!("{}{}", BUCKET_COLUMN_PREFIX, bound), *count)?;
+        }
+
+        row.write_time(point.time_unix_nano)?;
+        writer.commit();
+    }
+    Ok(points.len())
+}
+
+/// Writes the columns of a single row, ensuring no column is written twice
+struct RowWriter<'a, 'b> {
+    writer: &'a mut Writer<'b>,
+    metric: &'a str,
+    columns: HashSet<String>,
+}
+
+impl<'a, 'b> RowWriter<'a, 'b> {
+    fn new(writer: &'a mut Writer<'b>, metric: &'a str) -> Self {
+        Self {
+            writer,
+            metric,
+            columns: HashSet::new(),
+        }
+    }
+
+    fn check_column(&mut self, column: &str) -> Result<()> {
+        ensure!(
+            self.columns.insert(column.to_string()),
+            DuplicateColumn {
+                metric: self.metric,
+                column,
+            }
+        );
+        Ok(())
+    }
+
+    fn write_tags(&mut self, resource: &[KeyValue], attributes: &[KeyValue]) -> Result<()> {
+        // Later values for the same key replace earlier ones
+        let tags: BTreeMap<_, _> = resource
+            .iter()
+            .chain(attributes)
+            .filter_map(|kv| Some((kv.key.as_str(), attribute_value(kv)?)))
+            .collect();
+
+        for (key, value) in tags {
+            self.check_column(key)?;
+            self.writer
+                .write_tag(key, None, std::iter::once(value.as_str()))
+                .context(Write {
+                    metric: self.metric,
+                })?;
+        }
+        Ok(())
+    }
+
+    fn write_f64(&mut self, column: &str, value: f64) -> Result<()> {
+        self.check_column(column)?;
+        self.writer
+            .write_f64(column, None, std::iter::once(value))
+            .context(Write {
+                metric: self.metric,
+            })
+    }
+
+    fn write_i64(&mut self, column: &str, value: i64) -> Result<()> {
+        self.check_column(column)?;
+        self.writer
+            .write_i64(column, None, std::iter::once(value))
+            .context(Write {
+                metric: self.metric,
+            })
+    }
+
+    fn write_u64(&mut self, column: &str, value: u64) -> Result<()> {
+        self.check_column(column)?;
+        self.writer
+            .write_u64(column, None, std::iter::once(value))
+            .context(Write {
+                metric: self.metric,
+            })
+    }
+
+    fn write_time(&mut self, time_unix_nano: u64) -> Result<()> {
+        self.check_column(TIME_COLUMN)?;
+        let time = i64::try_from(time_unix_nano).unwrap_or(i64::MAX);
+        self.writer
+            .write_time(TIME_COLUMN, std::iter::once(time))
+            .context(Write {
+                metric: self.metric,
+            })
+    }
+}
+
+/// Returns the string representation of a scalar attribute value
+fn attribute_value(kv: &KeyValue) -> Option<String> {
+    match kv.value.as_ref()?.value.as_ref()? {
+        any_value::Value::StringValue(v) => Some(v.clone()),
+        any_value::Value::BoolValue(v) => Some(v.to_string()),
+        any_value::Value::IntValue(v) => Some(v.to_string()),
+        any_value::Value::DoubleValue(v) => Some(v.to_string()),
+        any_value::Value::ArrayValue(_)
+        | any_value::Value::KvlistValue(_)
+        | any_value::Value::BytesValue(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_util::assert_batches_eq;
+    use generated_types::opentelemetry::proto::{
+        common::v1::AnyValue,
+        metrics::v1::{
+            Gauge, Histogram, InstrumentationLibraryMetrics, Metric, ResourceMetrics, Sum,
+        },
+        resource::v1::Resource,
+    };
+    use schema::selection::Selection;
+
+    fn kv(key: &str, value: any_value::Value) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: Some(AnyValue { value: Some(value) }),
+        }
+    }
+
+    fn string_kv(key: &str, value: &str) -> KeyValue {
+        kv(key, any_value::Value::StringValue(value.to_string()))
+    }
+
+    fn number_point(
+        attributes: Vec<KeyValue>,
+        time: u64,
+        value: number_data_point::Value,
+    ) -> NumberDataPoint {
+        NumberDataPoint {
+            attributes,
+            start_time_unix_nano: 0,
+            time_unix_nano: time,
+            value: Some(value),
+            flags: 0,
+        }
+    }
+
+    fn metric(name: &str, data: metric::Data) -> Metric {
+        Metric {
+            name: name.to_string(),
+            description: String::new(),
+            unit: String::new(),
+            data: Some(data),
+        }
+    }
+
+    fn request(resource: Vec<KeyValue>, metrics: Vec<Metric>) -> ExportMetricsServiceRequest {
+        ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: Some(Resource {
+                    attributes: resource,
+                    dropped_attributes_count: 0,
+                }),
+                instrumentation_library_metrics: vec![InstrumentationLibraryMetrics {
+                    instrumentation_library: None,
+                    metrics,
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_gauge_and_sum() {
+        let request = request(
+            vec![
+                string_kv("service.name", "api"),
+                string_kv("host", "resource"),
+                kv(
+                    "ignored",
+                    any_value::Value::BytesValue(b"not a tag".to_vec()),
+                ),
+            ],
+            vec![
+                metric(
+                    "cpu",
+                    metric::Data::Gauge(Gauge {
+                        data_points: vec![
+                            number_point(
+                                vec![
+                                    string_kv("host", "a"),
+                                    kv("core", any_value::Value::IntValue(1)),
+                                ],
+                                1,
+                                number_data_point::Value::AsDouble(0.5),
+                            ),
+                            number_point(
+                                vec![kv("up", any_value::Value::BoolValue(true))],
+                                2,
+                                number_data_point::Value::AsDouble(0.25),
+                            ),
+                        ],
+                    }),
+                ),
+                metric(
+                    "requests",
+                    metric::Data::Sum(Sum {
+                        data_points: vec![number_point(
+                            vec![],
+                            3,
+                            number_data_point::Value::AsInt(42),
+                        )],
+                        aggregation_temporality: 2,
+                        is_monotonic: true,
+                    }),
+                ),
+                Metric {
+                    data: None,
+                    ..metric("unsupported", metric::Data::Gauge(Gauge::default()))
+                },
+            ],
+        );
+
+        let (batches, stats) = export_request_to_batches_stats(&request).unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(stats.num_metrics, 2);
+        assert_eq!(stats.num_data_points, 3);
+        assert_eq!(stats.num_skipped_metrics, 1);
+
+        assert_batches_eq!(
+            &[
+                "+------+----------+--------------+--------------------------------+------+-------+",
+                "| core | host     | service.name | time                           | up   | value |",
+                "+------+----------+--------------+--------------------------------+------+-------+",
+                "| 1    | a        | api          | 1970-01-01T00:00:00.000000001Z |      | 0.5   |",
+                "|      | resource | api          | 1970-01-01T00:00:00.000000002Z | true | 0.25  |",
+                "+------+----------+--------------+--------------------------------+------+-------+",
+            ],
+            &[batches["cpu"].to_arrow(Selection::All).unwrap()]
+        );
+
+        assert_batches_eq!(
+            &[
+                "+----------+--------------+--------------------------------+-------+",
+                "| host     | service.name | time                           | value |",
+                "+----------+--------------+--------------------------------+-------+",
+                "| resource | api          | 1970-01-01T00:00:00.000000003Z | 42    |",
+                "+----------+--------------+--------------------------------+-------+",
+            ],
+            &[batches["requests"].to_arrow(Selection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let request = request(
+            vec![],
+            vec![metric(
+                "latency",
+                metric::Data::Histogram(Histogram {
+                    data_points: vec![HistogramDataPoint {
+                        attributes: vec![string_kv("path", "/")],
+                        start_time_unix_nano: 0,
+                        time_unix_nano: 10,
+                        count: 6,
+                        sum: 3.5,
+                        bucket_counts: vec![1, 2, 3],
+                        explicit_bounds: vec![0.25, 1.],
+                        flags: 0,
+                    }],
+                    aggregation_temporality: 1,
+                }),
+            )],
+        );
+
+        let batches = export_request_to_batches(&request).unwrap();
+        assert_batches_eq!(
+            &[
+                "+-------------+----------+------------+-------+------+-----+--------------------------------+",
+                "| bucket_0.25 | bucket_1 | bucket_inf | count | path | sum | time                           |",
+                "+-------------+----------+------------+-------+------+-----+--------------------------------+",
+                "| 1           | 2        | 3          | 6     | /    | 3.5 | 1970-01-01T00:00:00.000000010Z |",
+                "+-------------+----------+------------+-------+------+-----+--------------------------------+",
+            ],
+            &[batches["latency"].to_arrow(Selection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let gauge = |attributes, value| {
+            metric::Data::Gauge(Gauge {
+                data_points: vec![NumberDataPoint {
+                    value,
+                    ..number_point(attributes, 1, number_data_point::Value::AsInt(1))
+                }],
+            })
+        };
+
+        let err = export_request_to_batches(&request(
+            vec![],
+            vec![metric(
+                "cpu",
+                gauge(
+                    vec![string_kv("value", "a")],
+                    Some(number_data_point::Value::AsInt(1)),
+                ),
+            )],
+        ))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"data point of metric cpu has duplicate column "value""#
+        );
+
+        let err =
+            export_request_to_batches(&request(vec![], vec![metric("cpu", gauge(vec![], None))]))
+                .unwrap_err();
+        assert!(matches!(err, Error::MissingValue { .. }));
+
+        let err = export_request_to_batches(&request(
+            vec![],
+            vec![metric(
+                "",
+                gauge(vec![], Some(number_data_point::Value::AsInt(1))),
+            )],
+        ))
+        .unwrap_err();
+        assert!(matches!(err, Error::EmptyMetricName));
+
+        let err = export_request_to_batches(&request(
+            vec![],
+            vec![metric(
+                "latency",
+                metric::Data::Histogram(Histogram {
+                    data_points: vec![HistogramDataPoint {
+                        bucket_counts: vec![1],
+                        explicit_bounds: vec![1.],
+                        ..Default::default()
+                    }],
+                    aggregation_temporality: 1,
+                }),
+            )],
+        ))
+        .unwrap_err();
+        assert!(matches!(err, Error::InvalidBuckets { .. }));
+
+        // A type change within a table is rejected
+        let err = export_request_to_batches(&request(
+            vec![],
+            vec![
+                metric(
+                    "cpu",
+                    gauge(vec![], Some(number_data_point::Value::AsInt(1))),
+                ),
+                metric(
+                    "cpu",
+                    gauge(vec![], Some(number_data_point::Value::AsDouble(1.))),
+                ),
+            ],
+        ))
+        .unwrap_err();
+        assert!(matches!(err, Error::Write { .. }));
+
+        let err = export_request_to_batches(&ExportMetricsServiceRequest::default()).unwrap_err();
+        assert!(matches!(err, Error::EmptyPayload));
+    }
+}
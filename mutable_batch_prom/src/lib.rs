@@ -0,0 +1,270 @@
+//! Code to convert Prometheus remote write requests to [`MutableBatch`]
+
+#![deny(rustdoc::broken_intra_doc_links, rustdoc::bare_urls, rust_2018_idioms)]
+#![warn(
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    clippy::explicit_iter_loop,
+    clippy::future_not_send,
+    clippy::use_self,
+    clippy::clone_on_ref_ptr
+)]
+
+use generated_types::prometheus::{TimeSeries, WriteRequest};
+use hashbrown::{HashMap, HashSet};
+use mutable_batch::writer::Writer;
+use mutable_batch::MutableBatch;
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+
+/// The label containing the metric name, used as the table name
+pub const METRIC_NAME_LABEL: &str = "__name__";
+
+/// The name of the field column the sample values are written to
+pub const VALUE_COLUMN: &str = "value";
+
+/// The name of the timestamp column
+pub const TIME_COLUMN: &str = "time";
+
+/// Error type for Prometheus remote write conversion
+#[derive(Debug, Snafu)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[snafu(display("series {} has no {} label", series, METRIC_NAME_LABEL))]
+    MissingMetricName { series: usize },
+
+    #[snafu(display("series {} has duplicate label \"{}\"", series, label))]
+    DuplicateLabel { series: usize, label: String },
+
+    #[snafu(display("series {} uses reserved label name \"{}\"", series, label))]
+    ReservedLabel { series: usize, label: String },
+
+    #[snafu(display("error writing series {}: {}", series, source))]
+    Write {
+        source: mutable_batch::writer::Error,
+        series: usize,
+    },
+
+    #[snafu(display("empty write payload"))]
+    EmptyPayload,
+}
+
+/// Result type for Prometheus remote write conversion
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Statistics about a Prometheus remote write payload
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PayloadStatistics {
+    /// The number of time series
+    pub num_series: usize,
+    /// The number of samples
+    pub num_samples: usize,
+}
+
+/// Converts the provided [`WriteRequest`] to a set of [`MutableBatch`] keyed by
+/// metric name
+pub fn write_request_to_batches(request: &WriteRequest) -> Result<HashMap<String, MutableBatch>> {
+    Ok(write_request_to_batches_stats(request)?.0)
+}
+
+/// Converts the provided [`WriteRequest`] to a set of [`MutableBatch`] keyed by
+/// metric name, and a set of statistics about the converted request
+///
+/// Each series is written to the table named by its `__name__` label, with the
+/// remaining labels as tags, and each sample as a row with a `value` field.
+/// Series without samples are ignored.
+pub fn write_request_to_batches_stats(
+    request: &WriteRequest,
+) -> Result<(HashMap<String, MutableBatch>, PayloadStatistics)> {
+    let mut stats = PayloadStatistics::default();
+    let mut batches = HashMap::new();
+    for (series_idx, series) in request.timeseries.iter().enumerate() {
+        if series.samples.is_empty() {
+            continue;
+        }
+
+        let series_num = series_idx + 1;
+        let measurement = series
+            .labels
+            .iter()
+            .find(|label| label.name == METRIC_NAME_LABEL)
+            .map(|label| label.value.as_str())
+            .context(MissingMetricName { series: series_num })?;
+
+        stats.num_series += 1;
+        stats.num_samples += series.samples.len();
+
+        let (_, batch) = batches
+            .raw_entry_mut()
+            .from_key(measurement)
+            .or_insert_with(|| (measurement.to_string(), MutableBatch::new()));
+
+        let mut writer = Writer::new(batch, series.samples.len());
+        write_series(&mut writer, series, series_num)?;
+        writer.commit();
+    }
+    ensure!(!batches.is_empty(), EmptyPayload);
+
+    Ok((batches, stats))
+}
+
+/// Writes the samples of a [`TimeSeries`] to the [`MutableBatch`]
+///
+/// The [`Writer`] must have been created for `series.samples.len()` rows
+fn write_series(writer: &mut Writer<'_>, series: &TimeSeries, series_num: usize) -> Result<()> {
+    let num_samples = series.samples.len();
+    let mut seen = HashSet::with_capacity(series.labels.len());
+
+    for label in &series.labels {
+        let name = label.name.as_str();
+        ensure!(
+            seen.insert(name),
+            DuplicateLabel {
+                series: series_num,
+                label: name
+            }
+        );
+
+        if name == METRIC_NAME_LABEL {
+            continue;
+        }
+        ensure!(
+            name != VALUE_COLUMN && name != TIME_COLUMN,
+            ReservedLabel {
+                series: series_num,
+                label: name
+            }
+        );
+
+        writer
+            .write_tag(
+                name,
+                None,
+                std::iter::repeat(label.value.as_str()).take(num_samples),
+            )
+            .context(Write { series: series_num })?;
+    }
+
+    writer
+        .write_f64(
+            VALUE_COLUMN,
+            None,
+            series.samples.iter().map(|sample| sample.value),
+        )
+        .context(Write { series: series_num })?;
+
+    // Prometheus timestamps are in milliseconds
+    writer
+        .write_time(
+            TIME_COLUMN,
+            series
+                .samples
+                .iter()
+                .map(|sample| sample.timestamp.saturating_mul(1_000_000)),
+        )
+        .context(Write { series: series_num })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_util::assert_batches_eq;
+    use generated_types::prometheus::{Label, Sample};
+    use schema::selection::Selection;
+
+    fn series(labels: &[(&str, &str)], samples: &[(f64, i64)]) -> TimeSeries {
+        TimeSeries {
+            labels: labels
+                .iter()
+                .map(|(name, value)| Label {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            samples: samples
+                .iter()
+                .map(|(value, timestamp)| Sample {
+                    value: *value,
+                    timestamp: *timestamp,
+                })
+                .collect(),
+            exemplars: vec![],
+        }
+    }
+
+    fn request(timeseries: Vec<TimeSeries>) -> WriteRequest {
+        WriteRequest {
+            timeseries,
+            metadata: vec![],
+        }
+    }
+
+    #[test]
+    fn test_basic() {
+        let request = request(vec![
+            series(
+                &[("__name__", "cpu"), ("host", "a"), ("region", "west")],
+                &[(1.5, 1), (2.5, 2)],
+            ),
+            series(&[("__name__", "mem"), ("host", "b")], &[(10., 3)]),
+            series(&[("host", "c"), ("__name__", "cpu")], &[(3.5, 4)]),
+            series(&[("__name__", "disk")], &[]),
+        ]);
+
+        let (batches, stats) = write_request_to_batches_stats(&request).unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(stats.num_series, 3);
+        assert_eq!(stats.num_samples, 4);
+
+        assert_batches_eq!(
+            &[
+                "+------+--------+--------------------------+-------+",
+                "| host | region | time                     | value |",
+                "+------+--------+--------------------------+-------+",
+                "| a    | west   | 1970-01-01T00:00:00.001Z | 1.5   |",
+                "| a    | west   | 1970-01-01T00:00:00.002Z | 2.5   |",
+                "| c    |        | 1970-01-01T00:00:00.004Z | 3.5   |",
+                "+------+--------+--------------------------+-------+",
+            ],
+            &[batches["cpu"].to_arrow(Selection::All).unwrap()]
+        );
+
+        assert_batches_eq!(
+            &[
+                "+------+--------------------------+-------+",
+                "| host | time                     | value |",
+                "+------+--------------------------+-------+",
+                "| b    | 1970-01-01T00:00:00.003Z | 10    |",
+                "+------+--------------------------+-------+",
+            ],
+            &[batches["mem"].to_arrow(Selection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = write_request_to_batches(&request(vec![series(&[("host", "a")], &[(1., 1)])]))
+            .unwrap_err();
+        assert!(matches!(err, Error::MissingMetricName { series: 1 }));
+
+        let err = write_request_to_batches(&request(vec![series(
+            &[("__name__", "cpu"), ("host", "a"), ("host", "b")],
+            &[(1., 1)],
+        )]))
+        .unwrap_err();
+        assert_eq!(err.to_string(), r#"series 1 has duplicate label "host""#);
+
+        let err = write_request_to_batches(&request(vec![series(
+            &[("__name__", "cpu"), ("value", "a")],
+            &[(1., 1)],
+        )]))
+        .unwrap_err();
+        assert!(matches!(err, Error::ReservedLabel { .. }));
+
+        let err = write_request_to_batches(&request(vec![series(&[("__name__", "cpu")], &[])]))
+            .unwrap_err();
+        assert!(matches!(err, Error::EmptyPayload));
+    }
+}
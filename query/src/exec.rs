@@ -1,19 +1,26 @@
 //! This module handles the manipulation / execution of storage
 //! plans. This is currently implemented using DataFusion, and this
 //! interface abstracts away many of the details
+pub mod admission;
 pub(crate) mod context;
+pub mod deadline;
 pub mod field;
 pub mod fieldlist;
+pub mod gapfill;
+pub mod memory;
 mod non_null_checker;
 mod query_tracing;
 mod schema_pivot;
+pub mod series_transform;
+pub mod seriescardinality;
 pub mod seriesset;
+pub mod sort;
 pub(crate) mod split;
 pub mod stringset;
 mod task;
 pub use context::{DEFAULT_CATALOG, DEFAULT_SCHEMA};
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use datafusion::{
     self,
@@ -23,7 +30,13 @@ use datafusion::{
 pub use context::{IOxExecutionConfig, IOxExecutionContext};
 use schema_pivot::SchemaPivotNode;
 
-use self::{non_null_checker::NonNullCheckerNode, split::StreamSplitNode, task::DedicatedExecutor};
+use self::{
+    admission::{AdmissionConfig, AdmissionController},
+    memory::{MemoryConfig, MemoryPool},
+    non_null_checker::NonNullCheckerNode,
+    split::StreamSplitNode,
+    task::DedicatedExecutor,
+};
 
 /// Configuration for an Executor
 #[derive(Debug, Clone)]
@@ -33,6 +46,15 @@ pub struct ExecutorConfig {
 
     /// Target parallelism for query execution
     pub target_query_partitions: usize,
+
+    /// The default time after which user queries are cancelled
+    pub query_timeout: Option<Duration>,
+
+    /// The memory available to user queries
+    pub memory: MemoryConfig,
+
+    /// How many user queries may run at once
+    pub admission: AdmissionConfig,
 }
 
 /// Handles executing DataFusion plans, and marshalling the results into rust
@@ -49,6 +71,12 @@ pub struct Executor {
     /// compact
     reorg_exec: DedicatedExecutor,
 
+    /// The memory shared by all user queries, if limited
+    memory_pool: Option<Arc<MemoryPool>>,
+
+    /// Admits user queries across all databases, if limited
+    admission: Option<Arc<AdmissionController>>,
+
     /// The default configuration options with which to create contexts
     config: ExecutorConfig,
 }
@@ -68,16 +96,36 @@ impl Executor {
         Self::new_with_config(ExecutorConfig {
             num_threads,
             target_query_partitions: num_threads,
+            query_timeout: None,
+            memory: MemoryConfig::default(),
+            admission: AdmissionConfig::default(),
         })
     }
 
     pub fn new_with_config(config: ExecutorConfig) -> Self {
         let query_exec = DedicatedExecutor::new("IOx Query Executor Thread", config.num_threads);
         let reorg_exec = DedicatedExecutor::new("IOx Reorg Executor Thread", config.num_threads);
+        let memory_pool = config
+            .memory
+            .is_limited()
+            .then(|| Arc::new(MemoryPool::new(config.memory.clone())));
+
+        let admission = config
+            .admission
+            .max_concurrent_queries
+            .map(|max_concurrent| {
+                Arc::new(AdmissionController::new(
+                    "server",
+                    Some(max_concurrent),
+                    config.admission.max_queued_queries,
+                ))
+            });
 
         Self {
             query_exec,
             reorg_exec,
+            memory_pool,
+            admission,
             config,
         }
     }
@@ -87,7 +135,31 @@ impl Executor {
     /// Note that this context (and all its clones) will be shut down once `Executor` is dropped.
     pub fn new_execution_config(&self, executor_type: ExecutorType) -> IOxExecutionConfig {
         let exec = self.executor(executor_type).clone();
-        IOxExecutionConfig::new(exec).with_target_partitions(self.config.target_query_partitions)
+        let config = IOxExecutionConfig::new(exec)
+            .with_target_partitions(self.config.target_query_partitions);
+
+        match executor_type {
+            ExecutorType::Query => config
+                .with_timeout(self.config.query_timeout)
+                .with_memory_pool(self.memory_pool.clone())
+                .with_admission(self.admission.clone()),
+            ExecutorType::Reorg => config,
+        }
+    }
+
+    /// Returns a new controller admitting the user queries of a single
+    /// database, if limited
+    pub fn new_database_admission(&self) -> Option<Arc<AdmissionController>> {
+        let config = &self.config.admission;
+        config
+            .max_concurrent_queries_per_database
+            .map(|max_concurrent| {
+                Arc::new(AdmissionController::new(
+                    "database",
+                    Some(max_concurrent),
+                    config.max_queued_queries,
+                ))
+            })
     }
 
     /// Create a new execution context, suitable for executing a new query or system task
@@ -404,6 +476,40 @@ mod tests {
         assert_eq!(results, to_set(&["f1", "f2"]));
     }
 
+    #[tokio::test]
+    async fn request_timeout_cannot_extend_server_timeout() {
+        let exec = Executor::new_with_config(ExecutorConfig {
+            num_threads: 1,
+            target_query_partitions: 1,
+            query_timeout: Some(Duration::from_secs(60)),
+            memory: MemoryConfig::default(),
+            admission: AdmissionConfig::default(),
+        });
+        let timeout = |request: Option<Duration>| {
+            exec.new_context(ExecutorType::Query)
+                .with_request_timeout(request)
+                .deadline()
+                .map(|deadline| deadline.timeout())
+        };
+
+        assert_eq!(timeout(None), Some(Duration::from_secs(60)));
+        assert_eq!(
+            timeout(Some(Duration::from_secs(1))),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            timeout(Some(Duration::from_secs(3600))),
+            Some(Duration::from_secs(60))
+        );
+
+        // without a server-wide timeout the request's applies
+        let exec = Executor::new(1);
+        let ctx = exec
+            .new_context(ExecutorType::Query)
+            .with_request_timeout(Some(Duration::from_secs(3600)));
+        assert_eq!(ctx.deadline().unwrap().timeout(), Duration::from_secs(3600));
+    }
+
     /// return a set for testing
     fn to_set(strs: &[&str]) -> StringSetRef {
         StringSetRef::new(strs.iter().map(|s| s.to_string()).collect::<StringSet>())
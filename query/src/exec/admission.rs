@@ -0,0 +1,687 @@
+//! This module limits how many queries run by an
+//! [`IOxExecutionContext`](super::IOxExecutionContext) run concurrently
+//!
+//! Queries are admitted by an [`AdmissionController`] shared by all
+//! databases of an [`Executor`](super::Executor), and by another one per
+//! database. Queries that can not run yet wait in a bounded queue per
+//! [`QueryPriority`]. Once a query completes, the next one is chosen from
+//! these queues in proportion to the weights of their priorities, so that
+//! a burst of queries of one class can not starve the others.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use arrow::{datatypes::SchemaRef, error::Result as ArrowResult, record_batch::RecordBatch};
+use datafusion::physical_plan::{RecordBatchStream, SendableRecordBatchStream};
+use futures::{Stream, StreamExt};
+use metric::{Attributes, DurationHistogram, U64Counter, U64Gauge};
+use parking_lot::Mutex;
+use tokio::sync::{oneshot, OnceCell};
+
+use super::deadline::caused_by;
+
+/// The class of a query, deciding how it is queued for admission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryPriority {
+    /// Queries a user waits for, such as dashboards
+    Interactive,
+    /// Expensive queries nobody waits for, such as exports and continuous
+    /// queries
+    Batch,
+    /// Queries of internal system tables
+    System,
+}
+
+impl QueryPriority {
+    const ALL: [Self; 3] = [Self::Interactive, Self::Batch, Self::System];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Interactive => "interactive",
+            Self::Batch => "batch",
+            Self::System => "system",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Self::Interactive => 0,
+            Self::Batch => 1,
+            Self::System => 2,
+        }
+    }
+
+    /// The number of queries admitted from the queue of this priority for
+    /// every 8 queries admitted from the queue of system queries
+    fn weight(&self) -> u64 {
+        match self {
+            Self::Interactive => 4,
+            Self::Batch => 1,
+            Self::System => 8,
+        }
+    }
+}
+
+impl Default for QueryPriority {
+    fn default() -> Self {
+        Self::Interactive
+    }
+}
+
+impl fmt::Display for QueryPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The error returned when parsing an unknown [`QueryPriority`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPriority(String);
+
+impl fmt::Display for UnknownPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown query priority '{}', expected interactive, batch or system",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownPriority {}
+
+impl FromStr for QueryPriority {
+    type Err = UnknownPriority;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|priority| priority.as_str() == s)
+            .ok_or_else(|| UnknownPriority(s.to_string()))
+    }
+}
+
+/// Configures the admission of queries
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AdmissionConfig {
+    /// The maximum number of queries running at once
+    pub max_concurrent_queries: Option<usize>,
+
+    /// The maximum number of queries of a single database running at once
+    pub max_concurrent_queries_per_database: Option<usize>,
+
+    /// The maximum number of queries waiting for either limit
+    pub max_queued_queries: Option<usize>,
+}
+
+/// The error a query fails with when too many queries are already waiting
+/// for admission
+#[derive(Debug, Clone, Copy)]
+pub struct AdmissionRejected {
+    scope: &'static str,
+    max_queued: usize,
+}
+
+impl fmt::Display for AdmissionRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Query rejected as {} queries are already waiting to run on the {}",
+            self.max_queued, self.scope
+        )
+    }
+}
+
+impl std::error::Error for AdmissionRejected {}
+
+/// Returns true if `err`, or any error it was caused by, is an
+/// [`AdmissionRejected`]
+pub fn is_admission_rejected(err: &(dyn std::error::Error + 'static)) -> bool {
+    caused_by::<AdmissionRejected>(err)
+}
+
+/// Scale of the pass of a queue, see [`State::next_waiter`]
+const STRIDE: u64 = 840;
+
+#[derive(Debug, Default)]
+struct State {
+    running: usize,
+
+    /// Waiting queries, by priority
+    queues: [VecDeque<oneshot::Sender<AdmissionPermit>>; 3],
+
+    /// The pass of each queue, which grows inversely to its weight with
+    /// every query admitted from it
+    passes: [u64; 3],
+
+    /// The pass of the queue last admitted from
+    current_pass: u64,
+}
+
+impl State {
+    fn num_queued(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+
+    /// Removes the next query to admit, taken from the non-empty queue with
+    /// the lowest pass (stride scheduling)
+    fn next_waiter(&mut self) -> Option<oneshot::Sender<AdmissionPermit>> {
+        let priority = QueryPriority::ALL
+            .into_iter()
+            .filter(|priority| !self.queues[priority.index()].is_empty())
+            .min_by_key(|priority| self.passes[priority.index()])?;
+
+        let idx = priority.index();
+        self.current_pass = self.passes[idx];
+        self.passes[idx] += STRIDE / priority.weight();
+        self.queues[idx].pop_front()
+    }
+}
+
+/// Limits the number of queries running at once, queueing the queries
+/// exceeding it
+#[derive(Debug)]
+pub struct AdmissionController {
+    /// What the limit applies to, e.g. "server"
+    scope: &'static str,
+    max_concurrent: Option<usize>,
+    max_queued: Option<usize>,
+    state: Mutex<State>,
+}
+
+impl AdmissionController {
+    pub fn new(
+        scope: &'static str,
+        max_concurrent: Option<usize>,
+        max_queued: Option<usize>,
+    ) -> Self {
+        Self {
+            scope,
+            max_concurrent,
+            max_queued,
+            state: Default::default(),
+        }
+    }
+
+    /// The number of queries currently admitted
+    pub fn running(&self) -> usize {
+        self.state.lock().running
+    }
+
+    /// The number of queries currently waiting for admission
+    pub fn queued(&self) -> usize {
+        self.state.lock().num_queued()
+    }
+
+    /// Waits until a query of `priority` may run, returning a permit to
+    /// hold while it does
+    pub async fn admit(
+        self: &Arc<Self>,
+        priority: QueryPriority,
+    ) -> Result<AdmissionPermit, AdmissionRejected> {
+        let rx = {
+            let mut state = self.state.lock();
+
+            // Forget about queries that stopped waiting
+            for queue in &mut state.queues {
+                queue.retain(|tx| !tx.is_closed());
+            }
+
+            let has_capacity = match self.max_concurrent {
+                Some(max_concurrent) => state.running < max_concurrent,
+                None => true,
+            };
+            if has_capacity && state.num_queued() == 0 {
+                state.running += 1;
+                return Ok(AdmissionPermit {
+                    controller: Some(Arc::clone(self)),
+                });
+            }
+
+            if let Some(max_queued) = self.max_queued {
+                if state.num_queued() >= max_queued {
+                    return Err(AdmissionRejected {
+                        scope: self.scope,
+                        max_queued,
+                    });
+                }
+            }
+
+            // A queue that was empty continues from the current pass, so it
+            // does not catch up on the admissions it missed while empty
+            let idx = priority.index();
+            if state.queues[idx].is_empty() {
+                state.passes[idx] = state.passes[idx].max(state.current_pass);
+            }
+
+            let (tx, rx) = oneshot::channel();
+            state.queues[idx].push_back(tx);
+            rx
+        };
+
+        // The sender is only dropped after sending a permit
+        Ok(rx
+            .await
+            .expect("admission controller dropped waiting query"))
+    }
+
+    /// Hands the permit of a completed query to the next waiting query
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock();
+        while let Some(tx) = state.next_waiter() {
+            let permit = AdmissionPermit {
+                controller: Some(Arc::clone(self)),
+            };
+            match tx.send(permit) {
+                Ok(()) => return,
+                // The query stopped waiting, don't release the permit again
+                Err(mut permit) => permit.controller = None,
+            }
+        }
+        state.running -= 1;
+    }
+}
+
+/// Permits a query to run, until dropped
+#[derive(Debug)]
+pub struct AdmissionPermit {
+    controller: Option<Arc<AdmissionController>>,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        if let Some(controller) = self.controller.take() {
+            controller.release()
+        }
+    }
+}
+
+/// Metrics of the admission of the queries of a database
+#[derive(Debug, Clone)]
+pub struct AdmissionMetrics {
+    by_priority: Arc<[PriorityMetrics; 3]>,
+}
+
+#[derive(Debug)]
+struct PriorityMetrics {
+    num_queued: AtomicU64,
+    queued: U64Gauge,
+    wait: DurationHistogram,
+    rejected: U64Counter,
+}
+
+impl AdmissionMetrics {
+    pub fn new(registry: &metric::Registry, attributes: Attributes) -> Self {
+        let queued = registry.register_metric::<U64Gauge>(
+            "query_admission_queued",
+            "The number of queries waiting for admission",
+        );
+        let wait = registry.register_metric::<DurationHistogram>(
+            "query_admission_wait",
+            "The time queries waited for admission",
+        );
+        let rejected = registry.register_metric::<U64Counter>(
+            "query_admission_rejected",
+            "The number of queries rejected as too many queries were waiting",
+        );
+
+        let by_priority = QueryPriority::ALL.map(|priority| {
+            let mut attributes = attributes.clone();
+            attributes.insert("priority", priority.as_str());
+
+            PriorityMetrics {
+                num_queued: AtomicU64::new(0),
+                queued: queued.recorder(attributes.clone()),
+                wait: wait.recorder(attributes.clone()),
+                rejected: rejected.recorder(attributes),
+            }
+        });
+
+        Self {
+            by_priority: Arc::new(by_priority),
+        }
+    }
+
+    fn priority(&self, priority: QueryPriority) -> &PriorityMetrics {
+        &self.by_priority[priority.index()]
+    }
+}
+
+/// Counts a query as queued until dropped
+struct QueuedGuard<'a>(Option<&'a PriorityMetrics>);
+
+impl<'a> QueuedGuard<'a> {
+    fn new(metrics: Option<&'a PriorityMetrics>) -> Self {
+        if let Some(metrics) = metrics {
+            let queued = metrics.num_queued.fetch_add(1, Ordering::Relaxed) + 1;
+            metrics.queued.set(queued);
+        }
+        Self(metrics)
+    }
+}
+
+impl<'a> Drop for QueuedGuard<'a> {
+    fn drop(&mut self) {
+        if let Some(metrics) = self.0 {
+            let queued = metrics.num_queued.fetch_sub(1, Ordering::Relaxed) - 1;
+            metrics.queued.set(queued);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AdmissionState {
+    Pending,
+    Queued(Instant),
+    Admitted(Duration),
+    Rejected,
+}
+
+/// The progress of the admission of a query, as reported in the query log
+#[derive(Debug)]
+pub struct AdmissionStats {
+    priority: Mutex<QueryPriority>,
+    state: Mutex<AdmissionState>,
+}
+
+impl AdmissionStats {
+    fn new(priority: QueryPriority) -> Self {
+        Self {
+            priority: Mutex::new(priority),
+            state: Mutex::new(AdmissionState::Pending),
+        }
+    }
+
+    pub fn priority(&self) -> QueryPriority {
+        *self.priority.lock()
+    }
+
+    /// The time the query waited, or has been waiting so far, for
+    /// admission
+    pub fn queue_wait(&self) -> Option<Duration> {
+        match *self.state.lock() {
+            AdmissionState::Queued(since) => Some(since.elapsed()),
+            AdmissionState::Admitted(wait) => Some(wait),
+            AdmissionState::Pending | AdmissionState::Rejected => None,
+        }
+    }
+
+    /// Returns true if the query is still waiting for admission
+    pub fn queued(&self) -> bool {
+        matches!(*self.state.lock(), AdmissionState::Queued(_))
+    }
+
+    /// Returns true if the query was rejected instead of queued
+    pub fn rejected(&self) -> bool {
+        matches!(*self.state.lock(), AdmissionState::Rejected)
+    }
+}
+
+/// The admission of a single query, shared by an execution context, its
+/// children and the streams it creates. The query holds its permits until
+/// all of them are dropped.
+#[derive(Debug)]
+pub(crate) struct QueryAdmission {
+    controllers: Vec<Arc<AdmissionController>>,
+    metrics: Option<AdmissionMetrics>,
+    stats: Arc<AdmissionStats>,
+    permits: OnceCell<Vec<AdmissionPermit>>,
+}
+
+impl QueryAdmission {
+    pub(crate) fn new(
+        controllers: Vec<Arc<AdmissionController>>,
+        metrics: Option<AdmissionMetrics>,
+        priority: QueryPriority,
+    ) -> Self {
+        Self {
+            controllers,
+            metrics,
+            stats: Arc::new(AdmissionStats::new(priority)),
+            permits: OnceCell::new(),
+        }
+    }
+
+    /// Returns a new admission of a query with `priority`, by the same
+    /// controllers
+    pub(crate) fn with_priority(&self, priority: QueryPriority) -> Self {
+        Self::new(self.controllers.clone(), self.metrics.clone(), priority)
+    }
+
+    pub(crate) fn stats(&self) -> &Arc<AdmissionStats> {
+        &self.stats
+    }
+
+    /// Admits the query as a query of system tables, unless its admission
+    /// already started
+    pub(crate) fn set_system(&self) {
+        let state = self.stats.state.lock();
+        if matches!(*state, AdmissionState::Pending) {
+            *self.stats.priority.lock() = QueryPriority::System;
+        }
+    }
+
+    /// Waits until the query is admitted by all controllers, unless it
+    /// already was
+    pub(crate) async fn admit(&self) -> Result<(), AdmissionRejected> {
+        self.permits
+            .get_or_try_init(|| self.acquire_permits())
+            .await
+            .map(|_| ())
+    }
+
+    async fn acquire_permits(&self) -> Result<Vec<AdmissionPermit>, AdmissionRejected> {
+        // Queries are only queued if their number is limited
+        if self.controllers.is_empty() {
+            *self.stats.state.lock() = AdmissionState::Admitted(Duration::ZERO);
+            return Ok(vec![]);
+        }
+
+        let priority = self.stats.priority();
+        let metrics = self.metrics.as_ref().map(|m| m.priority(priority));
+        let start = Instant::now();
+        *self.stats.state.lock() = AdmissionState::Queued(start);
+
+        let permits = {
+            let _guard = QueuedGuard::new(metrics);
+
+            let mut permits = Vec::with_capacity(self.controllers.len());
+            for controller in &self.controllers {
+                match controller.admit(priority).await {
+                    Ok(permit) => permits.push(permit),
+                    Err(e) => {
+                        *self.stats.state.lock() = AdmissionState::Rejected;
+                        if let Some(metrics) = metrics {
+                            metrics.rejected.inc(1);
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+            permits
+        };
+
+        let wait = start.elapsed();
+        *self.stats.state.lock() = AdmissionState::Admitted(wait);
+        if let Some(metrics) = metrics {
+            metrics.wait.record(wait);
+        }
+
+        Ok(permits)
+    }
+}
+
+/// A stream keeping the query that produces it admitted
+pub(crate) struct AdmittedStream {
+    inner: SendableRecordBatchStream,
+    _admission: Arc<QueryAdmission>,
+}
+
+impl AdmittedStream {
+    pub(crate) fn new(inner: SendableRecordBatchStream, admission: Arc<QueryAdmission>) -> Self {
+        Self {
+            inner,
+            _admission: admission,
+        }
+    }
+}
+
+impl RecordBatchStream for AdmittedStream {
+    fn schema(&self) -> SchemaRef {
+        self.inner.schema()
+    }
+}
+
+impl Stream for AdmittedStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_max_concurrent() {
+        let controller = Arc::new(AdmissionController::new("server", Some(2), Some(1)));
+
+        let p1 = controller.admit(QueryPriority::Interactive).await.unwrap();
+        let _p2 = controller.admit(QueryPriority::Interactive).await.unwrap();
+        assert_eq!(controller.running(), 2);
+
+        let mut queued = Box::pin(controller.admit(QueryPriority::Interactive));
+        assert!(queued.as_mut().now_or_never().is_none());
+        assert_eq!(controller.queued(), 1);
+
+        let err = controller.admit(QueryPriority::Batch).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Query rejected as 1 queries are already waiting to run on the server"
+        );
+        assert!(is_admission_rejected(&err));
+
+        // the permit is handed to the waiting query
+        drop(p1);
+        let _p3 = queued.await.unwrap();
+        assert_eq!(controller.running(), 2);
+        assert_eq!(controller.queued(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter() {
+        let controller = Arc::new(AdmissionController::new("server", Some(1), None));
+
+        let p1 = controller.admit(QueryPriority::Interactive).await.unwrap();
+        let mut queued = Box::pin(controller.admit(QueryPriority::Interactive));
+        assert!(queued.as_mut().now_or_never().is_none());
+        drop(queued);
+
+        // the permit is not handed to the query that stopped waiting
+        drop(p1);
+        assert_eq!(controller.running(), 0);
+        assert_eq!(controller.queued(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_weighted_priorities() {
+        let controller = Arc::new(AdmissionController::new("server", Some(1), None));
+        let permit = controller.admit(QueryPriority::Interactive).await.unwrap();
+
+        let admitted = Arc::new(Mutex::new(vec![]));
+        let mut tasks = vec![];
+        for priority in [QueryPriority::Batch, QueryPriority::Interactive] {
+            for _ in 0..5 {
+                let controller = Arc::clone(&controller);
+                let admitted = Arc::clone(&admitted);
+                let mut admit = Box::pin(async move {
+                    let _permit = controller.admit(priority).await.unwrap();
+                    admitted.lock().push(priority);
+                });
+                // enqueue in order
+                assert!(admit.as_mut().now_or_never().is_none());
+                tasks.push(tokio::spawn(admit));
+            }
+        }
+
+        drop(permit);
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // 4 interactive queries are admitted for each batch query
+        use QueryPriority::*;
+        assert_eq!(
+            *admitted.lock(),
+            vec![
+                Interactive,
+                Batch,
+                Interactive,
+                Interactive,
+                Interactive,
+                Interactive,
+                Batch,
+                Batch,
+                Batch,
+                Batch
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_admission() {
+        let registry = metric::Registry::new();
+        let metrics = AdmissionMetrics::new(&registry, Attributes::from(&[("db_name", "foo")]));
+        let controller = Arc::new(AdmissionController::new("server", Some(1), None));
+
+        let admission = QueryAdmission::new(
+            vec![Arc::clone(&controller)],
+            Some(metrics),
+            QueryPriority::Batch,
+        );
+        assert_eq!(admission.stats().queue_wait(), None);
+
+        admission.admit().await.unwrap();
+        assert!(admission.stats().queue_wait().is_some());
+        assert!(!admission.stats().queued());
+        assert_eq!(controller.running(), 1);
+
+        // admitting again does not acquire another permit
+        admission.admit().await.unwrap();
+        assert_eq!(controller.running(), 1);
+
+        let other = admission.with_priority(QueryPriority::System);
+        let mut admit = Box::pin(other.admit());
+        assert!(admit.as_mut().now_or_never().is_none());
+        assert!(other.stats().queued());
+
+        let queued = registry
+            .get_instrument::<metric::Metric<U64Gauge>>("query_admission_queued")
+            .unwrap()
+            .get_observer(&Attributes::from(&[
+                ("db_name", "foo"),
+                ("priority", "system"),
+            ]))
+            .unwrap()
+            .fetch();
+        assert_eq!(queued, 1);
+
+        drop(admission);
+        admit.await.unwrap();
+        assert_eq!(controller.running(), 1);
+    }
+}
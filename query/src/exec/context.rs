@@ -2,14 +2,15 @@
 //! DataFusion
 
 use async_trait::async_trait;
-use std::{convert::TryInto, fmt, sync::Arc};
+use std::{convert::TryInto, fmt, sync::Arc, time::Duration};
 
 use arrow::record_batch::RecordBatch;
 
 use datafusion::{
-    catalog::catalog::CatalogProvider,
+    catalog::{catalog::CatalogProvider, TableReference},
     execution::context::{ExecutionContextState, QueryPlanner},
     logical_plan::{LogicalPlan, UserDefinedLogicalNode},
+    optimizer::optimizer::OptimizerRule,
     physical_plan::{
         coalesce_partitions::CoalescePartitionsExec,
         displayable,
@@ -19,14 +20,24 @@ use datafusion::{
     prelude::*,
 };
 use futures::TryStreamExt;
+use metric::U64Counter;
 use observability_deps::tracing::{debug, trace};
 use trace::{ctx::SpanContext, span::SpanRecorder};
 
 use crate::exec::{
+    admission::{
+        AdmissionController, AdmissionMetrics, AdmissionStats, AdmittedStream, QueryAdmission,
+        QueryPriority,
+    },
+    deadline::{DeadlineStream, QueryDeadline},
     fieldlist::{FieldList, IntoFieldList},
+    gapfill::{plan_gap_fill, GapFillExec, GapFillNode},
+    memory::{MemoryLimitRule, MemoryPool, QueryMemory},
     non_null_checker::NonNullCheckerExec,
     query_tracing::TracedStream,
     schema_pivot::{SchemaPivotExec, SchemaPivotNode},
+    series_transform::{plan_series_transforms, SeriesTransformExec, SeriesTransformNode},
+    seriescardinality::SeriesCounter,
     seriesset::{
         converter::{GroupGenerator, SeriesSetConverter},
         series::Series,
@@ -35,8 +46,10 @@ use crate::exec::{
     stringset::{IntoStringSet, StringSetRef},
 };
 
+use crate::func::{aggregates, gapfill, transform};
 use crate::plan::{
     fieldlist::FieldListPlan,
+    seriescardinality::{SeriesCardinalityPlan, TableSeriesPlan},
     seriesset::{SeriesSetPlan, SeriesSetPlans},
     stringset::StringSetPlan,
 };
@@ -128,6 +141,70 @@ impl ExtensionPlanner for IOxExtensionPlanner {
                 Arc::clone(&physical_inputs[0]),
                 split_expr,
             )) as Arc<dyn ExecutionPlan>)
+        } else if let Some(gap_fill) = any.downcast_ref::<GapFillNode>() {
+            assert_eq!(
+                logical_inputs.len(),
+                1,
+                "Inconsistent number of logical inputs"
+            );
+            assert_eq!(
+                physical_inputs.len(),
+                1,
+                "Inconsistent number of physical inputs"
+            );
+
+            let input_schema = logical_inputs[0].schema();
+            let series_columns = gap_fill
+                .series_columns()
+                .iter()
+                .map(|column| input_schema.index_of_column(column))
+                .collect::<Result<Vec<_>>>()?;
+            let time_column = input_schema.index_of_column(gap_fill.time_column())?;
+            let fills = gap_fill
+                .fills()
+                .iter()
+                .map(|(column, fill)| Ok((input_schema.index_of_column(column)?, fill.clone())))
+                .collect::<Result<Vec<_>>>()?;
+
+            Some(Arc::new(GapFillExec::new(
+                Arc::clone(&physical_inputs[0]),
+                series_columns,
+                time_column,
+                gap_fill.params().clone(),
+                fills,
+            )) as Arc<dyn ExecutionPlan>)
+        } else if let Some(series_transform) = any.downcast_ref::<SeriesTransformNode>() {
+            assert_eq!(
+                logical_inputs.len(),
+                1,
+                "Inconsistent number of logical inputs"
+            );
+            assert_eq!(
+                physical_inputs.len(),
+                1,
+                "Inconsistent number of physical inputs"
+            );
+
+            let input_schema = logical_inputs[0].schema();
+            let series_columns = series_transform
+                .series_columns()
+                .iter()
+                .map(|column| input_schema.index_of_column(column))
+                .collect::<Result<Vec<_>>>()?;
+            let time_column = input_schema.index_of_column(series_transform.time_column())?;
+            let transforms = series_transform
+                .transforms()
+                .iter()
+                .map(|t| Ok((t.transform, input_schema.index_of_column(&t.input)?)))
+                .collect::<Result<Vec<_>>>()?;
+
+            Some(Arc::new(SeriesTransformExec::new(
+                Arc::clone(&physical_inputs[0]),
+                series_transform.schema().as_ref().clone().into(),
+                series_columns,
+                time_column,
+                transforms,
+            )) as Arc<dyn ExecutionPlan>)
         } else {
             None
         };
@@ -151,6 +228,31 @@ pub struct IOxExecutionConfig {
 
     /// Span context from which to create spans for this query
     span_ctx: Option<SpanContext>,
+
+    /// The time after which queries are cancelled
+    timeout: Option<Duration>,
+
+    /// Counts queries that timed out
+    timeouts: Option<U64Counter>,
+
+    /// The memory pool queries reserve their memory from, if limited
+    memory_pool: Option<Arc<MemoryPool>>,
+
+    /// Admits queries across all databases, if limited
+    admission: Option<Arc<AdmissionController>>,
+
+    /// Admits queries of the database queried, if limited
+    database_admission: Option<Arc<AdmissionController>>,
+
+    /// Records how queries are admitted
+    admission_metrics: Option<AdmissionMetrics>,
+
+    /// The schema of the internal system tables, whose queries are admitted
+    /// as [`QueryPriority::System`]
+    system_schema: Option<&'static str>,
+
+    /// Additional rules optimizing the logical plans of queries
+    optimizer_rules: Vec<Arc<dyn OptimizerRule + Send + Sync>>,
 }
 
 impl fmt::Debug for IOxExecutionConfig {
@@ -166,6 +268,14 @@ impl IOxExecutionConfig {
             target_partitions: None,
             default_catalog: None,
             span_ctx: None,
+            timeout: None,
+            timeouts: None,
+            memory_pool: None,
+            admission: None,
+            database_admission: None,
+            admission_metrics: None,
+            system_schema: None,
+            optimizer_rules: vec![],
         }
     }
 
@@ -190,6 +300,68 @@ impl IOxExecutionConfig {
         Self { span_ctx, ..self }
     }
 
+    /// Set the time after which queries are cancelled, failing with a
+    /// [`QueryTimeout`](super::deadline::QueryTimeout)
+    pub fn with_timeout(self, timeout: Option<Duration>) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Set the counter incremented for each query that times out
+    pub fn with_timeout_metric(self, timeouts: U64Counter) -> Self {
+        Self {
+            timeouts: Some(timeouts),
+            ..self
+        }
+    }
+
+    /// Set the memory pool queries reserve their memory from. Sorts spill
+    /// once they exceed it, while deduplication fails with a
+    /// [`MemoryExhausted`](super::memory::MemoryExhausted)
+    pub fn with_memory_pool(self, memory_pool: Option<Arc<MemoryPool>>) -> Self {
+        Self {
+            memory_pool,
+            ..self
+        }
+    }
+
+    /// Set the controller admitting queries across all databases
+    pub fn with_admission(self, admission: Option<Arc<AdmissionController>>) -> Self {
+        Self { admission, ..self }
+    }
+
+    /// Set the controller admitting queries of the database queried, which
+    /// admits queries before the controller of all databases does
+    pub fn with_database_admission(self, admission: Option<Arc<AdmissionController>>) -> Self {
+        Self {
+            database_admission: admission,
+            ..self
+        }
+    }
+
+    /// Set the metrics recording how queries are admitted
+    pub fn with_admission_metrics(self, metrics: AdmissionMetrics) -> Self {
+        Self {
+            admission_metrics: Some(metrics),
+            ..self
+        }
+    }
+
+    /// Set the schema of the internal system tables. Queries only reading
+    /// tables of this schema are admitted as [`QueryPriority::System`].
+    pub fn with_system_schema(self, system_schema: &'static str) -> Self {
+        Self {
+            system_schema: Some(system_schema),
+            ..self
+        }
+    }
+
+    /// Add a rule optimizing the logical plans of queries, which runs after
+    /// the DataFusion optimizer rules
+    pub fn with_optimizer_rule(mut self, rule: Arc<dyn OptimizerRule + Send + Sync>) -> Self {
+        self.optimizer_rules.push(rule);
+        self
+    }
+
     /// Create an ExecutionContext suitable for executing DataFusion plans
     pub fn build(self) -> IOxExecutionContext {
         const BATCH_SIZE: usize = 1000;
@@ -205,7 +377,28 @@ impl IOxExecutionConfig {
             config = config.with_target_partitions(target_partitions)
         }
 
-        let inner = ExecutionContext::with_config(config);
+        if let Some(memory_pool) = self.memory_pool {
+            let memory = Arc::new(QueryMemory::new(memory_pool));
+            config = config.add_physical_optimizer_rule(Arc::new(MemoryLimitRule::new(memory)))
+        }
+
+        for rule in self.optimizer_rules {
+            config = config.add_optimizer_rule(rule)
+        }
+
+        let mut inner = ExecutionContext::with_config(config);
+
+        // make the InfluxDB aggregates, transforms and gap filling functions
+        // available to SQL queries
+        for udaf in aggregates::all_aggregates() {
+            inner.register_udaf(udaf);
+        }
+        for udf in transform::all_transforms()
+            .into_iter()
+            .chain(gapfill::all_gapfill_functions())
+        {
+            inner.register_udf(udf);
+        }
 
         if let Some(default_catalog) = self.default_catalog {
             inner.register_catalog(DEFAULT_CATALOG, default_catalog);
@@ -213,10 +406,31 @@ impl IOxExecutionConfig {
 
         let maybe_span = self.span_ctx.map(|ctx| ctx.child("Query Execution"));
 
+        let timeouts = self.timeouts;
+        let deadline = self
+            .timeout
+            .map(|timeout| Arc::new(QueryDeadline::new(timeout, timeouts)));
+
+        // A query waiting for its database must not hold a permit of the
+        // server, blocking queries of other databases
+        let controllers = self
+            .database_admission
+            .into_iter()
+            .chain(self.admission)
+            .collect();
+        let admission = Arc::new(QueryAdmission::new(
+            controllers,
+            self.admission_metrics,
+            QueryPriority::default(),
+        ));
+
         IOxExecutionContext {
             inner,
             exec: self.exec,
             recorder: SpanRecorder::new(maybe_span),
+            deadline,
+            admission,
+            system_schema: self.system_schema,
         }
     }
 }
@@ -227,9 +441,9 @@ impl IOxExecutionConfig {
 /// Methods on this struct should be preferred to using the raw
 /// DataFusion functions (such as `collect`) directly.
 ///
-/// Eventually we envision this also managing additional resource
-/// types such as Memory and providing visibility into what plans are
-/// running
+/// Queries may be limited in the time they run for and in the memory
+/// they use. Eventually we envision this also providing visibility into
+/// what plans are running
 ///
 /// An IOxExecutionContext is created directly from an Executor, or from
 /// an IOxExecutionConfig created by an Executor
@@ -246,6 +460,17 @@ pub struct IOxExecutionContext {
 
     /// Span context from which to create spans for this query
     recorder: SpanRecorder,
+
+    /// The deadline after which the query is cancelled, shared with all
+    /// child contexts
+    deadline: Option<Arc<QueryDeadline>>,
+
+    /// The admission of the query, shared with all child contexts and the
+    /// streams they create
+    admission: Arc<QueryAdmission>,
+
+    /// The schema of the internal system tables, if any
+    system_schema: Option<&'static str>,
 }
 
 impl fmt::Debug for IOxExecutionContext {
@@ -262,6 +487,46 @@ impl IOxExecutionContext {
         &self.inner
     }
 
+    /// Applies the timeout of the request, if it sets one. The request can
+    /// only shorten the timeout of this context, such as the server-wide
+    /// default, not extend it. The new deadline starts now.
+    pub fn with_request_timeout(self, timeout: Option<Duration>) -> Self {
+        let timeout = match (timeout, &self.deadline) {
+            (Some(timeout), Some(deadline)) => timeout.min(deadline.timeout()),
+            (Some(timeout), None) => timeout,
+            (None, _) => return self,
+        };
+
+        let timeouts = self
+            .deadline
+            .as_ref()
+            .and_then(|deadline| deadline.timeouts().cloned());
+
+        Self {
+            deadline: Some(Arc::new(QueryDeadline::new(timeout, timeouts))),
+            ..self
+        }
+    }
+
+    /// Returns the deadline of queries run by this context, if any
+    pub fn deadline(&self) -> Option<&Arc<QueryDeadline>> {
+        self.deadline.as_ref()
+    }
+
+    /// Sets the priority the query is admitted with. This must be set
+    /// before the context runs anything.
+    pub fn with_priority(self, priority: QueryPriority) -> Self {
+        Self {
+            admission: Arc::new(self.admission.with_priority(priority)),
+            ..self
+        }
+    }
+
+    /// Returns how the query of this context is admitted
+    pub fn admission_stats(&self) -> &Arc<AdmissionStats> {
+        self.admission.stats()
+    }
+
     /// Prepare a SQL statement for execution. This assumes that any
     /// tables referenced in the SQL have been registered with this context
     pub async fn prepare_sql(&self, sql: &str) -> Result<Arc<dyn ExecutionPlan>> {
@@ -277,7 +542,18 @@ impl IOxExecutionContext {
         let mut ctx = self.child_ctx("prepare_plan");
         debug!(text=%plan.display_indent_schema(), "prepare_plan: initial plan");
 
-        let plan = ctx.inner.optimize(plan)?;
+        // gap filling and transform functions are planned before
+        // optimization, while the filters on time and the columns
+        // identifying each series are still available
+        let plan = plan_gap_fill(plan)?;
+        let plan = plan_series_transforms(&plan)?;
+        let plan = ctx.inner.optimize(&plan)?;
+
+        if let Some(system_schema) = ctx.system_schema {
+            if only_scans_schema(&plan, system_schema) {
+                ctx.admission.set_system();
+            }
+        }
 
         ctx.recorder.event("optimized plan");
         trace!(text=%plan.display_indent_schema(), graphviz=%plan.display_graphviz(), "optimized plan");
@@ -332,23 +608,32 @@ impl IOxExecutionContext {
     /// Executes a single partition of a physical plan and produces a
     /// `SendableRecordBatchStream` to stream over the result that
     /// iterates over the results. The creation of the stream is
-    /// performed in a separate thread pool.
+    /// performed in a separate thread pool, once the query is admitted.
     pub async fn execute_stream_partitioned(
         &self,
         physical_plan: Arc<dyn ExecutionPlan>,
         partition: usize,
     ) -> Result<SendableRecordBatchStream> {
+        self.admit().await?;
+
         let span = self
             .recorder
             .span()
             .map(|span| span.child("execute_stream_partitioned"));
 
-        self.run(async move {
-            let stream = physical_plan.execute(partition).await?;
-            let stream = TracedStream::new(stream, span, physical_plan);
-            Ok(Box::pin(stream) as _)
+        let stream = self
+            .run(async move {
+                let stream = physical_plan.execute(partition).await?;
+                let stream = TracedStream::new(stream, span, physical_plan);
+                Ok(Box::pin(stream) as SendableRecordBatchStream)
+            })
+            .await?;
+
+        let stream = Box::pin(AdmittedStream::new(stream, Arc::clone(&self.admission)));
+        Ok(match &self.deadline {
+            Some(deadline) => Box::pin(DeadlineStream::new(stream, Arc::clone(deadline))),
+            None => stream,
         })
-        .await
     }
 
     /// Executes the SeriesSetPlans on the query executor, in
@@ -496,6 +781,44 @@ impl IOxExecutionContext {
         }
     }
 
+    /// Executes `plan` and returns the (estimated) number of series
+    pub async fn to_series_cardinality(&self, plan: SeriesCardinalityPlan) -> Result<u64> {
+        let SeriesCardinalityPlan { known, plans } = plan;
+
+        // Run the plans in parallel
+        let handles = plans
+            .into_iter()
+            .map(|plan| {
+                let ctx = self.child_ctx("to_series_cardinality");
+                self.run(async move {
+                    let TableSeriesPlan {
+                        plan,
+                        tag_columns,
+                        field_columns,
+                    } = plan;
+
+                    let physical_plan = ctx.prepare_plan(&plan).await?;
+                    let mut stream = ctx.execute_stream(physical_plan).await?;
+
+                    let mut counter = SeriesCounter::new(tag_columns, field_columns);
+                    while let Some(batch) = stream.try_next().await? {
+                        counter.add_batch(&batch)?;
+                    }
+
+                    Ok(counter.count())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // the series of different tables are distinct
+        let mut count = known;
+        for join_handle in handles {
+            count += join_handle.await?;
+        }
+
+        Ok(count)
+    }
+
     /// Run the plan and return a record batch reader for reading the results
     pub async fn run_logical_plan(&self, plan: LogicalPlan) -> Result<Vec<RecordBatch>> {
         self.run_logical_plans(vec![plan]).await
@@ -526,16 +849,43 @@ impl IOxExecutionContext {
         Ok(results)
     }
 
+    /// Waits until the query is admitted to execute, unless it already was
+    ///
+    /// Fails if too many queries are already waiting or the deadline of
+    /// this context passes first
+    async fn admit(&self) -> Result<()> {
+        let admit = self.admission.admit();
+        let result = match &self.deadline {
+            Some(deadline) => deadline
+                .run(admit)
+                .await
+                .map_err(|e| Error::External(Box::new(e)))?,
+            None => admit.await,
+        };
+        result.map_err(|e| Error::External(Box::new(e)))
+    }
+
     /// Runs the provided future using this execution context
+    ///
+    /// If the deadline of this context passes first, the future is
+    /// cancelled and a [`QueryTimeout`](super::deadline::QueryTimeout) is
+    /// returned
     pub async fn run<Fut, T>(&self, fut: Fut) -> Result<T>
     where
         Fut: std::future::Future<Output = Result<T>> + Send + 'static,
         T: Send + 'static,
     {
-        self.exec
-            .spawn(fut)
-            .await
-            .unwrap_or_else(|e| Err(Error::Execution(format!("Join Error: {}", e))))
+        // Dropping the job on timeout cancels it
+        let job = self.exec.spawn(fut);
+        let result = match &self.deadline {
+            Some(deadline) => deadline
+                .run(job)
+                .await
+                .map_err(|e| Error::External(Box::new(e)))?,
+            None => job.await,
+        };
+
+        result.unwrap_or_else(|e| Err(Error::Execution(format!("Join Error: {}", e))))
     }
 
     /// Returns a IOxExecutionContext with a SpanRecorder that is a child of the current
@@ -544,6 +894,9 @@ impl IOxExecutionContext {
             inner: self.inner.clone(),
             exec: self.exec.clone(),
             recorder: self.recorder.child(name),
+            deadline: self.deadline.as_ref().map(Arc::clone),
+            admission: Arc::clone(&self.admission),
+            system_schema: self.system_schema,
         }
     }
 
@@ -552,3 +905,33 @@ impl IOxExecutionContext {
         self.exec.tasks()
     }
 }
+
+/// Returns true if `plan` reads tables, all of which are in `schema`
+fn only_scans_schema(plan: &LogicalPlan, schema: &str) -> bool {
+    fn visit<'a>(plan: &'a LogicalPlan, table_names: &mut Vec<&'a str>) {
+        if let LogicalPlan::TableScan { table_name, .. } = plan {
+            table_names.push(table_name);
+        }
+        for input in plan.inputs() {
+            visit(input, table_names);
+        }
+    }
+
+    let mut table_names = vec![];
+    visit(plan, &mut table_names);
+
+    !table_names.is_empty()
+        && table_names
+            .into_iter()
+            .all(|table_name| match TableReference::from(table_name) {
+                TableReference::Bare { .. } => false,
+                TableReference::Partial {
+                    schema: table_schema,
+                    ..
+                }
+                | TableReference::Full {
+                    schema: table_schema,
+                    ..
+                } => table_schema == schema,
+            })
+}
@@ -0,0 +1,261 @@
+//! This module enforces the timeouts of queries run by an
+//! [`IOxExecutionContext`](super::IOxExecutionContext)
+
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use arrow::{datatypes::SchemaRef, error::ArrowError, record_batch::RecordBatch};
+use datafusion::{
+    error::DataFusionError,
+    physical_plan::{RecordBatchStream, SendableRecordBatchStream},
+};
+use futures::{Future, Stream};
+use metric::U64Counter;
+use tokio::time::{Instant, Sleep};
+
+/// The error a query fails with when it runs past its deadline
+#[derive(Debug, Clone, Copy)]
+pub struct QueryTimeout {
+    timeout: Duration,
+}
+
+impl fmt::Display for QueryTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Query exceeded its timeout of {:?}", self.timeout)
+    }
+}
+
+impl std::error::Error for QueryTimeout {}
+
+/// Returns true if `err`, or any error it was caused by, is a
+/// [`QueryTimeout`]
+pub fn is_query_timeout(err: &(dyn std::error::Error + 'static)) -> bool {
+    caused_by::<QueryTimeout>(err)
+}
+
+/// Returns true if `err`, or any error it was caused by, is an `E`
+pub(crate) fn caused_by<E>(err: &(dyn std::error::Error + 'static)) -> bool
+where
+    E: std::error::Error + 'static,
+{
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if err.is::<E>() {
+            return true;
+        }
+
+        // DataFusion and Arrow errors don't expose their external errors
+        // as their source
+        current = match err.downcast_ref::<DataFusionError>() {
+            Some(DataFusionError::External(e)) => Some(e.as_ref()),
+            Some(DataFusionError::ArrowError(ArrowError::ExternalError(e))) => Some(e.as_ref()),
+            _ => match err.downcast_ref::<ArrowError>() {
+                Some(ArrowError::ExternalError(e)) => Some(e.as_ref()),
+                _ => err.source(),
+            },
+        };
+    }
+    false
+}
+
+/// The deadline of a query, shared by an execution context and all its
+/// children
+#[derive(Debug)]
+pub struct QueryDeadline {
+    timeout: Duration,
+    deadline: Instant,
+    timed_out: AtomicBool,
+
+    /// Incremented once if the query times out
+    timeouts: Option<U64Counter>,
+}
+
+/// Timeouts longer than this, about 30 years, are treated as this long, as
+/// their deadline may not be representable
+const MAX_TIMEOUT: Duration = Duration::from_secs(86400 * 365 * 30);
+
+impl QueryDeadline {
+    /// Creates a deadline `timeout` from now
+    pub(crate) fn new(timeout: Duration, timeouts: Option<U64Counter>) -> Self {
+        Self {
+            timeout,
+            deadline: Instant::now() + timeout.min(MAX_TIMEOUT),
+            timed_out: AtomicBool::new(false),
+            timeouts,
+        }
+    }
+
+    /// The timeout the deadline was created with
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns true if the query ran past this deadline
+    pub fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn timeouts(&self) -> Option<&U64Counter> {
+        self.timeouts.as_ref()
+    }
+
+    /// Runs `fut` until this deadline, dropping it if it has not completed
+    /// by then
+    pub(crate) async fn run<Fut, T>(&self, fut: Fut) -> Result<T, QueryTimeout>
+    where
+        Fut: Future<Output = T>,
+    {
+        tokio::time::timeout_at(self.deadline, fut)
+            .await
+            .map_err(|_| self.expire())
+    }
+
+    /// Records that the query ran past this deadline
+    fn expire(&self) -> QueryTimeout {
+        if !self.timed_out.swap(true, Ordering::Relaxed) {
+            if let Some(timeouts) = &self.timeouts {
+                timeouts.inc(1)
+            }
+        }
+        QueryTimeout {
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// A stream that fails with a [`QueryTimeout`] once its deadline passes,
+/// dropping the stream it wraps and thus cancelling the tasks producing it
+pub(crate) struct DeadlineStream {
+    schema: SchemaRef,
+    inner: Option<SendableRecordBatchStream>,
+    sleep: Pin<Box<Sleep>>,
+    deadline: Arc<QueryDeadline>,
+}
+
+impl DeadlineStream {
+    pub(crate) fn new(inner: SendableRecordBatchStream, deadline: Arc<QueryDeadline>) -> Self {
+        Self {
+            schema: inner.schema(),
+            inner: Some(inner),
+            sleep: Box::pin(tokio::time::sleep_until(deadline.deadline)),
+            deadline,
+        }
+    }
+}
+
+impl RecordBatchStream for DeadlineStream {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+}
+
+impl Stream for DeadlineStream {
+    type Item = Result<RecordBatch, ArrowError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let inner = match this.inner.as_mut() {
+            Some(inner) => inner,
+            None => return Poll::Ready(None),
+        };
+
+        if this.sleep.as_mut().poll(cx).is_ready() {
+            this.inner = None;
+            let e = this.deadline.expire();
+            return Poll::Ready(Some(Err(ArrowError::ExternalError(Box::new(e)))));
+        }
+
+        inner.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::Schema;
+    use datafusion::physical_plan::common::SizedRecordBatchStream;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_deadline_run() {
+        let timeouts = U64Counter::default();
+        let deadline = QueryDeadline::new(Duration::from_millis(10), Some(timeouts.clone()));
+
+        assert_eq!(deadline.run(async { 1 }).await.unwrap(), 1);
+        assert!(!deadline.timed_out());
+
+        let err = deadline
+            .run(futures::future::pending::<()>())
+            .await
+            .unwrap_err();
+        assert!(deadline.timed_out());
+        assert_eq!(err.to_string(), "Query exceeded its timeout of 10ms");
+
+        // timing out again is only counted once
+        deadline
+            .run(futures::future::pending::<()>())
+            .await
+            .unwrap_err();
+        assert_eq!(timeouts.fetch(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_huge_timeout() {
+        // such a deadline overflows an `Instant`
+        let deadline = QueryDeadline::new(Duration::MAX, None);
+
+        assert_eq!(deadline.timeout(), Duration::MAX);
+        assert_eq!(deadline.run(async { 1 }).await.unwrap(), 1);
+        assert!(!deadline.timed_out());
+    }
+
+    /// A stream that never produces a batch
+    struct PendingStream(SchemaRef);
+
+    impl RecordBatchStream for PendingStream {
+        fn schema(&self) -> SchemaRef {
+            Arc::clone(&self.0)
+        }
+    }
+
+    impl Stream for PendingStream {
+        type Item = Result<RecordBatch, ArrowError>;
+
+        fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Pending
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deadline_stream() {
+        let deadline = Arc::new(QueryDeadline::new(Duration::from_millis(10), None));
+        let schema = Arc::new(Schema::empty());
+
+        // A stream completing before its deadline is unaffected
+        let inner = Box::pin(SizedRecordBatchStream::new(Arc::clone(&schema), vec![]));
+        let mut stream = DeadlineStream::new(inner, Arc::clone(&deadline));
+        assert!(stream.next().await.is_none());
+        assert!(!deadline.timed_out());
+
+        let inner = Box::pin(PendingStream(schema));
+        let mut stream = DeadlineStream::new(inner, Arc::clone(&deadline));
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(is_query_timeout(&err));
+        assert!(deadline.timed_out());
+        assert!(stream.next().await.is_none());
+
+        let err = DataFusionError::ArrowError(err);
+        assert!(is_query_timeout(&err));
+        assert!(!is_query_timeout(&DataFusionError::Execution(
+            "foo".to_string()
+        )));
+    }
+}
@@ -0,0 +1,1238 @@
+//! This module contains the "GapFill" DataFusion extension plan node,
+//! which emits rows for the windows of a windowed aggregate that have no
+//! rows, as configured by [`crate::func::gapfill`].
+//!
+//! A GapFill node takes an input sorted by the columns identifying a
+//! series and then by the time of its window. For example, filling the
+//! windows of 10 between 0 and 40 of this input
+//!
+//!  host | time | usage
+//! ------+------+-------
+//!   a   |  0   |  1.0
+//!   a   |  30  |  4.0
+//!   b   |  10  |  5.0
+//!
+//! produces (with the `previous` fill mode for `usage`)
+//!
+//!  host | time | usage
+//! ------+------+-------
+//!   a   |  0   |  1.0
+//!   a   |  10  |  1.0
+//!   a   |  20  |  1.0
+//!   a   |  30  |  4.0
+//!   b   |  0   |  NULL
+//!   b   |  10  |  5.0
+//!   b   |  20  |  5.0
+//!   b   |  30  |  5.0
+//!
+//! Fill modes only apply to the inserted rows, the rows of the input are
+//! passed through unchanged. Columns without a fill mode are NULL in the
+//! inserted rows.
+//!
+//! [`plan_gap_fill`] adds GapFill nodes to the aggregates of SQL queries
+//! grouped by `date_bin_gapfill`, and `InfluxRpcPlanner` adds them to
+//! `read_window_aggregate` plans creating empty windows.
+
+use std::{
+    any::Any,
+    borrow::Cow,
+    fmt::{self, Debug},
+    ops::Range,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, StringArray, TimestampNanosecondArray, UInt32Array},
+    compute::{cast, kernels::cast_utils::string_to_timestamp_nanos, take},
+    datatypes::{DataType, SchemaRef},
+    error::{ArrowError, Result as ArrowResult},
+    record_batch::RecordBatch,
+};
+use data_types::timestamp::TimestampRange;
+use datafusion::{
+    error::{DataFusionError as Error, Result},
+    logical_plan::{
+        plan::Extension, Column, DFSchemaRef, Expr, ExprRewriter, LogicalPlan, LogicalPlanBuilder,
+        Operator, UserDefinedLogicalNode,
+    },
+    optimizer::utils,
+    physical_plan::{
+        functions::BuiltinScalarFunction,
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet, RecordOutput},
+        DisplayFormatType, Distribution, ExecutionPlan, Partitioning, SendableRecordBatchStream,
+        Statistics,
+    },
+    scalar::ScalarValue,
+};
+use datafusion_util::AdapterStream;
+use observability_deps::tracing::debug;
+use schema::TIME_DATA_TYPE;
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+
+use crate::{
+    exec::memory::{batch_memory_size, MemoryReservation, QueryMemory},
+    func::gapfill::{
+        date_bin_gapfill_args, FillMode, GapFillParams, WindowBound, DATE_BIN_GAPFILL_UDF_NAME,
+    },
+    group_by::WindowDuration,
+};
+
+/// The number of rows of the batches a GapFill node outputs
+const OUTPUT_BATCH_SIZE: usize = 8192;
+
+/// Implements the GapFill operation as described in this module's
+/// documentation
+pub struct GapFillNode {
+    input: LogicalPlan,
+
+    /// The columns identifying a series
+    series_columns: Vec<Column>,
+
+    /// The column with the time of the window of each row
+    time_column: Column,
+
+    params: GapFillParams,
+
+    /// The fill modes of the columns that are not NULL in inserted rows
+    fills: Vec<(Column, FillMode)>,
+
+    /// The columns used by this node, so that they are not optimized away
+    exprs: Vec<Expr>,
+}
+
+impl GapFillNode {
+    /// Create a node filling the windows of `params`. `input` must be
+    /// sorted by `series_columns` and then by `time_column`.
+    pub fn try_new(
+        input: LogicalPlan,
+        series_columns: Vec<Column>,
+        time_column: Column,
+        params: GapFillParams,
+        fills: Vec<(Column, FillMode)>,
+    ) -> Result<Self> {
+        let input_schema = input.schema();
+        input_schema.index_of_column(&time_column)?;
+
+        // Constants are cast to the type of their column up front
+        let fills = fills
+            .into_iter()
+            .map(|(column, fill)| {
+                let field = &input_schema.fields()[input_schema.index_of_column(&column)?];
+                let fill = match fill {
+                    FillMode::Constant(value) => {
+                        let array = cast(&value.to_array(), field.data_type())?;
+                        FillMode::Constant(ScalarValue::try_from_array(&array, 0)?)
+                    }
+                    FillMode::Linear if !can_interpolate(field.data_type()) => {
+                        return Err(Error::Plan(format!(
+                            "linear fill requires a numeric column, but {} is {:?}",
+                            column.flat_name(),
+                            field.data_type()
+                        )))
+                    }
+                    fill => fill,
+                };
+                Ok((column, fill))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let exprs = series_columns
+            .iter()
+            .chain(std::iter::once(&time_column))
+            .chain(fills.iter().map(|(column, _)| column))
+            .map(|column| Expr::Column(column.clone()))
+            .collect();
+
+        Ok(Self {
+            input,
+            series_columns,
+            time_column,
+            params,
+            fills,
+            exprs,
+        })
+    }
+
+    pub fn series_columns(&self) -> &[Column] {
+        &self.series_columns
+    }
+
+    pub fn time_column(&self) -> &Column {
+        &self.time_column
+    }
+
+    pub fn params(&self) -> &GapFillParams {
+        &self.params
+    }
+
+    pub fn fills(&self) -> &[(Column, FillMode)] {
+        &self.fills
+    }
+}
+
+impl Debug for GapFillNode {
+    /// Use explain format for the Debug format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_for_explain(f)
+    }
+}
+
+impl UserDefinedLogicalNode for GapFillNode {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    /// Schema is the same as the input schema
+    fn schema(&self) -> &DFSchemaRef {
+        self.input.schema()
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        self.exprs.clone()
+    }
+
+    /// For example: `GapFill series=[#cpu.host] time=#time range=[0, 40) fills=[#usage: previous]`
+    fn fmt_for_explain(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let series: Vec<_> = self
+            .series_columns
+            .iter()
+            .map(|c| format!("#{}", c.flat_name()))
+            .collect();
+        let fills: Vec<_> = self
+            .fills
+            .iter()
+            .map(|(column, fill)| format!("#{}: {}", column.flat_name(), fill))
+            .collect();
+        write!(
+            f,
+            "GapFill series=[{}] time=#{} range=[{}, {}) fills=[{}]",
+            series.join(", "),
+            self.time_column.flat_name(),
+            self.params.range.start,
+            self.params.range.end,
+            fills.join(", ")
+        )
+    }
+
+    fn from_template(
+        &self,
+        exprs: &[Expr],
+        inputs: &[LogicalPlan],
+    ) -> Arc<dyn UserDefinedLogicalNode + Send + Sync> {
+        assert_eq!(inputs.len(), 1, "GapFill: input sizes inconsistent");
+        assert_eq!(
+            exprs.len(),
+            self.exprs.len(),
+            "GapFill: expression sizes inconsistent"
+        );
+        Arc::new(Self {
+            input: inputs[0].clone(),
+            series_columns: self.series_columns.clone(),
+            time_column: self.time_column.clone(),
+            params: self.params.clone(),
+            fills: self.fills.clone(),
+            exprs: self.exprs.clone(),
+        })
+    }
+}
+
+/// Adds a [`GapFillNode`] to the aggregates of `plan` grouped by
+/// `date_bin_gapfill`, and replaces the fill functions applied to their
+/// output by the fill modes of the node
+///
+/// The windows are filled between the bounds on the binned time column in
+/// the `WHERE` clause, which are required.
+pub fn plan_gap_fill(plan: &LogicalPlan) -> Result<LogicalPlan> {
+    Ok(rewrite_plan(plan)?.unwrap_or_else(|| plan.clone()))
+}
+
+/// Returns the rewritten plan, or `None` if `plan` does not fill gaps
+fn rewrite_plan(plan: &LogicalPlan) -> Result<Option<LogicalPlan>> {
+    let inputs = plan.inputs();
+    let new_inputs = inputs
+        .iter()
+        .map(|input| rewrite_plan(input))
+        .collect::<Result<Vec<_>>>()?;
+
+    let plan = if new_inputs.iter().any(Option::is_some) {
+        let new_inputs: Vec<_> = new_inputs
+            .into_iter()
+            .zip(inputs)
+            .map(|(new_input, input)| new_input.unwrap_or_else(|| input.clone()))
+            .collect();
+        Cow::Owned(utils::from_plan(plan, &plan.expressions(), &new_inputs)?)
+    } else {
+        Cow::Borrowed(plan)
+    };
+
+    let new_plan = match plan.as_ref() {
+        LogicalPlan::Aggregate {
+            input, group_expr, ..
+        } => rewrite_aggregate(plan.as_ref(), input, group_expr)?,
+        LogicalPlan::Projection {
+            expr, input, alias, ..
+        } => rewrite_projection(expr, input, alias.clone())?,
+        _ => None,
+    };
+
+    Ok(match (new_plan, plan) {
+        (Some(new_plan), _) => Some(new_plan),
+        (None, Cow::Owned(plan)) => Some(plan),
+        (None, Cow::Borrowed(_)) => None,
+    })
+}
+
+/// Returns the aggregate sorted by series and a gap filling node, if it
+/// is grouped by `date_bin_gapfill`
+fn rewrite_aggregate(
+    aggregate: &LogicalPlan,
+    input: &LogicalPlan,
+    group_expr: &[Expr],
+) -> Result<Option<LogicalPlan>> {
+    let mut gap_fill = None;
+    for (index, expr) in group_expr.iter().enumerate() {
+        if let Some((every, time)) = date_bin_gapfill_args(expr)? {
+            if gap_fill.is_some() {
+                return Err(Error::Plan(format!(
+                    "{} can only be used once in GROUP BY",
+                    DATE_BIN_GAPFILL_UDF_NAME
+                )));
+            }
+            gap_fill = Some((index, every, time));
+        }
+    }
+    let (time_index, every, time) = match gap_fill {
+        Some(gap_fill) => gap_fill,
+        None => return Ok(None),
+    };
+
+    let range = match time {
+        Expr::Column(time) => time_range(input, time)?,
+        _ => {
+            return Err(Error::Plan(format!(
+                "{} requires a time column, got {}",
+                DATE_BIN_GAPFILL_UDF_NAME, time
+            )))
+        }
+    };
+
+    // the output of an aggregate starts with the group columns
+    let schema = aggregate.schema();
+    let time_column = schema.field(time_index).qualified_column();
+    let series_columns: Vec<_> = (0..group_expr.len())
+        .filter(|index| *index != time_index)
+        .map(|index| schema.field(index).qualified_column())
+        .collect();
+
+    let sort_exprs = series_columns
+        .iter()
+        .chain(std::iter::once(&time_column))
+        .map(|column| Expr::Sort {
+            expr: Box::new(Expr::Column(column.clone())),
+            asc: true,
+            nulls_first: true,
+        })
+        .collect::<Vec<_>>();
+
+    let sorted = LogicalPlanBuilder::from(aggregate.clone())
+        .sort(sort_exprs)?
+        .build()?;
+
+    let params = GapFillParams {
+        every,
+        offset: WindowDuration::empty(),
+        range,
+        bound: WindowBound::Start,
+    };
+    let node = GapFillNode::try_new(sorted, series_columns, time_column, params, vec![])?;
+
+    Ok(Some(LogicalPlan::Extension(Extension {
+        node: Arc::new(node),
+    })))
+}
+
+/// Rewrites a projection applying fill functions to the output of a
+/// GapFill node as a projection of a GapFill node with these fill modes
+fn rewrite_projection(
+    exprs: &[Expr],
+    input: &LogicalPlan,
+    alias: Option<String>,
+) -> Result<Option<LogicalPlan>> {
+    let node = match input {
+        LogicalPlan::Extension(Extension { node }) => {
+            match node.as_any().downcast_ref::<GapFillNode>() {
+                Some(node) => node,
+                None => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    let mut rewriter = FillRewriter::default();
+    let new_exprs = exprs
+        .iter()
+        .map(|expr| {
+            let new_expr = expr.clone().rewrite(&mut rewriter)?;
+            // keep the name of the expression in the output
+            Ok(match expr {
+                Expr::Alias(_, _) => new_expr,
+                _ if &new_expr == expr => new_expr,
+                _ => new_expr.alias(&expr.name(input.schema())?),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if rewriter.fills.is_empty() {
+        return Ok(None);
+    }
+
+    let node = GapFillNode::try_new(
+        node.input.clone(),
+        node.series_columns.clone(),
+        node.time_column.clone(),
+        node.params.clone(),
+        rewriter.fills,
+    )?;
+
+    let plan = LogicalPlanBuilder::from(LogicalPlan::Extension(Extension {
+        node: Arc::new(node),
+    }))
+    .project_with_alias(new_exprs, alias)?
+    .build()?;
+
+    Ok(Some(plan))
+}
+
+/// Returns the range of the bounds on `time_column` in the filters of
+/// `input`
+fn time_range(input: &LogicalPlan, time_column: &Column) -> Result<TimestampRange> {
+    let mut start: Option<i64> = None;
+    let mut end: Option<i64> = None;
+
+    let mut plan = input;
+    while let LogicalPlan::Filter { predicate, input } = plan {
+        let mut conjuncts = vec![];
+        split_conjunction(predicate, &mut conjuncts);
+        for expr in conjuncts {
+            let (lower, upper) = time_bounds(expr, time_column);
+            start = start.max(lower);
+            end = match (end, upper) {
+                (Some(end), Some(upper)) => Some(end.min(upper)),
+                (end, upper) => end.or(upper),
+            };
+        }
+        plan = input.as_ref();
+    }
+
+    match (start, end) {
+        (Some(start), Some(end)) => Ok(TimestampRange::new(start, end.max(start))),
+        _ => Err(Error::Plan(format!(
+            "{} requires lower and upper bounds on {} in the WHERE clause",
+            DATE_BIN_GAPFILL_UDF_NAME, time_column.name
+        ))),
+    }
+}
+
+fn split_conjunction<'a>(expr: &'a Expr, conjuncts: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::BinaryExpr {
+            left,
+            op: Operator::And,
+            right,
+        } => {
+            split_conjunction(left, conjuncts);
+            split_conjunction(right, conjuncts);
+        }
+        expr => conjuncts.push(expr),
+    }
+}
+
+/// Returns the inclusive lower and exclusive upper bound `expr` puts on
+/// `time_column`, if any
+fn time_bounds(expr: &Expr, time_column: &Column) -> (Option<i64>, Option<i64>) {
+    let is_time = |expr: &Expr| matches!(expr, Expr::Column(c) if c.name == time_column.name);
+
+    match expr {
+        Expr::BinaryExpr { left, op, right } => {
+            let (op, value) = if is_time(left) {
+                (*op, right.as_ref())
+            } else if is_time(right) {
+                let op = match op {
+                    Operator::Lt => Operator::Gt,
+                    Operator::LtEq => Operator::GtEq,
+                    Operator::Gt => Operator::Lt,
+                    Operator::GtEq => Operator::LtEq,
+                    op => *op,
+                };
+                (op, left.as_ref())
+            } else {
+                return (None, None);
+            };
+
+            match (op, timestamp_value(value)) {
+                (Operator::Eq, Some(v)) => (Some(v), Some(v.saturating_add(1))),
+                (Operator::Gt, Some(v)) => (Some(v.saturating_add(1)), None),
+                (Operator::GtEq, Some(v)) => (Some(v), None),
+                (Operator::Lt, Some(v)) => (None, Some(v)),
+                (Operator::LtEq, Some(v)) => (None, Some(v.saturating_add(1))),
+                _ => (None, None),
+            }
+        }
+        Expr::Between {
+            expr,
+            negated: false,
+            low,
+            high,
+        } if is_time(expr) => (
+            timestamp_value(low),
+            timestamp_value(high).map(|v| v.saturating_add(1)),
+        ),
+        _ => (None, None),
+    }
+}
+
+/// Returns the value of a constant timestamp, such as `'2021-01-01T00:00:00Z'`
+/// or `to_timestamp('2021-01-01T00:00:00Z')`, in nanoseconds
+fn timestamp_value(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Literal(ScalarValue::TimestampNanosecond(Some(v)))
+        | Expr::Literal(ScalarValue::Int64(Some(v))) => Some(*v),
+        Expr::Literal(ScalarValue::Utf8(Some(s))) => string_to_timestamp_nanos(s).ok(),
+        Expr::Cast { expr, .. } | Expr::TryCast { expr, .. } => timestamp_value(expr),
+        Expr::ScalarFunction {
+            fun: BuiltinScalarFunction::ToTimestamp,
+            args,
+        } if args.len() == 1 => timestamp_value(&args[0]),
+        _ => None,
+    }
+}
+
+/// Replaces the calls to fill functions with the columns they fill
+#[derive(Debug, Default)]
+struct FillRewriter {
+    fills: Vec<(Column, FillMode)>,
+}
+
+impl ExprRewriter for FillRewriter {
+    fn mutate(&mut self, expr: Expr) -> Result<Expr> {
+        let (fill, input) = match FillMode::try_from_expr(&expr)? {
+            Some(fill) => fill,
+            None => return Ok(expr),
+        };
+
+        let column = match input {
+            Expr::Column(column) => column,
+            _ => {
+                return Err(Error::Plan(format!(
+                    "fill functions can only be applied to aggregates: {}",
+                    expr
+                )))
+            }
+        };
+
+        match self.fills.iter().find(|(c, _)| c == column) {
+            Some((_, existing)) if existing != &fill => Err(Error::Plan(format!(
+                "{} is filled with both {} and {}",
+                column.flat_name(),
+                existing,
+                fill
+            ))),
+            Some(_) => Ok(input.clone()),
+            None => {
+                self.fills.push((column.clone(), fill));
+                Ok(input.clone())
+            }
+        }
+    }
+}
+
+fn can_interpolate(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Float64 | DataType::Int64 | DataType::UInt64
+    )
+}
+
+// ------ The implementation of GapFill code follows -----
+
+/// Physical operator that implements the GapFill operation
+pub struct GapFillExec {
+    input: Arc<dyn ExecutionPlan>,
+    /// Indexes of the input columns identifying a series
+    series_columns: Vec<usize>,
+    /// Index of the input time column
+    time_column: usize,
+    params: GapFillParams,
+    /// The fill modes and the index of the column each fills
+    fills: Vec<(usize, FillMode)>,
+    /// Memory of the query the buffered input and output are reserved
+    /// from, if limited
+    memory: Option<Arc<QueryMemory>>,
+    /// Execution metrics
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl GapFillExec {
+    pub fn new(
+        input: Arc<dyn ExecutionPlan>,
+        series_columns: Vec<usize>,
+        time_column: usize,
+        params: GapFillParams,
+        fills: Vec<(usize, FillMode)>,
+    ) -> Self {
+        Self {
+            input,
+            series_columns,
+            time_column,
+            params,
+            fills,
+            memory: None,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+
+    /// Returns a copy of this operator that reserves the rows it buffers
+    /// from `memory`, failing the query once it can not reserve more
+    pub fn with_memory(&self, memory: Arc<QueryMemory>) -> Self {
+        Self {
+            memory: Some(memory),
+            ..Self::new(
+                Arc::clone(&self.input),
+                self.series_columns.clone(),
+                self.time_column,
+                self.params.clone(),
+                self.fills.clone(),
+            )
+        }
+    }
+}
+
+impl Debug for GapFillExec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GapFillExec")
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for GapFillExec {
+    fn as_any(&self) -> &(dyn std::any::Any + 'static) {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    /// Series must not be split across partitions
+    fn required_child_distribution(&self) -> Distribution {
+        Distribution::SinglePartition
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![Arc::clone(&self.input)]
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self {
+                memory: self.memory.clone(),
+                ..Self::new(
+                    Arc::clone(&children[0]),
+                    self.series_columns.clone(),
+                    self.time_column,
+                    self.params.clone(),
+                    self.fills.clone(),
+                )
+            })),
+            _ => Err(Error::Internal(
+                "GapFillExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    /// Execute one partition and return an iterator over RecordBatch
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        if partition != 0 {
+            return Err(Error::Internal(format!(
+                "GapFillExec invalid partition {}",
+                partition
+            )));
+        }
+
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+        let reservation = self
+            .memory
+            .as_ref()
+            .map(|m| MemoryReservation::new(Arc::clone(m)));
+        let input_stream = self.input.execute(partition).await?;
+
+        let (tx, rx) = mpsc::channel(1);
+
+        let task = tokio::task::spawn(gap_fill(
+            input_stream,
+            self.schema(),
+            self.series_columns.clone(),
+            self.time_column,
+            self.params.clone(),
+            self.fills.clone(),
+            baseline_metrics,
+            reservation,
+            tx.clone(),
+        ));
+
+        // A second task watches the output of the worker task
+        tokio::task::spawn(async move {
+            let msg = match task.await {
+                Err(join_err) => {
+                    debug!(e=%join_err, "Error joining gap_fill task");
+                    Some(ArrowError::ExternalError(Box::new(join_err)))
+                }
+                Ok(Err(e)) => {
+                    debug!(%e, "Error in gap_fill task itself");
+                    Some(e)
+                }
+                Ok(Ok(())) => None,
+            };
+
+            if let Some(e) = msg {
+                // Ignore errors sending as the receiver is gone then
+                if tx.send(Err(e)).await.is_err() {
+                    debug!("gap_fill receiver hung up");
+                }
+            }
+        });
+
+        Ok(AdapterStream::adapt(self.schema(), rx))
+    }
+
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                let fills: Vec<_> = self
+                    .fills
+                    .iter()
+                    .map(|(column, fill)| format!("{}: {}", column, fill))
+                    .collect();
+                write!(
+                    f,
+                    "GapFillExec: range=[{}, {}) fills=[{}]",
+                    self.params.range.start,
+                    self.params.range.end,
+                    fills.join(", ")
+                )
+            }
+        }
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        // don't know anything about the statistics
+        Statistics::default()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn gap_fill(
+    mut input_stream: SendableRecordBatchStream,
+    schema: SchemaRef,
+    series_columns: Vec<usize>,
+    time_column: usize,
+    params: GapFillParams,
+    fills: Vec<(usize, FillMode)>,
+    baseline_metrics: BaselineMetrics,
+    mut reservation: Option<MemoryReservation>,
+    tx: mpsc::Sender<ArrowResult<RecordBatch>>,
+) -> ArrowResult<()> {
+    let to_arrow_error = |e: Error| ArrowError::ExternalError(Box::new(e));
+    let mut reserve = |size: usize| match reservation.as_mut() {
+        Some(reservation) => reservation
+            .try_resize(size)
+            .map_err(|e| ArrowError::ExternalError(Box::new(e))),
+        None => Ok(()),
+    };
+
+    // The input is buffered as interpolation needs the next value of a
+    // series. It is the output of an aggregate, so usually small.
+    let mut batches = vec![];
+    let mut input_size = 0;
+    while let Some(batch) = input_stream.next().await.transpose()? {
+        input_size += batch_memory_size(&batch);
+        reserve(input_size)?;
+        batches.push(batch);
+    }
+
+    let timer = baseline_metrics.elapsed_compute().timer();
+    let batch = RecordBatch::concat(&schema, &batches)?;
+    std::mem::drop(batches);
+    if batch.num_rows() == 0 {
+        return Ok(());
+    }
+    let input_size = batch_memory_size(&batch);
+    reserve(input_size)?;
+
+    let window_times = params.window_times().map_err(to_arrow_error)?;
+    let times = cast(batch.column(time_column), &TIME_DATA_TYPE())?;
+    let times = times
+        .as_any()
+        .downcast_ref::<TimestampNanosecondArray>()
+        .expect("cast to timestamp");
+    let series_ranges = series_ranges(&batch, &series_columns)?;
+    timer.done();
+
+    // The output is built for whole series, as fill modes depend on the
+    // other rows of a series, and sent in batches of OUTPUT_BATCH_SIZE rows
+    let mut rows = vec![];
+    for (idx, series) in series_ranges.iter().enumerate() {
+        let timer = baseline_metrics.elapsed_compute().timer();
+        fill_series(times, &window_times, series.clone(), &mut rows);
+        if rows.len() < OUTPUT_BATCH_SIZE && idx + 1 < series_ranges.len() {
+            timer.done();
+            continue;
+        }
+
+        let output = fill_gaps(&batch, &series_columns, time_column, &fills, &rows)
+            .map_err(to_arrow_error)?;
+        rows.clear();
+        reserve(input_size + batch_memory_size(&output))?;
+        timer.done();
+
+        for offset in (0..output.num_rows()).step_by(OUTPUT_BATCH_SIZE) {
+            let len = OUTPUT_BATCH_SIZE.min(output.num_rows() - offset);
+            let output_batch = output.slice(offset, len).record_output(&baseline_metrics);
+            if tx.send(Ok(output_batch)).await.is_err() {
+                debug!("gap_fill receiver hung up");
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A row of the output of a GapFill node
+#[derive(Debug)]
+struct OutputRow {
+    time: Option<i64>,
+
+    /// The input row, or `None` for an inserted row
+    row: Option<usize>,
+
+    /// The first input row of the series
+    series_start: usize,
+}
+
+/// Returns the ranges of the rows of each series of `batch`
+fn series_ranges(batch: &RecordBatch, series_columns: &[usize]) -> ArrowResult<Vec<Range<usize>>> {
+    let series = series_columns
+        .iter()
+        .map(|index| cast(batch.column(*index), &DataType::Utf8))
+        .collect::<ArrowResult<Vec<_>>>()?;
+    let series: Vec<_> = series
+        .iter()
+        .map(|array| {
+            array
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .expect("cast to Utf8")
+        })
+        .collect();
+    let series_value = |row: usize| {
+        series
+            .iter()
+            .map(move |array| array.is_valid(row).then(|| array.value(row)))
+    };
+
+    let num_rows = batch.num_rows();
+    let mut ranges = vec![];
+    let mut series_start = 0;
+    while series_start < num_rows {
+        let series_end = (series_start + 1..num_rows)
+            .find(|row| !series_value(*row).eq(series_value(series_start)))
+            .unwrap_or(num_rows);
+        ranges.push(series_start..series_end);
+        series_start = series_end;
+    }
+    Ok(ranges)
+}
+
+/// Merges the input rows of a series with the windows, appending them to
+/// `rows`
+fn fill_series(
+    times: &TimestampNanosecondArray,
+    window_times: &[i64],
+    series: Range<usize>,
+    rows: &mut Vec<OutputRow>,
+) {
+    let series_start = series.start;
+    let mut windows = window_times.iter().copied().peekable();
+    for row in series {
+        let time = times.is_valid(row).then(|| times.value(row));
+        if let Some(time) = time {
+            while let Some(window) = windows.next_if(|window| *window < time) {
+                rows.push(OutputRow {
+                    time: Some(window),
+                    row: None,
+                    series_start,
+                });
+            }
+            windows.next_if_eq(&time);
+        }
+        rows.push(OutputRow {
+            time,
+            row: Some(row),
+            series_start,
+        });
+    }
+    rows.extend(windows.map(|window| OutputRow {
+        time: Some(window),
+        row: None,
+        series_start,
+    }));
+}
+
+/// Builds the output `rows` from the rows of `batch`
+fn fill_gaps(
+    batch: &RecordBatch,
+    series_columns: &[usize],
+    time_column: usize,
+    fills: &[(usize, FillMode)],
+    rows: &[OutputRow],
+) -> Result<RecordBatch> {
+    let columns = batch
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            if index == time_column {
+                let times: TimestampNanosecondArray = rows.iter().map(|r| r.time).collect();
+                Ok(cast(&(Arc::new(times) as ArrayRef), column.data_type())?)
+            } else if series_columns.contains(&index) {
+                let indices: UInt32Array =
+                    rows.iter().map(|r| Some(r.series_start as u32)).collect();
+                Ok(take(column.as_ref(), &indices, None)?)
+            } else {
+                let fill = fills
+                    .iter()
+                    .find(|(fill_index, _)| *fill_index == index)
+                    .map_or(&FillMode::Null, |(_, fill)| fill);
+                fill_column(column, fill, rows)
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(RecordBatch::try_new(batch.schema(), columns)?)
+}
+
+/// Returns the values of `column` for the output `rows`
+fn fill_column(column: &ArrayRef, fill: &FillMode, rows: &[OutputRow]) -> Result<ArrayRef> {
+    match fill {
+        FillMode::Null => {
+            let indices: UInt32Array = rows.iter().map(|r| r.row.map(|row| row as u32)).collect();
+            Ok(take(column.as_ref(), &indices, None)?)
+        }
+        FillMode::Previous => {
+            // the series and index of the last non-NULL value
+            let mut previous: Option<(usize, usize)> = None;
+            let indices: UInt32Array = rows
+                .iter()
+                .map(|r| {
+                    let previous_row = previous
+                        .filter(|(series_start, _)| *series_start == r.series_start)
+                        .map(|(_, row)| row);
+                    match r.row {
+                        Some(row) => {
+                            if column.is_valid(row) {
+                                previous = Some((r.series_start, row));
+                            }
+                            Some(row as u32)
+                        }
+                        None => previous_row.map(|row| row as u32),
+                    }
+                })
+                .collect();
+            Ok(take(column.as_ref(), &indices, None)?)
+        }
+        FillMode::Constant(value) => {
+            let values = rows
+                .iter()
+                .map(|r| match r.row {
+                    Some(row) => ScalarValue::try_from_array(column, row),
+                    None => Ok(value.clone()),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            ScalarValue::iter_to_array(values)
+        }
+        FillMode::Linear => interpolate(column, rows),
+    }
+}
+
+/// Returns the values of `column` for the output `rows`, interpolating
+/// the values of inserted rows between the surrounding values of their
+/// series
+fn interpolate(column: &ArrayRef, rows: &[OutputRow]) -> Result<ArrayRef> {
+    let values = cast(column, &DataType::Float64)?;
+    let values = values
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .expect("cast to Float64");
+
+    // the series, time and value of a row with a value
+    let point = |r: &OutputRow| match (r.row, r.time) {
+        (Some(row), Some(time)) if values.is_valid(row) => {
+            Some((r.series_start, time, values.value(row)))
+        }
+        _ => None,
+    };
+    let same_series = |point: Option<(usize, i64, f64)>, r: &OutputRow| {
+        point
+            .filter(|(series_start, _, _)| *series_start == r.series_start)
+            .map(|(_, time, value)| (time, value))
+    };
+
+    let mut next = vec![None; rows.len()];
+    let mut next_point = None;
+    for (i, r) in rows.iter().enumerate().rev() {
+        next[i] = same_series(next_point, r);
+        next_point = point(r).or(next_point);
+    }
+
+    let mut previous_point = None;
+    let values = rows
+        .iter()
+        .zip(next)
+        .map(|(r, next)| {
+            let value = match r.row {
+                Some(row) => ScalarValue::try_from_array(column, row)?,
+                None => {
+                    let value = match (same_series(previous_point, r), next, r.time) {
+                        (Some((t0, v0)), Some((t1, v1)), Some(t)) => {
+                            Some(v0 + (v1 - v0) * (t - t0) as f64 / (t1 - t0) as f64)
+                        }
+                        _ => None,
+                    };
+                    match column.data_type() {
+                        DataType::Float64 => ScalarValue::Float64(value),
+                        DataType::Int64 => ScalarValue::Int64(value.map(|v| v.round() as i64)),
+                        DataType::UInt64 => ScalarValue::UInt64(value.map(|v| v.round() as u64)),
+                        data_type => {
+                            return Err(Error::Internal(format!(
+                                "can not interpolate values of type {:?}",
+                                data_type
+                            )))
+                        }
+                    }
+                }
+            };
+            previous_point = point(r).or(previous_point);
+            Ok(value)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    ScalarValue::iter_to_array(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{DictionaryArray, Int64Array},
+        datatypes::{Field, Int32Type, Schema as ArrowSchema},
+    };
+    use arrow_util::assert_batches_eq;
+    use datafusion::physical_plan::{collect, memory::MemoryExec};
+
+    use crate::exec::memory::{is_memory_exhausted, MemoryConfig, MemoryPool};
+
+    #[tokio::test]
+    async fn test_fill_modes() {
+        // series a continues in the second batch, b is missing its first
+        // window and c is not in the range at all
+        let batch1 = make_batch(&["a", "a"], &[0, 30], &[Some(10), Some(40)]);
+        let batch2 = make_batch(&["a", "b", "b"], &[40, 10, 20], &[None, Some(5), Some(6)]);
+
+        let fills = vec![
+            (2, FillMode::Null),
+            (3, FillMode::Previous),
+            (4, FillMode::Linear),
+            (5, FillMode::Constant(ScalarValue::Int64(Some(0)))),
+        ];
+        let results = run(vec![batch1, batch2], TimestampRange::new(0, 40), fills).await;
+
+        let expected = vec![
+            "+------+--------------------------------+------+----------+--------+----------+",
+            "| host | time                           | null | previous | linear | constant |",
+            "+------+--------------------------------+------+----------+--------+----------+",
+            "| a    | 1970-01-01T00:00:00Z           | 10   | 10       | 10     | 10       |",
+            "| a    | 1970-01-01T00:00:00.000000010Z |      | 10       | 20     | 0        |",
+            "| a    | 1970-01-01T00:00:00.000000020Z |      | 10       | 30     | 0        |",
+            "| a    | 1970-01-01T00:00:00.000000030Z | 40   | 40       | 40     | 40       |",
+            "| a    | 1970-01-01T00:00:00.000000040Z |      |          |        |          |",
+            "| b    | 1970-01-01T00:00:00Z           |      |          |        | 0        |",
+            "| b    | 1970-01-01T00:00:00.000000010Z | 5    | 5        | 5      | 5        |",
+            "| b    | 1970-01-01T00:00:00.000000020Z | 6    | 6        | 6      | 6        |",
+            "| b    | 1970-01-01T00:00:00.000000030Z |      | 6        |        | 0        |",
+            "+------+--------------------------------+------+----------+--------+----------+",
+        ];
+        assert_batches_eq!(&expected, &results);
+    }
+
+    #[tokio::test]
+    async fn test_empty_range() {
+        let batch = make_batch(&["a", "a"], &[0, 30], &[Some(10), Some(40)]);
+        let results = run(vec![batch], TimestampRange::new(0, 0), vec![]).await;
+
+        let expected = vec![
+            "+------+--------------------------------+------+----------+--------+----------+",
+            "| host | time                           | null | previous | linear | constant |",
+            "+------+--------------------------------+------+----------+--------+----------+",
+            "| a    | 1970-01-01T00:00:00Z           | 10   | 10       | 10     | 10       |",
+            "| a    | 1970-01-01T00:00:00.000000030Z | 40   | 40       | 40     | 40       |",
+            "+------+--------------------------------+------+----------+--------+----------+",
+        ];
+        assert_batches_eq!(&expected, &results);
+    }
+
+    #[tokio::test]
+    async fn test_output_batches() {
+        // A series with more windows than fit into one output batch
+        let batch = make_batch(&["a"], &[0], &[Some(10)]);
+        let range = TimestampRange::new(0, 10 * (OUTPUT_BATCH_SIZE as i64 + 10));
+        let results = run(vec![batch], range, vec![]).await;
+        let rows: Vec<_> = results.iter().map(|batch| batch.num_rows()).collect();
+        assert_eq!(rows, vec![OUTPUT_BATCH_SIZE, 10]);
+
+        // Small series are output together
+        let batch = make_batch(&["a", "b", "c"], &[0, 0, 0], &[None, None, None]);
+        let results = run(vec![batch], TimestampRange::new(0, 40), vec![]).await;
+        let rows: Vec<_> = results.iter().map(|batch| batch.num_rows()).collect();
+        assert_eq!(rows, vec![12]);
+    }
+
+    #[tokio::test]
+    async fn test_memory_limit() {
+        let pool = Arc::new(MemoryPool::new(MemoryConfig {
+            pool_size: None,
+            query_limit: Some(1),
+            spill_dir: None,
+        }));
+        let memory = Arc::new(QueryMemory::new(pool));
+
+        let batch = make_batch(&["a", "a"], &[0, 30], &[Some(10), Some(40)]);
+        let exec = gap_fill_exec(vec![batch], TimestampRange::new(0, 40), vec![]);
+        let exec = Arc::new(exec.with_memory(Arc::clone(&memory)));
+
+        let err = collect(exec).await.unwrap_err();
+        assert!(is_memory_exhausted(&err), "{}", err);
+        assert_eq!(memory.used(), 0);
+    }
+
+    #[test]
+    fn test_time_range() {
+        let time = Column::from_name("time");
+        let scan = LogicalPlanBuilder::empty(false).build().unwrap();
+        let filter = |predicate: Expr| LogicalPlan::Filter {
+            predicate,
+            input: Arc::new(scan.clone()),
+        };
+        let time_col = || Expr::Column(time.clone());
+        let ts = |s: &str| Expr::Literal(ScalarValue::Utf8(Some(s.to_string())));
+
+        let plan = filter(
+            time_col()
+                .gt_eq(ts("1970-01-01T00:00:01Z"))
+                .and(time_col().lt(ts("1970-01-01T00:00:05Z"))),
+        );
+        assert_eq!(
+            time_range(&plan, &time).unwrap(),
+            TimestampRange::new(1_000_000_000, 5_000_000_000)
+        );
+
+        let plan = filter(Expr::Between {
+            expr: Box::new(time_col()),
+            negated: false,
+            low: Box::new(Expr::Literal(ScalarValue::Int64(Some(10)))),
+            high: Box::new(Expr::Literal(ScalarValue::Int64(Some(20)))),
+        });
+        assert_eq!(
+            time_range(&plan, &time).unwrap(),
+            TimestampRange::new(10, 21)
+        );
+
+        let plan = filter(Expr::Literal(ScalarValue::Int64(Some(10))).lt(time_col()));
+        let err = time_range(&plan, &time).unwrap_err();
+        assert!(
+            err.to_string().contains("requires lower and upper bounds"),
+            "{}",
+            err
+        );
+    }
+
+    fn make_batch(hosts: &[&str], times: &[i64], values: &[Option<i64>]) -> RecordBatch {
+        let hosts: DictionaryArray<Int32Type> = hosts.iter().copied().collect();
+        let times = TimestampNanosecondArray::from_vec(times.to_vec(), None);
+        let values: ArrayRef = Arc::new(values.iter().copied().collect::<Int64Array>());
+        RecordBatch::try_new(
+            make_schema(),
+            vec![
+                Arc::new(hosts),
+                Arc::new(times),
+                Arc::clone(&values),
+                Arc::clone(&values),
+                Arc::clone(&values),
+                values,
+            ],
+        )
+        .unwrap()
+    }
+
+    fn make_schema() -> SchemaRef {
+        let dictionary = DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+        Arc::new(ArrowSchema::new(vec![
+            Field::new("host", dictionary, true),
+            Field::new("time", TIME_DATA_TYPE(), false),
+            Field::new("null", DataType::Int64, true),
+            Field::new("previous", DataType::Int64, true),
+            Field::new("linear", DataType::Int64, true),
+            Field::new("constant", DataType::Int64, true),
+        ]))
+    }
+
+    fn gap_fill_exec(
+        batches: Vec<RecordBatch>,
+        range: TimestampRange,
+        fills: Vec<(usize, FillMode)>,
+    ) -> GapFillExec {
+        let input = Arc::new(MemoryExec::try_new(&[batches], make_schema(), None).unwrap());
+        let params = GapFillParams {
+            every: WindowDuration::from_nanoseconds(10),
+            offset: WindowDuration::empty(),
+            range,
+            bound: WindowBound::Start,
+        };
+        GapFillExec::new(input, vec![0], 1, params, fills)
+    }
+
+    async fn run(
+        batches: Vec<RecordBatch>,
+        range: TimestampRange,
+        fills: Vec<(usize, FillMode)>,
+    ) -> Vec<RecordBatch> {
+        let exec = Arc::new(gap_fill_exec(batches, range, fills));
+        collect(exec).await.unwrap()
+    }
+}
@@ -0,0 +1,392 @@
+//! This module limits the memory used by queries run by an
+//! [`IOxExecutionContext`](super::IOxExecutionContext)
+//!
+//! The memory of all queries of an [`Executor`](super::Executor) is drawn
+//! from a shared [`MemoryPool`], and each query may additionally be limited
+//! on its own. Operators buffering their input reserve memory before doing
+//! so: the [`SpillableSortExec`] spills sorted runs to disk once it can not
+//! reserve more, while deduplication and gap filling do not spill and fail
+//! the query with a [`MemoryExhausted`] error.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use arrow::record_batch::RecordBatch;
+use datafusion::{
+    error::Result as DataFusionResult,
+    execution::context::ExecutionConfig,
+    physical_optimizer::optimizer::PhysicalOptimizerRule,
+    physical_plan::{sort::SortExec, ExecutionPlan},
+};
+
+use crate::{
+    exec::{gapfill::GapFillExec, sort::SpillableSortExec},
+    provider::DeduplicateExec,
+};
+
+use super::deadline::caused_by;
+
+/// Configures the memory available to queries
+#[derive(Debug, Clone, Default)]
+pub struct MemoryConfig {
+    /// Memory available to all running queries together, in bytes
+    pub pool_size: Option<usize>,
+
+    /// Memory available to any single query, in bytes
+    pub query_limit: Option<usize>,
+
+    /// Directory sorts spill to once they exceed their memory. If not set,
+    /// such queries fail instead.
+    pub spill_dir: Option<PathBuf>,
+}
+
+impl MemoryConfig {
+    /// Returns true if the memory of queries is limited
+    pub fn is_limited(&self) -> bool {
+        self.pool_size.is_some() || self.query_limit.is_some()
+    }
+}
+
+/// The error a query fails with when it requires more memory than it is
+/// allowed and can not spill
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryExhausted {
+    requested: usize,
+    limit: usize,
+    scope: &'static str,
+}
+
+impl fmt::Display for MemoryExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Query exceeded the {} memory limit of {} bytes, requiring {} bytes",
+            self.scope, self.limit, self.requested
+        )
+    }
+}
+
+impl std::error::Error for MemoryExhausted {}
+
+/// Returns true if `err`, or any error it was caused by, is a
+/// [`MemoryExhausted`]
+pub fn is_memory_exhausted(err: &(dyn std::error::Error + 'static)) -> bool {
+    caused_by::<MemoryExhausted>(err)
+}
+
+/// Returns the memory used by the arrays of `batch`, in bytes
+pub(crate) fn batch_memory_size(batch: &RecordBatch) -> usize {
+    batch
+        .columns()
+        .iter()
+        .map(|array| array.get_array_memory_size())
+        .sum()
+}
+
+/// Adds `bytes` to `used` unless that exceeds `limit`, in which case the
+/// limit is returned
+fn try_grow(used: &AtomicUsize, bytes: usize, limit: Option<usize>) -> Result<(), usize> {
+    match limit {
+        Some(limit) => used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|used| *used <= limit)
+            })
+            .map(|_| ())
+            .map_err(|_| limit),
+        None => {
+            used.fetch_add(bytes, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+}
+
+/// The memory shared by all queries of an [`Executor`](super::Executor)
+#[derive(Debug)]
+pub struct MemoryPool {
+    config: MemoryConfig,
+    used: AtomicUsize,
+}
+
+impl MemoryPool {
+    pub fn new(config: MemoryConfig) -> Self {
+        Self {
+            config,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &MemoryConfig {
+        &self.config
+    }
+
+    /// Memory currently reserved by all queries, in bytes
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    fn try_grow(&self, bytes: usize) -> Result<(), MemoryExhausted> {
+        try_grow(&self.used, bytes, self.config.pool_size).map_err(|limit| MemoryExhausted {
+            requested: self.used() + bytes,
+            limit,
+            scope: "server",
+        })
+    }
+
+    fn shrink(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+/// The memory of a single query, drawn from a [`MemoryPool`] and shared by
+/// all of its operators
+#[derive(Debug)]
+pub struct QueryMemory {
+    pool: Arc<MemoryPool>,
+    used: AtomicUsize,
+}
+
+impl QueryMemory {
+    pub fn new(pool: Arc<MemoryPool>) -> Self {
+        Self {
+            pool,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Memory currently reserved by this query, in bytes
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// The directory to spill to, if spilling is enabled
+    pub fn spill_dir(&self) -> Option<&Path> {
+        self.pool.config.spill_dir.as_deref()
+    }
+
+    fn try_grow(&self, bytes: usize) -> Result<(), MemoryExhausted> {
+        try_grow(&self.used, bytes, self.pool.config.query_limit).map_err(|limit| {
+            MemoryExhausted {
+                requested: self.used() + bytes,
+                limit,
+                scope: "query",
+            }
+        })?;
+
+        self.pool.try_grow(bytes).map_err(|e| {
+            self.used.fetch_sub(bytes, Ordering::Relaxed);
+            e
+        })
+    }
+
+    fn shrink(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::Relaxed);
+        self.pool.shrink(bytes);
+    }
+}
+
+/// Memory reserved by an operator of a query, which is released once
+/// dropped
+#[derive(Debug)]
+pub struct MemoryReservation {
+    memory: Arc<QueryMemory>,
+    size: usize,
+}
+
+impl MemoryReservation {
+    pub fn new(memory: Arc<QueryMemory>) -> Self {
+        Self { memory, size: 0 }
+    }
+
+    /// The reserved memory, in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Grows or shrinks this reservation to `size` bytes, failing if the
+    /// query or server do not have enough memory left
+    pub fn try_resize(&mut self, size: usize) -> Result<(), MemoryExhausted> {
+        if size > self.size {
+            self.memory.try_grow(size - self.size)?;
+        } else {
+            self.memory.shrink(self.size - size);
+        }
+        self.size = size;
+        Ok(())
+    }
+
+    /// Releases all reserved memory
+    pub fn free(&mut self) {
+        self.memory.shrink(self.size);
+        self.size = 0;
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.free()
+    }
+}
+
+/// Physical optimizer rule limiting the memory of a query: sorts are
+/// replaced by [`SpillableSortExec`]s, and deduplication and gap filling
+/// account for the rows they buffer
+pub(crate) struct MemoryLimitRule {
+    memory: Arc<QueryMemory>,
+}
+
+impl MemoryLimitRule {
+    pub(crate) fn new(memory: Arc<QueryMemory>) -> Self {
+        Self { memory }
+    }
+}
+
+impl PhysicalOptimizerRule for MemoryLimitRule {
+    fn optimize(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+        config: &ExecutionConfig,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let children = plan.children();
+        let new_children = children
+            .iter()
+            .map(|child| self.optimize(Arc::clone(child), config))
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        // Only recreate nodes whose children changed
+        let changed = children
+            .iter()
+            .zip(&new_children)
+            .any(|(child, new_child)| !Arc::ptr_eq(child, new_child));
+        let plan = if changed {
+            plan.with_new_children(new_children)?
+        } else {
+            plan
+        };
+
+        if let Some(sort) = plan.as_any().downcast_ref::<SortExec>() {
+            // A sort that does not preserve the partitioning of its input
+            // requires a single input partition, so both can be sorted
+            // partition by partition
+            let partitions = sort.output_partitioning().partition_count();
+            if sort.input().output_partitioning().partition_count() == partitions {
+                return Ok(Arc::new(SpillableSortExec::new(
+                    sort.expr().to_vec(),
+                    Arc::clone(sort.input()),
+                    Arc::clone(&self.memory),
+                )));
+            }
+        }
+
+        if let Some(dedup) = plan.as_any().downcast_ref::<DeduplicateExec>() {
+            return Ok(Arc::new(dedup.with_memory(Arc::clone(&self.memory))));
+        }
+
+        if let Some(gap_fill) = plan.as_any().downcast_ref::<GapFillExec>() {
+            return Ok(Arc::new(gap_fill.with_memory(Arc::clone(&self.memory))));
+        }
+
+        Ok(plan)
+    }
+
+    fn name(&self) -> &str {
+        "memory_limit"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::physical_plan::{
+        expressions::{col, PhysicalSortExpr},
+        memory::MemoryExec,
+    };
+
+    use super::*;
+
+    fn memory(pool_size: Option<usize>, query_limit: Option<usize>) -> Arc<QueryMemory> {
+        let pool = Arc::new(MemoryPool::new(MemoryConfig {
+            pool_size,
+            query_limit,
+            spill_dir: None,
+        }));
+        Arc::new(QueryMemory::new(pool))
+    }
+
+    #[test]
+    fn test_query_limit() {
+        let memory = memory(None, Some(100));
+
+        let mut r1 = MemoryReservation::new(Arc::clone(&memory));
+        let mut r2 = MemoryReservation::new(Arc::clone(&memory));
+        r1.try_resize(60).unwrap();
+
+        let err = r2.try_resize(50).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Query exceeded the query memory limit of 100 bytes, requiring 110 bytes"
+        );
+        assert!(is_memory_exhausted(&err));
+        assert_eq!(r2.size(), 0);
+        assert_eq!(memory.used(), 60);
+
+        r1.try_resize(10).unwrap();
+        r2.try_resize(50).unwrap();
+        assert_eq!(memory.used(), 60);
+
+        drop(r1);
+        r2.free();
+        assert_eq!(memory.used(), 0);
+        assert_eq!(memory.pool.used(), 0);
+    }
+
+    #[test]
+    fn test_pool_size() {
+        let q1 = memory(Some(100), Some(80));
+        let q2 = Arc::new(QueryMemory::new(Arc::clone(&q1.pool)));
+
+        let mut r1 = MemoryReservation::new(Arc::clone(&q1));
+        let mut r2 = MemoryReservation::new(Arc::clone(&q2));
+        r1.try_resize(80).unwrap();
+        r2.try_resize(20).unwrap();
+
+        let err = r2.try_resize(30).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Query exceeded the server memory limit of 100 bytes, requiring 110 bytes"
+        );
+
+        // the failed reservation is not accounted to the query
+        assert_eq!(q2.used(), 20);
+        assert_eq!(q1.pool.used(), 100);
+
+        drop(r1);
+        r2.try_resize(30).unwrap();
+        assert_eq!(q1.pool.used(), 30);
+    }
+
+    #[test]
+    fn test_memory_limit_rule() {
+        let schema = Arc::new(Schema::new(vec![Field::new("t", DataType::Utf8, false)]));
+        let sort_keys = vec![PhysicalSortExpr {
+            expr: col("t", &schema).unwrap(),
+            options: Default::default(),
+        }];
+
+        let input = Arc::new(MemoryExec::try_new(&[vec![]], schema, None).unwrap());
+        let sort = Arc::new(SortExec::try_new(sort_keys.clone(), input).unwrap());
+        let dedup = Arc::new(DeduplicateExec::new(sort, sort_keys));
+
+        let rule = MemoryLimitRule::new(memory(None, Some(100)));
+        let plan = rule.optimize(dedup, &ExecutionConfig::new()).unwrap();
+
+        assert!(plan.as_any().is::<DeduplicateExec>());
+        assert!(plan.children()[0].as_any().is::<SpillableSortExec>());
+        assert!(plan.children()[0].children()[0].as_any().is::<MemoryExec>());
+    }
+}
@@ -0,0 +1,791 @@
+//! This module contains the "SeriesTransform" DataFusion extension plan
+//! node, which computes the time series transformations of
+//! [`crate::func::transform`] per series.
+//!
+//! A SeriesTransform node takes an input sorted by the columns
+//! identifying a series and then by time, and appends one column per
+//! transformation. For example `difference(usage)` on this input
+//!
+//!  host | time | usage
+//! ------+------+-------
+//!   a   |  1   |  1.0
+//!   a   |  2   |  3.0
+//!   b   |  1   |  5.0
+//!   b   |  3   |  4.0
+//!
+//! produces
+//!
+//!  host | time | usage | __iox_transform_0
+//! ------+------+-------+-------------------
+//!   a   |  1   |  1.0  |  NULL
+//!   a   |  2   |  3.0  |  2.0
+//!   b   |  1   |  5.0  |  NULL
+//!   b   |  3   |  4.0  |  -1.0
+//!
+//! [`plan_series_transforms`] replaces the transform function calls of a
+//! SQL query with SeriesTransform nodes. Series are identified by the tag
+//! columns of the queried table and sorted in the same order as the
+//! series of `InfluxRpcPlanner::read_filter`.
+
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::HashSet,
+    fmt::{self, Debug},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+
+use arrow::{
+    array::{Array, ArrayRef, StringArray, TimestampNanosecondArray},
+    compute::cast,
+    datatypes::{DataType, SchemaRef},
+    error::{ArrowError, Result as ArrowResult},
+    record_batch::RecordBatch,
+};
+use datafusion::{
+    error::{DataFusionError as Error, Result},
+    logical_plan::{
+        col, plan::Extension, Column, DFField, DFSchema, DFSchemaRef, Expr, ExprRewriter,
+        LogicalPlan, LogicalPlanBuilder, UserDefinedLogicalNode,
+    },
+    optimizer::utils,
+    physical_plan::{
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet, RecordOutput},
+        DisplayFormatType, Distribution, ExecutionPlan, Partitioning, SendableRecordBatchStream,
+        Statistics,
+    },
+};
+use datafusion_util::AdapterStream;
+use observability_deps::tracing::debug;
+use schema::{sort::SortKey, Schema, TIME_COLUMN_NAME, TIME_DATA_TYPE};
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+
+use crate::{
+    frontend::influxrpc::series_sort_key,
+    func::transform::{SeriesTransformer, Transform},
+};
+
+/// Prefix of the columns appended by SeriesTransform nodes
+const OUTPUT_COLUMN_PREFIX: &str = "__iox_transform_";
+
+/// Prefix of the columns holding the values to transform
+const INPUT_COLUMN_PREFIX: &str = "__iox_transform_input_";
+
+/// A transformation computed by a SeriesTransform node
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformColumn {
+    pub transform: Transform,
+
+    /// The column with the values to transform
+    pub input: Column,
+
+    /// The name of the output column
+    pub output: String,
+}
+
+/// Implements the SeriesTransform operation as described in this module's
+/// documentation
+pub struct SeriesTransformNode {
+    input: LogicalPlan,
+
+    /// The columns identifying a series
+    series_columns: Vec<Column>,
+
+    time_column: Column,
+
+    transforms: Vec<TransformColumn>,
+
+    /// The input schema followed by the output columns
+    schema: DFSchemaRef,
+
+    /// The columns used by this node, so that they are not optimized away
+    exprs: Vec<Expr>,
+}
+
+impl SeriesTransformNode {
+    /// Create a node computing `transforms` per series. `input` must be
+    /// sorted by `series_columns` and then by `time_column`.
+    pub fn try_new(
+        input: LogicalPlan,
+        series_columns: Vec<Column>,
+        time_column: Column,
+        transforms: Vec<TransformColumn>,
+    ) -> Result<Self> {
+        let input_schema = input.schema();
+
+        let output_fields = transforms
+            .iter()
+            .map(|t| {
+                let input_field = &input_schema.fields()[input_schema.index_of_column(&t.input)?];
+                let data_type = t.transform.return_type(input_field.data_type());
+                Ok(DFField::new(None, &t.output, data_type, true))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let fields = input_schema
+            .fields()
+            .iter()
+            .cloned()
+            .chain(output_fields)
+            .collect();
+        let schema = Arc::new(DFSchema::new(fields)?);
+
+        let exprs = series_columns
+            .iter()
+            .chain(std::iter::once(&time_column))
+            .chain(transforms.iter().map(|t| &t.input))
+            .map(|column| Expr::Column(column.clone()))
+            .collect();
+
+        Ok(Self {
+            input,
+            series_columns,
+            time_column,
+            transforms,
+            schema,
+            exprs,
+        })
+    }
+
+    pub fn series_columns(&self) -> &[Column] {
+        &self.series_columns
+    }
+
+    pub fn time_column(&self) -> &Column {
+        &self.time_column
+    }
+
+    pub fn transforms(&self) -> &[TransformColumn] {
+        &self.transforms
+    }
+}
+
+impl Debug for SeriesTransformNode {
+    /// Use explain format for the Debug format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_for_explain(f)
+    }
+}
+
+impl UserDefinedLogicalNode for SeriesTransformNode {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        self.exprs.clone()
+    }
+
+    /// For example: `SeriesTransform series=[#cpu.host] time=#cpu.time transforms=[difference(#u) as __iox_transform_0]`
+    fn fmt_for_explain(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let series: Vec<_> = self.series_columns.iter().map(|c| c.flat_name()).collect();
+        let transforms: Vec<_> = self
+            .transforms
+            .iter()
+            .map(|t| format!("{}(#{}) as {}", t.transform, t.input.flat_name(), t.output))
+            .collect();
+        write!(
+            f,
+            "SeriesTransform series=[{}] time=#{} transforms=[{}]",
+            series.join(", "),
+            self.time_column.flat_name(),
+            transforms.join(", ")
+        )
+    }
+
+    fn from_template(
+        &self,
+        exprs: &[Expr],
+        inputs: &[LogicalPlan],
+    ) -> Arc<dyn UserDefinedLogicalNode + Send + Sync> {
+        assert_eq!(inputs.len(), 1, "SeriesTransform: input sizes inconsistent");
+        assert_eq!(
+            exprs.len(),
+            self.exprs.len(),
+            "SeriesTransform: expression sizes inconsistent"
+        );
+        // The schema is recomputed as the optimizer may have removed unused
+        // columns from the input
+        Arc::new(
+            Self::try_new(
+                inputs[0].clone(),
+                self.series_columns.clone(),
+                self.time_column.clone(),
+                self.transforms.clone(),
+            )
+            .expect("input has the columns used by SeriesTransform"),
+        )
+    }
+}
+
+/// Replaces the calls to the functions of [`crate::func::transform`] in
+/// the projections of `plan` with [`SeriesTransformNode`]s computing them
+/// per series
+///
+/// Series are identified by the tags of the table the projection selects
+/// from, or by the dictionary encoded columns of other inputs, such as
+/// subqueries keeping the tag columns, and ordered by the `time` column.
+pub fn plan_series_transforms(plan: &LogicalPlan) -> Result<LogicalPlan> {
+    Ok(rewrite_plan(plan)?.unwrap_or_else(|| plan.clone()))
+}
+
+/// Returns the rewritten plan, or `None` if `plan` does not call any
+/// transform functions
+fn rewrite_plan(plan: &LogicalPlan) -> Result<Option<LogicalPlan>> {
+    let inputs = plan.inputs();
+    let new_inputs = inputs
+        .iter()
+        .map(|input| rewrite_plan(input))
+        .collect::<Result<Vec<_>>>()?;
+
+    let plan = if new_inputs.iter().any(Option::is_some) {
+        let new_inputs: Vec<_> = new_inputs
+            .into_iter()
+            .zip(inputs)
+            .map(|(new_input, input)| new_input.unwrap_or_else(|| input.clone()))
+            .collect();
+        Cow::Owned(utils::from_plan(plan, &plan.expressions(), &new_inputs)?)
+    } else {
+        Cow::Borrowed(plan)
+    };
+
+    if let LogicalPlan::Projection {
+        expr, input, alias, ..
+    } = plan.as_ref()
+    {
+        if let Some(plan) = rewrite_projection(expr, input, alias.clone())? {
+            return Ok(Some(plan));
+        }
+    }
+
+    Ok(match plan {
+        Cow::Owned(plan) => Some(plan),
+        Cow::Borrowed(_) => None,
+    })
+}
+
+/// Rewrites a projection calling transform functions as a projection of
+/// the columns computed by a SeriesTransform node
+fn rewrite_projection(
+    exprs: &[Expr],
+    input: &LogicalPlan,
+    alias: Option<String>,
+) -> Result<Option<LogicalPlan>> {
+    let mut rewriter = TransformRewriter::default();
+    let new_exprs = exprs
+        .iter()
+        .map(|expr| {
+            let new_expr = expr.clone().rewrite(&mut rewriter)?;
+            // keep the name of the expression in the output
+            Ok(match expr {
+                Expr::Alias(_, _) => new_expr,
+                _ if &new_expr == expr => new_expr,
+                _ => new_expr.alias(&expr.name(input.schema())?),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if rewriter.transforms.is_empty() {
+        return Ok(None);
+    }
+
+    let sort_key = series_key(input)?;
+    let columns = sort_key
+        .iter()
+        .map(|(name, _)| {
+            let field = input.schema().field_with_unqualified_name(name)?;
+            Ok(field.qualified_column())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let (time_column, series_columns) = columns
+        .split_last()
+        .ok_or_else(|| Error::Plan("transform functions require a time column".to_string()))?;
+
+    // Evaluate the values to transform next to all columns of the input
+    let input_exprs = input
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| Expr::Column(field.qualified_column()))
+        .chain(
+            rewriter
+                .transforms
+                .iter()
+                .enumerate()
+                .map(|(i, (_, expr))| expr.clone().alias(&input_column_name(i))),
+        );
+
+    let sort_exprs = columns
+        .iter()
+        .zip(sort_key.iter())
+        .map(|(column, (_, options))| Expr::Sort {
+            expr: Box::new(Expr::Column(column.clone())),
+            asc: !options.descending,
+            nulls_first: options.nulls_first,
+        });
+
+    let sorted = LogicalPlanBuilder::from(input.clone())
+        .project(input_exprs.collect::<Vec<_>>())?
+        .sort(sort_exprs.collect::<Vec<_>>())?
+        .build()?;
+
+    let transforms = rewriter
+        .transforms
+        .iter()
+        .enumerate()
+        .map(|(i, (transform, _))| TransformColumn {
+            transform: *transform,
+            input: Column::from_name(input_column_name(i)),
+            output: output_column_name(i),
+        })
+        .collect();
+
+    let node = SeriesTransformNode::try_new(
+        sorted,
+        series_columns.to_vec(),
+        time_column.clone(),
+        transforms,
+    )?;
+
+    let plan = LogicalPlanBuilder::from(LogicalPlan::Extension(Extension {
+        node: Arc::new(node),
+    }))
+    .project_with_alias(new_exprs, alias)?
+    .build()?;
+
+    Ok(Some(plan))
+}
+
+/// Returns the columns identifying the series of `input` followed by the
+/// time column, in the order the input must be sorted
+fn series_key(input: &LogicalPlan) -> Result<SortKey<'_>> {
+    // The tags of a table are known from its IOx schema
+    let mut plan = input;
+    let table_schema = loop {
+        match plan {
+            LogicalPlan::Filter { input, .. } => plan = input.as_ref(),
+            LogicalPlan::TableScan { source, .. } => break Schema::try_from(source.schema()).ok(),
+            _ => break None,
+        }
+    };
+
+    if let Some(table_schema) = table_schema {
+        if table_schema.time_iter().next().is_some() {
+            // borrow the column names from the input schema
+            return series_sort_key(&table_schema).iter().try_fold(
+                SortKey::default(),
+                |mut sort_key, (name, options)| {
+                    let field = input.schema().field_with_unqualified_name(name)?;
+                    sort_key.push(field.name(), *options);
+                    Ok(sort_key)
+                },
+            );
+        }
+    }
+
+    let schema = input.schema();
+    let time_field = schema
+        .field_with_unqualified_name(TIME_COLUMN_NAME)
+        .map_err(|_| {
+            Error::Plan(format!(
+                "transform functions require a '{}' column",
+                TIME_COLUMN_NAME
+            ))
+        })?;
+
+    let mut sort_key = SortKey::default();
+    for field in schema.fields() {
+        if matches!(field.data_type(), DataType::Dictionary(_, _)) {
+            sort_key.with_col(field.name());
+        }
+    }
+    sort_key.with_col(time_field.name());
+    Ok(sort_key)
+}
+
+fn input_column_name(i: usize) -> String {
+    format!("{}{}", INPUT_COLUMN_PREFIX, i)
+}
+
+fn output_column_name(i: usize) -> String {
+    format!("{}{}", OUTPUT_COLUMN_PREFIX, i)
+}
+
+/// Replaces transform function calls with references to the columns
+/// computing them, collecting the transforms and their inputs
+#[derive(Debug, Default)]
+struct TransformRewriter {
+    transforms: Vec<(Transform, Expr)>,
+}
+
+impl ExprRewriter for TransformRewriter {
+    fn mutate(&mut self, expr: Expr) -> Result<Expr> {
+        let (transform, input) = match Transform::try_from_expr(&expr)? {
+            Some(call) => call,
+            None => return Ok(expr),
+        };
+
+        // calls are rewritten bottom up, so nested calls were already
+        // replaced by columns
+        let mut columns = HashSet::new();
+        utils::expr_to_columns(input, &mut columns)?;
+        if columns
+            .iter()
+            .any(|column| column.name.starts_with(OUTPUT_COLUMN_PREFIX))
+        {
+            return Err(Error::Plan(format!(
+                "transform functions can not be nested: {}",
+                expr
+            )));
+        }
+
+        self.transforms.push((transform, input.clone()));
+        Ok(col(&output_column_name(self.transforms.len() - 1)))
+    }
+}
+
+// ------ The implementation of SeriesTransform code follows -----
+
+/// Physical operator that implements the SeriesTransform operation
+pub struct SeriesTransformExec {
+    input: Arc<dyn ExecutionPlan>,
+    /// Output schema
+    schema: SchemaRef,
+    /// Indexes of the input columns identifying a series
+    series_columns: Vec<usize>,
+    /// Index of the input time column
+    time_column: usize,
+    /// The transforms and the index of the input column each transforms
+    transforms: Vec<(Transform, usize)>,
+    /// Execution metrics
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl SeriesTransformExec {
+    pub fn new(
+        input: Arc<dyn ExecutionPlan>,
+        schema: SchemaRef,
+        series_columns: Vec<usize>,
+        time_column: usize,
+        transforms: Vec<(Transform, usize)>,
+    ) -> Self {
+        Self {
+            input,
+            schema,
+            series_columns,
+            time_column,
+            transforms,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl Debug for SeriesTransformExec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SeriesTransformExec")
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for SeriesTransformExec {
+    fn as_any(&self) -> &(dyn std::any::Any + 'static) {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    /// Series must not be split across partitions
+    fn required_child_distribution(&self) -> Distribution {
+        Distribution::SinglePartition
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![Arc::clone(&self.input)]
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self::new(
+                Arc::clone(&children[0]),
+                Arc::clone(&self.schema),
+                self.series_columns.clone(),
+                self.time_column,
+                self.transforms.clone(),
+            ))),
+            _ => Err(Error::Internal(
+                "SeriesTransformExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    /// Execute one partition and return an iterator over RecordBatch
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        if partition != 0 {
+            return Err(Error::Internal(format!(
+                "SeriesTransformExec invalid partition {}",
+                partition
+            )));
+        }
+
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+        let input_stream = self.input.execute(partition).await?;
+
+        let (tx, rx) = mpsc::channel(1);
+
+        let transformers = self
+            .transforms
+            .iter()
+            .map(|(transform, column)| (SeriesTransformer::new(*transform), *column))
+            .collect();
+
+        let task = tokio::task::spawn(transform_series(
+            input_stream,
+            Arc::clone(&self.schema),
+            self.series_columns.clone(),
+            self.time_column,
+            transformers,
+            baseline_metrics,
+            tx.clone(),
+        ));
+
+        // A second task watches the output of the worker task
+        tokio::task::spawn(async move {
+            let msg = match task.await {
+                Err(join_err) => {
+                    debug!(e=%join_err, "Error joining series_transform task");
+                    Some(ArrowError::ExternalError(Box::new(join_err)))
+                }
+                Ok(Err(e)) => {
+                    debug!(%e, "Error in series_transform task itself");
+                    Some(e)
+                }
+                Ok(Ok(())) => None,
+            };
+
+            if let Some(e) = msg {
+                // Ignore errors sending as the receiver is gone then
+                if tx.send(Err(e)).await.is_err() {
+                    debug!("series_transform receiver hung up");
+                }
+            }
+        });
+
+        Ok(AdapterStream::adapt(self.schema(), rx))
+    }
+
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                let transforms: Vec<_> = self
+                    .transforms
+                    .iter()
+                    .map(|(transform, column)| format!("{}({})", transform, column))
+                    .collect();
+                write!(f, "SeriesTransformExec: [{}]", transforms.join(", "))
+            }
+        }
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        // don't know anything about the statistics
+        Statistics::default()
+    }
+}
+
+async fn transform_series(
+    mut input_stream: SendableRecordBatchStream,
+    schema: SchemaRef,
+    series_columns: Vec<usize>,
+    time_column: usize,
+    mut transformers: Vec<(SeriesTransformer, usize)>,
+    baseline_metrics: BaselineMetrics,
+    tx: mpsc::Sender<ArrowResult<RecordBatch>>,
+) -> ArrowResult<()> {
+    // The series of the last row of the previous batch
+    let mut previous_series: Option<Vec<Option<String>>> = None;
+
+    while let Some(batch) = input_stream.next().await.transpose()? {
+        let timer = baseline_metrics.elapsed_compute().timer();
+
+        let series = series_columns
+            .iter()
+            .map(|index| cast(batch.column(*index), &DataType::Utf8))
+            .collect::<ArrowResult<Vec<_>>>()?;
+        let series: Vec<_> = series
+            .iter()
+            .map(|array| {
+                array
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .expect("cast to Utf8")
+            })
+            .collect();
+        let series_value = |row: usize| {
+            series
+                .iter()
+                .map(move |array| array.is_valid(row).then(|| array.value(row)))
+        };
+
+        let new_series: Vec<_> = (0..batch.num_rows())
+            .map(|row| match row {
+                0 => previous_series.as_ref().map_or(true, |previous| {
+                    !series_value(0).eq(previous.iter().map(|s| s.as_deref()))
+                }),
+                _ => !series_value(row).eq(series_value(row - 1)),
+            })
+            .collect();
+
+        if batch.num_rows() > 0 {
+            let last = batch.num_rows() - 1;
+            previous_series = Some(series_value(last).map(|s| s.map(String::from)).collect());
+        }
+
+        let times = cast(batch.column(time_column), &TIME_DATA_TYPE())?;
+        let times = times
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .expect("cast to timestamp");
+
+        let outputs = transformers
+            .iter_mut()
+            .map(|(transformer, column)| {
+                transformer
+                    .transform(&new_series, times, batch.column(*column))
+                    .map_err(|e| ArrowError::ExternalError(Box::new(e)))
+            })
+            .collect::<ArrowResult<Vec<ArrayRef>>>()?;
+
+        let columns = batch.columns().iter().cloned().chain(outputs).collect();
+        let output_batch =
+            RecordBatch::try_new(Arc::clone(&schema), columns)?.record_output(&baseline_metrics);
+        std::mem::drop(timer);
+
+        if tx.send(Ok(output_batch)).await.is_err() {
+            debug!("series_transform receiver hung up");
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{DictionaryArray, Int64Array},
+        datatypes::{Field, Int32Type, Schema as ArrowSchema},
+    };
+    use arrow_util::assert_batches_eq;
+    use datafusion::physical_plan::{collect, memory::MemoryExec};
+
+    #[tokio::test]
+    async fn test_series_across_batches() {
+        // series a continues in the second batch
+        let batch1 = make_batch(&["a", "a", "b"], &[1, 2, 1], &[1, 2, 10]);
+        let batch2 = make_batch(&["b", "c", "c"], &[2, 1, 3], &[15, 7, 3]);
+
+        let results = run(
+            vec![batch1, batch2],
+            vec![
+                (Transform::Difference, 2),
+                (Transform::CumulativeSum, 2),
+                (
+                    Transform::Derivative {
+                        unit: 1,
+                        non_negative: false,
+                    },
+                    2,
+                ),
+            ],
+        )
+        .await;
+
+        let expected = vec![
+            "+-----+--------------------------------+-------+------------+-----+------------+",
+            "| tag | time                           | value | difference | sum | derivative |",
+            "+-----+--------------------------------+-------+------------+-----+------------+",
+            "| a   | 1970-01-01T00:00:00.000000001Z | 1     |            | 1   |            |",
+            "| a   | 1970-01-01T00:00:00.000000002Z | 2     | 1          | 3   | 1          |",
+            "| b   | 1970-01-01T00:00:00.000000001Z | 10    |            | 10  |            |",
+            "| b   | 1970-01-01T00:00:00.000000002Z | 15    | 5          | 25  | 5          |",
+            "| c   | 1970-01-01T00:00:00.000000001Z | 7     |            | 7   |            |",
+            "| c   | 1970-01-01T00:00:00.000000003Z | 3     | -4         | 10  | -2         |",
+            "+-----+--------------------------------+-------+------------+-----+------------+",
+        ];
+        assert_batches_eq!(&expected, &results);
+    }
+
+    fn make_batch(tags: &[&str], times: &[i64], values: &[i64]) -> RecordBatch {
+        let tags: DictionaryArray<Int32Type> = tags.iter().copied().collect();
+        let times = TimestampNanosecondArray::from(times.to_vec());
+        let values = Int64Array::from(values.to_vec());
+
+        RecordBatch::try_from_iter(vec![
+            ("tag", Arc::new(tags) as ArrayRef),
+            ("time", Arc::new(times) as ArrayRef),
+            ("value", Arc::new(values) as ArrayRef),
+        ])
+        .unwrap()
+    }
+
+    /// Run the input through the transforms and return results
+    async fn run(input: Vec<RecordBatch>, transforms: Vec<(Transform, usize)>) -> Vec<RecordBatch> {
+        test_helpers::maybe_start_logging();
+
+        let input_schema = input[0].schema();
+        let projection = None;
+        let exec_input =
+            Arc::new(MemoryExec::try_new(&[input], Arc::clone(&input_schema), projection).unwrap());
+
+        let names = ["difference", "sum", "derivative"];
+        let fields = input_schema
+            .fields()
+            .iter()
+            .cloned()
+            .chain(
+                transforms
+                    .iter()
+                    .zip(names)
+                    .map(|((transform, column), name)| {
+                        let data_type =
+                            transform.return_type(input_schema.field(*column).data_type());
+                        Field::new(name, data_type, true)
+                    }),
+            )
+            .collect();
+        let schema = Arc::new(ArrowSchema::new(fields));
+
+        let exec = Arc::new(SeriesTransformExec::new(
+            exec_input,
+            schema,
+            vec![0],
+            1,
+            transforms,
+        ));
+        collect(exec as Arc<dyn ExecutionPlan>).await.unwrap()
+    }
+}
@@ -0,0 +1,171 @@
+//! This module contains code to estimate the number of distinct series
+//! in the output of a [`TableSeriesPlan`] with a HyperLogLog sketch, so
+//! the series of a table can be counted in bounded memory.
+//!
+//! [`TableSeriesPlan`]: crate::plan::seriescardinality::TableSeriesPlan
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, StringArray},
+    compute::cast,
+    datatypes::DataType,
+    error::Result as ArrowResult,
+    record_batch::RecordBatch,
+};
+use datafusion::error::{DataFusionError, Result};
+
+use crate::func::aggregates::hyperloglog::HyperLogLog;
+
+/// Estimates the number of distinct series, the combinations of the
+/// values of the tag columns and the non null field columns, in a
+/// stream of record batches
+#[derive(Debug)]
+pub struct SeriesCounter {
+    tag_columns: Vec<Arc<str>>,
+    field_columns: Vec<Arc<str>>,
+    hll: HyperLogLog,
+}
+
+impl SeriesCounter {
+    pub fn new(tag_columns: Vec<Arc<str>>, field_columns: Vec<Arc<str>>) -> Self {
+        Self {
+            tag_columns,
+            field_columns,
+            hll: HyperLogLog::default(),
+        }
+    }
+
+    /// Adds the series of the rows of `batch`
+    pub fn add_batch(&mut self, batch: &RecordBatch) -> Result<()> {
+        let tags = self
+            .tag_columns
+            .iter()
+            .map(|name| {
+                let column = batch.column(batch.schema().index_of(name)?);
+                cast(column, &DataType::Utf8)
+            })
+            .collect::<ArrowResult<Vec<_>>>()?;
+        let tags = tags
+            .iter()
+            .map(|tag| {
+                tag.as_any().downcast_ref::<StringArray>().ok_or_else(|| {
+                    DataFusionError::Internal(format!(
+                        "Internal error: tag column of type {:?} is not a string",
+                        tag.data_type()
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let fields = self
+            .field_columns
+            .iter()
+            .map(|name| Ok((&**name, batch.column(batch.schema().index_of(name)?))))
+            .collect::<Result<Vec<_>>>()?;
+
+        for row in 0..batch.num_rows() {
+            let tag_set = tags
+                .iter()
+                .map(|tag| tag.is_valid(row).then(|| tag.value(row)))
+                .collect::<Vec<_>>();
+
+            for (name, field) in &fields {
+                if field.is_valid(row) {
+                    self.hll.add(&(&tag_set, *name));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the estimated number of series added
+    pub fn count(&self) -> u64 {
+        self.hll.count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{ArrayRef, DictionaryArray, Float64Array},
+        datatypes::Int32Type,
+    };
+
+    #[test]
+    fn test_count_series() {
+        let mut counter = SeriesCounter::new(
+            vec![Arc::from("host"), Arc::from("region")],
+            vec![Arc::from("usage"), Arc::from("idle")],
+        );
+
+        // host=a has values of both fields, host=b only of usage
+        counter
+            .add_batch(&make_batch(
+                vec![Some("a"), Some("a"), Some("b")],
+                vec![Some("west"), Some("west"), Some("west")],
+                vec![Some(1.0), None, Some(3.0)],
+                vec![None, Some(2.0), None],
+            ))
+            .unwrap();
+        assert_eq!(counter.count(), 3);
+
+        // series seen before are not counted again, rows without a
+        // region are series of their own
+        counter
+            .add_batch(&make_batch(
+                vec![Some("b"), Some("b"), None],
+                vec![Some("west"), None, Some("east")],
+                vec![Some(4.0), Some(5.0), None],
+                vec![None, None, Some(6.0)],
+            ))
+            .unwrap();
+        assert_eq!(counter.count(), 5);
+    }
+
+    #[test]
+    fn test_count_series_no_tags() {
+        let mut counter = SeriesCounter::new(vec![], vec![Arc::from("usage"), Arc::from("idle")]);
+
+        counter
+            .add_batch(&make_batch(
+                vec![None, None],
+                vec![None, None],
+                vec![Some(1.0), Some(2.0)],
+                vec![None, None],
+            ))
+            .unwrap();
+        assert_eq!(counter.count(), 1);
+    }
+
+    #[test]
+    fn test_count_series_missing_column() {
+        let mut counter = SeriesCounter::new(vec![Arc::from("cpu")], vec![Arc::from("usage")]);
+
+        let err = counter
+            .add_batch(&make_batch(vec![], vec![], vec![], vec![]))
+            .unwrap_err();
+        assert!(err.to_string().contains("cpu"), "{}", err);
+    }
+
+    fn make_batch(
+        host: Vec<Option<&str>>,
+        region: Vec<Option<&str>>,
+        usage: Vec<Option<f64>>,
+        idle: Vec<Option<f64>>,
+    ) -> RecordBatch {
+        let host: DictionaryArray<Int32Type> = host.into_iter().collect();
+        let region: StringArray = region.into_iter().collect();
+        let usage: Float64Array = usage.into_iter().collect();
+        let idle: Float64Array = idle.into_iter().collect();
+
+        RecordBatch::try_from_iter(vec![
+            ("host", Arc::new(host) as ArrayRef),
+            ("region", Arc::new(region) as ArrayRef),
+            ("usage", Arc::new(usage) as ArrayRef),
+            ("idle", Arc::new(idle) as ArrayRef),
+        ])
+        .unwrap()
+    }
+}
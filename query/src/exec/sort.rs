@@ -0,0 +1,589 @@
+//! This module contains [`SpillableSortExec`], a sort that spills sorted
+//! runs of its input to disk once it exceeds the memory of its query
+
+use std::{
+    fmt,
+    fs::File,
+    io::{Seek, SeekFrom},
+    path::Path,
+    sync::Arc,
+};
+
+use arrow::{
+    array::UInt32Array,
+    compute::{lexsort_to_indices, take},
+    datatypes::SchemaRef,
+    error::{ArrowError, Result as ArrowResult},
+    ipc::{reader::FileReader, writer::FileWriter},
+    record_batch::RecordBatch,
+};
+use async_trait::async_trait;
+use datafusion::{
+    error::{DataFusionError, Result},
+    physical_plan::{
+        coalesce_batches::concat_batches,
+        expressions::PhysicalSortExpr,
+        metrics::{
+            self, BaselineMetrics, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet, RecordOutput,
+        },
+        sort_preserving_merge::SortPreservingMergeExec,
+        DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream, Statistics,
+    },
+};
+use datafusion_util::AdapterStream;
+use futures::StreamExt;
+use observability_deps::tracing::debug;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+
+use super::memory::{batch_memory_size, MemoryReservation, QueryMemory};
+
+/// Number of rows of the batches written to and read from spill files
+const BATCH_SIZE: usize = 1000;
+
+/// Sorts each partition of its input, like DataFusion's `SortExec`.
+///
+/// The input is buffered in memory reserved from its query, which also
+/// covers the copies made when sorting it. Once no more memory can be
+/// reserved, the buffered batches are sorted and spilled to a file, and the
+/// sorted runs are finally merged. If spilling is disabled the query fails
+/// instead.
+#[derive(Debug)]
+pub struct SpillableSortExec {
+    expr: Vec<PhysicalSortExpr>,
+    input: Arc<dyn ExecutionPlan>,
+    memory: Arc<QueryMemory>,
+    /// Execution metrics
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl SpillableSortExec {
+    pub fn new(
+        expr: Vec<PhysicalSortExpr>,
+        input: Arc<dyn ExecutionPlan>,
+        memory: Arc<QueryMemory>,
+    ) -> Self {
+        Self {
+            expr,
+            input,
+            memory,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SortMetrics {
+    baseline_metrics: BaselineMetrics,
+    spill_count: metrics::Count,
+    spilled_rows: metrics::Count,
+}
+
+impl SortMetrics {
+    fn new(metrics: &ExecutionPlanMetricsSet, partition: usize) -> Self {
+        Self {
+            baseline_metrics: BaselineMetrics::new(metrics, partition),
+            spill_count: MetricBuilder::new(metrics).counter("spill_count", partition),
+            spilled_rows: MetricBuilder::new(metrics).counter("spilled_rows", partition),
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for SpillableSortExec {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![Arc::clone(&self.input)]
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        assert_eq!(children.len(), 1);
+        Ok(Arc::new(Self::new(
+            self.expr.clone(),
+            Arc::clone(&children[0]),
+            Arc::clone(&self.memory),
+        )))
+    }
+
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        let sort_metrics = SortMetrics::new(&self.metrics, partition);
+        let input_stream = self.input.execute(partition).await?;
+
+        // the sort is performed in a separate task which sends its output
+        // via a channel
+        let (tx, rx) = mpsc::channel(1);
+
+        let task = tokio::task::spawn(sort(
+            input_stream,
+            self.expr.clone(),
+            Arc::clone(&self.memory),
+            tx.clone(),
+            sort_metrics,
+        ));
+
+        // A second task watches the output of the worker task
+        tokio::task::spawn(async move {
+            let msg = match task.await {
+                Err(join_err) => {
+                    debug!(e=%join_err, "Error joining sort task");
+                    Some(ArrowError::ExternalError(Box::new(join_err)))
+                }
+                Ok(Err(e)) => {
+                    debug!(%e, "Error in sort task itself");
+                    Some(e)
+                }
+                Ok(Ok(())) => None,
+            };
+
+            if let Some(e) = msg {
+                // Ignore errors sending as the receiver is gone then
+                if tx.send(Err(e)).await.is_err() {
+                    debug!("sort receiver hung up");
+                }
+            }
+        });
+
+        Ok(AdapterStream::adapt(self.schema(), rx))
+    }
+
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                let expr: Vec<String> = self.expr.iter().map(|e| e.to_string()).collect();
+                write!(f, "SpillableSortExec: [{}]", expr.join(","))
+            }
+        }
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        self.input.statistics()
+    }
+}
+
+async fn sort(
+    mut input_stream: SendableRecordBatchStream,
+    expr: Vec<PhysicalSortExpr>,
+    memory: Arc<QueryMemory>,
+    tx: mpsc::Sender<ArrowResult<RecordBatch>>,
+    sort_metrics: SortMetrics,
+) -> ArrowResult<()> {
+    let SortMetrics {
+        baseline_metrics,
+        spill_count,
+        spilled_rows,
+    } = sort_metrics;
+    let schema = input_stream.schema();
+
+    let mut reservation = MemoryReservation::new(Arc::clone(&memory));
+    let mut buffered = vec![];
+    let mut runs = vec![];
+
+    let sort_and_spill = |batches: Vec<RecordBatch>, spill_dir: &Path| {
+        let timer = baseline_metrics.elapsed_compute().timer();
+        let sorted = sort_batches(&schema, batches, &expr)?;
+        timer.done();
+
+        let run = spill(&schema, sorted, spill_dir)?;
+        debug!(rows = run.rows, "spilled sorted run");
+        spill_count.add(1);
+        spilled_rows.add(run.rows);
+        ArrowResult::Ok(run.file)
+    };
+
+    while let Some(batch) = input_stream.next().await {
+        let batch = batch?;
+
+        // Sorting allocates a copy of the buffered batches to concatenate
+        // them and another one for the sorted output, but the buffered
+        // batches are dropped once concatenated
+        let size = 2 * batch_memory_size(&batch);
+
+        if let Err(e) = reservation.try_resize(reservation.size() + size) {
+            let spill_dir = memory
+                .spill_dir()
+                .ok_or_else(|| ArrowError::ExternalError(Box::new(e)))?;
+
+            if !buffered.is_empty() {
+                runs.push(sort_and_spill(std::mem::take(&mut buffered), spill_dir)?);
+                reservation.free();
+            }
+
+            // A batch that does not fit on its own is spilled right away
+            if reservation.try_resize(size).is_err() {
+                runs.push(sort_and_spill(vec![batch], spill_dir)?);
+                continue;
+            }
+        }
+
+        buffered.push(batch);
+    }
+
+    if !runs.is_empty() {
+        // Spill the remaining batches as well, so that all runs can be merged
+        if !buffered.is_empty() {
+            let spill_dir = memory.spill_dir().expect("spilled before");
+            runs.push(sort_and_spill(buffered, spill_dir)?);
+        }
+    } else {
+        let timer = baseline_metrics.elapsed_compute().timer();
+        let sorted = sort_batches(&schema, buffered, &expr)?;
+        timer.done();
+
+        for batch in sorted {
+            let batch = batch.record_output(&baseline_metrics);
+            tx.send(Ok(batch))
+                .await
+                .map_err(|e| ArrowError::from_external_error(Box::new(e)))?;
+        }
+        return Ok(());
+    }
+    reservation.free();
+
+    let merge = SortPreservingMergeExec::new(
+        expr,
+        Arc::new(SpilledRunsExec::new(Arc::clone(&schema), runs)),
+        BATCH_SIZE,
+    );
+    let mut merged = merge
+        .execute(0)
+        .await
+        .map_err(DataFusionError::into_arrow_external_error)?;
+
+    while let Some(batch) = merged.next().await {
+        let batch = batch?.record_output(&baseline_metrics);
+        tx.send(Ok(batch))
+            .await
+            .map_err(|e| ArrowError::from_external_error(Box::new(e)))?;
+    }
+
+    Ok(())
+}
+
+/// Sorts `batches` on `expr`, returning the sorted rows in batches of
+/// [`BATCH_SIZE`] rows
+///
+/// `batches` are dropped once concatenated, so that no more than twice
+/// their size is allocated at any time.
+fn sort_batches(
+    schema: &SchemaRef,
+    mut batches: Vec<RecordBatch>,
+    expr: &[PhysicalSortExpr],
+) -> ArrowResult<Vec<RecordBatch>> {
+    let num_rows = batches.iter().map(|batch| batch.num_rows()).sum();
+    if num_rows == 0 {
+        return Ok(vec![]);
+    }
+    let batch = match batches.len() {
+        1 => batches.pop().expect("one batch"),
+        _ => concat_batches(schema, &batches, num_rows)?,
+    };
+    drop(batches);
+
+    let sort_columns = expr
+        .iter()
+        .map(|e| e.evaluate_to_sort_column(&batch))
+        .collect::<Result<Vec<_>>>()
+        .map_err(DataFusionError::into_arrow_external_error)?;
+    let indices = lexsort_to_indices(&sort_columns, None)?;
+
+    indices
+        .values()
+        .chunks(BATCH_SIZE)
+        .map(|chunk| {
+            let indices = UInt32Array::from(chunk.to_vec());
+            let columns = batch
+                .columns()
+                .iter()
+                .map(|column| take(column.as_ref(), &indices, None))
+                .collect::<ArrowResult<Vec<_>>>()?;
+            RecordBatch::try_new(Arc::clone(schema), columns)
+        })
+        .collect()
+}
+
+/// A sorted run spilled to disk
+struct SpilledRun {
+    file: File,
+    rows: usize,
+}
+
+/// Writes the sorted `batches` to an anonymous file in `spill_dir`, which
+/// is deleted once closed
+fn spill(
+    schema: &SchemaRef,
+    batches: Vec<RecordBatch>,
+    spill_dir: &Path,
+) -> ArrowResult<SpilledRun> {
+    let mut file = tempfile::tempfile_in(spill_dir)?;
+    let mut rows = 0;
+
+    {
+        let mut writer = FileWriter::try_new(file.try_clone()?, schema)?;
+        for batch in batches {
+            rows += batch.num_rows();
+            writer.write(&batch)?;
+        }
+        writer.finish()?;
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+    Ok(SpilledRun { file, rows })
+}
+
+/// Reads sorted runs from spill files, one per partition
+#[derive(Debug)]
+struct SpilledRunsExec {
+    schema: SchemaRef,
+    runs: Mutex<Vec<Option<File>>>,
+}
+
+impl SpilledRunsExec {
+    fn new(schema: SchemaRef, runs: Vec<File>) -> Self {
+        Self {
+            schema,
+            runs: Mutex::new(runs.into_iter().map(Some).collect()),
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for SpilledRunsExec {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(self.runs.lock().len())
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        &self,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Err(DataFusionError::Internal(
+            "SpilledRunsExec does not support children".to_string(),
+        ))
+    }
+
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        let file = self
+            .runs
+            .lock()
+            .get_mut(partition)
+            .and_then(Option::take)
+            .ok_or_else(|| {
+                DataFusionError::Internal(format!(
+                    "Spilled run {} does not exist or was already read",
+                    partition
+                ))
+            })?;
+
+        let (tx, rx) = mpsc::channel(1);
+
+        // Reading from disk blocks
+        tokio::task::spawn_blocking(move || {
+            let reader = match FileReader::try_new(file) {
+                Ok(reader) => reader,
+                Err(e) => {
+                    // Ignore errors sending as the receiver is gone then
+                    let _ = tx.blocking_send(Err(e));
+                    return;
+                }
+            };
+
+            for batch in reader {
+                if tx.blocking_send(batch).is_err() {
+                    debug!("spilled run receiver hung up");
+                    return;
+                }
+            }
+        });
+
+        Ok(AdapterStream::adapt(self.schema(), rx))
+    }
+
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default => write!(f, "SpilledRunsExec"),
+        }
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{ArrayRef, Int64Array, StringArray},
+        compute::SortOptions,
+    };
+    use arrow_util::assert_batches_eq;
+    use datafusion::physical_plan::{collect, expressions::col, memory::MemoryExec};
+
+    use crate::exec::memory::{is_memory_exhausted, MemoryConfig, MemoryPool};
+
+    use super::*;
+
+    fn memory(query_limit: usize, spill_dir: Option<&Path>) -> Arc<QueryMemory> {
+        let pool = Arc::new(MemoryPool::new(MemoryConfig {
+            pool_size: None,
+            query_limit: Some(query_limit),
+            spill_dir: spill_dir.map(Path::to_path_buf),
+        }));
+        Arc::new(QueryMemory::new(pool))
+    }
+
+    fn input() -> Arc<dyn ExecutionPlan> {
+        let batches: Vec<_> = [
+            (vec!["c", "a", "b"], vec![3, 1, 2]),
+            (vec!["a", "c", "b"], vec![4, 6, 5]),
+            (vec!["b", "a"], vec![8, 7]),
+        ]
+        .into_iter()
+        .map(|(tags, values)| {
+            RecordBatch::try_from_iter(vec![
+                ("t", Arc::new(StringArray::from(tags)) as ArrayRef),
+                ("v", Arc::new(Int64Array::from(values)) as ArrayRef),
+            ])
+            .unwrap()
+        })
+        .collect();
+
+        let schema = batches[0].schema();
+        Arc::new(MemoryExec::try_new(&[batches], schema, None).unwrap())
+    }
+
+    fn sort_exec(input: Arc<dyn ExecutionPlan>, memory: Arc<QueryMemory>) -> SpillableSortExec {
+        let sort_keys = vec![
+            PhysicalSortExpr {
+                expr: col("t", &input.schema()).unwrap(),
+                options: SortOptions::default(),
+            },
+            PhysicalSortExpr {
+                expr: col("v", &input.schema()).unwrap(),
+                options: SortOptions {
+                    descending: true,
+                    nulls_first: false,
+                },
+            },
+        ];
+        SpillableSortExec::new(sort_keys, input, memory)
+    }
+
+    /// Returns the value of the metric `name` of `exec`
+    fn metric(exec: &SpillableSortExec, name: &str) -> usize {
+        exec.metrics()
+            .unwrap()
+            .iter()
+            .filter(|m| m.value().name() == name)
+            .map(|m| m.value().as_usize())
+            .sum()
+    }
+
+    const EXPECTED: [&str; 12] = [
+        "+---+---+",
+        "| t | v |",
+        "+---+---+",
+        "| a | 7 |",
+        "| a | 4 |",
+        "| a | 1 |",
+        "| b | 8 |",
+        "| b | 5 |",
+        "| b | 2 |",
+        "| c | 6 |",
+        "| c | 3 |",
+        "+---+---+",
+    ];
+
+    #[tokio::test]
+    async fn test_sort_in_memory() {
+        let memory = memory(usize::MAX, None);
+        let exec = Arc::new(sort_exec(input(), Arc::clone(&memory)));
+
+        let output = collect(Arc::clone(&exec) as _).await.unwrap();
+        assert_batches_eq!(&EXPECTED, &output);
+
+        assert_eq!(metric(&exec, "spill_count"), 0);
+        assert_eq!(memory.used(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sort_spill() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Not even a single batch fits into memory
+        let memory = memory(1, Some(dir.path()));
+        let exec = Arc::new(sort_exec(input(), Arc::clone(&memory)));
+
+        let output = collect(Arc::clone(&exec) as _).await.unwrap();
+        assert_batches_eq!(&EXPECTED, &output);
+
+        assert_eq!(metric(&exec, "spill_count"), 3);
+        assert_eq!(metric(&exec, "spilled_rows"), 8);
+        assert_eq!(metric(&exec, "output_rows"), 8);
+        assert_eq!(memory.used(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sort_reserves_copies() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = input();
+        let input_size: usize = collect(Arc::clone(&input))
+            .await
+            .unwrap()
+            .iter()
+            .map(batch_memory_size)
+            .sum();
+
+        // Sorting the buffered input requires twice its size
+        let fits = memory(2 * input_size, Some(dir.path()));
+        let exec = Arc::new(sort_exec(Arc::clone(&input), fits));
+        let output = collect(Arc::clone(&exec) as _).await.unwrap();
+        assert_batches_eq!(&EXPECTED, &output);
+        assert_eq!(metric(&exec, "spill_count"), 0);
+
+        let too_small = memory(2 * input_size - 1, Some(dir.path()));
+        let exec = Arc::new(sort_exec(input, too_small));
+        let output = collect(Arc::clone(&exec) as _).await.unwrap();
+        assert_batches_eq!(&EXPECTED, &output);
+        assert!(metric(&exec, "spill_count") > 0);
+    }
+
+    #[tokio::test]
+    async fn test_sort_without_spilling() {
+        let memory = memory(1, None);
+        let exec = Arc::new(sort_exec(input(), Arc::clone(&memory)));
+
+        let err = collect(exec).await.unwrap_err();
+        assert!(is_memory_exhausted(&err), "{}", err);
+        assert_eq!(memory.used(), 0);
+    }
+}
@@ -5,12 +5,14 @@ use std::{
 };
 
 use arrow::datatypes::DataType;
-use data_types::chunk_metadata::ChunkId;
+use data_types::{
+    chunk_metadata::ChunkId, partition_metadata::Statistics, timestamp::TimestampRange,
+};
 use datafusion::{
     error::{DataFusionError, Result as DatafusionResult},
     logical_plan::{
-        binary_expr, lit, when, Column, DFSchema, DFSchemaRef, Expr, ExprRewriter, LogicalPlan,
-        LogicalPlanBuilder, Operator,
+        binary_expr, lit, plan::Extension, when, Column, DFSchema, DFSchemaRef, Expr, ExprRewriter,
+        LogicalPlan, LogicalPlanBuilder, Operator,
     },
     prelude::col,
     scalar::ScalarValue,
@@ -21,22 +23,28 @@ use hashbrown::{HashMap, HashSet};
 use observability_deps::tracing::{debug, trace};
 use predicate::predicate::{BinaryExpr, Predicate, PredicateMatch};
 use schema::selection::Selection;
+use schema::sort::SortKey;
 use schema::{InfluxColumnType, Schema, TIME_COLUMN_NAME};
 use snafu::{ensure, OptionExt, ResultExt, Snafu};
 
 use crate::{
-    exec::{field::FieldColumns, make_non_null_checker, make_schema_pivot},
+    exec::{field::FieldColumns, gapfill::GapFillNode, make_non_null_checker, make_schema_pivot},
     func::{
+        gapfill::{FillMode, GapFillParams, WindowBound},
         selectors::{selector_first, selector_last, selector_max, selector_min, SelectorOutput},
         window::make_window_bound_expr,
     },
     group_by::{Aggregate, WindowDuration},
     plan::{
         fieldlist::FieldListPlan,
+        seriescardinality::{SeriesCardinalityPlan, TableSeriesPlan},
         seriesset::{SeriesSetPlan, SeriesSetPlans},
         stringset::{Error as StringSetError, StringSetPlan, StringSetPlanBuilder},
     },
-    provider::ProviderBuilder,
+    provider::{
+        scan_partial_aggregates, AggregateWindow, ChunkAggregates, PartialAggregate,
+        ProviderBuilder,
+    },
     QueryChunk, QueryChunkMeta, QueryDatabase,
 };
 
@@ -654,6 +662,112 @@ impl InfluxRpcPlanner {
         Ok(field_list_plan)
     }
 
+    /// Returns a plan that counts the distinct series, the
+    /// combinations of table, tag set and field, with at least one
+    /// value which passes the conditions specified by `predicate`.
+    /// These are the series returned by `read_filter`.
+    ///
+    /// The series of tables whose chunks are entirely selected by the
+    /// predicate, and have at most one tag, are counted exactly at plan
+    /// time from the chunk statistics and tag dictionaries. The series
+    /// of all other tables are estimated when the plan is run.
+    pub fn series_cardinality<D>(
+        &self,
+        database: &D,
+        predicate: Predicate,
+    ) -> Result<SeriesCardinalityPlan>
+    where
+        D: QueryDatabase + 'static,
+    {
+        debug!(predicate=?predicate, "planning series_cardinality");
+
+        let mut normalizer = PredicateNormalizer::new(predicate);
+
+        // Key is table name, value is the chunks which may have data
+        // for that table
+        let mut table_chunks = BTreeMap::new();
+
+        // Key is table name, value is the series found from metadata
+        let mut known_series = BTreeMap::new();
+
+        // tables with chunks whose series can't be found from metadata
+        let mut need_full_plans = BTreeSet::new();
+
+        for chunk in database.chunks(normalizer.unnormalized()) {
+            let table_name = chunk.table_name();
+            let predicate = normalizer.normalized(table_name);
+
+            // Try and apply the predicate using only metadata
+            let pred_result = chunk
+                .apply_predicate_to_metadata(&predicate)
+                .map_err(|e| Box::new(e) as _)
+                .context(CheckingChunkPredicate {
+                    chunk_id: chunk.id(),
+                })?;
+
+            if matches!(pred_result, PredicateMatch::Zero) {
+                continue;
+            }
+
+            // If there are delete predicates, we need to scan the data
+            // to eliminate deleted data before finding series
+            let series = if chunk.has_delete_predicates() {
+                None
+            } else {
+                series_from_metadata(chunk.as_ref(), &predicate)?
+            };
+
+            match series {
+                Some(mut series) => {
+                    debug!(
+                        table_name,
+                        chunk_id=%chunk.id().get(),
+                        "series found from metadata",
+                    );
+                    known_series
+                        .entry(table_name.to_string())
+                        .or_insert_with(BTreeSet::new)
+                        .append(&mut series);
+                }
+                None => {
+                    debug!(
+                        table_name,
+                        chunk_id=%chunk.id().get(),
+                        "need full plan to find series",
+                    );
+                    need_full_plans.insert(table_name.to_string());
+                }
+            }
+
+            table_chunks
+                .entry(table_name.to_string())
+                .or_insert_with(Vec::new)
+                .push(Arc::clone(&chunk));
+        }
+
+        // The series of a table can only be counted from metadata if
+        // they are known for all of its chunks
+        let mut plan = SeriesCardinalityPlan::new();
+        for (table_name, chunks) in table_chunks {
+            if !need_full_plans.contains(&table_name) {
+                let count = known_series.get(&table_name).map(BTreeSet::len);
+                plan = plan.append_known(count.unwrap_or(0) as u64);
+                continue;
+            }
+
+            let schema = database.table_schema(&table_name).context(TableRemoved {
+                table_name: &table_name,
+            })?;
+            if let Some(table_plan) =
+                self.series_cardinality_plan(&table_name, schema, &mut normalizer, chunks)?
+            {
+                plan = plan.append(table_plan);
+            }
+        }
+
+        Ok(plan)
+    }
+
     /// Returns a plan that finds all rows which pass the
     /// conditions specified by `predicate` in the form of logical
     /// time series.
@@ -772,6 +886,10 @@ impl InfluxRpcPlanner {
 
     /// Creates a GroupedSeriesSet plan that produces an output table with rows
     /// that are grouped by window definitions
+    ///
+    /// If `create_empty` is set, windows without rows within the time
+    /// range of the predicate are emitted as well, with a count of 0 and
+    /// NULL for other aggregates, like Flux's `aggregateWindow`.
     pub fn read_window_aggregate<D>(
         &self,
         database: &D,
@@ -779,6 +897,7 @@ impl InfluxRpcPlanner {
         agg: Aggregate,
         every: WindowDuration,
         offset: WindowDuration,
+        create_empty: bool,
     ) -> Result<SeriesSetPlans>
     where
         D: QueryDatabase + 'static,
@@ -788,6 +907,7 @@ impl InfluxRpcPlanner {
             ?agg,
             ?every,
             ?offset,
+            create_empty,
             "planning read_window_aggregate"
         );
 
@@ -810,6 +930,7 @@ impl InfluxRpcPlanner {
                 agg,
                 &every,
                 &offset,
+                create_empty,
                 chunks,
             )?;
             // If we have to do real work, add it to the list of plans
@@ -970,6 +1091,65 @@ impl InfluxRpcPlanner {
         Ok(Some(plan))
     }
 
+    /// Creates a plan producing the tags and fields of the rows of a
+    /// table which pass the predicate, to count their series:
+    ///
+    /// The created plan looks like:
+    ///
+    /// ```text
+    ///    Projection (select tags and fields)
+    ///      Filter(predicate) [optional]
+    ///        Scan
+    /// ```
+    fn series_cardinality_plan<C>(
+        &self,
+        table_name: &str,
+        schema: Arc<Schema>,
+        normalizer: &mut PredicateNormalizer,
+        chunks: Vec<Arc<C>>,
+    ) -> Result<Option<TableSeriesPlan>>
+    where
+        C: QueryChunk + 'static,
+    {
+        let scan_and_filter = self.scan_and_filter(table_name, schema, normalizer, chunks)?;
+        let TableScanAndFilter {
+            plan_builder,
+            schema,
+        } = match scan_and_filter {
+            None => return Ok(None),
+            Some(t) => t,
+        };
+
+        let predicate = normalizer.normalized(table_name);
+
+        let tags_and_fields: Vec<Expr> = schema
+            .tags_iter()
+            .map(|field| field.name().as_expr())
+            .chain(filtered_fields_iter(&schema, &predicate).map(|f| f.expr))
+            .collect();
+
+        let plan = plan_builder
+            .project(tags_and_fields)
+            .context(BuildingPlan)?
+            .build()
+            .context(BuildingPlan)?;
+
+        let tag_columns = schema
+            .tags_iter()
+            .map(|field| Arc::from(field.name().as_str()))
+            .collect();
+
+        let field_columns = filtered_fields_iter(&schema, &predicate)
+            .map(|field| Arc::from(field.name))
+            .collect();
+
+        Ok(Some(TableSeriesPlan {
+            plan,
+            tag_columns,
+            field_columns,
+        }))
+    }
+
     /// Creates a DataFusion LogicalPlan that returns the values in
     /// the fields for a specified table:
     ///
@@ -1059,12 +1239,14 @@ impl InfluxRpcPlanner {
             Some(t) => t,
         };
 
-        let tags_and_timestamp: Vec<_> = schema
-            .tags_iter()
-            .chain(schema.time_iter())
-            .map(|f| f.name() as &str)
+        let tags_and_timestamp: Vec<_> = series_sort_key(&schema)
+            .iter()
             // Convert to SortExprs to pass to the plan builder
-            .map(|n| n.as_sort_expr())
+            .map(|(name, options)| Expr::Sort {
+                expr: Box::new(name.as_expr()),
+                asc: !options.descending,
+                nulls_first: options.nulls_first,
+            })
             .collect();
 
         // Order by
@@ -1159,15 +1341,42 @@ impl InfluxRpcPlanner {
         C: QueryChunk + 'static,
     {
         let table_name = table_name.into();
-        let scan_and_filter = self.scan_and_filter(&table_name, schema, normalizer, chunks)?;
         let predicate = normalizer.normalized(&table_name);
 
-        let TableScanAndFilter {
-            plan_builder,
-            schema,
-        } = match scan_and_filter {
-            None => return Ok(None),
-            Some(t) => t,
+        let pushdown =
+            self.pushdown_aggregate_plan(&table_name, &schema, &predicate, agg, None, &chunks)?;
+
+        let (plan_builder, schema, field_columns) = match pushdown {
+            Some((plan_builder, field_columns)) => (plan_builder, schema, field_columns),
+            None => {
+                let scan_and_filter =
+                    self.scan_and_filter(&table_name, schema, normalizer, chunks)?;
+
+                let TableScanAndFilter {
+                    plan_builder,
+                    schema,
+                } = match scan_and_filter {
+                    None => return Ok(None),
+                    Some(t) => t,
+                };
+
+                // Group by all tag columns
+                let group_exprs = schema
+                    .tags_iter()
+                    .map(|field| field.name().as_expr())
+                    .collect::<Vec<_>>();
+
+                let AggExprs {
+                    agg_exprs,
+                    field_columns,
+                } = AggExprs::try_new_for_read_group(agg, &schema, &predicate)?;
+
+                let plan_builder = plan_builder
+                    .aggregate(group_exprs, agg_exprs)
+                    .context(BuildingPlan)?;
+
+                (plan_builder, schema, field_columns)
+            }
         };
 
         // order the tag columns so that the group keys come first (we
@@ -1175,21 +1384,6 @@ impl InfluxRpcPlanner {
         // order in the same order)
         let tag_columns: Vec<_> = schema.tags_iter().map(|f| f.name() as &str).collect();
 
-        // Group by all tag columns
-        let group_exprs = tag_columns
-            .iter()
-            .map(|tag_name| tag_name.as_expr())
-            .collect::<Vec<_>>();
-
-        let AggExprs {
-            agg_exprs,
-            field_columns,
-        } = AggExprs::try_new_for_read_group(agg, &schema, &predicate)?;
-
-        let plan_builder = plan_builder
-            .aggregate(group_exprs, agg_exprs)
-            .context(BuildingPlan)?;
-
         // Reorganize the output so it is ordered and sorted on tag columns
 
         // no columns if there are no tags in the input and no group columns in the query
@@ -1250,7 +1444,8 @@ impl InfluxRpcPlanner {
     ///
     /// The created plan looks like:
     ///
-    ///  OrderBy(gby: tag columns, window_function; agg: aggregate(field))
+    ///  GapFill(series: tag columns, time: window_function) [if create_empty]
+    ///    OrderBy(gby: tag columns, window_function; agg: aggregate(field))
     ///      GroupBy(gby: tag columns, window_function; agg: aggregate(field))
     ///        Filter(predicate)
     ///          Scan
@@ -1263,49 +1458,105 @@ impl InfluxRpcPlanner {
         agg: Aggregate,
         every: &WindowDuration,
         offset: &WindowDuration,
+        create_empty: bool,
         chunks: Vec<Arc<C>>,
     ) -> Result<Option<SeriesSetPlan>>
     where
         C: QueryChunk + 'static,
     {
         let table_name = table_name.into();
-        let scan_and_filter = self.scan_and_filter(&table_name, schema, normalizer, chunks)?;
         let predicate = normalizer.normalized(&table_name);
 
-        let TableScanAndFilter {
-            plan_builder,
-            schema,
-        } = match scan_and_filter {
-            None => return Ok(None),
-            Some(t) => t,
+        // Chunks can only compute fixed windows
+        let pushdown = match (every, offset) {
+            (
+                WindowDuration::Fixed { nanoseconds: every },
+                WindowDuration::Fixed {
+                    nanoseconds: offset,
+                },
+            ) if *every > 0 => {
+                let window = AggregateWindow {
+                    every: *every,
+                    offset: *offset,
+                };
+                self.pushdown_aggregate_plan(
+                    &table_name,
+                    &schema,
+                    &predicate,
+                    agg,
+                    Some(window),
+                    &chunks,
+                )?
+            }
+            _ => None,
         };
 
-        // Group by all tag columns and the window bounds
-        let window_bound = make_window_bound_expr(TIME_COLUMN_NAME.as_expr(), every, offset)
-            .alias(TIME_COLUMN_NAME);
+        let (plan_builder, schema, field_columns) = match pushdown {
+            Some((plan_builder, field_columns)) => {
+                // the chunks already computed the window bounds as time
+                let sort_exprs = schema
+                    .tags_iter()
+                    .map(|field| field.name().as_sort_expr())
+                    .chain(std::iter::once(TIME_COLUMN_NAME.as_sort_expr()))
+                    .collect::<Vec<_>>();
 
-        let group_exprs = schema
-            .tags_iter()
-            .map(|field| field.name().as_expr())
-            .chain(std::iter::once(window_bound))
-            .collect::<Vec<_>>();
+                let plan_builder = plan_builder.sort(sort_exprs).context(BuildingPlan)?;
 
-        let AggExprs {
-            agg_exprs,
-            field_columns,
-        } = AggExprs::try_new_for_read_window_aggregate(agg, &schema, &predicate)?;
+                (plan_builder, schema, field_columns)
+            }
+            None => {
+                let scan_and_filter =
+                    self.scan_and_filter(&table_name, schema, normalizer, chunks)?;
+
+                let TableScanAndFilter {
+                    plan_builder,
+                    schema,
+                } = match scan_and_filter {
+                    None => return Ok(None),
+                    Some(t) => t,
+                };
+
+                // Group by all tag columns and the window bounds
+                let window_bound =
+                    make_window_bound_expr(TIME_COLUMN_NAME.as_expr(), every, offset)
+                        .alias(TIME_COLUMN_NAME);
+
+                let group_exprs = schema
+                    .tags_iter()
+                    .map(|field| field.name().as_expr())
+                    .chain(std::iter::once(window_bound))
+                    .collect::<Vec<_>>();
+
+                let AggExprs {
+                    agg_exprs,
+                    field_columns,
+                } = AggExprs::try_new_for_read_window_aggregate(agg, &schema, &predicate)?;
+
+                // sort by the group by expressions as well
+                let sort_exprs = group_exprs
+                    .iter()
+                    .map(|expr| expr.as_sort_expr())
+                    .collect::<Vec<_>>();
+
+                let plan_builder = plan_builder
+                    .aggregate(group_exprs, agg_exprs)
+                    .context(BuildingPlan)?
+                    .sort(sort_exprs)
+                    .context(BuildingPlan)?;
 
-        // sort by the group by expressions as well
-        let sort_exprs = group_exprs
-            .iter()
-            .map(|expr| expr.as_sort_expr())
-            .collect::<Vec<_>>();
+                (plan_builder, schema, field_columns)
+            }
+        };
 
-        let plan_builder = plan_builder
-            .aggregate(group_exprs, agg_exprs)
-            .context(BuildingPlan)?
-            .sort(sort_exprs)
-            .context(BuildingPlan)?;
+        // Empty windows can only be created within a time range
+        let plan_builder = match (create_empty, predicate.range) {
+            (true, Some(range)) => {
+                let plan = plan_builder.build().context(BuildingPlan)?;
+                let plan = make_empty_windows(plan, &schema, agg, every, offset, range)?;
+                LogicalPlanBuilder::from(plan)
+            }
+            _ => plan_builder,
+        };
 
         let plan_builder = cast_aggregates(plan_builder, agg, &field_columns)?;
 
@@ -1325,6 +1576,98 @@ impl InfluxRpcPlanner {
         )))
     }
 
+    /// Creates a plan computing `agg` of the fields of `table_name`
+    /// grouped by all its tags, and `window` if any, by pushing the
+    /// aggregate down into `chunks`. Its output is the same as the
+    /// aggregate of `read_group_plan` / `read_window_aggregate_plan`,
+    /// except for the order of rows.
+    ///
+    /// Returns `None` if the aggregate can not be pushed down, in which
+    /// case the chunks are scanned and aggregated by DataFusion.
+    fn pushdown_aggregate_plan<C>(
+        &self,
+        table_name: &str,
+        schema: &Schema,
+        predicate: &Predicate,
+        agg: Aggregate,
+        window: Option<AggregateWindow>,
+        chunks: &[Arc<C>],
+    ) -> Result<Option<(LogicalPlanBuilder, FieldColumns)>>
+    where
+        C: QueryChunk + 'static,
+    {
+        // selectors also output the time of the selected value, and can
+        // not be combined across chunks
+        let partial_agg = match agg {
+            Aggregate::Sum => PartialAggregate::Sum,
+            Aggregate::Count => PartialAggregate::Count,
+            _ => return Ok(None),
+        };
+
+        // expressions on `_value` are applied to the field values before
+        // aggregating them
+        if !predicate.value_expr.is_empty() {
+            return Ok(None);
+        }
+
+        let field_names = filtered_fields_iter(schema, predicate)
+            .map(|field| field.name)
+            .collect::<Vec<_>>();
+
+        let mut aggregates = ChunkAggregates {
+            group_columns: schema
+                .tags_iter()
+                .map(|field| field.name().to_string())
+                .collect(),
+            window,
+            aggregates: vec![],
+        };
+        let mut agg_exprs = Vec::with_capacity(field_names.len() + 1);
+        for field_name in &field_names {
+            aggregates.add_aggregate(*field_name, partial_agg);
+            agg_exprs.push(partial_agg.combine_expr(field_name).alias(field_name));
+        }
+
+        // read_group aggregates the time column as well, using `MAX` as in
+        // `make_agg_expr`
+        if window.is_none() {
+            for field in schema.time_iter() {
+                aggregates.add_aggregate(field.name(), PartialAggregate::Max);
+                agg_exprs.push(
+                    PartialAggregate::Max
+                        .combine_expr(field.name())
+                        .alias(field.name()),
+                );
+            }
+        }
+
+        let group_exprs = aggregates
+            .group_columns
+            .iter()
+            .map(|name| name.as_expr())
+            .chain(window.map(|_| TIME_COLUMN_NAME.as_expr()))
+            .collect::<Vec<_>>();
+
+        let plan_builder =
+            scan_partial_aggregates(table_name, schema, chunks.to_vec(), predicate, aggregates)
+                .context(BuildingPlan)?;
+
+        let plan_builder = match plan_builder {
+            Some(plan_builder) => plan_builder
+                .aggregate(group_exprs, agg_exprs)
+                .context(BuildingPlan)?,
+            None => return Ok(None),
+        };
+
+        let field_columns = field_names
+            .into_iter()
+            .map(Arc::from)
+            .collect::<Vec<_>>()
+            .into();
+
+        Ok(Some((plan_builder, field_columns)))
+    }
+
     /// Create a plan that scans the specified table, and applies any
     /// filtering specified on the predicate, if any.
     ///
@@ -1479,6 +1822,156 @@ struct TableScanAndFilter {
     schema: Arc<Schema>,
 }
 
+/// Returns the series of `chunk`, as (tag name, tag value, field name),
+/// if they can be found from its metadata
+///
+/// This is only possible if the predicate selects all of the rows of
+/// the chunk. The series of a chunk without tags are the fields with
+/// values. The series of a chunk with a single tag are found from the
+/// values of the tag, if every row has a value of the tag and each
+/// field.
+fn series_from_metadata<C>(
+    chunk: &C,
+    predicate: &Predicate,
+) -> Result<Option<BTreeSet<(String, String, String)>>>
+where
+    C: QueryChunk,
+{
+    let summary = chunk.summary();
+
+    let time_range = summary
+        .column(TIME_COLUMN_NAME)
+        .and_then(|column| match &column.stats {
+            Statistics::I64(stats) => stats.min.zip(stats.max),
+            _ => None,
+        });
+    let all_rows = predicate.exprs.is_empty()
+        && predicate.value_expr.is_empty()
+        && match (predicate.range, time_range) {
+            (None, _) => true,
+            (Some(range), Some((min, max))) => range.contains(min) && range.contains(max),
+            (Some(_), None) => false,
+        };
+    if !all_rows {
+        return Ok(None);
+    }
+
+    let schema = chunk.schema();
+    let fields: Vec<_> = schema
+        .fields_iter()
+        .map(|field| field.name().as_str())
+        .filter(|name| predicate.should_include_field(name))
+        .collect();
+    let tags: Vec<_> = schema.tags_iter().collect();
+
+    match tags.as_slice() {
+        [] => {
+            let has_values = |name: &&str| {
+                summary
+                    .column(name)
+                    .map(|column| column.null_count() < column.total_count())
+                    .unwrap_or(false)
+            };
+
+            let series = fields
+                .into_iter()
+                .filter(has_values)
+                .map(|name| (String::new(), String::new(), name.to_string()))
+                .collect();
+            Ok(Some(series))
+        }
+        [tag] => {
+            let dense = |name: &str| {
+                summary
+                    .column(name)
+                    .map(|column| column.null_count() == 0)
+                    .unwrap_or(false)
+            };
+            if !dense(tag.name()) || !fields.iter().all(|name| dense(name)) {
+                return Ok(None);
+            }
+
+            let values = chunk
+                .column_values(tag.name(), predicate)
+                .map_err(|e| Box::new(e) as _)
+                .context(FindingColumnValues)?;
+
+            Ok(values.map(|values| {
+                values
+                    .iter()
+                    .flat_map(|value| {
+                        fields
+                            .iter()
+                            .map(move |name| (tag.name().clone(), value.clone(), name.to_string()))
+                    })
+                    .collect()
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Adds the windows in `range` without rows to each series of the
+/// aggregated and sorted `plan`, with a count of 0 and NULL for other
+/// aggregates
+fn make_empty_windows(
+    plan: LogicalPlan,
+    schema: &Schema,
+    agg: Aggregate,
+    every: &WindowDuration,
+    offset: &WindowDuration,
+    range: TimestampRange,
+) -> Result<LogicalPlan> {
+    let plan_schema = Arc::clone(plan.schema());
+    let column = |name: &str| {
+        plan_schema
+            .field_with_unqualified_name(name)
+            .map(|field| field.qualified_column())
+            .context(BuildingPlan)
+    };
+
+    let series_columns = schema
+        .tags_iter()
+        .map(|field| column(field.name()))
+        .collect::<Result<Vec<_>>>()?;
+    let time_column = column(TIME_COLUMN_NAME)?;
+
+    // all other columns are aggregates of fields
+    let fills = match agg {
+        Aggregate::Count => plan_schema
+            .fields()
+            .iter()
+            .map(|field| field.qualified_column())
+            .filter(|c| c != &time_column && !series_columns.contains(c))
+            .map(|c| (c, FillMode::Constant(ScalarValue::Int64(Some(0)))))
+            .collect(),
+        _ => vec![],
+    };
+
+    let params = GapFillParams {
+        every: every.clone(),
+        offset: offset.clone(),
+        range,
+        bound: WindowBound::Stop,
+    };
+    let node = GapFillNode::try_new(plan, series_columns, time_column, params, fills)
+        .context(BuildingPlan)?;
+
+    Ok(LogicalPlan::Extension(Extension {
+        node: Arc::new(node),
+    }))
+}
+
+/// Returns the sort key that orders the rows of a table by series (its
+/// tag columns) and then by time, as produced by `read_filter` plans.
+pub fn series_sort_key(schema: &Schema) -> SortKey<'_> {
+    let mut sort_key = SortKey::with_capacity(schema.len());
+    for field in schema.tags_iter().chain(schema.time_iter()) {
+        sort_key.with_col_opts(field.name(), false, true);
+    }
+    sort_key
+}
+
 /// Helper for creating aggregates
 pub(crate) struct AggExprs {
     agg_exprs: Vec<Expr>,
@@ -2040,16 +2533,31 @@ impl<'a> ExprRewriter for MissingColumnsToNull<'a> {
         //
         // Until then, we need to know what type of expr the column is
         // being compared with, so workaround by finding the datatype of the other arg
-        if let Expr::BinaryExpr { left, op, right } = expr {
-            let left = self.rewrite_op_arg(*left, &right)?;
-            let right = self.rewrite_op_arg(*right, &left)?;
-            Ok(Expr::BinaryExpr {
-                left: Box::new(left),
-                op,
-                right: Box::new(right),
-            })
-        } else {
-            Ok(expr)
+        match expr {
+            Expr::BinaryExpr { left, op, right } => {
+                let left = self.rewrite_op_arg(*left, &right)?;
+                let right = self.rewrite_op_arg(*right, &left)?;
+                Ok(Expr::BinaryExpr {
+                    left: Box::new(left),
+                    op,
+                    right: Box::new(right),
+                })
+            }
+            Expr::IsNull(arg) if self.is_null_column(&arg) => Ok(lit(true)),
+            Expr::IsNotNull(arg) if self.is_null_column(&arg) => Ok(lit(false)),
+            // User defined functions, such as regex matches, are applied to
+            // tags, so a missing column is a null string
+            Expr::ScalarUDF { fun, args } => Ok(Expr::ScalarUDF {
+                fun,
+                args: args
+                    .into_iter()
+                    .map(|arg| match self.is_null_column(&arg) {
+                        true => Expr::Literal(ScalarValue::Utf8(None)),
+                        false => arg,
+                    })
+                    .collect(),
+            }),
+            expr => Ok(expr),
         }
     }
 }
@@ -2057,6 +2565,7 @@ impl<'a> ExprRewriter for MissingColumnsToNull<'a> {
 #[cfg(test)]
 mod tests {
     use datafusion::logical_plan::{binary_expr, Operator};
+    use predicate::regex::regex_match_expr;
     use schema::builder::SchemaBuilder;
 
     use super::*;
@@ -2130,7 +2639,24 @@ mod tests {
 
         // int < 5 OR unknown != "foo"
         let expr = col("int").lt(lit(5)).or(col("unknown").not_eq(lit("foo")));
-        let expected = col("int").lt(lit(5)).or(utf8_null.not_eq(lit("foo")));
+        let expected = col("int")
+            .lt(lit(5))
+            .or(utf8_null.clone().not_eq(lit("foo")));
+        assert_rewrite(&schema, &expr, &expected);
+
+        // unknown IS NULL --> true
+        let expr = col("tag").is_null().or(col("unknown").is_null());
+        let expected = col("tag").is_null().or(lit(true));
+        assert_rewrite(&schema, &expr, &expected);
+
+        // unknown IS NOT NULL --> false
+        let expr = col("unknown").is_not_null();
+        let expected = lit(false);
+        assert_rewrite(&schema, &expr, &expected);
+
+        // regex(unknown) --> regex(NULL)
+        let expr = regex_match_expr(col("unknown"), "foo".to_string(), true);
+        let expected = regex_match_expr(utf8_null, "foo".to_string(), true);
         assert_rewrite(&schema, &expr, &expected);
     }
 
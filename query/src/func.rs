@@ -1,3 +1,6 @@
 //! Special IOx functions used in DataFusion plans
+pub mod aggregates;
+pub mod gapfill;
 pub mod selectors;
+pub mod transform;
 pub mod window;
@@ -0,0 +1,635 @@
+//! Implementation of InfluxDB aggregate functions DataFusion does not
+//! provide
+//!
+//! * `percentile(value, p)`: estimates the `p`th percentile (0 to 100)
+//! * `median(value)`: estimates the 50th percentile
+//! * `stddev(value)`: the sample standard deviation
+//! * `spread(value)`: the difference between the maximum and minimum
+//! * `approx_count_distinct(value)`: estimates the number of distinct values
+//!
+//! Percentiles are estimated with a t-digest and distinct values are
+//! counted with a HyperLogLog sketch, so that all of them can be computed
+//! in bounded memory and merged across partitions.
+use std::sync::Arc;
+
+use arrow::{
+    array::{
+        Array, ArrayRef, BinaryArray, BooleanArray, Float64Array, Int64Array, StringArray,
+        UInt64Array,
+    },
+    datatypes::DataType,
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    physical_plan::{
+        aggregates::{AccumulatorFunctionImplementation, StateTypeFunction},
+        functions::{ReturnTypeFunction, Signature, Volatility},
+        udaf::AggregateUDF,
+        Accumulator,
+    },
+    scalar::ScalarValue,
+};
+
+pub(crate) mod hyperloglog;
+mod tdigest;
+
+use hyperloglog::HyperLogLog;
+use tdigest::TDigest;
+
+pub const PERCENTILE_UDAF_NAME: &str = "percentile";
+pub const MEDIAN_UDAF_NAME: &str = "median";
+pub const STDDEV_UDAF_NAME: &str = "stddev";
+pub const SPREAD_UDAF_NAME: &str = "spread";
+pub const APPROX_COUNT_DISTINCT_UDAF_NAME: &str = "approx_count_distinct";
+
+/// Returns all aggregate functions of this module, to make them available
+/// to SQL queries
+pub fn all_aggregates() -> Vec<AggregateUDF> {
+    vec![
+        percentile(),
+        median(),
+        stddev(),
+        spread(),
+        approx_count_distinct(),
+    ]
+}
+
+/// Returns a DataFusion user defined aggregate function estimating a
+/// percentile of its first argument
+///
+/// percentile(value, p) -> the `p`th percentile of `value`
+///
+/// `p` must be a constant between 0 and 100. The percentile is
+/// interpolated between the values surrounding it.
+pub fn percentile() -> AggregateUDF {
+    make_udaf(
+        PERCENTILE_UDAF_NAME,
+        Signature::exact(
+            vec![DataType::Float64, DataType::Float64],
+            Volatility::Immutable,
+        ),
+        DataType::Float64,
+        vec![DataType::Binary, DataType::Float64],
+        || Box::new(PercentileAccumulator::new(None)),
+    )
+}
+
+/// Returns a DataFusion user defined aggregate function estimating the
+/// median of its argument
+///
+/// median(value) -> percentile(value, 50)
+pub fn median() -> AggregateUDF {
+    make_udaf(
+        MEDIAN_UDAF_NAME,
+        Signature::uniform(1, vec![DataType::Float64], Volatility::Immutable),
+        DataType::Float64,
+        vec![DataType::Binary, DataType::Float64],
+        || Box::new(PercentileAccumulator::new(Some(50.0))),
+    )
+}
+
+/// Returns a DataFusion user defined aggregate function computing the
+/// sample standard deviation of its argument
+///
+/// stddev(value) -> the standard deviation of `value`, or NULL if there are
+/// fewer than two values
+pub fn stddev() -> AggregateUDF {
+    make_udaf(
+        STDDEV_UDAF_NAME,
+        Signature::uniform(1, vec![DataType::Float64], Volatility::Immutable),
+        DataType::Float64,
+        vec![DataType::UInt64, DataType::Float64, DataType::Float64],
+        || Box::new(StddevAccumulator::default()),
+    )
+}
+
+/// Returns a DataFusion user defined aggregate function computing the
+/// difference between the maximum and minimum of its argument
+///
+/// spread(value) -> max(value) - min(value)
+pub fn spread() -> AggregateUDF {
+    make_udaf(
+        SPREAD_UDAF_NAME,
+        Signature::uniform(1, vec![DataType::Float64], Volatility::Immutable),
+        DataType::Float64,
+        vec![DataType::Float64, DataType::Float64],
+        || Box::new(SpreadAccumulator::default()),
+    )
+}
+
+/// Returns a DataFusion user defined aggregate function estimating the
+/// number of distinct non-null values of its argument, of any type
+///
+/// approx_count_distinct(value) -> the number of distinct values
+pub fn approx_count_distinct() -> AggregateUDF {
+    make_udaf(
+        APPROX_COUNT_DISTINCT_UDAF_NAME,
+        Signature::any(1, Volatility::Immutable),
+        DataType::UInt64,
+        vec![DataType::Binary],
+        || Box::new(ApproxCountDistinctAccumulator::default()),
+    )
+}
+
+/// Factory function for creating the UDA function for DataFusion
+fn make_udaf(
+    name: &str,
+    signature: Signature,
+    return_type: DataType,
+    state_type: Vec<DataType>,
+    accumulator: fn() -> Box<dyn Accumulator>,
+) -> AggregateUDF {
+    let return_type = Arc::new(return_type);
+    let return_type_func: ReturnTypeFunction = Arc::new(move |_| Ok(Arc::clone(&return_type)));
+
+    let state_type = Arc::new(state_type);
+    let state_type_factory: StateTypeFunction = Arc::new(move |_| Ok(Arc::clone(&state_type)));
+
+    let factory: AccumulatorFunctionImplementation = Arc::new(move || Ok(accumulator()));
+
+    AggregateUDF::new(
+        name,
+        &signature,
+        &return_type_func,
+        &factory,
+        &state_type_factory,
+    )
+}
+
+/// Downcasts an argument or state array, whose type is ensured by
+/// DataFusion
+fn downcast<T: 'static>(array: &ArrayRef) -> DataFusionResult<&T> {
+    array.as_any().downcast_ref::<T>().ok_or_else(|| {
+        DataFusionError::Internal(format!(
+            "Internal error: unexpected aggregate input type {:?}",
+            array.data_type()
+        ))
+    })
+}
+
+/// Returns the `index`th array of `arrays`
+fn arg(arrays: &[ArrayRef], index: usize) -> DataFusionResult<&ArrayRef> {
+    arrays.get(index).ok_or_else(|| {
+        DataFusionError::Internal(format!(
+            "Internal error: expected at least {} aggregate inputs but got {}",
+            index + 1,
+            arrays.len()
+        ))
+    })
+}
+
+/// Estimates percentiles with a t-digest
+#[derive(Debug)]
+struct PercentileAccumulator {
+    digest: TDigest,
+
+    /// The percentile to estimate, known once the first value of the
+    /// percentile argument or state is seen unless fixed
+    percentile: Option<f64>,
+}
+
+impl PercentileAccumulator {
+    fn new(percentile: Option<f64>) -> Self {
+        Self {
+            digest: TDigest::default(),
+            percentile,
+        }
+    }
+
+    /// Sets the percentile from the first non-null value of `array`
+    fn set_percentile(&mut self, array: &ArrayRef) -> DataFusionResult<()> {
+        if self.percentile.is_some() {
+            return Ok(());
+        }
+
+        if let Some(percentile) = downcast::<Float64Array>(array)?.iter().flatten().next() {
+            if !(0.0..=100.0).contains(&percentile) {
+                return Err(DataFusionError::Execution(format!(
+                    "percentile must be between 0 and 100, got {}",
+                    percentile
+                )));
+            }
+            self.percentile = Some(percentile);
+        }
+        Ok(())
+    }
+}
+
+impl Accumulator for PercentileAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        Ok(vec![
+            ScalarValue::Binary(Some(self.digest.to_bytes())),
+            ScalarValue::Float64(self.percentile),
+        ])
+    }
+
+    fn update(&mut self, _values: &[ScalarValue]) -> DataFusionResult<()> {
+        unreachable!("Should only be calling update_batch for performance reasons");
+    }
+
+    fn merge(&mut self, _states: &[ScalarValue]) -> DataFusionResult<()> {
+        unreachable!("Should only be calling merge_batch for performance reasons");
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        for value in downcast::<Float64Array>(arg(values, 0)?)?.iter().flatten() {
+            self.digest.add(value);
+        }
+
+        // median has no percentile argument
+        if let Some(percentile) = values.get(1) {
+            self.set_percentile(percentile)?;
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        for bytes in downcast::<BinaryArray>(arg(states, 0)?)?.iter().flatten() {
+            let digest = TDigest::from_bytes(bytes).ok_or_else(|| {
+                DataFusionError::Internal("Internal error: invalid t-digest state".to_string())
+            })?;
+            self.digest.merge(&digest);
+        }
+        self.set_percentile(arg(states, 1)?)
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let value = self
+            .percentile
+            .and_then(|percentile| self.digest.quantile(percentile / 100.0));
+        Ok(ScalarValue::Float64(value))
+    }
+}
+
+/// Computes the sample standard deviation with Welford's algorithm
+#[derive(Debug, Default)]
+struct StddevAccumulator {
+    count: u64,
+    mean: f64,
+    /// The sum of squared differences from the mean
+    m2: f64,
+}
+
+impl StddevAccumulator {
+    /// Combines the statistics of another set of values with these
+    fn combine(&mut self, count: u64, mean: f64, m2: f64) {
+        if count == 0 {
+            return;
+        }
+        let total = self.count + count;
+        let delta = mean - self.mean;
+        self.mean += delta * count as f64 / total as f64;
+        self.m2 += m2 + delta * delta * self.count as f64 * count as f64 / total as f64;
+        self.count = total;
+    }
+}
+
+impl Accumulator for StddevAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        Ok(vec![
+            ScalarValue::UInt64(Some(self.count)),
+            ScalarValue::Float64(Some(self.mean)),
+            ScalarValue::Float64(Some(self.m2)),
+        ])
+    }
+
+    fn update(&mut self, _values: &[ScalarValue]) -> DataFusionResult<()> {
+        unreachable!("Should only be calling update_batch for performance reasons");
+    }
+
+    fn merge(&mut self, _states: &[ScalarValue]) -> DataFusionResult<()> {
+        unreachable!("Should only be calling merge_batch for performance reasons");
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        for value in downcast::<Float64Array>(arg(values, 0)?)?.iter().flatten() {
+            self.count += 1;
+            let delta = value - self.mean;
+            self.mean += delta / self.count as f64;
+            self.m2 += delta * (value - self.mean);
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        let counts = downcast::<UInt64Array>(arg(states, 0)?)?;
+        let means = downcast::<Float64Array>(arg(states, 1)?)?;
+        let m2s = downcast::<Float64Array>(arg(states, 2)?)?;
+        for ((count, mean), m2) in counts.iter().zip(means.iter()).zip(m2s.iter()) {
+            if let (Some(count), Some(mean), Some(m2)) = (count, mean, m2) {
+                self.combine(count, mean, m2);
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let value = (self.count > 1).then(|| (self.m2 / (self.count - 1) as f64).sqrt());
+        Ok(ScalarValue::Float64(value))
+    }
+}
+
+/// Tracks the minimum and maximum value
+#[derive(Debug, Default)]
+struct SpreadAccumulator {
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl SpreadAccumulator {
+    fn add(&mut self, array: &ArrayRef) -> DataFusionResult<()> {
+        for value in downcast::<Float64Array>(array)?.iter().flatten() {
+            self.min = Some(self.min.map_or(value, |min| min.min(value)));
+            self.max = Some(self.max.map_or(value, |max| max.max(value)));
+        }
+        Ok(())
+    }
+}
+
+impl Accumulator for SpreadAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        Ok(vec![
+            ScalarValue::Float64(self.min),
+            ScalarValue::Float64(self.max),
+        ])
+    }
+
+    fn update(&mut self, _values: &[ScalarValue]) -> DataFusionResult<()> {
+        unreachable!("Should only be calling update_batch for performance reasons");
+    }
+
+    fn merge(&mut self, _states: &[ScalarValue]) -> DataFusionResult<()> {
+        unreachable!("Should only be calling merge_batch for performance reasons");
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        self.add(arg(values, 0)?)
+    }
+
+    // The minimum and maximum of the states are the minimum and maximum of
+    // all their values
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+        self.add(arg(states, 0)?)?;
+        self.add(arg(states, 1)?)
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let value = self.min.zip(self.max).map(|(min, max)| max - min);
+        Ok(ScalarValue::Float64(value))
+    }
+}
+
+/// Estimates the number of distinct values with a HyperLogLog sketch
+#[derive(Debug, Default)]
+struct ApproxCountDistinctAccumulator {
+    hll: HyperLogLog,
+}
+
+impl Accumulator for ApproxCountDistinctAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        Ok(vec![ScalarValue::Binary(Some(self.hll.to_bytes()))])
+    }
+
+    fn update(&mut self, _values: &[ScalarValue]) -> DataFusionResult<()> {
+        unreachable!("Should only be calling update_batch for performance reasons");
+    }
+
+    fn merge(&mut self, _states: &[ScalarValue]) -> DataFusionResult<()> {
+        unreachable!("Should only be calling merge_batch for performance reasons");
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let array = arg(values, 0)?;
+        match array.data_type() {
+            DataType::Int64 => downcast::<Int64Array>(array)?
+                .iter()
+                .flatten()
+                .for_each(|v| self.hll.add(&v)),
+            DataType::Float64 => downcast::<Float64Array>(array)?
+                .iter()
+                .flatten()
+                .for_each(|v| self.hll.add(&v.to_bits())),
+            DataType::Utf8 => downcast::<StringArray>(array)?
+                .iter()
+                .flatten()
+                .for_each(|v| self.hll.add(v)),
+            DataType::Boolean => downcast::<BooleanArray>(array)?
+                .iter()
+                .flatten()
+                .for_each(|v| self.hll.add(&v)),
+            // e.g. tags and timestamps
+            _ => {
+                for index in 0..array.len() {
+                    if array.is_valid(index) {
+                        self.hll.add(&ScalarValue::try_from_array(array, index)?);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        for bytes in downcast::<BinaryArray>(arg(states, 0)?)?.iter().flatten() {
+            let hll = HyperLogLog::from_bytes(bytes).ok_or_else(|| {
+                DataFusionError::Internal("Internal error: invalid HyperLogLog state".to_string())
+            })?;
+            self.hll.merge(&hll);
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        Ok(ScalarValue::UInt64(Some(self.hll.count())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use arrow::{
+        array::DictionaryArray,
+        datatypes::{Field, Int32Type, Schema},
+        record_batch::RecordBatch,
+        util::pretty::pretty_format_batches,
+    };
+    use datafusion::{datasource::MemTable, logical_plan::Expr, prelude::*};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_aggregates() {
+        let aggs = vec![
+            percentile().call(vec![col("f"), lit(25.0)]).alias("p25"),
+            percentile().call(vec![col("f"), lit(90.0)]).alias("p90"),
+            median().call(vec![col("f")]).alias("median"),
+            median().call(vec![col("i")]).alias("median_i"),
+            stddev().call(vec![col("f")]).alias("stddev"),
+            spread().call(vec![col("i")]).alias("spread"),
+            approx_count_distinct()
+                .call(vec![col("f")])
+                .alias("distinct"),
+            approx_count_distinct()
+                .call(vec![col("tag")])
+                .alias("distinct_tag"),
+        ];
+
+        let expected = vec![
+            "+-----+-----+--------+----------+--------+--------+----------+--------------+",
+            "| p25 | p90 | median | median_i | stddev | spread | distinct | distinct_tag |",
+            "+-----+-----+--------+----------+--------+--------+----------+--------------+",
+            "| 3   | 7   | 5      | 50       | 2      | 40     | 3        | 2            |",
+            "+-----+-----+--------+----------+--------+--------+----------+--------------+",
+        ];
+
+        let actual = run_plan(aggs).await;
+        assert_eq!(
+            expected, actual,
+            "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+            expected, actual
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty() {
+        let aggs = vec![
+            median().call(vec![col("f")]).alias("median"),
+            stddev().call(vec![col("f")]).alias("stddev"),
+            spread().call(vec![col("f")]).alias("spread"),
+            approx_count_distinct()
+                .call(vec![col("f")])
+                .alias("distinct"),
+        ];
+
+        let expected = vec![
+            "+--------+--------+--------+----------+",
+            "| median | stddev | spread | distinct |",
+            "+--------+--------+--------+----------+",
+            "|        |        |        | 0        |",
+            "+--------+--------+--------+----------+",
+        ];
+
+        let actual = run_with_inputs(aggs, vec![]).await;
+        assert_eq!(
+            expected, actual,
+            "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+            expected, actual
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_percentile() {
+        let provider = MemTable::try_new(schema(), vec![input()]).unwrap();
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table("t", Arc::new(provider)).unwrap();
+
+        let df = ctx.table("t").unwrap();
+        let df = df
+            .aggregate(vec![], vec![percentile().call(vec![col("f"), lit(101.0)])])
+            .unwrap();
+
+        let err = df.collect().await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("percentile must be between 0 and 100, got 101"),
+            "{}",
+            err
+        );
+    }
+
+    fn schema() -> Arc<Schema> {
+        Arc::new(Schema::new(vec![
+            Field::new("f", DataType::Float64, true),
+            Field::new("i", DataType::Int64, true),
+            Field::new(
+                "tag",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+        ]))
+    }
+
+    /// Two batches with the values 3, 3, 5, 7, 7 and 30, 30, 50, 70, 70
+    fn input() -> Vec<RecordBatch> {
+        let batch1 = RecordBatch::try_new(
+            schema(),
+            vec![
+                Arc::new(Float64Array::from(vec![Some(3.0), None, Some(5.0)])),
+                Arc::new(Int64Array::from(vec![Some(30), None, Some(50)])),
+                Arc::new(
+                    vec![Some("a"), None, Some("b")]
+                        .into_iter()
+                        .collect::<DictionaryArray<Int32Type>>(),
+                ),
+            ],
+        )
+        .unwrap();
+
+        let batch2 = RecordBatch::try_new(
+            schema(),
+            vec![
+                Arc::new(Float64Array::from(vec![Some(7.0), Some(3.0), Some(7.0)])),
+                Arc::new(Int64Array::from(vec![Some(70), Some(30), Some(70)])),
+                Arc::new(
+                    vec![Some("a"), Some("a"), Some("b")]
+                        .into_iter()
+                        .collect::<DictionaryArray<Int32Type>>(),
+                ),
+            ],
+        )
+        .unwrap();
+
+        vec![batch1, batch2]
+    }
+
+    async fn run_plan(aggs: Vec<Expr>) -> Vec<String> {
+        run_with_inputs(aggs, input()).await
+    }
+
+    async fn run_with_inputs(aggs: Vec<Expr>, inputs: Vec<RecordBatch>) -> Vec<String> {
+        let provider = MemTable::try_new(schema(), vec![inputs]).unwrap();
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table("t", Arc::new(provider)).unwrap();
+
+        let df = ctx.table("t").unwrap();
+        let df = df.aggregate(vec![], aggs).unwrap();
+
+        // execute the query
+        let record_batches = df.collect().await.unwrap();
+
+        pretty_format_batches(&record_batches)
+            .unwrap()
+            .split('\n')
+            .map(|s| s.to_owned())
+            .collect()
+    }
+}
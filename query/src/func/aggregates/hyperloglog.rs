@@ -0,0 +1,140 @@
+//! A HyperLogLog sketch, estimating the number of distinct values of a
+//! stream in bounded memory
+//!
+//! See Flajolet et al., "HyperLogLog: the analysis of a near-optimal
+//! cardinality estimation algorithm"
+//! <http://algo.inria.fr/flajolet/Publications/FlFuGaMe07.pdf>
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// The number of bits of a hash selecting its register
+const PRECISION: u32 = 12;
+
+/// The number of registers, with a standard error of about 1.6%
+const NUM_REGISTERS: usize = 1 << PRECISION;
+
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; NUM_REGISTERS],
+        }
+    }
+}
+
+impl HyperLogLog {
+    /// Adds a value to the sketch
+    pub fn add<T: Hash + ?Sized>(&mut self, value: &T) {
+        // The default hasher uses fixed keys, so equal values hash the same
+        // in all sketches that are merged
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        self.add_hash(hasher.finish());
+    }
+
+    fn add_hash(&mut self, hash: u64) {
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // The sentinel bit bounds the rank if the remaining bits are zero
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// Adds all values added to `other` to this sketch
+    pub fn merge(&mut self, other: &Self) {
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+    }
+
+    /// Estimates the number of distinct values added
+    pub fn count(&self) -> u64 {
+        let m = NUM_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|register| 2f64.powi(-(*register as i32)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // Small cardinalities are estimated better by linear counting
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        let estimate = if estimate <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            estimate
+        };
+        estimate.round() as u64
+    }
+
+    /// Serializes the sketch, to be restored with [`Self::from_bytes`]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.registers.clone()
+    }
+
+    /// Restores a sketch serialized by [`Self::to_bytes`], returning `None`
+    /// if `bytes` is not a serialized sketch
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == NUM_REGISTERS).then(|| Self {
+            registers: bytes.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small() {
+        let mut hll = HyperLogLog::default();
+        assert_eq!(hll.count(), 0);
+
+        for v in ["a", "b", "a", "c", "b"] {
+            hll.add(v);
+        }
+        assert_eq!(hll.count(), 3);
+    }
+
+    #[test]
+    fn test_accuracy() {
+        let mut hll = HyperLogLog::default();
+        for v in 0..100_000_i64 {
+            hll.add(&v);
+            hll.add(&v);
+        }
+
+        let count = hll.count() as f64;
+        assert!(
+            (count - 100_000.0).abs() < 5_000.0,
+            "estimated {} distinct values",
+            count
+        );
+    }
+
+    #[test]
+    fn test_merge_and_serialize() {
+        let mut left = HyperLogLog::default();
+        let mut right = HyperLogLog::default();
+        for v in 0..1000_i64 {
+            left.add(&v);
+            right.add(&(v + 500));
+        }
+
+        let mut merged = HyperLogLog::from_bytes(&left.to_bytes()).unwrap();
+        merged.merge(&HyperLogLog::from_bytes(&right.to_bytes()).unwrap());
+
+        let count = merged.count() as f64;
+        assert!((count - 1500.0).abs() < 50.0, "estimated {}", count);
+
+        assert!(HyperLogLog::from_bytes(&[0; 3]).is_none());
+    }
+}
@@ -0,0 +1,278 @@
+//! A merging t-digest, estimating quantiles of a stream of values in
+//! bounded memory
+//!
+//! See Dunning and Ertl, "Computing Extremely Accurate Quantiles Using
+//! t-Digests" <https://arxiv.org/abs/1902.04023>
+
+use std::{cmp::Ordering, f64::consts::PI};
+
+/// The compression of digests: the number of centroids is bounded by
+/// about this number, and quantiles are most accurate near the extremes
+pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+/// The number of centroids buffered per unit of compression before they
+/// are merged into the digest
+const BUFFER_FACTOR: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TDigest {
+    compression: f64,
+
+    /// Merged centroids, ordered by mean
+    centroids: Vec<Centroid>,
+
+    /// Centroids not yet merged, in any order
+    buffer: Vec<Centroid>,
+
+    min: f64,
+    max: f64,
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        Self {
+            compression,
+            centroids: vec![],
+            buffer: vec![],
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Adds a value to the digest. NaN values are ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.push(Centroid {
+            mean: value,
+            weight: 1.0,
+        });
+    }
+
+    /// Adds all values summarised by `other` to this digest
+    pub fn merge(&mut self, other: &Self) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        for centroid in other.centroids.iter().chain(&other.buffer) {
+            self.push(*centroid);
+        }
+    }
+
+    fn push(&mut self, centroid: Centroid) {
+        self.buffer.push(centroid);
+        if self.buffer.len() >= BUFFER_FACTOR * self.compression as usize {
+            self.centroids = self.merged();
+            self.buffer.clear();
+        }
+    }
+
+    /// The number of values added to the digest
+    pub fn count(&self) -> f64 {
+        self.centroids
+            .iter()
+            .chain(&self.buffer)
+            .map(|c| c.weight)
+            .sum()
+    }
+
+    /// Returns the centroids of this digest and its buffer, merging
+    /// adjacent centroids as long as their weight stays within the bound of
+    /// the k1 scale function
+    fn merged(&self) -> Vec<Centroid> {
+        let mut all: Vec<_> = self.centroids.iter().chain(&self.buffer).copied().collect();
+        if all.len() <= 1 {
+            return all;
+        }
+        all.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+        let k = |q: f64| self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin();
+        let k_inverse = |k: f64| ((k * 2.0 * PI / self.compression).sin() + 1.0) / 2.0;
+        let weight_limit = |weight_before: f64| {
+            let q = (weight_before / total).min(1.0);
+            total * k_inverse((k(q) + 1.0).min(self.compression / 4.0))
+        };
+
+        let mut merged = Vec::with_capacity(all.len());
+        let mut iter = all.into_iter();
+        let mut current = iter.next().expect("at least two centroids");
+        let mut weight_before = 0.0;
+        let mut limit = weight_limit(weight_before);
+
+        for centroid in iter {
+            if weight_before + current.weight + centroid.weight <= limit {
+                let weight = current.weight + centroid.weight;
+                current.mean += (centroid.mean - current.mean) * centroid.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_before += current.weight;
+                merged.push(current);
+                limit = weight_limit(weight_before);
+                current = centroid;
+            }
+        }
+        merged.push(current);
+        merged
+    }
+
+    /// Estimates the value at quantile `q`, between 0 and 1, interpolating
+    /// between the centroids surrounding it. Returns `None` if no values
+    /// were added.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let centroids = self.merged();
+        let first = centroids.first()?;
+        let last = centroids.last()?;
+
+        let q = q.clamp(0.0, 1.0);
+        let total: f64 = centroids.iter().map(|c| c.weight).sum();
+        let rank = q * total;
+
+        // Each centroid is centered on the middle of its weight, beyond
+        // the first and last centers interpolate towards the extremes
+        if rank <= first.weight / 2.0 {
+            let fraction = if first.weight > 0.0 {
+                rank / (first.weight / 2.0)
+            } else {
+                0.0
+            };
+            return Some(self.min + (first.mean - self.min) * fraction);
+        }
+        if rank >= total - last.weight / 2.0 {
+            let fraction = if last.weight > 0.0 {
+                (total - rank) / (last.weight / 2.0)
+            } else {
+                0.0
+            };
+            return Some(self.max - (self.max - last.mean) * fraction);
+        }
+
+        let mut center = first.weight / 2.0;
+        for pair in centroids.windows(2) {
+            let (left, right) = (pair[0], pair[1]);
+            let next_center = center + (left.weight + right.weight) / 2.0;
+            if rank <= next_center {
+                let fraction = (rank - center) / (next_center - center);
+                return Some(left.mean + (right.mean - left.mean) * fraction);
+            }
+            center = next_center;
+        }
+        Some(last.mean)
+    }
+
+    /// Serializes the digest, to be restored with [`Self::from_bytes`]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let centroids = self.merged();
+        let mut bytes = Vec::with_capacity(8 * (3 + 2 * centroids.len()));
+        for v in [self.compression, self.min, self.max] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        for centroid in centroids {
+            bytes.extend_from_slice(&centroid.mean.to_le_bytes());
+            bytes.extend_from_slice(&centroid.weight.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Restores a digest serialized by [`Self::to_bytes`], returning `None`
+    /// if `bytes` is not a serialized digest
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 24 || bytes.len() % 16 != 8 {
+            return None;
+        }
+        let mut values = bytes
+            .chunks_exact(8)
+            .map(|chunk| f64::from_le_bytes(chunk.try_into().expect("8 bytes")));
+
+        let mut digest = Self::new(values.next()?);
+        digest.min = values.next()?;
+        digest.max = values.next()?;
+        while let (Some(mean), Some(weight)) = (values.next(), values.next()) {
+            digest.centroids.push(Centroid { mean, weight });
+        }
+        Some(digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty() {
+        let digest = TDigest::default();
+        assert_eq!(digest.quantile(0.5), None);
+        assert_eq!(digest.count(), 0.0);
+    }
+
+    #[test]
+    fn test_small() {
+        let mut digest = TDigest::default();
+        for v in [3.0, 1.0, 4.0, 2.0, f64::NAN] {
+            digest.add(v);
+        }
+
+        assert_eq!(digest.count(), 4.0);
+        assert_eq!(digest.quantile(0.0), Some(1.0));
+        assert_eq!(digest.quantile(0.5), Some(2.5));
+        assert_eq!(digest.quantile(1.0), Some(4.0));
+    }
+
+    #[test]
+    fn test_accuracy() {
+        let mut digest = TDigest::default();
+        for v in (0..100_000).rev() {
+            digest.add(v as f64);
+        }
+        assert!(digest.centroids.len() < 200);
+
+        for (q, expected) in [(0.01, 1_000.0), (0.5, 50_000.0), (0.99, 99_000.0)] {
+            let actual = digest.quantile(q).unwrap();
+            assert!(
+                (actual - expected).abs() < 500.0,
+                "quantile {} was {}, expected about {}",
+                q,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_and_serialize() {
+        let mut left = TDigest::default();
+        let mut right = TDigest::default();
+        for v in 0..1000 {
+            if v % 2 == 0 {
+                left.add(v as f64)
+            } else {
+                right.add(v as f64)
+            }
+        }
+
+        let mut merged = TDigest::from_bytes(&left.to_bytes()).unwrap();
+        merged.merge(&TDigest::from_bytes(&right.to_bytes()).unwrap());
+
+        assert_eq!(merged.count(), 1000.0);
+        assert_eq!(merged.quantile(0.0), Some(0.0));
+        assert_eq!(merged.quantile(1.0), Some(999.0));
+        let median = merged.quantile(0.5).unwrap();
+        assert!((median - 499.5).abs() < 5.0, "median was {}", median);
+
+        assert!(TDigest::from_bytes(&[1, 2, 3]).is_none());
+    }
+}
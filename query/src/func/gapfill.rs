@@ -0,0 +1,429 @@
+//! Implementation of the functions filling the gaps of windowed
+//! aggregates, so that windows without any rows are not silently
+//! dropped from the output
+//!
+//! * `date_bin_gapfill(stride, time)`: the start of the window of length
+//!   `stride` containing `time`, with windows aligned to the Unix epoch.
+//!   When used as a `GROUP BY` expression, a row is emitted for every
+//!   window in the time range of the `WHERE` clause, for each group.
+//! * `locf(aggregate)`: fills missing windows with the last value of the
+//!   series
+//! * `interpolate(aggregate)`: fills missing windows with the value
+//!   linearly interpolated between the surrounding values of the series
+//! * `fill_value(aggregate, value)`: fills missing windows with a constant
+//!
+//! Missing windows are NULL by default. The rows for missing windows are
+//! emitted by a `GapFillNode` (see [`crate::exec::gapfill`]), which is
+//! planned in place of the fill functions.
+use std::sync::Arc;
+
+use arrow::{
+    array::{ArrayRef, TimestampNanosecondArray},
+    datatypes::{DataType, IntervalUnit},
+};
+use data_types::timestamp::TimestampRange;
+use datafusion::{
+    error::{DataFusionError, Result},
+    logical_plan::Expr,
+    physical_plan::{
+        functions::{
+            make_scalar_function, ReturnTypeFunction, ScalarFunctionImplementation, Signature,
+            TypeSignature, Volatility,
+        },
+        udf::ScalarUDF,
+    },
+    scalar::ScalarValue,
+};
+use schema::TIME_DATA_TYPE;
+
+use crate::{
+    func::window::{self, Window},
+    group_by::WindowDuration,
+};
+
+pub const DATE_BIN_GAPFILL_UDF_NAME: &str = "date_bin_gapfill";
+pub const LOCF_UDF_NAME: &str = "locf";
+pub const INTERPOLATE_UDF_NAME: &str = "interpolate";
+pub const FILL_VALUE_UDF_NAME: &str = "fill_value";
+
+/// The maximum number of windows filled per series, which protects
+/// against exhausting memory with tiny windows over a large time range
+pub const MAX_GAP_FILL_WINDOWS: usize = 1_000_000;
+
+const NANOSECONDS_PER_DAY: i64 = 86_400_000_000_000;
+const NANOSECONDS_PER_MILLISECOND: i64 = 1_000_000;
+
+/// Returns all gap filling functions of this module, to make them
+/// available to SQL queries
+pub fn all_gapfill_functions() -> Vec<ScalarUDF> {
+    vec![
+        date_bin_gapfill(),
+        make_fill_udf(LOCF_UDF_NAME, 1),
+        make_fill_udf(INTERPOLATE_UDF_NAME, 1),
+        make_fill_udf(FILL_VALUE_UDF_NAME, 2),
+    ]
+}
+
+fn date_bin_gapfill() -> ScalarUDF {
+    let signature = Signature::one_of(
+        vec![
+            TypeSignature::Exact(vec![
+                DataType::Interval(IntervalUnit::DayTime),
+                TIME_DATA_TYPE(),
+            ]),
+            TypeSignature::Exact(vec![
+                DataType::Interval(IntervalUnit::YearMonth),
+                TIME_DATA_TYPE(),
+            ]),
+        ],
+        Volatility::Immutable,
+    );
+    let return_type: ReturnTypeFunction = Arc::new(|_| Ok(Arc::new(TIME_DATA_TYPE())));
+    let fun = make_scalar_function(date_bin);
+
+    ScalarUDF::new(DATE_BIN_GAPFILL_UDF_NAME, &signature, &return_type, &fun)
+}
+
+/// Computes the start of the window containing each timestamp
+fn date_bin(args: &[ArrayRef]) -> Result<ArrayRef> {
+    // this is guaranteed by DataFusion based on the function's signature.
+    assert_eq!(args.len(), 2);
+
+    let time = args[1]
+        .as_any()
+        .downcast_ref::<TimestampNanosecondArray>()
+        .expect("cast of time failed");
+    if time.is_empty() {
+        return Ok(Arc::clone(&args[1]));
+    }
+
+    // The stride is a constant, which DataFusion passes as an array
+    let stride = ScalarValue::try_from_array(&args[0], 0)?;
+    let every = scalar_window_duration(&stride).ok_or_else(|| {
+        DataFusionError::Execution(format!(
+            "{} requires a positive interval as stride, got {}",
+            DATE_BIN_GAPFILL_UDF_NAME, stride
+        ))
+    })?;
+    let window = make_window(&every, &WindowDuration::empty());
+
+    let array = time
+        .iter()
+        .map(|ts| ts.map(|ts| window.get_earliest_bounds(ts).start))
+        .collect::<TimestampNanosecondArray>();
+    Ok(Arc::new(array))
+}
+
+/// Creates a fill function, which fails if it is executed as a scalar
+/// function rather than planned as part of a gap filling aggregate
+fn make_fill_udf(name: &'static str, arg_count: usize) -> ScalarUDF {
+    let signature = Signature::any(arg_count, Volatility::Immutable);
+    let return_type: ReturnTypeFunction = Arc::new(|arg_types| Ok(Arc::new(arg_types[0].clone())));
+
+    let fun: ScalarFunctionImplementation = Arc::new(move |_| {
+        Err(DataFusionError::Plan(format!(
+            "{} can only be applied to the aggregates of a query grouped by {}",
+            name, DATE_BIN_GAPFILL_UDF_NAME
+        )))
+    });
+
+    ScalarUDF::new(name, &signature, &return_type, &fun)
+}
+
+/// Returns the stride of a call to `date_bin_gapfill` and the expression
+/// of the binned time, or `None` if `expr` does not call `date_bin_gapfill`
+pub fn date_bin_gapfill_args(expr: &Expr) -> Result<Option<(WindowDuration, &Expr)>> {
+    match expr {
+        Expr::Alias(expr, _) => date_bin_gapfill_args(expr),
+        Expr::ScalarUDF { fun, args } if fun.name == DATE_BIN_GAPFILL_UDF_NAME => {
+            match args.as_slice() {
+                [Expr::Literal(stride), time] => match scalar_window_duration(stride) {
+                    Some(every) => Ok(Some((every, time))),
+                    None => Err(DataFusionError::Plan(format!(
+                        "{} requires a positive interval as stride, got {}",
+                        DATE_BIN_GAPFILL_UDF_NAME, stride
+                    ))),
+                },
+                [stride, _] => Err(DataFusionError::Plan(format!(
+                    "{} requires a constant stride, got {}",
+                    DATE_BIN_GAPFILL_UDF_NAME, stride
+                ))),
+                _ => Err(DataFusionError::Plan(format!(
+                    "unexpected number of arguments to {}: {}",
+                    DATE_BIN_GAPFILL_UDF_NAME,
+                    args.len()
+                ))),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Converts a positive interval to a window duration
+fn scalar_window_duration(value: &ScalarValue) -> Option<WindowDuration> {
+    match value {
+        ScalarValue::IntervalDayTime(Some(interval)) => {
+            let days = interval >> 32;
+            let milliseconds = *interval as i32 as i64;
+            let nanoseconds =
+                days * NANOSECONDS_PER_DAY + milliseconds * NANOSECONDS_PER_MILLISECOND;
+            (nanoseconds > 0).then(|| WindowDuration::from_nanoseconds(nanoseconds))
+        }
+        ScalarValue::IntervalYearMonth(Some(months)) if *months > 0 => {
+            Some(WindowDuration::from_months(*months as i64, false))
+        }
+        _ => None,
+    }
+}
+
+fn make_window(every: &WindowDuration, offset: &WindowDuration) -> Window {
+    let every = window::Duration::from(every);
+    Window::new(every, every, offset.into())
+}
+
+/// How the values of missing windows are filled
+#[derive(Debug, Clone, PartialEq)]
+pub enum FillMode {
+    /// The values are NULL
+    Null,
+
+    /// The last non-NULL value of the series
+    Previous,
+
+    /// The value linearly interpolated between the previous and the next
+    /// non-NULL values of the series, or NULL if there are none
+    Linear,
+
+    /// A constant value
+    Constant(ScalarValue),
+}
+
+impl FillMode {
+    /// Returns the fill mode of `expr` and the expression of the filled
+    /// values, or `None` if `expr` does not call a fill function
+    pub fn try_from_expr(expr: &Expr) -> Result<Option<(Self, &Expr)>> {
+        let (name, args) = match expr {
+            Expr::ScalarUDF { fun, args } => (fun.name.as_str(), args),
+            _ => return Ok(None),
+        };
+
+        let fill = match (name, args.as_slice()) {
+            (LOCF_UDF_NAME, [_]) => Self::Previous,
+            (INTERPOLATE_UDF_NAME, [_]) => Self::Linear,
+            (FILL_VALUE_UDF_NAME, [_, Expr::Literal(value)]) => Self::Constant(value.clone()),
+            (FILL_VALUE_UDF_NAME, [_, value]) => {
+                return Err(DataFusionError::Plan(format!(
+                    "{} requires a constant value, got {}",
+                    name, value
+                )))
+            }
+            (LOCF_UDF_NAME | INTERPOLATE_UDF_NAME | FILL_VALUE_UDF_NAME, _) => {
+                return Err(DataFusionError::Plan(format!(
+                    "unexpected number of arguments to {}: {}",
+                    name,
+                    args.len()
+                )))
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some((fill, &args[0])))
+    }
+}
+
+impl std::fmt::Display for FillMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Previous => write!(f, "previous"),
+            Self::Linear => write!(f, "linear"),
+            Self::Constant(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Which bound of its window the time of an aggregated row is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowBound {
+    /// The inclusive start, as computed by `date_bin_gapfill`
+    Start,
+
+    /// The exclusive stop, as computed by `window_bounds`
+    Stop,
+}
+
+/// The windows a series is filled with
+#[derive(Debug, Clone, PartialEq)]
+pub struct GapFillParams {
+    pub every: WindowDuration,
+    pub offset: WindowDuration,
+
+    /// Every window overlapping this range is filled
+    pub range: TimestampRange,
+
+    pub bound: WindowBound,
+}
+
+impl GapFillParams {
+    /// Returns the times of all windows overlapping the range, in order
+    pub fn window_times(&self) -> Result<Vec<i64>> {
+        let window = make_window(&self.every, &self.offset);
+        let range = self.range;
+
+        let first = (range.start < range.end).then(|| window.get_earliest_bounds(range.start));
+        let bounds = std::iter::successors(first, |bounds| {
+            let next = window.get_earliest_bounds(bounds.stop);
+            // stop rather than loop forever on windows of negative length
+            (next.start > bounds.start).then(|| next)
+        })
+        .take_while(|bounds| bounds.start < range.end);
+
+        let mut times = vec![];
+        for bounds in bounds {
+            if times.len() == MAX_GAP_FILL_WINDOWS {
+                return Err(DataFusionError::Plan(format!(
+                    "gap filling [{}, {}) with windows of {:?} would produce more than {} windows",
+                    range.start, range.end, self.every, MAX_GAP_FILL_WINDOWS
+                )));
+            }
+            times.push(match self.bound {
+                WindowBound::Start => bounds.start,
+                WindowBound::Stop => bounds.stop,
+            });
+        }
+        Ok(times)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::logical_plan::{col, lit};
+    use schema::TIME_DATA_TIMEZONE;
+
+    use super::*;
+
+    #[test]
+    fn test_date_bin() {
+        let time: ArrayRef = Arc::new(TimestampNanosecondArray::from_opt_vec(
+            vec![
+                Some(0),
+                Some(59_999_999_999),
+                None,
+                Some(60_000_000_000),
+                Some(-1),
+            ],
+            TIME_DATA_TIMEZONE(),
+        ));
+        let one_minute: ArrayRef =
+            Arc::new(arrow::array::IntervalDayTimeArray::from(vec![60_000; 5]));
+
+        let binned = date_bin(&[one_minute, time]).unwrap();
+        let expected: ArrayRef = Arc::new(TimestampNanosecondArray::from_opt_vec(
+            vec![
+                Some(0),
+                Some(0),
+                None,
+                Some(60_000_000_000),
+                Some(-60_000_000_000),
+            ],
+            TIME_DATA_TIMEZONE(),
+        ));
+        assert_eq!(&expected, &binned);
+    }
+
+    #[test]
+    fn test_date_bin_months() {
+        // 2021-02-15T00:00:00Z
+        let time: ArrayRef = Arc::new(TimestampNanosecondArray::from_opt_vec(
+            vec![Some(1_613_347_200_000_000_000)],
+            TIME_DATA_TIMEZONE(),
+        ));
+        let one_month: ArrayRef = Arc::new(arrow::array::IntervalYearMonthArray::from(vec![1]));
+
+        let binned = date_bin(&[one_month, time]).unwrap();
+        // 2021-02-01T00:00:00Z
+        let expected: ArrayRef = Arc::new(TimestampNanosecondArray::from_opt_vec(
+            vec![Some(1_612_137_600_000_000_000)],
+            TIME_DATA_TIMEZONE(),
+        ));
+        assert_eq!(&expected, &binned);
+    }
+
+    #[test]
+    fn test_window_times() {
+        let mut params = GapFillParams {
+            every: WindowDuration::from_nanoseconds(200),
+            offset: WindowDuration::from_nanoseconds(50),
+            range: TimestampRange::new(100, 500),
+            bound: WindowBound::Start,
+        };
+        assert_eq!(params.window_times().unwrap(), vec![50, 250, 450]);
+
+        params.bound = WindowBound::Stop;
+        assert_eq!(params.window_times().unwrap(), vec![250, 450, 650]);
+
+        params.range = TimestampRange::new(100, 100);
+        assert_eq!(params.window_times().unwrap(), Vec::<i64>::new());
+
+        params.every = WindowDuration::from_nanoseconds(1);
+        params.range = TimestampRange::new(0, 2_000_000);
+        let err = params.window_times().unwrap_err();
+        assert!(
+            err.to_string().contains("more than 1000000 windows"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_try_from_expr() {
+        let udfs = all_gapfill_functions();
+        let call = |name: &str, args: Vec<Expr>| {
+            udfs.iter()
+                .find(|udf| udf.name == name)
+                .expect("function exists")
+                .call(args)
+        };
+
+        let (fill, input) = FillMode::try_from_expr(&call(LOCF_UDF_NAME, vec![col("a")]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(fill, FillMode::Previous);
+        assert_eq!(input, &col("a"));
+
+        let (fill, _) = FillMode::try_from_expr(&call(INTERPOLATE_UDF_NAME, vec![col("a")]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(fill, FillMode::Linear);
+
+        let (fill, _) =
+            FillMode::try_from_expr(&call(FILL_VALUE_UDF_NAME, vec![col("a"), lit(0.5)]))
+                .unwrap()
+                .unwrap();
+        assert_eq!(fill, FillMode::Constant(ScalarValue::Float64(Some(0.5))));
+
+        let err = FillMode::try_from_expr(&call(FILL_VALUE_UDF_NAME, vec![col("a"), col("b")]))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("requires a constant value"),
+            "{}",
+            err
+        );
+
+        assert!(FillMode::try_from_expr(&col("a")).unwrap().is_none());
+
+        let stride = lit(ScalarValue::IntervalDayTime(Some(60_000)));
+        let (every, time) =
+            date_bin_gapfill_args(&call(DATE_BIN_GAPFILL_UDF_NAME, vec![stride, col("time")]))
+                .unwrap()
+                .unwrap();
+        assert_eq!(every, WindowDuration::from_nanoseconds(60_000_000_000));
+        assert_eq!(time, &col("time"));
+
+        let stride = lit(ScalarValue::IntervalDayTime(Some(0)));
+        let err =
+            date_bin_gapfill_args(&call(DATE_BIN_GAPFILL_UDF_NAME, vec![stride, col("time")]))
+                .unwrap_err();
+        assert!(err.to_string().contains("positive interval"), "{}", err);
+    }
+}
@@ -0,0 +1,548 @@
+//! Implementation of InfluxDB functions transforming the values of each
+//! series in time order
+//!
+//! * `derivative(value [, unit])`: the rate of change per `unit`, an
+//!   interval defaulting to one second
+//! * `non_negative_derivative(value [, unit])`: the derivative, or NULL
+//!   where it is negative (e.g. where a counter was reset)
+//! * `difference(value)`: the difference to the previous value
+//! * `moving_average(value, n)`: the mean of the last `n` values
+//! * `cumulative_sum(value)`: the sum of all values so far
+//!
+//! The value of these functions depends on the previous rows of the same
+//! series, which DataFusion's user defined functions can not express.
+//! They are therefore registered as scalar functions that can not be
+//! executed themselves: before a plan is optimized, calls to them are
+//! replaced by a `SeriesTransformNode` (see
+//! [`crate::exec::series_transform`]) that computes them with a
+//! [`SeriesTransformer`] per series.
+//!
+//! Rows without a value are skipped and produce NULL, as does the first
+//! row of a series for `derivative` and `difference` and the first `n - 1`
+//! rows of a series for `moving_average`.
+use std::{collections::VecDeque, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, Int64Array, TimestampNanosecondArray},
+    compute::cast,
+    datatypes::{DataType, IntervalUnit},
+};
+use datafusion::{
+    error::{DataFusionError, Result},
+    logical_plan::Expr,
+    physical_plan::{
+        functions::{
+            ReturnTypeFunction, ScalarFunctionImplementation, Signature, TypeSignature, Volatility,
+        },
+        udf::ScalarUDF,
+    },
+    scalar::ScalarValue,
+};
+
+pub const DERIVATIVE_UDF_NAME: &str = "derivative";
+pub const NON_NEGATIVE_DERIVATIVE_UDF_NAME: &str = "non_negative_derivative";
+pub const DIFFERENCE_UDF_NAME: &str = "difference";
+pub const MOVING_AVERAGE_UDF_NAME: &str = "moving_average";
+pub const CUMULATIVE_SUM_UDF_NAME: &str = "cumulative_sum";
+
+/// The default unit of derivatives: one second
+const DEFAULT_DERIVATIVE_UNIT: i64 = 1_000_000_000;
+
+const NANOSECONDS_PER_DAY: i64 = 86_400_000_000_000;
+const NANOSECONDS_PER_MILLISECOND: i64 = 1_000_000;
+
+/// Returns all transform functions of this module, to make them available
+/// to SQL queries
+pub fn all_transforms() -> Vec<ScalarUDF> {
+    let derivative_signature = || {
+        Signature::one_of(
+            vec![
+                TypeSignature::Exact(vec![DataType::Float64]),
+                TypeSignature::Exact(vec![
+                    DataType::Float64,
+                    DataType::Interval(IntervalUnit::DayTime),
+                ]),
+            ],
+            Volatility::Immutable,
+        )
+    };
+    let numeric = || vec![DataType::Int64, DataType::UInt64, DataType::Float64];
+
+    vec![
+        make_transform_udf(DERIVATIVE_UDF_NAME, derivative_signature()),
+        make_transform_udf(NON_NEGATIVE_DERIVATIVE_UDF_NAME, derivative_signature()),
+        make_transform_udf(
+            DIFFERENCE_UDF_NAME,
+            Signature::uniform(1, numeric(), Volatility::Immutable),
+        ),
+        make_transform_udf(
+            MOVING_AVERAGE_UDF_NAME,
+            Signature::exact(
+                vec![DataType::Float64, DataType::Int64],
+                Volatility::Immutable,
+            ),
+        ),
+        make_transform_udf(
+            CUMULATIVE_SUM_UDF_NAME,
+            Signature::uniform(1, numeric(), Volatility::Immutable),
+        ),
+    ]
+}
+
+/// Creates a transform function, which fails if it is executed as a
+/// scalar function rather than planned per series
+fn make_transform_udf(name: &'static str, signature: Signature) -> ScalarUDF {
+    // see `Transform::return_type`
+    let return_type: ReturnTypeFunction = Arc::new(move |arg_types| {
+        let return_type = match (name, arg_types.first()) {
+            (DIFFERENCE_UDF_NAME | CUMULATIVE_SUM_UDF_NAME, Some(DataType::Int64)) => {
+                DataType::Int64
+            }
+            _ => DataType::Float64,
+        };
+        Ok(Arc::new(return_type))
+    });
+
+    let fun: ScalarFunctionImplementation = Arc::new(move |_| {
+        Err(DataFusionError::Plan(format!(
+            "{} can only be used in the select list of a query",
+            name
+        )))
+    });
+
+    ScalarUDF::new(name, &signature, &return_type, &fun)
+}
+
+/// A transformation of the values of a series
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    /// The rate of change per `unit` nanoseconds, NULL where it is
+    /// negative if `non_negative`
+    Derivative { unit: i64, non_negative: bool },
+
+    /// The difference to the previous value
+    Difference,
+
+    /// The mean of the last `n` values
+    MovingAverage { n: usize },
+
+    /// The sum of all values so far
+    CumulativeSum,
+}
+
+impl Transform {
+    /// Returns the transform computed by `expr` and the expression of the
+    /// transformed values, or `None` if `expr` does not call a transform
+    /// function
+    pub fn try_from_expr(expr: &Expr) -> Result<Option<(Self, &Expr)>> {
+        let (name, args) = match expr {
+            Expr::ScalarUDF { fun, args } => (fun.name.as_str(), args),
+            _ => return Ok(None),
+        };
+
+        let transform = match (name, args.as_slice()) {
+            (DERIVATIVE_UDF_NAME | NON_NEGATIVE_DERIVATIVE_UDF_NAME, [_] | [_, _]) => {
+                let unit = match args.get(1) {
+                    Some(unit) => interval_nanoseconds(name, unit)?,
+                    None => DEFAULT_DERIVATIVE_UNIT,
+                };
+                Self::Derivative {
+                    unit,
+                    non_negative: name == NON_NEGATIVE_DERIVATIVE_UDF_NAME,
+                }
+            }
+            (DIFFERENCE_UDF_NAME, [_]) => Self::Difference,
+            (MOVING_AVERAGE_UDF_NAME, [_, n]) => match n {
+                Expr::Literal(ScalarValue::Int64(Some(n))) if *n > 0 => {
+                    Self::MovingAverage { n: *n as usize }
+                }
+                _ => {
+                    return Err(DataFusionError::Plan(format!(
+                        "{} requires a positive integer number of values, got {}",
+                        name, n
+                    )))
+                }
+            },
+            (CUMULATIVE_SUM_UDF_NAME, [_]) => Self::CumulativeSum,
+            (
+                DERIVATIVE_UDF_NAME
+                | NON_NEGATIVE_DERIVATIVE_UDF_NAME
+                | DIFFERENCE_UDF_NAME
+                | MOVING_AVERAGE_UDF_NAME
+                | CUMULATIVE_SUM_UDF_NAME,
+                _,
+            ) => {
+                return Err(DataFusionError::Plan(format!(
+                    "unexpected number of arguments to {}: {}",
+                    name,
+                    args.len()
+                )))
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some((transform, &args[0])))
+    }
+
+    /// The type of the transformed values of `input_type`
+    ///
+    /// Differences and sums of integers are integers, everything else
+    /// is computed as floats.
+    pub fn return_type(&self, input_type: &DataType) -> DataType {
+        match (self, input_type) {
+            (Self::Difference | Self::CumulativeSum, DataType::Int64) => DataType::Int64,
+            _ => DataType::Float64,
+        }
+    }
+}
+
+impl std::fmt::Display for Transform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Derivative {
+                unit,
+                non_negative: false,
+            } => write!(f, "{}({}ns)", DERIVATIVE_UDF_NAME, unit),
+            Self::Derivative {
+                unit,
+                non_negative: true,
+            } => write!(f, "{}({}ns)", NON_NEGATIVE_DERIVATIVE_UDF_NAME, unit),
+            Self::Difference => write!(f, "{}", DIFFERENCE_UDF_NAME),
+            Self::MovingAverage { n } => write!(f, "{}({})", MOVING_AVERAGE_UDF_NAME, n),
+            Self::CumulativeSum => write!(f, "{}", CUMULATIVE_SUM_UDF_NAME),
+        }
+    }
+}
+
+/// Returns the length of the interval literal `expr` in nanoseconds
+fn interval_nanoseconds(name: &str, expr: &Expr) -> Result<i64> {
+    let nanoseconds = match expr {
+        Expr::Literal(ScalarValue::IntervalDayTime(Some(interval))) => {
+            let days = interval >> 32;
+            let milliseconds = *interval as i32 as i64;
+            days * NANOSECONDS_PER_DAY + milliseconds * NANOSECONDS_PER_MILLISECOND
+        }
+        _ => 0,
+    };
+
+    if nanoseconds <= 0 {
+        return Err(DataFusionError::Plan(format!(
+            "{} requires a positive interval of days or less as unit, got {}",
+            name, expr
+        )));
+    }
+    Ok(nanoseconds)
+}
+
+/// Computes a [`Transform`] over the rows of consecutive series, which
+/// may span several batches
+#[derive(Debug)]
+pub struct SeriesTransformer {
+    transform: Transform,
+    state: SeriesState,
+}
+
+/// What is remembered of the previous rows of the current series
+#[derive(Debug, Default)]
+struct SeriesState {
+    /// The time and value of the previous row with a value
+    previous: Option<(i64, f64)>,
+    previous_int: Option<i64>,
+
+    sum: f64,
+    sum_int: i64,
+
+    /// The last values, for moving averages
+    window: VecDeque<f64>,
+}
+
+impl SeriesTransformer {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            transform,
+            state: SeriesState::default(),
+        }
+    }
+
+    /// Transforms `values`, where `new_series[i]` is true if row `i`
+    /// starts a new series and `times` are the times of the rows
+    pub fn transform(
+        &mut self,
+        new_series: &[bool],
+        times: &TimestampNanosecondArray,
+        values: &ArrayRef,
+    ) -> Result<ArrayRef> {
+        if self.transform.return_type(values.data_type()) == DataType::Int64 {
+            let values = values
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .ok_or_else(|| {
+                    DataFusionError::Internal(format!(
+                        "Internal error: expected Int64 values for {} but got {:?}",
+                        self.transform,
+                        values.data_type()
+                    ))
+                })?;
+
+            let output = values
+                .iter()
+                .zip(new_series)
+                .map(|(value, new_series)| {
+                    if *new_series {
+                        self.state = SeriesState::default();
+                    }
+                    self.next_int(value?)
+                })
+                .collect::<Int64Array>();
+            return Ok(Arc::new(output));
+        }
+
+        let values = cast(values, &DataType::Float64)?;
+        let values = values
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .expect("cast to Float64");
+
+        let output = values
+            .iter()
+            .zip(times.iter())
+            .zip(new_series)
+            .map(|((value, time), new_series)| {
+                if *new_series {
+                    self.state = SeriesState::default();
+                }
+                self.next_float(time?, value?)
+            })
+            .collect::<Float64Array>();
+        Ok(Arc::new(output))
+    }
+
+    fn next_int(&mut self, value: i64) -> Option<i64> {
+        let state = &mut self.state;
+        match self.transform {
+            Transform::Difference => {
+                let previous = state.previous_int.replace(value)?;
+                Some(value.wrapping_sub(previous))
+            }
+            Transform::CumulativeSum => {
+                state.sum_int = state.sum_int.wrapping_add(value);
+                Some(state.sum_int)
+            }
+            Transform::Derivative { .. } | Transform::MovingAverage { .. } => {
+                unreachable!("{} is computed as floats", self.transform)
+            }
+        }
+    }
+
+    fn next_float(&mut self, time: i64, value: f64) -> Option<f64> {
+        let state = &mut self.state;
+        match self.transform {
+            Transform::Derivative { unit, non_negative } => {
+                let (previous_time, previous_value) = match state.previous {
+                    // a second value at the same time has no rate of change
+                    Some((previous_time, _)) if previous_time == time => return None,
+                    _ => state.previous.replace((time, value))?,
+                };
+
+                let derivative =
+                    (value - previous_value) * unit as f64 / (time - previous_time) as f64;
+                (!non_negative || derivative >= 0.0).then(|| derivative)
+            }
+            Transform::Difference => {
+                let (_, previous) = state.previous.replace((time, value))?;
+                Some(value - previous)
+            }
+            Transform::MovingAverage { n } => {
+                state.window.push_back(value);
+                if state.window.len() > n {
+                    state.window.pop_front();
+                }
+                (state.window.len() == n).then(|| state.window.iter().sum::<f64>() / n as f64)
+            }
+            Transform::CumulativeSum => {
+                state.sum += value;
+                Some(state.sum)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::UInt64Array;
+
+    use super::*;
+
+    #[test]
+    fn test_derivative() {
+        let transform = Transform::Derivative {
+            unit: 10,
+            non_negative: false,
+        };
+        let actual = run(
+            transform,
+            &[1, 2, 4, 4, 5],
+            float(&[Some(1.0), Some(3.0), Some(2.0), Some(9.0), None]),
+        );
+        assert_eq!(
+            float_values(&actual),
+            vec![None, Some(20.0), Some(-5.0), None, None]
+        );
+
+        let transform = Transform::Derivative {
+            unit: 10,
+            non_negative: true,
+        };
+        let actual = run(
+            transform,
+            &[1, 2, 4, 4, 5],
+            float(&[Some(1.0), Some(3.0), Some(2.0), Some(9.0), None]),
+        );
+        assert_eq!(
+            float_values(&actual),
+            vec![None, Some(20.0), None, None, None]
+        );
+    }
+
+    #[test]
+    fn test_difference() {
+        let actual = run(
+            Transform::Difference,
+            &[1, 2, 3, 4],
+            Arc::new(Int64Array::from(vec![Some(5), None, Some(3), Some(10)])),
+        );
+        let actual = actual.as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(
+            actual.iter().collect::<Vec<_>>(),
+            vec![None, None, Some(-2), Some(7)]
+        );
+
+        let actual = run(
+            Transform::Difference,
+            &[1, 2, 3],
+            Arc::new(UInt64Array::from(vec![5, 3, 10])),
+        );
+        assert_eq!(float_values(&actual), vec![None, Some(-2.0), Some(7.0)]);
+    }
+
+    #[test]
+    fn test_moving_average() {
+        let actual = run(
+            Transform::MovingAverage { n: 2 },
+            &[1, 2, 3, 4],
+            float(&[Some(1.0), Some(3.0), None, Some(7.0)]),
+        );
+        assert_eq!(
+            float_values(&actual),
+            vec![None, Some(2.0), None, Some(5.0)]
+        );
+    }
+
+    #[test]
+    fn test_cumulative_sum() {
+        let actual = run(
+            Transform::CumulativeSum,
+            &[1, 2, 3],
+            float(&[Some(1.5), None, Some(2.0)]),
+        );
+        assert_eq!(float_values(&actual), vec![Some(1.5), None, Some(3.5)]);
+    }
+
+    #[test]
+    fn test_series_across_batches() {
+        let mut transformer = SeriesTransformer::new(Transform::CumulativeSum);
+        let times = TimestampNanosecondArray::from(vec![1, 2]);
+
+        let first = transformer
+            .transform(&[true, false], &times, &float(&[Some(1.0), Some(2.0)]))
+            .unwrap();
+        // the series continues into the second batch, then a new one starts
+        let second = transformer
+            .transform(&[false, true], &times, &float(&[Some(3.0), Some(4.0)]))
+            .unwrap();
+
+        assert_eq!(float_values(&first), vec![Some(1.0), Some(3.0)]);
+        assert_eq!(float_values(&second), vec![Some(6.0), Some(4.0)]);
+    }
+
+    #[test]
+    fn test_try_from_expr() {
+        use datafusion::logical_plan::{col, lit};
+
+        let udf = |name: &str| {
+            all_transforms()
+                .into_iter()
+                .find(|udf| udf.name == name)
+                .unwrap()
+        };
+        let one_minute = lit(ScalarValue::IntervalDayTime(Some(60_000)));
+
+        let expr = udf(DERIVATIVE_UDF_NAME).call(vec![col("f")]);
+        let (transform, input) = Transform::try_from_expr(&expr).unwrap().unwrap();
+        assert_eq!(
+            transform,
+            Transform::Derivative {
+                unit: 1_000_000_000,
+                non_negative: false
+            }
+        );
+        assert_eq!(input, &col("f"));
+
+        let expr = udf(NON_NEGATIVE_DERIVATIVE_UDF_NAME).call(vec![col("f"), one_minute]);
+        let (transform, _) = Transform::try_from_expr(&expr).unwrap().unwrap();
+        assert_eq!(
+            transform,
+            Transform::Derivative {
+                unit: 60_000_000_000,
+                non_negative: true
+            }
+        );
+
+        let expr = udf(MOVING_AVERAGE_UDF_NAME).call(vec![col("f"), lit(3_i64)]);
+        let (transform, _) = Transform::try_from_expr(&expr).unwrap().unwrap();
+        assert_eq!(transform, Transform::MovingAverage { n: 3 });
+
+        let expr = udf(MOVING_AVERAGE_UDF_NAME).call(vec![col("f"), lit(0_i64)]);
+        let err = Transform::try_from_expr(&expr).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("moving_average requires a positive integer number of values"),
+            "{}",
+            err
+        );
+
+        let expr = udf(DIFFERENCE_UDF_NAME).call(vec![col("f"), col("g")]);
+        let err = Transform::try_from_expr(&expr).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unexpected number of arguments to difference: 2"),
+            "{}",
+            err
+        );
+
+        assert!(Transform::try_from_expr(&col("f")).unwrap().is_none());
+    }
+
+    fn run(transform: Transform, times: &[i64], values: ArrayRef) -> ArrayRef {
+        let times = TimestampNanosecondArray::from(times.to_vec());
+        let mut new_series = vec![false; times.len()];
+        new_series[0] = true;
+
+        SeriesTransformer::new(transform)
+            .transform(&new_series, &times, &values)
+            .unwrap()
+    }
+
+    fn float(values: &[Option<f64>]) -> ArrayRef {
+        Arc::new(Float64Array::from(values.to_vec()))
+    }
+
+    fn float_values(array: &ArrayRef) -> Vec<Option<f64>> {
+        array
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap()
+            .iter()
+            .collect()
+    }
+}
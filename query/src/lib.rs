@@ -14,9 +14,10 @@ use data_types::{
     partition_metadata::{InfluxDbType, TableSummary},
 };
 use datafusion::physical_plan::SendableRecordBatchStream;
-use exec::stringset::StringSet;
+use exec::{stringset::StringSet, IOxExecutionContext};
 use observability_deps::tracing::{debug, trace};
 use predicate::predicate::{Predicate, PredicateMatch};
+use provider::ChunkAggregates;
 use schema::selection::Selection;
 use schema::{sort::SortKey, Schema, TIME_COLUMN_NAME};
 
@@ -100,8 +101,13 @@ pub trait QueryDatabase: Debug + Send + Sync {
     /// Return a summary of all chunks in this database, in all partitions
     fn chunk_summaries(&self) -> Result<Vec<ChunkSummary>, Self::Error>;
 
-    /// Record that particular type of query was run / planned
-    fn record_query(&self, query_type: impl Into<String>, query_text: impl Into<String>);
+    /// Record that particular type of query was run / planned by `ctx`
+    fn record_query(
+        &self,
+        ctx: &IOxExecutionContext,
+        query_type: impl Into<String>,
+        query_text: impl Into<String>,
+    );
 }
 
 /// Collection of data that shares the same partition key
@@ -170,6 +176,32 @@ pub trait QueryChunk: QueryChunkMeta + Debug + Send + Sync {
         selection: Selection<'_>,
     ) -> Result<SendableRecordBatchStream, Self::Error>;
 
+    /// Returns true if the chunk can compute `aggregates` over its rows
+    /// matching `predicate` itself, using `read_aggregate`
+    fn supports_read_aggregate(
+        &self,
+        _predicate: &Predicate,
+        _aggregates: &ChunkAggregates,
+    ) -> bool {
+        false
+    }
+
+    /// Provides the `aggregates` of the rows matching `predicate` as an
+    /// asynchronous stream of `RecordBatch`es, with one row per group.
+    ///
+    /// The output has the group columns, the window stop as `time` if
+    /// windowed, and one column per aggregate named after
+    /// [`PartialAggregate::column_name`](provider::PartialAggregate::column_name).
+    /// Columns may come in any order and need not have the types of the
+    /// table, as they are adapted by the caller.
+    ///
+    /// Only called if `supports_read_aggregate` returns true.
+    fn read_aggregate(
+        &self,
+        predicate: &Predicate,
+        aggregates: &ChunkAggregates,
+    ) -> Result<SendableRecordBatchStream, Self::Error>;
+
     /// Returns true if data of this chunk is sorted
     fn is_sorted_on_pk(&self) -> bool;
 
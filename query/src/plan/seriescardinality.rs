@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use datafusion::logical_plan::LogicalPlan;
+
+/// A plan which produces the number of distinct series (the
+/// combinations of table, tag set and field with at least one value)
+/// in a database. The series of some tables may be known from
+/// metadata, the series of others are counted by running plans.
+#[derive(Debug, Default)]
+pub struct SeriesCardinalityPlan {
+    /// The number of series known without running any plan
+    pub known: u64,
+
+    /// Plans producing the rows of the tables whose series were not
+    /// known from metadata. The series of different plans are
+    /// distinct.
+    pub plans: Vec<TableSeriesPlan>,
+}
+
+impl SeriesCardinalityPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the series of a table known from metadata
+    pub fn append_known(mut self, count: u64) -> Self {
+        self.known += count;
+        self
+    }
+
+    /// Append a plan producing the rows of a table
+    pub fn append(mut self, plan: TableSeriesPlan) -> Self {
+        self.plans.push(plan);
+        self
+    }
+}
+
+/// A plan producing the rows of a single table, whose series are the
+/// distinct combinations of the values of `tag_columns` and the non
+/// null `field_columns`
+#[derive(Debug)]
+pub struct TableSeriesPlan {
+    /// Datafusion plan to execute. The plan must produce RecordBatches
+    /// with a column for each name in `tag_columns` and
+    /// `field_columns`
+    pub plan: LogicalPlan,
+
+    /// The names of the columns that define tags
+    pub tag_columns: Vec<Arc<str>>,
+
+    /// The names of the columns which are "fields"
+    pub field_columns: Vec<Arc<str>>,
+}
@@ -31,10 +31,15 @@ use crate::{
 use snafu::{ResultExt, Snafu};
 
 mod adapter;
+mod aggregate;
 mod deduplicate;
 mod overlap;
 mod physical;
 use self::overlap::group_potential_duplicates;
+pub use aggregate::{
+    can_push_down_aggregates, scan_partial_aggregates, AggregatePushdown, AggregateWindow,
+    ChunkAggregates, PartialAggregate,
+};
 pub(crate) use deduplicate::DeduplicateExec;
 pub(crate) use physical::IOxReadFilterNode;
 
@@ -0,0 +1,795 @@
+//! Pushes aggregates down into `QueryChunk`s that can compute them
+//! natively, such as read buffer chunks.
+//!
+//! Each chunk computes partial aggregates over its own rows, which a
+//! DataFusion aggregate then combines across chunks. This is only
+//! correct when no row needs to be deduplicated or deleted, since the
+//! chunks see their rows in isolation.
+
+use std::{collections::HashSet, fmt, marker::PhantomData, sync::Arc};
+
+use arrow::{
+    array::{new_null_array, ArrayRef, UInt64Array},
+    compute::cast,
+    datatypes::{DataType, Field as ArrowField, Schema as ArrowSchema, SchemaRef},
+    error::Result as ArrowResult,
+    record_batch::RecordBatch,
+};
+use async_trait::async_trait;
+use datafusion::{
+    datasource::TableProvider,
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::context::ExecutionProps,
+    logical_plan::{col, max, min, sum, Expr, LogicalPlan, LogicalPlanBuilder},
+    optimizer::{optimizer::OptimizerRule, utils},
+    physical_plan::{
+        aggregates::AggregateFunction,
+        common::collect,
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet, RecordOutput},
+        DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream, Statistics,
+    },
+};
+use datafusion_util::MemoryStream;
+use observability_deps::tracing::debug;
+use predicate::predicate::{Predicate, PredicateBuilder};
+use schema::{InfluxColumnType, InfluxFieldType, Schema, TIME_COLUMN_NAME, TIME_DATA_TYPE};
+
+use crate::QueryChunk;
+
+use super::{overlap::group_potential_duplicates, ChunkTableProvider};
+
+/// An aggregate computed by each chunk over its own rows, whose results
+/// can be combined across chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PartialAggregate {
+    Count,
+    Sum,
+    Min,
+    Max,
+}
+
+impl fmt::Display for PartialAggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Count => write!(f, "count"),
+            Self::Sum => write!(f, "sum"),
+            Self::Min => write!(f, "min"),
+            Self::Max => write!(f, "max"),
+        }
+    }
+}
+
+impl PartialAggregate {
+    /// Returns the name of the column holding this aggregate of `column`
+    /// in the output of [`QueryChunk::read_aggregate`]
+    pub fn column_name(&self, column: &str) -> String {
+        format!("{}_{}", column, self)
+    }
+
+    /// Returns the expression combining the partial aggregates of
+    /// `column` of all chunks
+    pub fn combine_expr(&self, column: &str) -> Expr {
+        let partial = col(&self.column_name(column));
+        match self {
+            Self::Count | Self::Sum => sum(partial),
+            Self::Min => min(partial),
+            Self::Max => max(partial),
+        }
+    }
+}
+
+/// Windows of time, as used by `read_window_aggregate`, that rows are
+/// grouped by in addition to their group columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregateWindow {
+    /// The duration of each window, in nanoseconds
+    pub every: i64,
+    /// The offset of the windows from the epoch, in nanoseconds
+    pub offset: i64,
+}
+
+/// The aggregates a chunk computes in [`QueryChunk::read_aggregate`]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChunkAggregates {
+    /// The tag columns to group rows by
+    pub group_columns: Vec<String>,
+
+    /// The windows to additionally group rows by, if any. Windows are
+    /// identified by their stop time, which is output as the `time`
+    /// column
+    pub window: Option<AggregateWindow>,
+
+    /// The aggregates to compute, as (column, aggregate)
+    pub aggregates: Vec<(String, PartialAggregate)>,
+}
+
+impl ChunkAggregates {
+    /// Adds an aggregate of `column` to compute, unless it is computed
+    /// already
+    pub fn add_aggregate(&mut self, column: impl Into<String>, agg: PartialAggregate) {
+        let aggregate = (column.into(), agg);
+        if !self.aggregates.contains(&aggregate) {
+            self.aggregates.push(aggregate);
+        }
+    }
+
+    /// Returns the schema of the partial aggregates of a table with
+    /// `schema`: the group columns, the `time` column if windowed, and
+    /// the aggregates.
+    ///
+    /// Returns `None` if the aggregates can not be computed by chunks:
+    /// they may only group by tags, and only count non numeric fields.
+    pub fn partial_schema(&self, schema: &Schema) -> Option<SchemaRef> {
+        let mut fields = Vec::with_capacity(self.group_columns.len() + self.aggregates.len() + 1);
+
+        for column in &self.group_columns {
+            match schema.field(schema.find_index_of(column)?) {
+                (Some(InfluxColumnType::Tag), field) => {
+                    fields.push(ArrowField::new(column, field.data_type().clone(), true))
+                }
+                _ => return None,
+            }
+        }
+
+        if self.window.is_some() {
+            fields.push(ArrowField::new(TIME_COLUMN_NAME, TIME_DATA_TYPE(), true));
+        }
+
+        for (column, agg) in &self.aggregates {
+            let (influx_type, field) = schema.field(schema.find_index_of(column)?);
+            let supported = match (agg, influx_type?) {
+                (PartialAggregate::Sum, InfluxColumnType::Timestamp) => false,
+                (_, InfluxColumnType::Timestamp) => true,
+                (PartialAggregate::Count, InfluxColumnType::Field(_)) => true,
+                (
+                    _,
+                    InfluxColumnType::Field(
+                        InfluxFieldType::Float
+                        | InfluxFieldType::Integer
+                        | InfluxFieldType::UInteger,
+                    ),
+                ) => true,
+                _ => false,
+            };
+            if !supported {
+                return None;
+            }
+
+            let data_type = match agg {
+                PartialAggregate::Count => DataType::UInt64,
+                _ => field.data_type().clone(),
+            };
+            fields.push(ArrowField::new(&agg.column_name(column), data_type, true));
+        }
+
+        // a tag may clash with the name of an aggregate column
+        let mut names = HashSet::with_capacity(fields.len());
+        if !fields
+            .iter()
+            .all(|field| names.insert(field.name().clone()))
+        {
+            return None;
+        }
+
+        Some(Arc::new(ArrowSchema::new(fields)))
+    }
+}
+
+/// Returns true if the chunks can compute `aggregates` over their rows
+/// matching `predicate`, and the results can be combined across chunks.
+///
+/// This requires that no chunk contains rows to deduplicate or delete,
+/// within itself or with any other chunk.
+pub fn can_push_down_aggregates<C>(
+    chunks: &[Arc<C>],
+    predicate: &Predicate,
+    aggregates: &ChunkAggregates,
+) -> bool
+where
+    C: QueryChunk + 'static,
+{
+    // The aggregates of no rows at all are a single row of NULLs, which
+    // can not be combined from the chunks
+    let grouped = !aggregates.group_columns.is_empty() || aggregates.window.is_some();
+
+    if chunks.is_empty()
+        || aggregates.aggregates.is_empty()
+        || !grouped
+        || !predicate.value_expr.is_empty()
+    {
+        return false;
+    }
+
+    if chunks
+        .iter()
+        .any(|chunk| chunk.may_contain_pk_duplicates() || chunk.has_delete_predicates())
+    {
+        return false;
+    }
+
+    match group_potential_duplicates(chunks.to_vec()) {
+        Ok(groups) if groups.iter().all(|group| group.len() == 1) => {}
+        _ => return false,
+    }
+
+    chunks
+        .iter()
+        .all(|chunk| chunk.supports_read_aggregate(predicate, aggregates))
+}
+
+/// Creates a scan of the partial aggregates of `chunks`, which are
+/// combined using [`PartialAggregate::combine_expr`].
+///
+/// Returns `None` if the aggregates can not be pushed down into the
+/// chunks, per [`can_push_down_aggregates`].
+pub fn scan_partial_aggregates<C>(
+    table_name: &str,
+    schema: &Schema,
+    chunks: Vec<Arc<C>>,
+    predicate: &Predicate,
+    aggregates: ChunkAggregates,
+) -> DataFusionResult<Option<LogicalPlanBuilder>>
+where
+    C: QueryChunk + 'static,
+{
+    let partial_schema = match aggregates.partial_schema(schema) {
+        Some(partial_schema) => partial_schema,
+        None => return Ok(None),
+    };
+
+    if !can_push_down_aggregates(&chunks, predicate, &aggregates) {
+        return Ok(None);
+    }
+
+    debug!(
+        table_name,
+        num_chunks = chunks.len(),
+        %predicate,
+        ?aggregates,
+        "pushing aggregates down into chunks"
+    );
+
+    let provider = PartialAggregateProvider {
+        table_name: Arc::from(table_name),
+        schema: partial_schema,
+        chunks,
+        predicate: predicate.clone(),
+        aggregates,
+    };
+
+    LogicalPlanBuilder::scan(table_name, Arc::new(provider), None).map(Some)
+}
+
+/// A `TableProvider` for the partial aggregates computed by chunks
+#[derive(Debug)]
+struct PartialAggregateProvider<C: QueryChunk + 'static> {
+    table_name: Arc<str>,
+    schema: SchemaRef,
+    chunks: Vec<Arc<C>>,
+    predicate: Predicate,
+    aggregates: ChunkAggregates,
+}
+
+#[async_trait]
+impl<C: QueryChunk + 'static> TableProvider for PartialAggregateProvider<C> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    async fn scan(
+        &self,
+        projection: &Option<Vec<usize>>,
+        _batch_size: usize,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let schema = match projection {
+            Some(indices) => Arc::new(ArrowSchema::new(
+                indices
+                    .iter()
+                    .map(|&i| self.schema.field(i).clone())
+                    .collect(),
+            )),
+            None => Arc::clone(&self.schema),
+        };
+
+        Ok(Arc::new(IOxReadAggregateNode {
+            table_name: Arc::clone(&self.table_name),
+            schema,
+            chunks: self.chunks.clone(),
+            predicate: self.predicate.clone(),
+            aggregates: self.aggregates.clone(),
+            metrics: ExecutionPlanMetricsSet::new(),
+        }))
+    }
+}
+
+/// Implements the DataFusion physical plan interface for reading the
+/// partial aggregates of chunks, with one partition per chunk
+#[derive(Debug)]
+pub(crate) struct IOxReadAggregateNode<C: QueryChunk + 'static> {
+    table_name: Arc<str>,
+    /// The output schema. Note the chunks may not produce all these
+    /// columns, or with different types.
+    schema: SchemaRef,
+    chunks: Vec<Arc<C>>,
+    predicate: Predicate,
+    aggregates: ChunkAggregates,
+    /// Execution metrics
+    metrics: ExecutionPlanMetricsSet,
+}
+
+#[async_trait]
+impl<C: QueryChunk + 'static> ExecutionPlan for IOxReadAggregateNode<C> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(self.chunks.len())
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        // no inputs
+        vec![]
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        assert!(children.is_empty(), "no children expected in iox plan");
+
+        Ok(Arc::new(Self {
+            table_name: Arc::clone(&self.table_name),
+            schema: Arc::clone(&self.schema),
+            chunks: self.chunks.clone(),
+            predicate: self.predicate.clone(),
+            aggregates: self.aggregates.clone(),
+            metrics: ExecutionPlanMetricsSet::new(),
+        }))
+    }
+
+    async fn execute(&self, partition: usize) -> DataFusionResult<SendableRecordBatchStream> {
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+
+        let chunk = Arc::clone(&self.chunks[partition]);
+        let stream = chunk
+            .read_aggregate(&self.predicate, &self.aggregates)
+            .map_err(|e| {
+                DataFusionError::Execution(format!(
+                    "Error aggregating table {} chunk {}: {}",
+                    self.table_name,
+                    chunk.id(),
+                    e
+                ))
+            })?;
+
+        // there is at most one row per group, so simply buffer them
+        let batches = collect(stream).await?;
+
+        let timer = baseline_metrics.elapsed_compute().timer();
+        let counts = self
+            .aggregates
+            .aggregates
+            .iter()
+            .filter(|(_, agg)| *agg == PartialAggregate::Count)
+            .map(|(column, agg)| agg.column_name(column))
+            .collect::<HashSet<_>>();
+        let batches = batches
+            .iter()
+            .map(|batch| adapt_batch(batch, &self.schema, &counts))
+            .collect::<ArrowResult<Vec<_>>>()?;
+        timer.done();
+
+        for batch in &batches {
+            batch.record_output(&baseline_metrics);
+        }
+
+        Ok(Box::pin(MemoryStream::new_with_schema(
+            batches,
+            Arc::clone(&self.schema),
+        )))
+    }
+
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                write!(
+                    f,
+                    "IOxReadAggregateNode: table_name={}, chunks={} predicate={}",
+                    self.table_name,
+                    self.chunks.len(),
+                    self.predicate,
+                )
+            }
+        }
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+/// Adapts partial aggregates produced by a chunk to `schema`, matching
+/// columns by name and casting them to the expected type. Columns the
+/// chunk did not produce are NULL, except for the aggregates named in
+/// `counts` which are zero.
+fn adapt_batch(
+    batch: &RecordBatch,
+    schema: &SchemaRef,
+    counts: &HashSet<String>,
+) -> ArrowResult<RecordBatch> {
+    let num_rows = batch.num_rows();
+    let batch_schema = batch.schema();
+
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| match batch_schema.index_of(field.name()) {
+            Ok(idx) => {
+                let column = batch.column(idx);
+                if column.data_type() == field.data_type() {
+                    Ok(Arc::clone(column))
+                } else {
+                    cast(column, field.data_type())
+                }
+            }
+            Err(_) if counts.contains(field.name()) => {
+                Ok(Arc::new(UInt64Array::from(vec![0; num_rows])) as ArrayRef)
+            }
+            Err(_) => Ok(new_null_array(field.data_type(), num_rows)),
+        })
+        .collect::<ArrowResult<Vec<_>>>()?;
+
+    RecordBatch::try_new(Arc::clone(schema), columns)
+}
+
+/// An optimizer rule pushing simple aggregates over chunks of type `C`,
+/// such as
+///
+/// ```sql
+/// SELECT region, count(usage), max(time)
+/// FROM cpu
+/// WHERE host = 'a'
+/// GROUP BY region
+/// ```
+///
+/// down into the chunks. The aggregates are computed by the chunks
+/// and combined across them, instead of aggregating all rows read
+/// from the chunks:
+///
+/// ```text
+///   Aggregate(gby: region; agg: sum(usage_count), max(time_max))
+///     TableScan(partial aggregates of chunks)
+/// ```
+///
+/// Aggregates are only pushed down if they only group by tags, are
+/// `count`, `sum`, `min` or `max` of a column, and the chunks can
+/// evaluate the entire filter.
+#[derive(Debug)]
+pub struct AggregatePushdown<C: QueryChunk + 'static> {
+    phantom: PhantomData<C>,
+}
+
+impl<C: QueryChunk + 'static> Default for AggregatePushdown<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: QueryChunk + 'static> AggregatePushdown<C> {
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the rewritten `plan` if its aggregate can be pushed down
+    fn try_push_down(&self, plan: &LogicalPlan) -> DataFusionResult<Option<LogicalPlan>> {
+        let (input, group_expr, aggr_expr, schema) = match plan {
+            LogicalPlan::Aggregate {
+                input,
+                group_expr,
+                aggr_expr,
+                schema,
+            } => (input, group_expr, aggr_expr, schema),
+            _ => return Ok(None),
+        };
+
+        let (filter, scan) = match input.as_ref() {
+            LogicalPlan::Filter { predicate, input } => (Some(predicate), input.as_ref()),
+            other => (None, other),
+        };
+
+        let (table_name, source, filters) = match scan {
+            LogicalPlan::TableScan {
+                table_name,
+                source,
+                filters,
+                limit: None,
+                ..
+            } => (table_name, source, filters),
+            _ => return Ok(None),
+        };
+
+        let provider = match source.as_any().downcast_ref::<ChunkTableProvider<C>>() {
+            Some(provider) => provider,
+            None => return Ok(None),
+        };
+
+        let mut aggregates = ChunkAggregates::default();
+        for expr in group_expr {
+            match expr {
+                Expr::Column(column) => aggregates.group_columns.push(column.name.clone()),
+                _ => return Ok(None),
+            }
+        }
+
+        let input_schema = input.schema();
+        let mut combine_exprs = Vec::with_capacity(aggr_expr.len());
+        for expr in aggr_expr {
+            let (column, agg) = match partial_aggregate(expr) {
+                Some(partial) => partial,
+                None => return Ok(None),
+            };
+            combine_exprs.push(agg.combine_expr(&column).alias(&expr.name(input_schema)?));
+            aggregates.add_aggregate(column, agg);
+        }
+
+        // the chunks evaluate the entire filter, as it is not applied
+        // to their aggregates anymore
+        let mut exprs = filters.clone();
+        if let Some(filter) = filter {
+            PredicateBuilder::split_members(filter, &mut exprs);
+        }
+        let predicate = exprs
+            .into_iter()
+            .fold(PredicateBuilder::default(), |builder, expr| {
+                builder.add_expr(expr)
+            })
+            .build();
+
+        let chunks = provider
+            .chunk_pruner
+            .prune_chunks(provider.chunks.clone(), &predicate);
+
+        let plan_builder = match scan_partial_aggregates(
+            table_name,
+            &provider.iox_schema,
+            chunks,
+            &predicate,
+            aggregates,
+        )? {
+            Some(plan_builder) => plan_builder,
+            None => return Ok(None),
+        };
+
+        let new_plan = plan_builder
+            .aggregate(group_expr.clone(), combine_exprs)?
+            .build()?;
+
+        // The combined aggregates must be indistinguishable from the
+        // original ones
+        let new_schema = new_plan.schema();
+        let same_schema = new_schema.fields().len() == schema.fields().len()
+            && new_schema
+                .fields()
+                .iter()
+                .zip(schema.fields())
+                .all(|(new, old)| {
+                    new.qualified_name() == old.qualified_name()
+                        && new.data_type() == old.data_type()
+                });
+        if !same_schema {
+            debug!(
+                ?new_schema,
+                ?schema,
+                "not pushing down aggregates with different schema"
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(new_plan))
+    }
+}
+
+impl<C: QueryChunk + 'static> OptimizerRule for AggregatePushdown<C> {
+    fn optimize(
+        &self,
+        plan: &LogicalPlan,
+        execution_props: &ExecutionProps,
+    ) -> DataFusionResult<LogicalPlan> {
+        match self.try_push_down(plan)? {
+            Some(new_plan) => Ok(new_plan),
+            None => utils::optimize_children(self, plan, execution_props),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "aggregate_pushdown"
+    }
+}
+
+/// Returns the column and aggregate of `expr`, if it is an aggregate
+/// that can be computed by chunks
+fn partial_aggregate(expr: &Expr) -> Option<(String, PartialAggregate)> {
+    match expr {
+        Expr::AggregateFunction {
+            fun,
+            args,
+            distinct: false,
+        } => {
+            let agg = match fun {
+                AggregateFunction::Count => PartialAggregate::Count,
+                AggregateFunction::Sum => PartialAggregate::Sum,
+                AggregateFunction::Min => PartialAggregate::Min,
+                AggregateFunction::Max => PartialAggregate::Max,
+                _ => return None,
+            };
+            match args.as_slice() {
+                [Expr::Column(column)] => Some((column.name.clone(), agg)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::TestChunk;
+
+    fn schema() -> Schema {
+        schema::builder::SchemaBuilder::new()
+            .tag("region")
+            .field("usage", DataType::Float64)
+            .field("state", DataType::Utf8)
+            .timestamp()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn partial_schema() {
+        let aggregates = ChunkAggregates {
+            group_columns: vec!["region".into()],
+            window: Some(AggregateWindow {
+                every: 10,
+                offset: 0,
+            }),
+            aggregates: vec![
+                ("usage".into(), PartialAggregate::Sum),
+                ("state".into(), PartialAggregate::Count),
+                ("time".into(), PartialAggregate::Max),
+            ],
+        };
+
+        let partial_schema = aggregates.partial_schema(&schema()).unwrap();
+        let fields = partial_schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                (
+                    "region",
+                    DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+                ),
+                ("time", TIME_DATA_TYPE()),
+                ("usage_sum", DataType::Float64),
+                ("state_count", DataType::UInt64),
+                ("time_max", TIME_DATA_TYPE()),
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_schema_unsupported() {
+        let cases = vec![
+            // group by a field
+            (vec!["usage"], ("usage", PartialAggregate::Sum)),
+            // sum of a string
+            (vec!["region"], ("state", PartialAggregate::Sum)),
+            // min of a string
+            (vec!["region"], ("state", PartialAggregate::Min)),
+            // sum of time
+            (vec!["region"], ("time", PartialAggregate::Sum)),
+            // unknown column
+            (vec!["region"], ("foo", PartialAggregate::Count)),
+        ];
+
+        for (group_columns, (column, agg)) in cases {
+            let aggregates = ChunkAggregates {
+                group_columns: group_columns.into_iter().map(String::from).collect(),
+                window: None,
+                aggregates: vec![(column.into(), agg)],
+            };
+            assert!(
+                aggregates.partial_schema(&schema()).is_none(),
+                "{:?}",
+                aggregates
+            );
+        }
+    }
+
+    #[test]
+    fn push_down_requires_support() {
+        let predicate = Predicate::default();
+        let aggregates = ChunkAggregates {
+            group_columns: vec!["region".into()],
+            window: None,
+            aggregates: vec![("time".into(), PartialAggregate::Max)],
+        };
+
+        let chunk = Arc::new(
+            TestChunk::new("t")
+                .with_tag_column("region")
+                .with_time_column(),
+        );
+
+        // TestChunks can not compute aggregates
+        assert!(!can_push_down_aggregates(&[chunk], &predicate, &aggregates));
+        assert!(!can_push_down_aggregates::<TestChunk>(
+            &[],
+            &predicate,
+            &aggregates
+        ));
+    }
+
+    #[test]
+    fn adapt_partial_batch() {
+        let schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new(
+                "region",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+            ArrowField::new("usage_count", DataType::UInt64, true),
+            ArrowField::new("usage_sum", DataType::Float64, true),
+            ArrowField::new("time_max", TIME_DATA_TYPE(), true),
+        ]));
+
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "time_max",
+                Arc::new(arrow::array::Int64Array::from(vec![10, 20])) as ArrayRef,
+            ),
+            (
+                "region",
+                Arc::new(arrow::array::StringArray::from(vec!["east", "west"])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+
+        let counts = vec!["usage_count".to_string()].into_iter().collect();
+        let adapted = adapt_batch(&batch, &schema, &counts).unwrap();
+
+        arrow_util::assert_batches_eq!(
+            vec![
+                "+--------+-------------+-----------+--------------------------------+",
+                "| region | usage_count | usage_sum | time_max                       |",
+                "+--------+-------------+-----------+--------------------------------+",
+                "| east   | 0           |           | 1970-01-01T00:00:00.000000010Z |",
+                "| west   | 0           |           | 1970-01-01T00:00:00.000000020Z |",
+                "+--------+-------------+-----------+--------------------------------+",
+            ],
+            &[adapted]
+        );
+    }
+}
@@ -12,6 +12,7 @@ use async_trait::async_trait;
 use datafusion_util::AdapterStream;
 
 use self::algo::RecordBatchDeduplicator;
+use crate::exec::memory::{MemoryReservation, QueryMemory};
 use datafusion::{
     error::{DataFusionError, Result},
     physical_plan::{
@@ -110,6 +111,9 @@ use tokio::sync::mpsc;
 pub struct DeduplicateExec {
     input: Arc<dyn ExecutionPlan>,
     sort_keys: Vec<PhysicalSortExpr>,
+    /// Memory of the query the rows buffered across batches are
+    /// reserved from, if limited
+    memory: Option<Arc<QueryMemory>>,
     /// Execution metrics
     metrics: ExecutionPlanMetricsSet,
 }
@@ -119,9 +123,23 @@ impl DeduplicateExec {
         Self {
             input,
             sort_keys,
+            memory: None,
             metrics: ExecutionPlanMetricsSet::new(),
         }
     }
+
+    /// Returns a copy of this operator that reserves the rows it buffers
+    /// from `memory`, failing the query once it can not reserve more
+    ///
+    /// Unlike sorts, deduplication does not spill: it only buffers the rows
+    /// of the last primary key of a batch until the next batch, which only
+    /// exceed the memory of a query if a single key has that many duplicates.
+    pub fn with_memory(&self, memory: Arc<QueryMemory>) -> Self {
+        Self {
+            memory: Some(memory),
+            ..Self::new(Arc::clone(&self.input), self.sort_keys.clone())
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -163,7 +181,10 @@ impl ExecutionPlan for DeduplicateExec {
     ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
         assert_eq!(children.len(), 1);
         let input = Arc::clone(&children[0]);
-        Ok(Arc::new(Self::new(input, self.sort_keys.clone())))
+        Ok(Arc::new(Self {
+            memory: self.memory.clone(),
+            ..Self::new(input, self.sort_keys.clone())
+        }))
     }
 
     async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
@@ -173,6 +194,10 @@ impl ExecutionPlan for DeduplicateExec {
             ));
         }
         let deduplicate_metrics = DeduplicateMetrics::new(&self.metrics, partition);
+        let reservation = self
+            .memory
+            .as_ref()
+            .map(|m| MemoryReservation::new(Arc::clone(m)));
 
         let input_stream = self.input.execute(0).await?;
 
@@ -185,6 +210,7 @@ impl ExecutionPlan for DeduplicateExec {
             self.sort_keys.clone(),
             tx.clone(),
             deduplicate_metrics,
+            reservation,
         ));
 
         // A second task watches the output of the worker task
@@ -257,6 +283,7 @@ async fn deduplicate(
     sort_keys: Vec<PhysicalSortExpr>,
     tx: mpsc::Sender<ArrowResult<RecordBatch>>,
     deduplicate_metrics: DeduplicateMetrics,
+    mut reservation: Option<MemoryReservation>,
 ) -> ArrowResult<()> {
     let DeduplicateMetrics {
         baseline_metrics,
@@ -289,6 +316,14 @@ async fn deduplicate(
         let timer = elapsed_compute.timer();
         let output_batch = deduplicator.push(batch).record_output(&baseline_metrics)?;
         timer.done();
+
+        // The rows of the last key are kept until the next batch
+        if let Some(reservation) = reservation.as_mut() {
+            reservation
+                .try_resize(deduplicator.buffered_size())
+                .map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+        }
+
         tx.send(Ok(output_batch))
             .await
             .map_err(|e| ArrowError::from_external_error(Box::new(e)))?;
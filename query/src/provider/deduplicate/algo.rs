@@ -16,7 +16,7 @@ use datafusion::physical_plan::{
 };
 use observability_deps::tracing::trace;
 
-use crate::provider::deduplicate::key_ranges::key_ranges;
+use crate::{exec::memory::batch_memory_size, provider::deduplicate::key_ranges::key_ranges};
 
 // Handles the deduplication across potentially multiple
 // [`RecordBatch`]es which are already sorted on a primary key,
@@ -87,6 +87,15 @@ impl RecordBatchDeduplicator {
         Ok(output_record_batch)
     }
 
+    /// Returns the memory used by the rows kept until the next batch, in
+    /// bytes
+    pub fn buffered_size(&self) -> usize {
+        self.last_batch
+            .as_ref()
+            .map(batch_memory_size)
+            .unwrap_or_default()
+    }
+
     /// Return last_batch if it does not overlap with the given batch
     /// Note that since last_batch, if exists, will include at least one row and all of its rows will have the same key
     pub fn last_batch_with_no_same_sort_key(&mut self, batch: &RecordBatch) -> Option<RecordBatch> {
@@ -3,8 +3,11 @@
 use arrow::array::ArrayRef;
 use data_types::partition_metadata::{ColumnSummary, TableSummary};
 use datafusion::{
-    logical_plan::{Column, Expr},
+    error::Result as DatafusionResult,
+    logical_plan::{lit, Column, Expr, ExprRewriter},
     physical_optimizer::pruning::{PruningPredicate, PruningStatistics},
+    physical_plan::functions::BuiltinScalarFunction,
+    scalar::ScalarValue,
 };
 use observability_deps::tracing::{debug, trace};
 use predicate::predicate::Predicate;
@@ -48,6 +51,12 @@ where
     };
     trace!(%filter_expr, "Filter_expr of pruning chunks");
 
+    // DataFusion can only prune using comparisons, so express any prefix
+    // comparisons as the range of values they match
+    let filter_expr = filter_expr
+        .rewrite(&mut StartsWithRewriter {})
+        .expect("rewrite is infallible");
+
     // TODO: performance optimization: batch the chunk pruning by
     // grouping the chunks with the same types for all columns
     // together and then creating a single PruningPredicate for each
@@ -108,6 +117,53 @@ where
     }
 }
 
+/// Rewrites `starts_with(column, 'prefix')` as the equivalent range
+/// `column >= 'prefix' AND column < 'prefiy'`, which can be evaluated
+/// against the min and max values of the column
+struct StartsWithRewriter {}
+
+impl ExprRewriter for StartsWithRewriter {
+    fn mutate(&mut self, expr: Expr) -> DatafusionResult<Expr> {
+        Ok(match expr {
+            Expr::ScalarFunction {
+                fun: BuiltinScalarFunction::StartsWith,
+                args,
+            } => match args.as_slice() {
+                [column @ Expr::Column(_), Expr::Literal(ScalarValue::Utf8(Some(prefix)))] => {
+                    let lower = column.clone().gt_eq(lit(prefix.as_str()));
+                    match prefix_upper_bound(prefix) {
+                        Some(upper) => lower.and(column.clone().lt(lit(upper))),
+                        None => lower,
+                    }
+                }
+                _ => Expr::ScalarFunction {
+                    fun: BuiltinScalarFunction::StartsWith,
+                    args,
+                },
+            },
+            _ => expr,
+        })
+    }
+}
+
+/// Returns the smallest string greater than every string starting with
+/// `prefix`, if there is one
+fn prefix_upper_bound(prefix: &str) -> Option<String> {
+    let mut upper = prefix.to_string();
+    while let Some(c) = upper.pop() {
+        // skip over the surrogate code points, which are not chars
+        let next = match c {
+            '\u{D7FF}' => Some('\u{E000}'),
+            c => char::from_u32(c as u32 + 1),
+        };
+        if let Some(next) = next {
+            upper.push(next);
+            return Some(upper);
+        }
+    }
+    None
+}
+
 // struct to implement pruning
 struct ChunkMetaStats<'a> {
     summary: &'a TableSummary,
@@ -394,6 +450,57 @@ mod test {
         assert_eq!(names(&pruned), vec!["chunk1"]);
     }
 
+    #[test]
+    fn test_pruned_starts_with() {
+        test_helpers::maybe_start_logging();
+        // column1 starts with "r" where
+        //   c1: ["a", "q"] --> pruned
+        //   c2: ["s", "z"] --> pruned
+        //   c3: ["q", "s"] --> not pruned
+        //   c4: ["ra", "rz"] --> not pruned
+
+        let observer = TestObserver::new();
+        let chunks = vec![
+            ("chunk1", "a", "q"),
+            ("chunk2", "s", "z"),
+            ("chunk3", "q", "s"),
+            ("chunk4", "ra", "rz"),
+        ]
+        .into_iter()
+        .map(|(name, min, max)| {
+            Arc::new(TestChunk::new(name).with_string_field_column_with_stats(
+                "column1",
+                Some(min),
+                Some(max),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+        let predicate = PredicateBuilder::new()
+            .add_expr(Expr::ScalarFunction {
+                fun: BuiltinScalarFunction::StartsWith,
+                args: vec![col("column1"), lit("r")],
+            })
+            .build();
+
+        let pruned = prune_chunks(&observer, chunks, &predicate);
+
+        assert_eq!(observer.events(), vec!["chunk1: Pruned", "chunk2: Pruned"]);
+        assert_eq!(names(&pruned), vec!["chunk3", "chunk4"]);
+    }
+
+    #[test]
+    fn test_prefix_upper_bound() {
+        assert_eq!(prefix_upper_bound("abc"), Some("abd".to_string()));
+        assert_eq!(prefix_upper_bound("a\u{10FFFF}"), Some("b".to_string()));
+        assert_eq!(
+            prefix_upper_bound("a\u{D7FF}"),
+            Some("a\u{E000}".to_string())
+        );
+        assert_eq!(prefix_upper_bound("\u{10FFFF}"), None);
+        assert_eq!(prefix_upper_bound(""), None);
+    }
+
     #[test]
     fn test_pruned_null() {
         test_helpers::maybe_start_logging();
@@ -6,6 +6,7 @@
 use crate::exec::{ExecutionContextProvider, Executor, ExecutorType, IOxExecutionContext};
 use crate::{
     exec::stringset::{StringSet, StringSetRef},
+    provider::ChunkAggregates,
     Predicate, PredicateMatch, QueryChunk, QueryChunkMeta, QueryDatabase,
 };
 use arrow::{
@@ -135,7 +136,13 @@ impl QueryDatabase for TestDatabase {
         found_one.then(|| Arc::new(merger.build()))
     }
 
-    fn record_query(&self, _query_type: impl Into<String>, _query_text: impl Into<String>) {}
+    fn record_query(
+        &self,
+        _ctx: &IOxExecutionContext,
+        _query_type: impl Into<String>,
+        _query_text: impl Into<String>,
+    ) {
+    }
 }
 
 impl ExecutionContextProvider for TestDatabase {
@@ -847,6 +854,17 @@ impl QueryChunk for TestChunk {
         Ok(Box::pin(stream))
     }
 
+    fn read_aggregate(
+        &self,
+        _predicate: &Predicate,
+        _aggregates: &ChunkAggregates,
+    ) -> Result<SendableRecordBatchStream, Self::Error> {
+        // Model not being able to compute aggregates itself
+        Err(TestError::General {
+            message: "read_aggregate not supported by TestChunk".to_string(),
+        })
+    }
+
     /// Returns true if data of this chunk is sorted
     fn is_sorted_on_pk(&self) -> bool {
         false
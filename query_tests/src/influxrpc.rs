@@ -1,6 +1,7 @@
 pub mod field_columns;
 pub mod read_filter;
 pub mod read_group;
+pub mod read_series_cardinality;
 pub mod read_window_aggregate;
 pub mod table_names;
 pub mod tag_keys;
@@ -10,7 +10,10 @@ use data_types::{
     delete_predicate::{DeleteExpr, DeletePredicate},
     timestamp::TimestampRange,
 };
-use datafusion::logical_plan::{col, lit};
+use datafusion::{
+    logical_plan::{col, lit, Expr},
+    physical_plan::functions::BuiltinScalarFunction,
+};
 use predicate::predicate::{Predicate, PredicateBuilder, EMPTY_PREDICATE};
 use query::frontend::influxrpc::InfluxRpcPlanner;
 
@@ -240,6 +243,37 @@ async fn test_read_filter_data_filter() {
     run_read_filter_test_case(TwoMeasurementsMultiSeries {}, predicate, expected_results).await;
 }
 
+#[tokio::test]
+async fn test_read_filter_data_filter_starts_with() {
+    // city starts with "L"
+    let predicate = PredicateBuilder::default()
+        .timestamp_range(200, 300)
+        .add_expr(starts_with(col("city"), "L"))
+        .build();
+
+    let expected_results = vec![
+        "Series tags={_measurement=h2o, city=LA, state=CA, _field=temp}\n  FloatPoints timestamps: [200], values: [90.0]",
+    ];
+
+    run_read_filter_test_case(TwoMeasurementsMultiSeries {}, predicate, expected_results).await;
+
+    // no city starts with "X"
+    let predicate = PredicateBuilder::default()
+        .add_expr(starts_with(col("city"), "X"))
+        .build();
+
+    let expected_results = vec![] as Vec<&str>;
+
+    run_read_filter_test_case(TwoMeasurementsMultiSeries {}, predicate, expected_results).await;
+}
+
+fn starts_with(column: Expr, prefix: &str) -> Expr {
+    Expr::ScalarFunction {
+        fun: BuiltinScalarFunction::StartsWith,
+        args: vec![column, lit(prefix)],
+    }
+}
+
 #[tokio::test]
 async fn test_read_filter_data_filter_with_delete() {
     // filter out one row in h20 but the leftover row was deleted to nothing will be returned
@@ -0,0 +1,126 @@
+//! Tests for the Influx gRPC queries
+use crate::scenarios::*;
+
+use async_trait::async_trait;
+use datafusion::logical_plan::{col, lit};
+use predicate::predicate::{Predicate, PredicateBuilder};
+use query::frontend::influxrpc::InfluxRpcPlanner;
+
+/// runs series_cardinality(predicate) and compares it to the expected
+/// output
+async fn run_series_cardinality_test_case<D>(db_setup: D, predicate: Predicate, expected_count: u64)
+where
+    D: DbSetup,
+{
+    test_helpers::maybe_start_logging();
+
+    for scenario in db_setup.make().await {
+        let DbScenario {
+            scenario_name, db, ..
+        } = scenario;
+        println!("Running scenario '{}'", scenario_name);
+        println!("Predicate: '{:#?}'", predicate);
+        let planner = InfluxRpcPlanner::new();
+        let ctx = db.executor().new_context(query::exec::ExecutorType::Query);
+
+        let plan = planner
+            .series_cardinality(db.as_ref(), predicate.clone())
+            .expect("built plan successfully");
+        let count = ctx
+            .to_series_cardinality(plan)
+            .await
+            .expect("counted series successfully");
+
+        assert_eq!(
+            count, expected_count,
+            "Error in  scenario '{}'\n\nexpected:\n{:#?}\nactual:\n{:#?}",
+            scenario_name, expected_count, count
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_series_cardinality_empty_database() {
+    let predicate = PredicateBuilder::default().build();
+    run_series_cardinality_test_case(NoData {}, predicate, 0).await;
+}
+
+#[tokio::test]
+async fn test_series_cardinality_no_predicate() {
+    // h2o: (MA, Boston) temp, other_temp and moisture, (CA, Boston) other_temp
+    // o2: (MA, Boston) temp and reading, (CA) temp
+    let predicate = PredicateBuilder::default().build();
+    run_series_cardinality_test_case(TwoMeasurementsManyFields {}, predicate, 7).await;
+}
+
+#[tokio::test]
+async fn test_series_cardinality_with_pred() {
+    let predicate = PredicateBuilder::default()
+        .add_expr(col("state").eq(lit("MA"))) // state=MA
+        .build();
+    run_series_cardinality_test_case(TwoMeasurementsManyFields {}, predicate, 5).await;
+}
+
+#[tokio::test]
+async fn test_series_cardinality_with_table_and_timestamp_pred() {
+    // only the row at 50 of h2o
+    let predicate = PredicateBuilder::default()
+        .table("h2o")
+        .timestamp_range(0, 200)
+        .build();
+    run_series_cardinality_test_case(TwoMeasurementsManyFields {}, predicate, 1).await;
+}
+
+#[tokio::test]
+async fn test_series_cardinality_with_field_pred() {
+    let predicate = PredicateBuilder::default()
+        .field_columns(vec!["temp"])
+        .build();
+    run_series_cardinality_test_case(TwoMeasurementsManyFields {}, predicate, 3).await;
+}
+
+struct MeasurementsWithOneTag {}
+#[async_trait]
+impl DbSetup for MeasurementsWithOneTag {
+    async fn make(&self) -> Vec<DbScenario> {
+        let partition_key = "1970-01-01T00";
+
+        // every row has a value for each field, so the series can be
+        // found from the tag values
+        let lp_lines1 = vec![
+            "cpu,host=a usage=1.0,idle=2.0 10",
+            "cpu,host=b usage=3.0,idle=4.0 20",
+            "disk bytes=100i 10",
+        ];
+        let lp_lines2 = vec![
+            "cpu,host=a usage=5.0,idle=6.0 30",
+            "cpu,host=c usage=7.0,idle=8.0 40",
+            "disk bytes=200i 20",
+        ];
+
+        make_two_chunk_scenarios(partition_key, &lp_lines1.join("\n"), &lp_lines2.join("\n")).await
+    }
+}
+
+#[tokio::test]
+async fn test_series_cardinality_one_tag() {
+    // cpu: hosts a, b and c with usage and idle, disk: bytes
+    let predicate = PredicateBuilder::default().build();
+    run_series_cardinality_test_case(MeasurementsWithOneTag {}, predicate, 7).await;
+}
+
+#[tokio::test]
+async fn test_series_cardinality_one_tag_with_timestamp_pred() {
+    // cpu: hosts a and b with usage and idle, disk: bytes
+    let predicate = PredicateBuilder::default().timestamp_range(0, 25).build();
+    run_series_cardinality_test_case(MeasurementsWithOneTag {}, predicate, 5).await;
+}
+
+#[tokio::test]
+async fn test_series_cardinality_one_tag_with_field_pred() {
+    // cpu: hosts a, b and c with usage
+    let predicate = PredicateBuilder::default()
+        .field_columns(vec!["usage"])
+        .build();
+    run_series_cardinality_test_case(MeasurementsWithOneTag {}, predicate, 3).await;
+}
@@ -26,6 +26,53 @@ async fn run_read_window_aggregate_test_case<D>(
     expected_results: Vec<&str>,
 ) where
     D: DbSetup,
+{
+    run_read_window_aggregate_test_case_impl(
+        db_setup,
+        predicate,
+        agg,
+        every,
+        offset,
+        false,
+        expected_results,
+    )
+    .await
+}
+
+/// runs read_window_aggregate(predicate) creating empty windows and
+/// compares it to the expected output
+async fn run_read_window_aggregate_create_empty_test_case<D>(
+    db_setup: D,
+    predicate: Predicate,
+    agg: Aggregate,
+    every: WindowDuration,
+    offset: WindowDuration,
+    expected_results: Vec<&str>,
+) where
+    D: DbSetup,
+{
+    run_read_window_aggregate_test_case_impl(
+        db_setup,
+        predicate,
+        agg,
+        every,
+        offset,
+        true,
+        expected_results,
+    )
+    .await
+}
+
+async fn run_read_window_aggregate_test_case_impl<D>(
+    db_setup: D,
+    predicate: Predicate,
+    agg: Aggregate,
+    every: WindowDuration,
+    offset: WindowDuration,
+    create_empty: bool,
+    expected_results: Vec<&str>,
+) where
+    D: DbSetup,
 {
     test_helpers::maybe_start_logging();
 
@@ -45,6 +92,7 @@ async fn run_read_window_aggregate_test_case<D>(
                 agg,
                 every.clone(),
                 offset.clone(),
+                create_empty,
             )
             .expect("built plan successfully");
 
@@ -191,6 +239,88 @@ async fn test_read_window_aggregate_nanoseconds_measurement_count() {
     .await;
 }
 
+#[tokio::test]
+async fn test_read_window_aggregate_create_empty_count() {
+    // windows without data within the range have a count of 0
+    let predicate = PredicateBuilder::default()
+        .timestamp_range(100, 1000)
+        .build();
+
+    let agg = Aggregate::Count;
+    let every = WindowDuration::from_nanoseconds(200);
+    let offset = WindowDuration::from_nanoseconds(0);
+
+    let expected_results = vec![
+        "Series tags={_measurement=h2o, city=Boston, state=MA, _field=temp}\n  IntegerPoints timestamps: [200, 400, 600, 800, 1000], values: [1, 2, 2, 0, 0]",
+        "Series tags={_measurement=h2o, city=Cambridge, state=MA, _field=temp}\n  IntegerPoints timestamps: [200, 400, 600, 800, 1000], values: [1, 2, 2, 0, 0]",
+        "Series tags={_measurement=h2o, city=LA, state=CA, _field=temp}\n  IntegerPoints timestamps: [200, 400, 600, 800, 1000], values: [1, 2, 2, 0, 0]",
+    ];
+
+    run_read_window_aggregate_create_empty_test_case(
+        MeasurementForWindowAggregate {},
+        predicate,
+        agg,
+        every,
+        offset,
+        expected_results,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_read_window_aggregate_create_empty_mean() {
+    // empty windows have no value for aggregates other than count, so
+    // there are no points for them
+    let predicate = PredicateBuilder::default()
+        .add_expr(col("city").eq(lit("Boston")))
+        .timestamp_range(100, 1000)
+        .build();
+
+    let agg = Aggregate::Mean;
+    let every = WindowDuration::from_nanoseconds(200);
+    let offset = WindowDuration::from_nanoseconds(0);
+
+    let expected_results = vec![
+        "Series tags={_measurement=h2o, city=Boston, state=MA, _field=temp}\n  FloatPoints timestamps: [200, 400, 600], values: [70.0, 71.5, 73.5]",
+    ];
+
+    run_read_window_aggregate_create_empty_test_case(
+        MeasurementForWindowAggregate {},
+        predicate,
+        agg,
+        every,
+        offset,
+        expected_results,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_read_window_aggregate_create_empty_no_range() {
+    // without a time range there are no bounds to create windows in
+    let predicate = PredicateBuilder::default()
+        .add_expr(col("city").eq(lit("Boston")))
+        .build();
+
+    let agg = Aggregate::Count;
+    let every = WindowDuration::from_nanoseconds(200);
+    let offset = WindowDuration::from_nanoseconds(0);
+
+    let expected_results = vec![
+        "Series tags={_measurement=h2o, city=Boston, state=MA, _field=temp}\n  IntegerPoints timestamps: [200, 400, 600], values: [1, 2, 2]",
+    ];
+
+    run_read_window_aggregate_create_empty_test_case(
+        MeasurementForWindowAggregate {},
+        predicate,
+        agg,
+        every,
+        offset,
+        expected_results,
+    )
+    .await;
+}
+
 struct MeasurementForWindowAggregateMonths {}
 #[async_trait]
 impl DbSetup for MeasurementForWindowAggregateMonths {
@@ -271,6 +271,9 @@ impl<W: Write> Runner<W> {
             let executor = Arc::new(Executor::new_with_config(ExecutorConfig {
                 num_threads: 1,
                 target_query_partitions: 4,
+                query_timeout: None,
+                memory: Default::default(),
+                admission: Default::default(),
             }));
             let ctx = executor
                 .new_execution_config(ExecutorType::Query)
@@ -1044,6 +1044,30 @@ impl DbSetup for OneMeasurementAllChunksDropped {
     }
 }
 
+/// One measurement with two series whose points are split across
+/// chunks, for testing functions computed per series in time order
+#[derive(Debug)]
+pub struct OneMeasurementForTransforms {}
+#[async_trait]
+impl DbSetup for OneMeasurementForTransforms {
+    async fn make(&self) -> Vec<DbScenario> {
+        let partition_key = "1970-01-01T00";
+
+        let lp_lines1 = vec![
+            "cpu,host=a counter=10i,usage=1.0 1000000000",
+            "cpu,host=a counter=15i,usage=2.0 2000000000",
+            "cpu,host=b counter=100i,usage=10.0 1000000000",
+        ];
+        let lp_lines2 = vec![
+            "cpu,host=a counter=25i,usage=4.0 4000000000",
+            "cpu,host=b counter=90i,usage=20.0 2000000000",
+            "cpu,host=b counter=120i,usage=30.0 3000000000",
+        ];
+
+        make_two_chunk_scenarios(partition_key, &lp_lines1.join("\n"), &lp_lines2.join("\n")).await
+    }
+}
+
 /// This function loads two chunks of lp data into 4 different scenarios
 ///
 /// Data in single open mutable buffer chunk
@@ -752,6 +752,190 @@ async fn sql_select_all_different_tags_chunks() {
     .await;
 }
 
+#[tokio::test]
+async fn sql_select_difference() {
+    let expected = vec![
+        "+------+----------------------+------------+",
+        "| host | time                 | difference |",
+        "+------+----------------------+------------+",
+        "| a    | 1970-01-01T00:00:01Z |            |",
+        "| a    | 1970-01-01T00:00:02Z | 5          |",
+        "| a    | 1970-01-01T00:00:04Z | 10         |",
+        "| b    | 1970-01-01T00:00:01Z |            |",
+        "| b    | 1970-01-01T00:00:02Z | -10        |",
+        "| b    | 1970-01-01T00:00:03Z | 30         |",
+        "+------+----------------------+------------+",
+    ];
+    run_sql_test_case(
+        OneMeasurementForTransforms {},
+        "SELECT host, time, difference(counter) AS difference from cpu",
+        &expected,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn sql_select_derivative() {
+    let expected = vec![
+        "+------+----------------------+------------+-------------------------+",
+        "| host | time                 | derivative | non_negative_derivative |",
+        "+------+----------------------+------------+-------------------------+",
+        "| a    | 1970-01-01T00:00:01Z |            |                         |",
+        "| a    | 1970-01-01T00:00:02Z | 1          | 5                       |",
+        "| a    | 1970-01-01T00:00:04Z | 1          | 5                       |",
+        "| b    | 1970-01-01T00:00:01Z |            |                         |",
+        "| b    | 1970-01-01T00:00:02Z | 10         |                         |",
+        "| b    | 1970-01-01T00:00:03Z | 10         | 30                      |",
+        "+------+----------------------+------------+-------------------------+",
+    ];
+    run_sql_test_case(
+        OneMeasurementForTransforms {},
+        "SELECT host, time, derivative(usage) AS derivative, \
+         non_negative_derivative(counter) AS non_negative_derivative from cpu",
+        &expected,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn sql_select_moving_average_and_cumulative_sum() {
+    let expected = vec![
+        "+------+----------------------+----------------+----------------+",
+        "| host | time                 | moving_average | cumulative_sum |",
+        "+------+----------------------+----------------+----------------+",
+        "| a    | 1970-01-01T00:00:01Z |                | 1              |",
+        "| a    | 1970-01-01T00:00:02Z | 1.5            | 3              |",
+        "| a    | 1970-01-01T00:00:04Z | 3              | 7              |",
+        "| b    | 1970-01-01T00:00:01Z |                | 10             |",
+        "| b    | 1970-01-01T00:00:02Z | 15             | 30             |",
+        "| b    | 1970-01-01T00:00:03Z | 25             | 60             |",
+        "+------+----------------------+----------------+----------------+",
+    ];
+    run_sql_test_case(
+        OneMeasurementForTransforms {},
+        "SELECT host, time, moving_average(usage, 2) AS moving_average, \
+         cumulative_sum(usage) AS cumulative_sum from cpu",
+        &expected,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn sql_select_transform_with_predicate() {
+    // the predicate is applied before the transform
+    let expected = vec![
+        "+------+----------------------+------------+",
+        "| host | time                 | difference |",
+        "+------+----------------------+------------+",
+        "| b    | 1970-01-01T00:00:01Z |            |",
+        "| b    | 1970-01-01T00:00:02Z | -10        |",
+        "| b    | 1970-01-01T00:00:03Z | 30         |",
+        "+------+----------------------+------------+",
+    ];
+    run_sql_test_case(
+        OneMeasurementForTransforms {},
+        "SELECT host, time, difference(counter) AS difference from cpu where host = 'b'",
+        &expected,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn sql_select_nested_transforms() {
+    run_sql_error_test_case(
+        OneMeasurementForTransforms {},
+        "SELECT cumulative_sum(difference(counter)) from cpu",
+        "transform functions can not be nested",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn sql_select_date_bin_gapfill() {
+    // windows without rows are filled with NULL
+    let expected = vec![
+        "+------+----------------------+-------+",
+        "| host | bin                  | usage |",
+        "+------+----------------------+-------+",
+        "| a    | 1970-01-01T00:00:01Z | 1     |",
+        "| a    | 1970-01-01T00:00:02Z | 2     |",
+        "| a    | 1970-01-01T00:00:03Z |       |",
+        "| a    | 1970-01-01T00:00:04Z | 4     |",
+        "| a    | 1970-01-01T00:00:05Z |       |",
+        "| b    | 1970-01-01T00:00:01Z | 10    |",
+        "| b    | 1970-01-01T00:00:02Z | 20    |",
+        "| b    | 1970-01-01T00:00:03Z | 30    |",
+        "| b    | 1970-01-01T00:00:04Z |       |",
+        "| b    | 1970-01-01T00:00:05Z |       |",
+        "+------+----------------------+-------+",
+    ];
+    run_sql_test_case(
+        OneMeasurementForTransforms {},
+        "SELECT host, date_bin_gapfill(INTERVAL '1 second', time) AS bin, avg(usage) AS usage \
+         FROM cpu \
+         WHERE time >= '1970-01-01T00:00:01Z' AND time < '1970-01-01T00:00:06Z' \
+         GROUP BY host, date_bin_gapfill(INTERVAL '1 second', time) \
+         ORDER BY host, bin",
+        &expected,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn sql_select_date_bin_gapfill_fill_modes() {
+    let expected = vec![
+        "+------+----------------------+------+-------------+----------+",
+        "| host | bin                  | locf | interpolate | constant |",
+        "+------+----------------------+------+-------------+----------+",
+        "| a    | 1970-01-01T00:00:01Z | 1    | 1           | 1        |",
+        "| a    | 1970-01-01T00:00:02Z | 2    | 2           | 2        |",
+        "| a    | 1970-01-01T00:00:03Z | 2    | 3           | 0        |",
+        "| a    | 1970-01-01T00:00:04Z | 4    | 4           | 4        |",
+        "| a    | 1970-01-01T00:00:05Z | 4    |             | 0        |",
+        "| b    | 1970-01-01T00:00:01Z | 10   | 10          | 10       |",
+        "| b    | 1970-01-01T00:00:02Z | 20   | 20          | 20       |",
+        "| b    | 1970-01-01T00:00:03Z | 30   | 30          | 30       |",
+        "| b    | 1970-01-01T00:00:04Z | 30   |             | 0        |",
+        "| b    | 1970-01-01T00:00:05Z | 30   |             | 0        |",
+        "+------+----------------------+------+-------------+----------+",
+    ];
+    run_sql_test_case(
+        OneMeasurementForTransforms {},
+        "SELECT host, date_bin_gapfill(INTERVAL '1 second', time) AS bin, \
+         locf(avg(usage)) AS locf, interpolate(min(usage)) AS interpolate, \
+         fill_value(max(usage), 0) AS constant \
+         FROM cpu \
+         WHERE time >= '1970-01-01T00:00:01Z' AND time < '1970-01-01T00:00:06Z' \
+         GROUP BY host, date_bin_gapfill(INTERVAL '1 second', time) \
+         ORDER BY host, bin",
+        &expected,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn sql_select_date_bin_gapfill_no_range() {
+    run_sql_error_test_case(
+        OneMeasurementForTransforms {},
+        "SELECT host, date_bin_gapfill(INTERVAL '1 second', time), avg(usage) \
+         FROM cpu \
+         WHERE time >= '1970-01-01T00:00:01Z' \
+         GROUP BY host, date_bin_gapfill(INTERVAL '1 second', time)",
+        "date_bin_gapfill requires lower and upper bounds on time in the WHERE clause",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn sql_select_fill_without_gapfill() {
+    run_sql_error_test_case(
+        OneMeasurementForTransforms {},
+        "SELECT host, locf(avg(usage)) FROM cpu GROUP BY host",
+        "locf can only be applied to the aggregates of a query grouped by date_bin_gapfill",
+    )
+    .await;
+}
+
 // ----------------------------------------------
 // tests without delete
 #[tokio::test]
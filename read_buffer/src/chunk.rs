@@ -206,7 +206,7 @@ impl Chunk {
     ///
     /// Note: `read_aggregate` currently only supports grouping on "tag"
     /// columns.
-    pub(crate) fn read_aggregate(
+    pub fn read_aggregate(
         &self,
         predicate: Predicate,
         group_columns: &Selection<'_>,
@@ -217,6 +217,27 @@ impl Chunk {
             .context(TableError)
     }
 
+    /// Returns an iterable collection of data in group columns and aggregate
+    /// columns for each window of time, optionally filtered by the provided
+    /// predicate. Windows are `every` nanoseconds wide, shifted by `offset`
+    /// nanoseconds, and are identified by their exclusive upper bounds.
+    /// Results are merged across all row groups.
+    ///
+    /// Note: `aggregate_window` currently only supports grouping on "tag"
+    /// columns.
+    pub fn aggregate_window(
+        &self,
+        predicate: Predicate,
+        group_columns: &Selection<'_>,
+        aggregates: &[(ColumnName<'_>, AggregateType)],
+        every: i64,
+        offset: i64,
+    ) -> Result<table::ReadWindowAggregateResults> {
+        self.table
+            .aggregate_window(predicate, group_columns, aggregates, every, offset)
+            .context(TableError)
+    }
+
     //
     // ---- Schema queries
     //
@@ -1157,6 +1178,71 @@ mod test {
         assert!(itr.next().is_none());
     }
 
+    #[test]
+    fn aggregate_window() {
+        let chunk = read_filter_setup();
+
+        // Build the operation equivalent to the following query:
+        //
+        //   SELECT env, max(counter), count(sketchy_sensor), max(time)
+        //   FROM "table_1"
+        //   WHERE "time" >= 100 AND "time" < 700
+        //   GROUP BY env, window(500ns)
+        //
+        let predicate = Predicate::with_time_range(&[], 100, 700);
+        let mut itr = chunk
+            .aggregate_window(
+                predicate,
+                &Selection::Some(&["env"]),
+                &[
+                    ("counter", AggregateType::Max),
+                    ("sketchy_sensor", AggregateType::Count),
+                    ("time", AggregateType::Max),
+                ],
+                500,
+                0,
+            )
+            .unwrap();
+
+        let (window, rb) = itr.next().unwrap();
+        assert_eq!(window, 500);
+        assert_rb_column_equals(
+            &rb,
+            "env",
+            &Values::String(vec![Some("us-east"), Some("us-west")]),
+        );
+        assert_rb_column_equals(&rb, "counter_max", &Values::F64(vec![300.3, 4500.3]));
+        assert_rb_column_equals(&rb, "sketchy_sensor_count", &Values::U64(vec![2, 1]));
+        assert_rb_column_equals(&rb, "time_max", &Values::I64(vec![400, 300]));
+
+        let (window, rb) = itr.next().unwrap();
+        assert_eq!(window, 1000);
+        assert_rb_column_equals(
+            &rb,
+            "env",
+            &Values::String(vec![Some("us-east"), Some("us-west")]),
+        );
+        assert_rb_column_equals(&rb, "counter_max", &Values::F64(vec![300.3, 4500.3]));
+        assert_rb_column_equals(&rb, "sketchy_sensor_count", &Values::U64(vec![1, 1]));
+        assert_rb_column_equals(&rb, "time_max", &Values::I64(vec![600, 600]));
+
+        // No more data
+        assert!(itr.next().is_none());
+
+        // Error when predicate is invalid
+        let predicate =
+            Predicate::with_time_range(&[BinaryExpr::from(("env", "=", 22.3))], 100, 205);
+        assert!(chunk
+            .aggregate_window(
+                predicate,
+                &Selection::Some(&["env"]),
+                &[("counter", AggregateType::Max)],
+                500,
+                0
+            )
+            .is_err());
+    }
+
     #[test]
     fn read_filter_with_deletes() {
         // Chunk should be initialized now.
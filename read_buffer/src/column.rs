@@ -508,6 +508,14 @@ impl Column {
                     return PredicateMatch::All;
                 }
             }
+
+            // Prefix comparisons are only supported on string columns, where
+            // the values starting with the prefix form a contiguous range.
+            cmp::Operator::StartsWith => {
+                if let Self::String(meta, data) = &self {
+                    return meta.evaluate_starts_with(value.str(), data.contains_null());
+                }
+            }
         }
 
         if self.predicate_matches_no_values(op, value) {
@@ -824,6 +832,8 @@ impl<T: PartialOrd + std::fmt::Debug> MetaData<T> {
                 cmp::Operator::LT => range.1 < u,
                 // all values in column <= v
                 cmp::Operator::LTE => range.1 <= u,
+                // only handled for string columns
+                cmp::Operator::StartsWith => false,
             },
             None => false, // only null values in column.
         }
@@ -850,12 +860,39 @@ impl<T: PartialOrd + std::fmt::Debug> MetaData<T> {
                 cmp::Operator::LT => range.0 >= u,
                 // min value in column is `> v` so no values can be `<= v`
                 cmp::Operator::LTE => range.0 > u,
+                // only handled for string columns
+                cmp::Operator::StartsWith => false,
             },
             None => true, // only null values in column so no values satisfy `v`
         }
     }
 }
 
+impl MetaData<String> {
+    // Determines if a predicate on the prefix of the values could match none,
+    // all or only some of the rows in the column. All values starting with
+    // `prefix` sort after `prefix`, and every value between two such values
+    // also starts with `prefix`.
+    fn evaluate_starts_with(&self, prefix: &str, contains_null: bool) -> PredicateMatch {
+        match &self.range {
+            Some((min, max)) => {
+                if !contains_null && min.starts_with(prefix) && max.starts_with(prefix) {
+                    // every value lies between two values starting with `prefix`
+                    PredicateMatch::All
+                } else if max.as_str() < prefix
+                    || (min.as_str() > prefix && !min.starts_with(prefix))
+                {
+                    // all values sort before or after those starting with `prefix`
+                    PredicateMatch::None
+                } else {
+                    PredicateMatch::SomeMaybe
+                }
+            }
+            None => PredicateMatch::None, // only null values in column
+        }
+    }
+}
+
 // Converts an Arrow `StringArray` into a `Column`.
 impl From<arrow::array::StringArray> for Column {
     fn from(arr: arrow::array::StringArray) -> Self {
@@ -2341,6 +2378,42 @@ mod test {
         }
     }
 
+    #[test]
+    fn evaluate_predicate_on_meta_starts_with() {
+        let col = Column::from(&["northeast", "northwest", "north"][..]);
+
+        let cases = vec![
+            ("north", PredicateMatch::All),
+            ("", PredicateMatch::All),
+            ("northe", PredicateMatch::SomeMaybe),
+            ("nor", PredicateMatch::All),
+            ("n", PredicateMatch::All),
+            ("east", PredicateMatch::None),
+            ("south", PredicateMatch::None),
+            ("northwesterly", PredicateMatch::None),
+        ];
+
+        for (prefix, result) in cases {
+            assert_eq!(
+                col.evaluate_predicate_on_meta(&cmp::Operator::StartsWith, &Value::from(prefix)),
+                result,
+                "case {:?} failed",
+                prefix
+            );
+        }
+
+        // Not all values will start with "north" because of NULL
+        let col = Column::from(&[Some("northeast"), None, Some("north")][..]);
+        assert_eq!(
+            col.evaluate_predicate_on_meta(&cmp::Operator::StartsWith, &Value::from("north")),
+            PredicateMatch::SomeMaybe,
+        );
+        assert_eq!(
+            col.evaluate_predicate_on_meta(&cmp::Operator::StartsWith, &Value::from("west")),
+            PredicateMatch::None,
+        );
+    }
+
     #[test]
     fn min() {
         let input = &[100_i64, 200, 300, 2, 200, 22, 30];
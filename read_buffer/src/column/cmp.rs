@@ -10,6 +10,7 @@ pub enum Operator {
     GTE,
     LT,
     LTE,
+    StartsWith,
 }
 
 impl Display for Operator {
@@ -24,6 +25,7 @@ impl Display for Operator {
                 Self::GTE => ">=",
                 Self::LT => "<",
                 Self::LTE => "<=",
+                Self::StartsWith => "starts_with",
             }
         )
     }
@@ -40,6 +42,7 @@ impl TryFrom<&str> for Operator {
             ">=" => Ok(Self::GTE),
             "<" => Ok(Self::LT),
             "<=" => Ok(Self::LTE),
+            "starts_with" => Ok(Self::StartsWith),
             v => Err(format!("unknown operator {:?}", v)),
         }
     }
@@ -155,6 +155,7 @@ macro_rules! make_float_trimmer {
                         // domain [23.2, 24.0).
                         Some((v.floor() as $type, Operator::GT))
                     }
+                    Operator::StartsWith => panic!("operator {:?} not expected", op),
                 }
             }
 
@@ -457,6 +457,52 @@ mod test {
         assert_eq!(ids, RowIDs::Vector(vec![3, 10, 11]), "{}", name);
     }
 
+    #[test]
+    fn row_ids_filter_starts_with() {
+        let encodings = vec![
+            Encoding::RLE(RLE::default()),
+            Encoding::Plain(Dictionary::default()),
+        ];
+
+        for enc in encodings {
+            _row_ids_filter_starts_with(enc);
+        }
+    }
+
+    fn _row_ids_filter_starts_with(mut enc: Encoding) {
+        let name = enc.debug_name();
+        enc.push_additional(Some("east".to_string()), 2); // 0, 1
+        enc.push_additional(Some("north".to_string()), 1); // 2
+        enc.push_additional(Some("northeast".to_string()), 2); // 3, 4
+        enc.push_none(); // 5
+        enc.push_additional(Some("north".to_string()), 1); // 6
+        enc.push_additional(Some("south".to_string()), 2); // 7, 8
+
+        let ids = enc.row_ids_filter("north", &cmp::Operator::StartsWith, RowIDs::Vector(vec![]));
+        assert_eq!(ids, RowIDs::Vector(vec![2, 3, 4, 6]), "{}", name);
+
+        let ids = enc.row_ids_filter("northe", &cmp::Operator::StartsWith, RowIDs::Vector(vec![]));
+        assert_eq!(ids, RowIDs::Vector(vec![3, 4]), "{}", name);
+
+        let ids = enc.row_ids_filter("s", &cmp::Operator::StartsWith, RowIDs::Vector(vec![]));
+        assert_eq!(ids, RowIDs::Vector(vec![7, 8]), "{}", name);
+
+        // the empty prefix matches all non-null values
+        let ids = enc.row_ids_filter("", &cmp::Operator::StartsWith, RowIDs::Vector(vec![]));
+        assert_eq!(
+            ids,
+            RowIDs::Vector(vec![0, 1, 2, 3, 4, 6, 7, 8]),
+            "{}",
+            name
+        );
+
+        let ids = enc.row_ids_filter("west", &cmp::Operator::StartsWith, RowIDs::Vector(vec![]));
+        assert!(ids.is_empty(), "{}", name);
+
+        let ids = enc.row_ids_filter("a", &cmp::Operator::StartsWith, RowIDs::Vector(vec![]));
+        assert!(ids.is_empty(), "{}", name);
+    }
+
     #[test]
     fn row_ids_filter_equal_no_null() {
         let encodings = vec![
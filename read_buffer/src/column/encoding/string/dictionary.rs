@@ -232,6 +232,7 @@ impl Dictionary {
             cmp::Operator::LT | cmp::Operator::LTE | cmp::Operator::GT | cmp::Operator::GTE => {
                 self.row_ids_cmp(value, op, dst)
             }
+            cmp::Operator::StartsWith => self.row_ids_starts_with(value, dst),
         }
     }
 
@@ -464,6 +465,51 @@ impl Dictionary {
         dst
     }
 
+    // Finds row ids for values starting with `prefix`. Since the entries are
+    // sorted the encoded ids of all matching values form a contiguous range.
+    fn row_ids_starts_with(&self, prefix: &str, mut dst: RowIDs) -> RowIDs {
+        dst.clear();
+
+        let first_id = match self.encoded_id(Some(prefix)) {
+            Ok(id) | Err(id) => id,
+        };
+        let matching = self.entries[first_id as usize..]
+            .iter()
+            .take_while(|entry| matches!(entry, Some(entry) if entry.starts_with(prefix)))
+            .count() as u32;
+        if matching == 0 {
+            return dst; // no values in the column start with `prefix`.
+        }
+        let ids = first_id..first_id + matching;
+
+        let mut found = false;
+        let mut count = 0_u32;
+        for (i, next) in self.encoded_data.iter().enumerate() {
+            if !ids.contains(next) {
+                if found {
+                    let (min, max) = (i as u32 - count, i as u32);
+                    dst.add_range(min, max);
+                    found = false;
+                    count = 0;
+                }
+                continue;
+            }
+
+            found = true;
+            count += 1;
+        }
+
+        // add any remaining range.
+        if found {
+            let (min, max) = (
+                self.encoded_data.len() as u32 - count,
+                self.encoded_data.len() as u32,
+            );
+            dst.add_range(min, max);
+        }
+        dst
+    }
+
     /// Populates the provided destination container with the row ids for rows
     /// that null.
     pub fn row_ids_null(&self, dst: RowIDs) -> RowIDs {
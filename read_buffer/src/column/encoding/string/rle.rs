@@ -301,6 +301,7 @@ impl RLE {
             cmp::Operator::LT | cmp::Operator::LTE | cmp::Operator::GT | cmp::Operator::GTE => {
                 self.row_ids_cmp(value, op, dst)
             }
+            cmp::Operator::StartsWith => self.row_ids_starts_with(value, dst),
         }
     }
 
@@ -403,6 +404,25 @@ impl RLE {
         dst
     }
 
+    // Finds row ids for values starting with `prefix`. Since the entries are
+    // sorted all matching values are adjacent in the index.
+    fn row_ids_starts_with(&self, prefix: &str, mut dst: RowIDs) -> RowIDs {
+        dst.clear();
+
+        // Skip the first "" representing NULL
+        let matching = self
+            .index_entries
+            .iter()
+            .zip(&self.index_row_ids)
+            .skip(1)
+            .skip_while(|(entry, _)| entry.as_str() < prefix)
+            .take_while(|(entry, _)| entry.starts_with(prefix));
+        for (_, row_ids) in matching {
+            dst.union(row_ids);
+        }
+        dst
+    }
+
     /// Populates the provided destination container with the row ids for rows
     /// that null.
     pub fn row_ids_null(&self, dst: RowIDs) -> RowIDs {
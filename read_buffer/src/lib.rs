@@ -12,7 +12,7 @@ mod value;
 pub use self::schema::*;
 pub use chunk::{Chunk as RBChunk, ChunkMetrics, Error};
 pub use row_group::{BinaryExpr, Predicate};
-pub use table::ReadFilterResults;
+pub use table::{ReadAggregateResults, ReadFilterResults, ReadWindowAggregateResults};
 
 /// THIS MODULE SHOULD ONLY BE IMPORTED FOR BENCHMARKS.
 ///
@@ -27,7 +27,7 @@ use arrow::{
 };
 use datafusion::{
     logical_plan::Expr as DfExpr, logical_plan::Operator as DFOperator,
-    scalar::ScalarValue as DFScalarValue,
+    physical_plan::functions::BuiltinScalarFunction, scalar::ScalarValue as DFScalarValue,
 };
 use std::num::NonZeroU64;
 
@@ -204,6 +204,19 @@ impl RowGroup {
         &self.columns[self.time_column]
     }
 
+    // Returns the minimum and maximum timestamps in the row group.
+    fn time_range(&self) -> (i64, i64) {
+        match self.time_column().column_range() {
+            (OwnedValue::Scalar(Scalar::I64(min)), OwnedValue::Scalar(Scalar::I64(max))) => {
+                (min, max)
+            }
+            (min, max) => panic!(
+                "invalid range type for timestamp column: ({:?}, {:?})",
+                min, max
+            ),
+        }
+    }
+
     /// Efficiently determines if the row group _might_ satisfy all of the
     /// provided binary expressions, when conjunctively applied.
     ///
@@ -576,7 +589,116 @@ impl RowGroup {
             RowIDsOption::All(_) => None,
         };
 
-        let agg_cols_num = result.schema.aggregate_columns.len();
+        self.read_group_rows(filter_row_ids.as_deref(), &mut result);
+        result
+    }
+
+    /// Materialises grouped aggregates for each window of time that the rows
+    /// satisfying the predicate fall into. Windows are `every` nanoseconds
+    /// wide and shifted by `offset` nanoseconds. Each result is paired with
+    /// the exclusive upper bound of its window, and results are ordered by
+    /// those bounds. Windows without any rows are not returned.
+    ///
+    /// Rows are first bucketed into windows by row id, and each window is then
+    /// aggregated over the encoded values of the group columns in the same way
+    /// as `read_aggregate`.
+    pub fn read_aggregate_window(
+        &self,
+        predicate: &Predicate,
+        group_columns: &[ColumnName<'_>],
+        aggregates: &[(ColumnName<'_>, AggregateType)],
+        every: i64,
+        offset: i64,
+    ) -> Vec<(i64, ReadAggregateResult<'_>)> {
+        assert!(every > 0, "window duration must be positive");
+
+        let schema = ResultSchema {
+            select_columns: vec![],
+            group_columns: self.meta.schema_for_column_names(group_columns),
+            aggregate_columns: self.meta.schema_for_aggregate_column_names(aggregates),
+        };
+
+        let filter_row_ids = match self.row_ids_from_predicate(predicate) {
+            RowIDsOption::None(_) => return vec![], // no matching rows
+            RowIDsOption::Some(row_ids) => Some(row_ids.to_vec()),
+            RowIDsOption::All(_) => None,
+        };
+
+        // If all the rows in the row group fall into the same window then
+        // there is no need to bucket them.
+        let (min, max) = self.time_range();
+        let (min_bound, max_bound) = (
+            window_bound(min, every, offset),
+            window_bound(max, every, offset),
+        );
+        if min_bound == max_bound {
+            let mut result = ReadAggregateResult::new(schema);
+            self.aggregate_rows(filter_row_ids.as_deref(), &mut result);
+            return vec![(min_bound, result)];
+        }
+
+        // Bucket the row ids by the upper bound of their windows.
+        let time_column = self.time_column();
+        let times = match &filter_row_ids {
+            Some(row_ids) => time_column.values(row_ids),
+            None => time_column.all_values(),
+        };
+
+        let mut windows: BTreeMap<i64, Vec<u32>> = BTreeMap::new();
+        for i in 0..times.len() {
+            let row_id = match &filter_row_ids {
+                Some(row_ids) => row_ids[i],
+                None => i as u32,
+            };
+            windows
+                .entry(window_bound(times.value(i).i64(), every, offset))
+                .or_default()
+                .push(row_id);
+        }
+
+        windows
+            .into_iter()
+            .map(|(bound, row_ids)| {
+                let mut result = ReadAggregateResult::new(schema.clone());
+                self.aggregate_rows(Some(&row_ids), &mut result);
+                (bound, result)
+            })
+            .collect()
+    }
+
+    // Aggregates the provided rows into `dst`, or all rows in the row group if
+    // `row_ids` is `None`. Unlike `read_aggregate` there is always at least
+    // one row to aggregate.
+    fn aggregate_rows<'a>(&'a self, row_ids: Option<&[u32]>, dst: &mut ReadAggregateResult<'a>) {
+        if dst.schema.group_columns.is_empty() {
+            match row_ids {
+                Some(row_ids) => self.aggregate_columns_for_rows(row_ids, dst),
+                None => {
+                    let row_ids = (0..self.rows()).into_iter().collect::<Vec<u32>>();
+                    self.aggregate_columns_for_rows(&row_ids, dst);
+                }
+            }
+            return;
+        }
+
+        let all_group_cols_pre_computed = dst.schema.group_column_names_iter().all(|name| {
+            self.column_by_name(name)
+                .properties()
+                .has_pre_computed_row_ids
+        });
+        if row_ids.is_none() && all_group_cols_pre_computed {
+            self.read_group_all_rows_all_rle(dst);
+            return;
+        }
+
+        self.read_group_rows(row_ids, dst);
+    }
+
+    // Executes a grouped aggregate over the provided rows, or over all rows in
+    // the row group if `row_ids` is `None`, using the encoded values of the
+    // group columns to build group keys.
+    fn read_group_rows<'a>(&'a self, row_ids: Option<&[u32]>, dst: &mut ReadAggregateResult<'a>) {
+        let agg_cols_num = dst.schema.aggregate_columns.len();
 
         // materialise all *encoded* values for each column we are grouping on.
         // These will not be the logical (typically string) values, but will be
@@ -591,7 +713,7 @@ impl RowGroup {
 
                 // Do we want some rows for the column (predicate filtered some
                 // rows) or all of them (predicates filtered no rows).
-                match &filter_row_ids {
+                match row_ids {
                     Some(row_ids) => {
                         encoded_values_buf = col.encoded_values(row_ids, encoded_values_buf);
                     }
@@ -607,7 +729,7 @@ impl RowGroup {
 
         // Materialise values in aggregate columns.
         let mut aggregate_columns_data = Vec::with_capacity(agg_cols_num);
-        for (col_type, _, _) in &result.schema.aggregate_columns {
+        for (col_type, _, _) in &dst.schema.aggregate_columns {
             let col = self.column_by_name(col_type.as_str());
 
             // TODO(edd): this materialises a column per aggregate. If there are
@@ -615,7 +737,7 @@ impl RowGroup {
             // over-allocate
 
             // Do we want some rows for the column or all of them?
-            let column_values = match &filter_row_ids {
+            let column_values = match row_ids {
                 Some(row_ids) => col.values(row_ids),
                 None => {
                     // None here means "no partial set of row ids", i.e., get
@@ -628,8 +750,7 @@ impl RowGroup {
         }
 
         // Perform the group by using a hashmap
-        self.read_group_with_hashing(&mut result, &groupby_encoded_ids, aggregate_columns_data);
-        result
+        self.read_group_with_hashing(dst, &groupby_encoded_ids, aggregate_columns_data);
     }
 
     // read_group_hash executes a read-group-aggregate operation on the
@@ -1005,7 +1126,11 @@ impl RowGroup {
                     }
                     AggregateType::Sum => {
                         let agg = agg_col.sum(&group_key_row_ids.to_vec());
-                        agg_cols_out[agg_col_i].push(Value::Scalar(agg));
+                        agg_cols_out[agg_col_i].push(match agg {
+                            // the sum of only NULL values is NULL
+                            Scalar::Null => Value::Null,
+                            agg => Value::Scalar(agg),
+                        });
                     }
                 }
             }
@@ -1057,6 +1182,16 @@ impl RowGroup {
             },
         };
 
+        self.aggregate_columns_for_rows(&row_ids, dst);
+    }
+
+    // Produces a single aggregate for each aggregate column over the provided
+    // rows.
+    fn aggregate_columns_for_rows<'a>(
+        &'a self,
+        row_ids: &[u32],
+        dst: &mut ReadAggregateResult<'a>,
+    ) {
         dst.aggregate_cols = dst
             .schema
             .aggregate_columns
@@ -1069,14 +1204,18 @@ impl RowGroup {
                 // predicate filter.
                 match agg_type {
                     AggregateType::Count => {
-                        let value = Value::Scalar(Scalar::U64(col.count(&row_ids) as u64));
+                        let value = Value::Scalar(Scalar::U64(col.count(row_ids) as u64));
                         agg_vec.push(value);
                     }
                     AggregateType::First => unimplemented!("First not yet implemented"),
                     AggregateType::Last => unimplemented!("Last not yet implemented"),
-                    AggregateType::Min => agg_vec.push(col.min(&row_ids)),
-                    AggregateType::Max => agg_vec.push(col.max(&row_ids)),
-                    AggregateType::Sum => agg_vec.push(Value::Scalar(col.sum(&row_ids))),
+                    AggregateType::Min => agg_vec.push(col.min(row_ids)),
+                    AggregateType::Max => agg_vec.push(col.max(row_ids)),
+                    AggregateType::Sum => agg_vec.push(match col.sum(row_ids) {
+                        // the sum of only NULL values is NULL
+                        Scalar::Null => Value::Null,
+                        sum => Value::Scalar(sum),
+                    }),
                 }
                 agg_vec
             })
@@ -1639,6 +1778,20 @@ impl TryFrom<&DfExpr> for BinaryExpr {
                     }
                 }
             }
+            // starts_with(column, 'prefix')
+            DfExpr::ScalarFunction {
+                fun: BuiltinScalarFunction::StartsWith,
+                args,
+            } => match args.as_slice() {
+                [DfExpr::Column(c), DfExpr::Literal(DFScalarValue::Utf8(Some(prefix)))] => {
+                    Ok(Self::new(
+                        &c.name,
+                        Operator::StartsWith,
+                        Literal::String(prefix.clone()),
+                    ))
+                }
+                _ => Err(format!("unsupported starts_with arguments {:?}", args)),
+            },
             _ => return Err(format!("unsupported expression type {:?}", df_expr)),
         }
     }
@@ -1803,6 +1956,16 @@ impl MetaData {
             // if the column min is at least as small as value then the column
             // could contain the value.
             Operator::LTE => column_min <= value,
+
+            // if the column max is at least as large as the prefix, and the
+            // column min either starts with the prefix or is smaller than it,
+            // then the column could contain a value starting with the prefix.
+            Operator::StartsWith => match (column_min, column_max, value) {
+                (OwnedValue::String(min), OwnedValue::String(max), Value::String(prefix)) => {
+                    max.as_str() >= *prefix && (min.as_str() <= *prefix || min.starts_with(prefix))
+                }
+                _ => true,
+            },
         }
     }
 
@@ -2002,6 +2165,21 @@ impl Display for &ReadFilterResult<'_> {
     }
 }
 
+/// Returns the exclusive upper bound of the window of width `every` and
+/// shifted by `offset` that the timestamp `t` falls into. All values are in
+/// nanoseconds.
+///
+/// This matches the window boundaries computed by the query engine for fixed
+/// width windows.
+pub fn window_bound(t: i64, every: i64, offset: i64) -> i64 {
+    // Like the query engine, and Flux, ignore overflow
+    // see https://github.com/influxdata/influxdb_iox/issues/2890
+    let t = t.wrapping_sub(offset);
+    t.wrapping_sub(t.rem_euclid(every))
+        .wrapping_add(every)
+        .wrapping_add(offset)
+}
+
 #[derive(Default, Clone)]
 pub struct ReadAggregateResult<'row_group> {
     // a schema describing the columns in the results and their types.
@@ -2127,32 +2305,32 @@ impl<'row_group> ReadAggregateResult<'row_group> {
                         LogicalDataType::Integer => {
                             let arr = other.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_i64(arr.take_as_i64().into_iter());
+                                .extend_with_i64(arr.take_as_i64().into_iter().skip(other_i));
                         }
                         LogicalDataType::Unsigned => {
                             let arr = other.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_u64(arr.take_as_u64().into_iter());
+                                .extend_with_u64(arr.take_as_u64().into_iter().skip(other_i));
                         }
                         LogicalDataType::Float => {
                             let arr = other.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_f64(arr.take_as_f64().into_iter());
+                                .extend_with_f64(arr.take_as_f64().into_iter().skip(other_i));
                         }
                         LogicalDataType::String => {
                             let arr = other.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_str(arr.take_as_str().into_iter());
+                                .extend_with_str(arr.take_as_str().into_iter().skip(other_i));
                         }
                         LogicalDataType::Binary => {
                             let arr = other.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_bytes(arr.take_as_bytes().into_iter());
+                                .extend_with_bytes(arr.take_as_bytes().into_iter().skip(other_i));
                         }
                         LogicalDataType::Boolean => {
                             let arr = other.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_bool(arr.take_as_bool().into_iter());
+                                .extend_with_bool(arr.take_as_bool().into_iter().skip(other_i));
                         }
                     }
                 }
@@ -2171,32 +2349,32 @@ impl<'row_group> ReadAggregateResult<'row_group> {
                         LogicalDataType::Integer => {
                             let arr = self.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_i64(arr.take_as_i64().into_iter());
+                                .extend_with_i64(arr.take_as_i64().into_iter().skip(self_i));
                         }
                         LogicalDataType::Unsigned => {
                             let arr = self.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_u64(arr.take_as_u64().into_iter());
+                                .extend_with_u64(arr.take_as_u64().into_iter().skip(self_i));
                         }
                         LogicalDataType::Float => {
                             let arr = self.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_f64(arr.take_as_f64().into_iter());
+                                .extend_with_f64(arr.take_as_f64().into_iter().skip(self_i));
                         }
                         LogicalDataType::String => {
                             let arr = self.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_str(arr.take_as_str().into_iter());
+                                .extend_with_str(arr.take_as_str().into_iter().skip(self_i));
                         }
                         LogicalDataType::Binary => {
                             let arr = self.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_bytes(arr.take_as_bytes().into_iter());
+                                .extend_with_bytes(arr.take_as_bytes().into_iter().skip(self_i));
                         }
                         LogicalDataType::Boolean => {
                             let arr = self.aggregate_cols.remove(0);
                             result.aggregate_cols[col_i]
-                                .extend_with_bool(arr.take_as_bool().into_iter());
+                                .extend_with_bool(arr.take_as_bool().into_iter().skip(self_i));
                         }
                     }
                 }
@@ -2244,6 +2422,15 @@ impl<'row_group> ReadAggregateResult<'row_group> {
                         let (_, agg_type, _) = &self.schema.aggregate_columns[col_i];
                         col.push(match agg_type {
                             AggregateType::Count => self_value + other_value,
+                            // NULL aggregates are the result of groups that
+                            // only had NULL values, and so they must not win
+                            // against a non-NULL min or max.
+                            AggregateType::Min | AggregateType::Max if self_value.is_null() => {
+                                other_value
+                            }
+                            AggregateType::Min | AggregateType::Max if other_value.is_null() => {
+                                self_value
+                            }
                             AggregateType::Min => match self_value.partial_cmp(&other_value) {
                                 Some(ord) => match ord {
                                     Ordering::Less => self_value,
@@ -3072,6 +3259,129 @@ POST,west,2
         read_aggregate_single_groupby_column(&row_group);
     }
 
+    #[test]
+    fn read_aggregate_window() {
+        let mut columns = vec![];
+        let tc = ColumnType::Time(Column::from(&[1_i64, 2, 3, 4, 5, 6][..]));
+        columns.push(("time".to_string(), tc));
+
+        let rc = ColumnType::Tag(Column::from(
+            &["west", "west", "east", "west", "west", "east"][..],
+        ));
+        columns.push(("region".to_string(), rc));
+
+        let fc = ColumnType::Field(Column::from(arrow::array::Int64Array::from(vec![
+            Some(100),
+            Some(101),
+            None,
+            Some(203),
+            Some(203),
+            Some(10),
+        ])));
+        columns.push(("counter".to_string(), fc));
+
+        let row_group = RowGroup::new(6, columns);
+
+        let cases = vec![
+            (
+                Predicate::default(),
+                vec!["region"],
+                2,
+                0,
+                vec![
+                    (2, "region,counter_sum,counter_count\nwest,100,1\n"),
+                    (
+                        4,
+                        "region,counter_sum,counter_count\neast,NULL,0\nwest,101,1\n",
+                    ),
+                    (6, "region,counter_sum,counter_count\nwest,406,2\n"),
+                    (8, "region,counter_sum,counter_count\neast,10,1\n"),
+                ],
+            ),
+            (
+                Predicate::with_time_range(&[], 2, 6),
+                vec!["region"],
+                3,
+                1,
+                vec![
+                    (
+                        4,
+                        "region,counter_sum,counter_count\neast,NULL,0\nwest,101,1\n",
+                    ),
+                    (7, "region,counter_sum,counter_count\nwest,406,2\n"),
+                ],
+            ),
+            (
+                Predicate::default(),
+                vec![],
+                3,
+                0,
+                vec![
+                    (3, "counter_sum,counter_count\n201,2\n"),
+                    (6, "counter_sum,counter_count\n406,2\n"),
+                    (9, "counter_sum,counter_count\n10,1\n"),
+                ],
+            ),
+            // all rows in a single window
+            (
+                Predicate::default(),
+                vec!["region"],
+                10,
+                0,
+                vec![(
+                    10,
+                    "region,counter_sum,counter_count\neast,10,1\nwest,607,4\n",
+                )],
+            ),
+            // no matching rows
+            (
+                Predicate::with_time_range(&[], 10, 20),
+                vec!["region"],
+                10,
+                0,
+                vec![],
+            ),
+        ];
+
+        let aggs = vec![
+            ("counter", AggregateType::Sum),
+            ("counter", AggregateType::Count),
+        ];
+        for (predicate, group_cols, every, offset, expected) in cases {
+            let results = row_group
+                .read_aggregate_window(&predicate, &group_cols, &aggs, every, offset)
+                .into_iter()
+                .map(|(bound, mut result)| {
+                    result.sort();
+                    (bound, format!("{:?}", &result))
+                })
+                .collect::<Vec<_>>();
+
+            let expected = expected
+                .into_iter()
+                .map(|(bound, result)| (bound, result.to_owned()))
+                .collect::<Vec<_>>();
+            assert_eq!(results, expected);
+        }
+    }
+
+    #[test]
+    fn window_bound() {
+        assert_eq!(super::window_bound(0, 10, 0), 10);
+        assert_eq!(super::window_bound(9, 10, 0), 10);
+        assert_eq!(super::window_bound(10, 10, 0), 20);
+        assert_eq!(super::window_bound(-1, 10, 0), 0);
+        assert_eq!(super::window_bound(-10, 10, 0), 0);
+        assert_eq!(super::window_bound(-11, 10, 0), -10);
+        assert_eq!(super::window_bound(4, 10, 5), 5);
+        assert_eq!(super::window_bound(5, 10, 5), 15);
+        assert_eq!(super::window_bound(4, 10, -5), 5);
+
+        // overflow wraps around
+        assert_eq!(super::window_bound(i64::MAX, 10, 0), i64::MIN + 2);
+        assert_eq!(super::window_bound(i64::MIN, 10, 5), i64::MIN + 7);
+    }
+
     // the read_group path where grouping is on fewer than five columns.
     fn read_aggregate_hash_u128_key(row_group: &RowGroup) {
         let cases = vec![
@@ -3301,6 +3611,26 @@ west,POST,304,101,203
                 predicate
             );
         }
+
+        let cases = vec![
+            ("we", true),        // region column might contain "west"
+            ("ea", true),        // region column min is "east"
+            ("ab", false),       // region column values all sort after "ab…"
+            ("x", false),        // region column values all sort before "x…"
+            ("westerly", false), // region column max is "west"
+        ];
+
+        for (prefix, exp) in cases {
+            let predicate =
+                Predicate::new(vec![BinaryExpr::from(("region", "starts_with", prefix))]);
+
+            assert_eq!(
+                row_group.could_satisfy_conjunctive_binary_expressions(predicate.iter()),
+                exp,
+                "{:?} failed",
+                predicate
+            );
+        }
     }
 
     #[test]
@@ -3680,6 +4010,70 @@ west,host-c,pro,10,6
         );
     }
 
+    #[test]
+    fn read_aggregate_result_merge_interleaved() {
+        let schema = ResultSchema {
+            group_columns: vec![(
+                schema::ColumnType::Tag("region".to_owned()),
+                LogicalDataType::String,
+            )],
+            aggregate_columns: vec![
+                (
+                    schema::ColumnType::Field("temp".to_owned()),
+                    AggregateType::Min,
+                    LogicalDataType::Integer,
+                ),
+                (
+                    schema::ColumnType::Field("temp".to_owned()),
+                    AggregateType::Sum,
+                    LogicalDataType::Integer,
+                ),
+            ],
+            ..ResultSchema::default()
+        };
+
+        let result = ReadAggregateResult {
+            schema: schema.clone(),
+            group_key_cols: vec![vec![Some("east"), Some("north")]],
+            aggregate_cols: vec![
+                AggregateVec::MinI64(vec![None, Some(3)]),
+                AggregateVec::SumI64(vec![None, Some(3)]),
+            ],
+            ..Default::default()
+        };
+
+        let other_result = ReadAggregateResult {
+            schema: schema.clone(),
+            group_key_cols: vec![vec![Some("east"), Some("south"), Some("west")]],
+            aggregate_cols: vec![
+                AggregateVec::MinI64(vec![Some(10), Some(1), Some(2)]),
+                AggregateVec::SumI64(vec![Some(10), Some(1), Some(2)]),
+            ],
+            ..Default::default()
+        };
+
+        // NULL aggregates from groups without any values do not replace the
+        // aggregates from other results, and the remaining rows of the
+        // undrained result are all appended.
+        assert_eq!(
+            result.merge(other_result),
+            ReadAggregateResult {
+                schema,
+                group_key_cols: vec![vec![
+                    Some("east"),
+                    Some("north"),
+                    Some("south"),
+                    Some("west")
+                ]],
+                aggregate_cols: vec![
+                    AggregateVec::MinI64(vec![Some(10), Some(3), Some(1), Some(2)]),
+                    AggregateVec::SumI64(vec![Some(10), Some(3), Some(1), Some(2)]),
+                ],
+                ..Default::default()
+            }
+        );
+    }
+
     #[test]
     fn column_meta_equal() {
         let col1 = ColumnMeta {
@@ -4092,6 +4486,17 @@ west,host-c,pro,10,6
                 )))),
                 BinaryExpr::from(("a", "=", 1000000_i64)),
             ),
+            (
+                // starts_with(a, 'we')
+                Expr::ScalarFunction {
+                    fun: BuiltinScalarFunction::StartsWith,
+                    args: vec![
+                        col("a"),
+                        Expr::Literal(ScalarValue::Utf8(Some("we".into()))),
+                    ],
+                },
+                BinaryExpr::from(("a", "starts_with", "we")),
+            ),
         ];
 
         for (input, exp) in cases {
@@ -4105,6 +4510,14 @@ west,host-c,pro,10,6
                 .eq(Expr::Literal(ScalarValue::Int64(Some(33)))),
             // a > b
             col("a").gt(col("b")),
+            // starts_with('west', a)
+            Expr::ScalarFunction {
+                fun: BuiltinScalarFunction::StartsWith,
+                args: vec![
+                    Expr::Literal(ScalarValue::Utf8(Some("west".into()))),
+                    col("a"),
+                ],
+            },
         ];
 
         for input in cases {
@@ -124,7 +124,11 @@ impl TryFrom<&ResultSchema> for ::schema::Schema {
 
             match col_type {
                 ColumnType::Field(_) => builder.influx_field(col_name.as_str(), data_type.into()),
-                ColumnType::Other(_) => builder.field(col_name.as_str(), data_type.into()),
+                // Aggregates of the timestamp column, such as the most recent
+                // timestamp in a group, are not themselves timestamp columns.
+                ColumnType::Other(_) | ColumnType::Timestamp(_) => {
+                    builder.field(col_name.as_str(), data_type.into())
+                }
                 ct => unreachable!("not possible to aggregate {:?} columns", ct),
             };
         }
@@ -11,7 +11,7 @@ use parking_lot::RwLock;
 use schema::selection::Selection;
 use snafu::{ensure, Snafu};
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     convert::TryInto,
     fmt::Display,
     sync::Arc,
@@ -27,6 +27,9 @@ pub enum Error {
 
     #[snafu(display("unsupported column operation on column \"{}\": {}", column_name, msg))]
     UnsupportedColumnOperation { msg: String, column_name: String },
+
+    #[snafu(display("invalid window duration {}: must be positive", every))]
+    InvalidWindow { every: i64 },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -354,26 +357,7 @@ impl Table {
         // Determine if predicate can be applied to table.
         let predicate: Predicate = meta.validate_exprs(predicate)?.into();
 
-        // Filter out any column names that we do not have data for.
-        let schema = ResultSchema {
-            group_columns: match group_columns {
-                Selection::All => meta.schema_for_all_columns(),
-                Selection::Some(column_names) => meta.schema_for_column_names(column_names),
-            },
-            aggregate_columns: meta.schema_for_aggregate_column_names(aggregates),
-            ..ResultSchema::default()
-        };
-
-        // Check all grouping columns are valid for grouping operation.
-        for (ct, _) in &schema.group_columns {
-            ensure!(
-                matches!(ct, ColumnType::Tag(_)),
-                UnsupportedColumnOperation {
-                    msg: format!("column type must be ColumnType::Tag, got {:?}", ct),
-                    column_name: ct.as_str().to_string(),
-                },
-            )
-        }
+        let schema = Self::aggregate_schema(&meta, group_columns, aggregates)?;
 
         // Filtered set of row groups
         let row_groups = self.filter_row_groups(&predicate, row_groups);
@@ -389,10 +373,8 @@ impl Table {
 
     /// Returns aggregates segmented by grouping keys and windowed by time.
     ///
-    /// The set of data to be aggregated may be filtered by (currently only)
-    /// equality predicates, but can be ranged by time, which should be
-    /// represented as nanoseconds since the epoch. Results are included if they
-    /// satisfy the predicate and fall with the [min, max) time range domain.
+    /// Rows are included if they satisfy the predicate, which may include a
+    /// time range represented as nanoseconds since the epoch.
     ///
     /// Group keys are determined according to the provided group column names
     /// (`group_columns`). Currently only grouping by string (tag key) columns
@@ -402,21 +384,72 @@ impl Table {
     /// and the type of aggregation required. Multiple aggregations can be
     /// applied to the same column.
     ///
-    /// Results are grouped and windowed according to the `window` parameter,
-    /// which represents an interval in nanoseconds. For example, to window
-    /// results by one minute, window should be set to 600_000_000_000.
-    pub fn aggregate_window<'a>(
+    /// Results are windowed into fixed intervals of `every` nanoseconds,
+    /// shifted by `offset` nanoseconds. For example, to window results by one
+    /// minute, `every` should be set to 60_000_000_000. Each window in the
+    /// results is identified by its exclusive upper bound.
+    pub fn aggregate_window<'input>(
         &self,
-        _time_range: (i64, i64),
-        _predicates: &[(&str, &str)],
-        _group_columns: Vec<ColumnName<'a>>,
-        _aggregates: Vec<(ColumnName<'a>, AggregateType)>,
-        _window: i64,
-    ) -> BTreeMap<Vec<String>, Vec<(ColumnName<'a>, ReadAggregateResults)>> {
-        // identify segments where time range and predicates match could match
-        // using segment meta data, and then execute against those segments and
-        // merge results.
-        todo!()
+        predicate: Predicate,
+        group_columns: &'input Selection<'_>,
+        aggregates: &'input [(ColumnName<'input>, AggregateType)],
+        every: i64,
+        offset: i64,
+    ) -> Result<ReadWindowAggregateResults> {
+        ensure!(every > 0, InvalidWindow { every });
+
+        let (meta, row_groups) = {
+            let table_data = self.table_data.read();
+            (Arc::clone(&table_data.meta), table_data.data.clone())
+        };
+
+        // Determine if predicate can be applied to table.
+        let predicate: Predicate = meta.validate_exprs(predicate)?.into();
+
+        let schema = Self::aggregate_schema(&meta, group_columns, aggregates)?;
+
+        // Filtered set of row groups
+        let row_groups = self.filter_row_groups(&predicate, row_groups);
+
+        Ok(ReadWindowAggregateResults {
+            schema,
+            predicate,
+            row_groups,
+            every,
+            offset,
+            results: None,
+        })
+    }
+
+    // Builds the schema for the results of an aggregate, filtering out any
+    // columns that we do not have data for, and checking that all the group
+    // columns can be grouped on.
+    fn aggregate_schema(
+        meta: &MetaData,
+        group_columns: &Selection<'_>,
+        aggregates: &[(ColumnName<'_>, AggregateType)],
+    ) -> Result<ResultSchema> {
+        let schema = ResultSchema {
+            group_columns: match group_columns {
+                Selection::All => meta.schema_for_all_columns(),
+                Selection::Some(column_names) => meta.schema_for_column_names(column_names),
+            },
+            aggregate_columns: meta.schema_for_aggregate_column_names(aggregates),
+            ..ResultSchema::default()
+        };
+
+        // Check all grouping columns are valid for grouping operation.
+        for (ct, _) in &schema.group_columns {
+            ensure!(
+                matches!(ct, ColumnType::Tag(_)),
+                UnsupportedColumnOperation {
+                    msg: format!("column type must be ColumnType::Tag, got {:?}", ct),
+                    column_name: ct.as_str().to_string(),
+                },
+            )
+        }
+
+        Ok(schema)
     }
 
     //
@@ -749,6 +782,20 @@ impl MetaData {
     ) -> Result<Vec<BinaryExpr>, Error> {
         iter.into_iter().try_fold(vec![], |mut arr, expr| {
             match self.columns.get(expr.column()) {
+                // prefix comparisons are only supported on string columns
+                Some(col_meta)
+                    if expr.op() == column::cmp::Operator::StartsWith
+                        && col_meta.logical_data_type != LogicalDataType::String =>
+                {
+                    return UnsupportedColumnOperation {
+                        column_name: expr.column().to_owned(),
+                        msg: format!(
+                            "cannot compare prefix of column type {}",
+                            col_meta.logical_data_type,
+                        ),
+                    }
+                    .fail()
+                }
                 Some(col_meta) => match (col_meta.logical_data_type, expr.literal()) {
                     (LogicalDataType::Integer, Literal::Integer(_))
                     | (LogicalDataType::Unsigned, Literal::Unsigned(_))
@@ -1084,6 +1131,97 @@ impl Iterator for ReadAggregateResults {
     }
 }
 
+pub struct ReadWindowAggregateResults {
+    // schema information for the results
+    schema: ResultSchema,
+
+    // the predicate to apply to each row group.
+    predicate: Predicate,
+
+    // row groups that will be executed against. The columns to group on and the
+    // aggregates to produce are determined by the `schema`.
+    row_groups: Vec<Arc<RowGroup>>,
+
+    // the width and offset of each window in nanoseconds.
+    every: i64,
+    offset: i64,
+
+    // results for each window, which are materialised on the first call to
+    // `next`.
+    results: Option<VecDeque<(i64, RecordBatch)>>,
+}
+
+impl ReadWindowAggregateResults {
+    /// Returns the schema associated with table result and therefore all of
+    /// results from row groups.
+    pub fn schema(&self) -> &ResultSchema {
+        &self.schema
+    }
+
+    // Executes against all row groups, merging the results for each window
+    // across row groups. Windows are ordered by their upper bounds.
+    fn merged_results(&self) -> BTreeMap<i64, row_group::ReadAggregateResult<'_>> {
+        let group_columns = self
+            .schema
+            .group_column_names_iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>();
+        let aggregates = self
+            .schema
+            .aggregate_columns
+            .iter()
+            .map(|(name, agg_type, _)| (name.as_str(), *agg_type))
+            .collect::<Vec<_>>();
+
+        let mut merged_results = BTreeMap::new();
+        for row_group in &self.row_groups {
+            let results = row_group.read_aggregate_window(
+                &self.predicate,
+                &group_columns,
+                &aggregates,
+                self.every,
+                self.offset,
+            );
+
+            for (window, result) in results {
+                assert_eq!(result.schema(), self.schema()); // validate schema
+
+                // merge result into on-going results for the window.
+                let result = match merged_results.remove(&window) {
+                    Some(merged_result) => result.merge(merged_result),
+                    None => result,
+                };
+                merged_results.insert(window, result);
+            }
+        }
+
+        merged_results
+    }
+}
+
+/// Implements an iterator on the Table's results for `aggregate_window`. Each
+/// item is the upper bound of a window along with the aggregates for that
+/// window, merged across all row groups. Windows without any rows are not
+/// yielded.
+impl Iterator for ReadWindowAggregateResults {
+    type Item = (i64, RecordBatch);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.results.is_none() {
+            let results = self
+                .merged_results()
+                .into_iter()
+                .map(|(window, merged_result)| (window, merged_result.try_into().unwrap()))
+                .collect();
+            self.results = Some(results);
+        }
+
+        self.results
+            .as_mut()
+            .and_then(|results| results.pop_front())
+    }
+}
+
 // Helper type that can pretty print a set of results for `read_aggregate`.
 struct DisplayReadAggregateResults<'a>(Vec<row_group::ReadAggregateResult<'a>>);
 
@@ -1198,6 +1336,7 @@ mod test {
             BinaryExpr::from(("f64_col", "=", 100.0)),
             BinaryExpr::from(("str_col", "=", "hello")),
             BinaryExpr::from(("bool_col", "=", true)),
+            BinaryExpr::from(("str_col", "starts_with", "so")),
         ];
 
         for expr in predicates {
@@ -1217,6 +1356,8 @@ mod test {
             vec![BinaryExpr::from(("f64_col", "=", false))],
             vec![BinaryExpr::from(("str_col", "=", 10_i64))],
             vec![BinaryExpr::from(("bool_col", "=", "true"))],
+            vec![BinaryExpr::from(("time", "starts_with", "1"))],
+            vec![BinaryExpr::from(("i64_col", "starts_with", 10_i64))],
             // mixture valid/invalid
             vec![
                 BinaryExpr::from(("time", "=", 100_i64)),
@@ -1642,6 +1783,81 @@ mod test {
         ),);
     }
 
+    #[test]
+    fn aggregate_window() {
+        // Build first row group.
+        let columns = vec![
+            (
+                "time".to_string(),
+                ColumnType::create_time(&[100, 200, 300]),
+            ),
+            (
+                "region".to_string(),
+                ColumnType::create_tag(&["west", "west", "east"]),
+            ),
+        ];
+        let rg = RowGroup::new(3, columns);
+        let mut table = Table::with_row_group("cpu", rg);
+
+        // Build another row group.
+        let columns = vec![
+            ("time".to_string(), ColumnType::create_time(&[150, 350])),
+            (
+                "region".to_string(),
+                ColumnType::create_tag(&["west", "north"]),
+            ),
+        ];
+        let rg = RowGroup::new(2, columns);
+        table.add_row_group(rg);
+
+        let results = table
+            .aggregate_window(
+                Predicate::default(),
+                &Selection::Some(&["region"]),
+                &[("time", AggregateType::Count), ("time", AggregateType::Sum)],
+                200,
+                0,
+            )
+            .unwrap();
+
+        let merged_results = results
+            .merged_results()
+            .into_iter()
+            .map(|(window, mut result)| {
+                result.sort();
+                (
+                    window,
+                    DisplayReadAggregateResults(vec![result]).to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            merged_results,
+            vec![
+                (200, "region,time_count,time_sum\nwest,2,250\n".to_owned()),
+                (
+                    400,
+                    "region,time_count,time_sum\neast,1,300\nnorth,1,350\nwest,1,200\n".to_owned()
+                ),
+            ]
+        );
+
+        // each window is yielded once as a record batch.
+        let windows = results.map(|(window, _)| window).collect::<Vec<_>>();
+        assert_eq!(windows, vec![200, 400]);
+
+        // window durations must be positive.
+        let results = table.aggregate_window(
+            Predicate::default(),
+            &Selection::Some(&["region"]),
+            &[("time", AggregateType::Count)],
+            0,
+            0,
+        );
+        assert!(matches!(&results, Err(Error::InvalidWindow { every: 0 })));
+    }
+
     #[test]
     fn read_aggregate_result_display() {
         let result_a = ReadAggregateResult {
@@ -111,6 +111,9 @@ impl AggregateVec {
     /// Panics if the type of `Value` does not satisfy the aggregate type.
     pub fn update(&mut self, values: &Values<'_>, row_id: usize, offset: usize) {
         if values.is_null(row_id) {
+            // NULL values do not contribute to the aggregate, but the group
+            // at `offset` must still have an aggregate value.
+            self.ensure_offset(offset);
             return;
         }
 
@@ -297,6 +300,51 @@ impl AggregateVec {
         }
     }
 
+    // Ensures there is an aggregate value at `offset`, which is zero for
+    // counts and NULL for all other aggregates until a value is added.
+    fn ensure_offset(&mut self, offset: usize) {
+        match self {
+            Self::Count(arr) => {
+                if offset >= arr.len() {
+                    arr.resize(offset + 1, None);
+                }
+                arr[offset].get_or_insert(0);
+            }
+            Self::SumU64(arr) | Self::MinU64(arr) | Self::MaxU64(arr) => {
+                if offset >= arr.len() {
+                    arr.resize(offset + 1, None);
+                }
+            }
+            Self::SumI64(arr) | Self::MinI64(arr) | Self::MaxI64(arr) => {
+                if offset >= arr.len() {
+                    arr.resize(offset + 1, None);
+                }
+            }
+            Self::SumF64(arr) | Self::MinF64(arr) | Self::MaxF64(arr) => {
+                if offset >= arr.len() {
+                    arr.resize(offset + 1, None);
+                }
+            }
+            Self::MinString(arr) | Self::MaxString(arr) => {
+                if offset >= arr.len() {
+                    arr.resize(offset + 1, None);
+                }
+            }
+            Self::MinBytes(arr) | Self::MaxBytes(arr) => {
+                if offset >= arr.len() {
+                    arr.resize(offset + 1, None);
+                }
+            }
+            Self::MinBool(arr) | Self::MaxBool(arr) => {
+                if offset >= arr.len() {
+                    arr.resize(offset + 1, None);
+                }
+            }
+            // TODO - implement first/last
+            _ => unimplemented!("aggregate update not implemented"),
+        }
+    }
+
     /// Appends the provided value to the end of the aggregate vector.
     /// Panics if the type of `Value` does not satisfy the aggregate type.
     ///
@@ -1461,6 +1509,9 @@ impl std::ops::Add for Value<'_> {
     fn add(self, other: Self) -> Self {
         match (self, other) {
             (Self::Scalar(a), Self::Scalar(b)) => Self::Scalar(a + b),
+            // NULL is the identity for addition of aggregates.
+            (Self::Null, other) => other,
+            (this, Self::Null) => this,
             _ => panic!("unsupported operation on Value"),
         }
     }
@@ -1969,6 +2020,37 @@ mod test {
         );
     }
 
+    #[test]
+    fn aggregate_vec_update_null_group() {
+        // The group at offset 1 only has NULL values but must still be
+        // represented in every aggregate.
+        let values = Values::I64N(vec![Some(1), None, None]);
+        let offsets = [0, 1, 1];
+
+        let mut aggs = vec![
+            AggregateVec::Count(vec![]),
+            AggregateVec::SumI64(vec![]),
+            AggregateVec::MinI64(vec![]),
+            AggregateVec::MaxI64(vec![]),
+        ];
+
+        for (i, offset) in offsets.iter().enumerate() {
+            for agg in &mut aggs {
+                agg.update(&values, i, *offset);
+            }
+        }
+
+        assert_eq!(
+            aggs,
+            vec![
+                AggregateVec::Count(vec![Some(1), Some(0)]),
+                AggregateVec::SumI64(vec![Some(1), None]),
+                AggregateVec::MinI64(vec![Some(1), None]),
+                AggregateVec::MaxI64(vec![Some(1), None]),
+            ]
+        );
+    }
+
     #[test]
     fn size() {
         let v1 = OwnedValue::new_null();
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use cache_loader_async::cache_api::LoadingCache;
 use data_types::write_buffer::WriteBufferConnection;
+use influxdb_iox_client::connection::Builder;
 use observability_deps::tracing::debug;
 use write_buffer::{
     config::WriteBufferConfigFactory,
@@ -45,8 +46,14 @@ pub struct ConnectionPool {
 impl ConnectionPool {
     /// Create new connection pool.
     ///
-    /// If `use_mock_grpc` is set only mock gRPC clients are created.
-    pub async fn new(use_mock_grpc: bool, wb_factory: Arc<WriteBufferConfigFactory>) -> Self {
+    /// gRPC connections are established with the settings of `grpc_builder`, e.g. the TLS
+    /// certificates used for `https` remotes. If `use_mock_grpc` is set only mock gRPC clients are
+    /// created.
+    pub async fn new(
+        use_mock_grpc: bool,
+        grpc_builder: Builder,
+        wb_factory: Arc<WriteBufferConfigFactory>,
+    ) -> Self {
         // Note: this function is async even though it does not contain any `.await` calls because `LoadingCache::new`
         // requires tokio to be running and even if documented people will forget about this.
 
@@ -57,15 +64,17 @@ impl ConnectionPool {
                 Ok(Arc::new(MockClient::default()) as Arc<dyn GrpcClient>)
             })
         } else {
-            LoadingCache::new(|connection_string: String| async move {
-                use crate::grpc_client::RealClient;
-                use influxdb_iox_client::connection::Builder;
-
-                let connection = Builder::default()
-                    .build(&connection_string)
-                    .await
-                    .map_err(|e| Arc::new(e) as ConnectionError)?;
-                Ok(Arc::new(RealClient::new(connection)) as Arc<dyn GrpcClient>)
+            LoadingCache::new(move |connection_string: String| {
+                let grpc_builder = grpc_builder.clone();
+                async move {
+                    use crate::grpc_client::RealClient;
+
+                    let connection = grpc_builder
+                        .build(&connection_string)
+                        .await
+                        .map_err(|e| Arc::new(e) as ConnectionError)?;
+                    Ok(Arc::new(RealClient::new(connection)) as Arc<dyn GrpcClient>)
+                }
             })
         };
 
@@ -94,6 +103,7 @@ impl ConnectionPool {
         let metric_registry = Arc::new(metric::Registry::new());
         Self::new(
             true,
+            Builder::default(),
             Arc::new(WriteBufferConfigFactory::new(
                 time_provider,
                 metric_registry,
@@ -147,6 +157,7 @@ mod tests {
 
         let pool1 = ConnectionPool::new(
             false,
+            Builder::default(),
             Arc::new(WriteBufferConfigFactory::new(
                 Arc::clone(&time_provider),
                 Arc::clone(&metric_registry),
@@ -158,6 +169,7 @@ mod tests {
 
         let pool2 = ConnectionPool::new(
             true,
+            Builder::default(),
             Arc::new(WriteBufferConfigFactory::new(
                 time_provider,
                 metric_registry,
@@ -25,6 +25,11 @@ pub enum WriteError {
     MultiWriteFailure {
         errors: BTreeMap<ShardId, WriteErrorShard>,
     },
+
+    #[snafu(display("Error applying ingest rules: {}", source))]
+    IngestRules {
+        source: mutable_batch::transform::Error,
+    },
 }
 
 fn fmt_write_errors(errors: &BTreeMap<ShardId, WriteErrorShard>) -> String {
@@ -99,6 +104,20 @@ impl Router {
 
     /// Shard and write data.
     pub async fn write(&self, operation: DmlOperation) -> Result<(), WriteError> {
+        let operation = match operation {
+            DmlOperation::Write(write) => {
+                match write
+                    .transform(&self.config.ingest_rules)
+                    .context(IngestRules)?
+                {
+                    Some(write) => DmlOperation::Write(write),
+                    // Every row was dropped by the ingest rules
+                    None => return Ok(()),
+                }
+            }
+            operation => operation,
+        };
+
         let mut errors: BTreeMap<ShardId, WriteErrorShard> = Default::default();
 
         // The iteration order is stable here, so we ensure deterministic behavior and error order.
@@ -136,6 +155,7 @@ mod tests {
 
     use data_types::{
         delete_predicate::DeletePredicate,
+        ingest::{IngestAction, IngestRule},
         non_empty::NonEmptyString,
         router::{
             Matcher, MatcherToShard, ShardConfig, WriteSink as WriteSinkConfig,
@@ -160,6 +180,7 @@ mod tests {
             write_sharder: Default::default(),
             write_sinks: Default::default(),
             query_sinks: Default::default(),
+            ingest_rules: vec![],
         };
         let router = Router::new(cfg.clone(), resolver, connection_pool);
 
@@ -256,6 +277,7 @@ mod tests {
                 ),
             ]),
             query_sinks: Default::default(),
+            ingest_rules: vec![],
         };
         let router = Router::new(cfg.clone(), resolver, connection_pool);
 
@@ -345,6 +367,107 @@ mod tests {
         )]);
     }
 
+    #[tokio::test]
+    async fn test_write_ingest_rules() {
+        let server_id_1 = ServerId::try_from(1).unwrap();
+        let server_id_2 = ServerId::try_from(2).unwrap();
+
+        let resolver = Arc::new(Resolver::new(Some(RemoteTemplate::new("{id}"))));
+        let connection_pool = Arc::new(ConnectionPool::new_testing().await);
+
+        let client_1 = connection_pool.grpc_client("1").await.unwrap();
+        let client_2 = connection_pool.grpc_client("2").await.unwrap();
+        let client_1 = client_1.as_any().downcast_ref::<MockClient>().unwrap();
+        let client_2 = client_2.as_any().downcast_ref::<MockClient>().unwrap();
+
+        let cfg = RouterConfig {
+            name: String::from("my_router"),
+            write_sharder: ShardConfig {
+                specific_targets: vec![
+                    MatcherToShard {
+                        matcher: Matcher {
+                            table_name_regex: Some(Regex::new("^foo_").unwrap()),
+                        },
+                        shard: ShardId::new(10),
+                    },
+                    MatcherToShard {
+                        matcher: Matcher {
+                            table_name_regex: Some(Regex::new(".*").unwrap()),
+                        },
+                        shard: ShardId::new(20),
+                    },
+                ],
+                hash_ring: None,
+            },
+            write_sinks: BTreeMap::from([
+                (
+                    ShardId::new(10),
+                    WriteSinkSetConfig {
+                        sinks: vec![WriteSinkConfig {
+                            sink: WriteSinkVariantConfig::GrpcRemote(server_id_1),
+                            ignore_errors: false,
+                        }],
+                    },
+                ),
+                (
+                    ShardId::new(20),
+                    WriteSinkSetConfig {
+                        sinks: vec![WriteSinkConfig {
+                            sink: WriteSinkVariantConfig::GrpcRemote(server_id_2),
+                            ignore_errors: false,
+                        }],
+                    },
+                ),
+            ]),
+            query_sinks: Default::default(),
+            ingest_rules: vec![
+                IngestRule {
+                    table_name_regex: None,
+                    action: IngestAction::DropRows {
+                        column: "host".to_string(),
+                        regex: Regex::new("^test-").unwrap(),
+                    },
+                },
+                IngestRule {
+                    table_name_regex: None,
+                    action: IngestAction::RenameTable {
+                        regex: Regex::new("^legacy_(.*)$").unwrap(),
+                        replacement: "foo_$1".to_string(),
+                    },
+                },
+            ],
+        };
+        let router = Router::new(cfg, resolver, connection_pool);
+
+        // tables are renamed before the write is sharded
+        let meta = DmlMeta::unsequenced(None);
+        let write = db_write(
+            &[
+                "legacy_bar x=1 1",
+                "www,host=test-1 x=2 2",
+                "www,host=a x=3 3",
+            ],
+            &meta,
+        );
+        router.write(write).await.unwrap();
+        client_1.assert_writes(&[(
+            String::from("my_router"),
+            db_write(&["foo_bar x=1 1"], &meta),
+        )]);
+        client_2.assert_writes(&[(
+            String::from("my_router"),
+            db_write(&["www,host=a x=3 3"], &meta),
+        )]);
+
+        // writes without rows left are not forwarded
+        let write = db_write(&["www,host=test-2 x=4 4"], &meta);
+        router.write(write).await.unwrap();
+        client_2.assert_writes(&[(
+            String::from("my_router"),
+            db_write(&["www,host=a x=3 3"], &meta),
+        )]);
+    }
+
     #[tokio::test]
     async fn test_delete() {
         let server_id_1 = ServerId::try_from(1).unwrap();
@@ -398,6 +521,7 @@ mod tests {
                 ),
             ]),
             query_sinks: Default::default(),
+            ingest_rules: vec![],
         };
         let router = Router::new(cfg.clone(), resolver, connection_pool);
 
@@ -1,6 +1,7 @@
 use std::{collections::BTreeMap, sync::Arc};
 
 use data_types::{router::Router as RouterConfig, server_id::ServerId};
+use influxdb_iox_client::connection::Builder;
 use metric::Registry as MetricRegistry;
 use parking_lot::RwLock;
 use snafu::Snafu;
@@ -33,12 +34,23 @@ pub struct RouterServer {
 }
 
 impl RouterServer {
+    /// Create a new router server, connecting to remote gRPC servers with the settings of
+    /// `grpc_builder`.
     pub async fn new(
         remote_template: Option<RemoteTemplate>,
         trace_collector: Option<Arc<dyn TraceCollector>>,
         time_provider: Arc<dyn TimeProvider>,
+        grpc_builder: Builder,
     ) -> Self {
-        Self::new_inner(remote_template, trace_collector, time_provider, None, false).await
+        Self::new_inner(
+            remote_template,
+            trace_collector,
+            time_provider,
+            grpc_builder,
+            None,
+            false,
+        )
+        .await
     }
 
     pub async fn for_testing(
@@ -51,6 +63,7 @@ impl RouterServer {
             remote_template,
             trace_collector,
             time_provider,
+            Builder::default(),
             wb_factory,
             true,
         )
@@ -61,6 +74,7 @@ impl RouterServer {
         remote_template: Option<RemoteTemplate>,
         trace_collector: Option<Arc<dyn TraceCollector>>,
         time_provider: Arc<dyn TimeProvider>,
+        grpc_builder: Builder,
         wb_factory: Option<Arc<WriteBufferConfigFactory>>,
         use_mock_grpc: bool,
     ) -> Self {
@@ -71,7 +85,8 @@ impl RouterServer {
                 Arc::clone(&metric_registry),
             ))
         });
-        let connection_pool = Arc::new(ConnectionPool::new(use_mock_grpc, wb_factory).await);
+        let connection_pool =
+            Arc::new(ConnectionPool::new(use_mock_grpc, grpc_builder, wb_factory).await);
 
         Self {
             server_id: RwLock::new(None),
@@ -169,7 +184,13 @@ pub mod test_utils {
     use super::RouterServer;
 
     pub async fn make_router_server() -> RouterServer {
-        RouterServer::new(None, None, Arc::new(SystemProvider::new())).await
+        RouterServer::new(
+            None,
+            None,
+            Arc::new(SystemProvider::new()),
+            Default::default(),
+        )
+        .await
     }
 }
 
@@ -212,6 +233,7 @@ mod tests {
             write_sharder: Default::default(),
             write_sinks: Default::default(),
             query_sinks: Default::default(),
+            ingest_rules: vec![],
         };
         let cfg_foo_2 = RouterConfig {
             query_sinks: QuerySinks {
@@ -226,6 +248,7 @@ mod tests {
             write_sharder: Default::default(),
             write_sinks: Default::default(),
             query_sinks: Default::default(),
+            ingest_rules: vec![],
         };
 
         // no routers
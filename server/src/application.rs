@@ -1,13 +1,13 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use object_store::ObjectStore;
 use observability_deps::tracing::info;
-use query::exec::Executor;
+use query::exec::{admission::AdmissionConfig, memory::MemoryConfig, Executor, ExecutorConfig};
 use time::TimeProvider;
 use trace::TraceCollector;
 use write_buffer::config::WriteBufferConfigFactory;
 
-use crate::JobRegistry;
+use crate::{db::subscription::SubscriptionConfig, JobRegistry};
 
 /// A container for application-global resources
 /// shared between server and all DatabaseInstances
@@ -20,20 +20,38 @@ pub struct ApplicationState {
     metric_registry: Arc<metric::Registry>,
     time_provider: Arc<dyn TimeProvider>,
     trace_collector: Option<Arc<dyn TraceCollector>>,
+    subscription_config: Arc<SubscriptionConfig>,
 }
 
 impl ApplicationState {
     /// Creates a new `ApplicationState`
     ///
-    /// Uses number of CPUs in the system if num_worker_threads is not set
+    /// Uses number of CPUs in the system if num_worker_threads is not set.
+    /// Queries run without a timeout unless `query_timeout` or the query
+    /// request sets one, their memory is limited by `query_memory`, and how
+    /// many run at once by `query_admission`. Databases connect to the
+    /// targets of their subscriptions with the settings of
+    /// `subscription_config`.
     pub fn new(
         object_store: Arc<ObjectStore>,
         num_worker_threads: Option<usize>,
+        query_timeout: Option<Duration>,
+        query_memory: MemoryConfig,
+        query_admission: AdmissionConfig,
+        subscription_config: SubscriptionConfig,
         trace_collector: Option<Arc<dyn TraceCollector>>,
     ) -> Self {
         let num_threads = num_worker_threads.unwrap_or_else(num_cpus::get);
         info!(%num_threads, "using specified number of threads per thread pool");
 
+        let executor = Executor::new_with_config(ExecutorConfig {
+            num_threads,
+            target_query_partitions: num_threads,
+            query_timeout,
+            memory: query_memory,
+            admission: query_admission,
+        });
+
         let metric_registry = Arc::new(metric::Registry::new());
         let time_provider: Arc<dyn TimeProvider> = Arc::new(time::SystemProvider::new());
         let job_registry = Arc::new(JobRegistry::new(
@@ -49,11 +67,12 @@ impl ApplicationState {
         Self {
             object_store,
             write_buffer_factory,
-            executor: Arc::new(Executor::new(num_threads)),
+            executor: Arc::new(executor),
             job_registry,
             metric_registry,
             time_provider,
             trace_collector,
+            subscription_config: Arc::new(subscription_config),
         }
     }
 
@@ -85,6 +104,10 @@ impl ApplicationState {
         &self.executor
     }
 
+    pub fn subscription_config(&self) -> &Arc<SubscriptionConfig> {
+        &self.subscription_config
+    }
+
     pub fn join(&self) {
         self.executor.join()
     }
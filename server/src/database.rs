@@ -118,6 +118,18 @@ pub enum Error {
         state: DatabaseStateCode,
     },
 
+    #[snafu(display("cannot lock schema of database {}: {}", db_name, source))]
+    CannotLockSchema {
+        db_name: String,
+        source: crate::db::declared_schema::Error,
+    },
+
+    #[snafu(display("locked schema of database {} is invalid: {}", db_name, source))]
+    InvalidLockedSchema {
+        db_name: String,
+        source: generated_types::google::FieldViolation,
+    },
+
     #[snafu(display("cannot persisted updated rules: {}", source))]
     CannotPersistUpdatedRules { source: crate::rules::Error },
 
@@ -423,6 +435,33 @@ impl Database {
         self.shared.config.location.clone()
     }
 
+    /// Declares every table and column of the database in its schema, and
+    /// switches the schema to strict mode so that writes can no longer create
+    /// new tables or columns
+    pub async fn lock_schema(&self) -> Result<Arc<ProvidedDatabaseRules>, Error> {
+        let (db, provided_rules) = {
+            let state = self.shared.state.read();
+            let initialized = state.get_initialized().context(RulesNotUpdateable {
+                db_name: &self.shared.config.name,
+                state: state.state_code(),
+            })?;
+            (
+                Arc::clone(initialized.db()),
+                Arc::clone(&initialized.provided_rules),
+            )
+        };
+
+        let db_name = &self.shared.config.name;
+        let locked_schema = db.locked_schema().context(CannotLockSchema { db_name })?;
+
+        let mut rules = provided_rules.original().clone();
+        rules.schema = Some(locked_schema.into());
+        let rules =
+            ProvidedDatabaseRules::new_rules(rules).context(InvalidLockedSchema { db_name })?;
+
+        self.update_provided_rules(rules).await
+    }
+
     /// Update the database rules, panic'ing if the state is invalid
     pub async fn update_provided_rules(
         &self,
@@ -1481,6 +1520,7 @@ impl DatabaseStateRulesLoaded {
             catalog,
             metric_registry: Arc::clone(shared.application.metric_registry()),
             time_provider: Arc::clone(shared.application.time_provider()),
+            subscription_config: Arc::clone(shared.application.subscription_config()),
         };
 
         let db = Arc::new(Db::new(
@@ -1835,6 +1875,10 @@ mod tests {
                 connection: "my_mock".to_string(),
                 ..Default::default()
             }),
+            schema: None,
+            ingest_rules: vec![],
+            continuous_queries: vec![],
+            subscriptions: vec![],
         };
         let location = Database::create(
             Arc::clone(&application),
@@ -1944,6 +1988,10 @@ mod tests {
                 connection: "my_mock".to_string(),
                 ..Default::default()
             }),
+            schema: None,
+            ingest_rules: vec![],
+            continuous_queries: vec![],
+            subscriptions: vec![],
         };
         let location = Database::create(
             Arc::clone(&application),
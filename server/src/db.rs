@@ -3,7 +3,9 @@
 
 use std::{
     any::Any,
+    borrow::Cow,
     collections::{HashMap, HashSet},
+    num::NonZeroUsize,
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
@@ -20,7 +22,7 @@ use snafu::{ensure, OptionExt, ResultExt, Snafu};
 pub use ::lifecycle::{LifecycleChunk, LockableChunk, LockablePartition};
 use data_types::{
     chunk_metadata::{ChunkId, ChunkLifecycleAction, ChunkOrder, ChunkSummary},
-    database_rules::DatabaseRules,
+    database_rules::{DatabaseRules, DatabaseSchema},
     delete_predicate::DeletePredicate,
     job::Job,
     partition_metadata::{PartitionSummary, TableSummary},
@@ -30,7 +32,8 @@ use datafusion::catalog::{catalog::CatalogProvider, schema::SchemaProvider};
 use dml::{DmlDelete, DmlMeta, DmlOperation, DmlWrite};
 use internal_types::mailbox::Mailbox;
 use iox_object_store::IoxObjectStore;
-use mutable_batch::payload::PartitionWrite;
+use metric::{Attributes, U64Counter};
+use mutable_batch::{payload::PartitionWrite, MutableBatch};
 use mutable_buffer::{ChunkMetrics as MutableBufferChunkMetrics, MBChunk};
 use observability_deps::tracing::{debug, error, info, warn};
 use parquet_catalog::{
@@ -42,11 +45,16 @@ use parquet_catalog::{
 use persistence_windows::{checkpoint::ReplayPlan, persistence_windows::PersistenceWindows};
 use predicate::predicate::Predicate;
 use query::{
-    exec::{ExecutionContextProvider, Executor, ExecutorType, IOxExecutionContext},
+    exec::{
+        admission::{AdmissionController, AdmissionMetrics},
+        ExecutionContextProvider, Executor, ExecutorType, IOxExecutionContext,
+    },
+    provider::AggregatePushdown,
     QueryDatabase,
 };
 use schema::selection::Selection;
 use schema::Schema;
+pub use system_tables::SYSTEM_SCHEMA;
 use time::{Time, TimeProvider};
 use trace::ctx::SpanContext;
 use tracker::TaskTracker;
@@ -64,7 +72,10 @@ use crate::{
             table::TableSchemaUpsertHandle,
             Catalog, TableNameFilter,
         },
+        declared_schema::DeclaredSchema,
         lifecycle::{LockableCatalogChunk, LockableCatalogPartition},
+        limits::{LimitMetrics, TagValues},
+        subscription::{SubscriptionConfig, Subscriptions},
     },
     JobRegistry,
 };
@@ -72,12 +83,16 @@ use crate::{
 pub mod access;
 pub mod catalog;
 mod chunk;
+pub mod continuous_query;
+pub mod declared_schema;
 mod lifecycle;
+pub mod limits;
 pub mod load;
 pub mod pred;
 mod query_log;
 mod replay;
 mod streams;
+pub mod subscription;
 mod system_tables;
 pub mod write;
 
@@ -144,6 +159,17 @@ pub enum DmlError {
         errors.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
     ))]
     SchemaErrors { errors: Vec<schema::merge::Error> },
+
+    #[snafu(display("Write rejected by the database schema: {}", source))]
+    DeclaredSchemaViolation { source: declared_schema::Error },
+
+    #[snafu(display("Write rejected by a database limit: {}", source))]
+    LimitExceeded { source: limits::Error },
+
+    #[snafu(display("Error applying ingest rules: {}", source))]
+    IngestRules {
+        source: mutable_batch::transform::Error,
+    },
 }
 
 /// `Db` is an instance-local, queryable, possibly persisted, and possibly mutable data store
@@ -208,6 +234,9 @@ pub enum DmlError {
 pub struct Db {
     rules: RwLock<Arc<DatabaseRules>>,
 
+    /// The schema declared in `rules`, if any, prepared for checking writes
+    declared_schema: RwLock<Option<Arc<DeclaredSchema>>>,
+
     name: Arc<str>,
 
     #[allow(dead_code)]
@@ -239,6 +268,18 @@ pub struct Db {
     /// The global metric registry
     metric_registry: Arc<metric::Registry>,
 
+    /// Counts writes rejected for exceeding a limit of the lifecycle rules
+    limit_metrics: LimitMetrics,
+
+    /// Counts queries cancelled for exceeding their timeout
+    query_timeouts: U64Counter,
+
+    /// Admits the queries of this database, if their number is limited
+    admission: Option<Arc<AdmissionController>>,
+
+    /// Records how the queries of this database are admitted
+    admission_metrics: AdmissionMetrics,
+
     /// Catalog interface for query
     catalog_access: Arc<QueryCatalogAccess>,
 
@@ -262,6 +303,16 @@ pub struct Db {
 
     /// TESTING ONLY: Override of IDs for persisted chunks.
     persisted_chunk_id_override: Mutex<Option<ChunkId>>,
+
+    /// The progress and retries of the continuous queries
+    continuous_query_state: tokio::sync::Mutex<continuous_query::State>,
+
+    /// The settings the subscriptions connect to their targets with
+    subscription_config: Arc<SubscriptionConfig>,
+
+    /// Delivers the writes stored by this database to the subscriptions of
+    /// the rules
+    subscriptions: RwLock<Subscriptions>,
 }
 
 /// All the information needed to commit a database
@@ -275,6 +326,7 @@ pub(crate) struct DatabaseToCommit {
     pub(crate) rules: Arc<DatabaseRules>,
     pub(crate) time_provider: Arc<dyn TimeProvider>,
     pub(crate) metric_registry: Arc<metric::Registry>,
+    pub(crate) subscription_config: Arc<SubscriptionConfig>,
 }
 
 impl Db {
@@ -288,10 +340,13 @@ impl Db {
             rules,
             time_provider,
             metric_registry,
+            subscription_config,
         } = database_to_commit;
 
         let name = Arc::from(rules.name.as_str());
 
+        let declared_schema =
+            RwLock::new(rules.schema.as_ref().map(DeclaredSchema::new).map(Arc::new));
         let rules = RwLock::new(rules);
         let server_id = server_id;
         let iox_object_store = Arc::clone(&iox_object_store);
@@ -307,8 +362,31 @@ impl Db {
         );
         let catalog_access = Arc::new(catalog_access);
 
+        let limit_metrics = LimitMetrics::new(metric_registry.as_ref(), &name);
+
+        let query_timeouts = metric_registry
+            .register_metric::<U64Counter>(
+                "query_timeouts",
+                "Number of queries cancelled for exceeding their timeout",
+            )
+            .recorder(Attributes::from([("db_name", name.to_string().into())]));
+
+        let admission = exec.new_database_admission();
+        let admission_metrics = AdmissionMetrics::new(
+            metric_registry.as_ref(),
+            Attributes::from([("db_name", name.to_string().into())]),
+        );
+
+        let subscriptions = RwLock::new(Subscriptions::new(
+            &name,
+            &rules.read().subscriptions,
+            &subscription_config,
+            metric_registry.as_ref(),
+        ));
+
         Self {
             rules,
+            declared_schema,
             name,
             server_id,
             iox_object_store,
@@ -317,6 +395,10 @@ impl Db {
             catalog,
             jobs,
             metric_registry,
+            limit_metrics,
+            query_timeouts,
+            admission,
+            admission_metrics,
             catalog_access,
             worker_iterations_cleanup: AtomicUsize::new(0),
             worker_iterations_delete_predicate_preservation: AtomicUsize::new(0),
@@ -324,6 +406,9 @@ impl Db {
             time_provider,
             delete_predicates_mailbox: Default::default(),
             persisted_chunk_id_override: Default::default(),
+            continuous_query_state: Default::default(),
+            subscription_config,
+            subscriptions,
         }
     }
 
@@ -342,6 +427,38 @@ impl Db {
         Arc::clone(&*self.rules.read())
     }
 
+    /// Returns a strict [`DatabaseSchema`] declaring every table and column
+    /// of this database, as well as those already declared in the rules. Used
+    /// to lock a schema learned from writes.
+    ///
+    /// Fails if a column was written with a different type than it has since
+    /// been declared with.
+    pub fn locked_schema(&self) -> declared_schema::Result<DatabaseSchema> {
+        let schemas: Vec<_> = self
+            .catalog
+            .table_names()
+            .into_iter()
+            .filter_map(|table_name| {
+                let table = self.catalog.table(&table_name).ok()?;
+                let schema = Arc::clone(&*table.schema().read());
+                Some((table_name, schema))
+            })
+            .collect();
+
+        let mut locked = declared_schema::lock(
+            schemas
+                .iter()
+                .map(|(table_name, schema)| (table_name.clone(), schema.as_ref())),
+        );
+
+        // Keep declared tables and columns that have not been written yet
+        if let Some(declared) = &self.rules.read().schema {
+            declared_schema::merge_declared(&mut locked, declared)?;
+        }
+
+        Ok(locked)
+    }
+
     pub fn name(&self) -> Arc<str> {
         Arc::clone(&self.name)
     }
@@ -354,6 +471,25 @@ impl Db {
             let late_arrive_window_updated = rules.lifecycle_rules.late_arrive_window_seconds
                 != new_rules.lifecycle_rules.late_arrive_window_seconds;
 
+            if rules.schema != new_rules.schema {
+                *self.declared_schema.write() = new_rules
+                    .schema
+                    .as_ref()
+                    .map(DeclaredSchema::new)
+                    .map(Arc::new);
+            }
+
+            if rules.subscriptions != new_rules.subscriptions {
+                // Dropping the previous subscriptions lets them deliver the
+                // writes already queued
+                *self.subscriptions.write() = Subscriptions::new(
+                    &self.name,
+                    &new_rules.subscriptions,
+                    &self.subscription_config,
+                    self.metric_registry.as_ref(),
+                );
+            }
+
             *rules = new_rules;
             late_arrive_window_updated
         };
@@ -959,17 +1095,35 @@ impl Db {
             }
         };
 
+        // worker loop to run continuous queries over closed windows
+        let continuous_query_loop = async {
+            loop {
+                // Errors are logged by the queries, which retry the failed
+                // window once their backoff has passed
+                let _ = self.run_continuous_queries().await;
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        };
+
         // None of the futures need to perform drain logic on shutdown.
         // When the first one finishes, all of them are dropped
         tokio::select! {
             _ = object_store_cleanup_loop => error!("object store cleanup loop exited - db worker bailing out"),
             _ = delete_predicate_persistence_loop => error!("delete predicate persistence loop exited - db worker bailing out"),
+            _ = continuous_query_loop => error!("continuous query loop exited - db worker bailing out"),
             _ = shutdown.cancelled() => info!("db worker shutting down"),
         }
 
         info!("finished db background worker");
     }
 
+    /// Runs each continuous query over the windows that closed since it last
+    /// ran, writing the results to this database
+    pub async fn run_continuous_queries(self: &Arc<Self>) -> continuous_query::Result<()> {
+        continuous_query::run(self).await
+    }
+
     async fn cleanup_unreferenced_parquet_files(
         self: &Arc<Self>,
     ) -> std::result::Result<(), parquet_catalog::cleanup::Error> {
@@ -1043,9 +1197,20 @@ impl Db {
         }
     }
 
-    /// Writes the provided [`DmlWrite`] to this database
+    /// Writes the provided [`DmlWrite`] to this database, and publishes it
+    /// to the subscriptions of this database once stored
     pub(crate) fn store_write(&self, db_write: &DmlWrite) -> Result<(), DmlError> {
-        self.store_filtered_write(db_write, WriteFilterNone::default())
+        self.can_store(db_write.meta())?;
+
+        let db_write = match self.apply_ingest_rules(db_write)? {
+            Some(db_write) => db_write,
+            // Every row was dropped by the ingest rules
+            None => return Ok(()),
+        };
+
+        self.store_transformed_write(&db_write, WriteFilterNone::default())?;
+        self.subscriptions.read().publish(&db_write);
+        Ok(())
     }
 
     /// Writes the provided [`DmlWrite`] to this database with the provided [`WriteFilter`]
@@ -1056,6 +1221,38 @@ impl Db {
     ) -> Result<(), DmlError> {
         self.can_store(db_write.meta())?;
 
+        match self.apply_ingest_rules(db_write)? {
+            Some(db_write) => self.store_transformed_write(&db_write, filter),
+            // Every row was dropped by the ingest rules
+            None => Ok(()),
+        }
+    }
+
+    /// Applies the ingest rules of this database to `db_write`, returning
+    /// `None` if they drop every row
+    fn apply_ingest_rules<'a>(
+        &self,
+        db_write: &'a DmlWrite,
+    ) -> Result<Option<Cow<'a, DmlWrite>>, DmlError> {
+        let db_rules = self.rules();
+        let ingest_rules = &db_rules.ingest_rules;
+        if ingest_rules.is_empty() {
+            return Ok(Some(Cow::Borrowed(db_write)));
+        }
+
+        Ok(db_write
+            .clone()
+            .transform(ingest_rules)
+            .context(IngestRules)?
+            .map(Cow::Owned))
+    }
+
+    /// Writes a [`DmlWrite`] the ingest rules have already been applied to
+    fn store_transformed_write(
+        &self,
+        db_write: &DmlWrite,
+        filter: impl WriteFilter,
+    ) -> Result<(), DmlError> {
         // Get all needed database rule values, then release the lock
         let rules = self.rules.read();
         let partition_template = rules.partition_template.clone();
@@ -1063,6 +1260,9 @@ impl Db {
         let buffer_size_hard = rules.lifecycle_rules.buffer_size_hard;
         let late_arrival_window = rules.lifecycle_rules.late_arrive_window();
         let mub_row_threshold = rules.lifecycle_rules.mub_row_threshold;
+        let max_tables = rules.lifecycle_rules.max_tables;
+        let max_columns_per_table = rules.lifecycle_rules.max_columns_per_table;
+        let max_tag_cardinality = rules.lifecycle_rules.max_tag_cardinality;
         std::mem::drop(rules);
 
         // We may have gotten here through `store_entry`, in which case this is checking the
@@ -1078,11 +1278,56 @@ impl Db {
             }
         }
 
+        // Check every table before storing any of them, so that a rejected
+        // write is not partially applied
+        let declared_schema = self.declared_schema.read().as_ref().map(Arc::clone);
+        if let Some(declared_schema) = declared_schema {
+            for (table_name, batch) in db_write.tables() {
+                let write_schema = batch.schema(Selection::All).unwrap();
+                declared_schema
+                    .check(table_name, &write_schema)
+                    .context(DeclaredSchemaViolation)?;
+            }
+        }
+
+        if max_tables.is_some() || max_columns_per_table.is_some() {
+            self.check_table_limits(db_write, max_tables, max_columns_per_table)
+                .map_err(|e| {
+                    self.limit_metrics.record(&e);
+                    DmlError::LimitExceeded { source: e }
+                })?;
+        }
+
+        // Partition every table up front, so that the tag cardinality limit
+        // is checked for every partition before any of them is stored
+        let partitioned: Vec<_> = db_write
+            .tables()
+            .map(|(table_name, batch)| {
+                let writes: Vec<_> =
+                    PartitionWrite::partition(table_name, batch, &partition_template)
+                        .into_iter()
+                        .filter_map(|(partition_key, write)| {
+                            let write = filter.filter_write(table_name, &partition_key, write)?;
+                            Some((partition_key, write))
+                        })
+                        .collect();
+                (table_name, batch, writes)
+            })
+            .collect();
+
+        if let Some(max_tag_cardinality) = max_tag_cardinality {
+            self.check_tag_cardinalities(&partitioned, max_tag_cardinality)
+                .map_err(|e| {
+                    self.limit_metrics.record(&e);
+                    DmlError::LimitExceeded { source: e }
+                })?;
+        }
+
         // Protect against DoS by limiting the number of errors we might collect
         const MAX_ERRORS: usize = 10;
         let mut schema_errors = vec![];
 
-        for (table_name, batch) in db_write.tables() {
+        for (table_name, batch, writes) in partitioned {
             let write_schema = batch.schema(Selection::All).unwrap();
             let table_metrics = {
                 let table = self.catalog.get_or_create_table(table_name);
@@ -1107,13 +1352,7 @@ impl Db {
                 Arc::clone(table.metrics())
             };
 
-            let partitioned = PartitionWrite::partition(table_name, batch, &partition_template);
-            for (partition_key, write) in partitioned {
-                let write = match filter.filter_write(table_name, &partition_key, write) {
-                    Some(write) => write,
-                    None => continue,
-                };
-
+            for (partition_key, write) in writes {
                 let partition = self
                     .catalog
                     .get_or_create_partition(table_name, &partition_key);
@@ -1154,6 +1393,10 @@ impl Db {
                     }
                 };
 
+                if max_tag_cardinality.is_some() {
+                    partition.tag_values_mut().insert(write.tag_values());
+                }
+
                 partition.update_last_write_at();
 
                 let sequence = db_write.meta().sequence();
@@ -1189,6 +1432,88 @@ impl Db {
 
         Ok(())
     }
+
+    /// Checks that storing the partitioned writes of a [`DmlWrite`] gives no
+    /// tag more than `max_cardinality` distinct values in any partition,
+    /// counting the values already written to the partition
+    fn check_tag_cardinalities(
+        &self,
+        partitioned: &[(&str, &MutableBatch, Vec<(String, PartitionWrite<'_>)>)],
+        max_cardinality: NonZeroUsize,
+    ) -> Result<(), limits::Error> {
+        for (table_name, _, writes) in partitioned {
+            for (partition_key, write) in writes {
+                let tag_values = write.tag_values();
+                let cardinalities = match self.catalog.partition(table_name, partition_key) {
+                    Ok(partition) => partition.read().tag_values().cardinalities(&tag_values),
+                    Err(_) => TagValues::default().cardinalities(&tag_values),
+                };
+
+                limits::check_tag_cardinalities(
+                    table_name,
+                    partition_key,
+                    cardinalities,
+                    max_cardinality,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that storing `db_write` creates no more than `max_tables`
+    /// tables in total and no more than `max_columns` columns in any table.
+    ///
+    /// Writes to tables that are already over the column limit, e.g. because
+    /// the limit was lowered, are allowed as long as they add no columns.
+    fn check_table_limits(
+        &self,
+        db_write: &DmlWrite,
+        max_tables: Option<NonZeroUsize>,
+        max_columns: Option<NonZeroUsize>,
+    ) -> Result<(), limits::Error> {
+        let mut num_tables = self.catalog.num_tables();
+
+        for (table_name, batch) in db_write.tables() {
+            let (existing_columns, new_columns) = match self.catalog.table(table_name) {
+                Ok(table) => {
+                    let schema = table.schema().read();
+                    let new_columns = batch
+                        .columns()
+                        .filter(|(column, _)| schema.find_index_of(column).is_none())
+                        .count();
+                    (schema.len(), new_columns)
+                }
+                Err(_) => {
+                    num_tables += 1;
+                    if let Some(max_tables) = max_tables {
+                        ensure!(
+                            num_tables <= max_tables.get(),
+                            limits::TooManyTables {
+                                table_name,
+                                max_tables: max_tables.get(),
+                            }
+                        );
+                    }
+                    (0, batch.columns().count())
+                }
+            };
+
+            if let Some(max_columns) = max_columns {
+                let columns = existing_columns + new_columns;
+                ensure!(
+                    new_columns == 0 || columns <= max_columns.get(),
+                    limits::TooManyColumns {
+                        table_name,
+                        columns,
+                        max_columns: max_columns.get(),
+                    }
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -1215,8 +1540,14 @@ impl QueryDatabase for Db {
         self.catalog_access.table_schema(table_name)
     }
 
-    fn record_query(&self, query_type: impl Into<String>, query_text: impl Into<String>) {
-        self.catalog_access.record_query(query_type, query_text)
+    fn record_query(
+        &self,
+        ctx: &IOxExecutionContext,
+        query_type: impl Into<String>,
+        query_text: impl Into<String>,
+    ) {
+        self.catalog_access
+            .record_query(ctx, query_type, query_text)
     }
 }
 
@@ -1226,6 +1557,12 @@ impl ExecutionContextProvider for Db {
             .new_execution_config(ExecutorType::Query)
             .with_default_catalog(Arc::<Self>::clone(self))
             .with_span_context(span_ctx)
+            .with_timeout_metric(self.query_timeouts.clone())
+            .with_database_admission(self.admission.clone())
+            .with_admission_metrics(self.admission_metrics.clone())
+            .with_system_schema(SYSTEM_SCHEMA)
+            // compute simple aggregates in read buffer chunks where possible
+            .with_optimizer_rule(Arc::new(AggregatePushdown::<DbChunk>::new()))
             .build()
     }
 }
@@ -1444,17 +1781,23 @@ mod tests {
     };
     use ::test_helpers::{assert_contains, assert_error};
     use arrow::record_batch::RecordBatch;
-    use arrow_util::{assert_batches_eq, assert_batches_sorted_eq};
+    use arrow_util::{assert_batches_eq, assert_batches_sorted_eq, display::pretty_format_batches};
     use bytes::Bytes;
     use data_types::{
         chunk_metadata::{ChunkAddr, ChunkStorage},
-        database_rules::{LifecycleRules, PartitionTemplate, TemplatePart},
+        database_rules::{
+            ContinuousQuery, FieldType, LifecycleRules, PartitionTemplate, SchemaMode, TableSchema,
+            TemplatePart,
+        },
+        ingest::{IngestAction, IngestRule},
         partition_metadata::{ColumnSummary, InfluxDbType, StatValues, Statistics, TableSummary},
+        router::Matcher,
+        subscription::{Subscription, SubscriptionTarget},
         write_summary::TimestampSummary,
     };
     use futures::{stream, StreamExt, TryStreamExt};
     use iox_object_store::ParquetFilePath;
-    use metric::{Attributes, CumulativeGauge, Metric, Observation};
+    use metric::{Attributes, CumulativeGauge, Metric, Observation, U64Counter};
     use mutable_batch_lp::lines_to_batches;
     use object_store::ObjectStore;
     use parquet_catalog::test_helpers::load_ok;
@@ -1860,6 +2203,43 @@ mod tests {
         assert_batches_sorted_eq!(expected, &batches);
     }
 
+    #[tokio::test]
+    async fn aggregate_in_read_buffer() {
+        // Test that simple aggregates are computed by read buffer chunks
+        let test_db = make_db().await;
+        let db = Arc::new(test_db.db);
+
+        write_lp(db.as_ref(), "cpu,region=west bar=1 10");
+        write_lp(db.as_ref(), "cpu,region=west bar=2 20");
+        write_lp(db.as_ref(), "cpu,region=east bar=4 30");
+
+        let query = "select region, sum(bar), count(bar), max(time) from cpu group by region";
+        let expected = vec![
+            "+--------+--------------+----------------+--------------------------------+",
+            "| region | SUM(cpu.bar) | COUNT(cpu.bar) | MAX(cpu.time)                  |",
+            "+--------+--------------+----------------+--------------------------------+",
+            "| east   | 4            | 1              | 1970-01-01T00:00:00.000000030Z |",
+            "| west   | 3            | 2              | 1970-01-01T00:00:00.000000020Z |",
+            "+--------+--------------+----------------+--------------------------------+",
+        ];
+
+        let batches = run_query(Arc::clone(&db), query).await;
+        assert_batches_sorted_eq!(&expected, &batches);
+
+        let explain = format!("explain {}", query);
+        let plan = pretty_format_batches(&run_query(Arc::clone(&db), &explain).await).unwrap();
+        assert!(!plan.contains("IOxReadAggregateNode"), "{}", plan);
+
+        db.compact_partition("cpu", "1970-01-01T00").await.unwrap();
+
+        // the same results, now aggregated by the read buffer chunk
+        let batches = run_query(Arc::clone(&db), query).await;
+        assert_batches_sorted_eq!(&expected, &batches);
+
+        let plan = pretty_format_batches(&run_query(Arc::clone(&db), &explain).await).unwrap();
+        assert!(plan.contains("IOxReadAggregateNode"), "{}", plan);
+    }
+
     #[tokio::test]
     async fn read_from_read_buffer() {
         // Test that data can be loaded into the ReadBuffer
@@ -3502,6 +3882,371 @@ mod tests {
         assert_error!(r, DmlError::SchemaErrors { .. });
     }
 
+    #[tokio::test]
+    async fn declared_schema_enforcement() {
+        let db = make_db().await.db;
+
+        let mut cpu = TableSchema::default();
+        cpu.tags.insert("host".to_string());
+        cpu.fields.insert("usage".to_string(), FieldType::Float);
+
+        let mut rules = db.rules().as_ref().clone();
+        rules.schema = Some(DatabaseSchema {
+            mode: SchemaMode::Learn,
+            tables: std::iter::once(("cpu".to_string(), cpu)).collect(),
+        });
+        db.update_rules(Arc::new(rules.clone()));
+
+        // new tables and columns are learned, but declared columns are enforced
+        try_write_lp(&db, "cpu,host=a usage=1.0,idle=2.0 10").unwrap();
+        try_write_lp(&db, "mem,host=a free=1i 10").unwrap();
+        let r = try_write_lp(&db, "cpu,host=a usage=1i 10");
+        assert_error!(r, DmlError::DeclaredSchemaViolation { .. });
+
+        let locked = db.locked_schema().unwrap();
+        assert_eq!(locked.mode, SchemaMode::Strict);
+        assert_eq!(locked.tables.len(), 2);
+        assert_eq!(locked.tables["cpu"].fields["idle"], FieldType::Float);
+        assert_eq!(locked.tables["mem"].fields["free"], FieldType::Integer);
+
+        rules.schema = Some(locked);
+        db.update_rules(Arc::new(rules));
+
+        try_write_lp(&db, "cpu,host=b usage=1.0,idle=3.0 20").unwrap();
+        try_write_lp(&db, "mem free=2i 20").unwrap();
+
+        let r = try_write_lp(&db, "cpu,host=b usgae=1.0 20");
+        assert_error!(r, DmlError::DeclaredSchemaViolation { .. });
+
+        // rejected writes are not partially applied
+        let r = try_write_lp(&db, "mem free=3i 30\ndisk,host=b used=1.0 30");
+        assert_error!(r, DmlError::DeclaredSchemaViolation { .. });
+        let mut table_names = db.table_names();
+        table_names.sort();
+        assert_eq!(table_names, vec!["cpu".to_string(), "mem".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn declared_schema_lock_conflict() {
+        let db = make_db().await.db;
+
+        let mut rules = db.rules().as_ref().clone();
+        rules.schema = Some(DatabaseSchema {
+            mode: SchemaMode::Learn,
+            tables: Default::default(),
+        });
+        db.update_rules(Arc::new(rules.clone()));
+
+        try_write_lp(&db, "cpu,host=a usage=1.0,region=\"west\" 10").unwrap();
+
+        // region was written as a field, and is later declared as a tag
+        let mut cpu = TableSchema::default();
+        cpu.tags.insert("host".to_string());
+        cpu.tags.insert("region".to_string());
+        rules.schema = Some(DatabaseSchema {
+            mode: SchemaMode::Learn,
+            tables: std::iter::once(("cpu".to_string(), cpu)).collect(),
+        });
+        db.update_rules(Arc::new(rules));
+
+        let err = db.locked_schema().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Column 'region' of table 'cpu' is declared as a tag but was written as a string field"
+        );
+    }
+
+    #[tokio::test]
+    async fn write_limits() {
+        let test_db = TestDb::builder()
+            .lifecycle_rules(LifecycleRules {
+                max_tables: NonZeroUsize::new(2),
+                max_columns_per_table: NonZeroUsize::new(4),
+                max_tag_cardinality: NonZeroUsize::new(2),
+                ..Default::default()
+            })
+            .build()
+            .await;
+        let db = test_db.db;
+
+        try_write_lp(&db, "cpu,host=a usage=1.0 10").unwrap();
+        try_write_lp(&db, "mem,host=a free=1i 10").unwrap();
+
+        // a third table is rejected without applying the rest of the write
+        let r = try_write_lp(
+            &db,
+            "cpu,host=a usage=2.0 20
+disk,host=a used=1.0 20",
+        );
+        assert_error!(
+            r,
+            DmlError::LimitExceeded {
+                source: limits::Error::TooManyTables { .. }
+            }
+        );
+        let mut table_names = db.table_names();
+        table_names.sort();
+        assert_eq!(table_names, vec!["cpu".to_string(), "mem".to_string()]);
+
+        // host, usage, idle and time fill the column limit
+        try_write_lp(&db, "cpu,host=a usage=1.0,idle=2.0 10").unwrap();
+        let r = try_write_lp(&db, "cpu,host=a system=1.0 10");
+        assert_error!(
+            r,
+            DmlError::LimitExceeded {
+                source: limits::Error::TooManyColumns { columns: 5, .. }
+            }
+        );
+
+        // existing tag values do not count towards the limit
+        try_write_lp(
+            &db,
+            "cpu,host=b usage=1.0 10
+cpu,host=a usage=1.0 20",
+        )
+        .unwrap();
+        let r = try_write_lp(&db, "cpu,host=c usage=1.0 30");
+        assert_error!(
+            r,
+            DmlError::LimitExceeded {
+                source: limits::Error::TagCardinality { cardinality: 3, .. }
+            }
+        );
+
+        // values in closed chunks of the partition are counted
+        db.rollover_partition("cpu", "1970-01-01T00").await.unwrap();
+        let r = try_write_lp(
+            &db,
+            "mem,host=a free=2i 30
+cpu,host=c usage=1.0 30",
+        );
+        assert_error!(
+            r,
+            DmlError::LimitExceeded {
+                source: limits::Error::TagCardinality { cardinality: 3, .. }
+            }
+        );
+
+        // a rejected write is not partially applied
+        let batches = run_query(Arc::clone(&db), "select free from mem").await;
+        assert_batches_sorted_eq!(
+            &["+------+", "| free |", "+------+", "| 1    |", "+------+",],
+            &batches
+        );
+
+        // the limit applies per partition
+        try_write_lp(&db, "cpu,host=c usage=1.0 3600000000000").unwrap();
+
+        let rejections = |limit: &'static str| {
+            test_db
+                .metric_registry
+                .get_instrument::<Metric<U64Counter>>("write_limit_rejections")
+                .unwrap()
+                .get_observer(&Attributes::from(&[
+                    ("db_name", "placeholder"),
+                    ("limit", limit),
+                ]))
+                .unwrap()
+                .fetch()
+        };
+        assert_eq!(rejections("tables"), 1);
+        assert_eq!(rejections("columns"), 1);
+        assert_eq!(rejections("tag_cardinality"), 2);
+    }
+
+    #[tokio::test]
+    async fn ingest_rules() {
+        let db = make_db().await.db;
+
+        let mut rules = db.rules().as_ref().clone();
+        rules.ingest_rules = vec![
+            IngestRule {
+                table_name_regex: None,
+                action: IngestAction::DropRows {
+                    column: "host".to_string(),
+                    regex: regex::Regex::new("^test-").unwrap(),
+                },
+            },
+            IngestRule {
+                table_name_regex: Some(regex::Regex::new("^cpu$").unwrap()),
+                action: IngestAction::AddTag {
+                    tag: "region".to_string(),
+                    value: "west".to_string(),
+                },
+            },
+        ];
+        db.update_rules(Arc::new(rules));
+
+        write_lp(&db, "cpu,host=a bar=1 10\ncpu,host=test-1 bar=2 20");
+        // writes dropped entirely by the rules are accepted
+        try_write_lp(&db, "mem,host=test-1 free=2 20").unwrap();
+
+        assert_eq!(db.table_names(), vec!["cpu".to_string()]);
+
+        let batches = run_query(db, "select * from cpu").await;
+        let expected = vec![
+            "+-----+------+--------+--------------------------------+",
+            "| bar | host | region | time                           |",
+            "+-----+------+--------+--------------------------------+",
+            "| 1   | a    | west   | 1970-01-01T00:00:00.000000010Z |",
+            "+-----+------+--------+--------------------------------+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+    }
+
+    #[tokio::test]
+    async fn continuous_queries() {
+        let (db, time) = make_db_time().await;
+        time.set(Time::from_timestamp(1000, 0));
+
+        let mut rules = db.rules().as_ref().clone();
+        rules.continuous_queries = vec![ContinuousQuery {
+            name: "cpu_10s".to_string(),
+            sql: "SELECT host, avg(usage) AS usage, count(*) AS n FROM cpu \
+                  WHERE time >= $start AND time < $end GROUP BY host"
+                .to_string(),
+            target_table: "cpu_10s".to_string(),
+            interval: Duration::from_secs(10),
+            delay: None,
+        }];
+        db.update_rules(Arc::new(rules));
+
+        // The window open when the query is first seen is the first one run
+        db.run_continuous_queries().await.unwrap();
+
+        write_lp(
+            &db,
+            "cpu,host=a usage=1 995000000000\n\
+             cpu,host=a usage=3 998000000000\n\
+             cpu,host=b usage=5 1002000000000",
+        );
+
+        // Windows close one second, the late arrival window, after their end
+        time.set(Time::from_timestamp(1010, 0));
+        db.run_continuous_queries().await.unwrap();
+        assert_eq!(db.table_names().len(), 2);
+
+        time.set(Time::from_timestamp(1011, 0));
+        db.run_continuous_queries().await.unwrap();
+
+        // Windows that completed are not run again after a restart
+        db.continuous_query_state.lock().await.progress = None;
+        db.run_continuous_queries().await.unwrap();
+
+        let batches = run_query(Arc::clone(&db), "select * from cpu_10s").await;
+        let expected = vec![
+            "+------+---+----------------------+-------+",
+            "| host | n | time                 | usage |",
+            "+------+---+----------------------+-------+",
+            "| a    | 2 | 1970-01-01T00:16:30Z | 2     |",
+            "| b    | 1 | 1970-01-01T00:16:40Z | 5     |",
+            "+------+---+----------------------+-------+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+
+        let jobs = db.jobs.tracked();
+        assert!(jobs
+            .iter()
+            .all(|job| matches!(job.metadata(), Job::RunContinuousQuery { .. })));
+        assert_eq!(jobs.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn continuous_query_backoff() {
+        let (db, time) = make_db_time().await;
+        time.set(Time::from_timestamp(1000, 0));
+
+        let mut rules = db.rules().as_ref().clone();
+        rules.continuous_queries = vec![ContinuousQuery {
+            name: "missing_10s".to_string(),
+            sql: "SELECT count(*) AS n FROM missing WHERE time >= $start AND time < $end"
+                .to_string(),
+            target_table: "missing_10s".to_string(),
+            interval: Duration::from_secs(10),
+            delay: None,
+        }];
+        db.update_rules(Arc::new(rules));
+        db.run_continuous_queries().await.unwrap();
+
+        time.set(Time::from_timestamp(1011, 0));
+        db.run_continuous_queries().await.unwrap_err();
+
+        // The failed query is not retried, nor a job registered, until its
+        // backoff has passed
+        db.run_continuous_queries().await.unwrap();
+        assert_eq!(db.jobs.tracked().len(), 1);
+
+        time.inc(Duration::from_secs(1));
+        db.run_continuous_queries().await.unwrap_err();
+        assert_eq!(db.jobs.tracked().len(), 2);
+
+        // The backoff doubles with each failure
+        time.inc(Duration::from_secs(1));
+        db.run_continuous_queries().await.unwrap();
+        time.inc(Duration::from_secs(1));
+        db.run_continuous_queries().await.unwrap_err();
+        assert_eq!(db.jobs.tracked().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn subscriptions() {
+        let dir = ::test_helpers::tmp_dir().unwrap();
+        let path = dir.path().join("cpu.lp");
+        let db = TestDb::builder()
+            .subscription_config(SubscriptionConfig {
+                file_dir: Some(dir.path().to_path_buf()),
+                ..Default::default()
+            })
+            .build()
+            .await
+            .db;
+
+        let mut rules = db.rules().as_ref().clone();
+        rules.ingest_rules = vec![IngestRule {
+            table_name_regex: None,
+            action: IngestAction::AddTag {
+                tag: "region".to_string(),
+                value: "west".to_string(),
+            },
+        }];
+        rules.subscriptions = vec![Subscription {
+            name: "cpu".to_string(),
+            matcher: Matcher {
+                table_name_regex: Some(regex::Regex::new("^cpu$").unwrap()),
+            },
+            target: SubscriptionTarget::File {
+                path: "cpu.lp".to_string(),
+            },
+            queue_size: NonZeroUsize::new(10).unwrap(),
+        }];
+        db.update_rules(Arc::new(rules));
+
+        write_lp(&db, "cpu,host=a bar=1 10\nmem,host=a free=2 10");
+        // writes rejected by the database are not delivered
+        try_write_lp(&db, "cpu bar=\"x\" 20").unwrap_err();
+
+        let delivered = db
+            .metric_registry
+            .get_instrument::<Metric<U64Counter>>("subscription_writes")
+            .unwrap()
+            .get_observer(&Attributes::from(&[
+                ("db_name", "placeholder"),
+                ("subscription", "cpu"),
+                ("status", "delivered"),
+            ]))
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while delivered.fetch() < 1 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("write not delivered");
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents, "cpu,host=a,region=west bar=1 10\n");
+    }
+
     #[tokio::test]
     async fn drop_unpersisted_chunk_on_persisted_db() {
         // We don't support dropping unpersisted chunks from a persisted DB because we would forget
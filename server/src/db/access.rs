@@ -21,6 +21,7 @@ use metric::{Attributes, Metric, U64Counter};
 use observability_deps::tracing::debug;
 use predicate::predicate::{Predicate, PredicateBuilder};
 use query::{
+    exec::IOxExecutionContext,
     provider::{ChunkPruner, ProviderBuilder},
     QueryChunk, QueryChunkMeta, DEFAULT_SCHEMA,
 };
@@ -241,8 +242,18 @@ impl QueryDatabase for QueryCatalogAccess {
             .map(|table| Arc::clone(&table.schema().read()))
     }
 
-    fn record_query(&self, query_type: impl Into<String>, query_text: impl Into<String>) {
-        self.query_log.push(query_type, query_text)
+    fn record_query(
+        &self,
+        ctx: &IOxExecutionContext,
+        query_type: impl Into<String>,
+        query_text: impl Into<String>,
+    ) {
+        self.query_log.push(
+            query_type,
+            query_text,
+            ctx.deadline().map(Arc::clone),
+            Some(Arc::clone(ctx.admission_stats())),
+        )
     }
 }
 
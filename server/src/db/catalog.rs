@@ -327,6 +327,11 @@ impl Catalog {
         self.tables.read().keys().map(ToString::to_string).collect()
     }
 
+    /// Return the number of tables in the catalog
+    pub fn num_tables(&self) -> usize {
+        self.tables.read().len()
+    }
+
     pub fn metrics(&self) -> &CatalogMetrics {
         &self.metrics
     }
@@ -2,6 +2,7 @@
 
 use super::chunk::{CatalogChunk, Error as ChunkError};
 use crate::db::catalog::metrics::PartitionMetrics;
+use crate::db::limits::TagValues;
 use data_types::{
     chunk_metadata::{ChunkAddr, ChunkId, ChunkLifecycleAction, ChunkOrder, ChunkSummary},
     delete_predicate::DeletePredicate,
@@ -143,6 +144,10 @@ pub struct Partition {
     /// Tracks next chunk order in this partition.
     next_chunk_order: ChunkOrder,
 
+    /// The distinct tag values written to this partition, for enforcing
+    /// the tag cardinality limit
+    tag_values: TagValues,
+
     /// The time provider
     time_provider: Arc<dyn TimeProvider>,
 }
@@ -166,6 +171,7 @@ impl Partition {
             metrics: Arc::new(metrics),
             persistence_windows: None,
             next_chunk_order: ChunkOrder::MIN,
+            tag_values: Default::default(),
             time_provider,
         }
     }
@@ -447,6 +453,16 @@ impl Partition {
         self.persistence_windows = Some(windows);
     }
 
+    /// Return the distinct tag values written to this partition
+    pub fn tag_values(&self) -> &TagValues {
+        &self.tag_values
+    }
+
+    /// Return mutable reference to the distinct tag values written to this partition
+    pub fn tag_values_mut(&mut self) -> &mut TagValues {
+        &mut self.tag_values
+    }
+
     /// Construct sequencer numbers out of contained persistence window, if any.
     pub fn sequencer_numbers(&self) -> Option<BTreeMap<u32, OptionalMinMaxSequence>> {
         self.persistence_windows
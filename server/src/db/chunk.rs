@@ -16,17 +16,28 @@ use observability_deps::tracing::debug;
 use parquet_file::chunk::ParquetChunk;
 use partition_metadata::TableSummary;
 use predicate::predicate::{Predicate, PredicateMatch};
-use query::{exec::stringset::StringSet, QueryChunk, QueryChunkMeta};
-use read_buffer::RBChunk;
-use schema::InfluxColumnType;
+use query::{
+    exec::stringset::StringSet,
+    provider::{ChunkAggregates, PartialAggregate},
+    QueryChunk, QueryChunkMeta,
+};
+use read_buffer::{AggregateType, RBChunk};
 use schema::{selection::Selection, sort::SortKey, Schema};
+use schema::{InfluxColumnType, TIME_COLUMN_NAME, TIME_DATA_TYPE};
 use snafu::{OptionExt, ResultExt, Snafu};
 use std::{
     collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
     sync::Arc,
 };
 use time::Time;
 
+use arrow::{
+    array::{ArrayRef, TimestampNanosecondArray},
+    datatypes::{Field as ArrowField, Schema as ArrowSchema},
+    record_batch::RecordBatch,
+};
+
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -69,6 +80,15 @@ pub enum Error {
 
     #[snafu(display("arrow conversion error: {}", source))]
     ArrowConversion { source: arrow::error::ArrowError },
+
+    #[snafu(display("Internal error converting read buffer schema: {}", source))]
+    InternalConvertingSchema { source: schema::builder::Error },
+
+    #[snafu(display("Chunk {} can not compute aggregates in its {} state", chunk_id, state))]
+    InternalAggregateNotSupported {
+        chunk_id: ChunkId,
+        state: &'static str,
+    },
 }
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -426,6 +446,139 @@ impl QueryChunk for DbChunk {
         }
     }
 
+    fn supports_read_aggregate(&self, predicate: &Predicate, aggregates: &ChunkAggregates) -> bool {
+        // Only the read buffer computes aggregates itself, which requires
+        // the predicate to be evaluated exactly rather than only pruning
+        // rows on a best effort basis
+        let chunk = match &self.state {
+            State::ReadBuffer { chunk, .. } => chunk,
+            State::MutableBuffer { .. } | State::ParquetFile { .. } => return false,
+        };
+
+        let rb_predicate = match to_read_buffer_predicate(predicate) {
+            Ok(rb_predicate) => rb_predicate,
+            Err(e) => {
+                debug!(?predicate, %e, "read buffer predicate not supported for read_aggregate, falling back");
+                return false;
+            }
+        };
+        if let Err(e) = chunk.validate_predicate(rb_predicate) {
+            debug!(?predicate, %e, "invalid read buffer predicate for read_aggregate, falling back");
+            return false;
+        }
+
+        // Without any of the group columns the rows of this chunk would
+        // not be grouped at all
+        let schema = self.schema();
+        let has_column = |column: &String| schema.find_index_of(column).is_some();
+        (aggregates.window.is_some() || aggregates.group_columns.iter().any(has_column))
+            && aggregates
+                .aggregates
+                .iter()
+                .all(|(column, _)| has_column(column))
+    }
+
+    fn read_aggregate(
+        &self,
+        predicate: &Predicate,
+        aggregates: &ChunkAggregates,
+    ) -> Result<SendableRecordBatchStream, Self::Error> {
+        debug!(table=?self.table_name(), chunk_id=%self.addr().chunk_id, ?predicate, ?aggregates, "read_aggregate called");
+
+        let chunk = match &self.state {
+            State::ReadBuffer { chunk, .. } => chunk,
+            State::MutableBuffer { .. } => {
+                return InternalAggregateNotSupported {
+                    chunk_id: self.id(),
+                    state: "mutable buffer",
+                }
+                .fail()
+            }
+            State::ParquetFile { .. } => {
+                return InternalAggregateNotSupported {
+                    chunk_id: self.id(),
+                    state: "parquet file",
+                }
+                .fail()
+            }
+        };
+        self.access_recorder.record_access();
+
+        let rb_predicate = chunk
+            .validate_predicate(to_read_buffer_predicate(predicate).context(PredicateConversion)?)
+            .context(ReadBufferChunkError {
+                chunk_id: self.id(),
+            })?;
+
+        // Group columns missing in this chunk are NULL in all its rows,
+        // which the caller fills in
+        let schema = self.schema();
+        let group_columns = aggregates
+            .group_columns
+            .iter()
+            .filter(|column| schema.find_index_of(column).is_some())
+            .map(|column| column.as_str())
+            .collect::<Vec<_>>();
+        let group_columns = Selection::Some(&group_columns);
+
+        let rb_aggregates = aggregates
+            .aggregates
+            .iter()
+            .map(|(column, agg)| (column.as_str(), to_read_buffer_aggregate(*agg)))
+            .collect::<Vec<_>>();
+
+        let (schema, batches) = match aggregates.window {
+            None => {
+                let results = chunk
+                    .read_aggregate(rb_predicate, &group_columns, &rb_aggregates)
+                    .context(ReadBufferChunkError {
+                        chunk_id: self.id(),
+                    })?;
+                let schema = Schema::try_from(results.schema())
+                    .context(InternalConvertingSchema)?
+                    .as_arrow();
+
+                (schema, results.collect())
+            }
+            Some(window) => {
+                let results = chunk
+                    .aggregate_window(
+                        rb_predicate,
+                        &group_columns,
+                        &rb_aggregates,
+                        window.every,
+                        window.offset,
+                    )
+                    .context(ReadBufferChunkError {
+                        chunk_id: self.id(),
+                    })?;
+
+                // The windows are identified by their stop time
+                let mut fields = Schema::try_from(results.schema())
+                    .context(InternalConvertingSchema)?
+                    .as_arrow()
+                    .fields()
+                    .clone();
+                fields.push(ArrowField::new(TIME_COLUMN_NAME, TIME_DATA_TYPE(), false));
+                let schema = Arc::new(ArrowSchema::new(fields));
+
+                let batches = results
+                    .map(|(stop, batch)| {
+                        let time = TimestampNanosecondArray::from(vec![stop; batch.num_rows()]);
+                        let mut columns = batch.columns().to_vec();
+                        columns.push(Arc::new(time) as ArrayRef);
+                        RecordBatch::try_new(Arc::clone(&schema), columns)
+                    })
+                    .collect::<arrow::error::Result<Vec<_>>>()
+                    .context(ArrowConversion)?;
+
+                (schema, batches)
+            }
+        };
+
+        Ok(Box::pin(MemoryStream::new_with_schema(batches, schema)))
+    }
+
     fn column_names(
         &self,
         predicate: &Predicate,
@@ -572,6 +725,16 @@ impl QueryChunkMeta for DbChunk {
     }
 }
 
+/// Returns the read buffer aggregate computing `agg`
+fn to_read_buffer_aggregate(agg: PartialAggregate) -> AggregateType {
+    match agg {
+        PartialAggregate::Count => AggregateType::Count,
+        PartialAggregate::Sum => AggregateType::Sum,
+        PartialAggregate::Min => AggregateType::Min,
+        PartialAggregate::Max => AggregateType::Max,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -581,6 +744,9 @@ mod tests {
     };
     use crate::utils::make_db_time;
     use data_types::chunk_metadata::ChunkStorage;
+    use datafusion::logical_plan::{col, lit};
+    use predicate::predicate::PredicateBuilder;
+    use query::provider::AggregateWindow;
     use std::time::Duration;
 
     async fn test_chunk_access(chunk: &CatalogChunk, time: Arc<time::MockProvider>) {
@@ -671,6 +837,82 @@ mod tests {
         test_chunk_access(&chunk, time).await
     }
 
+    #[tokio::test]
+    async fn rub_read_aggregate() {
+        let (db, _time) = make_db_time().await;
+
+        write_lp(
+            &db,
+            "cpu,region=west usage=1 10\ncpu,region=west usage=2 20\ncpu,region=east usage=4 30",
+        );
+
+        let chunks = db.catalog.chunks();
+        let chunk = chunks.into_iter().next().unwrap();
+        let snapshot = DbChunk::snapshot(&chunk.read());
+
+        let mut aggregates = ChunkAggregates {
+            group_columns: vec!["region".into()],
+            window: None,
+            aggregates: vec![
+                ("usage".into(), PartialAggregate::Sum),
+                ("time".into(), PartialAggregate::Max),
+            ],
+        };
+
+        // only the read buffer computes aggregates
+        let predicate = Predicate::default();
+        assert!(!snapshot.supports_read_aggregate(&predicate, &aggregates));
+
+        db.compact_partition("cpu", "1970-01-01T00").await.unwrap();
+        let chunks = db.catalog.chunks();
+        let chunk = chunks.into_iter().next().unwrap();
+        let snapshot = DbChunk::snapshot(&chunk.read());
+        assert!(snapshot.supports_read_aggregate(&predicate, &aggregates));
+
+        let stream = snapshot.read_aggregate(&predicate, &aggregates).unwrap();
+        let batches = datafusion::physical_plan::common::collect(stream)
+            .await
+            .unwrap();
+        arrow_util::assert_batches_sorted_eq!(
+            vec![
+                "+--------+-----------+----------+",
+                "| region | usage_sum | time_max |",
+                "+--------+-----------+----------+",
+                "| east   | 4         | 30       |",
+                "| west   | 3         | 20       |",
+                "+--------+-----------+----------+",
+            ],
+            &batches
+        );
+
+        aggregates.window = Some(AggregateWindow {
+            every: 20,
+            offset: 0,
+        });
+        let stream = snapshot.read_aggregate(&predicate, &aggregates).unwrap();
+        let batches = datafusion::physical_plan::common::collect(stream)
+            .await
+            .unwrap();
+        arrow_util::assert_batches_sorted_eq!(
+            vec![
+                "+--------+-----------+----------+--------------------------------+",
+                "| region | usage_sum | time_max | time                           |",
+                "+--------+-----------+----------+--------------------------------+",
+                "| east   | 4         | 30       | 1970-01-01T00:00:00.000000040Z |",
+                "| west   | 1         | 10       | 1970-01-01T00:00:00.000000020Z |",
+                "| west   | 2         | 20       | 1970-01-01T00:00:00.000000040Z |",
+                "+--------+-----------+----------+--------------------------------+",
+            ],
+            &batches
+        );
+
+        // the predicate must be evaluated by the read buffer
+        let predicate = PredicateBuilder::default()
+            .add_expr(col("region").eq(lit("west")).or(col("usage").gt(lit(1.0))))
+            .build();
+        assert!(!snapshot.supports_read_aggregate(&predicate, &aggregates));
+    }
+
     #[tokio::test]
     async fn parquet_records_access() {
         let (db, time) = make_db_time().await;
@@ -0,0 +1,654 @@
+//! Running the [`ContinuousQuery`]s of a database over windows of time once
+//! they close, see [`DatabaseRules`](data_types::database_rules::DatabaseRules)
+use std::{collections::BTreeMap, convert::TryFrom, sync::Arc, time::Duration};
+
+use arrow::{
+    array::{
+        Array, BooleanArray, Float64Array, Int64Array, StringArray, TimestampNanosecondArray,
+        UInt64Array,
+    },
+    compute::cast,
+    datatypes::{DataType, TimeUnit},
+    record_batch::RecordBatch,
+};
+use data_types::{database_rules::ContinuousQuery, job::Job};
+use datafusion::error::DataFusionError;
+use dml::{DmlMeta, DmlWrite};
+use generated_types::{
+    database_rules::{decode_continuous_query_progress, encode_continuous_query_progress},
+    influxdata::iox::management::v1 as management,
+};
+use iox_object_store::IoxObjectStore;
+use mutable_batch::{writer::Writer, MutableBatch};
+use observability_deps::tracing::{debug, error, info};
+use query::{
+    exec::{admission::QueryPriority, ExecutionContextProvider},
+    frontend::sql::SqlQueryPlanner,
+};
+use schema::TIME_COLUMN_NAME;
+use snafu::{ResultExt, Snafu};
+use time::Time;
+use tracker::TrackedFutureExt;
+
+use super::{Db, DmlError};
+
+/// The maximum number of windows a query is run over at a time, so that a
+/// query catching up after downtime doesn't delay the others
+const MAX_WINDOWS_PER_RUN: usize = 100;
+
+/// The delay before a failed query, or loading or saving the progress of the
+/// queries, is retried, doubled for each consecutive failure up to
+/// [`MAX_RETRY_BACKOFF`]
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The maximum delay before a failed query is retried
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Error planning continuous query '{}': {}", query_name, source))]
+    Plan {
+        query_name: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display("Error executing continuous query '{}': {}", query_name, source))]
+    Execute {
+        query_name: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display(
+        "Column '{}' of the results of continuous query '{}' has unsupported type {:?}",
+        column,
+        query_name,
+        data_type
+    ))]
+    UnsupportedColumn {
+        query_name: String,
+        column: String,
+        data_type: DataType,
+    },
+
+    #[snafu(display(
+        "Error converting the results of continuous query '{}': {}",
+        query_name,
+        source
+    ))]
+    Convert {
+        query_name: String,
+        source: arrow::error::ArrowError,
+    },
+
+    #[snafu(display(
+        "Error writing the results of continuous query '{}': {}",
+        query_name,
+        source
+    ))]
+    Write {
+        query_name: String,
+        source: mutable_batch::writer::Error,
+    },
+
+    #[snafu(display(
+        "Error storing the results of continuous query '{}': {}",
+        query_name,
+        source
+    ))]
+    Store {
+        query_name: String,
+        source: DmlError,
+    },
+
+    #[snafu(display("Error loading continuous query progress: {}", source))]
+    LoadProgress { source: object_store::Error },
+
+    #[snafu(display("Error saving continuous query progress: {}", source))]
+    SaveProgress { source: object_store::Error },
+
+    #[snafu(display("Error deserializing continuous query progress: {}", source))]
+    Deserialization {
+        source: generated_types::DecodeError,
+    },
+
+    #[snafu(display("Error serializing continuous query progress: {}", source))]
+    Serialization {
+        source: generated_types::EncodeError,
+    },
+
+    #[snafu(display("Continuous query '{}' was cancelled", query_name))]
+    Cancelled { query_name: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The end of the last window each query completed, keyed by query name
+pub type Progress = BTreeMap<String, Time>;
+
+/// When something that failed is retried
+#[derive(Debug, Clone, Copy)]
+struct Retry {
+    backoff: Duration,
+    next_attempt: Time,
+}
+
+impl Retry {
+    /// Returns when to retry after a failure at `now`, backing off from the
+    /// `previous` retry if it failed again
+    fn after_failure(previous: Option<Self>, now: Time) -> Self {
+        let backoff = previous.map_or(RETRY_BACKOFF, |retry| {
+            (retry.backoff * 2).min(MAX_RETRY_BACKOFF)
+        });
+        Self {
+            backoff,
+            next_attempt: now + backoff,
+        }
+    }
+
+    /// Returns true if `retry` is set and not due yet at `now`
+    fn pending(retry: Option<Self>, now: Time) -> bool {
+        retry.map_or(false, |retry| now < retry.next_attempt)
+    }
+}
+
+/// The state of the continuous queries of a database between runs
+#[derive(Debug, Default)]
+pub struct State {
+    /// The progress of the queries, loaded from object store when they
+    /// first run
+    pub(super) progress: Option<Progress>,
+
+    /// Set if loading or saving the progress failed
+    progress_retry: Option<Retry>,
+
+    /// The queries that failed, keyed by query name
+    retries: BTreeMap<String, Retry>,
+}
+
+/// Runs each continuous query of `db` over the windows that closed since it
+/// last ran, returning the first error encountered. A query that fails is
+/// retried from the failed window once its backoff has passed, as is
+/// loading or saving the progress of the queries.
+pub(super) async fn run(db: &Arc<Db>) -> Result<()> {
+    let (queries, default_delay) = {
+        let rules = db.rules.read();
+        (
+            rules.continuous_queries.clone(),
+            rules.lifecycle_rules.late_arrive_window(),
+        )
+    };
+
+    if queries.is_empty() {
+        return Ok(());
+    }
+
+    let now = db.time_provider.now();
+    let mut guard = db.continuous_query_state.lock().await;
+    let State {
+        progress,
+        progress_retry,
+        retries,
+    } = &mut *guard;
+    if Retry::pending(*progress_retry, now) {
+        return Ok(());
+    }
+
+    let result = update_progress(db, progress, &queries, default_delay, now).await;
+    if let Err(e) = &result {
+        let retry = Retry::after_failure(*progress_retry, now);
+        *progress_retry = Some(retry);
+        error!(db_name=%db.name, %e, backoff=?retry.backoff, "error updating continuous query progress");
+    } else {
+        *progress_retry = None;
+    }
+    let progress = result?;
+    retries.retain(|name, _| progress.contains_key(name));
+
+    let mut result = Ok(());
+    for query in &queries {
+        let retry = retries.get(&query.name).copied();
+        if Retry::pending(retry, now) {
+            continue;
+        }
+
+        let delay = query.delay.unwrap_or(default_delay);
+        let windows = closed_windows(progress[&query.name], watermark(now, delay), query.interval);
+        if windows.is_empty() {
+            continue;
+        }
+
+        let (_, registration) = db.jobs.register(Job::RunContinuousQuery {
+            db_name: Arc::clone(&db.name),
+            query_name: Arc::from(query.name.as_str()),
+        });
+
+        let run_windows = async {
+            for (start, end) in windows {
+                run_window(db, query, start, end).await?;
+
+                progress.insert(query.name.clone(), end);
+                save_progress(&db.iox_object_store, progress).await?;
+            }
+            Ok::<_, Error>(())
+        };
+
+        let query_result = match run_windows.track(registration).await {
+            Ok(query_result) => query_result,
+            Err(_) => Err(Error::Cancelled {
+                query_name: query.name.clone(),
+            }),
+        };
+
+        match query_result {
+            Ok(()) => {
+                retries.remove(&query.name);
+            }
+            Err(e) => {
+                let retry = Retry::after_failure(retry, now);
+                retries.insert(query.name.clone(), retry);
+
+                error!(db_name=%db.name, query_name=%query.name, %e, backoff=?retry.backoff, "error running continuous query");
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Loads the progress of the queries if not loaded yet, and starts tracking
+/// the progress of new `queries` with the window that is open at `now`
+async fn update_progress<'a>(
+    db: &Db,
+    progress: &'a mut Option<Progress>,
+    queries: &[ContinuousQuery],
+    default_delay: Duration,
+    now: Time,
+) -> Result<&'a mut Progress> {
+    if progress.is_none() {
+        *progress = Some(load_progress(&db.iox_object_store).await?);
+    }
+    let progress = progress.as_mut().expect("progress loaded");
+
+    let len = progress.len();
+    progress.retain(|name, _| queries.iter().any(|query| &query.name == name));
+    let mut changed = progress.len() != len;
+    for query in queries {
+        let delay = query.delay.unwrap_or(default_delay);
+        progress.entry(query.name.clone()).or_insert_with(|| {
+            changed = true;
+            window_start(watermark(now, delay), query.interval)
+        });
+    }
+    if changed {
+        save_progress(&db.iox_object_store, progress).await?;
+    }
+
+    Ok(progress)
+}
+
+/// Runs `query` over the window from `start` to `end` and stores the results
+async fn run_window(db: &Arc<Db>, query: &ContinuousQuery, start: Time, end: Time) -> Result<()> {
+    let sql = render_sql(&query.sql, start, end);
+    debug!(db_name=%db.name, query_name=%query.name, %sql, "running continuous query");
+
+    // Nobody waits for continuous queries, so they must not delay queries
+    // of users
+    let ctx = db
+        .new_query_context(None)
+        .with_priority(QueryPriority::Batch);
+    let plan = SqlQueryPlanner::new()
+        .query(&sql, &ctx)
+        .await
+        .context(Plan {
+            query_name: &query.name,
+        })?;
+    let batches = ctx.collect(plan).await.context(Execute {
+        query_name: &query.name,
+    })?;
+
+    let batch = to_mutable_batch(&query.name, &batches, start)?;
+    if batch.rows() == 0 {
+        return Ok(());
+    }
+
+    info!(db_name=%db.name, query_name=%query.name, rows=batch.rows(), %start, %end, "continuous query completed window");
+
+    let tables = std::iter::once((query.target_table.clone(), batch)).collect();
+    let write = DmlWrite::new(tables, DmlMeta::unsequenced(None));
+    db.store_write(&write).context(Store {
+        query_name: &query.name,
+    })
+}
+
+/// Replaces `$start` and `$end` in `sql` by timestamp literals for the bounds
+/// of a window
+pub fn render_sql(sql: &str, start: Time, end: Time) -> String {
+    let literal = |time: Time| format!("to_timestamp('{}')", time.to_rfc3339());
+    sql.replace("$start", &literal(start))
+        .replace("$end", &literal(end))
+}
+
+/// Returns the time before which windows of a query with `delay` are closed
+fn watermark(now: Time, delay: Duration) -> Time {
+    now.checked_sub(delay)
+        .unwrap_or_else(|| Time::from_timestamp_nanos(i64::MIN))
+}
+
+/// Returns the start of the window of width `interval` containing `time`
+pub fn window_start(time: Time, interval: Duration) -> Time {
+    let nanos = time.timestamp_nanos();
+    Time::from_timestamp_nanos(nanos.saturating_sub(nanos.rem_euclid(interval_nanos(interval))))
+}
+
+/// Returns the bounds of up to [`MAX_WINDOWS_PER_RUN`] consecutive windows of
+/// width `interval`, starting at `completed_until`, that end before
+/// `watermark`
+pub fn closed_windows(
+    completed_until: Time,
+    watermark: Time,
+    interval: Duration,
+) -> Vec<(Time, Time)> {
+    let interval = interval_nanos(interval);
+    let watermark = watermark.timestamp_nanos();
+
+    let mut windows = vec![];
+    let mut start = completed_until.timestamp_nanos();
+    while windows.len() < MAX_WINDOWS_PER_RUN {
+        match start.checked_add(interval) {
+            Some(end) if end <= watermark => {
+                windows.push((
+                    Time::from_timestamp_nanos(start),
+                    Time::from_timestamp_nanos(end),
+                ));
+                start = end;
+            }
+            _ => break,
+        }
+    }
+    windows
+}
+
+fn interval_nanos(interval: Duration) -> i64 {
+    i64::try_from(interval.as_nanos())
+        .unwrap_or(i64::MAX)
+        .max(1)
+}
+
+/// Converts the results of the query `query_name` to a [`MutableBatch`],
+/// writing string and dictionary columns as tags and other columns as fields.
+/// Rows without a time are given the time `window_start`.
+pub fn to_mutable_batch(
+    query_name: &str,
+    batches: &[RecordBatch],
+    window_start: Time,
+) -> Result<MutableBatch> {
+    let mut mutable_batch = MutableBatch::new();
+
+    for batch in batches {
+        let rows = batch.num_rows();
+        if rows == 0 {
+            continue;
+        }
+
+        let schema = batch.schema();
+        let mut writer = Writer::new(&mut mutable_batch, rows);
+        let mut has_time = false;
+
+        for (field, column) in schema.fields().iter().zip(batch.columns()) {
+            let name = field.name().as_str();
+            let unsupported = || UnsupportedColumn {
+                query_name,
+                column: name,
+                data_type: field.data_type().clone(),
+            };
+
+            if name == TIME_COLUMN_NAME {
+                let times = match column.data_type() {
+                    DataType::Timestamp(TimeUnit::Nanosecond, _) => column
+                        .as_any()
+                        .downcast_ref::<TimestampNanosecondArray>()
+                        .expect("timestamp array"),
+                    _ => return unsupported().fail(),
+                };
+                let default = window_start.timestamp_nanos();
+                writer
+                    .write_time(
+                        name,
+                        (0..rows).map(|idx| match times.is_valid(idx) {
+                            true => times.value(idx),
+                            false => default,
+                        }),
+                    )
+                    .context(Write { query_name })?;
+                has_time = true;
+                continue;
+            }
+
+            let mask = valid_mask(column.as_ref());
+            let mask = mask.as_deref();
+            let result = match column.data_type() {
+                DataType::Utf8 | DataType::Dictionary(_, _) => {
+                    let values = cast(column, &DataType::Utf8).context(Convert { query_name })?;
+                    let values = values.as_any().downcast_ref::<StringArray>().unwrap();
+                    writer.write_tag(
+                        name,
+                        mask,
+                        valid_values(values).map(|idx| values.value(idx)),
+                    )
+                }
+                DataType::Float32 | DataType::Float64 => {
+                    let values =
+                        cast(column, &DataType::Float64).context(Convert { query_name })?;
+                    let values = values.as_any().downcast_ref::<Float64Array>().unwrap();
+                    writer.write_f64(
+                        name,
+                        mask,
+                        valid_values(values).map(|idx| values.value(idx)),
+                    )
+                }
+                DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+                    let values = cast(column, &DataType::Int64).context(Convert { query_name })?;
+                    let values = values.as_any().downcast_ref::<Int64Array>().unwrap();
+                    writer.write_i64(
+                        name,
+                        mask,
+                        valid_values(values).map(|idx| values.value(idx)),
+                    )
+                }
+                DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => {
+                    let values = cast(column, &DataType::UInt64).context(Convert { query_name })?;
+                    let values = values.as_any().downcast_ref::<UInt64Array>().unwrap();
+                    writer.write_u64(
+                        name,
+                        mask,
+                        valid_values(values).map(|idx| values.value(idx)),
+                    )
+                }
+                DataType::Boolean => {
+                    let values = column.as_any().downcast_ref::<BooleanArray>().unwrap();
+                    writer.write_bool(
+                        name,
+                        mask,
+                        valid_values(values).map(|idx| values.value(idx)),
+                    )
+                }
+                _ => return unsupported().fail(),
+            };
+            result.context(Write { query_name })?;
+        }
+
+        if !has_time {
+            writer
+                .write_time(
+                    TIME_COLUMN_NAME,
+                    std::iter::repeat(window_start.timestamp_nanos()).take(rows),
+                )
+                .context(Write { query_name })?;
+        }
+        writer.commit();
+    }
+
+    Ok(mutable_batch)
+}
+
+/// Returns the indexes of the non-null values of `array`
+fn valid_values(array: &dyn Array) -> impl Iterator<Item = usize> + '_ {
+    (0..array.len()).filter(move |idx| array.is_valid(*idx))
+}
+
+/// Returns a bitmask of the non-null values of `array`, as expected by
+/// [`Writer`], or `None` if every value is non-null
+fn valid_mask(array: &dyn Array) -> Option<Vec<u8>> {
+    if array.null_count() == 0 {
+        return None;
+    }
+
+    let mut mask = vec![0; (array.len() + 7) / 8];
+    for idx in valid_values(array) {
+        mask[idx / 8] |= 1 << (idx % 8);
+    }
+    Some(mask)
+}
+
+async fn load_progress(iox_object_store: &IoxObjectStore) -> Result<Progress> {
+    let bytes = match iox_object_store.get_continuous_query_progress_file().await {
+        Ok(bytes) => bytes,
+        Err(object_store::Error::NotFound { .. }) => return Ok(Default::default()),
+        Err(e) => return Err(Error::LoadProgress { source: e }),
+    };
+
+    let progress = decode_continuous_query_progress(bytes).context(Deserialization)?;
+    Ok(progress
+        .completed_until
+        .into_iter()
+        .map(|(name, nanos)| (name, Time::from_timestamp_nanos(nanos)))
+        .collect())
+}
+
+async fn save_progress(iox_object_store: &IoxObjectStore, progress: &Progress) -> Result<()> {
+    let progress = management::ContinuousQueryProgress {
+        completed_until: progress
+            .iter()
+            .map(|(name, time)| (name.clone(), time.timestamp_nanos()))
+            .collect(),
+    };
+
+    let mut data = bytes::BytesMut::new();
+    encode_continuous_query_progress(&progress, &mut data).context(Serialization)?;
+
+    iox_object_store
+        .put_continuous_query_progress_file(data.freeze())
+        .await
+        .context(SaveProgress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{ArrayRef, DictionaryArray};
+    use arrow::datatypes::Int32Type;
+    use arrow_util::assert_batches_eq;
+    use schema::selection::Selection;
+
+    fn time(nanos: i64) -> Time {
+        Time::from_timestamp_nanos(nanos)
+    }
+
+    #[test]
+    fn test_windows() {
+        let interval = Duration::from_nanos(10);
+
+        assert_eq!(window_start(time(25), interval), time(20));
+        assert_eq!(window_start(time(20), interval), time(20));
+        assert_eq!(window_start(time(-5), interval), time(-10));
+
+        assert_eq!(closed_windows(time(20), time(29), interval), vec![]);
+        assert_eq!(
+            closed_windows(time(20), time(40), interval),
+            vec![(time(20), time(30)), (time(30), time(40))]
+        );
+
+        let windows = closed_windows(time(0), time(i64::MAX), interval);
+        assert_eq!(windows.len(), MAX_WINDOWS_PER_RUN);
+        assert_eq!(
+            windows.last().unwrap().1,
+            time(10 * MAX_WINDOWS_PER_RUN as i64)
+        );
+    }
+
+    #[test]
+    fn test_retry() {
+        let now = Time::from_timestamp(1000, 0);
+        let retry = Retry::after_failure(None, now);
+        assert_eq!(retry.backoff, RETRY_BACKOFF);
+        assert!(Retry::pending(Some(retry), now));
+        assert!(!Retry::pending(Some(retry), now + RETRY_BACKOFF));
+        assert!(!Retry::pending(None, now));
+
+        let retry = Retry::after_failure(Some(retry), now);
+        assert_eq!(retry.backoff, 2 * RETRY_BACKOFF);
+
+        let retry = Retry::after_failure(
+            Some(Retry {
+                backoff: MAX_RETRY_BACKOFF,
+                next_attempt: now,
+            }),
+            now,
+        );
+        assert_eq!(retry.backoff, MAX_RETRY_BACKOFF);
+    }
+
+    #[test]
+    fn test_render_sql() {
+        let sql = render_sql(
+            "SELECT * FROM cpu WHERE time >= $start AND time < $end",
+            time(0),
+            time(60_000_000_000),
+        );
+        assert_eq!(
+            sql,
+            "SELECT * FROM cpu WHERE time >= to_timestamp('1970-01-01T00:00:00+00:00') \
+             AND time < to_timestamp('1970-01-01T00:01:00+00:00')"
+        );
+    }
+
+    #[test]
+    fn test_to_mutable_batch() {
+        let host: DictionaryArray<Int32Type> = vec![Some("a"), None].into_iter().collect();
+        let batch = RecordBatch::try_from_iter(vec![
+            ("host", Arc::new(host) as ArrayRef),
+            (
+                "usage",
+                Arc::new(Float64Array::from(vec![Some(1.5), None])) as ArrayRef,
+            ),
+            ("count", Arc::new(UInt64Array::from(vec![2, 3])) as ArrayRef),
+        ])
+        .unwrap();
+
+        let mutable_batch = to_mutable_batch("cpu_1m", &[batch.clone(), batch], time(60)).unwrap();
+        assert_batches_eq!(
+            &[
+                "+-------+------+--------------------------------+-------+",
+                "| count | host | time                           | usage |",
+                "+-------+------+--------------------------------+-------+",
+                "| 2     | a    | 1970-01-01T00:00:00.000000060Z | 1.5   |",
+                "| 3     |      | 1970-01-01T00:00:00.000000060Z |       |",
+                "| 2     | a    | 1970-01-01T00:00:00.000000060Z | 1.5   |",
+                "| 3     |      | 1970-01-01T00:00:00.000000060Z |       |",
+                "+-------+------+--------------------------------+-------+",
+            ],
+            &[mutable_batch.to_arrow(Selection::All).unwrap()]
+        );
+
+        let batch = RecordBatch::try_from_iter(vec![(
+            "time",
+            Arc::new(Int64Array::from(vec![1])) as ArrayRef,
+        )])
+        .unwrap();
+        let err = to_mutable_batch("cpu_1m", &[batch], time(60)).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedColumn { .. }));
+    }
+}
@@ -0,0 +1,368 @@
+//! Enforcement of the [`DatabaseSchema`] declared in the database rules
+use std::collections::{btree_map::Entry, HashMap};
+
+use data_types::database_rules::{DatabaseSchema, FieldType, SchemaMode, TableSchema};
+use schema::{
+    builder::SchemaBuilder, merge::SchemaMerger, InfluxColumnType, InfluxFieldType, Schema,
+};
+use snafu::{ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Table '{}' is not declared in the database schema", table_name))]
+    UnknownTable { table_name: String },
+
+    #[snafu(display(
+        "Column(s) {} of table '{}' are not declared in the database schema",
+        columns.join(", "),
+        table_name
+    ))]
+    UnknownColumns {
+        table_name: String,
+        columns: Vec<String>,
+    },
+
+    #[snafu(display(
+        "Write to table '{}' does not match the database schema: {}",
+        table_name,
+        source
+    ))]
+    ColumnMismatch {
+        table_name: String,
+        source: schema::merge::Error,
+    },
+
+    #[snafu(display(
+        "Column '{}' of table '{}' is declared as a {} but was written as a {}",
+        column,
+        table_name,
+        declared,
+        written
+    ))]
+    LockConflict {
+        table_name: String,
+        column: String,
+        declared: &'static str,
+        written: &'static str,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A [`DatabaseSchema`] with the columns declared for each table converted
+/// to a [`Schema`], so that writes can be checked using a [`SchemaMerger`]
+#[derive(Debug)]
+pub struct DeclaredSchema {
+    /// If writes may create tables and columns that are not declared
+    accepts_new_columns: bool,
+
+    tables: HashMap<String, Schema>,
+}
+
+impl DeclaredSchema {
+    pub fn new(database_schema: &DatabaseSchema) -> Self {
+        let tables = database_schema
+            .tables
+            .iter()
+            .map(|(table_name, table)| {
+                let mut builder = SchemaBuilder::new();
+                for tag in &table.tags {
+                    builder.influx_column(tag, InfluxColumnType::Tag);
+                }
+                for (field, field_type) in &table.fields {
+                    let field_type = match field_type {
+                        FieldType::Float => InfluxFieldType::Float,
+                        FieldType::Integer => InfluxFieldType::Integer,
+                        FieldType::UInteger => InfluxFieldType::UInteger,
+                        FieldType::String => InfluxFieldType::String,
+                        FieldType::Boolean => InfluxFieldType::Boolean,
+                    };
+                    builder.influx_column(field, InfluxColumnType::Field(field_type));
+                }
+                builder.timestamp();
+
+                // Tag and field names are unique and never "time", as checked
+                // when decoding the rules
+                let schema = builder.build().expect("valid declared schema");
+                (table_name.clone(), schema)
+            })
+            .collect();
+
+        Self {
+            accepts_new_columns: database_schema.accepts_new_columns(),
+            tables,
+        }
+    }
+
+    /// Checks that a write to `table_name` with `write_schema` is allowed by
+    /// the declared schema
+    pub fn check(&self, table_name: &str, write_schema: &Schema) -> Result<()> {
+        let declared = match self.tables.get(table_name) {
+            Some(declared) => declared,
+            None if self.accepts_new_columns => return Ok(()),
+            None => return UnknownTable { table_name }.fail(),
+        };
+
+        SchemaMerger::new()
+            .merge(declared)
+            .and_then(|merger| merger.merge(write_schema))
+            .context(ColumnMismatch { table_name })?;
+
+        if !self.accepts_new_columns {
+            let columns: Vec<_> = write_schema
+                .iter()
+                .map(|(_, field)| field.name())
+                .filter(|name| declared.find_index_of(name).is_none())
+                .cloned()
+                .collect();
+
+            if !columns.is_empty() {
+                return UnknownColumns {
+                    table_name,
+                    columns,
+                }
+                .fail();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a [`SchemaMode::Strict`] [`DatabaseSchema`] declaring every
+/// column of `tables`, which are pairs of table name and table schema
+pub fn lock<'a>(tables: impl IntoIterator<Item = (String, &'a Schema)>) -> DatabaseSchema {
+    let tables = tables
+        .into_iter()
+        .map(|(table_name, schema)| {
+            let mut table = TableSchema::default();
+            for (column_type, field) in schema.iter() {
+                let field_type = match column_type {
+                    Some(InfluxColumnType::Tag) => {
+                        table.tags.insert(field.name().clone());
+                        continue;
+                    }
+                    Some(InfluxColumnType::Field(field_type)) => field_type,
+                    _ => continue,
+                };
+
+                let field_type = match field_type {
+                    InfluxFieldType::Float => FieldType::Float,
+                    InfluxFieldType::Integer => FieldType::Integer,
+                    InfluxFieldType::UInteger => FieldType::UInteger,
+                    InfluxFieldType::String => FieldType::String,
+                    InfluxFieldType::Boolean => FieldType::Boolean,
+                };
+                table.fields.insert(field.name().clone(), field_type);
+            }
+            (table_name, table)
+        })
+        .collect();
+
+    DatabaseSchema {
+        mode: SchemaMode::Strict,
+        tables,
+    }
+}
+
+/// Adds the tables and columns of the `declared` schema to the `locked`
+/// schema returned by [`lock`], failing if a column was written with a
+/// different type than it is declared with
+pub fn merge_declared(locked: &mut DatabaseSchema, declared: &DatabaseSchema) -> Result<()> {
+    for (table_name, declared_table) in &declared.tables {
+        let table = locked.tables.entry(table_name.clone()).or_default();
+        let conflict = |column: &str, declared, written| LockConflict {
+            table_name,
+            column: column.to_string(),
+            declared,
+            written,
+        };
+
+        for tag in &declared_table.tags {
+            if let Some(field_type) = table.fields.get(tag) {
+                return conflict(tag, "tag", describe(*field_type)).fail();
+            }
+            table.tags.insert(tag.clone());
+        }
+
+        for (field, field_type) in &declared_table.fields {
+            if table.tags.contains(field) {
+                return conflict(field, describe(*field_type), "tag").fail();
+            }
+            match table.fields.entry(field.clone()) {
+                Entry::Occupied(written) if written.get() != field_type => {
+                    return conflict(field, describe(*field_type), describe(*written.get())).fail();
+                }
+                Entry::Occupied(_) => {}
+                Entry::Vacant(entry) => {
+                    entry.insert(*field_type);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn describe(field_type: FieldType) -> &'static str {
+    match field_type {
+        FieldType::Float => "float field",
+        FieldType::Integer => "integer field",
+        FieldType::UInteger => "unsigned integer field",
+        FieldType::String => "string field",
+        FieldType::Boolean => "boolean field",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declared(mode: SchemaMode) -> DeclaredSchema {
+        let mut cpu = TableSchema::default();
+        cpu.tags.insert("host".to_string());
+        cpu.fields.insert("usage".to_string(), FieldType::Float);
+
+        DeclaredSchema::new(&DatabaseSchema {
+            mode,
+            tables: std::iter::once(("cpu".to_string(), cpu)).collect(),
+        })
+    }
+
+    fn write_schema(columns: &[(&str, InfluxColumnType)]) -> Schema {
+        let mut builder = SchemaBuilder::new();
+        for (name, column_type) in columns {
+            builder.influx_column(name, *column_type);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_strict() {
+        let declared = declared(SchemaMode::Strict);
+
+        let write = write_schema(&[
+            ("host", InfluxColumnType::Tag),
+            ("usage", InfluxColumnType::Field(InfluxFieldType::Float)),
+            ("time", InfluxColumnType::Timestamp),
+        ]);
+        declared.check("cpu", &write).unwrap();
+
+        // Subsets of the declared columns are fine
+        let write = write_schema(&[("usage", InfluxColumnType::Field(InfluxFieldType::Float))]);
+        declared.check("cpu", &write).unwrap();
+
+        let err = declared.check("mem", &write).unwrap_err();
+        assert!(matches!(err, Error::UnknownTable { .. }));
+
+        let write = write_schema(&[
+            ("usage", InfluxColumnType::Field(InfluxFieldType::Float)),
+            ("usgae", InfluxColumnType::Field(InfluxFieldType::Float)),
+        ]);
+        let err = declared.check("cpu", &write).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Column(s) usgae of table 'cpu' are not declared in the database schema"
+        );
+
+        let write = write_schema(&[("usage", InfluxColumnType::Field(InfluxFieldType::Integer))]);
+        let err = declared.check("cpu", &write).unwrap_err();
+        assert!(matches!(err, Error::ColumnMismatch { .. }));
+    }
+
+    #[test]
+    fn test_learn() {
+        let declared = declared(SchemaMode::Learn);
+
+        let write = write_schema(&[
+            ("usage", InfluxColumnType::Field(InfluxFieldType::Float)),
+            ("usgae", InfluxColumnType::Field(InfluxFieldType::Float)),
+        ]);
+        declared.check("cpu", &write).unwrap();
+        declared.check("mem", &write).unwrap();
+
+        // Declared columns are still enforced
+        let write = write_schema(&[("host", InfluxColumnType::Field(InfluxFieldType::String))]);
+        let err = declared.check("cpu", &write).unwrap_err();
+        assert!(matches!(err, Error::ColumnMismatch { .. }));
+    }
+
+    #[test]
+    fn test_lock() {
+        let cpu = write_schema(&[
+            ("host", InfluxColumnType::Tag),
+            ("usage", InfluxColumnType::Field(InfluxFieldType::Float)),
+            ("time", InfluxColumnType::Timestamp),
+        ]);
+
+        let schema = lock(vec![("cpu".to_string(), &cpu)]);
+        assert_eq!(schema.mode, SchemaMode::Strict);
+
+        let table = &schema.tables["cpu"];
+        assert_eq!(table.tags.iter().collect::<Vec<_>>(), vec!["host"]);
+        assert_eq!(
+            table.fields.iter().collect::<Vec<_>>(),
+            vec![(&"usage".to_string(), &FieldType::Float)]
+        );
+
+        // The locked schema accepts the writes it was learned from
+        DeclaredSchema::new(&schema).check("cpu", &cpu).unwrap();
+    }
+
+    #[test]
+    fn test_merge_declared() {
+        let cpu = write_schema(&[
+            ("host", InfluxColumnType::Tag),
+            ("usage", InfluxColumnType::Field(InfluxFieldType::Float)),
+            ("region", InfluxColumnType::Field(InfluxFieldType::String)),
+            ("time", InfluxColumnType::Timestamp),
+        ]);
+        let locked = lock(vec![("cpu".to_string(), &cpu)]);
+
+        let mut declared_cpu = TableSchema::default();
+        declared_cpu.tags.insert("host".to_string());
+        declared_cpu.tags.insert("dc".to_string());
+        declared_cpu
+            .fields
+            .insert("usage".to_string(), FieldType::Float);
+        let mut declared = DatabaseSchema {
+            mode: SchemaMode::Learn,
+            tables: std::iter::once(("cpu".to_string(), declared_cpu)).collect(),
+        };
+
+        // Declared columns that have not been written yet are kept
+        let mut merged = locked.clone();
+        merge_declared(&mut merged, &declared).unwrap();
+        let table = &merged.tables["cpu"];
+        assert_eq!(table.tags.iter().collect::<Vec<_>>(), vec!["dc", "host"]);
+        assert_eq!(table.fields.len(), 2);
+
+        // A column written as a field and declared as a tag
+        let declared_cpu = declared.tables.get_mut("cpu").unwrap();
+        declared_cpu.tags.insert("region".to_string());
+        let err = merge_declared(&mut locked.clone(), &declared).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Column 'region' of table 'cpu' is declared as a tag but was written as a string field"
+        );
+
+        // A column written as a tag and declared as a field
+        let declared_cpu = declared.tables.get_mut("cpu").unwrap();
+        declared_cpu.tags.remove("region");
+        declared_cpu.tags.remove("host");
+        declared_cpu
+            .fields
+            .insert("host".to_string(), FieldType::String);
+        let err = merge_declared(&mut locked.clone(), &declared).unwrap_err();
+        assert!(matches!(err, Error::LockConflict { .. }));
+
+        // A field written with a different type than declared
+        let declared_cpu = declared.tables.get_mut("cpu").unwrap();
+        declared_cpu.fields.remove("host");
+        declared_cpu
+            .fields
+            .insert("usage".to_string(), FieldType::Integer);
+        let err = merge_declared(&mut locked.clone(), &declared).unwrap_err();
+        assert!(matches!(err, Error::LockConflict { .. }));
+    }
+}
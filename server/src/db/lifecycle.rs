@@ -7,9 +7,11 @@ use ::lifecycle::LifecycleDb;
 use data_types::{
     chunk_metadata::{ChunkAddr, ChunkId, ChunkLifecycleAction, ChunkOrder, ChunkStorage},
     database_rules::LifecycleRules,
+    delete_predicate::DeletePredicate,
     error::ErrorLogger,
     job::Job,
-    partition_metadata::{PartitionAddr, Statistics},
+    partition_metadata::{PartitionAddr, StatValues, Statistics},
+    timestamp::TimestampRange,
     DatabaseName,
 };
 use datafusion::physical_plan::SendableRecordBatchStream;
@@ -18,7 +20,7 @@ use lifecycle::{
     LifecycleChunk, LifecyclePartition, LifecycleReadGuard, LifecycleWriteGuard, LockableChunk,
     LockablePartition,
 };
-use observability_deps::tracing::{info, trace};
+use observability_deps::tracing::{info, trace, warn};
 use persistence_windows::persistence_windows::FlushHandle;
 use query::QueryChunkMeta;
 use schema::{merge::SchemaMerger, Schema, TIME_COLUMN_NAME};
@@ -240,6 +242,19 @@ impl LockablePartition for LockableCatalogPartition {
         let _ = tokio::spawn(async move { fut.await.log_if_error("drop chunk") });
         Ok(tracker)
     }
+
+    fn drop_partition(
+        partition: LifecycleWriteGuard<'_, Self::Partition, Self>,
+    ) -> Result<TaskTracker<Job>, Self::Error> {
+        info!(
+            table=%partition.table_name(),
+            partition=%partition.partition_key(),
+            "drop partition",
+        );
+        let (tracker, fut) = drop::drop_partition(partition)?;
+        let _ = tokio::spawn(async move { fut.await.log_if_error("drop partition") });
+        Ok(tracker)
+    }
 }
 
 impl LifecycleDb for ArcDb {
@@ -270,6 +285,19 @@ impl LifecycleDb for ArcDb {
     fn time_provider(&self) -> &Arc<dyn TimeProvider> {
         &self.0.time_provider
     }
+
+    fn delete_before(&self, table_name: &str, before: Time) {
+        let predicate = DeletePredicate {
+            range: TimestampRange::new(i64::MIN, before.timestamp_nanos()),
+            exprs: vec![],
+        };
+
+        // Retention is enforced by every database independently, so this delete
+        // is not sequenced through the write buffer
+        if let Err(e) = self.0.delete(table_name, Arc::new(predicate)) {
+            warn!(%e, %table_name, "failed to delete expired rows");
+        }
+    }
 }
 
 impl LifecyclePartition for Partition {
@@ -306,21 +334,21 @@ impl LifecycleChunk for CatalogChunk {
     }
 
     fn min_timestamp(&self) -> Time {
-        let table_summary = self.table_summary();
-        let col = table_summary
-            .columns
-            .iter()
-            .find(|x| x.name == TIME_COLUMN_NAME)
-            .expect("time column expected");
-
-        let min = match &col.stats {
-            Statistics::I64(stats) => stats.min.expect("time column cannot be empty"),
-            _ => panic!("unexpected time column type"),
-        };
+        let min = time_column_stats(self)
+            .min
+            .expect("time column cannot be empty");
 
         Time::from_timestamp_nanos(min)
     }
 
+    fn max_timestamp(&self) -> Time {
+        let max = time_column_stats(self)
+            .max
+            .expect("time column cannot be empty");
+
+        Time::from_timestamp_nanos(max)
+    }
+
     fn access_metrics(&self) -> AccessMetrics {
         self.access_recorder().get_metrics()
     }
@@ -342,6 +370,21 @@ impl LifecycleChunk for CatalogChunk {
     }
 }
 
+/// Returns the statistics of the time column of `chunk`
+fn time_column_stats(chunk: &CatalogChunk) -> StatValues<i64> {
+    let table_summary = chunk.table_summary();
+    let col = table_summary
+        .columns
+        .iter()
+        .find(|x| x.name == TIME_COLUMN_NAME)
+        .expect("time column expected");
+
+    match &col.stats {
+        Statistics::I64(stats) => stats.clone(),
+        _ => panic!("unexpected time column type"),
+    }
+}
+
 /// Executes a plan and collects the results into a read buffer chunk
 // This is an async function but has been desugared manually because it's hitting
 // https://github.com/rust-lang/rust/issues/63033
@@ -0,0 +1,216 @@
+//! Limits on the number of tables, columns and distinct tag values a database
+//! accumulates, see [`LifecycleRules`](data_types::database_rules::LifecycleRules)
+use std::num::NonZeroUsize;
+
+use hashbrown::{HashMap, HashSet};
+use metric::{Attributes, Metric, U64Counter};
+use snafu::Snafu;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display(
+        "Cannot create table '{}': the database is limited to {} tables",
+        table_name,
+        max_tables
+    ))]
+    TooManyTables {
+        table_name: String,
+        max_tables: usize,
+    },
+
+    #[snafu(display(
+        "Cannot write to table '{}': the write would give it {} columns but tables are limited to {} columns",
+        table_name,
+        columns,
+        max_columns
+    ))]
+    TooManyColumns {
+        table_name: String,
+        columns: usize,
+        max_columns: usize,
+    },
+
+    #[snafu(display(
+        "Cannot write to partition '{}' of table '{}': tag '{}' would have {} distinct values but tags are limited to {} distinct values per partition",
+        partition_key,
+        table_name,
+        tag,
+        cardinality,
+        max_cardinality
+    ))]
+    TagCardinality {
+        table_name: String,
+        partition_key: String,
+        tag: String,
+        cardinality: usize,
+        max_cardinality: usize,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The distinct values of each tag written to a partition
+///
+/// Values are only tracked while a tag cardinality limit is set, and are not
+/// forgotten when the chunks containing them are dropped
+#[derive(Debug, Default)]
+pub struct TagValues {
+    tags: HashMap<String, HashSet<String>>,
+}
+
+impl TagValues {
+    /// Returns the number of distinct values each tag would have after
+    /// inserting `tag_values`, as returned by
+    /// [`PartitionWrite::tag_values`](mutable_batch::PartitionWrite::tag_values)
+    pub fn cardinalities<'a>(&self, tag_values: &[(&'a str, Vec<&str>)]) -> Vec<(&'a str, usize)> {
+        tag_values
+            .iter()
+            .map(|(tag, values)| match self.tags.get(*tag) {
+                Some(existing) => {
+                    let new = values.iter().filter(|v| !existing.contains(**v)).count();
+                    (*tag, existing.len() + new)
+                }
+                None => (*tag, values.len()),
+            })
+            .collect()
+    }
+
+    /// Records `tag_values` as written to the partition
+    pub fn insert(&mut self, tag_values: Vec<(&str, Vec<&str>)>) {
+        for (tag, values) in tag_values {
+            let existing = self.tags.entry(tag.to_string()).or_default();
+            existing.extend(values.into_iter().map(ToString::to_string));
+        }
+    }
+}
+
+/// Checks the distinct values per tag, as returned by
+/// [`TagValues::cardinalities`], that a write would result in
+pub fn check_tag_cardinalities(
+    table_name: &str,
+    partition_key: &str,
+    cardinalities: Vec<(&str, usize)>,
+    max_cardinality: NonZeroUsize,
+) -> Result<()> {
+    match cardinalities
+        .into_iter()
+        .find(|(_, cardinality)| *cardinality > max_cardinality.get())
+    {
+        Some((tag, cardinality)) => TagCardinality {
+            table_name,
+            partition_key,
+            tag,
+            cardinality,
+            max_cardinality: max_cardinality.get(),
+        }
+        .fail(),
+        None => Ok(()),
+    }
+}
+
+/// Counts the writes rejected for exceeding each limit
+#[derive(Debug)]
+pub struct LimitMetrics {
+    tables: U64Counter,
+    columns: U64Counter,
+    tag_cardinality: U64Counter,
+}
+
+impl LimitMetrics {
+    pub fn new(registry: &metric::Registry, db_name: &str) -> Self {
+        let rejections: Metric<U64Counter> = registry.register_metric(
+            "write_limit_rejections",
+            "Number of writes rejected for exceeding a database limit",
+        );
+
+        let recorder = |limit: &'static str| {
+            rejections.recorder(Attributes::from([
+                ("db_name", db_name.to_string().into()),
+                ("limit", limit.into()),
+            ]))
+        };
+
+        Self {
+            tables: recorder("tables"),
+            columns: recorder("columns"),
+            tag_cardinality: recorder("tag_cardinality"),
+        }
+    }
+
+    /// Records a write rejected with `error`
+    pub fn record(&self, error: &Error) {
+        match error {
+            Error::TooManyTables { .. } => self.tables.inc(1),
+            Error::TooManyColumns { .. } => self.columns.inc(1),
+            Error::TagCardinality { .. } => self.tag_cardinality.inc(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_tag_cardinalities() {
+        let max = NonZeroUsize::new(2).unwrap();
+
+        check_tag_cardinalities("cpu", "1970-01-01", vec![("host", 2), ("region", 1)], max)
+            .unwrap();
+
+        let err =
+            check_tag_cardinalities("cpu", "1970-01-01", vec![("host", 2), ("region", 3)], max)
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot write to partition '1970-01-01' of table 'cpu': tag 'region' would have 3 \
+             distinct values but tags are limited to 2 distinct values per partition"
+        );
+    }
+
+    #[test]
+    fn test_tag_values() {
+        let mut tag_values = TagValues::default();
+        assert_eq!(
+            tag_values.cardinalities(&[("host", vec!["a", "b"])]),
+            vec![("host", 2)]
+        );
+
+        tag_values.insert(vec![("host", vec!["a", "b"]), ("region", vec!["east"])]);
+
+        // Values already written are only counted once
+        let mut cardinalities = tag_values.cardinalities(&[
+            ("host", vec!["b", "c"]),
+            ("region", vec!["east"]),
+            ("zone", vec!["1"]),
+        ]);
+        cardinalities.sort_unstable();
+        assert_eq!(cardinalities, vec![("host", 3), ("region", 1), ("zone", 1)]);
+    }
+
+    #[test]
+    fn test_limit_metrics() {
+        let registry = metric::Registry::new();
+        let metrics = LimitMetrics::new(&registry, "placeholder");
+
+        metrics.record(&Error::TooManyTables {
+            table_name: "cpu".to_string(),
+            max_tables: 1,
+        });
+
+        let observation = |limit: &'static str| {
+            registry
+                .get_instrument::<Metric<U64Counter>>("write_limit_rejections")
+                .unwrap()
+                .get_observer(&Attributes::from(&[
+                    ("db_name", "placeholder"),
+                    ("limit", limit),
+                ]))
+                .unwrap()
+                .fetch()
+        };
+
+        assert_eq!(observation("tables"), 1);
+        assert_eq!(observation("columns"), 0);
+    }
+}
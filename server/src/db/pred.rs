@@ -54,6 +54,7 @@ pub mod test {
     use super::*;
     use datafusion::logical_plan::{col, lit, Expr};
 
+    use datafusion::physical_plan::functions::BuiltinScalarFunction;
     use datafusion::scalar::ScalarValue;
     use predicate::predicate::PredicateBuilder;
     use read_buffer::BinaryExpr as RBBinaryExpr;
@@ -89,6 +90,16 @@ pub mod test {
                     RBBinaryExpr::from(("counter", ">", 2992_i64)),
                 ]),
             ),
+            // a prefix comparison
+            (
+                PredicateBuilder::default()
+                    .add_expr(Expr::ScalarFunction {
+                        fun: BuiltinScalarFunction::StartsWith,
+                        args: vec![col("track"), lit("Star")],
+                    })
+                    .build(),
+                RBPredicate::new(vec![RBBinaryExpr::from(("track", "starts_with", "Star"))]),
+            ),
             // a bit of everything
             (
                 PredicateBuilder::default()
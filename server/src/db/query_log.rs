@@ -1,8 +1,12 @@
 //! Ring buffer of queries that have been run with some brief information
 
-use std::{collections::VecDeque, sync::Arc};
+use std::{collections::VecDeque, sync::Arc, time::Duration};
 
 use parking_lot::Mutex;
+use query::exec::{
+    admission::{AdmissionStats, QueryPriority},
+    deadline::QueryDeadline,
+};
 use time::{Time, TimeProvider};
 
 /// Information about a single query that was executed
@@ -16,17 +20,61 @@ pub struct QueryLogEntry {
 
     /// Time at which the query was run
     pub issue_time: Time,
+
+    /// The deadline of the query, if it has a timeout
+    deadline: Option<Arc<QueryDeadline>>,
+
+    /// The admission of the query, if known
+    admission: Option<Arc<AdmissionStats>>,
 }
 
 impl QueryLogEntry {
     /// Creates a new QueryLogEntry -- use `QueryLog::push` to add new entries to the log
-    fn new(query_type: String, query_text: String, issue_time: Time) -> Self {
+    fn new(
+        query_type: String,
+        query_text: String,
+        issue_time: Time,
+        deadline: Option<Arc<QueryDeadline>>,
+        admission: Option<Arc<AdmissionStats>>,
+    ) -> Self {
         Self {
             query_type,
             query_text,
             issue_time,
+            deadline,
+            admission,
         }
     }
+
+    /// Returns true if the query was cancelled for exceeding its timeout
+    pub fn timed_out(&self) -> bool {
+        self.deadline
+            .as_ref()
+            .map(|deadline| deadline.timed_out())
+            .unwrap_or(false)
+    }
+
+    /// The priority the query is admitted with
+    pub fn priority(&self) -> Option<QueryPriority> {
+        self.admission
+            .as_ref()
+            .map(|admission| admission.priority())
+    }
+
+    /// Returns true if the query is waiting for admission
+    pub fn queued(&self) -> bool {
+        self.admission
+            .as_ref()
+            .map(|admission| admission.queued())
+            .unwrap_or(false)
+    }
+
+    /// The time the query waited, or has been waiting so far, for admission
+    pub fn queue_wait(&self) -> Option<Duration> {
+        self.admission
+            .as_ref()
+            .and_then(|admission| admission.queue_wait())
+    }
 }
 
 /// Stores a fixed number `QueryExcutions` -- handles locking
@@ -49,7 +97,13 @@ impl QueryLog {
         }
     }
 
-    pub fn push(&self, query_type: impl Into<String>, query_text: impl Into<String>) {
+    pub fn push(
+        &self,
+        query_type: impl Into<String>,
+        query_text: impl Into<String>,
+        deadline: Option<Arc<QueryDeadline>>,
+        admission: Option<Arc<AdmissionStats>>,
+    ) {
         if self.max_size == 0 {
             return;
         }
@@ -58,6 +112,8 @@ impl QueryLog {
             query_type.into(),
             query_text.into(),
             self.time_provider.now(),
+            deadline,
+            admission,
         ));
 
         let mut log = self.log.lock();
@@ -0,0 +1,608 @@
+//! Delivering the writes stored by a database to its [`Subscription`]s, see
+//! [`DatabaseRules`](data_types::database_rules::DatabaseRules)
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use data_types::subscription::{Subscription, SubscriptionTarget};
+use dml::DmlWrite;
+use influxdb_iox_client::{
+    connection::Builder,
+    write::{generated_types::WriteRequest, Client as WriteClient},
+};
+use metric::{Attributes, Metric, U64Counter, U64Gauge};
+use mutable_batch::MutableBatch;
+use mutable_batch_pb::encode::encode_write;
+use observability_deps::tracing::{debug, warn};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+/// The number of times delivering a write is attempted before it is dropped
+const MAX_DELIVERY_ATTEMPTS: usize = 3;
+
+/// The delay before the first retry of a failed delivery, doubled for each
+/// following retry
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The default timeout of a request delivering a write to an HTTP target
+pub const DEFAULT_HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Error encoding write as line protocol: {}", source))]
+    Encode {
+        source: mutable_batch_lp::encode::Error,
+    },
+
+    #[snafu(display("Error posting write to {}: {}", url, source))]
+    Http { url: String, source: reqwest::Error },
+
+    #[snafu(display("Error connecting to {}: {}", connection, source))]
+    Connect {
+        connection: String,
+        source: influxdb_iox_client::connection::Error,
+    },
+
+    #[snafu(display("Error writing to database {} at {}: {}", db_name, connection, source))]
+    Grpc {
+        connection: String,
+        db_name: String,
+        source: influxdb_iox_client::error::Error,
+    },
+
+    #[snafu(display("Error appending write to {}: {}", path.display(), source))]
+    File {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Error creating HTTP client: {}", source))]
+    HttpClient { source: reqwest::Error },
+
+    #[snafu(display("Invalid subscription auth token: {}", source))]
+    InvalidAuthToken {
+        source: reqwest::header::InvalidHeaderValue,
+    },
+
+    #[snafu(display("File subscriptions are disabled as no subscription file directory is set"))]
+    FileTargetsDisabled,
+
+    #[snafu(display(
+        "Invalid subscription file path '{}': must be relative and must not contain '..'",
+        path
+    ))]
+    InvalidFilePath { path: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Server-wide settings of the connections to subscription targets
+#[derive(Debug, Clone)]
+pub struct SubscriptionConfig {
+    /// The directory the paths of file targets are relative to. File targets
+    /// are rejected if not set
+    pub file_dir: Option<PathBuf>,
+
+    /// The timeout of a request delivering a write to an HTTP target
+    pub http_timeout: Duration,
+
+    /// The settings gRPC targets are connected with, e.g. the TLS
+    /// certificates used for `https` targets
+    pub grpc_builder: Builder,
+
+    /// The token presented to HTTP and gRPC targets in the `authorization`
+    /// header, if any
+    pub auth_token: Option<String>,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            file_dir: None,
+            http_timeout: DEFAULT_HTTP_TIMEOUT,
+            grpc_builder: Builder::default(),
+            auth_token: None,
+        }
+    }
+}
+
+/// The subscriptions of a database
+///
+/// Each subscription has a bounded queue of writes awaiting delivery, which
+/// is drained by a background task. Dropping the subscriptions lets the tasks
+/// deliver the writes still queued and then exit.
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    subscribers: Vec<Subscriber>,
+}
+
+impl Subscriptions {
+    /// Starts delivering to `subscriptions`, connecting to their targets
+    /// with the settings of `config`
+    ///
+    /// Must be called within a tokio runtime unless `subscriptions` is empty
+    pub fn new(
+        db_name: &str,
+        subscriptions: &[Subscription],
+        config: &SubscriptionConfig,
+        registry: &metric::Registry,
+    ) -> Self {
+        let subscribers = subscriptions
+            .iter()
+            .map(|subscription| Subscriber::new(db_name, subscription.clone(), config, registry))
+            .collect();
+
+        Self { subscribers }
+    }
+
+    /// Queues the tables of `write` matching each subscription for delivery,
+    /// dropping them for subscriptions whose queue is full
+    pub fn publish(&self, write: &DmlWrite) {
+        for subscriber in &self.subscribers {
+            subscriber.publish(write)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    subscription: Subscription,
+    /// `None` if the target of the subscription is invalid
+    sender: Option<mpsc::Sender<DmlWrite>>,
+    metrics: Arc<SubscriberMetrics>,
+}
+
+impl Subscriber {
+    fn new(
+        db_name: &str,
+        subscription: Subscription,
+        config: &SubscriptionConfig,
+        registry: &metric::Registry,
+    ) -> Self {
+        let metrics = Arc::new(SubscriberMetrics::new(
+            registry,
+            db_name,
+            &subscription.name,
+        ));
+
+        let sender = match Target::new(subscription.target.clone(), config) {
+            Ok(target) => {
+                let (sender, receiver) = mpsc::channel(subscription.queue_size.get());
+                tokio::spawn(deliver(
+                    subscription.name.clone(),
+                    target,
+                    receiver,
+                    Arc::clone(&metrics),
+                ));
+                Some(sender)
+            }
+            Err(e) => {
+                warn!(%e, %db_name, subscription=%subscription.name, "invalid subscription target, writes will not be delivered");
+                None
+            }
+        };
+
+        Self {
+            subscription,
+            sender,
+            metrics,
+        }
+    }
+
+    fn publish(&self, write: &DmlWrite) {
+        let write = match filter_write(&self.subscription, write) {
+            Some(write) => write,
+            None => return,
+        };
+
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => {
+                self.metrics.failed.inc(1);
+                return;
+            }
+        };
+
+        // Count the write as queued before sending it, as it may be
+        // delivered, and so dequeued, before `try_send` returns
+        self.metrics.queued();
+        if sender.try_send(write).is_err() {
+            debug!(subscription=%self.subscription.name, "subscription queue full, dropping write");
+            self.metrics.dequeued();
+            self.metrics.dropped.inc(1)
+        }
+    }
+}
+
+/// Returns the tables of `write` matching `subscription`, or `None` if there
+/// are none
+fn filter_write(subscription: &Subscription, write: &DmlWrite) -> Option<DmlWrite> {
+    let tables: HashMap<String, MutableBatch> = write
+        .tables()
+        .filter(|(table_name, _)| subscription.matches_table(table_name))
+        .map(|(table_name, batch)| (table_name.to_string(), batch.clone()))
+        .collect();
+
+    match tables.len() {
+        0 => None,
+        n if n == write.table_count() => Some(write.clone()),
+        _ => Some(DmlWrite::new(tables, write.meta().clone())),
+    }
+}
+
+/// Delivers the writes received from `receiver` until all senders are dropped
+async fn deliver(
+    name: String,
+    mut target: Target,
+    mut receiver: mpsc::Receiver<DmlWrite>,
+    metrics: Arc<SubscriberMetrics>,
+) {
+    while let Some(write) = receiver.recv().await {
+        metrics.dequeued();
+
+        let mut backoff = RETRY_BACKOFF;
+        for attempt in 1..=MAX_DELIVERY_ATTEMPTS {
+            match target.write(&write).await {
+                Ok(_) => {
+                    metrics.delivered.inc(1);
+                    break;
+                }
+                Err(e) if attempt == MAX_DELIVERY_ATTEMPTS => {
+                    warn!(%e, subscription=%name, "failed to deliver write to subscription");
+                    metrics.failed.inc(1);
+                }
+                Err(e) => {
+                    debug!(%e, subscription=%name, attempt, "retrying delivery to subscription");
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+
+    debug!(subscription=%name, "subscription closed");
+}
+
+/// A connection to the target of a subscription
+#[derive(Debug)]
+enum Target {
+    Http {
+        client: reqwest::Client,
+        url: String,
+    },
+    Grpc {
+        connection: String,
+        db_name: String,
+        builder: Builder,
+        /// Established on first use, and again after a failed write
+        client: Option<WriteClient>,
+    },
+    File {
+        path: PathBuf,
+    },
+}
+
+impl Target {
+    fn new(target: SubscriptionTarget, config: &SubscriptionConfig) -> Result<Self> {
+        let authorization = config
+            .auth_token
+            .as_ref()
+            .map(|token| HeaderValue::from_str(&format!("Token {}", token)))
+            .transpose()
+            .context(InvalidAuthToken)?;
+
+        Ok(match target {
+            SubscriptionTarget::Http { url } => {
+                let mut headers = reqwest::header::HeaderMap::new();
+                if let Some(authorization) = authorization {
+                    headers.insert(AUTHORIZATION, authorization);
+                }
+                let client = reqwest::Client::builder()
+                    .timeout(config.http_timeout)
+                    .default_headers(headers)
+                    .build()
+                    .context(HttpClient)?;
+                Self::Http { client, url }
+            }
+            SubscriptionTarget::Grpc {
+                connection,
+                db_name,
+            } => {
+                let builder = match authorization {
+                    Some(authorization) => config
+                        .grpc_builder
+                        .clone()
+                        .header(AUTHORIZATION, authorization),
+                    None => config.grpc_builder.clone(),
+                };
+                Self::Grpc {
+                    connection,
+                    db_name,
+                    builder,
+                    client: None,
+                }
+            }
+            SubscriptionTarget::File { path } => Self::File {
+                path: file_path(config.file_dir.as_deref(), &path)?,
+            },
+        })
+    }
+
+    async fn write(&mut self, write: &DmlWrite) -> Result<()> {
+        match self {
+            Self::Http { client, url } => {
+                let body = encode_line_protocol(write)?;
+                client
+                    .post(url.as_str())
+                    .header(reqwest::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+                    .body(body)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .context(Http { url: url.as_str() })?;
+            }
+            Self::Grpc {
+                connection,
+                db_name,
+                builder,
+                client,
+            } => {
+                let mut write_client =
+                    match client.take() {
+                        Some(write_client) => write_client,
+                        None => {
+                            let conn = builder.clone().build(connection.as_str()).await.context(
+                                Connect {
+                                    connection: connection.as_str(),
+                                },
+                            )?;
+                            WriteClient::new(conn)
+                        }
+                    };
+
+                let request = WriteRequest {
+                    database_batch: Some(encode_write(db_name, write)),
+                };
+                write_client.write_pb(request).await.context(Grpc {
+                    connection: connection.as_str(),
+                    db_name: db_name.as_str(),
+                })?;
+
+                *client = Some(write_client);
+            }
+            Self::File { path } => {
+                let body = encode_line_protocol(write)?;
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .await
+                    .context(File { path: path.clone() })?;
+                file.write_all(&body)
+                    .await
+                    .context(File { path: path.clone() })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the path of the file target `path` within `file_dir`
+///
+/// Rules cannot be trusted with arbitrary paths on the server, so `path` must
+/// be relative and must not leave `file_dir`.
+fn file_path(file_dir: Option<&Path>, path: &str) -> Result<PathBuf> {
+    let file_dir = file_dir.context(FileTargetsDisabled)?;
+
+    let relative = Path::new(path);
+    ensure!(
+        relative.file_name().is_some()
+            && relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir)),
+        InvalidFilePath { path }
+    );
+
+    Ok(file_dir.join(relative))
+}
+
+fn encode_line_protocol(write: &DmlWrite) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for (table_name, batch) in write.tables() {
+        mutable_batch_lp::encode::encode_batch(table_name, batch, &mut out).context(Encode)?;
+    }
+    Ok(out)
+}
+
+/// Counts the writes passing through the queue of a subscription
+#[derive(Debug)]
+struct SubscriberMetrics {
+    delivered: U64Counter,
+    dropped: U64Counter,
+    failed: U64Counter,
+    queue_length: U64Gauge,
+    queued: AtomicU64,
+}
+
+impl SubscriberMetrics {
+    fn new(registry: &metric::Registry, db_name: &str, subscription: &str) -> Self {
+        let writes: Metric<U64Counter> = registry.register_metric(
+            "subscription_writes",
+            "Number of writes delivered to, dropped by or failed to be delivered to a subscription",
+        );
+        let queue_length: Metric<U64Gauge> = registry.register_metric(
+            "subscription_queue_length",
+            "Number of writes waiting for delivery to a subscription",
+        );
+
+        let attributes = |status: Option<&'static str>| {
+            let mut attributes = Attributes::from([
+                ("db_name", db_name.to_string().into()),
+                ("subscription", subscription.to_string().into()),
+            ]);
+            if let Some(status) = status {
+                attributes.insert("status", status);
+            }
+            attributes
+        };
+
+        Self {
+            delivered: writes.recorder(attributes(Some("delivered"))),
+            dropped: writes.recorder(attributes(Some("dropped"))),
+            failed: writes.recorder(attributes(Some("failed"))),
+            queue_length: queue_length.recorder(attributes(None)),
+            queued: AtomicU64::new(0),
+        }
+    }
+
+    fn queued(&self) {
+        let queued = self.queued.fetch_add(1, Ordering::Relaxed) + 1;
+        self.queue_length.set(queued);
+    }
+
+    fn dequeued(&self) {
+        let queued = self.queued.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.queue_length.set(queued);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use data_types::router::Matcher;
+    use dml::DmlMeta;
+    use mutable_batch_lp::lines_to_batches;
+    use regex::Regex;
+
+    use super::*;
+
+    fn lp_to_write(lp: &str) -> DmlWrite {
+        DmlWrite::new(lines_to_batches(lp, 0).unwrap(), DmlMeta::unsequenced(None))
+    }
+
+    fn file_subscription(path: &str, table_name_regex: Option<&str>) -> Subscription {
+        Subscription {
+            name: "file".to_string(),
+            matcher: Matcher {
+                table_name_regex: table_name_regex.map(|re| Regex::new(re).unwrap()),
+            },
+            target: SubscriptionTarget::File {
+                path: path.to_string(),
+            },
+            queue_size: NonZeroUsize::new(10).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_filter_write() {
+        let write = lp_to_write("cpu,host=a usage=1 10\nmem,host=a free=2 10");
+
+        let subscription = file_subscription("writes.lp", Some("^cpu"));
+        let filtered = filter_write(&subscription, &write).unwrap();
+        assert_eq!(filtered.table_count(), 1);
+        assert!(filtered.table("cpu").is_some());
+
+        let subscription = file_subscription("writes.lp", None);
+        let filtered = filter_write(&subscription, &write).unwrap();
+        assert_eq!(filtered.table_count(), 2);
+
+        let subscription = file_subscription("writes.lp", Some("^disk"));
+        assert!(filter_write(&subscription, &write).is_none());
+    }
+
+    #[test]
+    fn test_file_path() {
+        let dir = Path::new("/var/lib/iox/subscriptions");
+
+        assert_eq!(
+            file_path(Some(dir), "db/writes.lp").unwrap(),
+            dir.join("db/writes.lp")
+        );
+        assert_eq!(
+            file_path(Some(dir), "./writes.lp").unwrap(),
+            dir.join("writes.lp")
+        );
+
+        for path in ["/etc/passwd", "../writes.lp", "db/../../writes.lp", "."] {
+            let err = file_path(Some(dir), path).unwrap_err();
+            assert!(matches!(err, Error::InvalidFilePath { .. }), "{}", path);
+        }
+
+        let err = file_path(None, "writes.lp").unwrap_err();
+        assert!(matches!(err, Error::FileTargetsDisabled));
+    }
+
+    #[tokio::test]
+    async fn test_file_subscription() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        let path = dir.path().join("writes.lp");
+        let registry = metric::Registry::new();
+        let config = SubscriptionConfig {
+            file_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let subscriptions = Subscriptions::new(
+            "placeholder",
+            &[file_subscription("writes.lp", Some("^cpu"))],
+            &config,
+            &registry,
+        );
+        subscriptions.publish(&lp_to_write("cpu,host=a usage=1 10\nmem,host=a free=2 10"));
+        subscriptions.publish(&lp_to_write("mem,host=a free=3 20"));
+        subscriptions.publish(&lp_to_write("cpu,host=b usage=4 30"));
+
+        let delivered = registry
+            .get_instrument::<Metric<U64Counter>>("subscription_writes")
+            .unwrap()
+            .get_observer(&Attributes::from(&[
+                ("db_name", "placeholder"),
+                ("subscription", "file"),
+                ("status", "delivered"),
+            ]))
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while delivered.fetch() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("writes not delivered");
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents, "cpu,host=a usage=1 10\ncpu,host=b usage=4 30\n");
+    }
+
+    #[tokio::test]
+    async fn test_file_subscription_disabled() {
+        let registry = metric::Registry::new();
+
+        let subscriptions = Subscriptions::new(
+            "placeholder",
+            &[file_subscription("writes.lp", None)],
+            &SubscriptionConfig::default(),
+            &registry,
+        );
+        subscriptions.publish(&lp_to_write("cpu,host=a usage=1 10"));
+
+        let failed = registry
+            .get_instrument::<Metric<U64Counter>>("subscription_writes")
+            .unwrap()
+            .get_observer(&Attributes::from(&[
+                ("db_name", "placeholder"),
+                ("subscription", "file"),
+                ("status", "failed"),
+            ]))
+            .unwrap();
+        assert_eq!(failed.fetch(), 1);
+    }
+}
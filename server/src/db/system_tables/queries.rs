@@ -3,7 +3,7 @@ use crate::db::{
     system_tables::IoxSystemTable,
 };
 use arrow::{
-    array::{StringArray, TimestampNanosecondArray},
+    array::{BooleanArray, StringArray, TimestampNanosecondArray},
     datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
     error::Result,
     record_batch::RecordBatch,
@@ -47,6 +47,14 @@ fn queries_schema() -> SchemaRef {
         ),
         Field::new("query_type", DataType::Utf8, false),
         Field::new("query_text", DataType::Utf8, false),
+        Field::new("timed_out", DataType::Boolean, false),
+        Field::new("priority", DataType::Utf8, true),
+        Field::new("queued", DataType::Boolean, false),
+        Field::new(
+            "queue_wait",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            true,
+        ),
     ]))
 }
 
@@ -70,12 +78,36 @@ fn from_query_log_entries(
         .map(|e| Some(&e.query_text))
         .collect::<StringArray>();
 
+    let timed_out = entries
+        .iter()
+        .map(|e| Some(e.timed_out()))
+        .collect::<BooleanArray>();
+
+    let priority = entries
+        .iter()
+        .map(|e| e.priority().map(|priority| priority.as_str()))
+        .collect::<StringArray>();
+
+    let queued = entries
+        .iter()
+        .map(|e| Some(e.queued()))
+        .collect::<BooleanArray>();
+
+    let queue_wait = entries
+        .iter()
+        .map(|e| e.queue_wait().map(|wait| wait.as_nanos() as i64))
+        .collect::<TimestampNanosecondArray>();
+
     RecordBatch::try_new(
         schema,
         vec![
             Arc::new(issue_time),
             Arc::new(query_type),
             Arc::new(query_text),
+            Arc::new(timed_out),
+            Arc::new(priority),
+            Arc::new(queued),
+            Arc::new(queue_wait),
         ],
     )
 }
@@ -84,26 +116,42 @@ fn from_query_log_entries(
 mod tests {
     use super::*;
     use arrow_util::assert_batches_eq;
+    use query::exec::{Executor, ExecutorType};
+    use std::time::Duration;
     use time::{Time, TimeProvider};
 
-    #[test]
-    fn test_from_query_log() {
+    #[tokio::test]
+    async fn test_from_query_log() {
         let now = Time::from_rfc3339("1996-12-19T16:39:57+00:00").unwrap();
         let time_provider = Arc::new(time::MockProvider::new(now));
         let query_log = QueryLog::new(10, Arc::clone(&time_provider) as Arc<dyn TimeProvider>);
-        query_log.push("sql", "select * from foo");
+        query_log.push("sql", "select * from foo", None, None);
         time_provider.inc(std::time::Duration::from_secs(24 * 60 * 60));
-        query_log.push("sql", "select * from bar");
-        query_log.push("read_filter", "json goop");
+        query_log.push("sql", "select * from bar", None, None);
+
+        let exec = Executor::new(1);
+        let ctx = exec
+            .new_execution_config(ExecutorType::Query)
+            .with_timeout(Some(Duration::from_millis(1)))
+            .build();
+        query_log.push(
+            "read_filter",
+            "json goop",
+            ctx.deadline().map(Arc::clone),
+            Some(Arc::clone(ctx.admission_stats())),
+        );
+        ctx.run(futures::future::pending::<datafusion::error::Result<()>>())
+            .await
+            .unwrap_err();
 
         let expected = vec![
-            "+----------------------+-------------+-------------------+",
-            "| issue_time           | query_type  | query_text        |",
-            "+----------------------+-------------+-------------------+",
-            "| 1996-12-19T16:39:57Z | sql         | select * from foo |",
-            "| 1996-12-20T16:39:57Z | sql         | select * from bar |",
-            "| 1996-12-20T16:39:57Z | read_filter | json goop         |",
-            "+----------------------+-------------+-------------------+",
+            "+----------------------+-------------+-------------------+-----------+-------------+--------+------------+",
+            "| issue_time           | query_type  | query_text        | timed_out | priority    | queued | queue_wait |",
+            "+----------------------+-------------+-------------------+-----------+-------------+--------+------------+",
+            "| 1996-12-19T16:39:57Z | sql         | select * from foo | false     |             | false  |            |",
+            "| 1996-12-20T16:39:57Z | sql         | select * from bar | false     |             | false  |            |",
+            "| 1996-12-20T16:39:57Z | read_filter | json goop         | true      | interactive | false  |            |",
+            "+----------------------+-------------+-------------------+-----------+-------------+--------+------------+",
         ];
 
         let schema = queries_schema();
@@ -836,6 +836,20 @@ impl Server {
             .context(CanNotUpdateRules { db_name })?)
     }
 
+    /// Locks the schema of a database to the tables and columns it has seen,
+    /// rejecting writes creating new ones from then on
+    pub async fn lock_db_schema(
+        &self,
+        db_name: &DatabaseName<'_>,
+    ) -> Result<Arc<ProvidedDatabaseRules>> {
+        let database = self.database(db_name)?;
+
+        Ok(database
+            .lock_schema()
+            .await
+            .context(CanNotUpdateRules { db_name })?)
+    }
+
     /// Closes a chunk and starts moving its data to the read buffer, as a
     /// background job, dropping when complete.
     pub fn close_chunk(
@@ -1174,6 +1188,10 @@ pub mod test_utils {
             Arc::new(ObjectStore::new_in_memory()),
             None,
             None,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            None,
         ))
     }
 
@@ -1295,6 +1313,10 @@ mod tests {
             },
             worker_cleanup_avg_sleep: Duration::from_secs(2),
             write_buffer_connection: None,
+            schema: None,
+            ingest_rules: vec![],
+            continuous_queries: vec![],
+            subscriptions: vec![],
         };
         let provided_rules = make_provided_rules(rules);
 
@@ -1413,6 +1435,10 @@ mod tests {
             lifecycle_rules: Default::default(),
             worker_cleanup_avg_sleep: Duration::from_secs(2),
             write_buffer_connection: None,
+            schema: None,
+            ingest_rules: vec![],
+            continuous_queries: vec![],
+            subscriptions: vec![],
         };
 
         // Create a database
@@ -1835,7 +1861,15 @@ mod tests {
     async fn init_error_generic() {
         // use an object store that will hopefully fail to read
         let store = Arc::new(ObjectStore::new_failing_store().unwrap());
-        let application = Arc::new(ApplicationState::new(store, None, None));
+        let application = Arc::new(ApplicationState::new(
+            store,
+            None,
+            None,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            None,
+        ));
         let server = make_server(application);
 
         server.set_id(ServerId::try_from(1).unwrap()).unwrap();
@@ -1997,6 +2031,10 @@ mod tests {
                 connection: "my_mock".to_string(),
                 ..Default::default()
             }),
+            schema: None,
+            ingest_rules: vec![],
+            continuous_queries: vec![],
+            subscriptions: vec![],
         };
 
         let provided_rules = make_provided_rules(rules);
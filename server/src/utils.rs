@@ -1,5 +1,8 @@
 use crate::{
-    db::{catalog::TableNameFilter, load::load_or_create_preserved_catalog, DatabaseToCommit, Db},
+    db::{
+        catalog::TableNameFilter, load::load_or_create_preserved_catalog,
+        subscription::SubscriptionConfig, DatabaseToCommit, Db,
+    },
     JobRegistry,
 };
 use data_types::{
@@ -45,6 +48,7 @@ pub struct TestDbBuilder {
     lifecycle_rules: LifecycleRules,
     partition_template: PartitionTemplate,
     time_provider: Arc<dyn TimeProvider>,
+    subscription_config: SubscriptionConfig,
 }
 
 impl Default for TestDbBuilder {
@@ -66,6 +70,7 @@ impl Default for TestDbBuilder {
                 parts: vec![TemplatePart::TimeFormat("%Y-%m-%dT%H".to_string())],
             },
             time_provider: Arc::new(time::SystemProvider::new()),
+            subscription_config: Default::default(),
         }
     }
 }
@@ -96,6 +101,9 @@ impl TestDbBuilder {
         let exec = Arc::new(Executor::new_with_config(ExecutorConfig {
             num_threads: 1,
             target_query_partitions: 4,
+            query_timeout: None,
+            memory: Default::default(),
+            admission: Default::default(),
         }));
 
         let metric_registry = Arc::new(metric::Registry::new());
@@ -130,6 +138,7 @@ impl TestDbBuilder {
             exec,
             metric_registry: Arc::clone(&metric_registry),
             time_provider,
+            subscription_config: Arc::new(self.subscription_config.clone()),
         };
 
         TestDb {
@@ -173,6 +182,11 @@ impl TestDbBuilder {
         self.time_provider = time_provider;
         self
     }
+
+    pub fn subscription_config(mut self, subscription_config: SubscriptionConfig) -> Self {
+        self.subscription_config = subscription_config;
+        self
+    }
 }
 
 /// Used for testing: create a Database with a local store
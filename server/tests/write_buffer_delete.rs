@@ -98,6 +98,7 @@ impl DistributedTest {
                 },
             )]),
             query_sinks: QuerySinks::default(),
+            ingest_rules: vec![],
         });
         let router = router_server.router(db_name).unwrap();
 